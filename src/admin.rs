@@ -0,0 +1,157 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Administrative operations run against the data directory instead of starting the registry
+
+use std::path::Path;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::Utc;
+use log::info;
+
+use crate::application::Application;
+use crate::model::backup::{backup_name, backups_to_prune, seal_backup_file, BackupKind};
+use crate::model::config::Configuration;
+use crate::restore::rebuild_index_from_database;
+use crate::services::database::db_transaction_write;
+use crate::services::{ServiceProvider, StandardServiceProvider};
+use crate::utils::apierror::{error_invalid_request, error_not_found, specialize, ApiError};
+use crate::utils::db::RwSqlitePool;
+
+/// The usage of the administrative commands
+pub const ADMIN_USAGE: &str = "Administrative commands, run with the configuration of the registry while it is stopped:
+  --create-admin <email> [<name>]                   create an administrator, or promote and reactivate an existing user
+  --issue-token <email> <name> [--write] [--admin]  issue a token for a user and print it with its Cargo configuration
+  --rebuild-index                                   rewrite the index from the database and the crates in the storage
+  --backup                                          take a full backup of the database to the storage
+  --restore-backup [<name>] [--rebuild-index]       restore the database from a backup, the most recent by default
+  --import-archive <path>                           import an export archive of another registry
+  --check-config                                    validate the configuration and the access to the services
+  --tenant <name>                                   run the command against a tenant instead of the main registry";
+
+/// Gets the argument after the one at a position, if it is not an option
+fn get_value(args: &[String], position: usize) -> Option<&str> {
+    args.get(position + 1)
+        .map(String::as_str)
+        .filter(|arg| !arg.starts_with("--"))
+}
+
+/// Gets a required argument after the one at a position
+fn require_value<'a>(args: &'a [String], position: usize, what: &str) -> Result<&'a str, ApiError> {
+    get_value(args, position).ok_or_else(|| specialize(error_invalid_request(), format!("{} expects {what}", args[position])))
+}
+
+/// Runs the administrative command in the arguments, if any
+///
+/// Returns `None` when the arguments do not contain an administrative command.
+pub async fn main_admin_command(args: &[String]) -> Option<Result<(), ApiError>> {
+    let position = |name: &str| args.iter().position(|arg| arg == name);
+    if let Some(position) = position("--create-admin") {
+        return Some(main_create_admin(args, position).await);
+    }
+    if let Some(position) = position("--issue-token") {
+        return Some(main_issue_token(args, position).await);
+    }
+    if position("--rebuild-index").is_some() {
+        return Some(main_rebuild_index().await);
+    }
+    if position("--backup").is_some() {
+        return Some(main_backup().await);
+    }
+    None
+}
+
+/// Opens the database of the registry
+async fn open_database() -> Result<(Configuration, RwSqlitePool), ApiError> {
+    let configuration = StandardServiceProvider::get_configuration().await?;
+    let pool = Application::open_database(&configuration).await?;
+    Ok((configuration, pool))
+}
+
+/// Creates an administrator, or promotes an existing user, for example to bootstrap a registry
+async fn main_create_admin(args: &[String], position: usize) -> Result<(), ApiError> {
+    let email = require_value(args, position, "the email of the user")?;
+    let name = get_value(args, position + 1);
+    let (_, pool) = open_database().await?;
+    let result = db_transaction_write(&pool, "create_or_promote_admin", |database| async move {
+        database.create_or_promote_admin(email, name).await
+    })
+    .await;
+    pool.close().await;
+    let user = result?;
+    println!("{} (login {}) is an administrator", user.email, user.login);
+    Ok(())
+}
+
+/// Issues a token for a user and prints it, with the configuration for Cargo
+async fn main_issue_token(args: &[String], position: usize) -> Result<(), ApiError> {
+    let email = require_value(args, position, "the email of the user and the name of the token")?;
+    let name = require_value(args, position + 1, "the name of the token after the email")?;
+    let can_write = args.iter().any(|arg| arg == "--write");
+    let can_admin = args.iter().any(|arg| arg == "--admin");
+    let (configuration, pool) = open_database().await?;
+    let result = db_transaction_write(&pool, "issue_token", |database| async move {
+        let uid = database
+            .check_is_user(email)
+            .await
+            .map_err(|_| specialize(error_not_found(), format!("no active user with email {email}")))?;
+        let user = database.get_user_profile(uid).await?;
+        let token = database.create_token(uid, name, can_write, can_admin).await?;
+        Ok::<_, ApiError>((user, token))
+    })
+    .await;
+    pool.close().await;
+    let (user, token) = result?;
+    let credentials = STANDARD.encode(format!("{}:{}", user.login, token.secret));
+    println!("token {} for {}: {}", token.name, user.login, token.secret);
+    println!();
+    println!("[registries.{}]", configuration.self_local_name);
+    println!("token = \"Basic {credentials}\"");
+    Ok(())
+}
+
+/// Rewrites the index from the crate versions in the database and their data in the storage
+async fn main_rebuild_index() -> Result<(), ApiError> {
+    let configuration = StandardServiceProvider::get_configuration().await?;
+    let service_storage = StandardServiceProvider::get_storage(&configuration);
+    rebuild_index_from_database(&configuration, service_storage).await
+}
+
+/// Takes a full backup of the database and uploads it to the storage, then prunes the old backups
+async fn main_backup() -> Result<(), ApiError> {
+    let (configuration, pool) = open_database().await?;
+    let service_storage = StandardServiceProvider::get_storage(&configuration);
+    let key = configuration.backup.get_encryption_key()?;
+    let name = backup_name(
+        Utc::now(),
+        BackupKind {
+            incremental: false,
+            encrypted: key.is_some(),
+        },
+    );
+    let snapshot = format!("{}/{name}.tmp", configuration.data_dir);
+    let result = pool.snapshot_into(&snapshot).await;
+    pool.close().await;
+    let sealed = match result {
+        Ok(()) => {
+            let snapshot = snapshot.clone();
+            tokio::task::spawn_blocking(move || seal_backup_file(Path::new(&snapshot), None, key.as_deref())).await?
+        }
+        Err(e) => Err(e),
+    };
+    let _ = tokio::fs::remove_file(&snapshot).await;
+    service_storage.store_backup(&name, sealed?.content).await?;
+    info!("backup: stored {name}");
+    let mut existing = service_storage.list_backups().await?;
+    if !existing.contains(&name) {
+        existing.push(name.clone());
+    }
+    for old in backups_to_prune(&existing, configuration.backup.retention) {
+        service_storage.delete_backup(&old).await?;
+        info!("backup: pruned {old}");
+    }
+    println!("{name}");
+    Ok(())
+}
@@ -4,40 +4,107 @@
 
 //! Main application
 
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::future::Future;
+use std::io::Write;
 use std::ops::Deref;
-use std::sync::Arc;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
-use log::{error, info};
+use arc_swap::ArcSwap;
+use axum::http::Uri;
+use chrono::{Local, NaiveDateTime, Utc};
+use log::{error, info, warn};
+use semver::{Version, VersionReq};
 use tokio::sync::mpsc::{channel, Receiver, Sender};
+use tokio::time::Instant;
 
-use crate::model::auth::{Authentication, RegistryUserToken, RegistryUserTokenWithSecret};
+use crate::model::announcements::Announcement;
+use crate::model::audit::AuditLogEntry;
+use crate::model::auth::{
+    Authentication, AuthenticationPrincipal, ImpersonationRequest, ImpersonationToken, ProvisioningRule, RegistryUserToken,
+    RegistryUserTokenWithSecret, TrafficSubject, TrafficUsage,
+};
+use crate::model::backup::{
+    backup_kind, backup_name, backups_to_prune, seal_backup_file, BackupChain, BackupKind, BackupReport, BackupStatus,
+    SealedBackup,
+};
+use crate::model::binstall::{extract_binstall_metadata, validate_binary_target, BinstallInfo, CrateBinary};
 use crate::model::cargo::{
-    CrateUploadData, CrateUploadResult, OwnersQueryResult, RegistryUser, SearchResults, YesNoMsgResult, YesNoResult,
+    CrateMetadata, CrateUploadData, CrateUploadResult, CratesIoVersionResult, CratesIoVersions, CratesIoVersionsMeta,
+    IndexCrateDependency, IndexCrateMetadata, OwnersQueryResult, RegistryUser, SearchResults, YesNoMsgResult, YesNoResult,
+};
+use crate::model::config::{load_config_file, with_tenant, Configuration, ConfusionCheck, MaintenanceConfig, SecretsScan};
+use crate::model::deps::{AdvisoriesReport, CrateVersionAdvisory, DepsAnalysis};
+use crate::model::docs::{DocGenEvent, DocGenJob, DocGenJobSpec, DocGenJobState, DocGenJobUpdate, DocGenTrigger, DocsCoverage};
+use crate::model::export::{
+    ExportArchiveBuilder, ExportManifest, ExportedCrate, EXPORT_DATABASE_PATH, EXPORT_FORMAT_VERSION, EXPORT_INDEX_FOLDER,
+    EXPORT_MANIFEST_PATH,
+};
+use crate::model::feeds::{build_atom_feed, FEED_DEFAULT_LIMIT};
+use crate::model::health::{
+    HealthCheck, HealthReport, LivenessReport, HEALTH_CHECK_DATABASE, HEALTH_CHECK_INDEX, HEALTH_CHECK_STORAGE,
+};
+use crate::model::licenses::{extract_crate_license, LicenseInventory, LicenseInventoryEntry};
+use crate::model::osv::SimpleAdvisory;
+use crate::model::packages::{
+    BulkCrateMetadata, BulkMetadataQuery, BulkMetadataResult, BulkYankQuery, BulkYankResult, CrateInfo, CrateInfoTarget,
+    CrateVersionChecksums, CrateVisibility, CrateVisibilityQuery, QuarantinedVersion, ReverseDependencies, VersionProvenance,
+    VersionProvenanceDeclaration, BULK_METADATA_MAX_CRATES,
+};
+use crate::model::pagination::{matches_filter, AuditLogSort, CrateListSort, Page, Pagination, TokenSort, UserSort};
+use crate::model::profile::{UserPreferences, UserProfileUpdate};
+use crate::model::quotas::{QuotaUsage, TeamQuota, UserQuota};
+use crate::model::sbom::{Sbom, CRATES_IO_REGISTRY_URI};
+use crate::model::scheduler::ScheduledJobStatus;
+use crate::model::search::{CrateSearchDocument, SearchFilters, SearchPagination, SearchRankingWeights, SearchReindexProgress};
+use crate::model::secrets::{compile_secret_patterns, scan_crate_secrets};
+use crate::model::sources::{diff_crates, extract_crate_file, list_crate_files, CrateDiff, CrateFile};
+use crate::model::stats::{
+    CrateDownloads, DownloadStats, GlobalStats, PublishActivityReport, RegistryStats, StorageUsageReport, TrafficReport,
+};
+use crate::model::vendor::{
+    build_vendor_archive, LockFileAudit, LockFileAuditEntry, LockedPackage, VendorMissing, VendoredCrate, CRATES_IO_SOURCE_GIT,
+    CRATES_IO_SOURCE_SPARSE,
+};
+use crate::model::webhooks::{
+    UserNotification, Webhook, WebhookCreateQuery, WebhookDelivery, WebhookEvent, WebhookPayload, WebhookWithSecret,
+    USER_NOTIFICATIONS_DEFAULT_LIMIT, WEBHOOK_DELIVERIES_DEFAULT_LIMIT,
 };
-use crate::model::config::Configuration;
-use crate::model::deps::DepsAnalysis;
-use crate::model::docs::{DocGenEvent, DocGenJob, DocGenJobSpec, DocGenTrigger};
-use crate::model::packages::{CrateInfo, CrateInfoTarget};
-use crate::model::stats::{DownloadStats, GlobalStats};
 use crate::model::worker::{WorkerEvent, WorkerPublicData, WorkersManager};
-use crate::model::{AppEvent, CrateVersion, RegistryInformation};
+use crate::model::{
+    AppEvent, ByteRange, ContentPart, CrateContent, CrateVersion, IndexResyncQuery, IndexResyncResult, RegistryInformation,
+};
+use crate::services::chat::{ChatMessage, ChatNotifier};
 use crate::services::database::{db_transaction_read, db_transaction_write, Database};
 use crate::services::deps::DepsChecker;
 use crate::services::docs::DocsGenerator;
 use crate::services::emails::EmailSender;
-use crate::services::index::Index;
+use crate::services::index::{validate_crate_name, Index};
+use crate::services::notifications;
+use crate::services::proxy::Proxy;
 use crate::services::rustsec::RustSecChecker;
-use crate::services::storage::Storage;
+use crate::services::search::Search;
+use crate::services::storage::{extract_readme, Storage};
+use crate::services::webhooks::WebhookSender;
 use crate::services::ServiceProvider;
-use crate::utils::apierror::{error_forbidden, error_invalid_request, error_unauthorized, specialize, ApiError};
+use crate::utils::apierror::{
+    error_backend_failure, error_conflict, error_forbidden, error_invalid_request, error_not_found, error_service_unavailable,
+    error_unauthorized, specialize, ApiError,
+};
 use crate::utils::axum::auth::{AuthData, Token};
+use crate::utils::cache::TimedCache;
+use crate::utils::concurrent::n_at_a_time;
+use crate::utils::context::{set_request_package, set_request_principal, set_request_traffic_subject};
 use crate::utils::db::RwSqlitePool;
+use crate::utils::hashes::sha256;
+use crate::utils::scheduler::{period_from_secs, FirstRun, Scheduler};
 
 /// The state of this application for axum
 pub struct Application {
-    /// The configuration
-    pub configuration: Arc<Configuration>,
+    /// The configuration, parts of which can be reloaded at runtime
+    pub configuration: Arc<ArcSwap<Configuration>>,
     /// The database pool
     service_db_pool: RwSqlitePool,
     /// The storage layer
@@ -45,21 +112,191 @@ pub struct Application {
     /// Service to index the metadata of crates
     service_index: Arc<dyn Index + Send + Sync>,
     /// The `RustSec` checker service
-    #[allow(dead_code)]
     service_rustsec: Arc<dyn RustSecChecker + Send + Sync>,
+    /// The service proxying the upstream registry
+    service_proxy: Arc<dyn Proxy + Send + Sync>,
+    /// The full-text search service
+    service_search: Arc<dyn Search + Send + Sync>,
+    /// The progress of the last rebuild of the search index
+    search_reindex: Mutex<SearchReindexProgress>,
+    /// The documents for the crates that failed to be indexed for search, by name, to be indexed again
+    search_pending: Mutex<HashMap<String, CrateSearchDocument>>,
+    /// The current state of the read-only maintenance mode
+    maintenance: Mutex<MaintenanceConfig>,
+    /// The status of the backups of the database
+    backup_status: Mutex<BackupStatus>,
+    /// The current chain of backups, for the incremental backups
+    backup_chain: Mutex<Option<BackupChain>>,
+    /// The timestamp of the last synchronization of the advisories
+    advisories_last_sync: Mutex<Option<NaiveDateTime>>,
     /// Service to check the dependencies of a crate
     service_deps_checker: Arc<dyn DepsChecker + Send + Sync>,
     /// The service to send emails
-    #[allow(dead_code)]
     service_email_sender: Arc<dyn EmailSender + Send + Sync>,
     /// The service to generator documentation
     service_docs_generator: Arc<dyn DocsGenerator + Send + Sync>,
+    /// The service to deliver the payloads of webhooks
+    service_webhook_sender: Arc<dyn WebhookSender + Send + Sync>,
+    /// The service to post notifications to chat services
+    service_chat_notifier: Arc<dyn ChatNotifier + Send + Sync>,
     /// Sender to use to notify about events that will be asynchronously handled
     app_events_sender: Sender<AppEvent>,
     /// The connected worker nodes
     pub worker_nodes: WorkersManager,
+    /// The scheduler for the background jobs
+    scheduler: Arc<Scheduler>,
+    /// The downloads of crate versions that are not yet written to the database
+    pending_downloads: Mutex<HashMap<CrateVersion, u32>>,
+    /// The cached data about the crates, by name
+    crate_info_cache: TimedCache<CrateInfo>,
+    /// The last report of the health checks for the readiness probe, with the time it was produced
+    readiness: Mutex<Option<(Instant, HealthReport)>>,
+    /// The timestamp of the start of the registry
+    started_on: NaiveDateTime,
+}
+
+/// Normalizes the source of a registry, as found in a lock file, for comparisons
+fn normalize_source(source: &str) -> &str {
+    let source = source
+        .strip_prefix("sparse+")
+        .or_else(|| source.strip_prefix("registry+"))
+        .unwrap_or(source);
+    source.trim_end_matches('/')
+}
+
+/// Gets the metadata of the latest version of a crate, with the index entry of the locked version if any
+///
+/// Returns `None` when the crate has no version in the index.
+async fn get_crate_latest_metadata(
+    service_index: &(dyn Index + Send + Sync),
+    service_storage: &(dyn Storage + Send + Sync),
+    name: &str,
+    locked_version: Option<&str>,
+) -> Result<Option<BulkCrateMetadata>, ApiError> {
+    let versions = match service_index.get_crate_data(name).await {
+        Ok(versions) => versions,
+        Err(error) if error.http == 404 => Vec::new(),
+        Err(error) => return Err(error),
+    };
+    let latest = versions
+        .iter()
+        .filter_map(|version| version.vers.parse::<Version>().ok().map(|semver| (version, semver)))
+        .max_by(|(a, semver_a), (b, semver_b)| b.yanked.cmp(&a.yanked).then_with(|| semver_a.cmp(semver_b)))
+        .map(|(version, _)| version.clone());
+    let Some(index) = latest else {
+        return Ok(None);
+    };
+    let metadata = service_storage.download_crate_metadata(name, &index.vers).await?;
+    let locked = locked_version.and_then(|locked| versions.iter().find(|version| version.vers == locked).cloned());
+    Ok(Some(BulkCrateMetadata {
+        name: index.name.clone(),
+        index,
+        metadata,
+        locked,
+    }))
+}
+
+/// Writes an export archive of the registry, to be called on a blocking thread
+fn write_export_archive(
+    archive: &str,
+    manifest: &ExportManifest,
+    snapshot: &str,
+    index_snapshot: &str,
+    service_storage: &(dyn Storage + Send + Sync),
+    runtime: &tokio::runtime::Handle,
+) -> Result<(), ApiError> {
+    let mut builder = ExportArchiveBuilder::new(std::io::BufWriter::new(std::fs::File::create(archive)?));
+    builder.append_data(EXPORT_MANIFEST_PATH, &serde_json::to_vec_pretty(manifest)?)?;
+    builder.append_file(EXPORT_DATABASE_PATH, std::path::Path::new(snapshot))?;
+    builder.append_folder(EXPORT_INDEX_FOLDER, std::path::Path::new(index_snapshot))?;
+    if manifest.include_crates {
+        for exported in &manifest.crates {
+            let metadata = runtime.block_on(service_storage.download_crate_metadata(&exported.package, &exported.version))?;
+            let Some(metadata) = metadata else {
+                warn!("export: missing metadata for {} {}", exported.package, exported.version);
+                continue;
+            };
+            let content = runtime.block_on(service_storage.download_crate(&exported.package, &exported.version))?;
+            builder.append_data(&exported.metadata_path(), &serde_json::to_vec(&metadata)?)?;
+            builder.append_data(&exported.data_path(), &content)?;
+        }
+    }
+    builder.finish()?.flush()?;
+    Ok(())
+}
+
+/// Runs the health checks of the database, the storage and the index, concurrently
+///
+/// Returns the result of each check by name, with the details of the failure.
+async fn run_health_checks(
+    service_db_pool: &RwSqlitePool,
+    service_storage: &(dyn Storage + Send + Sync),
+    service_index: &(dyn Index + Send + Sync),
+) -> Vec<(&'static str, Result<(), String>)> {
+    /// Runs a single check with a timeout
+    async fn run_check(future: impl Future<Output = Result<(), ApiError>>) -> Result<(), String> {
+        match tokio::time::timeout(HEALTH_CHECK_TIMEOUT, future).await {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(e)) => Err(e.details.as_deref().unwrap_or(&e.message).trim().to_string()),
+            Err(_) => Err(format!("timed out after {}s", HEALTH_CHECK_TIMEOUT.as_secs())),
+        }
+    }
+    let (database, storage, index) = tokio::join!(
+        run_check(async {
+            db_transaction_read(service_db_pool, |database| async move { database.get_is_empty().await }).await?;
+            Ok(())
+        }),
+        run_check(service_storage.check_health()),
+        run_check(service_index.check_health()),
+    );
+    vec![
+        (HEALTH_CHECK_DATABASE, database),
+        (HEALTH_CHECK_STORAGE, storage),
+        (HEALTH_CHECK_INDEX, index),
+    ]
+}
+
+/// Runs the health checks on startup to fail fast when a service the registry depends on is not usable
+async fn check_startup_health(
+    service_db_pool: &RwSqlitePool,
+    service_storage: &(dyn Storage + Send + Sync),
+    service_index: &(dyn Index + Send + Sync),
+) -> Result<(), ApiError> {
+    let failures = run_health_checks(service_db_pool, service_storage, service_index)
+        .await
+        .into_iter()
+        .filter_map(|(name, result)| result.err().map(|e| format!("{name}: {e}")))
+        .collect::<Vec<_>>();
+    if failures.is_empty() {
+        return Ok(());
+    }
+    Err(specialize(
+        error_backend_failure(),
+        format!("startup health checks failed, {}", failures.join("; ")),
+    ))
 }
 
+/// The timeout for each health check
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The duration for which the result of the health checks is reused for the readiness probe
+const READINESS_CACHE_DURATION: Duration = Duration::from_secs(30);
+
+/// The period between two checks for quarantined versions that can be released
+const QUARANTINE_CHECK_PERIOD: Duration = Duration::from_secs(60);
+
+/// The period between two attempts to index the crates that failed to be indexed for search
+const SEARCH_PENDING_PERIOD: Duration = Duration::from_secs(60);
+
+/// The period between the runs computing the missing checksums of crate versions
+const CHECKSUMS_BACKFILL_PERIOD: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The maximum number of crates fetched at the same time for the inventory of the licenses
+const LICENSE_INVENTORY_CONCURRENCY: usize = 8;
+
+/// The maximum number of crates fetched at the same time for a bulk query of metadata
+const BULK_METADATA_CONCURRENCY: usize = 8;
+
 /// The empty database
 const DB_EMPTY: &[u8] = include_bytes!("empty.db");
 
@@ -69,19 +306,7 @@ impl Application {
         // load configuration
         let configuration = Arc::new(configuration);
 
-        // connection pool to the database
-        let db_filename = configuration.get_database_filename();
-        if tokio::fs::metadata(&db_filename).await.is_err() {
-            // write the file
-            info!("db file is inaccessible => attempt to create an empty one");
-            tokio::fs::write(&db_filename, DB_EMPTY).await?;
-        }
-        let service_db_pool = RwSqlitePool::new(&configuration.get_database_url())?;
-        // migrate the database, if appropriate
-        db_transaction_write(&service_db_pool, "migrate_to_last", |database| async move {
-            crate::migrations::migrate_to_last(database.transaction).await
-        })
-        .await?;
+        let service_db_pool = Self::open_database(&configuration).await?;
 
         let worker_nodes = WorkersManager::default();
 
@@ -89,58 +314,60 @@ impl Application {
             db_transaction_read(&service_db_pool, |database| async move { database.get_is_empty().await }).await?;
         let service_storage = P::get_storage(&configuration.deref().clone());
         let service_index = P::get_index(&configuration, db_is_empty).await?;
+        check_startup_health(&service_db_pool, service_storage.as_ref(), service_index.as_ref()).await?;
         let service_rustsec = P::get_rustsec(&configuration);
-        let service_deps_checker = P::get_deps_checker(configuration.clone(), service_index.clone(), service_rustsec.clone());
+        let service_proxy = P::get_proxy(&configuration)?;
+        let service_search = P::get_search(&configuration)?;
+        let shared_configuration = Arc::new(ArcSwap::new(configuration.clone()));
+        let service_deps_checker =
+            P::get_deps_checker(shared_configuration.clone(), service_index.clone(), service_rustsec.clone());
         let service_email_sender = P::get_email_sender(configuration.clone());
+        let service_chat_notifier = P::get_chat_notifier(configuration.clone());
+        let service_webhook_sender = P::get_webhook_sender();
         let service_docs_generator = P::get_docs_generator(
-            configuration.clone(),
+            shared_configuration.clone(),
             service_db_pool.clone(),
             service_storage.clone(),
             worker_nodes.clone(),
         );
 
         // check undocumented packages
-        let default_target = &configuration.self_toolchain_host;
-        let job_specs = db_transaction_write(
-            &service_db_pool,
-            "Application::launch::get_undocumented_crates",
-            |database| async move {
-                let jobs = database.get_undocumented_crates(default_target).await?;
-                for job in &jobs {
-                    // resolve the docs
-                    database
-                        .set_crate_documentation(&job.package, &job.version, &job.target, false, false)
-                        .await?;
-                }
-                Ok::<_, ApiError>(jobs)
-            },
-        )
-        .await?;
-        for spec in &job_specs {
-            service_docs_generator.queue(spec, &DocGenTrigger::MissingOnLaunch).await?;
-        }
+        Self::queue_undocumented_crates(&configuration, &service_db_pool, service_docs_generator.as_ref()).await?;
 
-        // deps worker
-        crate::services::deps::create_deps_worker(
-            configuration.clone(),
-            service_deps_checker.clone(),
-            service_email_sender.clone(),
-            service_db_pool.clone(),
-        );
+        crate::services::deps::precache_crates_io_spawn(service_deps_checker.clone());
 
         let (app_events_sender, app_events_receiver) = channel(64);
 
+        let maintenance = configuration.maintenance.clone();
         let this = Arc::new(Self {
-            configuration,
+            configuration: shared_configuration,
             service_db_pool,
             service_storage,
             service_index,
             service_rustsec,
+            service_proxy,
+            service_search,
+            search_reindex: Mutex::new(SearchReindexProgress::default()),
+            search_pending: Mutex::new(HashMap::new()),
+            maintenance: Mutex::new(maintenance),
+            backup_status: Mutex::new(BackupStatus::default()),
+            backup_chain: Mutex::new(None),
+            advisories_last_sync: Mutex::new(None),
             service_deps_checker,
             service_email_sender,
             service_docs_generator,
+            service_webhook_sender,
+            service_chat_notifier,
             app_events_sender,
             worker_nodes,
+            scheduler: Arc::new(Scheduler::default()),
+            pending_downloads: Mutex::new(HashMap::new()),
+            crate_info_cache: TimedCache::new(
+                configuration.crate_info_cache.capacity,
+                Duration::from_secs(configuration.crate_info_cache.max_age),
+            ),
+            readiness: Mutex::new(None),
+            started_on: Local::now().naive_local(),
         });
 
         let _handle = {
@@ -149,120 +376,976 @@ impl Application {
                 app.events_handler(app_events_receiver).await;
             })
         };
+        this.docs_jobs_listener_spawn().await?;
+        this.schedulers_spawn(&configuration);
+        crate::services::webhooks::resume_pending(this.service_db_pool.clone(), this.service_webhook_sender.clone());
+
+        if !db_is_empty && this.service_search.is_empty().await? {
+            // the search index is new, fill it
+            this.search_index_rebuild_spawn()?;
+        }
+
+        if !db_is_empty
+            && !db_transaction_read(&this.service_db_pool, |database| async move {
+                database.get_has_dependencies().await
+            })
+            .await?
+        {
+            // the dependencies were never recorded, fill them from the index
+            let app = this.clone();
+            let _handle = tokio::spawn(async move {
+                if let Err(e) = app.dependencies_rebuild().await {
+                    error!("{e}");
+                    if let Some(backtrace) = &e.backtrace {
+                        error!("{backtrace}");
+                    }
+                }
+            });
+        }
 
         Ok(this)
     }
 
+    /// Opens the connection pool to the database, creating an empty one if necessary, and migrates it to the current schema
+    pub async fn open_database(configuration: &Configuration) -> Result<RwSqlitePool, ApiError> {
+        let db_filename = configuration.get_database_filename();
+        if tokio::fs::metadata(&db_filename).await.is_err() {
+            // write the file
+            info!("db file is inaccessible => attempt to create an empty one");
+            // the data directory of a new tenant may not exist yet
+            tokio::fs::create_dir_all(&configuration.data_dir).await?;
+            tokio::fs::write(&db_filename, DB_EMPTY).await?;
+        }
+        let service_db_pool = RwSqlitePool::new(&configuration.get_database_url())?;
+        // migrate the database, if appropriate
+        db_transaction_write(&service_db_pool, "migrate_to_last", |database| async move {
+            crate::migrations::migrate_to_last(database.transaction).await
+        })
+        .await?;
+        Ok(service_db_pool)
+    }
+
+    /// Queues the documentation jobs for the crate versions that are not documented yet
+    async fn queue_undocumented_crates(
+        configuration: &Configuration,
+        service_db_pool: &RwSqlitePool,
+        service_docs_generator: &(dyn DocsGenerator + Send + Sync),
+    ) -> Result<(), ApiError> {
+        let default_target = &configuration.self_toolchain_host;
+        let job_specs = db_transaction_write(
+            service_db_pool,
+            "Application::launch::get_undocumented_crates",
+            |database| async move {
+                let jobs = database.get_undocumented_crates(default_target).await?;
+                for job in &jobs {
+                    // resolve the docs
+                    database
+                        .set_crate_documentation(&job.package, &job.version, &job.target, false, false)
+                        .await?;
+                }
+                Ok::<_, ApiError>(jobs)
+            },
+        )
+        .await?;
+        for spec in &job_specs {
+            service_docs_generator.queue(spec, &DocGenTrigger::MissingOnLaunch).await?;
+        }
+        Ok(())
+    }
+
     /// Gets the storage service
     #[must_use]
     pub fn get_service_storage(&self) -> Arc<dyn Storage + Send + Sync> {
         self.service_storage.clone()
     }
 
+    /// Gets the pool of connections to the database
+    #[must_use]
+    pub fn get_service_db_pool(&self) -> &RwSqlitePool {
+        &self.service_db_pool
+    }
+
     /// Gets the index service
     #[must_use]
     pub fn get_service_index(&self) -> &(dyn Index + Send + Sync) {
         self.service_index.as_ref()
     }
 
-    /// The worker to handle the update of token usage
-    async fn events_handler(&self, mut receiver: Receiver<AppEvent>) {
-        const BUFFER_SIZE: usize = 16;
-        let mut events = Vec::with_capacity(BUFFER_SIZE);
-        loop {
-            let count = receiver.recv_many(&mut events, BUFFER_SIZE).await;
-            if count == 0 {
-                break;
-            }
-            if let Err(e) = self.events_handler_handle(&events).await {
-                error!("{e}");
-                if let Some(backtrace) = e.backtrace {
-                    error!("{backtrace}");
+    /// Registers the background jobs in the scheduler, with the periods from the configuration
+    ///
+    /// The jobs that are not activated in the configuration cannot be run, even on demand.
+    fn schedulers_spawn(self: &Arc<Self>, configuration: &Configuration) {
+        self.schedule_job(
+            "backup",
+            "Backs up the database to the storage",
+            configuration.backup.is_enabled(),
+            period_from_secs(configuration.backup.period),
+            FirstRun::AfterPeriod,
+            |app| async move {
+                app.backup_begin()?;
+                app.backup_execute().await
+            },
+        );
+        self.schedule_job(
+            "advisories",
+            "Synchronizes the RustSec advisories and records those affecting the hosted crates",
+            true,
+            period_from_secs(configuration.deps_advisories_sync_period),
+            FirstRun::OnStart,
+            |app| async move { app.advisories_sync().await },
+        );
+        self.schedule_job(
+            "deps",
+            "Analyzes the dependencies of the crates whose last analysis is stale",
+            true,
+            period_from_secs(configuration.deps_check_period),
+            FirstRun::OnStart,
+            |app| async move { app.deps_check_stale().await },
+        );
+        self.schedule_job(
+            "confusion",
+            "Checks the names of the local crates against crates.io",
+            configuration.proxy.confusion_check != ConfusionCheck::Off,
+            period_from_secs(configuration.proxy.confusion_check_period),
+            FirstRun::OnStart,
+            |app| async move { app.confusion_check().await },
+        );
+        self.schedule_job(
+            "mirror",
+            "Mirrors the configured crates from crates.io",
+            crate::services::proxy::is_mirror_configured(configuration),
+            period_from_secs(configuration.proxy.mirror_period),
+            FirstRun::OnStart,
+            |app| async move {
+                let configuration = app.configuration.load_full();
+                crate::services::proxy::mirror_worker_job(&configuration, app.service_proxy.as_ref()).await
+            },
+        );
+        self.schedule_job(
+            "downloads",
+            "Writes the buffered download counts to the database",
+            true,
+            period_from_secs(configuration.downloads_flush_period),
+            FirstRun::AfterPeriod,
+            |app| async move { app.flush_downloads().await },
+        );
+        self.schedule_job(
+            "checksums",
+            "Computes the checksums and the sizes of the crate versions published before they were stored",
+            true,
+            Some(CHECKSUMS_BACKFILL_PERIOD),
+            FirstRun::OnStart,
+            |app| async move { app.checksums_backfill().await },
+        );
+        self.schedule_job(
+            "search",
+            "Indexes the crates that failed to be indexed for search when published",
+            true,
+            Some(SEARCH_PENDING_PERIOD),
+            FirstRun::AfterPeriod,
+            |app| async move { app.search_index_pending().await },
+        );
+        // always active, so that the versions quarantined before a change of the configuration are still released
+        self.schedule_job(
+            "quarantine",
+            "Releases the quarantined versions that are due",
+            true,
+            Some(QUARANTINE_CHECK_PERIOD),
+            FirstRun::OnStart,
+            |app| async move { app.quarantine_release_due().await },
+        );
+    }
+
+    /// Analyzes the dependencies of the crates whose last analysis is stale
+    async fn deps_check_stale(&self) -> Result<(), ApiError> {
+        let configuration = self.configuration.load_full();
+        let result = crate::services::deps::deps_worker_job(
+            &configuration,
+            self.service_deps_checker.clone(),
+            self.service_email_sender.clone(),
+            self.service_chat_notifier.clone(),
+            self.service_webhook_sender.clone(),
+            &self.service_db_pool,
+        )
+        .await;
+        match result {
+            Ok(packages) => {
+                for package in &packages {
+                    self.crate_info_cache.invalidate(package);
                 }
+                Ok(())
+            }
+            Err(e) => {
+                // some crates may have been analyzed before the failure
+                self.crate_info_cache.invalidate_all();
+                Err(e)
             }
-            events.clear();
         }
     }
 
-    /// Handles a set of events
-    async fn events_handler_handle(&self, events: &[AppEvent]) -> Result<(), ApiError> {
-        self.db_transaction_write("events_handler_handle", |app| async move {
-            for event in events {
-                match event {
-                    AppEvent::TokenUse(usage) => {
-                        app.database.update_token_last_usage(usage).await?;
-                    }
-                    AppEvent::CrateDownload(CrateVersion { package: name, version }) => {
-                        app.database.increment_crate_version_dl_count(name, version).await?;
-                    }
-                }
+    /// Registers a background job in the scheduler
+    fn schedule_job<F, FUT>(
+        self: &Arc<Self>,
+        name: &str,
+        description: &str,
+        enabled: bool,
+        period: Option<Duration>,
+        first_run: FirstRun,
+        job: F,
+    ) where
+        F: Fn(Arc<Self>) -> FUT + Send + Sync + 'static,
+        FUT: Future<Output = Result<(), ApiError>> + Send + 'static,
+    {
+        let app = self.clone();
+        self.scheduler.schedule(
+            name,
+            description,
+            enabled,
+            period,
+            first_run,
+            Box::new(move || Box::pin(job(app.clone()))),
+        );
+    }
+
+    /// Releases the quarantined versions whose quarantine has elapsed, or whose checks pass when configured
+    pub(crate) async fn quarantine_release_due(&self) -> Result<(), ApiError> {
+        let versions = self
+            .db_transaction_read(|app| async move { app.database.get_quarantined_versions().await })
+            .await?;
+        let release_on_checks = self.configuration.load().quarantine.release_on_checks;
+        let now = Local::now().naive_local();
+        for quarantined in versions {
+            // a failure for a version must not prevent the release of the others
+            match self.quarantine_release_if_due(&quarantined, release_on_checks, now).await {
+                Ok(true) => info!("quarantine: released {} {}", quarantined.package, quarantined.version),
+                Ok(false) => {}
+                Err(error) => error!(
+                    "quarantine: failed to release {} {}: {error}",
+                    quarantined.package, quarantined.version
+                ),
             }
-            Ok::<_, ApiError>(())
-        })
-        .await
+        }
+        Ok(())
     }
 
-    /// Executes a piece of work in the context of a transaction
-    /// The transaction is committed if the operation succeed,
-    /// or rolled back if it fails
+    /// Releases a quarantined version when its quarantine has elapsed, or its checks pass when configured
     ///
-    /// # Errors
+    /// Gets whether the version was released.
+    async fn quarantine_release_if_due(
+        &self,
+        quarantined: &QuarantinedVersion,
+        release_on_checks: bool,
+        now: NaiveDateTime,
+    ) -> Result<bool, ApiError> {
+        let is_due = quarantined.quarantine_until <= now
+            || (release_on_checks
+                && self
+                    .quarantine_checks_pass(&quarantined.package, &quarantined.version)
+                    .await?);
+        if !is_due {
+            return Ok(false);
+        }
+        self.quarantine_release(&quarantined.package, &quarantined.version).await
+    }
+
+    /// Gets whether the checks pass for a quarantined version, i.e. whether its dependencies are free of known advisories
+    async fn quarantine_checks_pass(&self, package: &str, version: &str) -> Result<bool, ApiError> {
+        let metadata = self
+            .service_storage
+            .download_crate_metadata(package, version)
+            .await?
+            .ok_or_else(error_not_found)?;
+        let deps = metadata.deps.iter().map(IndexCrateDependency::from).collect::<Vec<_>>();
+        let targets = self
+            .db_transaction_read(|app| async move { app.database.get_crate_targets(package).await })
+            .await?
+            .into_iter()
+            .map(|info| info.target)
+            .collect::<Vec<_>>();
+        let analysis = self.service_deps_checker.check_crate_deps(&deps, &targets).await?;
+        Ok(analysis.advisories.is_empty())
+    }
+
+    /// Releases a quarantined version, adding it to the index, then generating its documentation and notifying its publication
     ///
-    /// Returns an instance of the `E` type argument
-    pub(crate) async fn db_transaction_read<'s, F, FUT, T, E>(&'s self, workload: F) -> Result<T, E>
-    where
-        F: FnOnce(ApplicationWithTransaction<'s>) -> FUT,
-        FUT: Future<Output = Result<T, E>>,
-        E: From<sqlx::Error>,
-    {
-        db_transaction_read(&self.service_db_pool, |database| async move {
-            workload(ApplicationWithTransaction {
-                database,
-                application: self,
+    /// Gets whether the version was released by this call, it is not when it was released concurrently.
+    async fn quarantine_release(&self, package: &str, version: &str) -> Result<bool, ApiError> {
+        let content = self.service_storage.download_crate(package, version).await?;
+        let metadata = self
+            .service_storage
+            .download_crate_metadata(package, version)
+            .await?
+            .ok_or_else(error_not_found)?;
+        let search_document = CrateSearchDocument::from_metadata(&metadata, &extract_readme(&content)?);
+        let index_data = CrateUploadData {
+            metadata: metadata.clone(),
+            content,
+        }
+        .build_index_data();
+        // lift the quarantine first, so that only one of concurrent releases goes through
+        let released = self
+            .db_transaction_write("quarantine_release", |app| async move {
+                let Some(quarantine_until) = app.database.release_crate_version_quarantine(package, version).await? else {
+                    return Ok(None);
+                };
+                let user = app.database.get_crate_version_uploader(package, version).await?;
+                let targets = app.get_crate_targets_or_default(package).await?;
+                let capabilities = app.database.get_crate_required_capabilities(package).await?;
+                Ok::<_, ApiError>(Some((quarantine_until, user, targets, capabilities)))
             })
-            .await
+            .await?;
+        let Some((quarantine_until, user, targets, capabilities)) = released else {
+            return Ok(false);
+        };
+        if let Err(error) = self.service_index.publish_crate_version(&index_data).await {
+            // put the version back in quarantine so that its release is attempted again
+            self.db_transaction_write("quarantine_restore", |app| async move {
+                app.database
+                    .set_crate_version_quarantine(package, version, Some(quarantine_until))
+                    .await
+            })
+            .await?;
+            return Err(error);
+        }
+        self.crate_info_cache.invalidate(package);
+        self.search_index_crate(search_document).await;
+        self.queue_crate_version_docs(&index_data, targets, &capabilities, &user)
+            .await?;
+        self.notify_crate_version_published(&index_data, &metadata, &user);
+        Ok(true)
+    }
+
+    /// Checks the names of all the local crates against crates.io and flags those that are claimed there
+    async fn confusion_check(&self) -> Result<(), ApiError> {
+        let crates = self
+            .db_transaction_read(|app| async move { app.database.get_crates_claimed_upstream().await })
+            .await?;
+        let mut changed = Vec::new();
+        for (name, was_claimed) in crates {
+            let is_claimed = match self.service_proxy.get_crates_io_versions(&name).await {
+                Ok(versions) => versions.is_some(),
+                Err(e) => {
+                    warn!("dependency confusion: failed to check {name} on crates.io: {e}");
+                    continue;
+                }
+            };
+            if is_claimed != was_claimed {
+                changed.push((name, is_claimed));
+            }
+        }
+        if changed.is_empty() {
+            return Ok(());
+        }
+        let changed = &changed;
+        self.db_transaction_write("confusion_check", |app| async move {
+            for (name, is_claimed) in changed {
+                app.database.set_crate_claimed_upstream(name, *is_claimed).await?;
+            }
+            Ok::<_, ApiError>(())
         })
-        .await
+        .await?;
+        for (name, _) in changed {
+            self.crate_info_cache.invalidate(name);
+        }
+        for (name, _) in changed.iter().filter(|(_, is_claimed)| *is_claimed) {
+            warn!("dependency confusion: crate {name} is now claimed on crates.io");
+            self.chat_notify_spawn(ChatMessage {
+                title: format!("{name} is now claimed on crates.io"),
+                lines: vec![String::from(
+                    "Its users must always specify the registry to avoid dependency confusion",
+                )],
+                link: format!("{}/crates/{name}", self.configuration.load().web_public_uri),
+            });
+        }
+        Ok(())
     }
 
-    /// Executes a piece of work in the context of a transaction
-    /// The transaction is committed if the operation succeed,
-    /// or rolled back if it fails
-    ///
-    /// # Errors
+    /// Synchronizes the `RustSec` advisory database and checks all the hosted crate versions against it
     ///
-    /// Returns an instance of the `E` type argument
-    pub(crate) async fn db_transaction_write<'s, F, FUT, T, E>(&'s self, operation: &'static str, workload: F) -> Result<T, E>
-    where
-        F: FnOnce(ApplicationWithTransaction<'s>) -> FUT,
-        FUT: Future<Output = Result<T, E>>,
-        E: From<sqlx::Error>,
-    {
-        db_transaction_write(&self.service_db_pool, operation, |database| async move {
-            workload(ApplicationWithTransaction {
-                database,
-                application: self,
+    /// When the advisories changed, the dependency analysis of the crates is performed again.
+    async fn advisories_sync(&self) -> Result<(), ApiError> {
+        let changed = self.service_rustsec.sync().await?;
+        if changed && self.configuration.load().deps_stale_analysis >= 0 {
+            // the change is only reported once, so the analyses are reset before the checks that may fail
+            self.db_transaction_write("reset_deps_analysis", |app| async move {
+                app.database.reset_deps_analysis().await
             })
-            .await
-        })
-        .await
+            .await?;
+        }
+        let versions = self
+            .db_transaction_read(|app| async move { app.database.get_all_crate_versions().await })
+            .await?;
+        let mut checked = Vec::with_capacity(versions.len());
+        for crate_version in versions {
+            let Ok(semver) = crate_version.version.parse::<Version>() else {
+                continue;
+            };
+            let advisories = self
+                .service_rustsec
+                .check_crate(&crate_version.package, &semver)
+                .await?
+                .iter()
+                .map(|advisory| CrateVersionAdvisory::new(&crate_version, &crate_version.package, &semver, advisory, true))
+                .collect::<Vec<_>>();
+            checked.push((crate_version, advisories));
+        }
+        let found = self
+            .db_transaction_write("sync_advisories", |app| async move {
+                let mut found = Vec::new();
+                for (crate_version, advisories) in checked {
+                    found.extend(
+                        app.database
+                            .set_crate_version_advisories(&crate_version.package, &crate_version.version, true, advisories)
+                            .await?,
+                    );
+                }
+                Ok::<_, ApiError>(found)
+            })
+            .await?;
+        self.crate_info_cache.invalidate_all();
+        for advisory in found {
+            warn!(
+                "advisory {} found against {} {}: {}",
+                advisory.advisory, advisory.package, advisory.version, advisory.summary
+            );
+        }
+        *self.advisories_last_sync.lock().unwrap() = Some(Local::now().naive_local());
+        Ok(())
     }
 
-    /// Attempts the authentication of a user
-    pub async fn authenticate(&self, auth_data: &AuthData) -> Result<Authentication, ApiError> {
-        self.db_transaction_read(|app| async move { app.authenticate(auth_data).await })
-            .await
+    /// Starts a backup of the database in the background
+    /// Fails with a conflict if a backup is already running
+    fn backup_spawn(self: &Arc<Self>) -> Result<BackupStatus, ApiError> {
+        let status = self.backup_begin()?;
+        let app = self.clone();
+        let _handle = tokio::spawn(async move {
+            if let Err(e) = app.backup_execute().await {
+                error!("backup failed: {e}");
+                if let Some(backtrace) = &e.backtrace {
+                    error!("{backtrace}");
+                }
+            }
+        });
+        Ok(status)
+    }
+
+    /// Marks a backup as running
+    ///
+    /// Fails when a backup is already running.
+    fn backup_begin(&self) -> Result<BackupStatus, ApiError> {
+        let mut status = self.backup_status.lock().unwrap();
+        if status.is_running {
+            return Err(specialize(error_conflict(), String::from("a backup is already running")));
+        }
+        status.is_running = true;
+        status.last_attempt = Some(Local::now().naive_local());
+        Ok(status.clone())
+    }
+
+    /// Runs a backup that was marked as running and records its outcome
+    async fn backup_execute(&self) -> Result<(), ApiError> {
+        let result = self.backup_run().await;
+        let mut status = self.backup_status.lock().unwrap();
+        status.is_running = false;
+        match result {
+            Ok(report) => {
+                status.last_success = Some(Local::now().naive_local());
+                status.last_name = Some(report.name);
+                status.last_error = None;
+                status.last_duration = Some(report.duration);
+                status.last_database_size = Some(report.database_size);
+                status.last_size = Some(report.size);
+                Ok(())
+            }
+            Err(e) => {
+                status.last_error = Some(e.to_string());
+                Err(e)
+            }
+        }
+    }
+
+    /// Takes a consistent snapshot of the database, uploads it to the storage and prunes the old backups
+    ///
+    /// The backup is incremental when the current chain of backups is not complete yet.
+    async fn backup_run(&self) -> Result<BackupReport, ApiError> {
+        let configuration = self.configuration.load_full();
+        let started = Instant::now();
+        let key = configuration.backup.get_encryption_key()?;
+        let mut existing = self.service_storage.list_backups().await?;
+        let chain = self.backup_chain.lock().unwrap().clone().filter(|chain| {
+            chain.length < configuration.backup.full_every
+                && existing.contains(&chain.last_name)
+                && backup_kind(&chain.last_name).is_some_and(|kind| kind.encrypted == key.is_some())
+        });
+        let name = backup_name(
+            Utc::now(),
+            BackupKind {
+                incremental: chain.is_some(),
+                encrypted: key.is_some(),
+            },
+        );
+        let snapshot = format!("{}/{name}.tmp", configuration.data_dir);
+        let _ = tokio::fs::remove_file(&snapshot).await;
+        let sealed = match self.service_db_pool.snapshot_into(&snapshot).await {
+            Ok(()) => {
+                let (snapshot, chain) = (snapshot.clone(), chain.clone());
+                tokio::task::spawn_blocking(move || {
+                    seal_backup_file(std::path::Path::new(&snapshot), chain.as_ref(), key.as_deref())
+                })
+                .await?
+            }
+            Err(e) => Err(e),
+        };
+        let _ = tokio::fs::remove_file(&snapshot).await;
+        let SealedBackup {
+            content,
+            digests,
+            database_size,
+        } = sealed?;
+        let size = content.len() as u64;
+        self.service_storage.store_backup(&name, content).await?;
+        let duration = u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX);
+        info!("backup: stored {name} ({size} bytes, {database_size} bytes uncompressed) in {duration}ms");
+        *self.backup_chain.lock().unwrap() = Some(BackupChain {
+            last_name: name.clone(),
+            length: chain.map_or(1, |chain| chain.length + 1),
+            digests,
+        });
+
+        existing.push(name.clone());
+        for old in backups_to_prune(&existing, configuration.backup.retention) {
+            self.service_storage.delete_backup(&old).await?;
+            info!("backup: pruned {old}");
+        }
+        Ok(BackupReport {
+            name,
+            duration,
+            database_size,
+            size,
+        })
+    }
+
+    /// Starts rebuilding the search index in the background
+    /// Fails with a conflict if a rebuild is already running
+    fn search_index_rebuild_spawn(self: &Arc<Self>) -> Result<SearchReindexProgress, ApiError> {
+        let progress = {
+            let mut progress = self.search_reindex.lock().unwrap();
+            if progress.is_running {
+                return Err(specialize(
+                    error_conflict(),
+                    String::from("the search index is already being rebuilt"),
+                ));
+            }
+            *progress = SearchReindexProgress {
+                is_running: true,
+                started_on: Some(Local::now().naive_local()),
+                ..Default::default()
+            };
+            progress.clone()
+        };
+        let app = self.clone();
+        let _handle = tokio::spawn(async move {
+            let result = app.search_index_rebuild().await;
+            let mut progress = app.search_reindex.lock().unwrap();
+            progress.is_running = false;
+            progress.finished_on = Some(Local::now().naive_local());
+            if let Err(e) = result {
+                error!("{e}");
+                if let Some(backtrace) = &e.backtrace {
+                    error!("{backtrace}");
+                }
+                progress.error = Some(e.to_string());
+            }
+        });
+        Ok(progress)
+    }
+
+    /// Records the dependencies of all the crate versions from the index
+    async fn dependencies_rebuild(&self) -> Result<(), ApiError> {
+        info!("recording the dependencies of all crates ...");
+        let versions = db_transaction_read(&self.service_db_pool, |database| async move {
+            database.get_crates_last_versions().await
+        })
+        .await?;
+        for CrateVersion { package, .. } in &versions {
+            let metadata = self.service_index.get_crate_data(package).await?;
+            db_transaction_write(&self.service_db_pool, "dependencies_rebuild", |database| async move {
+                for data in &metadata {
+                    database.set_crate_version_dependencies(data).await?;
+                }
+                Ok::<_, ApiError>(())
+            })
+            .await?;
+        }
+        info!("recorded the dependencies of {} crates", versions.len());
+        Ok(())
+    }
+
+    /// Gets the service proxying the upstream registry
+    #[must_use]
+    pub fn get_service_proxy(&self) -> &(dyn Proxy + Send + Sync) {
+        self.service_proxy.as_ref()
+    }
+
+    /// Indexes a crate in the full-text search once it is published
+    /// On failure, the crate is indexed again later by the `search` job, so that the publication still succeeds
+    async fn search_index_crate(&self, document: CrateSearchDocument) {
+        if let Err(e) = self.service_search.index_crate(&document).await {
+            error!("failed to index {} for search, it will be retried: {e}", document.name);
+            self.search_pending.lock().unwrap().insert(document.name.clone(), document);
+        }
+    }
+
+    /// Indexes again the crates that failed to be indexed for search
+    pub(crate) async fn search_index_pending(&self) -> Result<(), ApiError> {
+        let pending = std::mem::take(&mut *self.search_pending.lock().unwrap());
+        let mut failure = None;
+        for (name, document) in pending {
+            if let Err(e) = self.service_search.index_crate(&document).await {
+                // keep the document unless a more recent one is pending
+                self.search_pending.lock().unwrap().entry(name).or_insert(document);
+                failure = Some(e);
+            }
+        }
+        failure.map_or(Ok(()), Err)
+    }
+
+    /// Indexes the last version of all crates in the full-text search, replacing the previous content
+    async fn search_index_rebuild(&self) -> Result<(), ApiError> {
+        info!("rebuilding the search index ...");
+        let versions = db_transaction_read(&self.service_db_pool, |database| async move {
+            database.get_crates_last_versions().await
+        })
+        .await?;
+        self.search_reindex.lock().unwrap().total = versions.len();
+        let mut documents = Vec::with_capacity(versions.len());
+        for CrateVersion { package, version } in &versions {
+            if let Some(metadata) = self.service_storage.download_crate_metadata(package, version).await? {
+                let readme = self
+                    .service_storage
+                    .download_crate_readme(package, version)
+                    .await
+                    .unwrap_or_default();
+                documents.push(CrateSearchDocument::from_metadata(&metadata, &readme));
+            }
+            self.search_reindex.lock().unwrap().done += 1;
+        }
+        self.service_search.rebuild(documents).await?;
+        info!("rebuilt the search index with {} crates", versions.len());
+        Ok(())
+    }
+
+    /// The worker to handle the update of token usage
+    async fn events_handler(&self, mut receiver: Receiver<AppEvent>) {
+        const BUFFER_SIZE: usize = 64;
+        let mut events = Vec::with_capacity(BUFFER_SIZE);
+        loop {
+            let count = receiver.recv_many(&mut events, BUFFER_SIZE).await;
+            if count == 0 {
+                break;
+            }
+            if let Err(e) = self.events_handler_handle(&events).await {
+                error!("{e}");
+                if let Some(backtrace) = e.backtrace {
+                    error!("{backtrace}");
+                }
+            }
+            events.clear();
+        }
+    }
+
+    /// Starts listening to the documentation jobs in the background to notify about finished jobs
+    async fn docs_jobs_listener_spawn(self: &Arc<Self>) -> Result<(), ApiError> {
+        let (sender, receiver) = channel(16);
+        self.service_docs_generator.add_listener(sender).await?;
+        let app = self.clone();
+        let _handle = tokio::spawn(async move {
+            app.docs_jobs_listener(receiver).await;
+        });
+        Ok(())
+    }
+
+    /// Listens to the documentation jobs and notifies the webhooks and owners when one is finished
+    async fn docs_jobs_listener(&self, mut receiver: Receiver<DocGenEvent>) {
+        while let Some(event) = receiver.recv().await {
+            let DocGenEvent::Update(DocGenJobUpdate { job_id, state, .. }) = event else {
+                continue;
+            };
+            let event = match state {
+                DocGenJobState::Success => WebhookEvent::DocsBuilt,
+                DocGenJobState::Failure => WebhookEvent::DocsFailed,
+                _ => continue,
+            };
+            match db_transaction_read(&self.service_db_pool, |database| async move {
+                database.get_docgen_job(job_id).await
+            })
+            .await
+            {
+                Ok(job) => {
+                    self.crate_info_cache.invalidate(&job.package);
+                    if event == WebhookEvent::DocsFailed {
+                        let configuration = self.configuration.load_full();
+                        let service_email_sender = self.service_email_sender.clone();
+                        let pool = self.service_db_pool.clone();
+                        let job = job.clone();
+                        Self::notify_spawn(async move {
+                            notifications::notify_docs_failure(&configuration, service_email_sender, &pool, &job).await
+                        });
+                    }
+                    self.webhooks_dispatch(WebhookPayload {
+                        event,
+                        timestamp: Local::now().naive_local(),
+                        package: job.package,
+                        version: Some(job.version),
+                        target: Some(job.target),
+                        owners: None,
+                    });
+                }
+                Err(e) => error!("{e}"),
+            }
+        }
+    }
+
+    /// Sends a notification by email in the background, logging the failures
+    fn notify_spawn<F>(notification: F)
+    where
+        F: Future<Output = Result<(), ApiError>> + Send + 'static,
+    {
+        let _handle = tokio::spawn(async move {
+            if let Err(e) = notification.await {
+                error!("{e}");
+                if let Some(backtrace) = &e.backtrace {
+                    error!("{backtrace}");
+                }
+            }
+        });
+    }
+
+    /// Posts a notification to the configured chat services, in the background
+    fn chat_notify_spawn(&self, message: ChatMessage) {
+        if !self.configuration.load().chat.is_enabled() {
+            return;
+        }
+        let service_chat_notifier = self.service_chat_notifier.clone();
+        Self::notify_spawn(async move { service_chat_notifier.notify(&message).await });
+    }
+
+    /// Notifies about a yanked version, in the background
+    fn notify_yank_spawn(&self, package: &str, version: &str, reason: Option<&str>) {
+        self.chat_notify_spawn(ChatMessage {
+            title: format!("{package} {version} was yanked"),
+            lines: reason.map(|reason| format!("Reason: {reason}")).into_iter().collect(),
+            link: format!("{}/crates/{package}/{version}", self.configuration.load().web_public_uri),
+        });
+        self.notify_yank_email_spawn(package, version, reason);
+    }
+
+    /// Notifies the owners of the crates depending on a yanked version, in the background
+    fn notify_yank_email_spawn(&self, package: &str, version: &str, reason: Option<&str>) {
+        let configuration = self.configuration.load_full();
+        let service_email_sender = self.service_email_sender.clone();
+        let pool = self.service_db_pool.clone();
+        let package = package.to_string();
+        let version = version.to_string();
+        let reason = reason.map(str::to_string);
+        Self::notify_spawn(async move {
+            notifications::notify_yank(
+                &configuration,
+                service_email_sender,
+                &pool,
+                &package,
+                &version,
+                reason.as_deref(),
+            )
+            .await
+        });
+    }
+
+    /// Notifies the owners of the crates depending on a deprecated crate, in the background
+    fn notify_deprecation_email_spawn(&self, package: &str, reason: Option<&str>) {
+        let configuration = self.configuration.load_full();
+        let service_email_sender = self.service_email_sender.clone();
+        let pool = self.service_db_pool.clone();
+        let package = package.to_string();
+        let reason = reason.map(str::to_string);
+        Self::notify_spawn(async move {
+            notifications::notify_deprecation(&configuration, service_email_sender, &pool, &package, reason.as_deref()).await
+        });
+    }
+
+    /// Dispatches an event to the matching webhooks and to the users watching the crate, in the background
+    fn webhooks_dispatch(&self, payload: WebhookPayload) {
+        if payload.is_for_watchers() {
+            let configuration = self.configuration.load_full();
+            let service_email_sender = self.service_email_sender.clone();
+            let pool = self.service_db_pool.clone();
+            let payload = payload.clone();
+            Self::notify_spawn(async move {
+                notifications::notify_watchers(&configuration, service_email_sender, &pool, &payload).await
+            });
+        }
+        crate::services::webhooks::dispatch(self.service_db_pool.clone(), self.service_webhook_sender.clone(), payload);
+    }
+
+    /// Records the download of a crate version
+    ///
+    /// The downloads are buffered and written to the database in batches, unless the flush period is `0`.
+    async fn downloads_record(&self, package: &str, version: &str) {
+        *self
+            .pending_downloads
+            .lock()
+            .unwrap()
+            .entry(CrateVersion {
+                package: package.to_string(),
+                version: version.to_string(),
+            })
+            .or_insert(0) += 1;
+        if self.configuration.load().downloads_flush_period == 0 {
+            if let Err(e) = self.flush_downloads().await {
+                error!("failed to write the download counts: {e}");
+            }
+        }
+    }
+
+    /// Stops the background jobs, waiting for the running ones within the grace period, then writes the buffered data
+    pub async fn shutdown(&self, grace_period: Duration) {
+        if tokio::time::timeout(grace_period, self.scheduler.stop()).await.is_err() {
+            warn!("shutdown: some background jobs were still running after the grace period");
+        }
+        if let Err(e) = self.flush_downloads().await {
+            error!("shutdown: failed to write the download counts: {e}");
+        }
+    }
+
+    /// Writes the buffered download counts to the database
+    ///
+    /// On failure, the counts are kept in the buffer for the next attempt.
+    pub async fn flush_downloads(&self) -> Result<(), ApiError> {
+        let downloads = std::mem::take(&mut *self.pending_downloads.lock().unwrap());
+        if downloads.is_empty() {
+            return Ok(());
+        }
+        let result = self
+            .db_transaction_write("flush_downloads", |app| {
+                let downloads = &downloads;
+                async move {
+                    for (CrateVersion { package, version }, count) in downloads {
+                        app.database
+                            .increment_crate_version_dl_count(package, version, *count)
+                            .await?;
+                    }
+                    Ok::<_, ApiError>(())
+                }
+            })
+            .await;
+        if result.is_ok() {
+            for CrateVersion { package, .. } in downloads.keys() {
+                self.crate_info_cache.invalidate(package);
+            }
+        } else {
+            let mut pending = self.pending_downloads.lock().unwrap();
+            for (crate_version, count) in downloads {
+                *pending.entry(crate_version).or_insert(0) += count;
+            }
+        }
+        result
+    }
+
+    /// Handles a set of events
+    async fn events_handler_handle(&self, events: &[AppEvent]) -> Result<(), ApiError> {
+        self.db_transaction_write("events_handler_handle", |app| async move {
+            let mut traffic = HashMap::<TrafficSubject, (i64, i64)>::new();
+            for event in events {
+                match event {
+                    AppEvent::TokenUse(usage) => {
+                        app.database.update_token_last_usage(usage).await?;
+                    }
+                    AppEvent::Traffic(usage) => {
+                        let (requests, bytes) = traffic.entry(usage.subject).or_insert((0, 0));
+                        *requests += 1;
+                        *bytes += i64::try_from(usage.bytes).unwrap_or(i64::MAX);
+                    }
+                }
+            }
+            for (subject, (requests, bytes)) in traffic {
+                app.database.add_traffic(subject, requests, bytes).await?;
+            }
+            Ok::<_, ApiError>(())
+        })
+        .await
+    }
+
+    /// Executes a piece of work in the context of a transaction
+    /// The transaction is committed if the operation succeed,
+    /// or rolled back if it fails
+    ///
+    /// # Errors
+    ///
+    /// Returns an instance of the `E` type argument
+    pub(crate) async fn db_transaction_read<'s, F, FUT, T, E>(&'s self, workload: F) -> Result<T, E>
+    where
+        F: FnOnce(ApplicationWithTransaction<'s>) -> FUT,
+        FUT: Future<Output = Result<T, E>>,
+        E: From<sqlx::Error>,
+    {
+        db_transaction_read(&self.service_db_pool, |database| async move {
+            workload(ApplicationWithTransaction {
+                database,
+                application: self,
+                operation: None,
+            })
+            .await
+        })
+        .await
+    }
+
+    /// Executes a piece of work in the context of a transaction
+    /// The transaction is committed if the operation succeed,
+    /// or rolled back if it fails
+    ///
+    /// # Errors
+    ///
+    /// Returns an instance of the `E` type argument
+    pub(crate) async fn db_transaction_write<'s, F, FUT, T, E>(&'s self, operation: &'static str, workload: F) -> Result<T, E>
+    where
+        F: FnOnce(ApplicationWithTransaction<'s>) -> FUT,
+        FUT: Future<Output = Result<T, E>>,
+        E: From<sqlx::Error>,
+    {
+        db_transaction_write(&self.service_db_pool, operation, |database| async move {
+            workload(ApplicationWithTransaction {
+                database,
+                application: self,
+                operation: Some(operation),
+            })
+            .await
+        })
+        .await
+    }
+
+    /// Attempts the authentication of a user
+    pub async fn authenticate(&self, auth_data: &AuthData) -> Result<Authentication, ApiError> {
+        self.db_transaction_read(|app| async move { app.authenticate(auth_data).await })
+            .await
+    }
+
+    /// Verifies a token and gets the principal it authenticates
+    pub async fn verify_token(&self, token: &Token) -> Result<AuthenticationPrincipal, ApiError> {
+        self.db_transaction_read(|app| async move { app.authenticate_token(token).await })
+            .await
+            .map(|authentication| authentication.principal)
     }
 
     /// Gets the registry configuration
     pub async fn get_registry_information(&self, auth_data: &AuthData) -> Result<RegistryInformation, ApiError> {
         let _authentication = self.authenticate(auth_data).await?;
+        let configuration = self.configuration.load();
         Ok(RegistryInformation {
-            registry_name: self.configuration.self_local_name.clone(),
-            toolchain_host: self.configuration.self_toolchain_host.clone(),
-            toolchain_version_stable: self.configuration.self_toolchain_version_stable.clone(),
-            toolchain_version_nightly: self.configuration.self_toolchain_version_nightly.clone(),
-            toolchain_targets: self.configuration.self_known_targets.clone(),
+            registry_name: configuration.self_local_name.clone(),
+            toolchain_host: configuration.self_toolchain_host.clone(),
+            toolchain_version_stable: configuration.self_toolchain_version_stable.clone(),
+            toolchain_version_nightly: configuration.self_toolchain_version_nightly.clone(),
+            toolchain_targets: configuration.self_known_targets.clone(),
         })
     }
 
@@ -295,65 +1378,295 @@ impl Application {
         .await
     }
 
-    /// Attempts to login using an OAuth code
-    pub async fn login_with_oauth_code(&self, code: &str) -> Result<RegistryUser, ApiError> {
-        self.db_transaction_write("login_with_oauth_code", |app| async move {
-            app.database.login_with_oauth_code(&self.configuration, code).await
+    /// Gets whether the private data of a user, e.g. the email, can be seen, only by the user themselves and the administrators
+    pub async fn can_see_user_private_data(&self, auth_data: &AuthData, uid: i64) -> Result<bool, ApiError> {
+        self.db_transaction_read(|app| async move {
+            let authentication = app.authenticate(auth_data).await?;
+            match &authentication.principal {
+                AuthenticationPrincipal::User { uid: principal_uid, .. } => Ok(
+                    *principal_uid == uid || (authentication.can_admin && app.database.get_is_admin(*principal_uid).await?)
+                ),
+                AuthenticationPrincipal::Service { .. } | AuthenticationPrincipal::SelfAuth => Ok(authentication.can_admin),
+            }
         })
         .await
     }
 
-    /// Gets the known users
-    pub async fn get_users(&self, auth_data: &AuthData) -> Result<Vec<RegistryUser>, ApiError> {
+    /// Updates the profile of the current user
+    pub async fn update_current_user(
+        &self,
+        auth_data: &AuthData,
+        update: &UserProfileUpdate,
+    ) -> Result<RegistryUser, ApiError> {
+        let (user, packages) = self
+            .db_transaction_write("update_current_user", |app| async move {
+                let authentication = app.authenticate(auth_data).await?;
+                authentication.check_can_write()?;
+                let uid = authentication.uid()?;
+                let user = app.database.update_user_profile(uid, update).await?;
+                let packages = app.database.get_crates_showing_user(uid).await?;
+                Ok::<_, ApiError>((user, packages))
+            })
+            .await?;
+        // the profile of the user is shown as an owner and as the uploader of versions
+        for package in &packages {
+            self.crate_info_cache.invalidate(package);
+        }
+        Ok(user)
+    }
+
+    /// Gets the preferences of the current user
+    pub async fn get_current_user_preferences(&self, auth_data: &AuthData) -> Result<UserPreferences, ApiError> {
         self.db_transaction_read(|app| async move {
             let authentication = app.authenticate(auth_data).await?;
-            app.check_can_admin_registry(&authentication).await?;
-            app.database.get_users().await
+            app.database.get_user_preferences(authentication.uid()?).await
         })
         .await
     }
 
-    /// Updates the information of a user
-    pub async fn update_user(&self, auth_data: &AuthData, target: &RegistryUser) -> Result<RegistryUser, ApiError> {
-        self.db_transaction_write("update_user", |app| async move {
+    /// Sets the preferences of the current user
+    pub async fn set_current_user_preferences(
+        &self,
+        auth_data: &AuthData,
+        preferences: &UserPreferences,
+    ) -> Result<UserPreferences, ApiError> {
+        self.db_transaction_write("set_user_preferences", |app| async move {
             let authentication = app.authenticate(auth_data).await?;
-            let principal_uid = authentication.uid()?;
-            let can_admin = if target.id == principal_uid {
-                // same user
-                authentication.can_admin && app.database.get_is_admin(principal_uid).await?
-            } else {
-                // different users, requires admin
-                app.check_can_admin_registry(&authentication).await?;
-                true
-            };
-            app.database.update_user(principal_uid, target, can_admin).await
+            authentication.check_can_write()?;
+            let uid = authentication.uid()?;
+            app.database.set_user_preferences(uid, preferences).await?;
+            app.database.get_user_preferences(uid).await
         })
         .await
     }
 
-    /// Attempts to deactivate a user
-    pub async fn deactivate_user(&self, auth_data: &AuthData, target: &str) -> Result<(), ApiError> {
-        self.db_transaction_write("deactivate_user", |app| async move {
-            let authentication = app.authenticate(auth_data).await?;
-            let principal_uid = app.check_can_admin_registry(&authentication).await?;
-            app.database.deactivate_user(principal_uid, target).await
+    /// Attempts to login using an OAuth code
+    pub async fn login_with_oauth_code(&self, code: &str) -> Result<RegistryUser, ApiError> {
+        self.db_transaction_write("login_with_oauth_code", |app| async move {
+            app.database.login_with_oauth_code(&self.configuration.load(), code).await
         })
         .await
     }
 
-    /// Attempts to re-activate a user
-    pub async fn reactivate_user(&self, auth_data: &AuthData, target: &str) -> Result<(), ApiError> {
-        self.db_transaction_write("reactivate_user", |app| async move {
+    /// Gets a page of the known users
+    pub async fn get_users(
+        &self,
+        auth_data: &AuthData,
+        filter: Option<&str>,
+        pagination: Pagination<UserSort>,
+    ) -> Result<Page<RegistryUser>, ApiError> {
+        self.db_transaction_read(|app| async move {
             let authentication = app.authenticate(auth_data).await?;
             app.check_can_admin_registry(&authentication).await?;
-            app.database.reactivate_user(target).await
+            app.database.get_users(filter, pagination).await
         })
         .await
     }
 
-    /// Attempts to delete a user
-    pub async fn delete_user(&self, auth_data: &AuthData, target: &str) -> Result<(), ApiError> {
-        self.db_transaction_write("delete_user", |app| async move {
+    /// Starts impersonating a user, for a limited time
+    pub async fn impersonate_user(
+        &self,
+        auth_data: &AuthData,
+        target: &str,
+        request: &ImpersonationRequest,
+    ) -> Result<ImpersonationToken, ApiError> {
+        self.db_transaction_write("impersonate_user", |app| async move {
+            let authentication = app.authenticate(auth_data).await?;
+            let principal_uid = app.check_can_admin_registry(&authentication).await?;
+            let (target_uid, token) = app.database.create_impersonation(principal_uid, target, request).await?;
+            let details = format!(
+                "impersonation {}, canWrite={}, expires on {}: {}",
+                token.id, token.can_write, token.expires_on, request.reason
+            );
+            app.database
+                .add_audit_entry(Some(target_uid), Some(principal_uid), "impersonation_start", &details)
+                .await?;
+            Ok(token)
+        })
+        .await
+    }
+
+    /// Ends an impersonation before it expires
+    pub async fn end_impersonation(&self, auth_data: &AuthData, impersonation_id: i64) -> Result<(), ApiError> {
+        self.db_transaction_write("end_impersonation", |app| async move {
+            let authentication = app.authenticate(auth_data).await?;
+            let principal_uid = app.check_can_admin_registry(&authentication).await?;
+            let target_uid = app.database.end_impersonation(impersonation_id).await?;
+            app.database
+                .add_audit_entry(
+                    Some(target_uid),
+                    Some(principal_uid),
+                    "impersonation_end",
+                    &format!("impersonation {impersonation_id}"),
+                )
+                .await
+        })
+        .await
+    }
+
+    /// Gets a page of the entries in the audit log
+    pub async fn get_audit_log(
+        &self,
+        auth_data: &AuthData,
+        filter: Option<&str>,
+        pagination: Pagination<AuditLogSort>,
+    ) -> Result<Page<AuditLogEntry>, ApiError> {
+        self.db_transaction_read(|app| async move {
+            let authentication = app.authenticate(auth_data).await?;
+            app.check_can_admin_registry(&authentication).await?;
+            app.database.get_audit_log(filter, pagination).await
+        })
+        .await
+    }
+
+    /// Updates the information of a user
+    pub async fn update_user(&self, auth_data: &AuthData, target: &RegistryUser) -> Result<RegistryUser, ApiError> {
+        let user = self
+            .db_transaction_write("update_user", |app| async move {
+                let authentication = app.authenticate(auth_data).await?;
+                let principal_uid = authentication.uid()?;
+                let can_admin = if target.id == principal_uid {
+                    // same user
+                    authentication.can_admin && app.database.get_is_admin(principal_uid).await?
+                } else {
+                    // different users, requires admin
+                    app.check_can_admin_registry(&authentication).await?;
+                    true
+                };
+                app.database.update_user(principal_uid, target, can_admin).await
+            })
+            .await?;
+        // the profile of the user is shown as the uploader of versions
+        self.crate_info_cache.invalidate_all();
+        Ok(user)
+    }
+
+    /// Attempts to deactivate a user
+    pub async fn deactivate_user(&self, auth_data: &AuthData, target: &str) -> Result<(), ApiError> {
+        self.db_transaction_write("deactivate_user", |app| async move {
+            let authentication = app.authenticate(auth_data).await?;
+            let principal_uid = app.check_can_admin_registry(&authentication).await?;
+            app.database.deactivate_user(principal_uid, target).await
+        })
+        .await
+    }
+
+    /// Attempts to re-activate a user
+    pub async fn reactivate_user(&self, auth_data: &AuthData, target: &str) -> Result<(), ApiError> {
+        self.db_transaction_write("reactivate_user", |app| async move {
+            let authentication = app.authenticate(auth_data).await?;
+            app.check_can_admin_registry(&authentication).await?;
+            app.database.reactivate_user(target).await
+        })
+        .await
+    }
+
+    /// Gets the usage of the current user against their quotas
+    pub async fn get_current_user_quota(&self, auth_data: &AuthData) -> Result<QuotaUsage, ApiError> {
+        let uid = self.authenticate(auth_data).await?.uid()?;
+        self.sizes_backfill_users(&[uid]).await?;
+        self.db_transaction_read(|app| async move { app.database.get_quota_usage(uid, &self.configuration.load().quota).await })
+            .await
+    }
+
+    /// Gets the usage of a user against their quotas, for administrators
+    pub async fn get_user_quota(&self, auth_data: &AuthData, target: &str) -> Result<QuotaUsage, ApiError> {
+        let target_uid = self
+            .db_transaction_read(|app| async move {
+                let authentication = app.authenticate(auth_data).await?;
+                app.check_can_admin_registry(&authentication).await?;
+                app.database.get_quota_target(target).await
+            })
+            .await?;
+        self.sizes_backfill_users(&[target_uid]).await?;
+        self.db_transaction_read(|app| async move {
+            app.database
+                .get_quota_usage(target_uid, &self.configuration.load().quota)
+                .await
+        })
+        .await
+    }
+
+    /// Sets the quotas of a user, overriding the defaults of the registry
+    pub async fn set_user_quota(&self, auth_data: &AuthData, target: &str, quota: &UserQuota) -> Result<QuotaUsage, ApiError> {
+        self.db_transaction_write("set_user_quota", |app| async move {
+            let authentication = app.authenticate(auth_data).await?;
+            let principal_uid = app.check_can_admin_registry(&authentication).await?;
+            let target_uid = app.database.set_user_quota(target, quota).await?;
+            let details = format!(
+                "quota of {target} set to {} bytes and {} crates",
+                quota.max_bytes.map_or_else(|| String::from("default"), |v| v.to_string()),
+                quota.max_crates.map_or_else(|| String::from("default"), |v| v.to_string())
+            );
+            app.database
+                .add_audit_entry(Some(principal_uid), authentication.impersonator, "set_user_quota", &details)
+                .await?;
+            app.database
+                .get_quota_usage(target_uid, &self.configuration.load().quota)
+                .await
+        })
+        .await
+    }
+
+    /// Gets the usage of the teams of the current user against their quotas, for the teams that have quotas
+    pub async fn get_current_user_team_quotas(&self, auth_data: &AuthData) -> Result<Vec<QuotaUsage>, ApiError> {
+        let (teams, uids) = self
+            .db_transaction_read(|app| async move {
+                let uid = app.authenticate(auth_data).await?.uid()?;
+                let teams = app.database.get_user_quota_teams(uid).await?;
+                let uids = app.database.get_quota_scope(uid).await?;
+                Ok::<_, ApiError>((teams, uids))
+            })
+            .await?;
+        self.sizes_backfill_users(&uids).await?;
+        self.db_transaction_read(|app| {
+            let teams = &teams;
+            async move {
+                let mut usages = Vec::with_capacity(teams.len());
+                for team in teams {
+                    usages.push(app.database.get_team_quota_usage(team).await?);
+                }
+                Ok(usages)
+            }
+        })
+        .await
+    }
+
+    /// Gets the usage of the members of a team against the quotas of the team, for administrators
+    pub async fn get_team_quota(&self, auth_data: &AuthData, team: &str) -> Result<QuotaUsage, ApiError> {
+        let members = self
+            .db_transaction_read(|app| async move {
+                let authentication = app.authenticate(auth_data).await?;
+                app.check_can_admin_registry(&authentication).await?;
+                app.database.get_team_members(team).await
+            })
+            .await?;
+        self.sizes_backfill_users(&members).await?;
+        self.db_transaction_read(|app| async move { app.database.get_team_quota_usage(team).await })
+            .await
+    }
+
+    /// Sets the quotas shared by the members of a team
+    pub async fn set_team_quota(&self, auth_data: &AuthData, team: &str, quota: &TeamQuota) -> Result<QuotaUsage, ApiError> {
+        self.db_transaction_write("set_team_quota", |app| async move {
+            let authentication = app.authenticate(auth_data).await?;
+            let principal_uid = app.check_can_admin_registry(&authentication).await?;
+            app.database.set_team_quota(team, quota).await?;
+            let details = format!(
+                "quota of team {team} set to {} bytes and {} crates",
+                quota.max_bytes, quota.max_crates
+            );
+            app.database
+                .add_audit_entry(Some(principal_uid), authentication.impersonator, "set_team_quota", &details)
+                .await?;
+            app.database.get_team_quota_usage(team).await
+        })
+        .await
+    }
+
+    /// Attempts to delete a user
+    pub async fn delete_user(&self, auth_data: &AuthData, target: &str) -> Result<(), ApiError> {
+        self.db_transaction_write("delete_user", |app| async move {
             let authentication = app.authenticate(auth_data).await?;
             let principal_uid = app.check_can_admin_registry(&authentication).await?;
             app.database.delete_user(principal_uid, target).await
@@ -361,12 +1674,17 @@ impl Application {
         .await
     }
 
-    /// Gets the tokens for a user
-    pub async fn get_tokens(&self, auth_data: &AuthData) -> Result<Vec<RegistryUserToken>, ApiError> {
+    /// Gets a page of the tokens for a user
+    pub async fn get_tokens(
+        &self,
+        auth_data: &AuthData,
+        filter: Option<&str>,
+        pagination: Pagination<TokenSort>,
+    ) -> Result<Page<RegistryUserToken>, ApiError> {
         self.db_transaction_read(|app| async move {
             let authentication = app.authenticate(auth_data).await?;
             authentication.check_can_admin()?;
-            app.database.get_tokens(authentication.uid()?).await
+            app.database.get_tokens(authentication.uid()?, filter, pagination).await
         })
         .await
     }
@@ -376,14 +1694,22 @@ impl Application {
         &self,
         auth_data: &AuthData,
         name: &str,
-        can_write: bool,
-        can_admin: bool,
+        can_write: Option<bool>,
+        can_admin: Option<bool>,
     ) -> Result<RegistryUserTokenWithSecret, ApiError> {
         self.db_transaction_write("create_token", |app| async move {
             let authentication = app.authenticate(auth_data).await?;
             authentication.check_can_admin()?;
+            let uid = authentication.uid()?;
+            // the unspecified access rights default to the preferences of the user
+            let preferences = app.database.get_user_preferences(uid).await?;
             app.database
-                .create_token(authentication.uid()?, name, can_write, can_admin)
+                .create_token(
+                    uid,
+                    name,
+                    can_write.unwrap_or(preferences.token_can_write),
+                    can_admin.unwrap_or(preferences.token_can_admin),
+                )
                 .await
         })
         .await
@@ -399,12 +1725,17 @@ impl Application {
         .await
     }
 
-    /// Gets the global tokens for the registry, usually for CI purposes
-    pub async fn get_global_tokens(&self, auth_data: &AuthData) -> Result<Vec<RegistryUserToken>, ApiError> {
+    /// Gets a page of the global tokens for the registry, usually for CI purposes
+    pub async fn get_global_tokens(
+        &self,
+        auth_data: &AuthData,
+        filter: Option<&str>,
+        pagination: Pagination<TokenSort>,
+    ) -> Result<Page<RegistryUserToken>, ApiError> {
         self.db_transaction_read(|app| async move {
             let authentication = app.authenticate(auth_data).await?;
             app.check_can_admin_registry(&authentication).await?;
-            app.database.get_global_tokens().await
+            app.database.get_global_tokens(filter, pagination).await
         })
         .await
     }
@@ -429,27 +1760,100 @@ impl Application {
         .await
     }
 
+    /// Checks that a user can publish and computes the sizes of the previous versions counted in their quotas
+    async fn check_publisher(&self, auth_data: &AuthData) -> Result<(), ApiError> {
+        let uids = self
+            .db_transaction_read(|app| async move {
+                let authentication = app.authenticate(auth_data).await?;
+                authentication.check_can_write()?;
+                app.database.get_quota_scope(authentication.uid()?).await
+            })
+            .await?;
+        self.sizes_backfill_users(&uids).await
+    }
+
     /// Publish a crate
     pub async fn publish_crate_version(&self, auth_data: &AuthData, content: &[u8]) -> Result<CrateUploadResult, ApiError> {
         // deserialize payload
         let package = CrateUploadData::new(content)?;
+        set_request_package(&package.metadata.name);
         let index_data = package.build_index_data();
+        // only scan and look upstream for authorized publishers
+        self.check_publisher(auth_data).await?;
+        let secrets_warnings = self.check_crate_secrets(&package).await?;
+        let provenance = VersionProvenance::from_crate(&package.content, &package.metadata.name, &package.metadata.vers)?;
+        let configuration = self.configuration.load_full();
+        let confusion_check = configuration.proxy.confusion_check;
+        let is_claimed_upstream = if confusion_check == ConfusionCheck::Off {
+            false
+        } else {
+            match self.service_proxy.get_crates_io_versions(&package.metadata.name).await {
+                Ok(versions) => versions.is_some(),
+                Err(error) if confusion_check == ConfusionCheck::Reject => return Err(error),
+                Err(error) => {
+                    // the crate is deemed unknown upstream, it is checked again by the periodic job
+                    warn!("failed to look up {} on crates.io: {error}", package.metadata.name);
+                    false
+                }
+            }
+        };
+        let quarantine_until = {
+            let quarantine = &configuration.quarantine;
+            quarantine.is_enabled().then(|| {
+                Local::now().naive_local() + chrono::Duration::seconds(i64::try_from(quarantine.period).unwrap_or(i64::MAX))
+            })
+        };
 
         let (user, result, targets, capabilities) = {
             let package = &package;
+            let index_data = &index_data;
+            let secrets_warnings = &secrets_warnings;
+            let provenance = &provenance;
             self.db_transaction_write("publish_crate_version", |app| async move {
                 let authentication = app.authenticate(auth_data).await?;
                 authentication.check_can_write()?;
                 let user = app.database.get_user_profile(authentication.uid()?).await?;
+                let name = &package.metadata.name;
+                app.database
+                    .check_publish_quota(
+                        user.id,
+                        name,
+                        i64::try_from(package.content.len())?,
+                        &configuration.quota,
+                    )
+                    .await?;
+                if is_claimed_upstream
+                    && confusion_check == ConfusionCheck::Reject
+                    && !app.database.get_crate_exists(name).await?
+                {
+                    return Err(specialize(
+                        error_forbidden(),
+                        format!("crate {name} exists on crates.io, publishing it here would expose its users to dependency confusion"),
+                    ));
+                }
                 // publish
-                let result = app.database.publish_crate_version(user.id, package).await?;
-                let mut targets = app.database.get_crate_targets(&package.metadata.name).await?;
-                if targets.is_empty() {
-                    targets.push(CrateInfoTarget {
-                        target: self.configuration.self_toolchain_host.clone(),
-                        docs_use_native: true,
-                    });
+                let mut result = app.database.publish_crate_version(user.id, package).await?;
+                result.warnings.other.extend(secrets_warnings.iter().cloned());
+                if !provenance.is_empty() {
+                    app.database
+                        .set_crate_version_provenance(name, &package.metadata.vers, provenance)
+                        .await?;
+                }
+                if is_claimed_upstream {
+                    app.database.set_crate_claimed_upstream(name, true).await?;
+                    result.warnings.other.push(format!(
+                        "crate {name} exists on crates.io, its users must always specify the registry to avoid dependency confusion"
+                    ));
+                }
+                if let Some(until) = quarantine_until {
+                    let version = &package.metadata.vers;
+                    app.database.set_crate_version_quarantine(name, version, Some(until)).await?;
+                    result.warnings.other.push(format!(
+                        "version {version} is quarantined until {until}, only the owners of the crate can download it meanwhile"
+                    ));
                 }
+                app.database.set_crate_version_dependencies(index_data).await?;
+                let targets = app.get_crate_targets_or_default(&package.metadata.name).await?;
                 for info in &targets {
                     app.database
                         .set_crate_documentation(&package.metadata.name, &package.metadata.vers, &info.target, false, false)
@@ -461,8 +1865,86 @@ impl Application {
             .await
         }?;
 
+        let search_document = CrateSearchDocument::from_metadata(&package.metadata, &extract_readme(&package.content)?);
         self.service_storage.store_crate(&package.metadata, package.content).await?;
-        self.service_index.publish_crate_version(&index_data).await?;
+        if quarantine_until.is_none() {
+            // quarantined versions are added to the index when released
+            self.service_index.publish_crate_version(&index_data).await?;
+            self.search_index_crate(search_document).await;
+        }
+        self.crate_info_cache.invalidate(&package.metadata.name);
+        if quarantine_until.is_none() {
+            // for quarantined versions, this is deferred until they are released
+            self.queue_crate_version_docs(&index_data, targets, &capabilities, &user)
+                .await?;
+            self.notify_crate_version_published(&index_data, &package.metadata, &user);
+        }
+        Ok(result)
+    }
+
+    /// Scans the content of a crate version to be published for secrets, depending on the configuration
+    ///
+    /// Fails when secrets are found and they must be rejected, otherwise gets the warnings about them.
+    async fn check_crate_secrets(&self, package: &CrateUploadData) -> Result<Vec<String>, ApiError> {
+        let configuration = self.configuration.load();
+        let config = &configuration.secrets_scan;
+        if config.mode == SecretsScan::Off {
+            return Ok(Vec::new());
+        }
+        let patterns = compile_secret_patterns(&config.patterns)?;
+        let findings = tokio::task::spawn_blocking({
+            let content = package.content.clone();
+            let name = package.metadata.name.clone();
+            let version = package.metadata.vers.clone();
+            move || scan_crate_secrets(&content, &name, &version, &patterns)
+        })
+        .await??;
+        if findings.is_empty() {
+            return Ok(Vec::new());
+        }
+        let findings = findings.iter().map(ToString::to_string).collect::<Vec<_>>();
+        if config.mode == SecretsScan::Reject {
+            return Err(specialize(
+                error_invalid_request(),
+                format!("possible secrets found in the crate: {}", findings.join(", ")),
+            ));
+        }
+        Ok(findings
+            .into_iter()
+            .map(|finding| format!("possible secret: {finding}"))
+            .collect())
+    }
+
+    /// Notifies the chat services and the webhooks of a newly published crate version
+    fn notify_crate_version_published(&self, index_data: &IndexCrateMetadata, metadata: &CrateMetadata, user: &RegistryUser) {
+        self.chat_notify_spawn(ChatMessage {
+            title: format!("{} {} was published", index_data.name, index_data.vers),
+            lines: vec![format!("Published by {} ({})", user.name, user.login)]
+                .into_iter()
+                .chain(metadata.description.clone())
+                .collect(),
+            link: format!(
+                "{}/crates/{}/{}",
+                self.configuration.load().web_public_uri,
+                index_data.name,
+                index_data.vers
+            ),
+        });
+        self.webhooks_dispatch(WebhookPayload::new(
+            WebhookEvent::Publish,
+            &index_data.name,
+            Some(&index_data.vers),
+        ));
+    }
+
+    /// Queues the generation of the documentation for a newly published crate version
+    async fn queue_crate_version_docs(
+        &self,
+        index_data: &IndexCrateMetadata,
+        targets: Vec<CrateInfoTarget>,
+        capabilities: &[String],
+        user: &RegistryUser,
+    ) -> Result<(), ApiError> {
         for info in targets {
             self.service_docs_generator
                 .queue(
@@ -471,20 +1953,27 @@ impl Application {
                         version: index_data.vers.clone(),
                         target: info.target,
                         use_native: info.docs_use_native,
-                        capabilities: capabilities.clone(),
+                        capabilities: capabilities.to_vec(),
                     },
                     &DocGenTrigger::Upload { by: user.clone() },
                 )
                 .await?;
         }
-        Ok(result)
+        Ok(())
     }
 
     /// Gets all the data about a crate
+    ///
+    /// The data is assembled once and then kept in memory until the crate is changed.
     pub async fn get_crate_info(&self, auth_data: &AuthData, package: &str) -> Result<CrateInfo, ApiError> {
+        self.check_can_read_crate(auth_data, package).await?;
+        let _authentication = self.authenticate(auth_data).await?;
+        if let Some(info) = self.crate_info_cache.get(package) {
+            return Ok(info);
+        }
+        let generation = self.crate_info_cache.generation(package);
         let info = self
             .db_transaction_read(|app| async move {
-                let _authentication = app.authenticate(auth_data).await?;
                 app.database
                     .get_crate_info(package, self.service_index.get_crate_data(package).await?)
                     .await
@@ -492,17 +1981,70 @@ impl Application {
             .await?;
         let metadata = self
             .service_storage
-            .download_crate_metadata(package, &info.versions.last().unwrap().index.vers)
+            .download_crate_metadata(package, &info.versions.last().ok_or_else(error_not_found)?.index.vers)
+            .await?;
+        let info = CrateInfo { metadata, ..info };
+        self.crate_info_cache.insert(package, info.clone(), generation);
+        Ok(info)
+    }
+
+    /// Checks that a user can read a crate, the restricted crates being reported as not found to the other users
+    ///
+    /// Returns whether the crate is restricted, in which case its content must not be stored by shared caches.
+    pub async fn check_can_read_crate(&self, auth_data: &AuthData, package: &str) -> Result<bool, ApiError> {
+        self.db_transaction_read(|app| async move {
+            if !app.database.get_crate_is_restricted(package).await? {
+                return Ok(false);
+            }
+            let authentication = app.authenticate(auth_data).await?;
+            app.check_can_read_crate(&authentication, package).await?;
+            Ok(true)
+        })
+        .await
+    }
+
+    /// Gets the raw content of the index file for a crate, as seen by cargo
+    pub async fn get_crate_index_file(&self, auth_data: &AuthData, package: &str) -> Result<Vec<u8>, ApiError> {
+        validate_crate_name(package)?;
+        let _authentication = self.authenticate(auth_data).await?;
+        self.service_index.get_crate_index_file(package).await
+    }
+
+    /// Gets the versions of a crate in the format of the crates.io web API
+    pub async fn get_crates_io_versions(&self, auth_data: &AuthData, package: &str) -> Result<CratesIoVersions, ApiError> {
+        let versions = self
+            .db_transaction_read(|app| async move {
+                let _authentication = app.authenticate(auth_data).await?;
+                app.database
+                    .get_crates_io_versions(package, self.service_index.get_crate_data(package).await?)
+                    .await
+            })
             .await?;
-        Ok(CrateInfo { metadata, ..info })
+        Ok(CratesIoVersions {
+            meta: CratesIoVersionsMeta { total: versions.len() },
+            versions,
+        })
+    }
+
+    /// Gets a crate version in the format of the crates.io web API
+    pub async fn get_crates_io_version(
+        &self,
+        auth_data: &AuthData,
+        package: &str,
+        version: &str,
+    ) -> Result<CratesIoVersionResult, ApiError> {
+        let versions = self.get_crates_io_versions(auth_data, package).await?.versions;
+        let version = versions.into_iter().find(|v| v.num == version).ok_or_else(error_not_found)?;
+        Ok(CratesIoVersionResult { version })
     }
 
     /// Downloads the last README for a crate
     pub async fn get_crate_last_readme(&self, auth_data: &AuthData, package: &str) -> Result<Vec<u8>, ApiError> {
         let version = self
             .db_transaction_read(|app| async move {
-                let _authentication = app.authenticate(auth_data).await?;
+                let authentication = app.authenticate(auth_data).await?;
                 let version = app.database.get_crate_last_version(package).await?;
+                app.check_can_read_crate_version(&authentication, package, &version).await?;
                 Ok::<_, ApiError>(version)
             })
             .await?;
@@ -512,90 +2054,765 @@ impl Application {
 
     /// Downloads the README for a crate
     pub async fn get_crate_readme(&self, auth_data: &AuthData, package: &str, version: &str) -> Result<Vec<u8>, ApiError> {
-        let _authentication = self.authenticate(auth_data).await?;
+        self.check_can_read_crate_version(auth_data, package, version).await?;
         let readme = self.service_storage.download_crate_readme(package, version).await?;
         Ok(readme)
     }
 
-    /// Downloads the content for a crate
-    pub async fn get_crate_content(&self, auth_data: &AuthData, package: &str, version: &str) -> Result<Vec<u8>, ApiError> {
-        self.db_transaction_read(|app| async move {
-            let _authentication = app.authenticate(auth_data).await?;
-            app.database.check_crate_exists(package, version).await?;
-            Ok::<_, ApiError>(())
-        })
-        .await?;
-        let content = self.service_storage.download_crate(package, version).await?;
-        self.app_events_sender
-            .send(AppEvent::CrateDownload(CrateVersion {
-                package: package.to_string(),
-                version: version.to_string(),
-            }))
+    /// Lists the files in the `.crate` archive of a crate version
+    pub async fn get_crate_files(
+        &self,
+        auth_data: &AuthData,
+        package: &str,
+        version: &str,
+    ) -> Result<Vec<CrateFile>, ApiError> {
+        let content = self.get_crate_archive(auth_data, package, version).await?;
+        list_crate_files(&content, package, version)
+    }
+
+    /// Gets the content of a single file in the `.crate` archive of a crate version
+    pub async fn get_crate_file(
+        &self,
+        auth_data: &AuthData,
+        package: &str,
+        version: &str,
+        path: &str,
+    ) -> Result<Vec<u8>, ApiError> {
+        let content = self.get_crate_archive(auth_data, package, version).await?;
+        extract_crate_file(&content, package, version, path)?
+            .ok_or_else(|| specialize(error_not_found(), format!("file {path} is not in {package} {version}")))
+    }
+
+    /// Gets the differences between the files of two versions of a crate
+    pub async fn get_crate_diff(
+        &self,
+        auth_data: &AuthData,
+        package: &str,
+        from: &str,
+        to: &str,
+    ) -> Result<CrateDiff, ApiError> {
+        let from_content = self.get_crate_archive(auth_data, package, from).await?;
+        let to_content = self.get_crate_archive(auth_data, package, to).await?;
+        diff_crates(package, (from, &from_content), (to, &to_content))
+    }
+
+    /// Gets the data for cargo-binstall about a crate version
+    pub async fn get_crate_binstall(
+        &self,
+        auth_data: &AuthData,
+        package: &str,
+        version: &str,
+    ) -> Result<BinstallInfo, ApiError> {
+        let binaries = self
+            .db_transaction_read(|app| async move {
+                let authentication = app.authenticate(auth_data).await?;
+                app.database.check_crate_exists(package, version).await?;
+                app.check_can_read_crate_version(&authentication, package, version).await?;
+                app.database.get_crate_binaries(package, version).await
+            })
             .await?;
-        Ok(content)
+        let content = self.service_storage.download_crate(package, version).await?;
+        let metadata = extract_binstall_metadata(&content, package, version)?;
+        Ok(BinstallInfo { metadata, binaries })
     }
 
-    /// Yank a crate version
-    pub async fn yank_crate_version(
+    /// Uploads a prebuilt binary artifact for a crate version and a target, replacing the previous one
+    pub async fn upload_crate_binary(
         &self,
         auth_data: &AuthData,
         package: &str,
         version: &str,
-    ) -> Result<YesNoResult, ApiError> {
-        self.db_transaction_write("yank_crate_version", |app| async move {
+        target: &str,
+        content: Vec<u8>,
+    ) -> Result<CrateBinary, ApiError> {
+        validate_binary_target(target)?;
+        let size = i64::try_from(content.len()).unwrap_or(i64::MAX);
+        let checksum = sha256(&content);
+        let checksum = &checksum;
+        self.db_transaction_read(|app| async move {
+            // do not replace the stored artifact when the upload cannot be recorded
+            app.check_not_in_maintenance()?;
             let authentication = app.authenticate(auth_data).await?;
             app.check_can_manage_crate(&authentication, package).await?;
-            app.database.yank_crate_version(package, version).await
+            app.database.check_crate_exists(package, version).await
+        })
+        .await?;
+        // store the artifact before recording it, outside of the write transaction
+        self.service_storage
+            .store_crate_binary(package, version, target, content)
+            .await?;
+        // check the rights again in the write transaction, rejecting the changes during maintenance
+        self.db_transaction_write("upload_crate_binary", |app| async move {
+            let authentication = app.authenticate(auth_data).await?;
+            let principal_uid = app.check_can_manage_crate(&authentication, package).await?;
+            app.database
+                .set_crate_binary(principal_uid, package, version, target, size, checksum)
+                .await
         })
         .await
     }
 
-    /// Unyank a crate version
-    pub async fn unyank_crate_version(
+    /// Downloads a prebuilt binary artifact for a crate version and a target
+    pub async fn get_crate_binary(
         &self,
         auth_data: &AuthData,
         package: &str,
         version: &str,
-    ) -> Result<YesNoResult, ApiError> {
-        self.db_transaction_write("unyank_crate_version", |app| async move {
+        target: &str,
+    ) -> Result<Vec<u8>, ApiError> {
+        self.db_transaction_read(|app| async move {
             let authentication = app.authenticate(auth_data).await?;
-            app.check_can_manage_crate(&authentication, package).await?;
-            app.database.unyank_crate_version(package, version).await
+            app.database.check_crate_binary_exists(package, version, target).await?;
+            app.check_can_read_crate_version(&authentication, package, version).await?;
+            Ok::<_, ApiError>(())
         })
-        .await
+        .await?;
+        self.service_storage.download_crate_binary(package, version, target).await
     }
 
-    /// Gets the packages that need documentation generation
-    pub async fn get_undocumented_crates(&self, auth_data: &AuthData) -> Result<Vec<DocGenJobSpec>, ApiError> {
+    /// Checks that a user can read a version of a crate in this registry, quarantined or restricted
+    async fn check_can_read_crate_version(&self, auth_data: &AuthData, package: &str, version: &str) -> Result<(), ApiError> {
         self.db_transaction_read(|app| async move {
-            let _authentication = app.authenticate(auth_data).await?;
-            app.database
-                .get_undocumented_crates(&self.configuration.self_toolchain_host)
-                .await
+            let authentication = app.authenticate(auth_data).await?;
+            app.database.check_crate_exists(package, version).await?;
+            app.check_can_read_crate_version(&authentication, package, version).await?;
+            Ok(())
         })
         .await
     }
 
+    /// Gets the `.crate` archive of a crate version in this registry, without counting a download
+    async fn get_crate_archive(&self, auth_data: &AuthData, package: &str, version: &str) -> Result<Vec<u8>, ApiError> {
+        self.check_can_read_crate_version(auth_data, package, version).await?;
+        self.service_storage.download_crate(package, version).await
+    }
+
+    /// Downloads the content for a crate, or only a range of bytes in it
+    pub async fn get_crate_content(
+        &self,
+        auth_data: &AuthData,
+        package: &str,
+        version: &str,
+        range: Option<ByteRange>,
+    ) -> Result<CrateContent, ApiError> {
+        // whether the version is only available to some users, when it is a local one
+        let restricted = self
+            .db_transaction_read(|app| async move {
+                let authentication = app.authenticate(auth_data).await?;
+                match app.database.check_crate_exists(package, version).await {
+                    Ok(()) => app
+                        .check_can_read_crate_version(&authentication, package, version)
+                        .await
+                        .map(Some),
+                    Err(error) if error.http == 404 => Ok(None),
+                    Err(error) => Err(error),
+                }
+            })
+            .await?;
+        let Some(restricted) = restricted else {
+            // try the proxied registry
+            let content = self
+                .service_proxy
+                .download_crate(None, package, version)
+                .await?
+                .ok_or_else(error_not_found)?;
+            return Ok(CrateContent::from_content(content, range));
+        };
+        let mut content = match range {
+            Some(range) => self.service_storage.download_crate_range(package, version, range).await?,
+            None => CrateContent::from_content(self.service_storage.download_crate(package, version).await?, None),
+        };
+        content.restricted = restricted;
+        if content.part != ContentPart::Unsatisfiable && range.is_none_or(ByteRange::is_from_start) {
+            // do not count the resumption of a download, or reading its end, as another download
+            self.downloads_record(package, version).await;
+        }
+        Ok(content)
+    }
+
+    /// Downloads the content for a crate of a proxied registry, `None` designating crates.io, or only a range of bytes in it
+    pub async fn get_proxied_crate_content(
+        &self,
+        auth_data: &AuthData,
+        registry: Option<&str>,
+        package: &str,
+        version: &str,
+        range: Option<ByteRange>,
+    ) -> Result<CrateContent, ApiError> {
+        let _authentication = self.authenticate(auth_data).await?;
+        let content = self
+            .service_proxy
+            .download_crate(registry, package, version)
+            .await?
+            .ok_or_else(error_not_found)?;
+        Ok(CrateContent::from_content(content, range))
+    }
+
+    /// Gets the metadata for the latest version of many crates at once,
+    /// given by their names or as the packages from this registry in a `Cargo.lock` file
+    pub async fn get_crates_metadata(
+        &self,
+        auth_data: &AuthData,
+        query: &BulkMetadataQuery,
+    ) -> Result<BulkMetadataResult, ApiError> {
+        let hidden = self
+            .db_transaction_read(|app| async move {
+                let authentication = app.authenticate(auth_data).await?;
+                app.get_hidden_crates_lowercase(&authentication).await
+            })
+            .await?;
+        let self_external = self.configuration.load().get_self_as_external();
+        let self_index = normalize_source(&self_external.index);
+        let mut requested: Vec<(&str, Option<&str>)> = query.names.iter().map(|name| (name.as_str(), None)).collect();
+        let locked = query
+            .lock_file
+            .as_deref()
+            .map(LockedPackage::parse_lock_file)
+            .unwrap_or_default();
+        requested.extend(
+            locked
+                .iter()
+                .filter(|package| normalize_source(package.source.as_deref().unwrap_or_default()) == self_index)
+                .map(|package| (package.name.as_str(), Some(package.version.as_str()))),
+        );
+        if requested.len() > BULK_METADATA_MAX_CRATES {
+            return Err(specialize(
+                error_invalid_request(),
+                format!("at most {BULK_METADATA_MAX_CRATES} crates can be queried at once"),
+            ));
+        }
+        let mut missing = Vec::new();
+        let mut fetched = Vec::new();
+        for (rank, (name, locked_version)) in requested.into_iter().enumerate() {
+            if hidden.contains(&name.to_ascii_lowercase()) {
+                // the restricted crates are reported as missing to the users that cannot read them
+                missing.push((rank, name.to_string()));
+                continue;
+            }
+            let service_index = self.service_index.clone();
+            let service_storage = self.service_storage.clone();
+            let name = name.to_string();
+            let locked_version = locked_version.map(str::to_string);
+            fetched.push(Box::pin(async move {
+                let result =
+                    get_crate_latest_metadata(&*service_index, &*service_storage, &name, locked_version.as_deref()).await;
+                (rank, name, result)
+            }));
+        }
+        let mut results = n_at_a_time(fetched, BULK_METADATA_CONCURRENCY, |(_, _, result)| result.is_err()).await;
+        // keep the order of the request
+        results.sort_unstable_by_key(|(rank, _, _)| *rank);
+        let mut crates = Vec::new();
+        for (rank, name, result) in results {
+            match result? {
+                Some(metadata) => crates.push(metadata),
+                None => missing.push((rank, name)),
+            }
+        }
+        missing.sort_unstable_by_key(|(rank, _)| *rank);
+        let missing = missing.into_iter().map(|(_, name)| name).collect();
+        Ok(BulkMetadataResult { crates, missing })
+    }
+
+    /// Exports the whole registry in an archive, for administrators
+    ///
+    /// The archive contains a snapshot of the database, the index, a manifest of all the crate versions
+    /// and optionally the `.crate` files with their metadata.
+    /// It is written to a temporary file that is removed once opened, so that it disappears when the returned file is closed.
+    pub async fn export_registry(&self, auth_data: &AuthData, include_crates: bool) -> Result<tokio::fs::File, ApiError> {
+        let versions = self
+            .db_transaction_read(|app| async move {
+                let authentication = app.authenticate(auth_data).await?;
+                app.check_can_admin_registry(&authentication).await?;
+                app.database.get_all_crate_versions().await
+            })
+            .await?;
+        let configuration = self.configuration.load_full();
+        let mut checksums = HashMap::<String, HashMap<String, String>>::new();
+        let mut crates = Vec::with_capacity(versions.len());
+        for CrateVersion { package, version } in versions {
+            if !checksums.contains_key(&package) {
+                let data = self.service_index.get_crate_data(&package).await.unwrap_or_default();
+                let data = data.into_iter().map(|metadata| (metadata.vers, metadata.cksum)).collect();
+                checksums.insert(package.clone(), data);
+            }
+            let checksum = checksums.get(&package).and_then(|data| data.get(&version)).cloned();
+            crates.push(ExportedCrate {
+                package,
+                version,
+                checksum,
+            });
+        }
+        let manifest = ExportManifest {
+            format: EXPORT_FORMAT_VERSION,
+            registry: configuration.web_public_uri.clone(),
+            exported_on: Local::now().naive_local(),
+            include_crates,
+            crates,
+        };
+
+        let id = uuid::Uuid::new_v4();
+        let snapshot = format!("{}/export-{id}.db", configuration.data_dir);
+        let index_snapshot = format!("{}/export-{id}-index", configuration.data_dir);
+        let archive = format!("{}/export-{id}.tar.gz", configuration.data_dir);
+        let result = match self.service_db_pool.snapshot_into(&snapshot).await {
+            Ok(()) => self.service_index.snapshot_into(std::path::Path::new(&index_snapshot)).await,
+            Err(e) => Err(e),
+        };
+        let result = match result {
+            Ok(()) => {
+                let service_storage = self.service_storage.clone();
+                let runtime = tokio::runtime::Handle::current();
+                let (snapshot, index_snapshot, archive) = (snapshot.clone(), index_snapshot.clone(), archive.clone());
+                tokio::task::spawn_blocking(move || {
+                    write_export_archive(
+                        &archive,
+                        &manifest,
+                        &snapshot,
+                        &index_snapshot,
+                        service_storage.as_ref(),
+                        &runtime,
+                    )
+                })
+                .await
+                .map_err(ApiError::from)
+                .and_then(|result| result)
+            }
+            Err(e) => Err(e),
+        };
+        let _ = tokio::fs::remove_file(&snapshot).await;
+        let _ = tokio::fs::remove_dir_all(&index_snapshot).await;
+        let file = match result {
+            Ok(()) => tokio::fs::File::open(&archive).await.map_err(ApiError::from),
+            Err(e) => Err(e),
+        };
+        let _ = tokio::fs::remove_file(&archive).await;
+        file
+    }
+
+    /// Audits the packages locked in a `Cargo.lock` file against the registry
+    ///
+    /// The packages from this registry are checked for yanked versions, deprecated crates, missing versions
+    /// and the advisories found against them, the restricted crates the user cannot read are reported as missing.
+    /// The packages from the external registries are checked for yanked and missing versions.
+    /// The packages from crates.io are checked against the known advisories.
+    pub async fn audit_lock_file(&self, auth_data: &AuthData, lock_file: &str) -> Result<LockFileAudit, ApiError> {
+        let packages = LockedPackage::parse_lock_file(lock_file)
+            .into_iter()
+            .filter(|package| package.source.as_ref().is_some_and(|source| !source.starts_with("git+")))
+            .collect::<Vec<_>>();
+        let configuration = self.configuration.load_full();
+        let self_external = configuration.get_self_as_external();
+        let self_index = normalize_source(&self_external.index);
+        let packages = &packages;
+        let mut entries = self
+            .db_transaction_read(|app| async move {
+                let authentication = app.authenticate(auth_data).await?;
+                let hidden = app.get_hidden_crates_lowercase(&authentication).await?;
+                let mut entries = Vec::new();
+                for package in packages {
+                    let mut entry = LockFileAuditEntry {
+                        name: package.name.clone(),
+                        version: package.version.clone(),
+                        source: package.source.clone(),
+                        yanked: false,
+                        deprecated: false,
+                        missing: false,
+                        advisories: Vec::new(),
+                    };
+                    if normalize_source(package.source.as_deref().unwrap_or_default()) == self_index {
+                        if hidden.contains(&package.name.to_ascii_lowercase()) {
+                            // the restricted crates are reported as missing to the users that cannot read them
+                            entry.missing = true;
+                            entries.push(entry);
+                            continue;
+                        }
+                        let versions = match self.service_index.get_crate_data(&package.name).await {
+                            Ok(versions) => versions,
+                            Err(error) if error.http == 404 => Vec::new(),
+                            Err(error) => return Err(error),
+                        };
+                        match versions.iter().find(|version| version.vers == package.version) {
+                            Some(version) => entry.yanked = version.yanked,
+                            None => entry.missing = true,
+                        }
+                        entry.deprecated = app.database.get_crate_deprecation(&package.name).await?.unwrap_or(false);
+                        entry.advisories = app
+                            .database
+                            .get_crate_advisories(&package.name)
+                            .await?
+                            .into_iter()
+                            .filter(|advisory| advisory.is_direct && advisory.version == package.version)
+                            .map(SimpleAdvisory::from)
+                            .collect();
+                    }
+                    entries.push(entry);
+                }
+                Ok::<_, ApiError>(entries)
+            })
+            .await?;
+        for entry in &mut entries {
+            let source = normalize_source(entry.source.as_deref().unwrap_or_default());
+            if source == normalize_source(CRATES_IO_SOURCE_GIT) || source == normalize_source(CRATES_IO_SOURCE_SPARSE) {
+                if let Ok(version) = entry.version.parse::<Version>() {
+                    entry.advisories = self.service_rustsec.check_crate(&entry.name, &version).await?;
+                }
+            } else if let Some(registry) = configuration
+                .external_registries
+                .iter()
+                .find(|registry| normalize_source(&registry.index) == source)
+            {
+                let versions = match self
+                    .service_deps_checker
+                    .get_crate_versions(Some(&registry.index), &entry.name)
+                    .await
+                {
+                    Ok(versions) => versions,
+                    Err(error) if error.http == 404 => Vec::new(),
+                    Err(error) => return Err(error),
+                };
+                match versions.iter().find(|version| version.vers == entry.version) {
+                    Some(version) => entry.yanked = version.yanked,
+                    None => entry.missing = true,
+                }
+            }
+        }
+        let checked = entries.len();
+        entries.retain(LockFileAuditEntry::has_issues);
+        Ok(LockFileAudit {
+            passed: entries.is_empty(),
+            checked,
+            entries,
+        })
+    }
+
+    /// Builds a vendor archive with all the crates locked in a `Cargo.lock` file that are available in this registry
+    /// This includes the local crates and those cached from the proxied registries
+    pub async fn export_vendor(&self, auth_data: &AuthData, lock_file: &str) -> Result<Vec<u8>, ApiError> {
+        let packages = LockedPackage::parse_lock_file(lock_file)
+            .into_iter()
+            .filter(|package| package.source.as_ref().is_some_and(|source| !source.starts_with("git+")))
+            .collect::<Vec<_>>();
+        let packages = &packages;
+        let local = self
+            .db_transaction_read(|app| async move {
+                let authentication = app.authenticate(auth_data).await?;
+                let hidden = app.get_hidden_crates_lowercase(&authentication).await?;
+                let mut local = Vec::new();
+                for package in packages {
+                    if hidden.contains(&package.name.to_ascii_lowercase()) {
+                        // the restricted crates are not available to the users that cannot read them
+                        local.push(false);
+                        continue;
+                    }
+                    let is_local = match app.database.check_crate_exists(&package.name, &package.version).await {
+                        Ok(()) => match app
+                            .check_can_read_crate_version(&authentication, &package.name, &package.version)
+                            .await
+                        {
+                            Ok(_) => true,
+                            // the quarantined versions are only available to the owners
+                            Err(error) if error.http == 403 || error.http == 404 => false,
+                            Err(error) => return Err(error),
+                        },
+                        Err(error) if error.http == 404 => false,
+                        Err(error) => return Err(error),
+                    };
+                    local.push(is_local);
+                }
+                Ok(local)
+            })
+            .await?;
+        let self_external = self.configuration.load().get_self_as_external();
+        let self_index = normalize_source(&self_external.index);
+        let mut crates = Vec::new();
+        let mut missing = Vec::new();
+        for (package, is_local) in packages.iter().zip(local) {
+            let source = normalize_source(package.source.as_deref().unwrap_or_default());
+            let content = if source == self_index && is_local {
+                let content = self.service_storage.download_crate(&package.name, &package.version).await?;
+                self.downloads_record(&package.name, &package.version).await;
+                Some(content)
+            } else if source == normalize_source(CRATES_IO_SOURCE_GIT) || source == normalize_source(CRATES_IO_SOURCE_SPARSE) {
+                self.service_proxy
+                    .download_crate(None, &package.name, &package.version)
+                    .await?
+            } else if let Some(registry) = self
+                .configuration
+                .load()
+                .external_registries
+                .iter()
+                .find(|registry| normalize_source(&registry.index) == source)
+            {
+                self.service_proxy
+                    .download_crate(Some(&registry.name), &package.name, &package.version)
+                    .await?
+            } else {
+                None
+            };
+            let Some(content) = content else {
+                missing.push(VendorMissing {
+                    name: package.name.clone(),
+                    version: package.version.clone(),
+                    reason: String::from("not available in this registry"),
+                });
+                continue;
+            };
+            if package
+                .checksum
+                .as_ref()
+                .is_some_and(|checksum| checksum != &sha256(&content))
+            {
+                missing.push(VendorMissing {
+                    name: package.name.clone(),
+                    version: package.version.clone(),
+                    reason: String::from("the checksum does not match the lock file"),
+                });
+                continue;
+            }
+            crates.push(VendoredCrate {
+                name: package.name.clone(),
+                version: package.version.clone(),
+                content,
+            });
+        }
+        tokio::task::spawn_blocking(move || build_vendor_archive(&crates, &missing)).await?
+    }
+
+    /// Yank a crate version
+    pub async fn yank_crate_version(
+        &self,
+        auth_data: &AuthData,
+        package: &str,
+        version: &str,
+        reason: Option<&str>,
+    ) -> Result<YesNoResult, ApiError> {
+        self.db_transaction_write("yank_crate_version", |app| async move {
+            let authentication = app.authenticate(auth_data).await?;
+            app.check_can_manage_crate(&authentication, package).await?;
+            app.database.yank_crate_version(package, version, reason).await
+        })
+        .await
+        .inspect(|_| {
+            self.crate_info_cache.invalidate(package);
+            self.notify_yank_spawn(package, version, reason);
+            self.webhooks_dispatch(WebhookPayload::new(WebhookEvent::Yank, package, Some(version)));
+        })
+    }
+
+    /// Yanks all the versions of the crates matching a pattern and a version requirement, for administrators
+    pub async fn bulk_yank(&self, auth_data: &AuthData, query: &BulkYankQuery) -> Result<BulkYankResult, ApiError> {
+        if query.pattern.trim().is_empty() {
+            return Err(specialize(error_invalid_request(), String::from("a pattern is required")));
+        }
+        let requirement = query
+            .versions
+            .as_deref()
+            .map(VersionReq::parse)
+            .transpose()
+            .map_err(|e| specialize(error_invalid_request(), format!("invalid version requirement: {e}")))?;
+        let requirement = requirement.as_ref();
+        if query.dry_run {
+            // only lists the matching versions, without changing anything
+            return self
+                .db_transaction_read(|app| async move {
+                    let authentication = app.authenticate(auth_data).await?;
+                    app.check_can_admin_registry(&authentication).await?;
+                    let yanked = app.get_bulk_yank_versions(query, requirement).await?;
+                    Ok(BulkYankResult { dry_run: true, yanked })
+                })
+                .await;
+        }
+        self.db_transaction_write("bulk_yank", |app| async move {
+            let authentication = app.authenticate(auth_data).await?;
+            let principal_uid = app.check_can_admin_registry(&authentication).await?;
+            let yanked = app.get_bulk_yank_versions(query, requirement).await?;
+            for crate_version in &yanked {
+                app.database
+                    .yank_crate_version(&crate_version.package, &crate_version.version, query.reason.as_deref())
+                    .await?;
+            }
+            let details = format!(
+                "pattern {}, versions {}: {} yanked",
+                query.pattern,
+                query.versions.as_deref().unwrap_or("*"),
+                yanked.len()
+            );
+            app.database
+                .add_audit_entry(Some(principal_uid), authentication.impersonator, "bulk_yank", &details)
+                .await?;
+            Ok(BulkYankResult { dry_run: false, yanked })
+        })
+        .await
+        .inspect(|result| self.bulk_yank_notify(result, query.reason.as_deref()))
+    }
+
+    /// Notifies the yanks of versions by a bulk yank, once they are committed
+    fn bulk_yank_notify(&self, result: &BulkYankResult, reason: Option<&str>) {
+        if result.dry_run {
+            return;
+        }
+        for crate_version in &result.yanked {
+            self.crate_info_cache.invalidate(&crate_version.package);
+            self.notify_yank_spawn(&crate_version.package, &crate_version.version, reason);
+            self.webhooks_dispatch(WebhookPayload::new(
+                WebhookEvent::Yank,
+                &crate_version.package,
+                Some(&crate_version.version),
+            ));
+        }
+    }
+
+    /// Unyank a crate version
+    pub async fn unyank_crate_version(
+        &self,
+        auth_data: &AuthData,
+        package: &str,
+        version: &str,
+    ) -> Result<YesNoResult, ApiError> {
+        self.db_transaction_write("unyank_crate_version", |app| async move {
+            let authentication = app.authenticate(auth_data).await?;
+            app.check_can_manage_crate(&authentication, package).await?;
+            app.database.unyank_crate_version(package, version).await
+        })
+        .await
+        .inspect(|_| {
+            self.crate_info_cache.invalidate(package);
+            self.webhooks_dispatch(WebhookPayload::new(WebhookEvent::Unyank, package, Some(version)));
+        })
+    }
+
+    /// Gets the documentation coverage for a crate version, for its default target
+    /// When the version is not specified, the last version is used
+    pub async fn get_crate_docs_coverage(
+        &self,
+        auth_data: &AuthData,
+        package: &str,
+        version: Option<&str>,
+    ) -> Result<Option<DocsCoverage>, ApiError> {
+        self.db_transaction_read(|app| async move {
+            let _authentication = app.authenticate(auth_data).await?;
+            let version = match version {
+                Some(version) => version.to_string(),
+                None => app.database.get_crate_last_version(package).await?,
+            };
+            let targets = app.database.get_crate_targets(package).await?;
+            let configuration = self.configuration.load();
+            let target = targets
+                .first()
+                .map_or(configuration.self_toolchain_host.as_str(), |info| info.target.as_str());
+            app.database.get_crate_documentation_coverage(package, &version, target).await
+        })
+        .await
+    }
+
+    /// Gets a page of the packages that need documentation generation, optionally only those whose name match a filter
+    pub async fn get_undocumented_crates(
+        &self,
+        auth_data: &AuthData,
+        filter: Option<&str>,
+        pagination: Pagination<CrateListSort>,
+    ) -> Result<Page<DocGenJobSpec>, ApiError> {
+        let mut jobs = self
+            .db_transaction_read(|app| async move {
+                let authentication = app.authenticate(auth_data).await?;
+                let hidden = app.get_hidden_crates(&authentication).await?;
+                let mut jobs = app
+                    .database
+                    .get_undocumented_crates(&self.configuration.load().self_toolchain_host)
+                    .await?;
+                jobs.retain(|job| !hidden.contains(&job.package));
+                Ok::<_, ApiError>(jobs)
+            })
+            .await?;
+        jobs.retain(|job| matches_filter(&job.package, filter));
+        pagination.sort.sort(&mut jobs, |job| (&job.package, &job.version));
+        Ok(pagination.paginate(jobs))
+    }
+
     /// Gets the documentation jobs
     pub async fn get_doc_gen_jobs(&self, auth_data: &AuthData) -> Result<Vec<DocGenJob>, ApiError> {
-        let _authentication = self.authenticate(auth_data).await?;
-        self.service_docs_generator.get_jobs().await
+        let hidden = self
+            .db_transaction_read(|app| async move {
+                let authentication = app.authenticate(auth_data).await?;
+                app.get_hidden_crates(&authentication).await
+            })
+            .await?;
+        let mut jobs = self.service_docs_generator.get_jobs().await?;
+        jobs.retain(|job| !hidden.contains(&job.package));
+        Ok(jobs)
     }
 
-    /// Gets the log for a documentation generation job
+    /// Gets the log for a documentation generation job, for the users that can read its crate
     pub async fn get_doc_gen_job_log(&self, auth_data: &AuthData, job_id: i64) -> Result<String, ApiError> {
-        let _authentication = self.authenticate(auth_data).await?;
+        self.check_can_read_doc_gen_job(auth_data, job_id).await?;
         self.service_docs_generator.get_job_log(job_id).await
     }
 
+    /// Checks that a user can read the crate of a documentation generation job, and then its log
+    async fn check_can_read_doc_gen_job(&self, auth_data: &AuthData, job_id: i64) -> Result<(), ApiError> {
+        self.db_transaction_read(|app| async move {
+            let authentication = app.authenticate(auth_data).await?;
+            let job = app.database.get_docgen_job(job_id).await?;
+            app.check_can_read_crate(&authentication, &job.package).await
+        })
+        .await
+    }
+
     /// Adds a listener to job updates
     pub async fn get_doc_gen_job_updates(&self, auth_data: &AuthData) -> Result<Receiver<DocGenEvent>, ApiError> {
-        let _authentication = self.authenticate(auth_data).await?;
+        self.db_transaction_read(|app| async move {
+            let authentication = app.authenticate(auth_data).await?;
+            app.check_can_admin_registry(&authentication).await
+        })
+        .await?;
         let (sender, receiver) = channel(16);
         self.service_docs_generator.add_listener(sender).await?;
         Ok(receiver)
     }
 
+    /// Adds a listener to the updates of a single job, for the users that can read its crate
+    /// The first event is the current state of the job, the stream ends when the job is finished
+    pub async fn get_doc_gen_job_updates_for(
+        &self,
+        auth_data: &AuthData,
+        job_id: i64,
+    ) -> Result<Receiver<DocGenEvent>, ApiError> {
+        // the updates carry the log of the job
+        self.check_can_read_doc_gen_job(auth_data, job_id).await?;
+        // register before getting the current state so that no transition is missed
+        let (sender, mut receiver) = channel(16);
+        self.service_docs_generator.add_listener(sender).await?;
+        let job = self
+            .db_transaction_read(|app| async move { app.database.get_docgen_job(job_id).await })
+            .await?;
+        let (job_sender, job_receiver) = channel(16);
+        let _handle = tokio::spawn(async move {
+            let current = DocGenEvent::Update(DocGenJobUpdate {
+                job_id,
+                state: job.state,
+                last_update: job.last_update,
+                log: None,
+                coverage: None,
+                size: None,
+            });
+            let is_final = current.is_final();
+            if job_sender.send(current).await.is_err() || is_final {
+                return;
+            }
+            while let Some(event) = receiver.recv().await {
+                if event.job_id() != job_id {
+                    continue;
+                }
+                let is_final = event.is_final();
+                if job_sender.send(event).await.is_err() || is_final {
+                    return;
+                }
+            }
+        });
+        Ok(job_receiver)
+    }
+
     /// Force the re-generation for the documentation of a package
     pub async fn regen_crate_version_doc(
         &self,
@@ -610,7 +2827,7 @@ impl Application {
                 let user = app.database.get_user_profile(principal_uid).await?;
                 let targets = app
                     .database
-                    .regen_crate_version_doc(package, version, &self.configuration.self_toolchain_host)
+                    .regen_crate_version_doc(package, version, &self.configuration.load().self_toolchain_host)
                     .await?;
                 let capabilities = app.database.get_crate_required_capabilities(package).await?;
                 Ok::<_, ApiError>((user, targets, capabilities))
@@ -637,167 +2854,1132 @@ impl Application {
         Ok(jobs)
     }
 
-    /// Gets all the packages that are outdated while also being the latest version
-    pub async fn get_crates_outdated_heads(&self, auth_data: &AuthData) -> Result<Vec<CrateVersion>, ApiError> {
+    /// Gets a page of the packages that are outdated while also being the latest version,
+    /// optionally only those whose name match a filter
+    pub async fn get_crates_outdated_heads(
+        &self,
+        auth_data: &AuthData,
+        filter: Option<&str>,
+        pagination: Pagination<CrateListSort>,
+    ) -> Result<Page<CrateVersion>, ApiError> {
+        let mut heads = self
+            .db_transaction_read(|app| async move {
+                let authentication = app.authenticate(auth_data).await?;
+                let hidden = app.get_hidden_crates(&authentication).await?;
+                let mut heads = app.database.get_crates_outdated_heads().await?;
+                heads.retain(|head| !hidden.contains(&head.package));
+                Ok::<_, ApiError>(heads)
+            })
+            .await?;
+        heads.retain(|head| matches_filter(&head.package, filter));
+        pagination.sort.sort(&mut heads, |head| (&head.package, &head.version));
+        Ok(pagination.paginate(heads))
+    }
+
+    /// Re-synchronizes the index with its remote origin, for administrators
+    pub async fn resync_index_remote(
+        &self,
+        auth_data: &AuthData,
+        query: &IndexResyncQuery,
+    ) -> Result<IndexResyncResult, ApiError> {
+        let principal_uid = self
+            .db_transaction_read(|app| async move {
+                let authentication = app.authenticate(auth_data).await?;
+                app.check_can_admin_registry(&authentication).await
+            })
+            .await?;
+        let result = self.service_index.resync_remote(query.mode, query.confirm).await?;
+        if result.performed {
+            self.crate_info_cache.invalidate_all();
+            let details = format!(
+                "{:?}: local {} -> {}, remote {}",
+                query.mode, result.local_head_before, result.local_head_after, result.remote_head
+            );
+            self.db_transaction_write("resync_index_remote", |app| async move {
+                app.database
+                    .add_audit_entry(Some(principal_uid), None, "resync_index_remote", &details)
+                    .await
+            })
+            .await?;
+        }
+        Ok(result)
+    }
+
+    /// Gets the storage used by each crate and each publishing user, for administrators
+    pub async fn get_storage_usage(&self, auth_data: &AuthData) -> Result<StorageUsageReport, ApiError> {
         self.db_transaction_read(|app| async move {
-            let _authentication = app.authenticate(auth_data).await?;
-            app.database.get_crates_outdated_heads().await
+            let authentication = app.authenticate(auth_data).await?;
+            app.check_can_admin_registry(&authentication).await?;
+            app.database.get_storage_usage().await
         })
         .await
     }
 
-    /// Gets the download statistics for a crate
-    pub async fn get_crate_dl_stats(&self, auth_data: &AuthData, package: &str) -> Result<DownloadStats, ApiError> {
+    /// Gets the publish activity of each user and the crates without active owner, for administrators
+    pub async fn get_publish_activity(&self, auth_data: &AuthData) -> Result<PublishActivityReport, ApiError> {
         self.db_transaction_read(|app| async move {
-            let _authentication = app.authenticate(auth_data).await?;
-            app.database.get_crate_dl_stats(package).await
+            let authentication = app.authenticate(auth_data).await?;
+            app.check_can_admin_registry(&authentication).await?;
+            app.database.get_publish_activity().await
         })
         .await
     }
 
-    /// Gets the list of owners for a package
-    pub async fn get_crate_owners(&self, auth_data: &AuthData, package: &str) -> Result<OwnersQueryResult, ApiError> {
+    /// Gets the requests served for each user and token, for administrators
+    pub async fn get_traffic_report(&self, auth_data: &AuthData) -> Result<TrafficReport, ApiError> {
+        self.db_transaction_read(|app| async move {
+            let authentication = app.authenticate(auth_data).await?;
+            app.check_can_admin_registry(&authentication).await?;
+            app.database.get_traffic_report().await
+        })
+        .await
+    }
+
+    /// Gets the webhooks visible to the user: all of them for administrators, those on the crates they own otherwise
+    pub async fn get_webhooks(&self, auth_data: &AuthData) -> Result<Vec<Webhook>, ApiError> {
+        self.db_transaction_read(|app| async move {
+            let authentication = app.authenticate(auth_data).await?;
+            if app.check_can_admin_registry(&authentication).await.is_ok() {
+                app.database.get_webhooks().await
+            } else {
+                app.database.get_webhooks_for_owner(authentication.uid()?).await
+            }
+        })
+        .await
+    }
+
+    /// Creates a webhook, on a single crate for its owners, or on the whole registry for administrators
+    pub async fn create_webhook(
+        &self,
+        auth_data: &AuthData,
+        query: &WebhookCreateQuery,
+    ) -> Result<WebhookWithSecret, ApiError> {
+        query.validate()?;
+        self.db_transaction_write("create_webhook", |app| async move {
+            let authentication = app.authenticate(auth_data).await?;
+            let principal_uid = app
+                .check_can_manage_webhook(&authentication, query.package.as_deref())
+                .await?;
+            app.database.create_webhook(principal_uid, query).await
+        })
+        .await
+    }
+
+    /// Deletes a webhook
+    pub async fn delete_webhook(&self, auth_data: &AuthData, webhook_id: i64) -> Result<(), ApiError> {
+        self.db_transaction_write("delete_webhook", |app| async move {
+            let authentication = app.authenticate(auth_data).await?;
+            let webhook = app.database.get_webhook(webhook_id).await?;
+            app.check_can_manage_webhook(&authentication, webhook.package.as_deref())
+                .await?;
+            app.database.delete_webhook(webhook_id).await
+        })
+        .await
+    }
+
+    /// Gets the most recent deliveries for a webhook
+    pub async fn get_webhook_deliveries(
+        &self,
+        auth_data: &AuthData,
+        webhook_id: i64,
+        limit: Option<i64>,
+    ) -> Result<Vec<WebhookDelivery>, ApiError> {
+        let limit = limit.unwrap_or(WEBHOOK_DELIVERIES_DEFAULT_LIMIT).clamp(1, 1000);
+        self.db_transaction_read(|app| async move {
+            let authentication = app.authenticate(auth_data).await?;
+            let webhook = app.database.get_webhook(webhook_id).await?;
+            app.check_can_manage_webhook(&authentication, webhook.package.as_deref())
+                .await?;
+            app.database.get_webhook_deliveries(webhook_id, limit).await
+        })
+        .await
+    }
+
+    /// Delivers again the payload of a previous delivery of a webhook, as a new delivery
+    pub async fn redeliver_webhook_delivery(
+        &self,
+        auth_data: &AuthData,
+        webhook_id: i64,
+        delivery_id: i64,
+    ) -> Result<i64, ApiError> {
+        let job = self
+            .db_transaction_write("redeliver_webhook_delivery", |app| async move {
+                let authentication = app.authenticate(auth_data).await?;
+                let webhook = app.database.get_webhook(webhook_id).await?;
+                app.check_can_manage_webhook(&authentication, webhook.package.as_deref())
+                    .await?;
+                app.database.redeliver_webhook_delivery(webhook_id, delivery_id).await
+            })
+            .await?;
+        let new_delivery_id = job.delivery_id;
+        crate::services::webhooks::spawn_delivery(self.service_db_pool.clone(), self.service_webhook_sender.clone(), job);
+        Ok(new_delivery_id)
+    }
+
+    /// Gets the crates watched by the current user
+    pub async fn get_watched_crates(&self, auth_data: &AuthData) -> Result<Vec<String>, ApiError> {
+        self.db_transaction_read(|app| async move {
+            let authentication = app.authenticate(auth_data).await?;
+            app.database.get_watched_crates(authentication.uid()?).await
+        })
+        .await
+    }
+
+    /// Starts watching a crate for the current user
+    pub async fn watch_crate(&self, auth_data: &AuthData, package: &str) -> Result<(), ApiError> {
+        self.db_transaction_write("watch_crate", |app| async move {
+            let authentication = app.authenticate(auth_data).await?;
+            // restricted crates are reported as not found to the users that cannot read them
+            app.check_can_read_crate(&authentication, package).await?;
+            app.database.watch_crate(authentication.uid()?, package).await
+        })
+        .await
+    }
+
+    /// Stops watching a crate for the current user
+    pub async fn unwatch_crate(&self, auth_data: &AuthData, package: &str) -> Result<(), ApiError> {
+        self.db_transaction_write("unwatch_crate", |app| async move {
+            let authentication = app.authenticate(auth_data).await?;
+            app.database.unwatch_crate(authentication.uid()?, package).await
+        })
+        .await
+    }
+
+    /// Gets the crates starred by the current user
+    pub async fn get_starred_crates(&self, auth_data: &AuthData) -> Result<Vec<String>, ApiError> {
+        self.db_transaction_read(|app| async move {
+            let authentication = app.authenticate(auth_data).await?;
+            app.database.get_starred_crates(authentication.uid()?).await
+        })
+        .await
+    }
+
+    /// Stars a crate for the current user
+    pub async fn star_crate(&self, auth_data: &AuthData, package: &str) -> Result<(), ApiError> {
+        self.db_transaction_write("star_crate", |app| async move {
+            let authentication = app.authenticate(auth_data).await?;
+            // restricted crates are reported as not found to the users that cannot read them
+            app.check_can_read_crate(&authentication, package).await?;
+            app.database.star_crate(authentication.uid()?, package).await
+        })
+        .await?;
+        self.crate_info_cache.invalidate(package);
+        Ok(())
+    }
+
+    /// Removes the star of the current user on a crate
+    pub async fn unstar_crate(&self, auth_data: &AuthData, package: &str) -> Result<(), ApiError> {
+        self.db_transaction_write("unstar_crate", |app| async move {
+            let authentication = app.authenticate(auth_data).await?;
+            app.database.unstar_crate(authentication.uid()?, package).await
+        })
+        .await?;
+        self.crate_info_cache.invalidate(package);
+        Ok(())
+    }
+
+    /// Gets the most recent notifications about watched crates for the current user
+    pub async fn get_user_notifications(
+        &self,
+        auth_data: &AuthData,
+        unread_only: bool,
+        limit: Option<i64>,
+    ) -> Result<Vec<UserNotification>, ApiError> {
+        let limit = limit.unwrap_or(USER_NOTIFICATIONS_DEFAULT_LIMIT).clamp(1, 1000);
+        self.db_transaction_read(|app| async move {
+            let authentication = app.authenticate(auth_data).await?;
+            app.database
+                .get_user_notifications(authentication.uid()?, unread_only, limit)
+                .await
+        })
+        .await
+    }
+
+    /// Marks all the notifications of the current user as read
+    pub async fn mark_user_notifications_read(&self, auth_data: &AuthData) -> Result<(), ApiError> {
+        self.db_transaction_write("mark_user_notifications_read", |app| async move {
+            let authentication = app.authenticate(auth_data).await?;
+            app.database.mark_user_notifications_read(authentication.uid()?).await
+        })
+        .await
+    }
+
+    /// Records a request served for an authenticated subject
+    pub async fn record_traffic(&self, usage: TrafficUsage) {
+        self.app_events_sender.send(AppEvent::Traffic(usage)).await.unwrap();
+    }
+
+    /// Gets the rules for provisioning new users
+    pub async fn get_provisioning_rules(&self, auth_data: &AuthData) -> Result<Vec<ProvisioningRule>, ApiError> {
+        self.db_transaction_read(|app| async move {
+            let authentication = app.authenticate(auth_data).await?;
+            app.check_can_admin_registry(&authentication).await?;
+            app.database.get_provisioning_rules().await
+        })
+        .await
+    }
+
+    /// Replaces the rules for provisioning new users
+    pub async fn set_provisioning_rules(&self, auth_data: &AuthData, rules: &[ProvisioningRule]) -> Result<(), ApiError> {
+        self.db_transaction_write("set_provisioning_rules", |app| async move {
+            let authentication = app.authenticate(auth_data).await?;
+            app.check_can_admin_registry(&authentication).await?;
+            app.database.set_provisioning_rules(rules).await
+        })
+        .await
+    }
+
+    /// Gets the registry-wide statistics, for administrators
+    pub async fn get_registry_stats(&self, auth_data: &AuthData) -> Result<RegistryStats, ApiError> {
+        self.db_transaction_read(|app| async move {
+            let authentication = app.authenticate(auth_data).await?;
+            app.check_can_admin_registry(&authentication).await?;
+            app.database.get_registry_stats().await
+        })
+        .await
+    }
+
+    /// Gets the download statistics for a crate
+    pub async fn get_crate_dl_stats(&self, auth_data: &AuthData, package: &str) -> Result<DownloadStats, ApiError> {
+        self.db_transaction_read(|app| async move {
+            let _authentication = app.authenticate(auth_data).await?;
+            app.database.get_crate_dl_stats(package).await
+        })
+        .await
+    }
+
+    /// Gets the Atom feed of the last published versions in the registry
+    pub async fn get_feed_versions(&self, auth_data: &AuthData) -> Result<String, ApiError> {
+        let entries = self
+            .db_transaction_read(|app| async move {
+                let authentication = app.authenticate(auth_data).await?;
+                let hidden = app.get_hidden_crates(&authentication).await?;
+                let mut entries = app.database.get_versions_feed(None, None, FEED_DEFAULT_LIMIT).await?;
+                entries.retain(|entry| !hidden.contains(&entry.package));
+                Ok::<_, ApiError>(entries)
+            })
+            .await?;
+        let configuration = self.configuration.load();
+        Ok(build_atom_feed(
+            &configuration.web_public_uri,
+            "/api/v1/feeds/versions",
+            &format!("{} - new versions", configuration.self_local_name),
+            &entries,
+        ))
+    }
+
+    /// Gets the Atom feed of the version history of a crate
+    pub async fn get_feed_crate(&self, auth_data: &AuthData, package: &str) -> Result<String, ApiError> {
+        let entries = self
+            .db_transaction_read(|app| async move {
+                let _authentication = app.authenticate(auth_data).await?;
+                app.database.get_versions_feed(Some(package), None, FEED_DEFAULT_LIMIT).await
+            })
+            .await?;
+        if entries.is_empty() {
+            return Err(error_not_found());
+        }
+        Ok(build_atom_feed(
+            &self.configuration.load().web_public_uri,
+            &format!("/api/v1/crates/{package}/feed"),
+            &format!("{package} - versions"),
+            &entries,
+        ))
+    }
+
+    /// Gets the Atom feed of the versions published for the crates owned by a user
+    pub async fn get_feed_owner(&self, auth_data: &AuthData, login: &str) -> Result<String, ApiError> {
+        let entries = self
+            .db_transaction_read(|app| async move {
+                let authentication = app.authenticate(auth_data).await?;
+                let hidden = app.get_hidden_crates(&authentication).await?;
+                let user = app.database.get_user_profile_by_login(login).await?;
+                let mut entries = app
+                    .database
+                    .get_versions_feed(None, Some(user.id), FEED_DEFAULT_LIMIT)
+                    .await?;
+                entries.retain(|entry| !hidden.contains(&entry.package));
+                Ok::<_, ApiError>(entries)
+            })
+            .await?;
+        Ok(build_atom_feed(
+            &self.configuration.load().web_public_uri,
+            &format!("/api/v1/feeds/owners/{login}"),
+            &format!("Crates owned by {login} - versions"),
+            &entries,
+        ))
+    }
+
+    /// Gets the daily downloads of each version of a crate over the last days
+    pub async fn get_crate_downloads(&self, auth_data: &AuthData, package: &str) -> Result<CrateDownloads, ApiError> {
+        self.db_transaction_read(|app| async move {
+            let _authentication = app.authenticate(auth_data).await?;
+            app.database.get_crate_downloads(package).await
+        })
+        .await
+    }
+
+    /// Gets the crates of the registry that depend on a crate
+    pub async fn get_crate_reverse_dependencies(
+        &self,
+        auth_data: &AuthData,
+        package: &str,
+    ) -> Result<ReverseDependencies, ApiError> {
+        self.db_transaction_read(|app| async move {
+            let authentication = app.authenticate(auth_data).await?;
+            let hidden = app.get_hidden_crates(&authentication).await?;
+            let mut result = app.database.get_reverse_dependencies(package).await?;
+            result.dependencies.retain(|dependency| !hidden.contains(&dependency.package));
+            result.meta.total = result.dependencies.len();
+            Ok(result)
+        })
+        .await
+    }
+
+    /// Gets the list of owners for a package
+    pub async fn get_crate_owners(&self, auth_data: &AuthData, package: &str) -> Result<OwnersQueryResult, ApiError> {
+        self.db_transaction_read(|app| async move {
+            let _authentication = app.authenticate(auth_data).await?;
+            app.database.get_crate_owners(package).await
+        })
+        .await
+    }
+
+    /// Add owners to a package
+    pub async fn add_crate_owners(
+        &self,
+        auth_data: &AuthData,
+        package: &str,
+        new_users: &[String],
+    ) -> Result<YesNoMsgResult, ApiError> {
+        let (result, previous, owners) = self
+            .db_transaction_write("add_crate_owners", |app| async move {
+                let authentication = app.authenticate(auth_data).await?;
+                app.check_can_manage_crate(&authentication, package).await?;
+                let previous = app.database.get_crate_owners(package).await?;
+                let new_users = app.database.resolve_owners(new_users).await?;
+                let result = app.database.add_crate_owners(package, &new_users).await?;
+                let owners = app.database.get_crate_owners(package).await?;
+                Ok::<_, ApiError>((result, previous, owners))
+            })
+            .await?;
+        let invited = owners
+            .users
+            .iter()
+            .filter(|owner| previous.users.iter().all(|previous| previous.id != owner.id))
+            .cloned()
+            .collect::<Vec<_>>();
+        let configuration = self.configuration.load_full();
+        let service_email_sender = self.service_email_sender.clone();
+        let pool = self.service_db_pool.clone();
+        let package_name = package.to_string();
+        Self::notify_spawn(async move {
+            notifications::notify_owners_invited(&configuration, service_email_sender, &pool, &package_name, invited).await
+        });
+        self.webhooks_dispatch_owners_change(package, owners);
+        Ok(result)
+    }
+
+    /// Remove owners from a package
+    pub async fn remove_crate_owners(
+        &self,
+        auth_data: &AuthData,
+        package: &str,
+        old_users: &[String],
+    ) -> Result<YesNoResult, ApiError> {
+        let (result, owners) = self
+            .db_transaction_write("remove_crate_owners", |app| async move {
+                let authentication = app.authenticate(auth_data).await?;
+                app.check_can_manage_crate(&authentication, package).await?;
+                let old_users = app.database.resolve_owners(old_users).await?;
+                let result = app.database.remove_crate_owners(package, &old_users).await?;
+                let owners = app.database.get_crate_owners(package).await?;
+                Ok::<_, ApiError>((result, owners))
+            })
+            .await?;
+        self.webhooks_dispatch_owners_change(package, owners);
+        Ok(result)
+    }
+
+    /// Notifies the webhooks of a change in the owners of a crate
+    fn webhooks_dispatch_owners_change(&self, package: &str, owners: OwnersQueryResult) {
+        self.webhooks_dispatch(WebhookPayload {
+            event: WebhookEvent::OwnersChange,
+            timestamp: Local::now().naive_local(),
+            package: package.to_string(),
+            version: None,
+            target: None,
+            owners: Some(owners.users.into_iter().map(|user| user.login).collect()),
+        });
+    }
+
+    /// Gets the targets for a crate
+    pub async fn get_crate_targets(&self, auth_data: &AuthData, package: &str) -> Result<Vec<CrateInfoTarget>, ApiError> {
+        self.db_transaction_read(|app| async move {
+            let _authentication = app.authenticate(auth_data).await?;
+            app.database.get_crate_targets(package).await
+        })
+        .await
+    }
+
+    /// Sets the targets for a crate
+    pub async fn set_crate_targets(
+        &self,
+        auth_data: &AuthData,
+        package: &str,
+        targets: &[CrateInfoTarget],
+    ) -> Result<(), ApiError> {
+        let (user, jobs) = self
+            .db_transaction_write("set_crate_targets", |app| async move {
+                let authentication = app.authenticate(auth_data).await?;
+                let principal_uid = app.check_can_manage_crate(&authentication, package).await?;
+                let user = app.database.get_user_profile(principal_uid).await?;
+                for info in targets {
+                    if !self.configuration.load().self_known_targets.contains(&info.target) {
+                        return Err(specialize(
+                            error_invalid_request(),
+                            format!("Unknown target: {}", info.target),
+                        ));
+                    }
+                }
+                let jobs = app.database.set_crate_targets(package, targets).await?;
+                for job in &jobs {
+                    app.database
+                        .set_crate_documentation(&job.package, &job.version, &job.target, false, false)
+                        .await?;
+                }
+                Ok::<_, ApiError>((user, jobs))
+            })
+            .await?;
+        self.crate_info_cache.invalidate(package);
+        for job in jobs {
+            self.service_docs_generator
+                .queue(&job, &DocGenTrigger::NewTarget { by: user.clone() })
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Gets the required capabilities for a crate
+    pub async fn get_crate_required_capabilities(&self, auth_data: &AuthData, package: &str) -> Result<Vec<String>, ApiError> {
+        self.db_transaction_read(|app| async move {
+            let _authentication = app.authenticate(auth_data).await?;
+            app.database.get_crate_required_capabilities(package).await
+        })
+        .await
+    }
+
+    /// Sets the required capabilities for a crate
+    pub async fn set_crate_required_capabilities(
+        &self,
+        auth_data: &AuthData,
+        package: &str,
+        capabilities: &[String],
+    ) -> Result<(), ApiError> {
+        self.db_transaction_write("set_crate_required_capabilities", |app| async move {
+            let authentication = app.authenticate(auth_data).await?;
+            let _ = app.check_can_manage_crate(&authentication, package).await?;
+            app.database.set_crate_required_capabilities(package, capabilities).await?;
+            Ok::<_, ApiError>(())
+        })
+        .await?;
+        self.crate_info_cache.invalidate(package);
+        Ok(())
+    }
+
+    /// Sets the deprecation status on a crate, with the reason for deprecating it, if any
+    ///
+    /// The owners of the dependent crates are only notified when the crate becomes deprecated.
+    pub async fn set_crate_deprecation(
+        &self,
+        auth_data: &AuthData,
+        package: &str,
+        deprecated: bool,
+        reason: Option<&str>,
+    ) -> Result<(), ApiError> {
+        let newly_deprecated = self
+            .db_transaction_write("set_crate_deprecation", |app| async move {
+                let authentication = app.authenticate(auth_data).await?;
+                app.check_can_manage_crate(&authentication, package).await?;
+                app.database.set_crate_deprecation(package, deprecated, reason).await
+            })
+            .await?;
+        self.crate_info_cache.invalidate(package);
+        if newly_deprecated {
+            self.notify_deprecation_email_spawn(package, reason);
+            self.webhooks_dispatch(WebhookPayload::new(WebhookEvent::Deprecation, package, None));
+        }
+        Ok(())
+    }
+
+    /// Declares the provenance of a crate version, after its publication
+    ///
+    /// The provenance can only be declared once and the commit must match the one recorded by cargo, if any.
+    pub async fn set_crate_version_provenance(
+        &self,
+        auth_data: &AuthData,
+        package: &str,
+        version: &str,
+        declaration: &VersionProvenanceDeclaration,
+    ) -> Result<VersionProvenance, ApiError> {
+        if let Some(commit) = &declaration.commit {
+            if !(7..=64).contains(&commit.len()) || !commit.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(specialize(error_invalid_request(), format!("invalid commit SHA: {commit}")));
+            }
+        }
+        if let Some(url) = &declaration.ci_run_url {
+            if !url
+                .parse::<Uri>()
+                .is_ok_and(|uri| matches!(uri.scheme_str(), Some("http" | "https")))
+            {
+                return Err(specialize(error_invalid_request(), format!("invalid CI run URL: {url}")));
+            }
+        }
+        let provenance = self
+            .db_transaction_write("set_crate_version_provenance", |app| async move {
+                let authentication = app.authenticate(auth_data).await?;
+                app.check_can_manage_crate(&authentication, package).await?;
+                let existing = app.database.get_crate_version_provenance(package, version).await?;
+                if existing.ci_run_url.is_some() || existing.builder.is_some() {
+                    return Err(specialize(
+                        error_conflict(),
+                        format!("the provenance of {package} {version} was already declared"),
+                    ));
+                }
+                if let (Some(recorded), Some(declared)) = (&existing.commit, &declaration.commit) {
+                    if !recorded.to_ascii_lowercase().starts_with(&declared.to_ascii_lowercase()) {
+                        return Err(specialize(
+                            error_invalid_request(),
+                            format!("commit {declared} does not match the commit {recorded} recorded by cargo"),
+                        ));
+                    }
+                }
+                let provenance = VersionProvenance {
+                    commit: existing.commit.or_else(|| declaration.commit.clone()),
+                    ci_run_url: declaration.ci_run_url.clone(),
+                    builder: declaration.builder.clone(),
+                    verified: false,
+                };
+                app.database
+                    .set_crate_version_provenance(package, version, &provenance)
+                    .await?;
+                Ok::<_, ApiError>(provenance)
+            })
+            .await?;
+        self.crate_info_cache.invalidate(package);
+        Ok(provenance)
+    }
+
+    /// Sets the frozen status on a crate
+    pub async fn set_crate_frozen(&self, auth_data: &AuthData, package: &str, frozen: bool) -> Result<(), ApiError> {
+        self.db_transaction_write("set_crate_frozen", |app| async move {
+            let authentication = app.authenticate(auth_data).await?;
+            let principal_uid = app.check_can_admin_registry(&authentication).await?;
+            app.database.set_crate_frozen(package, frozen).await?;
+            app.database
+                .add_audit_entry(
+                    Some(principal_uid),
+                    authentication.impersonator,
+                    if frozen { "freeze_crate" } else { "unfreeze_crate" },
+                    package,
+                )
+                .await
+        })
+        .await?;
+        self.crate_info_cache.invalidate(package);
+        Ok(())
+    }
+
+    /// Gets the visibility of a crate, for its owners
+    pub async fn get_crate_visibility(&self, auth_data: &AuthData, package: &str) -> Result<CrateVisibility, ApiError> {
+        self.db_transaction_read(|app| async move {
+            let authentication = app.authenticate(auth_data).await?;
+            app.database.check_is_crate_manager(authentication.uid()?, package).await?;
+            app.database.get_crate_visibility(package).await
+        })
+        .await
+    }
+
+    /// Sets the visibility of a crate
+    pub async fn set_crate_visibility(
+        &self,
+        auth_data: &AuthData,
+        package: &str,
+        query: &CrateVisibilityQuery,
+    ) -> Result<CrateVisibility, ApiError> {
+        self.db_transaction_write("set_crate_visibility", |app| async move {
+            let authentication = app.authenticate(auth_data).await?;
+            let principal_uid = app.check_can_manage_crate(&authentication, package).await?;
+            app.database.set_crate_visibility(package, query).await?;
+            let details = format!(
+                "{package} {} with readers {} and teams {}",
+                if query.is_restricted {
+                    "restricted"
+                } else {
+                    "visible to all users"
+                },
+                query.readers.join(", "),
+                query.teams.join(", ")
+            );
+            app.database
+                .add_audit_entry(
+                    Some(principal_uid),
+                    authentication.impersonator,
+                    "set_crate_visibility",
+                    &details,
+                )
+                .await?;
+            app.database.get_crate_visibility(package).await
+        })
+        .await
+    }
+
+    /// Gets the global statistics for the registry
+    pub async fn get_crates_stats(&self, auth_data: &AuthData) -> Result<GlobalStats, ApiError> {
+        self.db_transaction_read(|app| async move {
+            let authentication = app.authenticate(auth_data).await?;
+            let hidden = app.get_hidden_crates(&authentication).await?;
+            app.database.get_crates_stats(&hidden).await
+        })
+        .await
+    }
+
+    /// Search for crates
+    pub async fn search_crates(
+        &self,
+        auth_data: &AuthData,
+        query: &str,
+        filters: &SearchFilters,
+        pagination: SearchPagination,
+    ) -> Result<SearchResults, ApiError> {
+        if query.trim().is_empty() && filters.keyword.is_none() && filters.category.is_none() && filters.owner.is_none() {
+            return Err(specialize(
+                error_invalid_request(),
+                String::from("a search query or a filter on keywords, categories or owners is expected"),
+            ));
+        }
+        let hits = self.service_search.search(query, filters).await?;
+        let configuration = self.configuration.load_full();
+        let configuration = &configuration;
+        self.db_transaction_read(|app| async move {
+            let authentication = app.authenticate(auth_data).await?;
+            let hidden = app.get_hidden_crates(&authentication).await?;
+            let hits = hits.into_iter().filter(|hit| !hidden.contains(&hit.name)).collect::<Vec<_>>();
+            app.database
+                .search_crates(
+                    &hits,
+                    filters,
+                    pagination,
+                    SearchRankingWeights {
+                        downloads: configuration.search_weight_downloads,
+                        recency: configuration.search_weight_recency,
+                    },
+                )
+                .await
+        })
+        .await
+    }
+
+    /// Launches a backup of the database in the background
+    pub async fn launch_backup(self: &Arc<Self>, auth_data: &AuthData) -> Result<BackupStatus, ApiError> {
+        self.db_transaction_read(|app| async move {
+            let authentication = app.authenticate(auth_data).await?;
+            app.check_can_admin_registry(&authentication).await
+        })
+        .await?;
+        self.backup_spawn()
+    }
+
+    /// Gets the status of the backups of the database
+    pub async fn get_backup_status(&self, auth_data: &AuthData) -> Result<BackupStatus, ApiError> {
+        self.db_transaction_read(|app| async move {
+            let authentication = app.authenticate(auth_data).await?;
+            app.check_can_admin_registry(&authentication).await
+        })
+        .await?;
+        Ok(self.backup_status.lock().unwrap().clone())
+    }
+
+    /// Gets the report for the liveness probe, without checking the services the registry depends on
+    #[must_use]
+    pub fn get_liveness(&self) -> LivenessReport {
+        LivenessReport {
+            alive: true,
+            started_on: self.started_on,
+        }
+    }
+
+    /// Gets the report of the health checks of the database, the storage and the index
+    /// The report is reused for a short time so that frequent probes do not overload the services
+    pub async fn get_readiness(&self) -> HealthReport {
+        if let Some((checked_at, report)) = self.readiness.lock().unwrap().as_ref() {
+            if checked_at.elapsed() < READINESS_CACHE_DURATION {
+                return report.clone();
+            }
+        }
+        let results = run_health_checks(
+            &self.service_db_pool,
+            self.service_storage.as_ref(),
+            self.service_index.as_ref(),
+        )
+        .await;
+        let checks = results
+            .into_iter()
+            .map(|(name, result)| {
+                if let Err(e) = &result {
+                    warn!("health check failed for {name}: {e}");
+                }
+                HealthCheck {
+                    name: name.to_string(),
+                    healthy: result.is_ok(),
+                }
+            })
+            .collect::<Vec<_>>();
+        let report = HealthReport {
+            healthy: checks.iter().all(|check| check.healthy),
+            checked_on: Local::now().naive_local(),
+            checks,
+        };
+        *self.readiness.lock().unwrap() = Some((Instant::now(), report.clone()));
+        report
+    }
+
+    /// Gets the status of the background jobs
+    pub async fn get_scheduled_jobs(&self, auth_data: &AuthData) -> Result<Vec<ScheduledJobStatus>, ApiError> {
+        self.db_transaction_read(|app| async move {
+            let authentication = app.authenticate(auth_data).await?;
+            app.check_can_admin_registry(&authentication).await
+        })
+        .await?;
+        Ok(self.scheduler.get_statuses())
+    }
+
+    /// Triggers a run of a background job now
+    /// Fails with a conflict if the job is already running
+    pub async fn run_scheduled_job(&self, auth_data: &AuthData, name: &str) -> Result<ScheduledJobStatus, ApiError> {
         self.db_transaction_read(|app| async move {
-            let _authentication = app.authenticate(auth_data).await?;
-            app.database.get_crate_owners(package).await
+            let authentication = app.authenticate(auth_data).await?;
+            app.check_can_admin_registry(&authentication).await
         })
-        .await
+        .await?;
+        self.scheduler.trigger(name)
     }
 
-    /// Add owners to a package
-    pub async fn add_crate_owners(
-        &self,
-        auth_data: &AuthData,
-        package: &str,
-        new_users: &[String],
-    ) -> Result<YesNoMsgResult, ApiError> {
-        self.db_transaction_write("add_crate_owners", |app| async move {
-            let authentication = app.authenticate(auth_data).await?;
-            app.check_can_manage_crate(&authentication, package).await?;
-            app.database.add_crate_owners(package, new_users).await
+    /// Gets the report about the advisories affecting the hosted crates
+    pub async fn get_advisories_report(&self, auth_data: &AuthData) -> Result<AdvisoriesReport, ApiError> {
+        let advisories = self
+            .db_transaction_read(|app| async move {
+                let authentication = app.authenticate(auth_data).await?;
+                app.check_can_admin_registry(&authentication).await?;
+                app.database.get_all_advisories().await
+            })
+            .await?;
+        Ok(AdvisoriesReport {
+            last_sync: *self.advisories_last_sync.lock().unwrap(),
+            advisories,
         })
-        .await
     }
 
-    /// Remove owners from a package
-    pub async fn remove_crate_owners(
+    /// Gets the inventory of the licenses of the hosted crate versions, and optionally of their external dependencies
+    ///
+    /// The crate versions whose license cannot be read are reported with the error instead of failing the whole inventory.
+    pub async fn get_license_inventory(
         &self,
         auth_data: &AuthData,
-        package: &str,
-        old_users: &[String],
-    ) -> Result<YesNoResult, ApiError> {
-        self.db_transaction_write("remove_crate_owners", |app| async move {
+        with_dependencies: bool,
+    ) -> Result<LicenseInventory, ApiError> {
+        let versions = self
+            .db_transaction_read(|app| async move {
+                let authentication = app.authenticate(auth_data).await?;
+                app.check_can_admin_registry(&authentication).await?;
+                app.database.get_unyanked_crate_versions().await
+            })
+            .await?;
+        let mut crates = n_at_a_time(
+            versions.into_iter().map(|CrateVersion { package, version }| {
+                let service_storage = self.service_storage.clone();
+                Box::pin(async move {
+                    let metadata = service_storage.download_crate_metadata(&package, &version).await;
+                    let (metadata, error) = match metadata {
+                        Ok(metadata) => (metadata, None),
+                        Err(error) => (None, Some(error.to_string())),
+                    };
+                    LicenseInventoryEntry {
+                        registry: None,
+                        license: metadata.as_ref().and_then(|m| m.license.clone()),
+                        license_file: metadata.and_then(|m| m.license_file),
+                        error,
+                        package,
+                        version,
+                    }
+                })
+            }),
+            LICENSE_INVENTORY_CONCURRENCY,
+            |_| false,
+        )
+        .await;
+        crates.sort_by(|a, b| (&a.package, &a.version).cmp(&(&b.package, &b.version)));
+        let dependencies = if with_dependencies {
+            self.get_license_inventory_dependencies().await?
+        } else {
+            Vec::new()
+        };
+        Ok(LicenseInventory::new(crates, dependencies))
+    }
+
+    /// Gets the licenses of the external dependencies of the last version of the hosted crates
+    ///
+    /// The licenses are only known for the dependencies that can be fetched through the proxy.
+    async fn get_license_inventory_dependencies(&self) -> Result<Vec<LicenseInventoryEntry>, ApiError> {
+        let heads = self
+            .db_transaction_read(|app| async move {
+                let mut heads = Vec::new();
+                for head in app.database.get_crates_last_versions().await? {
+                    let targets = app.database.get_crate_targets(&head.package).await?;
+                    heads.push((head, targets.into_iter().map(|info| info.target).collect::<Vec<_>>()));
+                }
+                Ok::<_, ApiError>(heads)
+            })
+            .await?;
+        let results = n_at_a_time(
+            heads.into_iter().map(|(head, targets)| {
+                let service_deps_checker = self.service_deps_checker.clone();
+                Box::pin(async move {
+                    let result = service_deps_checker.check_crate(&head.package, &head.version, &targets).await;
+                    (head, result)
+                })
+            }),
+            LICENSE_INVENTORY_CONCURRENCY,
+            |_| false,
+        )
+        .await;
+        let mut resolved = BTreeSet::new();
+        for (head, result) in results {
+            match result {
+                Ok(analysis) => resolved.extend(
+                    analysis
+                        .resolved
+                        .into_iter()
+                        .filter_map(|dep| dep.registry.map(|registry| (registry, dep.package, dep.version))),
+                ),
+                Err(error) => warn!(
+                    "licenses: failed to resolve the dependencies of {} {}: {error}",
+                    head.package, head.version
+                ),
+            }
+        }
+        let configuration = self.configuration.load();
+        let mut dependencies = n_at_a_time(
+            resolved.into_iter().map(|(registry, package, version)| {
+                // the proxy designates crates.io with `None` and the other registries by their name
+                let registry_name = if registry == CRATES_IO_REGISTRY_URI {
+                    Some(None)
+                } else {
+                    let uri = registry.trim_start_matches("sparse+").trim_end_matches('/');
+                    configuration
+                        .external_registries
+                        .iter()
+                        .find(|external| external.index.trim_end_matches('/') == uri)
+                        .map(|external| Some(external.name.clone()))
+                };
+                let service_proxy = self.service_proxy.clone();
+                Box::pin(async move {
+                    let license = match registry_name {
+                        Some(name) => service_proxy
+                            .download_crate(name.as_deref(), &package, &version)
+                            .await
+                            .and_then(|content| {
+                                content
+                                    .map(|content| extract_crate_license(&content, &package, &version))
+                                    .transpose()
+                            }),
+                        None => Ok(None),
+                    };
+                    let ((license, license_file), error) = match license {
+                        Ok(license) => (license.unwrap_or_default(), None),
+                        Err(error) => ((None, None), Some(error.to_string())),
+                    };
+                    LicenseInventoryEntry {
+                        registry: Some(registry),
+                        package,
+                        version,
+                        license,
+                        license_file,
+                        error,
+                    }
+                })
+            }),
+            LICENSE_INVENTORY_CONCURRENCY,
+            |_| false,
+        )
+        .await;
+        dependencies.sort_by(|a, b| (&a.registry, &a.package, &a.version).cmp(&(&b.registry, &b.package, &b.version)));
+        Ok(dependencies)
+    }
+
+    /// Launches the rebuild of the whole search index in the background
+    pub async fn launch_search_reindex(self: &Arc<Self>, auth_data: &AuthData) -> Result<SearchReindexProgress, ApiError> {
+        self.db_transaction_read(|app| async move {
             let authentication = app.authenticate(auth_data).await?;
-            app.check_can_manage_crate(&authentication, package).await?;
-            app.database.remove_crate_owners(package, old_users).await
+            app.check_can_admin_registry(&authentication).await
         })
-        .await
+        .await?;
+        self.search_index_rebuild_spawn()
     }
 
-    /// Gets the targets for a crate
-    pub async fn get_crate_targets(&self, auth_data: &AuthData, package: &str) -> Result<Vec<CrateInfoTarget>, ApiError> {
+    /// Gets the progress of the last rebuild of the search index
+    pub async fn get_search_reindex_progress(&self, auth_data: &AuthData) -> Result<SearchReindexProgress, ApiError> {
         self.db_transaction_read(|app| async move {
-            let _authentication = app.authenticate(auth_data).await?;
-            app.database.get_crate_targets(package).await
+            let authentication = app.authenticate(auth_data).await?;
+            app.check_can_admin_registry(&authentication).await
         })
-        .await
+        .await?;
+        Ok(self.search_reindex.lock().unwrap().clone())
     }
 
-    /// Sets the targets for a crate
-    pub async fn set_crate_targets(
-        &self,
-        auth_data: &AuthData,
-        package: &str,
-        targets: &[CrateInfoTarget],
-    ) -> Result<(), ApiError> {
-        let (user, jobs) = self
-            .db_transaction_write("set_crate_targets", |app| async move {
+    /// Gets the current state of the read-only maintenance mode
+    pub async fn get_maintenance(&self, auth_data: &AuthData) -> Result<MaintenanceConfig, ApiError> {
+        let _authentication = self.authenticate(auth_data).await?;
+        Ok(self.maintenance.lock().unwrap().clone())
+    }
+
+    /// Sets the state of the read-only maintenance mode
+    ///
+    /// The state is only kept in memory, a restart goes back to the state from the configuration.
+    pub async fn set_maintenance(&self, auth_data: &AuthData, maintenance: MaintenanceConfig) -> Result<(), ApiError> {
+        // authenticate in a read transaction, so that the maintenance mode can be disabled
+        let authentication = self
+            .db_transaction_read(|app| async move {
                 let authentication = app.authenticate(auth_data).await?;
-                let principal_uid = app.check_can_manage_crate(&authentication, package).await?;
-                let user = app.database.get_user_profile(principal_uid).await?;
-                for info in targets {
-                    if !self.configuration.self_known_targets.contains(&info.target) {
-                        return Err(specialize(
-                            error_invalid_request(),
-                            format!("Unknown target: {}", info.target),
-                        ));
-                    }
-                }
-                let jobs = app.database.set_crate_targets(package, targets).await?;
-                for job in &jobs {
-                    app.database
-                        .set_crate_documentation(&job.package, &job.version, &job.target, false, false)
-                        .await?;
-                }
-                Ok::<_, ApiError>((user, jobs))
+                app.check_can_admin_registry(&authentication).await?;
+                Ok::<_, ApiError>(authentication)
             })
             .await?;
-        for job in jobs {
-            self.service_docs_generator
-                .queue(&job, &DocGenTrigger::NewTarget { by: user.clone() })
-                .await?;
-        }
+        let details = match (maintenance.enabled, &maintenance.message) {
+            (true, Some(message)) => format!("enabled: {message}"),
+            (true, None) => String::from("enabled"),
+            (false, _) => String::from("disabled"),
+        };
+        self.db_transaction_write("set_maintenance", |app| async move {
+            app.database
+                .add_audit_entry(
+                    Some(authentication.uid()?),
+                    authentication.impersonator,
+                    "set_maintenance",
+                    &details,
+                )
+                .await
+        })
+        .await?;
+        info!(
+            "maintenance mode {}",
+            if maintenance.enabled { "enabled" } else { "disabled" }
+        );
+        *self.maintenance.lock().unwrap() = maintenance;
         Ok(())
     }
 
-    /// Gets the required capabilities for a crate
-    pub async fn get_crate_required_capabilities(&self, auth_data: &AuthData, package: &str) -> Result<Vec<String>, ApiError> {
+    /// Gets the announcement currently displayed to the users, if any
+    pub async fn get_current_announcement(&self, auth_data: &AuthData) -> Result<Option<Announcement>, ApiError> {
         self.db_transaction_read(|app| async move {
             let _authentication = app.authenticate(auth_data).await?;
-            app.database.get_crate_required_capabilities(package).await
+            let announcement = app.database.get_announcement().await?;
+            let now = Local::now().naive_local();
+            Ok(announcement.filter(|announcement| announcement.is_active_at(now)))
         })
         .await
     }
 
-    /// Sets the required capabilities for a crate
-    pub async fn set_crate_required_capabilities(
-        &self,
-        auth_data: &AuthData,
-        package: &str,
-        capabilities: &[String],
-    ) -> Result<(), ApiError> {
-        self.db_transaction_write("set_crate_required_capabilities", |app| async move {
+    /// Gets the announcement set by the administrators, even when it is not currently displayed
+    pub async fn get_announcement(&self, auth_data: &AuthData) -> Result<Option<Announcement>, ApiError> {
+        self.db_transaction_read(|app| async move {
             let authentication = app.authenticate(auth_data).await?;
-            let _ = app.check_can_manage_crate(&authentication, package).await?;
-            app.database.set_crate_required_capabilities(package, capabilities).await?;
-            Ok::<_, ApiError>(())
+            app.check_can_admin_registry(&authentication).await?;
+            app.database.get_announcement().await
         })
-        .await?;
-        Ok(())
+        .await
     }
 
-    /// Sets the deprecation status on a crate
-    pub async fn set_crate_deprecation(&self, auth_data: &AuthData, package: &str, deprecated: bool) -> Result<(), ApiError> {
-        self.db_transaction_write("set_crate_deprecation", |app| async move {
+    /// Sets the announcement displayed to the users, replacing the previous one
+    pub async fn set_announcement(&self, auth_data: &AuthData, announcement: &Announcement) -> Result<(), ApiError> {
+        self.db_transaction_write("set_announcement", |app| async move {
             let authentication = app.authenticate(auth_data).await?;
-            app.check_can_manage_crate(&authentication, package).await?;
-            app.database.set_crate_deprecation(package, deprecated).await
+            let principal_uid = app.check_can_admin_registry(&authentication).await?;
+            app.database.set_announcement(announcement).await?;
+            app.database
+                .add_audit_entry(
+                    Some(principal_uid),
+                    authentication.impersonator,
+                    "set_announcement",
+                    &format!("{} announcement: {}", announcement.level, announcement.message),
+                )
+                .await
         })
         .await
     }
 
-    /// Gets the global statistics for the registry
-    pub async fn get_crates_stats(&self, auth_data: &AuthData) -> Result<GlobalStats, ApiError> {
-        self.db_transaction_read(|app| async move {
-            let _authentication = app.authenticate(auth_data).await?;
-            app.database.get_crates_stats().await
+    /// Clears the announcement displayed to the users
+    pub async fn clear_announcement(&self, auth_data: &AuthData) -> Result<(), ApiError> {
+        self.db_transaction_write("clear_announcement", |app| async move {
+            let authentication = app.authenticate(auth_data).await?;
+            let principal_uid = app.check_can_admin_registry(&authentication).await?;
+            app.database.clear_announcement().await?;
+            app.database
+                .add_audit_entry(Some(principal_uid), authentication.impersonator, "clear_announcement", "")
+                .await
         })
         .await
     }
 
-    /// Search for crates
-    pub async fn search_crates(
-        &self,
-        auth_data: &AuthData,
-        query: &str,
-        per_page: Option<usize>,
-        deprecated: Option<bool>,
-    ) -> Result<SearchResults, ApiError> {
+    /// Reloads the settings of the configuration that can be changed at runtime
+    pub async fn reload_configuration(&self, auth_data: &AuthData) -> Result<(), ApiError> {
         self.db_transaction_read(|app| async move {
-            let _authentication = app.authenticate(auth_data).await?;
-            app.database.search_crates(query, per_page, deprecated).await
+            let authentication = app.authenticate(auth_data).await?;
+            app.check_can_admin_registry(&authentication).await
         })
-        .await
+        .await?;
+        self.reload_configuration_from_env().await
+    }
+
+    /// Reloads the settings of the configuration that can be changed at runtime,
+    /// from the configuration file and the environment
+    ///
+    /// Structural settings are left untouched, see `Configuration::with_reloaded_from_env`.
+    pub async fn reload_configuration_from_env(&self) -> Result<(), ApiError> {
+        load_config_file()?;
+        let current = self.configuration.load_full();
+        let tenant = current.tenant.clone();
+        let configuration = with_tenant(tenant.as_deref(), async { current.with_reloaded_from_env() }).await?;
+        let log_level = log::LevelFilter::from_str(&configuration.log_level).map_err(|_| {
+            specialize(
+                error_invalid_request(),
+                format!("invalid log level {}", configuration.log_level),
+            )
+        })?;
+        // the credentials for the external registries may have changed
+        configuration.write_auth_config().await?;
+        if tenant.is_none() {
+            // the level of the logs is set for the whole process by the main registry
+            log::set_max_level(log_level);
+        }
+        self.configuration.store(Arc::new(configuration));
+        info!("configuration reloaded");
+        Ok(())
+    }
+
+    /// Suggests the names of crates that start with a prefix
+    pub async fn suggest_crates(&self, auth_data: &AuthData, prefix: &str, limit: usize) -> Result<Vec<String>, ApiError> {
+        let hidden = self
+            .db_transaction_read(|app| async move {
+                let authentication = app.authenticate(auth_data).await?;
+                app.get_hidden_crates(&authentication).await
+            })
+            .await?;
+        // request more suggestions to make up for the hidden ones
+        let mut names = self.service_search.suggest(prefix, limit + hidden.len()).await?;
+        names.retain(|name| !hidden.contains(name));
+        names.truncate(limit);
+        Ok(names)
     }
 
     /// Checks the dependencies of a local crate
@@ -817,6 +3999,137 @@ impl Application {
         let targets = targets.into_iter().map(|info| info.target).collect::<Vec<_>>();
         self.service_deps_checker.check_crate(package, version, &targets).await
     }
+
+    /// Gets the crate versions in quarantine, for administrators
+    pub async fn get_quarantined_versions(&self, auth_data: &AuthData) -> Result<Vec<QuarantinedVersion>, ApiError> {
+        self.db_transaction_read(|app| async move {
+            let authentication = app.authenticate(auth_data).await?;
+            app.check_can_admin_registry(&authentication).await?;
+            app.database.get_quarantined_versions().await
+        })
+        .await
+    }
+
+    /// Releases a crate version from quarantine before its end, for administrators
+    pub async fn release_quarantined_version(
+        &self,
+        auth_data: &AuthData,
+        package: &str,
+        version: &str,
+    ) -> Result<(), ApiError> {
+        let quarantine_until = self
+            .db_transaction_read(|app| async move {
+                let authentication = app.authenticate(auth_data).await?;
+                app.check_can_admin_registry(&authentication).await?;
+                app.database.get_crate_version_quarantine(package, version).await
+            })
+            .await?;
+        if quarantine_until.is_none() || !self.quarantine_release(package, version).await? {
+            return Err(specialize(
+                error_invalid_request(),
+                format!("{package} {version} is not in quarantine"),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Gets the checksums of the `.crate` file of a crate version
+    ///
+    /// The checksums of the versions published before they were stored are computed by the `checksums` job.
+    pub async fn get_crate_version_checksums(
+        &self,
+        auth_data: &AuthData,
+        package: &str,
+        version: &str,
+    ) -> Result<CrateVersionChecksums, ApiError> {
+        self.db_transaction_read(|app| async move {
+            let _authentication = app.authenticate(auth_data).await?;
+            app.database.get_crate_version_checksums(package, version).await
+        })
+        .await?
+        .ok_or_else(|| {
+            specialize(
+                error_not_found(),
+                format!("the checksums of {package} {version} are not computed yet"),
+            )
+        })
+    }
+
+    /// Computes and stores the checksums and the sizes of the versions published before they were stored
+    pub(crate) async fn checksums_backfill(&self) -> Result<(), ApiError> {
+        let versions = self
+            .db_transaction_read(|app| async move { app.database.get_crate_versions_without_checksums().await })
+            .await?;
+        for (package, version) in versions {
+            // a failing version is retried on the next run
+            if let Err(e) = self.checksums_backfill_version(&package, &version).await {
+                error!("failed to compute the checksums of {package} {version}: {e}");
+            }
+        }
+        Ok(())
+    }
+
+    /// Computes and stores the sizes of the versions uploaded by some users, before they are counted in the quotas
+    async fn sizes_backfill_users(&self, uids: &[i64]) -> Result<(), ApiError> {
+        let versions = self
+            .db_transaction_read(|app| async move { app.database.get_users_crate_versions_without_size(uids).await })
+            .await?;
+        for (package, version) in versions {
+            // a failing version is not counted, it is retried by the periodic job
+            if let Err(e) = self.checksums_backfill_version(&package, &version).await {
+                error!("failed to compute the size of {package} {version}: {e}");
+            }
+        }
+        Ok(())
+    }
+
+    /// Computes and stores the checksums and the size of a crate version, only when its content matches the checksum in the index
+    async fn checksums_backfill_version(&self, package: &str, version: &str) -> Result<(), ApiError> {
+        let expected = self
+            .service_index
+            .get_crate_data(package)
+            .await?
+            .into_iter()
+            .find(|data| data.vers == version)
+            .map(|data| data.cksum)
+            .ok_or_else(|| specialize(error_not_found(), format!("{package} {version} is not in the index")))?;
+        let content = self.service_storage.download_crate(package, version).await?;
+        let checksums = CrateVersionChecksums::new(&content);
+        let size = i64::try_from(content.len()).unwrap_or(i64::MAX);
+        if checksums.sha256 != expected {
+            return Err(specialize(
+                error_backend_failure(),
+                format!("the stored content of {package} {version} does not match its checksum in the index"),
+            ));
+        }
+        self.db_transaction_write("set_crate_version_checksums", |app| {
+            let checksums = &checksums;
+            async move {
+                app.database
+                    .set_crate_version_checksums(package, version, checksums, size)
+                    .await
+            }
+        })
+        .await
+    }
+
+    /// Gets the Software Bill of Materials (SBOM) of a crate version, from its metadata and resolved dependencies
+    pub async fn get_crate_version_sbom(&self, auth_data: &AuthData, package: &str, version: &str) -> Result<Sbom, ApiError> {
+        let analysis = self.check_crate_version_deps(auth_data, package, version).await?;
+        let index = self.service_index.get_crate_data(package).await?;
+        let index = index.iter().find(|meta| meta.vers == version).ok_or_else(error_not_found)?;
+        let metadata = self.service_storage.download_crate_metadata(package, version).await?;
+        let upload = self
+            .db_transaction_read(|app| async move { app.database.get_crate_version_upload(package, version).await })
+            .await?;
+        Ok(Sbom::new(
+            &self.configuration.load().web_public_uri,
+            index,
+            metadata.as_ref(),
+            upload,
+            &analysis,
+        ))
+    }
 }
 
 /// The application, running with a transaction
@@ -825,36 +4138,79 @@ pub(crate) struct ApplicationWithTransaction<'a> {
     pub(crate) application: &'a Application,
     /// The database access encapsulating a transaction
     pub(crate) database: Database,
+    /// The name of the operation for write transactions
+    pub(crate) operation: Option<&'static str>,
 }
 
-impl<'a> ApplicationWithTransaction<'a> {
+impl ApplicationWithTransaction<'_> {
     /// Attempts the authentication of a user
     async fn authenticate(&self, auth_data: &AuthData) -> Result<Authentication, ApiError> {
-        if let Some(token) = &auth_data.token {
-            self.authenticate_token(token).await
+        if self.operation.is_some() {
+            // all mutating operations on behalf of users authenticate in a write transaction
+            self.check_not_in_maintenance()?;
+        }
+        let mut authentication = if let Some(token) = &auth_data.token {
+            let authentication = self.authenticate_token(token).await?;
+            if let (Some(impersonator), Some(operation)) = (authentication.impersonator, self.operation) {
+                // tag all changes performed while impersonating
+                self.database
+                    .add_audit_entry(Some(authentication.uid()?), Some(impersonator), operation, "")
+                    .await?;
+            }
+            authentication
         } else {
             let authentication = auth_data.try_authenticate_cookie()?.ok_or_else(error_unauthorized)?;
             self.database.check_is_user(authentication.email()?).await?;
-            Ok(authentication)
+            authentication
+        };
+        if auth_data.token.is_none() || authentication.impersonator.is_some() {
+            // requests through the web application or while impersonating are accounted to the user
+            if let AuthenticationPrincipal::User { uid, .. } = &authentication.principal {
+                set_request_traffic_subject(TrafficSubject::User(*uid));
+            }
+        }
+        if let AuthenticationPrincipal::User { uid, .. } = &authentication.principal {
+            if authentication.can_write && self.database.get_is_readonly(*uid).await? {
+                authentication.can_write = false;
+            }
+        }
+        set_request_principal(&authentication.principal);
+        Ok(authentication)
+    }
+
+    /// Checks that the registry is not in read-only maintenance mode
+    fn check_not_in_maintenance(&self) -> Result<(), ApiError> {
+        let maintenance = self.application.maintenance.lock().unwrap();
+        if maintenance.enabled {
+            return Err(specialize(
+                error_service_unavailable(),
+                maintenance
+                    .message
+                    .clone()
+                    .unwrap_or_else(|| String::from("The registry is in read-only maintenance mode")),
+            ));
         }
+        Ok(())
     }
 
     /// Tries to authenticate using a token
     async fn authenticate_token(&self, token: &Token) -> Result<Authentication, ApiError> {
-        if token.id == self.application.configuration.self_service_login
-            && token.secret == self.application.configuration.self_service_token
-        {
+        let configuration = self.application.configuration.load();
+        if token.id == configuration.self_service_login && token.secret == configuration.self_service_token {
             // self authentication to read
             return Ok(Authentication::new_self());
         }
         let user = self
             .database
-            .check_token(&token.id, &token.secret, &|usage| async move {
-                self.application
-                    .app_events_sender
-                    .send(AppEvent::TokenUse(usage))
-                    .await
-                    .unwrap();
+            .check_token(&token.id, &token.secret, &|usage| {
+                set_request_traffic_subject(TrafficSubject::Token(usage.kind, usage.token_id));
+                async move {
+                    self.application
+                        .app_events_sender
+                        .send(AppEvent::TokenUse(usage))
+                        .await
+                        .unwrap();
+                }
             })
             .await?;
         Ok(user)
@@ -868,11 +4224,102 @@ impl<'a> ApplicationWithTransaction<'a> {
         Ok(principal_uid)
     }
 
+    /// Checks that the given authentication can manage the webhooks on a crate, or on the whole registry
+    async fn check_can_manage_webhook(&self, authentication: &Authentication, package: Option<&str>) -> Result<i64, ApiError> {
+        match package {
+            None => self.check_can_admin_registry(authentication).await,
+            Some(package) => {
+                authentication.check_can_write()?;
+                self.database.check_is_crate_manager(authentication.uid()?, package).await
+            }
+        }
+    }
+
+    /// Checks that the given authentication can read a crate, the restricted crates being hidden from the other users
+    async fn check_can_read_crate(&self, authentication: &Authentication, package: &str) -> Result<(), ApiError> {
+        match &authentication.principal {
+            AuthenticationPrincipal::User { uid, .. } => self.database.check_can_read_crate(*uid, package).await,
+            // the registry itself and the services can read all the crates
+            AuthenticationPrincipal::Service { .. } | AuthenticationPrincipal::SelfAuth => Ok(()),
+        }
+    }
+
+    /// Checks that the given authentication can read a version of a local crate,
+    /// the quarantined versions being only available to the owners and the registry itself
+    ///
+    /// Returns whether the version is only available to some users.
+    async fn check_can_read_crate_version(
+        &self,
+        authentication: &Authentication,
+        package: &str,
+        version: &str,
+    ) -> Result<bool, ApiError> {
+        if self.database.get_crate_version_quarantine(package, version).await?.is_some() {
+            if !matches!(authentication.principal, AuthenticationPrincipal::SelfAuth) {
+                self.database.check_is_crate_manager(authentication.uid()?, package).await?;
+            }
+            return Ok(true);
+        }
+        let is_restricted = self.database.get_crate_is_restricted(package).await?;
+        if is_restricted {
+            self.check_can_read_crate(authentication, package).await?;
+        }
+        Ok(is_restricted)
+    }
+
+    /// Gets the names of the restricted crates that the given authentication cannot read
+    async fn get_hidden_crates(&self, authentication: &Authentication) -> Result<HashSet<String>, ApiError> {
+        match &authentication.principal {
+            AuthenticationPrincipal::User { uid, .. } => self.database.get_hidden_crates(*uid).await,
+            AuthenticationPrincipal::Service { .. } | AuthenticationPrincipal::SelfAuth => Ok(HashSet::new()),
+        }
+    }
+
+    /// Gets the targets of a crate, or the host target of the registry when none is set
+    async fn get_crate_targets_or_default(&self, package: &str) -> Result<Vec<CrateInfoTarget>, ApiError> {
+        let mut targets = self.database.get_crate_targets(package).await?;
+        if targets.is_empty() {
+            targets.push(CrateInfoTarget {
+                target: self.application.configuration.load().self_toolchain_host.clone(),
+                docs_use_native: true,
+            });
+        }
+        Ok(targets)
+    }
+
+    /// Gets the lowercase names of the restricted crates that the given authentication cannot read,
+    /// to match the names given by clients in any case
+    async fn get_hidden_crates_lowercase(&self, authentication: &Authentication) -> Result<HashSet<String>, ApiError> {
+        let hidden = self.get_hidden_crates(authentication).await?;
+        Ok(hidden.iter().map(|name| name.to_ascii_lowercase()).collect())
+    }
+
+    /// Gets the versions that are not yanked yet and that match a bulk yank
+    async fn get_bulk_yank_versions(
+        &self,
+        query: &BulkYankQuery,
+        requirement: Option<&VersionReq>,
+    ) -> Result<Vec<CrateVersion>, ApiError> {
+        Ok(self
+            .database
+            .get_unyanked_crate_versions()
+            .await?
+            .into_iter()
+            .filter(|crate_version| {
+                query.matches_name(&crate_version.package)
+                    && requirement.is_none_or(|requirement| {
+                        Version::parse(&crate_version.version).is_ok_and(|version| requirement.matches(&version))
+                    })
+            })
+            .collect())
+    }
+
     /// Checks that the given authentication can manage a given crate
     async fn check_can_manage_crate(&self, authentication: &Authentication, package: &str) -> Result<i64, ApiError> {
         authentication.check_can_write()?;
         let principal_uid = authentication.uid()?;
         self.database.check_is_crate_manager(principal_uid, package).await?;
+        self.database.check_crate_not_frozen(package).await?;
         Ok(principal_uid)
     }
 }
@@ -0,0 +1,194 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Validation of the configuration without starting the registry
+
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+
+use axum::http::Uri;
+
+use crate::model::config::{get_var, load_config_file, Configuration, StorageConfig, CONFIG_FILE_VAR};
+use crate::services::storage::StorageImpl;
+use crate::utils::execute_git;
+
+/// The timeout for each check of the reachability of a remote service, in seconds
+const CHECK_TIMEOUT: u64 = 10;
+
+/// The minimum length of the secret for cookies, as required to derive the key
+const COOKIE_SECRET_MIN_LENGTH: usize = 64;
+
+/// The report of the checks, printed as they are executed
+#[derive(Debug, Default)]
+struct CheckReport {
+    /// The number of executed checks
+    checks: usize,
+    /// The number of failed checks
+    errors: usize,
+}
+
+impl CheckReport {
+    /// Records the result of a check
+    fn record(&mut self, name: &str, result: Result<String, String>) {
+        self.checks += 1;
+        match result {
+            Ok(details) => println!("[ok]    {name}: {details}"),
+            Err(details) => {
+                self.errors += 1;
+                println!("[error] {name}: {details}");
+            }
+        }
+    }
+}
+
+/// Loads and validates the configuration, checks the access to the remote services and prints a report
+///
+/// Returns whether all the checks succeeded.
+pub async fn main_check_config() -> bool {
+    let mut report = CheckReport::default();
+    if let Ok(path) = std::env::var(CONFIG_FILE_VAR) {
+        let result = load_config_file().map(|()| path).map_err(|e| e.to_string());
+        report.record("configuration file", result);
+    }
+    let configuration = match Configuration::from_env().await {
+        Ok(configuration) => {
+            report.record("configuration", Ok(String::from("loaded")));
+            configuration
+        }
+        Err(e) => {
+            report.record("configuration", Err(e.to_string()));
+            return print_summary(&report);
+        }
+    };
+
+    // use the same environment for git and the same outbound proxy as the registry
+    crate::utils::set_child_env(configuration.get_child_env());
+    match configuration.get_outbound_proxy() {
+        Ok(Some(proxy)) => crate::utils::set_outbound_proxy(proxy),
+        Ok(None) => {}
+        Err(e) => report.record("REGISTRY_OUTBOUND_PROXY", Err(e.to_string())),
+    }
+    check_uris(&mut report, &configuration);
+    report.record("REGISTRY_WEB_COOKIE_SECRET", check_cookie_secret());
+    report.record("storage", check_storage(&configuration).await);
+    check_oauth(&mut report, &configuration).await;
+    if let Some(remote) = &configuration.index.remote_origin {
+        report.record("git remote", check_git_remote(&configuration, remote).await);
+    }
+    print_summary(&report)
+}
+
+/// Prints the summary of the report and returns whether all the checks succeeded
+fn print_summary(report: &CheckReport) -> bool {
+    println!("{} checks, {} errors", report.checks, report.errors);
+    report.errors == 0
+}
+
+/// Checks that an URI is absolute and uses HTTP(S)
+fn check_uri(value: &str) -> Result<String, String> {
+    let uri = Uri::from_str(value).map_err(|e| format!("invalid URI {value}: {e}"))?;
+    match uri.scheme_str() {
+        Some("http" | "https") if uri.host().is_some_and(|host| !host.is_empty()) => Ok(value.to_string()),
+        _ => Err(format!("expected an absolute http(s) URI, got {value}")),
+    }
+}
+
+/// Checks all the URIs in the configuration
+fn check_uris(report: &mut CheckReport, configuration: &Configuration) {
+    report.record("REGISTRY_WEB_PUBLIC_URI", check_uri(&configuration.web_public_uri));
+    report.record("REGISTRY_OAUTH_LOGIN_URI", check_uri(&configuration.oauth_login_uri));
+    report.record("REGISTRY_OAUTH_CALLBACK_URI", check_uri(&configuration.oauth_callback_uri));
+    report.record("REGISTRY_OAUTH_TOKEN_URI", check_uri(&configuration.oauth_token_uri));
+    report.record("REGISTRY_OAUTH_USERINFO_URI", check_uri(&configuration.oauth_userinfo_uri));
+    if let StorageConfig::S3 { params, .. } = &configuration.storage {
+        report.record("REGISTRY_S3_URI", check_uri(&params.endpoint));
+    }
+    if configuration.proxy.enabled {
+        report.record("REGISTRY_PROXY_INDEX_URI", check_uri(&configuration.proxy.index_uri));
+        report.record("REGISTRY_PROXY_DOWNLOAD_URI", check_uri(&configuration.proxy.download_uri));
+    }
+    for (index, registry) in configuration.external_registries.iter().enumerate() {
+        let index = index + 1;
+        report.record(&format!("REGISTRY_EXTERNAL_{index}_INDEX"), check_uri(&registry.index));
+        report.record(&format!("REGISTRY_EXTERNAL_{index}_DOCS"), check_uri(&registry.docs_root));
+    }
+}
+
+/// Checks that the secret for cookies is set and long enough
+fn check_cookie_secret() -> Result<String, String> {
+    let secret = get_var("REGISTRY_WEB_COOKIE_SECRET").map_err(|e| e.to_string())?;
+    if secret.len() < COOKIE_SECRET_MIN_LENGTH {
+        return Err(format!(
+            "expected at least {COOKIE_SECRET_MIN_LENGTH} bytes, got {}",
+            secret.len()
+        ));
+    }
+    Ok(String::from("set"))
+}
+
+/// Checks that the storage can be accessed
+async fn check_storage(configuration: &Configuration) -> Result<String, String> {
+    let storage = StorageImpl::from(configuration);
+    let check = tokio::time::timeout(Duration::from_secs(CHECK_TIMEOUT), storage.check_access());
+    match check.await {
+        Ok(Ok(())) => Ok(match &configuration.storage {
+            StorageConfig::FileSystem => format!("file system at {}", configuration.data_dir),
+            StorageConfig::S3 { params, bucket } => format!("S3 bucket {bucket} at {}", params.endpoint),
+        }),
+        Ok(Err(e)) => Err(e.to_string()),
+        Err(_) => Err(format!("timed out after {CHECK_TIMEOUT}s")),
+    }
+}
+
+/// Checks that the OAuth identity provider can be reached
+async fn check_oauth(report: &mut CheckReport, configuration: &Configuration) {
+    let client = match crate::utils::http_client_builder()
+        .timeout(Duration::from_secs(CHECK_TIMEOUT))
+        .build()
+    {
+        Ok(client) => client,
+        Err(e) => {
+            report.record("OAuth", Err(e.to_string()));
+            return;
+        }
+    };
+    for (name, uri) in [
+        ("OAuth token endpoint", &configuration.oauth_token_uri),
+        ("OAuth userinfo endpoint", &configuration.oauth_userinfo_uri),
+    ] {
+        // any response, even an error because the request is not authenticated, means the endpoint is reachable
+        let result = match client.get(uri).send().await {
+            Ok(response) => Ok(format!("{uri} responded with {}", response.status())),
+            Err(e) => Err(format!("{uri} is unreachable: {e}")),
+        };
+        report.record(name, result);
+    }
+}
+
+/// Checks that the remote for the git index can be accessed
+async fn check_git_remote(configuration: &Configuration, remote: &str) -> Result<String, String> {
+    if let Some(key_filename) = configuration.index.get_remote_ssh_key_path() {
+        if !key_filename.exists() {
+            return Err(format!("missing key file: {}", key_filename.display()));
+        }
+    }
+    // when the index is already cloned, go through its origin so that its local git configuration also applies
+    let location = Path::new(&configuration.index.location);
+    let (location, target) = if location.join(".git").exists() {
+        (location, "origin")
+    } else {
+        (Path::new(&configuration.index.home_dir), remote)
+    };
+    let args = ["ls-remote", "--heads", target];
+    let check = tokio::time::timeout(Duration::from_secs(CHECK_TIMEOUT), execute_git(location, &args));
+    match check.await {
+        Ok(Ok(())) => Ok(format!("{remote} is accessible")),
+        Ok(Err(e)) => Err(format!(
+            "{remote} is not accessible: {}",
+            e.details.as_deref().unwrap_or(&e.message).trim()
+        )),
+        Err(_) => Err(format!("timed out after {CHECK_TIMEOUT}s")),
+    }
+}
@@ -0,0 +1,348 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! GraphQL API over the registry data
+//!
+//! The resolvers go through the application so that the same authentication and authorization rules apply as for the REST API.
+
+use std::sync::Arc;
+
+use async_graphql::{ComplexObject, Context, EmptyMutation, EmptySubscription, Object, Result, Schema, SimpleObject};
+use chrono::{NaiveDate, NaiveDateTime};
+use tokio::sync::OnceCell;
+
+use crate::application::Application;
+use crate::model::cargo::RegistryUser;
+use crate::model::packages::{CrateInfo, CrateInfoVersion, CrateInfoVersionDocs};
+use crate::model::search::{SearchFilters, SearchPagination};
+use crate::utils::axum::auth::AuthData;
+
+/// The maximum depth of a query
+const MAX_DEPTH: usize = 8;
+
+/// The maximum complexity of a query, as the number of requested fields
+const MAX_COMPLEXITY: usize = 256;
+
+/// The GraphQL schema for the registry
+pub type RegistrySchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+/// Builds the GraphQL schema
+///
+/// The application and the authentication data for the request are expected as data for each request.
+#[must_use]
+pub fn build_schema() -> RegistrySchema {
+    Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .limit_depth(MAX_DEPTH)
+        .limit_complexity(MAX_COMPLEXITY)
+        .finish()
+}
+
+/// Gets the application and the authentication data for the current request
+fn request_data<'a>(ctx: &Context<'a>) -> (&'a Arc<Application>, &'a AuthData) {
+    (ctx.data_unchecked::<Arc<Application>>(), ctx.data_unchecked::<AuthData>())
+}
+
+/// The root for queries
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    /// Gets the current user
+    async fn me(&self, ctx: &Context<'_>) -> Result<User> {
+        let (application, auth_data) = request_data(ctx);
+        Ok(application.get_current_user(auth_data).await?.into())
+    }
+
+    /// Gets a crate by its name
+    #[graphql(name = "crate")]
+    async fn get_crate(&self, ctx: &Context<'_>, name: String) -> Result<Crate> {
+        let (application, auth_data) = request_data(ctx);
+        let info = application.get_crate_info(auth_data, &name).await?;
+        Ok(Crate {
+            name,
+            info: OnceCell::new_with(Some(info)),
+        })
+    }
+
+    /// Searches for crates
+    #[allow(clippy::too_many_arguments)]
+    async fn crates(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default)] query: String,
+        keyword: Option<String>,
+        category: Option<String>,
+        owner: Option<String>,
+        page: Option<usize>,
+        per_page: Option<usize>,
+    ) -> Result<CratePage> {
+        let (application, auth_data) = request_data(ctx);
+        let results = application
+            .search_crates(
+                auth_data,
+                &query,
+                &SearchFilters {
+                    keyword,
+                    category,
+                    owner,
+                    deprecated: None,
+                    include_yanked: false,
+                },
+                SearchPagination::new(page, per_page, None),
+            )
+            .await?;
+        Ok(CratePage {
+            total: results.meta.total,
+            crates: results
+                .crates
+                .into_iter()
+                .map(|result| Crate {
+                    name: result.name,
+                    info: OnceCell::new(),
+                })
+                .collect(),
+        })
+    }
+}
+
+/// A page of crates
+#[derive(SimpleObject)]
+pub struct CratePage {
+    /// The total number of matching crates
+    total: usize,
+    /// The crates on this page
+    crates: Vec<Crate>,
+}
+
+/// A crate, whose data is loaded when first requested
+pub struct Crate {
+    /// The name of the crate
+    name: String,
+    /// The information about the crate, once loaded
+    info: OnceCell<CrateInfo>,
+}
+
+impl Crate {
+    /// Gets the information about the crate, loading it if necessary
+    async fn info(&self, ctx: &Context<'_>) -> Result<&CrateInfo> {
+        let (application, auth_data) = request_data(ctx);
+        Ok(self
+            .info
+            .get_or_try_init(|| application.get_crate_info(auth_data, &self.name))
+            .await?)
+    }
+}
+
+#[Object]
+impl Crate {
+    /// The name of the crate
+    async fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The description of the crate, from the last version
+    async fn description(&self, ctx: &Context<'_>) -> Result<Option<String>> {
+        let info = self.info(ctx).await?;
+        Ok(info.metadata.as_ref().and_then(|metadata| metadata.description.clone()))
+    }
+
+    /// Whether the entire crate is deprecated
+    async fn is_deprecated(&self, ctx: &Context<'_>) -> Result<bool> {
+        Ok(self.info(ctx).await?.is_deprecated)
+    }
+
+    /// Whether the crate is frozen, preventing publications and changes
+    async fn is_frozen(&self, ctx: &Context<'_>) -> Result<bool> {
+        Ok(self.info(ctx).await?.is_frozen)
+    }
+
+    /// Whether a crate with the same name exists on crates.io, exposing its users to dependency confusion
+    async fn is_claimed_upstream(&self, ctx: &Context<'_>) -> Result<bool> {
+        Ok(self.info(ctx).await?.is_claimed_upstream)
+    }
+
+    /// The total number of downloads for all the versions
+    async fn downloads(&self, ctx: &Context<'_>) -> Result<i64> {
+        Ok(self.info(ctx).await?.downloads)
+    }
+
+    /// The number of downloads for all the versions over the last days
+    async fn recent_downloads(&self, ctx: &Context<'_>) -> Result<i64> {
+        Ok(self.info(ctx).await?.recent_downloads)
+    }
+
+    /// The number of users that starred the crate
+    async fn stars(&self, ctx: &Context<'_>) -> Result<i64> {
+        Ok(self.info(ctx).await?.stars)
+    }
+
+    /// The build targets for the documentation and the analysis of dependencies
+    async fn targets(&self, ctx: &Context<'_>) -> Result<Vec<String>> {
+        Ok(self
+            .info(ctx)
+            .await?
+            .targets
+            .iter()
+            .map(|target| target.target.clone())
+            .collect())
+    }
+
+    /// The versions of the crate, the most recent first
+    async fn versions(&self, ctx: &Context<'_>) -> Result<Vec<Version>> {
+        Ok(self.info(ctx).await?.versions.iter().rev().map(Version::from).collect())
+    }
+
+    /// Gets a single version of the crate
+    async fn version(&self, ctx: &Context<'_>, num: String) -> Result<Option<Version>> {
+        Ok(self
+            .info(ctx)
+            .await?
+            .versions
+            .iter()
+            .find(|version| version.index.vers == num)
+            .map(Version::from))
+    }
+
+    /// The owners of the crate
+    async fn owners(&self, ctx: &Context<'_>) -> Result<Vec<User>> {
+        let (application, auth_data) = request_data(ctx);
+        application.check_can_read_crate(auth_data, &self.name).await?;
+        let owners = application.get_crate_owners(auth_data, &self.name).await?;
+        Ok(owners.users.into_iter().map(User::from).collect())
+    }
+
+    /// The daily downloads of each version over the last days, only for days with downloads
+    async fn daily_downloads(&self, ctx: &Context<'_>) -> Result<Vec<DailyDownloads>> {
+        let (application, auth_data) = request_data(ctx);
+        application.check_can_read_crate(auth_data, &self.name).await?;
+        let downloads = application.get_crate_downloads(auth_data, &self.name).await?;
+        Ok(downloads
+            .version_downloads
+            .into_iter()
+            .map(|entry| DailyDownloads {
+                version: entry.num,
+                date: entry.date,
+                downloads: entry.downloads,
+            })
+            .collect())
+    }
+}
+
+/// A version of a crate
+#[derive(SimpleObject)]
+pub struct Version {
+    /// The version number
+    num: String,
+    /// The SHA256 checksum of the `.crate` file
+    checksum: String,
+    /// Whether this version has been yanked
+    yanked: bool,
+    /// The timestamp of the publication
+    published_at: NaiveDateTime,
+    /// The user that published this version
+    published_by: User,
+    /// The number of times this version was downloaded
+    downloads: i64,
+    /// The number of times this version was downloaded over the last days
+    recent_downloads: i64,
+    /// Whether this version has outdated dependencies
+    deps_has_outdated: bool,
+    /// Whether vulnerabilities were reported against dependencies of this version
+    deps_has_cves: bool,
+    /// The status of the documentation for each target
+    docs: Vec<DocsStatus>,
+}
+
+impl From<&CrateInfoVersion> for Version {
+    fn from(version: &CrateInfoVersion) -> Self {
+        Self {
+            num: version.index.vers.clone(),
+            checksum: version.index.cksum.clone(),
+            yanked: version.index.yanked,
+            published_at: version.upload,
+            published_by: version.uploaded_by.clone().into(),
+            downloads: version.downloads,
+            recent_downloads: version.recent_downloads,
+            deps_has_outdated: version.deps_has_outdated,
+            deps_has_cves: version.deps_has_cves,
+            docs: version.docs.iter().map(DocsStatus::from).collect(),
+        }
+    }
+}
+
+/// The status of the documentation of a version for a target
+#[derive(SimpleObject)]
+pub struct DocsStatus {
+    /// The target
+    target: String,
+    /// Whether the generation of the documentation was attempted
+    is_attempted: bool,
+    /// Whether the documentation was generated
+    is_present: bool,
+    /// The total number of documentable items, if the coverage could be computed
+    coverage_total: Option<i64>,
+    /// The number of documented items, if the coverage could be computed
+    coverage_documented: Option<i64>,
+}
+
+impl From<&CrateInfoVersionDocs> for DocsStatus {
+    fn from(docs: &CrateInfoVersionDocs) -> Self {
+        Self {
+            target: docs.target.clone(),
+            is_attempted: docs.is_attempted,
+            is_present: docs.is_present,
+            coverage_total: docs.coverage.as_ref().map(|coverage| coverage.total),
+            coverage_documented: docs.coverage.as_ref().map(|coverage| coverage.documented),
+        }
+    }
+}
+
+/// The downloads of a version on a day
+#[derive(SimpleObject)]
+pub struct DailyDownloads {
+    /// The version number
+    version: String,
+    /// The day
+    date: NaiveDate,
+    /// The number of downloads on this day
+    downloads: u32,
+}
+
+/// A user of the registry
+#[derive(SimpleObject)]
+#[graphql(complex)]
+pub struct User {
+    /// The unique identifier
+    id: i64,
+    /// The login of the user
+    login: String,
+    /// The user's name
+    name: String,
+    /// The email of the user
+    #[graphql(skip)]
+    email: String,
+}
+
+#[ComplexObject]
+impl User {
+    /// The email of the user, only visible to the user themselves and to the administrators
+    async fn email(&self, ctx: &Context<'_>) -> Result<Option<String>> {
+        let (application, auth_data) = request_data(ctx);
+        Ok(application
+            .can_see_user_private_data(auth_data, self.id)
+            .await?
+            .then(|| self.email.clone()))
+    }
+}
+
+impl From<RegistryUser> for User {
+    fn from(user: RegistryUser) -> Self {
+        Self {
+            id: user.id,
+            login: user.login,
+            name: user.name,
+            email: user.email,
+        }
+    }
+}
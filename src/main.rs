@@ -12,21 +12,45 @@ use std::net::SocketAddr;
 use std::pin::pin;
 use std::str::FromStr;
 use std::sync::Arc;
+use std::time::Duration;
 
 use axum::extract::DefaultBodyLimit;
+use axum::handler::Handler;
+use axum::middleware;
 use axum::routing::{delete, get, patch, post, put};
 use axum::Router;
 use cookie::Key;
-use log::info;
+use log::{error, info, warn};
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+use tower::util::option_layer;
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
 
 use crate::application::Application;
+use crate::model::config::{get_var, is_valid_tenant_name, load_config_file, with_tenant, Configuration};
 use crate::routes::AxumState;
 use crate::services::ServiceProvider;
-use crate::utils::sigterm::waiting_sigterm;
+use crate::utils::apierror::{error_invalid_request, specialize, ApiError};
+use crate::utils::axum::compression::compression_layer;
+use crate::utils::axum::cors::cors_layer;
+use crate::utils::axum::etag::conditional_get;
+use crate::utils::axum::ratelimit::{rate_limit, RateLimits};
+use crate::utils::axum::security::{security_headers, SecurityHeaders};
+use crate::utils::axum::unix::serve_unix;
+use crate::utils::context::{format_log_line, track_request_context, with_request_context};
+use crate::utils::sigterm::{sigterm, waiting_sigterm};
+use crate::utils::telemetry::{setup_tracing, trace_request};
 
+pub mod admin;
 pub mod application;
+pub mod check;
+pub mod graphql;
 pub mod migrations;
 pub mod model;
+pub mod openapi;
+pub mod restore;
 pub mod routes;
 pub mod services;
 pub mod utils;
@@ -44,24 +68,77 @@ pub const GIT_HASH: &str = env!("GIT_HASH");
 pub const GIT_TAG: &str = env!("GIT_TAG");
 
 /// Main payload for serving the application
-async fn main_serve_app(application: Arc<Application>, cookie_key: Key) -> Result<(), std::io::Error> {
+///
+/// The tenants are served under their own prefix, each with its own application and key for cookies.
+/// When `shutdown` is cancelled, new connections are no longer accepted and the in-flight requests are drained.
+async fn main_serve_app(
+    application: Arc<Application>,
+    cookie_key: Key,
+    tenants: Vec<(Arc<Application>, Key)>,
+    shutdown: CancellationToken,
+) -> Result<(), std::io::Error> {
+    let configuration = application.configuration.load_full();
+    let body_limit = configuration.web_body_limit;
+    let socket_addr = SocketAddr::new(configuration.web_listenon_ip, configuration.web_listenon_port);
+    let socket_path = configuration.web_listenon_socket.clone();
+    let socket_mode = configuration.web_listenon_socket_mode;
+    let trusted_proxies = Arc::new(configuration.web_trusted_proxies.clone());
+    let compression = compression_layer(&configuration.web_compression);
+    let security = Arc::new(SecurityHeaders::new(&configuration.web_security_headers));
+    let mut app = registry_router(application, cookie_key);
+    for (tenant, cookie_key) in tenants {
+        let prefix = tenant.configuration.load().get_path_prefix();
+        app = app.nest(&prefix, registry_router(tenant, cookie_key));
+    }
+    let app = app
+        .layer(compression)
+        .layer(middleware::from_fn_with_state(security, security_headers))
+        .layer(middleware::from_fn(trace_request))
+        .layer(middleware::from_fn_with_state(trusted_proxies, track_request_context))
+        .layer(DefaultBodyLimit::max(body_limit));
+    if let Some(socket_path) = socket_path {
+        return serve_unix(&socket_path, socket_mode, app, shutdown).await;
+    }
+    axum::serve(
+        tokio::net::TcpListener::bind(socket_addr)
+            .await
+            .unwrap_or_else(|_| panic!("failed to bind {socket_addr}")),
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(shutdown.cancelled_owned())
+    .await
+}
+
+/// Builds the router for the routes of a registry, the main one or a tenant
+fn registry_router(application: Arc<Application>, cookie_key: Key) -> Router {
     // web application
     let webapp_resources = webapp::get_resources();
-    let body_limit = application.configuration.web_body_limit;
-    let socket_addr = SocketAddr::new(
-        application.configuration.web_listenon_ip,
-        application.configuration.web_listenon_port,
-    );
+    let configuration = application.configuration.load_full();
+    let cors = cors_layer(&configuration.web_cors);
+    let rate_limits = RateLimits::new(&configuration.web_rate_limit, &configuration.redis);
     let state = Arc::new(AxumState {
         application,
         cookie_key,
         webapp_resources,
+        graphql_schema: graphql::build_schema(),
     });
-    let app = Router::new()
+    Router::new()
         .route("/", get(routes::get_root))
         // special handling for git
         .route("/info/refs", get(routes::index_serve_info_refs))
         .route("/git-upload-pack", post(routes::index_serve_git_upload_pack))
+        // sparse index for the proxied registries
+        .route(
+            "/index/crates-io/*path",
+            get(routes::index_serve_crates_io).layer(middleware::from_fn(conditional_get)),
+        )
+        .route(
+            "/proxy/:registry/*path",
+            get(routes::index_serve_proxy).layer(middleware::from_fn(conditional_get)),
+        )
+        // liveness and readiness probes
+        .route("/healthz", get(routes::get_liveness))
+        .route("/readyz", get(routes::get_readiness))
         // web resources
         .route("/favicon.png", get(routes::get_favicon))
         .route("/crates/:package/:version", get(routes::get_redirection_crate_version))
@@ -71,24 +148,86 @@ async fn main_serve_app(application: Arc<Application>, cookie_key: Key) -> Resul
         .route("/me", get(routes::webapp_me))
         // serve the documentation
         .route("/docs/*path", get(routes::get_docs_resource))
+        // badges
+        .nest(
+            "/badges/:package",
+            Router::new()
+                .route("/docs-coverage.svg", get(routes::get_badge_docs_coverage))
+                .route("/version.svg", get(routes::get_badge_version))
+                .route("/docs.svg", get(routes::get_badge_docs_status))
+                .route("/downloads.svg", get(routes::get_badge_downloads))
+                .route_layer(middleware::from_fn_with_state(state.clone(), routes::check_crate_visibility)),
+        )
         // API
+        .nest(
+            "/api/v2",
+            Router::new()
+                .route("/me/tokens", get(routes::api_v2_get_user_tokens))
+                .route("/admin/users", get(routes::api_v2_get_users))
+                .route("/admin/audit", get(routes::api_v2_get_audit_log))
+                .route("/admin/tokens", get(routes::api_v2_get_global_tokens))
+                .route("/crates/undocumented", get(routes::api_v2_get_crates_undocumented))
+                .route("/crates/outdated", get(routes::api_v2_get_crates_outdated_heads))
+                .layer(option_layer(cors.clone())),
+        )
         .nest(
             "/api/v1",
             Router::new()
                 .route("/version", get(routes::get_version))
                 .route("/registry-information", get(routes::api_v1_get_registry_information))
+                .route("/maintenance", get(routes::api_v1_get_maintenance))
+                .route("/announcement", get(routes::api_v1_get_current_announcement))
                 .nest(
                     "/me",
-                    Router::new().route("/", get(routes::api_v1_get_current_user)).nest(
-                        "/tokens",
-                        Router::new()
-                            .route("/", get(routes::api_v1_get_user_tokens))
-                            .route("/", put(routes::api_v1_create_user_token))
-                            .route("/:token_id", delete(routes::api_v1_revoke_user_token)),
-                    ),
+                    Router::new()
+                        .route("/", get(routes::api_v1_get_current_user))
+                        .route("/", patch(routes::api_v1_update_current_user))
+                        .route("/quota", get(routes::api_v1_get_current_user_quota))
+                        .route("/quota/teams", get(routes::api_v1_get_current_user_team_quotas))
+                        .route("/preferences", get(routes::api_v1_get_current_user_preferences))
+                        .route("/preferences", put(routes::api_v1_set_current_user_preferences))
+                        .route("/watches", get(routes::api_v1_get_watched_crates))
+                        .route("/stars", get(routes::api_v1_get_starred_crates))
+                        .route("/notifications", get(routes::api_v1_get_user_notifications))
+                        .route("/notifications/read", post(routes::api_v1_mark_user_notifications_read))
+                        .nest(
+                            "/tokens",
+                            Router::new()
+                                .route("/", get(routes::api_v1_get_user_tokens))
+                                .route("/", put(routes::api_v1_create_user_token))
+                                .route("/:token_id", delete(routes::api_v1_revoke_user_token)),
+                        ),
                 )
                 .route("/oauth/code", post(routes::api_v1_login_with_oauth_code))
                 .route("/logout", post(routes::api_v1_logout))
+                .route("/vendor", post(routes::api_v1_export_vendor))
+                .route("/audit/lockfile", post(routes::api_v1_audit_lock_file))
+                .route(
+                    "/proxy/:registry/crates/:package/:version/download",
+                    get(routes::api_v1_download_proxied_crate).layer(middleware::from_fn_with_state(
+                        (rate_limits.downloads.clone(), state.clone()),
+                        rate_limit::<AxumState>,
+                    )),
+                )
+                .route("/graphql", post(routes::api_v1_graphql))
+                .route("/feeds/versions", get(routes::api_v1_get_feed_versions))
+                .route("/feeds/owners/:login", get(routes::api_v1_get_feed_owner))
+                .nest(
+                    "/webhooks",
+                    Router::new()
+                        .route("/", get(routes::api_v1_get_webhooks))
+                        .route("/", put(routes::api_v1_create_webhook))
+                        .route("/:webhook_id", delete(routes::api_v1_delete_webhook))
+                        .route("/:webhook_id/deliveries", get(routes::api_v1_get_webhook_deliveries))
+                        .route(
+                            "/:webhook_id/deliveries/:delivery_id/redeliver",
+                            post(routes::api_v1_redeliver_webhook_delivery),
+                        ),
+                )
+                .route(
+                    "/jobs/docgen/:job_id/updates",
+                    get(routes::api_v1_get_doc_gen_job_updates_for),
+                )
                 .nest(
                     "/admin",
                     Router::new()
@@ -98,9 +237,37 @@ async fn main_serve_app(application: Arc<Application>, cookie_key: Key) -> Resul
                                 .route("/", get(routes::api_v1_get_users))
                                 .route("/:target", patch(routes::api_v1_update_user))
                                 .route("/:target", delete(routes::api_v1_delete_user))
+                                .route("/:target/quota", get(routes::api_v1_get_user_quota))
+                                .route("/:target/quota", put(routes::api_v1_set_user_quota))
                                 .route("/:target/deactivate", post(routes::api_v1_deactivate_user))
-                                .route("/:target/reactivate", post(routes::api_v1_reactivate_user)),
+                                .route("/:target/reactivate", post(routes::api_v1_reactivate_user))
+                                .route("/:target/impersonate", post(routes::api_v1_impersonate_user)),
                         )
+                        .route("/teams/:team/quota", get(routes::api_v1_get_team_quota))
+                        .route("/teams/:team/quota", put(routes::api_v1_set_team_quota))
+                        .route("/impersonations/:impersonation_id", delete(routes::api_v1_end_impersonation))
+                        .route("/audit", get(routes::api_v1_get_audit_log))
+                        .route("/maintenance", put(routes::api_v1_set_maintenance))
+                        .route("/announcement", get(routes::api_v1_get_announcement))
+                        .route("/announcement", put(routes::api_v1_set_announcement))
+                        .route("/announcement", delete(routes::api_v1_clear_announcement))
+                        .route("/config/reload", post(routes::api_v1_reload_configuration))
+                        .route("/export", get(routes::api_v1_export_registry))
+                        .route("/stats", get(routes::api_v1_get_registry_stats))
+                        .route("/storage", get(routes::api_v1_get_storage_usage))
+                        .route("/activity", get(routes::api_v1_get_publish_activity))
+                        .route("/traffic", get(routes::api_v1_get_traffic_report))
+                        .route("/advisories", get(routes::api_v1_get_advisories_report))
+                        .route("/licenses", get(routes::api_v1_get_license_inventory))
+                        .route("/quarantine", get(routes::api_v1_get_quarantined_versions))
+                        .route(
+                            "/quarantine/:package/:version/release",
+                            post(routes::api_v1_release_quarantined_version),
+                        )
+                        .route("/index/resync", post(routes::api_v1_resync_index_remote))
+                        .route("/yank", post(routes::api_v1_bulk_yank))
+                        .route("/provisioning", get(routes::api_v1_get_provisioning_rules))
+                        .route("/provisioning", put(routes::api_v1_set_provisioning_rules))
                         .nest(
                             "/tokens",
                             Router::new()
@@ -111,6 +278,12 @@ async fn main_serve_app(application: Arc<Application>, cookie_key: Key) -> Resul
                         .route("/jobs/docgen", get(routes::api_v1_get_doc_gen_jobs))
                         .route("/jobs/docgen/updates", get(routes::api_v1_get_doc_gen_job_updates))
                         .route("/jobs/docgen/:job_id/log", get(routes::api_v1_get_doc_gen_job_log))
+                        .route("/jobs/reindex", get(routes::api_v1_get_search_reindex_progress))
+                        .route("/jobs/reindex", post(routes::api_v1_launch_search_reindex))
+                        .route("/jobs/backup", get(routes::api_v1_get_backup_status))
+                        .route("/jobs/backup", post(routes::api_v1_launch_backup))
+                        .route("/jobs/scheduled", get(routes::api_v1_get_scheduled_jobs))
+                        .route("/jobs/scheduled/:name", post(routes::api_v1_run_scheduled_job))
                         .route("/workers", get(routes::api_v1_get_workers))
                         .route("/workers/updates", get(routes::api_v1_get_workers_updates))
                         .route("/workers/connect", get(routes::api_v1_worker_connect)),
@@ -118,20 +291,113 @@ async fn main_serve_app(application: Arc<Application>, cookie_key: Key) -> Resul
                 .nest(
                     "/crates",
                     Router::new()
-                        .route("/", get(routes::api_v1_cargo_search))
+                        .route(
+                            "/",
+                            get(routes::api_v1_cargo_search).layer(middleware::from_fn_with_state(
+                                (rate_limits.search.clone(), state.clone()),
+                                rate_limit::<AxumState>,
+                            )),
+                        )
+                        .route(
+                            "/suggest",
+                            get(routes::api_v1_suggest_crates).layer(middleware::from_fn_with_state(
+                                (rate_limits.search.clone(), state.clone()),
+                                rate_limit::<AxumState>,
+                            )),
+                        )
                         .route("/stats", get(routes::api_v1_get_crates_stats))
+                        .route("/bulk", post(routes::api_v1_get_crates_metadata))
                         .route("/undocumented", get(routes::api_v1_get_crates_undocumented))
                         .route("/outdated", get(routes::api_v1_get_crates_outdated_heads))
-                        .route("/new", put(routes::api_v1_cargo_publish_crate_version))
-                        .route("/:package", get(routes::api_v1_get_crate_info))
-                        .route("/:package/readme", get(routes::api_v1_get_crate_last_readme))
-                        .route("/:package/:version/readme", get(routes::api_v1_get_crate_readme))
-                        .route("/:package/:version/download", get(routes::api_v1_download_crate))
+                        .route(
+                            "/new",
+                            put(routes::api_v1_cargo_publish_crate_version).layer(middleware::from_fn_with_state(
+                                (rate_limits.publish.clone(), state.clone()),
+                                rate_limit::<AxumState>,
+                            )),
+                        )
+                        .route(
+                            "/:package",
+                            get(routes::api_v1_get_crate_info).layer(middleware::from_fn(conditional_get)),
+                        )
+                        .route(
+                            "/:package/readme",
+                            get(routes::api_v1_get_crate_last_readme).layer(middleware::from_fn(conditional_get)),
+                        )
+                        .route(
+                            "/:package/readme/html",
+                            get(routes::api_v1_get_crate_last_readme_html).layer(middleware::from_fn(conditional_get)),
+                        )
+                        .route(
+                            "/:package/index",
+                            get(routes::api_v1_get_crate_index_file).layer(middleware::from_fn(conditional_get)),
+                        )
+                        .route("/:package/feed", get(routes::api_v1_get_crate_feed))
+                        .route(
+                            "/:package/diff",
+                            get(routes::api_v1_get_crate_diff).layer(middleware::from_fn(conditional_get)),
+                        )
+                        .route(
+                            "/:package/versions",
+                            get(routes::api_v1_get_crate_versions).layer(middleware::from_fn(conditional_get)),
+                        )
+                        .route(
+                            "/:package/:version",
+                            get(routes::api_v1_get_crate_version).layer(middleware::from_fn(conditional_get)),
+                        )
+                        .route("/:package/watch", put(routes::api_v1_watch_crate))
+                        .route("/:package/watch", delete(routes::api_v1_unwatch_crate))
+                        .route("/:package/star", put(routes::api_v1_star_crate))
+                        .route("/:package/star", delete(routes::api_v1_unstar_crate))
+                        .route(
+                            "/:package/:version/readme",
+                            get(routes::api_v1_get_crate_readme).layer(middleware::from_fn(conditional_get)),
+                        )
+                        .route(
+                            "/:package/:version/readme/html",
+                            get(routes::api_v1_get_crate_readme_html).layer(middleware::from_fn(conditional_get)),
+                        )
+                        .route(
+                            "/:package/:version/download",
+                            get(routes::api_v1_download_crate).layer(middleware::from_fn_with_state(
+                                (rate_limits.downloads.clone(), state.clone()),
+                                rate_limit::<AxumState>,
+                            )),
+                        )
+                        .route("/:package/:version/binstall", get(routes::api_v1_get_crate_binstall))
+                        .route(
+                            "/:package/:version/binaries/:target",
+                            get(routes::api_v1_download_crate_binary),
+                        )
+                        .route("/:package/:version/binaries/:target", put(routes::api_v1_upload_crate_binary))
+                        .route(
+                            "/:package/:version/files",
+                            get(routes::api_v1_get_crate_files).layer(middleware::from_fn(conditional_get)),
+                        )
+                        .route(
+                            "/:package/:version/files/*path",
+                            get(routes::api_v1_get_crate_file).layer(middleware::from_fn(conditional_get)),
+                        )
                         .route("/:package/:version/yank", delete(routes::api_v1_cargo_yank))
                         .route("/:package/:version/unyank", put(routes::api_v1_cargo_unyank))
                         .route("/:package/:version/docsregen", post(routes::api_v1_regen_crate_version_doc))
                         .route("/:package/:version/checkdeps", get(routes::api_v1_check_crate_version))
+                        .route("/:package/:version/sbom", get(routes::api_v1_get_crate_version_sbom))
+                        .route(
+                            "/:package/:version/checksums",
+                            get(routes::api_v1_get_crate_version_checksums),
+                        )
+                        .route(
+                            "/:package/:version/provenance",
+                            put(routes::api_v1_set_crate_version_provenance),
+                        )
+                        .route("/:package/:version/docscoverage", get(routes::api_v1_get_crate_docs_coverage))
                         .route("/:package/dlstats", get(routes::api_v1_get_crate_dl_stats))
+                        .route("/:package/downloads", get(routes::api_v1_get_crate_downloads))
+                        .route(
+                            "/:package/reverse_dependencies",
+                            get(routes::api_v1_get_crate_reverse_dependencies),
+                        )
                         .route("/:package/owners", get(routes::api_v1_cargo_get_crate_owners))
                         .route("/:package/owners", put(routes::api_v1_cargo_add_crate_owners))
                         .route("/:package/owners", delete(routes::api_v1_cargo_remove_crate_owners))
@@ -142,29 +408,34 @@ async fn main_serve_app(application: Arc<Application>, cookie_key: Key) -> Resul
                             "/:package/capabilities",
                             patch(routes::api_v1_set_crate_required_capabilities),
                         )
-                        .route("/:package/deprecated", patch(routes::api_v1_set_crate_deprecation)),
-                ),
+                        .route("/:package/deprecated", patch(routes::api_v1_set_crate_deprecation))
+                        .route("/:package/frozen", patch(routes::api_v1_set_crate_frozen))
+                        .route("/:package/visibility", get(routes::api_v1_get_crate_visibility))
+                        .route("/:package/visibility", patch(routes::api_v1_set_crate_visibility))
+                        .route_layer(middleware::from_fn_with_state(state.clone(), routes::check_crate_visibility)),
+                )
+                .layer(option_layer(cors)),
         )
+        // OpenAPI specification and Swagger UI
+        .merge(SwaggerUi::new(openapi::DOCS_PATH).url(openapi::DOCS_SPEC_PATH, openapi::ApiDoc::openapi()))
         // fall back to serving the index
-        .fallback(routes::index_serve)
-        .layer(DefaultBodyLimit::max(body_limit))
-        .with_state(state);
-    axum::serve(
-        tokio::net::TcpListener::bind(socket_addr)
-            .await
-            .unwrap_or_else(|_| panic!("failed to bind {socket_addr}")),
-        app.into_make_service_with_connect_info::<SocketAddr>(),
-    )
-    .await
+        .fallback(routes::index_serve.layer(middleware::from_fn(conditional_get)))
+        .layer(middleware::from_fn_with_state(state.clone(), routes::account_traffic))
+        .with_state(state)
 }
 
 fn setup_log() {
-    let log_date_time_format =
-        std::env::var("REGISTRY_LOG_DATE_TIME_FORMAT").unwrap_or_else(|_| String::from("[%Y-%m-%d %H:%M:%S]"));
+    let log_date_time_format = get_var("REGISTRY_LOG_DATE_TIME_FORMAT").unwrap_or_else(|_| String::from("[%Y-%m-%d %H:%M:%S]"));
 
-    let log_level = std::env::var("REGISTRY_LOG_LEVEL")
-        .map(|v| log::LevelFilter::from_str(&v).expect("invalid REGISTRY_LOG_LEVEL"))
-        .unwrap_or(log::LevelFilter::Info);
+    let log_level = get_var("REGISTRY_LOG_LEVEL").map_or(log::LevelFilter::Info, |v| {
+        log::LevelFilter::from_str(&v).expect("invalid REGISTRY_LOG_LEVEL")
+    });
+
+    let log_json = get_var("REGISTRY_LOG_FORMAT").is_ok_and(|v| match v.as_str() {
+        "text" => false,
+        "json" => true,
+        _ => panic!("invalid REGISTRY_LOG_FORMAT"),
+    });
 
     fern::Dispatch::new()
         .filter(move |metadata| {
@@ -172,38 +443,217 @@ fn setup_log() {
             target.starts_with("cratery") || target.starts_with("cenotelie")
         })
         .format(move |out, message, record| {
-            out.finish(format_args!(
-                "{}\t{}\t{}",
-                chrono::Local::now().format(&log_date_time_format),
-                record.level(),
-                message
-            ));
+            if log_json {
+                let mut line = serde_json::json!({
+                    "timestamp": chrono::Local::now().to_rfc3339(),
+                    "level": record.level().as_str(),
+                    "target": record.target(),
+                    "message": message.to_string(),
+                });
+                with_request_context(|context| {
+                    line["request_id"] = serde_json::Value::from(context.request_id.as_str());
+                    if let Some(client_request_id) = &context.client_request_id {
+                        line["client_request_id"] = serde_json::Value::from(client_request_id.as_str());
+                    }
+                    if let Some(client_ip) = context.client_ip {
+                        line["client_ip"] = serde_json::Value::from(client_ip.to_string());
+                    }
+                    if let Some(principal) = context.principal.lock().unwrap().as_deref() {
+                        line["principal"] = serde_json::Value::from(principal);
+                    }
+                    if let Some(package) = context.package.lock().unwrap().as_deref() {
+                        line["crate"] = serde_json::Value::from(package);
+                    }
+                });
+                out.finish(format_args!("{line}"));
+            } else {
+                out.finish(format_args!(
+                    "{}",
+                    format_log_line(chrono::Local::now().format(&log_date_time_format), record.level(), message)
+                ));
+            }
         })
-        .level(log_level)
+        // the effective level is set below so that it can be changed when reloading the configuration
+        .level(log::LevelFilter::Trace)
         .chain(std::io::stdout())
         .apply()
         .expect("log configuration failed");
+    log::set_max_level(log_level);
+}
+
+/// Gets the key for the private cookies of the registry whose configuration is being read
+fn get_cookie_key() -> Result<Key, ApiError> {
+    let secret = get_var("REGISTRY_WEB_COOKIE_SECRET")?;
+    Key::try_from(secret.as_bytes())
+        .map_err(|e| specialize(error_invalid_request(), format!("invalid REGISTRY_WEB_COOKIE_SECRET: {e}")))
+}
+
+/// Launches the applications for the tenants of the main registry, each with its key for cookies
+async fn launch_tenants(configuration: &Configuration) -> Result<Vec<(Arc<Application>, Key)>, ApiError> {
+    let mut tenants = Vec::with_capacity(configuration.tenants.len());
+    for tenant in &configuration.tenants {
+        let tenant_configuration = configuration.load_tenant(&tenant.name).await?;
+        info!("launching tenant {} at {}", tenant.name, tenant.web_public_uri);
+        let tenant_application = Application::launch::<services::StandardServiceProvider>(tenant_configuration).await?;
+        reload_on_sighup(tenant_application.clone());
+        let cookie_key = with_tenant(Some(&tenant.name), async { get_cookie_key() }).await?;
+        tenants.push((tenant_application, cookie_key));
+    }
+    Ok(tenants)
+}
+
+/// Reloads the configuration of the application when receiving SIGHUP
+fn reload_on_sighup(application: Arc<Application>) {
+    let mut signal = signal(SignalKind::hangup()).expect("failed to listen to SIGHUP");
+    let _handle = tokio::spawn(async move {
+        while signal.recv().await.is_some() {
+            info!("received SIGHUP, reloading the configuration");
+            if let Err(e) = application.reload_configuration_from_env().await {
+                error!("failed to reload the configuration: {e}");
+            }
+        }
+    });
 }
 
 /// Main entry point
 #[tokio::main]
 async fn main() {
+    if std::env::args().any(|arg| arg == "--help") {
+        println!("{}", admin::ADMIN_USAGE);
+        return;
+    }
+    if std::env::args().any(|arg| arg == "--check-config") {
+        let success = check::main_check_config().await;
+        std::process::exit(i32::from(!success));
+    }
+    // the log may be configured in the configuration file, so that a failure is reported once the log is set up
+    let config_file = load_config_file();
     setup_log();
-    info!("{} commit={} tag={}", CRATE_NAME, GIT_HASH, GIT_TAG);
-    let configuration = services::StandardServiceProvider::get_configuration().await.unwrap();
+    if let Err(e) = config_file {
+        error!("failed to load the configuration file: {e}");
+        std::process::exit(1);
+    }
+    info!("{CRATE_NAME} commit={GIT_HASH} tag={GIT_TAG}");
+    let args = std::env::args().collect::<Vec<_>>();
+    let tenant = args
+        .iter()
+        .position(|arg| arg == "--tenant")
+        .map(|position| args.get(position + 1).cloned().unwrap_or_default());
+    if let Some(tenant) = &tenant {
+        if !is_valid_tenant_name(tenant) {
+            error!("--tenant expects the name of a tenant");
+            std::process::exit(1);
+        }
+    }
+    if with_tenant(tenant.as_deref(), main_command(&args)).await {
+        return;
+    }
+    if tenant.is_some() {
+        error!("--tenant is only valid for the administrative commands, the tenants are served by the main registry");
+        std::process::exit(1);
+    }
+    main_serve().await;
+}
+
+/// Runs the administrative command in the arguments, if any
+///
+/// Returns whether a command was run.
+async fn main_command(args: &[String]) -> bool {
+    if let Some(position) = args.iter().position(|arg| arg == "--restore-backup") {
+        let name = args.get(position + 1).filter(|arg| !arg.starts_with("--")).cloned();
+        let rebuild_index = args.iter().any(|arg| arg == "--rebuild-index");
+        if let Err(e) = restore::main_restore_backup(name, rebuild_index).await {
+            error!("restore failed: {e}");
+            std::process::exit(1);
+        }
+        return true;
+    }
+    if let Some(position) = args.iter().position(|arg| arg == "--import-archive") {
+        let Some(archive) = args.get(position + 1) else {
+            error!("--import-archive expects the path to an export archive");
+            std::process::exit(1);
+        };
+        if let Err(e) = restore::main_import_archive(archive).await {
+            error!("import failed: {e}");
+            std::process::exit(1);
+        }
+        return true;
+    }
+    if let Some(result) = admin::main_admin_command(args).await {
+        if let Err(e) = result {
+            error!("{e}");
+            std::process::exit(1);
+        }
+        return true;
+    }
+    false
+}
+
+/// Serves the main registry and its tenants
+async fn main_serve() {
+    let tracer_provider = setup_tracing();
+    let configuration = match services::StandardServiceProvider::get_configuration().await {
+        Ok(configuration) => configuration,
+        Err(e) => {
+            error!("failed to load the configuration: {e}");
+            std::process::exit(1);
+        }
+    };
     if configuration.self_role.is_worker() {
         let _ = waiting_sigterm(pin!(worker::main_worker(configuration))).await;
     } else {
         // standalone or master
-        let application = Application::launch::<services::StandardServiceProvider>(configuration)
-            .await
-            .unwrap();
-        let cookie_key = Key::from(
-            std::env::var("REGISTRY_WEB_COOKIE_SECRET")
-                .expect("REGISTRY_WEB_COOKIE_SECRET must be set")
-                .as_bytes(),
-        );
-        let server = pin!(main_serve_app(application, cookie_key,));
-        let _ = waiting_sigterm(server).await;
+        let tenants = match launch_tenants(&configuration).await {
+            Ok(tenants) => tenants,
+            Err(e) => {
+                error!("failed to launch the tenants: {e}");
+                std::process::exit(1);
+            }
+        };
+        let cookie_key = match get_cookie_key() {
+            Ok(cookie_key) => cookie_key,
+            Err(e) => {
+                error!("{e}");
+                std::process::exit(1);
+            }
+        };
+        let application = match Application::launch::<services::StandardServiceProvider>(configuration).await {
+            Ok(application) => application,
+            Err(e) => {
+                error!("failed to launch the application: {e}");
+                std::process::exit(1);
+            }
+        };
+        reload_on_sighup(application.clone());
+        let tenant_applications = tenants.iter().map(|(tenant, _)| tenant.clone()).collect::<Vec<_>>();
+        let shutdown = CancellationToken::new();
+        let mut server = pin!(main_serve_app(application.clone(), cookie_key, tenants, shutdown.clone()));
+        let grace_period = Duration::from_secs(application.configuration.load().web_shutdown_grace_period);
+        let deadline = tokio::select! {
+            result = &mut server => {
+                if let Err(e) = result {
+                    error!("failed to serve the application: {e}");
+                }
+                Instant::now() + grace_period
+            }
+            () = sigterm() => {
+                info!("received SIGTERM, draining the in-flight requests");
+                shutdown.cancel();
+                let deadline = Instant::now() + grace_period;
+                if tokio::time::timeout_at(deadline, &mut server).await.is_err() {
+                    warn!("some requests were still in flight after the grace period");
+                }
+                deadline
+            }
+        };
+        // then wait for the running background jobs and write the buffered data
+        application.shutdown(deadline.saturating_duration_since(Instant::now())).await;
+        for tenant in tenant_applications {
+            tenant.shutdown(deadline.saturating_duration_since(Instant::now())).await;
+        }
+    }
+    if let Some(tracer_provider) = tracer_provider {
+        // flush the remaining spans
+        let _ = tracer_provider.shutdown();
     }
 }
@@ -58,6 +58,122 @@ const MIGRATIONS: &[Migration<'static>] = &[
         target: "1.10.0",
         content: MigrationContent::Sql(include_bytes!("v1.10.0.sql")),
     },
+    Migration {
+        target: "1.12.0",
+        content: MigrationContent::Sql(include_bytes!("v1.12.0.sql")),
+    },
+    Migration {
+        target: "1.13.0",
+        content: MigrationContent::Sql(include_bytes!("v1.13.0.sql")),
+    },
+    Migration {
+        target: "1.14.0",
+        content: MigrationContent::Sql(include_bytes!("v1.14.0.sql")),
+    },
+    Migration {
+        target: "1.15.0",
+        content: MigrationContent::Sql(include_bytes!("v1.15.0.sql")),
+    },
+    Migration {
+        target: "1.16.0",
+        content: MigrationContent::Sql(include_bytes!("v1.16.0.sql")),
+    },
+    Migration {
+        target: "1.17.0",
+        content: MigrationContent::Sql(include_bytes!("v1.17.0.sql")),
+    },
+    Migration {
+        target: "1.18.0",
+        content: MigrationContent::Sql(include_bytes!("v1.18.0.sql")),
+    },
+    Migration {
+        target: "1.19.0",
+        content: MigrationContent::Sql(include_bytes!("v1.19.0.sql")),
+    },
+    Migration {
+        target: "1.20.0",
+        content: MigrationContent::Sql(include_bytes!("v1.20.0.sql")),
+    },
+    Migration {
+        target: "1.21.0",
+        content: MigrationContent::Sql(include_bytes!("v1.21.0.sql")),
+    },
+    Migration {
+        target: "1.22.0",
+        content: MigrationContent::Sql(include_bytes!("v1.22.0.sql")),
+    },
+    Migration {
+        target: "1.23.0",
+        content: MigrationContent::Sql(include_bytes!("v1.23.0.sql")),
+    },
+    Migration {
+        target: "1.24.0",
+        content: MigrationContent::Sql(include_bytes!("v1.24.0.sql")),
+    },
+    Migration {
+        target: "1.25.0",
+        content: MigrationContent::Sql(include_bytes!("v1.25.0.sql")),
+    },
+    Migration {
+        target: "1.26.0",
+        content: MigrationContent::Sql(include_bytes!("v1.26.0.sql")),
+    },
+    Migration {
+        target: "1.27.0",
+        content: MigrationContent::Sql(include_bytes!("v1.27.0.sql")),
+    },
+    Migration {
+        target: "1.28.0",
+        content: MigrationContent::Sql(include_bytes!("v1.28.0.sql")),
+    },
+    Migration {
+        target: "1.29.0",
+        content: MigrationContent::Sql(include_bytes!("v1.29.0.sql")),
+    },
+    Migration {
+        target: "1.30.0",
+        content: MigrationContent::Sql(include_bytes!("v1.30.0.sql")),
+    },
+    Migration {
+        target: "1.31.0",
+        content: MigrationContent::Sql(include_bytes!("v1.31.0.sql")),
+    },
+    Migration {
+        target: "1.32.0",
+        content: MigrationContent::Sql(include_bytes!("v1.32.0.sql")),
+    },
+    Migration {
+        target: "1.33.0",
+        content: MigrationContent::Sql(include_bytes!("v1.33.0.sql")),
+    },
+    Migration {
+        target: "1.34.0",
+        content: MigrationContent::Sql(include_bytes!("v1.34.0.sql")),
+    },
+    Migration {
+        target: "1.35.0",
+        content: MigrationContent::Sql(include_bytes!("v1.35.0.sql")),
+    },
+    Migration {
+        target: "1.36.0",
+        content: MigrationContent::Sql(include_bytes!("v1.36.0.sql")),
+    },
+    Migration {
+        target: "1.37.0",
+        content: MigrationContent::Sql(include_bytes!("v1.37.0.sql")),
+    },
+    Migration {
+        target: "1.38.0",
+        content: MigrationContent::Sql(include_bytes!("v1.38.0.sql")),
+    },
+    Migration {
+        target: "1.39.0",
+        content: MigrationContent::Sql(include_bytes!("v1.39.0.sql")),
+    },
+    Migration {
+        target: "1.40.0",
+        content: MigrationContent::Sql(include_bytes!("v1.40.0.sql")),
+    },
 ];
 
 /// Gets the value for the metadata item
@@ -130,7 +246,7 @@ async fn migrate_db(transaction: AppTransaction, migrations: &[Migration<'_>]) -
     };
     let start_from = match current_version {
         Some(version) => {
-            info!("Database schema version = {}", version);
+            info!("Database schema version = {version}");
             let version: VersionNumber = version.as_str().try_into()?;
             let mut result = 0;
             for (index, migration) in migrations.iter().enumerate().rev() {
@@ -0,0 +1,4 @@
+ALTER TABLE PackageVersionDocs
+    ADD COLUMN coverageTotal INTEGER;
+ALTER TABLE PackageVersionDocs
+    ADD COLUMN coverageDocumented INTEGER;
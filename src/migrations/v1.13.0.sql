@@ -0,0 +1,20 @@
+CREATE TABLE Impersonation (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    impersonator INTEGER NOT NULL REFERENCES RegistryUser(id),
+    user INTEGER NOT NULL REFERENCES RegistryUser(id),
+    token TEXT NOT NULL,
+    canWrite BOOLEAN NOT NULL,
+    reason TEXT NOT NULL,
+    createdOn TIMESTAMP NOT NULL,
+    expiresOn TIMESTAMP NOT NULL
+);
+CREATE INDEX IndexImpersonation ON Impersonation (user);
+CREATE TABLE AuditLog (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    timestamp TIMESTAMP NOT NULL,
+    actor INTEGER,
+    impersonator INTEGER,
+    action TEXT NOT NULL,
+    details TEXT NOT NULL
+);
+CREATE INDEX IndexAuditLog ON AuditLog (timestamp);
@@ -0,0 +1,2 @@
+ALTER TABLE Package
+    ADD COLUMN isFrozen BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,5 @@
+CREATE TABLE ProvisioningRule (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    domain TEXT NOT NULL UNIQUE,
+    roles TEXT NOT NULL
+);
@@ -0,0 +1,2 @@
+ALTER TABLE PackageVersionDocs
+    ADD COLUMN size INTEGER;
@@ -0,0 +1,10 @@
+CREATE TABLE PackageDependency (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    package TEXT NOT NULL REFERENCES Package(name),
+    version TEXT NOT NULL,
+    dependency TEXT NOT NULL,
+    versionReq TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    optional BOOLEAN NOT NULL
+);
+CREATE INDEX IndexPackageDependency ON PackageDependency (dependency);
@@ -0,0 +1,12 @@
+ALTER TABLE RegistryUser
+    ADD COLUMN requestCount INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE RegistryUser
+    ADD COLUMN bytesServed INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE RegistryUserToken
+    ADD COLUMN requestCount INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE RegistryUserToken
+    ADD COLUMN bytesServed INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE RegistryGlobalToken
+    ADD COLUMN requestCount INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE RegistryGlobalToken
+    ADD COLUMN bytesServed INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,26 @@
+CREATE TABLE Webhook (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    package TEXT REFERENCES Package(name),
+    url TEXT NOT NULL,
+    secret TEXT NOT NULL,
+    events TEXT NOT NULL,
+    createdBy INTEGER NOT NULL REFERENCES RegistryUser(id),
+    createdOn TIMESTAMP NOT NULL
+);
+
+CREATE INDEX IndexWebhook ON Webhook (package);
+
+CREATE TABLE WebhookDelivery (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    webhook INTEGER NOT NULL REFERENCES Webhook(id),
+    event TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    attempts INTEGER NOT NULL,
+    isDelivered BOOLEAN NOT NULL,
+    statusCode INTEGER,
+    error TEXT,
+    createdOn TIMESTAMP NOT NULL,
+    lastAttempt TIMESTAMP
+);
+
+CREATE INDEX IndexWebhookDelivery ON WebhookDelivery (webhook);
@@ -0,0 +1,21 @@
+CREATE TABLE PackageWatch (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    package TEXT NOT NULL REFERENCES Package(name),
+    user INTEGER NOT NULL REFERENCES RegistryUser(id)
+);
+
+CREATE INDEX IndexPackageWatch ON PackageWatch (package);
+CREATE INDEX IndexPackageWatchUser ON PackageWatch (user);
+
+CREATE TABLE UserNotification (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    user INTEGER NOT NULL REFERENCES RegistryUser(id),
+    package TEXT NOT NULL REFERENCES Package(name),
+    version TEXT,
+    event TEXT NOT NULL,
+    details TEXT NOT NULL,
+    createdOn TIMESTAMP NOT NULL,
+    isRead BOOLEAN NOT NULL
+);
+
+CREATE INDEX IndexUserNotification ON UserNotification (user);
@@ -0,0 +1,12 @@
+CREATE TABLE PackageVersionBinary (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    package TEXT NOT NULL REFERENCES Package(name),
+    version TEXT NOT NULL,
+    target TEXT NOT NULL,
+    size INTEGER NOT NULL,
+    checksum TEXT NOT NULL,
+    uploadedBy INTEGER NOT NULL REFERENCES RegistryUser(id),
+    upload TIMESTAMP NOT NULL
+);
+
+CREATE INDEX IndexPackageVersionBinary ON PackageVersionBinary(package);
@@ -0,0 +1 @@
+ALTER TABLE AuditLog ADD COLUMN clientIp TEXT;
@@ -0,0 +1,13 @@
+CREATE TABLE PackageVersionAdvisory (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    package TEXT NOT NULL REFERENCES Package(name),
+    version TEXT NOT NULL,
+    advisory TEXT NOT NULL,
+    summary TEXT NOT NULL,
+    affectedPackage TEXT NOT NULL,
+    affectedVersion TEXT NOT NULL,
+    isDirect BOOLEAN NOT NULL,
+    detectedOn TIMESTAMP NOT NULL
+);
+
+CREATE INDEX IndexPackageVersionAdvisory ON PackageVersionAdvisory(package, version);
@@ -0,0 +1,2 @@
+ALTER TABLE Package
+    ADD COLUMN isClaimedUpstream BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,2 @@
+ALTER TABLE PackageVersion
+    ADD COLUMN quarantineUntil TIMESTAMP;
@@ -0,0 +1,8 @@
+ALTER TABLE PackageVersion
+    ADD COLUMN provenanceCommit TEXT;
+ALTER TABLE PackageVersion
+    ADD COLUMN provenanceCiRunUrl TEXT;
+ALTER TABLE PackageVersion
+    ADD COLUMN provenanceBuilder TEXT;
+ALTER TABLE PackageVersion
+    ADD COLUMN provenanceVerified BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,4 @@
+ALTER TABLE PackageVersion
+    ADD COLUMN checksumSha256 TEXT;
+ALTER TABLE PackageVersion
+    ADD COLUMN checksumSha512 TEXT;
@@ -0,0 +1,5 @@
+CREATE TABLE UserQuota (
+    user INTEGER NOT NULL PRIMARY KEY REFERENCES RegistryUser(id),
+    maxBytes INTEGER,
+    maxCrates INTEGER
+);
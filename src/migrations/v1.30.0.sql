@@ -0,0 +1,10 @@
+ALTER TABLE Package
+    ADD COLUMN isRestricted BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE PackageReader (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    package TEXT NOT NULL REFERENCES Package(name),
+    user INTEGER NOT NULL REFERENCES RegistryUser(id)
+);
+
+CREATE INDEX IndexPackageReader ON PackageReader (package);
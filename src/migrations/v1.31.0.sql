@@ -0,0 +1,7 @@
+CREATE TABLE Announcement (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    message TEXT NOT NULL,
+    level TEXT NOT NULL,
+    startsOn TIMESTAMP,
+    endsOn TIMESTAMP
+);
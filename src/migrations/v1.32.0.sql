@@ -0,0 +1,9 @@
+CREATE TABLE UserPreferences (
+    user INTEGER NOT NULL PRIMARY KEY REFERENCES RegistryUser(id),
+    notifyOwnerInvitations BOOLEAN NOT NULL,
+    notifyDependencies BOOLEAN NOT NULL,
+    notifyDocsFailures BOOLEAN NOT NULL,
+    notifyWatches BOOLEAN NOT NULL,
+    tokenCanWrite BOOLEAN NOT NULL,
+    tokenCanAdmin BOOLEAN NOT NULL
+);
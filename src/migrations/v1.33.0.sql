@@ -0,0 +1,8 @@
+CREATE TABLE PackageStar (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    package TEXT NOT NULL REFERENCES Package(name),
+    user INTEGER NOT NULL REFERENCES RegistryUser(id)
+);
+
+CREATE INDEX IndexPackageStar ON PackageStar (package);
+CREATE INDEX IndexPackageStarUser ON PackageStar (user);
@@ -0,0 +1,4 @@
+ALTER TABLE Package
+    ADD COLUMN deprecationReason TEXT;
+ALTER TABLE PackageVersion
+    ADD COLUMN yankReason TEXT;
@@ -0,0 +1,3 @@
+DELETE FROM PackageStar
+    WHERE id NOT IN (SELECT MIN(id) FROM PackageStar GROUP BY package, user);
+CREATE UNIQUE INDEX IndexPackageStarUnique ON PackageStar (package, user);
@@ -0,0 +1,2 @@
+ALTER TABLE AuditLog
+    ADD COLUMN clientRequestId TEXT;
@@ -0,0 +1,5 @@
+DELETE FROM PackageVersionBinary
+WHERE id NOT IN (SELECT MAX(id) FROM PackageVersionBinary GROUP BY package, version, target);
+
+DROP INDEX IndexPackageVersionBinary;
+CREATE UNIQUE INDEX IndexPackageVersionBinary ON PackageVersionBinary(package, version, target);
@@ -0,0 +1,5 @@
+CREATE TABLE TeamQuota (
+    team TEXT NOT NULL PRIMARY KEY,
+    maxBytes INTEGER NOT NULL,
+    maxCrates INTEGER NOT NULL
+);
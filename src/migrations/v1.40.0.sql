@@ -0,0 +1,7 @@
+CREATE TABLE PackageReaderTeam (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    package TEXT NOT NULL REFERENCES Package(name),
+    team TEXT NOT NULL
+);
+
+CREATE INDEX IndexPackageReaderTeam ON PackageReaderTeam (package);
@@ -0,0 +1,115 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Data types for the announcement published by the administrators to all users
+
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use chrono::NaiveDateTime;
+use serde_derive::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::utils::apierror::{error_invalid_request, specialize, ApiError};
+
+/// The maximum length of the message of an announcement
+pub const ANNOUNCEMENT_MAX_LENGTH: usize = 1000;
+
+/// The level of an announcement, for its display
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum AnnouncementLevel {
+    /// General information, for example a policy change
+    #[default]
+    Info,
+    /// Something users should act upon, for example an upcoming maintenance window
+    Warning,
+    /// An ongoing disruption of the service
+    Critical,
+}
+
+impl AnnouncementLevel {
+    /// Gets the name of the level
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Info => "info",
+            Self::Warning => "warning",
+            Self::Critical => "critical",
+        }
+    }
+}
+
+impl FromStr for AnnouncementLevel {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "info" => Ok(Self::Info),
+            "warning" => Ok(Self::Warning),
+            "critical" => Ok(Self::Critical),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Display for AnnouncementLevel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// An announcement to all the users of the registry, for example a maintenance window
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Announcement {
+    /// The message to display
+    pub message: String,
+    /// The level of the announcement
+    #[serde(default)]
+    pub level: AnnouncementLevel,
+    /// The date-time from which the announcement is displayed, `None` to display it immediately
+    #[serde(default)]
+    pub starts_on: Option<NaiveDateTime>,
+    /// The date-time after which the announcement is no longer displayed, `None` to display it until it is cleared
+    #[serde(default)]
+    pub ends_on: Option<NaiveDateTime>,
+}
+
+impl Announcement {
+    /// Validates the announcement
+    ///
+    /// # Errors
+    ///
+    /// Returns an error when the message is empty or too long, or when the announcement ends before it starts
+    pub fn validate(&self) -> Result<(), ApiError> {
+        if self.message.trim().is_empty() {
+            return Err(specialize(
+                error_invalid_request(),
+                String::from("the message of the announcement cannot be empty"),
+            ));
+        }
+        if self.message.chars().count() > ANNOUNCEMENT_MAX_LENGTH {
+            return Err(specialize(
+                error_invalid_request(),
+                format!("the message of the announcement cannot exceed {ANNOUNCEMENT_MAX_LENGTH} characters"),
+            ));
+        }
+        if let (Some(starts_on), Some(ends_on)) = (self.starts_on, self.ends_on) {
+            if ends_on <= starts_on {
+                return Err(specialize(
+                    error_invalid_request(),
+                    String::from("the announcement must end after it starts"),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Gets whether the announcement is to be displayed at the given date-time
+    #[must_use]
+    pub fn is_active_at(&self, now: NaiveDateTime) -> bool {
+        self.starts_on.is_none_or(|starts_on| starts_on <= now) && self.ends_on.is_none_or(|ends_on| now < ends_on)
+    }
+}
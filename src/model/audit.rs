@@ -0,0 +1,38 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Data types for the audit log
+
+use chrono::NaiveDateTime;
+use serde_derive::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// The default number of entries when getting the audit log
+pub const AUDIT_LOG_DEFAULT_LIMIT: usize = 100;
+
+/// An entry in the audit log
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AuditLogEntry {
+    /// The unique identifier
+    pub id: i64,
+    /// The timestamp of the action
+    pub timestamp: NaiveDateTime,
+    /// The uid of the user whose identity was used for the action, if any
+    pub actor: Option<i64>,
+    /// The uid of the administrator impersonating the actor, if any
+    pub impersonator: Option<i64>,
+    /// The name of the action
+    pub action: String,
+    /// Details about the action
+    pub details: String,
+    /// The IP address of the client that performed the action, if known
+    #[serde(rename = "clientIp")]
+    pub client_ip: Option<String>,
+    /// The identifier of the HTTP request that performed the action, if any, generated by the registry
+    #[serde(rename = "requestId")]
+    pub request_id: Option<String>,
+    /// The identifier given by the client for the HTTP request that performed the action, if any
+    #[serde(rename = "clientRequestId")]
+    pub client_request_id: Option<String>,
+}
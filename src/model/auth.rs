@@ -6,14 +6,75 @@
 
 use chrono::NaiveDateTime;
 use serde_derive::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
 use crate::utils::apierror::{error_forbidden, error_invalid_request, specialize, ApiError};
 
 /// The admin role
 pub const ROLE_ADMIN: &str = "admin";
+/// The role for users that cannot publish or modify crates, whatever their tokens
+pub const ROLE_READONLY: &str = "readonly";
+/// The domain of a provisioning rule that matches all emails
+pub const PROVISIONING_DOMAIN_ANY: &str = "*";
+
+/// Gets whether a comma-separated list of roles contains a role
+#[must_use]
+pub fn has_role(roles: &str, role: &str) -> bool {
+    roles.split(',').any(|r| r.trim() == role)
+}
+
+/// Gets whether a name can be used as a role, for example to designate a team as the users having the role
+#[must_use]
+pub fn is_valid_role(role: &str) -> bool {
+    !role.is_empty() && role.trim() == role && !role.contains(',')
+}
+
+/// A rule giving default roles to new users, depending on the domain of their email
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, ToSchema)]
+pub struct ProvisioningRule {
+    /// The email domain, matching also its sub-domains, or `*` to match all emails
+    pub domain: String,
+    /// The comma-separated roles to give to the new users
+    pub roles: String,
+}
+
+impl ProvisioningRule {
+    /// Gets the roles for a new user from the rule with the most specific domain matching its email
+    #[must_use]
+    pub fn resolve_roles<'r>(rules: &'r [ProvisioningRule], email: &str) -> Option<&'r str> {
+        let domain = email
+            .rsplit_once('@')
+            .map_or(email, |(_, domain)| domain)
+            .to_ascii_lowercase();
+        rules
+            .iter()
+            .filter(|rule| {
+                rule.domain == PROVISIONING_DOMAIN_ANY
+                    || domain == rule.domain
+                    || domain.strip_suffix(&rule.domain).is_some_and(|prefix| prefix.ends_with('.'))
+            })
+            .max_by_key(|rule| {
+                if rule.domain == PROVISIONING_DOMAIN_ANY {
+                    0
+                } else {
+                    rule.domain.len()
+                }
+            })
+            .map(|rule| rule.roles.as_str())
+    }
+
+    /// Normalizes the domain of this rule
+    #[must_use]
+    pub fn normalized(&self) -> ProvisioningRule {
+        ProvisioningRule {
+            domain: self.domain.trim().trim_start_matches('@').to_ascii_lowercase(),
+            roles: self.roles.trim().to_string(),
+        }
+    }
+}
 
 /// Represents a data about a successful authentication
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct Authentication {
     /// The principal (email of the user)
     pub principal: AuthenticationPrincipal,
@@ -23,6 +84,9 @@ pub struct Authentication {
     /// Whether administration can be done
     #[serde(rename = "canAdmin")]
     pub can_admin: bool,
+    /// The uid of the administrator impersonating the principal, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub impersonator: Option<i64>,
 }
 
 impl Authentication {
@@ -33,6 +97,7 @@ impl Authentication {
             principal: AuthenticationPrincipal::SelfAuth,
             can_write: false,
             can_admin: false,
+            impersonator: None,
         }
     }
 
@@ -43,6 +108,7 @@ impl Authentication {
             principal: AuthenticationPrincipal::Service { token_id },
             can_write: false,
             can_admin: false,
+            impersonator: None,
         }
     }
 
@@ -53,6 +119,7 @@ impl Authentication {
             principal: AuthenticationPrincipal::User { uid, email },
             can_write: true,
             can_admin: true,
+            impersonator: None,
         }
     }
 
@@ -106,7 +173,7 @@ impl Authentication {
 }
 
 /// The principal associated to an authentication
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub enum AuthenticationPrincipal {
     /// A user is authenticated
     User { uid: i64, email: String },
@@ -117,7 +184,7 @@ pub enum AuthenticationPrincipal {
 }
 
 /// A token for a registry user
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct RegistryUserToken {
     /// The unique identifier
     pub id: i64,
@@ -135,7 +202,7 @@ pub struct RegistryUserToken {
 }
 
 /// A token for a registry user
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct RegistryUserTokenWithSecret {
     /// The unique identifier
     pub id: i64,
@@ -154,8 +221,41 @@ pub struct RegistryUserTokenWithSecret {
     pub can_admin: bool,
 }
 
+/// The maximum duration of an impersonation, in minutes
+pub const IMPERSONATION_MAX_DURATION: i64 = 60;
+
+/// The request of an administrator to impersonate a user
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct ImpersonationRequest {
+    /// Whether writing is allowed while impersonating, administration never is
+    #[serde(rename = "canWrite", default)]
+    pub can_write: bool,
+    /// The duration of the impersonation in minutes, at most `IMPERSONATION_MAX_DURATION`
+    #[serde(rename = "durationMinutes")]
+    pub duration_minutes: i64,
+    /// The reason for the impersonation, recorded in the audit log
+    pub reason: String,
+}
+
+/// A token to impersonate a user, given to an administrator
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct ImpersonationToken {
+    /// The unique identifier of the impersonation
+    pub id: i64,
+    /// The login of the impersonated user, to be used with the secret
+    pub login: String,
+    /// The value for the token
+    pub secret: String,
+    /// Whether writing is allowed while impersonating
+    #[serde(rename = "canWrite")]
+    pub can_write: bool,
+    /// The timestamp when the impersonation expires
+    #[serde(rename = "expiresOn")]
+    pub expires_on: NaiveDateTime,
+}
+
 /// An OAuth access token
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct OAuthToken {
     /// The access token
     pub access_token: String,
@@ -180,7 +280,7 @@ pub fn find_field_in_blob<'v>(blob: &'v serde_json::Value, path: &str) -> Option
 }
 
 /// The kind of auth token
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum TokenKind {
     /// A user-specific token
     User,
@@ -198,3 +298,21 @@ pub struct TokenUsage {
     /// The timestamp when the token was used
     pub timestamp: NaiveDateTime,
 }
+
+/// The subject to which the traffic of a request is accounted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TrafficSubject {
+    /// A token, for requests authenticated with it
+    Token(TokenKind, i64),
+    /// A user, for requests authenticated otherwise (web application)
+    User(i64),
+}
+
+/// Event when a request was served for an authenticated subject
+#[derive(Debug, Clone)]
+pub struct TrafficUsage {
+    /// The subject for the request
+    pub subject: TrafficSubject,
+    /// The number of bytes served in the response body
+    pub bytes: u64,
+}
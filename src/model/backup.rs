@@ -0,0 +1,424 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Data types for the scheduled backups of the database
+
+use std::fs::File;
+use std::io::{BufReader, Read, Write};
+use std::path::Path;
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde_derive::{Deserialize, Serialize};
+use tar::{Archive, Builder, Header};
+use utoipa::ToSchema;
+
+use crate::utils::apierror::{error_backend_failure, error_invalid_request, specialize, ApiError};
+use crate::utils::hashes::sha256;
+
+/// The prefix for the names of the backups
+const BACKUP_NAME_PREFIX: &str = "registry-";
+/// The suffix for the names of the full backups, as gzipped `SQLite` databases
+const BACKUP_NAME_SUFFIX: &str = ".db.gz";
+/// The suffix for the names of the incremental backups, as gzipped tar archives of the changed blocks
+const INCREMENTAL_BACKUP_NAME_SUFFIX: &str = ".inc.gz";
+/// The additional suffix for the names of the encrypted backups
+const ENCRYPTED_BACKUP_NAME_SUFFIX: &str = ".enc";
+/// The size of the blocks of the database compared for the incremental backups
+pub const BACKUP_BLOCK_SIZE: usize = 64 * 1024;
+/// The path of the manifest in an incremental backup
+const INCREMENTAL_MANIFEST_PATH: &str = "manifest.json";
+/// The folder for the changed blocks in an incremental backup
+const INCREMENTAL_BLOCKS_FOLDER: &str = "blocks";
+
+/// The status of the backups of the database
+#[derive(Debug, Default, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupStatus {
+    /// Whether a backup is running
+    pub is_running: bool,
+    /// The timestamp when the last backup started
+    pub last_attempt: Option<NaiveDateTime>,
+    /// The timestamp when the last successful backup finished
+    pub last_success: Option<NaiveDateTime>,
+    /// The name of the last successful backup
+    pub last_name: Option<String>,
+    /// The error that made the last backup fail, if any
+    pub last_error: Option<String>,
+    /// The time it took to take and store the last successful backup, in milliseconds
+    pub last_duration: Option<u64>,
+    /// The size of the database in the last successful backup, in bytes
+    pub last_database_size: Option<u64>,
+    /// The size of the last successful backup once compressed, in bytes
+    pub last_size: Option<u64>,
+}
+
+/// The report for a successful backup
+#[derive(Debug, Clone)]
+pub struct BackupReport {
+    /// The name of the backup
+    pub name: String,
+    /// The time it took to take and store the backup, in milliseconds
+    pub duration: u64,
+    /// The size of the database in the backup, in bytes
+    pub database_size: u64,
+    /// The size of the compressed backup, in bytes
+    pub size: u64,
+}
+
+/// The state of the current chain of backups, from which incremental backups are taken
+#[derive(Debug, Clone)]
+pub struct BackupChain {
+    /// The name of the last backup in the chain
+    pub last_name: String,
+    /// The number of backups in the chain, including the full backup
+    pub length: usize,
+    /// The digests of the blocks of the database in the last backup
+    pub digests: Vec<String>,
+}
+
+/// A backup sealed from a snapshot of the database
+#[derive(Debug, Clone)]
+pub struct SealedBackup {
+    /// The content of the backup, compressed and encrypted when a key is given
+    pub content: Vec<u8>,
+    /// The digests of the blocks of the database
+    pub digests: Vec<String>,
+    /// The size of the database, in bytes
+    pub database_size: u64,
+}
+
+/// The kind of a backup, as given by its name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BackupKind {
+    /// Whether this is an incremental backup, that only contains the blocks changed since the previous backup
+    pub incremental: bool,
+    /// Whether the backup is encrypted
+    pub encrypted: bool,
+}
+
+/// The manifest of an incremental backup
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IncrementalManifest {
+    /// The name of the previous backup in the chain
+    pub previous: String,
+    /// The size of the database, in bytes
+    pub size: u64,
+    /// The size of the blocks, in bytes
+    pub block_size: usize,
+}
+
+/// Gets the name of a backup taken at a point in time
+///
+/// The names sort in the chronological order.
+#[must_use]
+pub fn backup_name(timestamp: DateTime<Utc>, kind: BackupKind) -> String {
+    format!(
+        "{BACKUP_NAME_PREFIX}{}{}{}",
+        timestamp.format("%Y%m%dT%H%M%SZ"),
+        if kind.incremental {
+            INCREMENTAL_BACKUP_NAME_SUFFIX
+        } else {
+            BACKUP_NAME_SUFFIX
+        },
+        if kind.encrypted { ENCRYPTED_BACKUP_NAME_SUFFIX } else { "" }
+    )
+}
+
+/// Gets the kind of a backup from its name, if this is the name of a backup
+#[must_use]
+pub fn backup_kind(name: &str) -> Option<BackupKind> {
+    let name = name.strip_prefix(BACKUP_NAME_PREFIX)?;
+    let (name, encrypted) = match name.strip_suffix(ENCRYPTED_BACKUP_NAME_SUFFIX) {
+        Some(name) => (name, true),
+        None => (name, false),
+    };
+    if name.ends_with(BACKUP_NAME_SUFFIX) {
+        Some(BackupKind {
+            incremental: false,
+            encrypted,
+        })
+    } else if name.ends_with(INCREMENTAL_BACKUP_NAME_SUFFIX) {
+        Some(BackupKind {
+            incremental: true,
+            encrypted,
+        })
+    } else {
+        None
+    }
+}
+
+/// Gets the existing backups, in the chronological order
+fn sorted_backups(names: &[String]) -> Vec<(&str, BackupKind)> {
+    let mut backups = names
+        .iter()
+        .filter_map(|name| backup_kind(name).map(|kind| (name.as_str(), kind)))
+        .collect::<Vec<_>>();
+    backups.sort_unstable_by_key(|(name, _)| *name);
+    backups
+}
+
+/// Gets the backups to prune among existing ones, to only keep the `retention` most recent ones
+///
+/// The most recent backup is always kept and files that are not backups are left alone.
+/// The backups that the kept incremental backups depend on are also kept, up to their full backup.
+#[must_use]
+pub fn backups_to_prune(names: &[String], retention: usize) -> Vec<String> {
+    let backups = sorted_backups(names);
+    let mut first_kept = backups.len().saturating_sub(retention.max(1));
+    while first_kept > 0 && backups[first_kept].1.incremental {
+        first_kept -= 1;
+    }
+    backups[..first_kept].iter().map(|(name, _)| (*name).to_string()).collect()
+}
+
+/// Gets the most recent backup among existing files
+#[must_use]
+pub fn latest_backup(names: &[String]) -> Option<String> {
+    names.iter().filter(|name| backup_kind(name).is_some()).max().cloned()
+}
+
+/// Gets the chain of backups to restore a backup, starting with the full backup it is based on
+#[must_use]
+pub fn backup_chain(names: &[String], name: &str) -> Option<Vec<String>> {
+    let backups = sorted_backups(names);
+    let last = backups.iter().position(|(candidate, _)| *candidate == name)?;
+    let first = backups[..=last].iter().rposition(|(_, kind)| !kind.incremental)?;
+    Some(backups[first..=last].iter().map(|(name, _)| (*name).to_string()).collect())
+}
+
+/// Seals the snapshot of the database in a file into a backup, see `seal_backup`
+pub fn seal_backup_file(path: &Path, previous: Option<&BackupChain>, key: Option<&[u8]>) -> Result<SealedBackup, ApiError> {
+    let file = File::open(path)?;
+    let database_size = file.metadata()?.len();
+    seal_backup(BufReader::new(file), database_size, previous, key)
+}
+
+/// Seals a snapshot of the database into a compressed backup, encrypted when a key is given
+///
+/// The backup is incremental when the previous backup in the chain is given,
+/// with only the blocks of the database that changed since then.
+/// The database is read block by block so that it is never entirely kept in memory.
+pub fn seal_backup(
+    mut database: impl Read,
+    database_size: u64,
+    previous: Option<&BackupChain>,
+    key: Option<&[u8]>,
+) -> Result<SealedBackup, ApiError> {
+    let encoder = GzEncoder::new(Vec::new(), Compression::default());
+    let mut digests = Vec::new();
+    let mut block = Vec::with_capacity(BACKUP_BLOCK_SIZE);
+    let mut read = 0;
+    let compressed = if let Some(previous) = previous {
+        let manifest = IncrementalManifest {
+            previous: previous.last_name.clone(),
+            size: database_size,
+            block_size: BACKUP_BLOCK_SIZE,
+        };
+        let mut builder = Builder::new(encoder);
+        append_data(&mut builder, INCREMENTAL_MANIFEST_PATH, &serde_json::to_vec(&manifest)?)?;
+        while read_block(&mut database, &mut block)? {
+            let digest = sha256(&block);
+            if previous.digests.get(digests.len()) != Some(&digest) {
+                append_data(
+                    &mut builder,
+                    &format!("{INCREMENTAL_BLOCKS_FOLDER}/{}", digests.len()),
+                    &block,
+                )?;
+            }
+            digests.push(digest);
+            read += block.len() as u64;
+        }
+        builder.into_inner()?.finish()?
+    } else {
+        let mut encoder = encoder;
+        while read_block(&mut database, &mut block)? {
+            encoder.write_all(&block)?;
+            digests.push(sha256(&block));
+            read += block.len() as u64;
+        }
+        encoder.finish()?
+    };
+    if read != database_size {
+        return Err(specialize(
+            error_backend_failure(),
+            format!("expected {database_size} bytes in the database, read {read}"),
+        ));
+    }
+    let content = match key {
+        Some(key) => encrypt(key, compressed)?,
+        None => compressed,
+    };
+    Ok(SealedBackup {
+        content,
+        digests,
+        database_size,
+    })
+}
+
+/// Reads the next block of a database, returns whether there was one
+fn read_block(database: &mut impl Read, block: &mut Vec<u8>) -> Result<bool, ApiError> {
+    block.clear();
+    database.by_ref().take(BACKUP_BLOCK_SIZE as u64).read_to_end(block)?;
+    Ok(!block.is_empty())
+}
+
+/// Appends a file to an incremental backup
+fn append_data<W: Write>(builder: &mut Builder<W>, path: &str, data: &[u8]) -> Result<(), ApiError> {
+    let mut header = Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    builder.append_data(&mut header, path, data)?;
+    Ok(())
+}
+
+/// Applies an incremental backup onto the database restored from the previous backups in its chain
+///
+/// The database only grows with the blocks in the backup, which must then follow the end of the previous database.
+pub fn apply_incremental_backup(database: &mut Vec<u8>, previous: &str, content: &[u8]) -> Result<(), ApiError> {
+    let mut archive = Archive::new(content);
+    let mut manifest: Option<IncrementalManifest> = None;
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.to_string_lossy().to_string();
+        if path == INCREMENTAL_MANIFEST_PATH {
+            let mut data = Vec::new();
+            entry.read_to_end(&mut data)?;
+            let parsed: IncrementalManifest = serde_json::from_slice(&data)?;
+            if parsed.previous != previous {
+                return Err(specialize(
+                    error_invalid_request(),
+                    format!("the incremental backup is based on {}, not {previous}", parsed.previous),
+                ));
+            }
+            if parsed.block_size == 0 {
+                return Err(specialize(
+                    error_invalid_request(),
+                    String::from("invalid block size in the incremental backup"),
+                ));
+            }
+            manifest = Some(parsed);
+        } else if let Some(index) = path.strip_prefix(INCREMENTAL_BLOCKS_FOLDER).and_then(|p| p.strip_prefix('/')) {
+            let Some(manifest) = &manifest else {
+                return Err(specialize(
+                    error_invalid_request(),
+                    String::from("the manifest of the incremental backup must come first"),
+                ));
+            };
+            let invalid_block = || {
+                specialize(
+                    error_invalid_request(),
+                    format!("invalid block {path} in the incremental backup"),
+                )
+            };
+            let offset = index
+                .parse::<usize>()
+                .ok()
+                .and_then(|index| index.checked_mul(manifest.block_size))
+                .filter(|offset| *offset <= database.len())
+                .ok_or_else(invalid_block)?;
+            let length = usize::try_from(entry.size())?;
+            let end = offset
+                .checked_add(length)
+                .filter(|end| length <= manifest.block_size && u64::try_from(*end).is_ok_and(|end| end <= manifest.size))
+                .ok_or_else(invalid_block)?;
+            if end > database.len() {
+                database.resize(end, 0);
+            }
+            entry.read_exact(&mut database[offset..end])?;
+        }
+    }
+    let Some(manifest) = manifest else {
+        return Err(specialize(
+            error_invalid_request(),
+            String::from("the incremental backup has no manifest"),
+        ));
+    };
+    let size = usize::try_from(manifest.size)?;
+    if size > database.len() {
+        return Err(specialize(
+            error_invalid_request(),
+            String::from("the incremental backup is missing blocks at the end of the database"),
+        ));
+    }
+    database.truncate(size);
+    Ok(())
+}
+
+/// Decrypts the content of a backup when it is encrypted and decompresses it
+pub fn open_backup(name: &str, content: &[u8], key: Option<&[u8]>) -> Result<Vec<u8>, ApiError> {
+    let kind =
+        backup_kind(name).ok_or_else(|| specialize(error_invalid_request(), format!("{name} is not the name of a backup")))?;
+    let decrypted;
+    let compressed = if kind.encrypted {
+        let key = key.ok_or_else(|| {
+            specialize(
+                error_invalid_request(),
+                format!("{name} is encrypted but no encryption key is configured"),
+            )
+        })?;
+        decrypted = decrypt(key, content.to_vec())?;
+        decrypted.as_slice()
+    } else {
+        content
+    };
+    let mut data = Vec::new();
+    GzDecoder::new(compressed).read_to_end(&mut data)?;
+    Ok(data)
+}
+
+/// Gets the key for the encryption of the backups
+fn encryption_key(key: &[u8]) -> Result<LessSafeKey, ApiError> {
+    let key = UnboundKey::new(&AES_256_GCM, key).map_err(|_| {
+        specialize(
+            error_invalid_request(),
+            String::from("the encryption key for the backups must be 32 bytes long"),
+        )
+    })?;
+    Ok(LessSafeKey::new(key))
+}
+
+/// Encrypts data with AES-256-GCM, the random nonce being prepended to the result
+fn encrypt(key: &[u8], mut data: Vec<u8>) -> Result<Vec<u8>, ApiError> {
+    let key = encryption_key(key)?;
+    let mut nonce = [0_u8; NONCE_LEN];
+    SystemRandom::new()
+        .fill(&mut nonce)
+        .map_err(|_| specialize(error_backend_failure(), String::from("failed to generate a nonce")))?;
+    key.seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut data)
+        .map_err(|_| specialize(error_backend_failure(), String::from("failed to encrypt the backup")))?;
+    let mut result = nonce.to_vec();
+    result.append(&mut data);
+    Ok(result)
+}
+
+/// Decrypts data encrypted by `encrypt`
+fn decrypt(key: &[u8], mut data: Vec<u8>) -> Result<Vec<u8>, ApiError> {
+    let key = encryption_key(key)?;
+    if data.len() < NONCE_LEN {
+        return Err(specialize(
+            error_invalid_request(),
+            String::from("the encrypted backup is truncated"),
+        ));
+    }
+    let mut content = data.split_off(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(&data)
+        .map_err(|_| specialize(error_invalid_request(), String::from("the encrypted backup is truncated")))?;
+    let length = key
+        .open_in_place(nonce, Aad::empty(), &mut content)
+        .map_err(|_| {
+            specialize(
+                error_invalid_request(),
+                String::from("failed to decrypt the backup, the key may be wrong or the backup corrupted"),
+            )
+        })?
+        .len();
+    content.truncate(length);
+    Ok(content)
+}
@@ -0,0 +1,74 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Data types for the distribution of prebuilt binaries with cargo-binstall
+
+use chrono::NaiveDateTime;
+use serde_derive::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::cargo::RegistryUser;
+use super::sources::extract_crate_file;
+use crate::utils::apierror::{error_invalid_request, specialize, ApiError};
+
+/// The maximum length of a target triple for a binary artifact
+const BINARY_TARGET_MAX_LENGTH: usize = 64;
+
+/// A prebuilt binary artifact hosted for a crate version and a target
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CrateBinary {
+    /// The target triple
+    pub target: String,
+    /// The size of the artifact, in bytes
+    pub size: i64,
+    /// The SHA256 checksum of the artifact
+    pub checksum: String,
+    /// The user that uploaded the artifact
+    #[serde(rename = "uploadedBy")]
+    pub uploaded_by: RegistryUser,
+    /// The timestamp of the upload
+    pub upload: NaiveDateTime,
+}
+
+/// The data for cargo-binstall about a crate version
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BinstallInfo {
+    /// The `[package.metadata.binstall]` table from the manifest of the crate, if any
+    #[schema(value_type = Option<Object>)]
+    pub metadata: Option<serde_json::Value>,
+    /// The prebuilt binary artifacts hosted by the registry
+    pub binaries: Vec<CrateBinary>,
+}
+
+/// Checks that a target triple is acceptable for a binary artifact
+pub fn validate_binary_target(target: &str) -> Result<(), ApiError> {
+    let is_valid = !target.is_empty()
+        && target.len() <= BINARY_TARGET_MAX_LENGTH
+        && !target.starts_with(['-', '.'])
+        && target
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.');
+    if is_valid {
+        Ok(())
+    } else {
+        Err(specialize(error_invalid_request(), format!("invalid target {target}")))
+    }
+}
+
+/// Extracts the `[package.metadata.binstall]` table from the manifest in a `.crate` archive
+pub fn extract_binstall_metadata(content: &[u8], name: &str, version: &str) -> Result<Option<serde_json::Value>, ApiError> {
+    let Some(manifest) = extract_crate_file(content, name, version, "Cargo.toml")? else {
+        return Ok(None);
+    };
+    let manifest = String::from_utf8_lossy(&manifest);
+    let manifest = toml::from_str::<toml::Table>(&manifest)
+        .map_err(|e| specialize(error_invalid_request(), format!("invalid manifest: {e}")))?;
+    let metadata = manifest
+        .get("package")
+        .and_then(|package| package.get("metadata"))
+        .and_then(|metadata| metadata.get("binstall"))
+        .map(serde_json::to_value)
+        .transpose()?;
+    Ok(metadata)
+}
@@ -5,38 +5,89 @@
 //! Data model for the Cargo web API
 
 use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
 use std::io::Cursor;
 use std::str::FromStr;
 
 use byteorder::{LittleEndian, ReadBytesExt};
+use chrono::NaiveDateTime;
 use serde_derive::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
 use crate::utils::apierror::{error_invalid_request, specialize, ApiError};
+use crate::utils::context::with_request_context;
 use crate::utils::hashes::sha256;
 
 /// A crate to appear in search results
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct SearchResultCrate {
     /// Name of the crate
     pub name: String,
     /// The highest version available
     pub max_version: String,
+    /// The highest stable (not pre-release) version that is not yanked, if any
+    pub max_stable_version: Option<String>,
     /// Whether the entire package is deprecated
     #[serde(rename = "isDeprecated")]
     pub is_deprecated: bool,
+    /// Whether all the versions of the package are yanked
+    #[serde(rename = "isYanked")]
+    pub is_yanked: bool,
     /// Textual description of the crate
     pub description: String,
+    /// The total number of downloads for all the versions
+    pub downloads: i64,
+    /// The number of downloads for all the versions over the last days, see `SERIES_LENGTH`
+    pub recent_downloads: i64,
+    /// The timestamp of the last publication of a version
+    pub updated_at: NaiveDateTime,
 }
 
 /// The metadata of the search results
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct SearchResultsMeta {
     /// Total number of results available on the server
     pub total: usize,
+    /// The facets for the results, used for guided browsing
+    #[serde(default)]
+    pub facets: SearchFacets,
+}
+
+/// The facets for search results
+#[derive(Debug, Default, Serialize, Deserialize, Clone, ToSchema)]
+pub struct SearchFacets {
+    /// The keywords of the matching crates
+    pub keywords: Vec<SearchFacetValue>,
+    /// The categories of the matching crates
+    pub categories: Vec<SearchFacetValue>,
+    /// The owners (logins) of the matching crates
+    pub owners: Vec<SearchFacetValue>,
+}
+
+/// A value for a facet, with the number of matching crates
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct SearchFacetValue {
+    /// The value
+    pub value: String,
+    /// The number of matching crates with this value
+    pub count: usize,
+}
+
+impl SearchFacetValue {
+    /// Builds the values for a facet from the count for each value, by decreasing count
+    #[must_use]
+    pub fn from_counts(counts: HashMap<String, usize>) -> Vec<Self> {
+        let mut values = counts
+            .into_iter()
+            .map(|(value, count)| Self { value, count })
+            .collect::<Vec<_>>();
+        values.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value)));
+        values
+    }
 }
 
 /// The search results for crates
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct SearchResults {
     /// The crates
     pub crates: Vec<SearchResultCrate>,
@@ -44,30 +95,132 @@ pub struct SearchResults {
     pub meta: SearchResultsMeta,
 }
 
+/// A user, in the format of the crates.io web API
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct CratesIoUser {
+    /// The unique identifier
+    pub id: i64,
+    /// The login of the user
+    pub login: String,
+    /// The user's name
+    pub name: Option<String>,
+    /// The URL to the avatar of the user, if any
+    pub avatar: Option<String>,
+    /// The URL to the profile of the user, if any
+    pub url: Option<String>,
+}
+
+impl From<RegistryUser> for CratesIoUser {
+    fn from(user: RegistryUser) -> Self {
+        Self {
+            id: user.id,
+            login: user.login,
+            name: Some(user.name),
+            avatar: None,
+            url: None,
+        }
+    }
+}
+
+/// A crate version, in the format of the crates.io web API
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct CratesIoVersion {
+    /// The unique identifier
+    pub id: i64,
+    /// The name of the crate
+    #[serde(rename = "crate")]
+    pub package: String,
+    /// The version number
+    pub num: String,
+    /// The path to download the crate
+    pub dl_path: String,
+    /// The path to the README of the crate
+    pub readme_path: String,
+    /// The SHA256 checksum of the `.crate` file
+    pub checksum: String,
+    /// The size of the `.crate` file, in bytes
+    pub crate_size: Option<i64>,
+    /// The timestamp of the publication
+    pub created_at: NaiveDateTime,
+    /// The timestamp of the last update
+    pub updated_at: NaiveDateTime,
+    /// The number of times this version was downloaded
+    pub downloads: i64,
+    /// Set of features defined for the version
+    pub features: HashMap<String, Vec<String>>,
+    /// Whether this version has been yanked
+    pub yanked: bool,
+    /// The `links` value from the manifest
+    pub links: Option<String>,
+    /// The minimal supported Rust version
+    pub rust_version: Option<String>,
+    /// The user that published this version
+    pub published_by: Option<CratesIoUser>,
+}
+
+/// The versions of a crate, in the format of the crates.io web API
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct CratesIoVersions {
+    /// The versions, the most recent first
+    pub versions: Vec<CratesIoVersion>,
+    /// The metadata for the versions
+    pub meta: CratesIoVersionsMeta,
+}
+
+/// The metadata for the versions of a crate, in the format of the crates.io web API
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct CratesIoVersionsMeta {
+    /// The total number of versions
+    pub total: usize,
+}
+
+/// A single crate version, in the format of the crates.io web API
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct CratesIoVersionResult {
+    /// The version
+    pub version: CratesIoVersion,
+}
+
 /// A set of errors as a response for the web API
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct ApiResponseErrors {
     /// The individual errors
     pub errors: Vec<ApiResponseError>,
 }
 
 /// An error response for the web API
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct ApiResponseError {
     /// The details for the error
     pub detail: String,
+    /// The identifier of the request that produced the error, if any
+    #[serde(rename = "requestId", default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    /// The identifier given by the client for the request that produced the error, if any
+    #[serde(rename = "clientRequestId", default, skip_serializing_if = "Option::is_none")]
+    pub client_request_id: Option<String>,
 }
 
 impl From<ApiError> for ApiResponseErrors {
     fn from(err: ApiError) -> Self {
+        let (request_id, client_request_id) = if err.request_id.is_some() {
+            (err.request_id.clone(), err.client_request_id.clone())
+        } else {
+            with_request_context(|context| (Some(context.request_id.clone()), context.client_request_id.clone()))
+                .unwrap_or_default()
+        };
         ApiResponseErrors {
-            errors: vec![ApiResponseError { detail: err.to_string() }],
+            errors: vec![ApiResponseError {
+                detail: err.to_string(),
+                request_id,
+                client_request_id,
+            }],
         }
     }
 }
 
 /// The result for a yank operation
-#[derive(Default, Debug, Serialize, Deserialize, Clone)]
+#[derive(Default, Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct YesNoResult {
     /// The value for the result
     pub ok: bool,
@@ -82,7 +235,7 @@ impl YesNoResult {
 }
 
 /// The result for a yank operation
-#[derive(Default, Debug, Serialize, Deserialize, Clone)]
+#[derive(Default, Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct YesNoMsgResult {
     /// The value for the result
     pub ok: bool,
@@ -99,21 +252,31 @@ impl YesNoMsgResult {
 }
 
 /// The result when querying for owners
-#[derive(Default, Debug, Serialize, Deserialize, Clone)]
+#[derive(Default, Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct OwnersQueryResult {
     /// The list of owners
     pub users: Vec<RegistryUser>,
 }
 
 /// The query for adding/removing owners to a crate
-#[derive(Default, Debug, Serialize, Deserialize, Clone)]
+#[derive(Default, Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct OwnersChangeQuery {
     /// The login of the users
     pub users: Vec<String>,
 }
 
+/// Parses an owner given as a team of crates.io, `github:<org>:<team>`, into the organization and the team
+///
+/// Other owners are users, identified by their email.
+#[must_use]
+pub fn parse_team_owner(owner: &str) -> Option<(&str, &str)> {
+    let (org, team) = owner.strip_prefix("github:")?.split_once(':')?;
+    let is_name = |name: &str| !name.is_empty() && name.bytes().all(|c| c.is_ascii_alphanumeric() || c == b'-' || c == b'_');
+    (is_name(org) && is_name(team)).then_some((org, team))
+}
+
 /// A user for the registry
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct RegistryUser {
     /// The unique identifier
     /// Expected for Cargo
@@ -134,7 +297,7 @@ pub struct RegistryUser {
 }
 
 /// The metadata for a crate
-#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[derive(Default, Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct CrateMetadata {
     /// The name of the package
     pub name: String,
@@ -226,7 +389,7 @@ pub fn validation_error(details: &str) -> Result<(), ApiError> {
 }
 
 /// The kind of dependency
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 pub enum DependencyKind {
     /// A normal dependency
     #[default]
@@ -253,8 +416,18 @@ impl FromStr for DependencyKind {
     }
 }
 
+impl Display for DependencyKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Normal => write!(f, "normal"),
+            Self::Dev => write!(f, "dev"),
+            Self::Build => write!(f, "build"),
+        }
+    }
+}
+
 /// A dependency for a crate
-#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[derive(Default, Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct CrateMetadataDependency {
     /// Name of the dependency.
     /// If the dependency is renamed from the original package name,
@@ -287,14 +460,14 @@ pub struct CrateMetadataDependency {
 }
 
 /// The result for the upload fo a crate
-#[derive(Default, Debug, Serialize, Deserialize, Clone)]
+#[derive(Default, Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct CrateUploadResult {
     /// The warnings
     pub warnings: CrateUploadWarnings,
 }
 
 /// The warnings for the upload of a crate
-#[derive(Default, Debug, Serialize, Deserialize, Clone)]
+#[derive(Default, Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct CrateUploadWarnings {
     /// Array of strings of categories that are invalid and ignored
     pub invalid_categories: Vec<String>,
@@ -348,7 +521,7 @@ impl CrateUploadData {
 }
 
 /// The metadata for a crate inside the index
-#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[derive(Default, Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct IndexCrateMetadata {
     /// The name of the package
     pub name: String,
@@ -382,9 +555,9 @@ pub struct IndexCrateMetadata {
     /// The current values are:
     ///
     /// * 1: The schema as documented here, not including newer additions.
-    ///      This is honored in Rust version 1.51 and newer.
+    ///   This is honored in Rust version 1.51 and newer.
     /// * 2: The addition of the `features2` field.
-    ///      This is honored in Rust version 1.60 and newer.
+    ///   This is honored in Rust version 1.60 and newer.
     pub v: Option<u32>,
     /// This optional field contains features with new, extended syntax.
     /// Specifically, namespaced features (`dep:`) and weak dependencies
@@ -421,7 +594,7 @@ impl IndexCrateMetadata {
 }
 
 /// A dependency for a crate in the index
-#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[derive(Default, Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct IndexCrateDependency {
     /// Name of the dependency.
     /// If the dependency is renamed from the original package name,
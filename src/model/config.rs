@@ -4,34 +4,261 @@
 
 //! Module for configuration management
 
+use std::collections::HashMap;
+use std::future::Future;
 use std::net::{IpAddr, Ipv4Addr};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
 use std::str::FromStr;
+use std::sync::RwLock;
 
-use axum::http::Uri;
+use axum::http::{HeaderName, HeaderValue, Method, Uri};
 use base64::engine::general_purpose::STANDARD;
 use base64::Engine;
+use ipnet::IpNet;
 use serde_derive::{Deserialize, Serialize};
 use tokio::fs::File;
 use tokio::io::{AsyncWriteExt, BufWriter};
-use tokio::process::Command;
+use utoipa::ToSchema;
 
 use super::{CHANNEL_NIGHTLY, CHANNEL_STABLE};
-use crate::model::errors::MissingEnvVar;
-use crate::utils::apierror::{error_backend_failure, specialize, ApiError};
-use crate::utils::comma_sep_to_vec;
+use crate::model::errors::{EnvVarError, MissingEnvVar};
+use crate::utils::apierror::{error_backend_failure, error_invalid_request, specialize, ApiError};
 use crate::utils::token::generate_token;
+use crate::utils::{comma_sep_to_vec, new_command};
+
+/// The environment variable that gives the path to the optional configuration file
+pub const CONFIG_FILE_VAR: &str = "REGISTRY_CONFIG_FILE";
+
+/// The values loaded from the configuration file, by name of the equivalent environment variable
+static CONFIG_FILE_VALUES: RwLock<Option<HashMap<String, String>>> = RwLock::new(None);
+
+/// The suffix for the variables that give the path to a file containing the value of another variable
+const SECRET_FILE_SUFFIX: &str = "_FILE";
 
 /// Gets the value for an environment variable
-pub fn get_var<T: AsRef<str>>(name: T) -> Result<String, MissingEnvVar> {
+///
+/// The value is looked up, in order of precedence:
+/// * in the environment variable,
+/// * in the file pointed to by the `<name>_FILE` environment variable, for secrets mounted as files,
+/// * in the configuration file, with the same two forms.
+///
+/// When reading the configuration of a tenant, the `REGISTRY_TENANT_<TENANT>_<rest>` form of a `REGISTRY_<rest>` variable
+/// takes precedence, so that tenants inherit the settings of the main registry unless overridden.
+///
+/// # Errors
+///
+/// Return an error when the variable is not set or when a file pointed to by a `<name>_FILE` variable cannot be read.
+pub fn get_var<T: AsRef<str>>(name: T) -> Result<String, EnvVarError> {
     let key = name.as_ref();
-    std::env::var(key).map_err(|original| MissingEnvVar {
+    if let Some(tenant) = get_current_tenant() {
+        if let Some(value) = get_tenant_var(key, &tenant)? {
+            return Ok(value);
+        }
+    }
+    get_main_var(key)
+}
+
+/// Gets the value for an environment variable, or `None` when it is not set
+///
+/// # Errors
+///
+/// Return an error when a file pointed to by a `<name>_FILE` variable cannot be read.
+pub fn get_var_opt<T: AsRef<str>>(name: T) -> Result<Option<String>, EnvVarError> {
+    match get_var(name) {
+        Ok(value) => Ok(Some(value)),
+        Err(EnvVarError::Missing(_)) => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+/// Gets the value for an environment variable for the main registry, ignoring the overrides of tenants
+fn get_main_var(key: &str) -> Result<String, EnvVarError> {
+    let file_key = format!("{key}{SECRET_FILE_SUFFIX}");
+    let original = match std::env::var(key) {
+        Ok(value) => return Ok(value),
+        Err(original) => original,
+    };
+    if let Ok(path) = std::env::var(&file_key) {
+        return read_secret_file(&file_key, &path);
+    }
+    let values = CONFIG_FILE_VALUES.read().unwrap();
+    let values = values.as_ref();
+    if let Some(value) = values.and_then(|values| values.get(key)) {
+        return Ok(value.clone());
+    }
+    if let Some(path) = values.and_then(|values| values.get(&file_key)) {
+        return read_secret_file(&file_key, path);
+    }
+    Err(EnvVarError::Missing(MissingEnvVar {
         original,
         var_name: key.to_string(),
+    }))
+}
+
+tokio::task_local! {
+    /// The tenant whose configuration is being read, if any
+    static CURRENT_TENANT: String;
+}
+
+/// Gets the tenant whose configuration is being read, if any
+#[must_use]
+pub fn get_current_tenant() -> Option<String> {
+    CURRENT_TENANT.try_with(Clone::clone).ok()
+}
+
+/// Runs a future that reads the configuration of a tenant, or of the main registry for `None`
+pub async fn with_tenant<F: Future>(tenant: Option<&str>, future: F) -> F::Output {
+    match tenant {
+        Some(tenant) => CURRENT_TENANT.scope(tenant.to_string(), future).await,
+        None => future.await,
+    }
+}
+
+/// Gets the value of the override for a tenant of a `REGISTRY_<rest>` variable, in `REGISTRY_TENANT_<TENANT>_<rest>`
+fn get_tenant_var(key: &str, tenant: &str) -> Result<Option<String>, EnvVarError> {
+    let Some(rest) = key.strip_prefix("REGISTRY_") else {
+        return Ok(None);
+    };
+    let tenant = tenant.to_ascii_uppercase().replace('-', "_");
+    match get_main_var(&format!("REGISTRY_TENANT_{tenant}_{rest}")) {
+        Ok(value) => Ok(Some(value)),
+        Err(EnvVarError::Missing(_)) => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+/// Gets the value for an environment variable that, for a tenant, defaults to a value derived from the one of the main registry
+///
+/// The `default` value is used for the main registry when the variable is not set.
+fn get_var_for_tenant(
+    name: &str,
+    default: Option<&str>,
+    derive: impl FnOnce(&str, &str) -> String,
+) -> Result<String, EnvVarError> {
+    let main = match get_main_var(name) {
+        Err(EnvVarError::Missing(missing)) => default.map(str::to_string).ok_or(EnvVarError::Missing(missing)),
+        result => result,
+    };
+    match get_current_tenant() {
+        None => main,
+        Some(tenant) => match get_tenant_var(name, &tenant)? {
+            Some(value) => Ok(value),
+            None => main.map(|main| derive(&main, &tenant)),
+        },
+    }
+}
+
+/// Gets the parsed value for an environment variable, or the default value when it is not set
+///
+/// # Errors
+///
+/// Return an error when the value cannot be parsed.
+fn get_var_parsed<T: FromStr>(name: &str, default: T) -> Result<T, ApiError>
+where
+    T::Err: std::fmt::Debug,
+{
+    get_var_opt(name)?.map_or(Ok(default), |s| {
+        s.parse().map_err(|e| error_invalid_var(name, format!("{e:?}")))
     })
 }
 
+/// Gets the error for an environment variable with an invalid value
+fn error_invalid_var(name: &str, details: impl std::fmt::Display) -> ApiError {
+    specialize(error_invalid_request(), format!("invalid {name}: {details}"))
+}
+
+/// Reads the value of a variable from a file, without the trailing line ending
+fn read_secret_file(file_key: &str, path: &str) -> Result<String, EnvVarError> {
+    let content = std::fs::read_to_string(path).map_err(|e| EnvVarError::UnreadableFile {
+        var_name: file_key.to_string(),
+        path: path.to_string(),
+        details: e.to_string(),
+    })?;
+    Ok(content.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// Parses comma-separated IP addresses or ranges
+fn parse_ip_ranges(name: &str, input: &str) -> Result<Vec<IpNet>, ApiError> {
+    comma_sep_to_vec(input)
+        .into_iter()
+        .map(|value| {
+            value
+                .parse::<IpNet>()
+                .or_else(|_| value.parse::<IpAddr>().map(IpNet::from))
+                .map_err(|_| error_invalid_var(name, format!("{value} is not an IP address or range")))
+        })
+        .collect()
+}
+
+/// Loads the TOML configuration file given by `REGISTRY_CONFIG_FILE`, if set
+///
+/// This must be called at startup, before reading the configuration, and again when reloading it.
+pub fn load_config_file() -> Result<(), ApiError> {
+    let Ok(path) = std::env::var(CONFIG_FILE_VAR) else {
+        return Ok(());
+    };
+    let content = std::fs::read_to_string(&path).map_err(|e| {
+        specialize(
+            error_backend_failure(),
+            format!("failed to read the configuration file {path}: {e}"),
+        )
+    })?;
+    let values = parse_config_file(&content)?;
+    *CONFIG_FILE_VALUES.write().unwrap() = Some(values);
+    Ok(())
+}
+
+/// Parses the content of a TOML configuration file into values for the equivalent environment variables
+///
+/// Nested tables are flattened so that `public_uri` in the `[web]` table gives `REGISTRY_WEB_PUBLIC_URI`.
+/// Keys that are already names of environment variables (starting with `REGISTRY_`) are kept as is.
+/// Arrays are joined with commas.
+pub fn parse_config_file(content: &str) -> Result<HashMap<String, String>, ApiError> {
+    let table = toml::from_str::<toml::Table>(content)
+        .map_err(|e| specialize(error_invalid_request(), format!("invalid configuration file: {e}")))?;
+    let mut values = HashMap::new();
+    for (key, value) in &table {
+        let name = if key.starts_with("REGISTRY_") {
+            key.clone()
+        } else {
+            format!("REGISTRY_{}", key.to_uppercase())
+        };
+        flatten_config_value(&name, value, &mut values)?;
+    }
+    Ok(values)
+}
+
+/// Flattens a value from the configuration file
+fn flatten_config_value(name: &str, value: &toml::Value, values: &mut HashMap<String, String>) -> Result<(), ApiError> {
+    let value = match value {
+        toml::Value::Table(table) => {
+            for (key, value) in table {
+                flatten_config_value(&format!("{name}_{}", key.to_uppercase()), value, values)?;
+            }
+            return Ok(());
+        }
+        toml::Value::String(value) => value.clone(),
+        toml::Value::Array(items) => items
+            .iter()
+            .map(|item| match item {
+                toml::Value::String(value) => Ok(value.clone()),
+                toml::Value::Integer(_) | toml::Value::Float(_) | toml::Value::Boolean(_) => Ok(item.to_string()),
+                _ => Err(specialize(
+                    error_invalid_request(),
+                    format!("invalid configuration file: unsupported value in array for {name}"),
+                )),
+            })
+            .collect::<Result<Vec<_>, _>>()?
+            .join(","),
+        toml::Value::Integer(_) | toml::Value::Float(_) | toml::Value::Boolean(_) | toml::Value::Datetime(_) => {
+            value.to_string()
+        }
+    };
+    values.insert(name.to_string(), value);
+    Ok(())
+}
+
 /// The protocol to use for an external registry
 #[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, Eq)]
 pub enum ExternalRegistryProtocol {
@@ -69,12 +296,27 @@ pub struct ExternalRegistry {
     pub login: String,
     /// The token for authentication
     pub token: String,
+    /// Whether to proxy and cache this registry, only for the sparse protocol
+    #[serde(default)]
+    pub proxy: bool,
+    /// Number of milliseconds after which a cached index file of this registry is deemed stale and must be pulled again
+    #[serde(rename = "proxyStaleIndex", default)]
+    pub proxy_stale_index: u64,
 }
 
 impl ExternalRegistry {
+    /// Loads the configuration for all the registries declared in the environment
+    fn all_from_env() -> Result<Vec<ExternalRegistry>, ApiError> {
+        let mut registries = Vec::new();
+        while let Some(registry) = ExternalRegistry::from_env(registries.len() + 1)? {
+            registries.push(registry);
+        }
+        Ok(registries)
+    }
+
     /// Loads the configuration for a registry from the environment
-    fn from_env(reg_index: usize) -> Result<Option<ExternalRegistry>, MissingEnvVar> {
-        if let Ok(name) = get_var(format!("REGISTRY_EXTERNAL_{reg_index}_NAME")) {
+    fn from_env(reg_index: usize) -> Result<Option<ExternalRegistry>, ApiError> {
+        if let Some(name) = get_var_opt(format!("REGISTRY_EXTERNAL_{reg_index}_NAME"))? {
             let mut index = get_var(format!("REGISTRY_EXTERNAL_{reg_index}_INDEX"))?;
             let protocol = if let Some(rest) = index.strip_prefix("sparse+") {
                 index = rest.to_string();
@@ -85,6 +327,11 @@ impl ExternalRegistry {
             let docs_root = get_var(format!("REGISTRY_EXTERNAL_{reg_index}_DOCS"))?;
             let login = get_var(format!("REGISTRY_EXTERNAL_{reg_index}_LOGIN"))?;
             let token = get_var(format!("REGISTRY_EXTERNAL_{reg_index}_TOKEN"))?;
+            let proxy = get_var_opt(format!("REGISTRY_EXTERNAL_{reg_index}_PROXY"))?.is_some_and(|v| v == "true");
+            let proxy_stale_index = get_var_parsed(
+                &format!("REGISTRY_EXTERNAL_{reg_index}_PROXY_STALE_INDEX"),
+                ProxyConfig::default().stale_index,
+            )?;
             Ok(Some(ExternalRegistry {
                 name,
                 index,
@@ -92,6 +339,8 @@ impl ExternalRegistry {
                 docs_root,
                 login,
                 token,
+                proxy,
+                proxy_stale_index,
             }))
         } else {
             Ok(None)
@@ -99,6 +348,120 @@ impl ExternalRegistry {
     }
 }
 
+/// The names that cannot be used for tenants because they are the first segment of a route
+const TENANT_RESERVED_NAMES: &[&str] = &[
+    "api",
+    "badges",
+    "crates",
+    "docs",
+    "git-upload-pack",
+    "healthz",
+    "index",
+    "info",
+    "proxy",
+    "readyz",
+    "webapp",
+];
+
+/// The configuration for a tenant, a registry hosted by the same process as the main one, under its own URL prefix
+///
+/// A tenant has its own database, index and storage.
+/// Its configuration is the one of the main registry, overridden by the `REGISTRY_TENANT_<TENANT>_*` variables.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TenantConfig {
+    /// The name of the tenant, also the prefix of its URLs
+    pub name: String,
+    /// The name to use for the registry of the tenant in cargo and git config
+    #[serde(rename = "localName")]
+    pub local_name: String,
+    /// The root uri from which the registry of the tenant is served
+    #[serde(rename = "webPublicUri")]
+    pub web_public_uri: String,
+    /// Whether the git protocol is enabled for the index of the tenant
+    #[serde(rename = "allowProtocolGit")]
+    pub allow_protocol_git: bool,
+    /// Whether the sparse protocol is enabled for the index of the tenant
+    #[serde(rename = "allowProtocolSparse")]
+    pub allow_protocol_sparse: bool,
+    /// The login to the service account for self authentication to the registry of the tenant
+    #[serde(rename = "selfServiceLogin")]
+    pub self_service_login: String,
+    /// The token to the service account for self authentication to the registry of the tenant
+    #[serde(rename = "selfServiceToken")]
+    pub self_service_token: String,
+}
+
+impl TenantConfig {
+    /// Loads the configuration for all the tenants declared in `REGISTRY_TENANTS`
+    fn all_from_env() -> Result<Vec<TenantConfig>, ApiError> {
+        let names = get_var_opt("REGISTRY_TENANTS")?.map_or_else(Vec::new, |v| comma_sep_to_vec(&v));
+        let mut tenants = Vec::<TenantConfig>::with_capacity(names.len());
+        for name in names {
+            if !is_valid_tenant_name(&name) {
+                return Err(error_invalid_var(
+                    "REGISTRY_TENANTS",
+                    format!("{name} is not a valid name for a tenant"),
+                ));
+            }
+            if tenants.iter().any(|tenant| tenant.name == name) {
+                return Err(error_invalid_var("REGISTRY_TENANTS", format!("{name} is declared twice")));
+            }
+            tenants.push(CURRENT_TENANT.sync_scope(name.clone(), || Self::from_env(name))?);
+        }
+        Ok(tenants)
+    }
+
+    /// Loads the configuration for a tenant from the environment
+    fn from_env(name: String) -> Result<TenantConfig, ApiError> {
+        let (web_public_uri, web_domain) = get_web_public_uri()?;
+        Ok(TenantConfig {
+            name,
+            local_name: get_self_local_name(&web_domain)?,
+            web_public_uri,
+            allow_protocol_git: get_var_opt("REGISTRY_INDEX_PROTOCOL_GIT")?.is_some_and(|v| v == "true"),
+            allow_protocol_sparse: get_var_opt("REGISTRY_INDEX_PROTOCOL_SPARSE")?.is_none_or(|v| v == "true"),
+            self_service_login: generate_token(16),
+            self_service_token: generate_token(64),
+        })
+    }
+}
+
+/// Gets whether a name can be used for a tenant
+///
+/// Names are lowercase and at least 3 characters long so that they cannot be mistaken for the folders of the sparse index.
+#[must_use]
+pub fn is_valid_tenant_name(name: &str) -> bool {
+    name.len() >= 3
+        && name.starts_with(|c: char| c.is_ascii_lowercase())
+        && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        && !TENANT_RESERVED_NAMES.contains(&name)
+}
+
+/// Gets the root uri from which the registry is served and its domain
+fn get_web_public_uri() -> Result<(String, String), ApiError> {
+    let web_public_uri = get_var_for_tenant("REGISTRY_WEB_PUBLIC_URI", None, |main, tenant| format!("{main}/{tenant}"))?;
+    let web_domain = Uri::from_str(&web_public_uri)
+        .map_err(|e| error_invalid_var("REGISTRY_WEB_PUBLIC_URI", e))?
+        .host()
+        .unwrap_or_default()
+        .to_string();
+    Ok((web_public_uri, web_domain))
+}
+
+/// Gets the name to use for the registry in cargo and git config, the name of the tenant by default for tenants
+fn get_self_local_name(web_domain: &str) -> Result<String, EnvVarError> {
+    if let Some(tenant) = get_current_tenant() {
+        return Ok(get_tenant_var("REGISTRY_SELF_LOCAL_NAME", &tenant)?.unwrap_or(tenant));
+    }
+    Ok(match get_var_opt("REGISTRY_SELF_LOCAL_NAME")? {
+        Some(value) => value,
+        None => match web_domain.rfind('.') {
+            Some(index) => web_domain[index..].to_string(),
+            None => web_domain.to_string(),
+        },
+    })
+}
+
 /// The specification of the storage system to use
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum StorageConfig {
@@ -107,7 +470,7 @@ pub enum StorageConfig {
     /// An S3 bucket
     S3 {
         /// The parameters to connect to S3
-        params: S3Params,
+        params: Box<S3Params>,
         /// The name of the s3 bucket to use
         bucket: String,
     },
@@ -115,21 +478,33 @@ pub enum StorageConfig {
 
 impl StorageConfig {
     /// Loads the configuration for a registry from the environment
-    fn from_env() -> Result<StorageConfig, MissingEnvVar> {
+    fn from_env() -> Result<StorageConfig, ApiError> {
         let storage_kind = get_var("REGISTRY_STORAGE")?;
         Ok(match storage_kind.as_str() {
             "s3" | "S3" => StorageConfig::S3 {
-                params: S3Params {
+                params: Box::new(S3Params {
                     endpoint: get_var("REGISTRY_S3_URI")?,
                     region: get_var("REGISTRY_S3_REGION")?,
-                    access_key: get_var("REGISTRY_S3_ACCESS_KEY")?,
-                    secret_key: get_var("REGISTRY_S3_SECRET_KEY")?,
-                    root: get_var("REGISTRY_S3_ROOT").unwrap_or_default(),
-                },
+                    access_key: get_var_opt("REGISTRY_S3_ACCESS_KEY")?.unwrap_or_default(),
+                    secret_key: get_var_opt("REGISTRY_S3_SECRET_KEY")?.unwrap_or_default(),
+                    role_arn: get_var_opt("REGISTRY_S3_ROLE_ARN")?.filter(|v| !v.is_empty()),
+                    external_id: get_var_opt("REGISTRY_S3_EXTERNAL_ID")?.filter(|v| !v.is_empty()),
+                    role_session_name: get_var_opt("REGISTRY_S3_ROLE_SESSION_NAME")?.filter(|v| !v.is_empty()),
+                    instance_metadata: get_var_opt("REGISTRY_S3_INSTANCE_METADATA")?.is_none_or(|v| v == "true"),
+                    root: get_var_for_tenant("REGISTRY_S3_ROOT", Some(""), |main, tenant| {
+                        format!("{}/{tenant}", main.trim_end_matches('/'))
+                    })?,
+                    addressing_style: get_var_parsed("REGISTRY_S3_ADDRESSING_STYLE", S3AddressingStyle::Path)?,
+                    request_timeout: get_var_parsed("REGISTRY_S3_REQUEST_TIMEOUT", S3_DEFAULT_REQUEST_TIMEOUT)?,
+                    pool_max_idle: get_var_parsed("REGISTRY_S3_POOL_MAX_IDLE", S3_DEFAULT_POOL_MAX_IDLE)?,
+                    pool_idle_timeout: get_var_parsed("REGISTRY_S3_POOL_IDLE_TIMEOUT", S3_DEFAULT_POOL_IDLE_TIMEOUT)?,
+                    checksum_uploads: get_var_opt("REGISTRY_S3_CHECKSUM_UPLOADS")?.is_none_or(|v| v == "true"),
+                    verify_etag: get_var_opt("REGISTRY_S3_VERIFY_ETAG")?.is_none_or(|v| v == "true"),
+                }),
                 bucket: get_var("REGISTRY_S3_BUCKET")?,
             },
             "" | "fs" | "FS" | "filesystem" | "FileSystem" => StorageConfig::FileSystem,
-            _ => panic!("invalid REGISTRY_STORAGE"),
+            _ => return Err(error_invalid_var("REGISTRY_STORAGE", storage_kind)),
         })
     }
 }
@@ -141,16 +516,79 @@ pub struct S3Params {
     pub endpoint: String,
     /// The region to target
     pub region: String,
-    /// The account access key
+    /// The account access key, empty to look for the credentials in the environment
     #[serde(rename = "accessKey")]
     pub access_key: String,
-    /// The account secret key
+    /// The account secret key, empty to look for the credentials in the environment
     #[serde(rename = "secretKey")]
     pub secret_key: String,
+    /// The ARN of a role to assume through STS, with the credentials from the access keys or the environment
+    #[serde(rename = "roleArn")]
+    pub role_arn: Option<String>,
+    /// The external identifier expected by the role to assume, if any
+    #[serde(rename = "externalId")]
+    pub external_id: Option<String>,
+    /// The name of the session when assuming the role, if any
+    #[serde(rename = "roleSessionName")]
+    pub role_session_name: Option<String>,
+    /// Whether the credentials can be obtained from the metadata service of an EC2 instance
+    #[serde(rename = "instanceMetadata")]
+    pub instance_metadata: bool,
     /// The prefix to use for the keys
     pub root: String,
+    /// How the bucket is addressed in the URLs of the requests
+    #[serde(rename = "addressingStyle")]
+    pub addressing_style: S3AddressingStyle,
+    /// Number of seconds after which a whole request to S3 is aborted
+    #[serde(rename = "requestTimeout")]
+    pub request_timeout: u64,
+    /// The maximum number of idle connections to S3 kept open for reuse
+    #[serde(rename = "poolMaxIdle")]
+    pub pool_max_idle: usize,
+    /// Number of seconds after which an idle connection to S3 is closed
+    #[serde(rename = "poolIdleTimeout")]
+    pub pool_idle_timeout: u64,
+    /// Whether a checksum of the content is sent with the uploads, for S3 to reject corrupted ones
+    #[serde(rename = "checksumUploads")]
+    pub checksum_uploads: bool,
+    /// Whether the `ETag` of the uploaded objects is verified against the MD5 digest of the content
+    #[serde(rename = "verifyEtag")]
+    pub verify_etag: bool,
+}
+
+/// How the bucket is addressed in the URLs of the requests to S3
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum S3AddressingStyle {
+    /// The bucket is in the path, e.g. `https://s3.acme.com/bucket/key`, as supported by `MinIO` and Ceph
+    #[default]
+    Path,
+    /// The bucket is in the host name, e.g. `https://bucket.s3.amazonaws.com/key`, as expected by AWS
+    VirtualHosted,
+}
+
+impl FromStr for S3AddressingStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "path" => Ok(Self::Path),
+            "virtual-hosted" | "virtual" => Ok(Self::VirtualHosted),
+            _ => Err(format!("expected path or virtual-hosted, got {s}")),
+        }
+    }
 }
 
+/// The default number of seconds after which a whole request to S3 is aborted
+const S3_DEFAULT_REQUEST_TIMEOUT: u64 = 300;
+/// The default maximum number of idle connections to S3
+const S3_DEFAULT_POOL_MAX_IDLE: usize = 32;
+/// The default number of seconds after which an idle connection to S3 is closed
+const S3_DEFAULT_POOL_IDLE_TIMEOUT: u64 = 90;
+
+/// The default number of index files and crate metadata kept in memory
+const INDEX_DEFAULT_CACHE_CAPACITY: usize = 4096;
+
 /// The configuration in the index
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct IndexConfig {
@@ -180,24 +618,28 @@ pub struct IndexConfig {
     /// The user email to use for commits
     #[serde(rename = "userEmail")]
     pub user_email: String,
+    /// The maximum number of index files and crate metadata kept in memory, 0 to disable the cache
+    #[serde(rename = "cacheCapacity")]
+    pub cache_capacity: usize,
     /// The public configuration
     pub public: IndexPublicConfig,
 }
 
 impl IndexConfig {
     /// Loads the configuration for a registry from the environment
-    fn from_env(home_dir: &str, data_dir: &str, web_public_uri: &str) -> Result<IndexConfig, MissingEnvVar> {
+    fn from_env(home_dir: &str, data_dir: &str, web_public_uri: &str) -> Result<IndexConfig, ApiError> {
         Ok(IndexConfig {
             home_dir: home_dir.to_string(),
             location: format!("{data_dir}/index"),
-            allow_protocol_git: get_var("REGISTRY_INDEX_PROTOCOL_GIT").map(|v| v == "true").unwrap_or(false),
-            allow_protocol_sparse: get_var("REGISTRY_INDEX_PROTOCOL_SPARSE").map(|v| v == "true").unwrap_or(true),
-            remote_origin: get_var("REGISTRY_GIT_REMOTE").ok(),
-            remote_ssh_key_file_name: get_var("REGISTRY_GIT_REMOTE_SSH_KEY_FILENAME").ok(),
-            remote_push_changes: get_var("REGISTRY_GIT_REMOTE_PUSH_CHANGES")
-                .is_ok_and(|value| value == "1" || value.eq_ignore_ascii_case("true")),
+            allow_protocol_git: get_var_opt("REGISTRY_INDEX_PROTOCOL_GIT")?.is_some_and(|v| v == "true"),
+            allow_protocol_sparse: get_var_opt("REGISTRY_INDEX_PROTOCOL_SPARSE")?.is_none_or(|v| v == "true"),
+            remote_origin: get_var_opt("REGISTRY_GIT_REMOTE")?,
+            remote_ssh_key_file_name: get_var_opt("REGISTRY_GIT_REMOTE_SSH_KEY_FILENAME")?,
+            remote_push_changes: get_var_opt("REGISTRY_GIT_REMOTE_PUSH_CHANGES")?
+                .is_some_and(|value| value == "1" || value.eq_ignore_ascii_case("true")),
             user_name: get_var("REGISTRY_GIT_USER_NAME")?,
             user_email: get_var("REGISTRY_GIT_USER_EMAIL")?,
+            cache_capacity: get_var_parsed("REGISTRY_INDEX_CACHE_CAPACITY", INDEX_DEFAULT_CACHE_CAPACITY)?,
             public: IndexPublicConfig {
                 dl: format!("{web_public_uri}/api/v1/crates"),
                 api: web_public_uri.to_string(),
@@ -205,6 +647,14 @@ impl IndexConfig {
             },
         })
     }
+
+    /// Gets the path to the SSH key for the remote, if any
+    #[must_use]
+    pub fn get_remote_ssh_key_path(&self) -> Option<PathBuf> {
+        self.remote_ssh_key_file_name
+            .as_ref()
+            .map(|file_name| Path::new(&self.home_dir).join(".ssh").join(file_name))
+    }
 }
 
 /// The configuration in the index
@@ -219,6 +669,667 @@ pub struct IndexPublicConfig {
     pub auth_required: bool,
 }
 
+/// The default URI to the sparse index of the proxied registry
+const PROXY_DEFAULT_INDEX_URI: &str = "https://index.crates.io/";
+/// The default URI to download crates from the proxied registry
+const PROXY_DEFAULT_DOWNLOAD_URI: &str = "https://static.crates.io/crates/";
+
+/// The check of the names of the local crates against crates.io, to prevent dependency confusion
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfusionCheck {
+    /// The names are not checked
+    #[default]
+    Off,
+    /// Publishing a new crate whose name exists on crates.io is accepted with a warning
+    Warn,
+    /// Publishing a new crate whose name exists on crates.io is rejected
+    Reject,
+}
+
+impl FromStr for ConfusionCheck {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(Self::Off),
+            "warn" => Ok(Self::Warn),
+            "reject" => Ok(Self::Reject),
+            _ => Err(format!("expected off, warn or reject, got {s}")),
+        }
+    }
+}
+
+/// The configuration for proxying and caching an upstream registry, usually crates.io
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProxyConfig {
+    /// Whether to serve the crates that are not hosted locally from the upstream registry
+    pub enabled: bool,
+    /// The URI to the sparse index of the upstream registry, ending with a trailing /
+    #[serde(rename = "indexUri")]
+    pub index_uri: String,
+    /// The URI to download crates from the upstream registry, ending with a trailing /
+    #[serde(rename = "downloadUri")]
+    pub download_uri: String,
+    /// Number of milliseconds after which a cached index file is deemed stale and must be pulled again
+    #[serde(rename = "staleIndex")]
+    pub stale_index: u64,
+    /// The names of the upstream crates for which all versions are kept mirrored locally
+    #[serde(rename = "mirrorCrates")]
+    pub mirror_crates: Vec<String>,
+    /// The path to a `Cargo.lock` file listing upstream crate versions to keep mirrored locally
+    #[serde(rename = "mirrorLockFile")]
+    pub mirror_lock_file: Option<String>,
+    /// Number of seconds between each synchronization of the mirrored crates
+    #[serde(rename = "mirrorPeriod")]
+    pub mirror_period: u64,
+    /// The check of the names of the local crates against the upstream registry, to prevent dependency confusion
+    #[serde(rename = "confusionCheck")]
+    pub confusion_check: ConfusionCheck,
+    /// Number of seconds between each check of all the local crates against the upstream registry, `0` to deactivate
+    #[serde(rename = "confusionCheckPeriod")]
+    pub confusion_check_period: u64,
+}
+
+impl Default for ProxyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            index_uri: String::from(PROXY_DEFAULT_INDEX_URI),
+            download_uri: String::from(PROXY_DEFAULT_DOWNLOAD_URI),
+            stale_index: 5 * 60 * 1000, // 5 minutes
+            mirror_crates: Vec::new(),
+            mirror_lock_file: None,
+            mirror_period: 60 * 60, // 1 hour
+            confusion_check: ConfusionCheck::Off,
+            confusion_check_period: 24 * 60 * 60, // 1 day
+        }
+    }
+}
+
+impl ProxyConfig {
+    /// Loads the configuration for the proxy from the environment
+    fn from_env() -> Result<Self, ApiError> {
+        let with_trailing_slash = |mut uri: String| {
+            if !uri.ends_with('/') {
+                uri.push('/');
+            }
+            uri
+        };
+        Ok(Self {
+            enabled: get_var_opt("REGISTRY_PROXY_ENABLED")?.is_some_and(|v| v == "true"),
+            index_uri: with_trailing_slash(
+                get_var_opt("REGISTRY_PROXY_INDEX_URI")?.unwrap_or_else(|| String::from(PROXY_DEFAULT_INDEX_URI)),
+            ),
+            download_uri: with_trailing_slash(
+                get_var_opt("REGISTRY_PROXY_DOWNLOAD_URI")?.unwrap_or_else(|| String::from(PROXY_DEFAULT_DOWNLOAD_URI)),
+            ),
+            stale_index: get_var_parsed("REGISTRY_PROXY_STALE_INDEX", 5 * 60 * 1000)?,
+            mirror_crates: get_var_opt("REGISTRY_PROXY_MIRROR_CRATES")?
+                .map(|v| comma_sep_to_vec(&v))
+                .unwrap_or_default(),
+            mirror_lock_file: get_var_opt("REGISTRY_PROXY_MIRROR_LOCK_FILE")?,
+            mirror_period: get_var_parsed("REGISTRY_PROXY_MIRROR_PERIOD", 60 * 60)?,
+            confusion_check: get_var_parsed("REGISTRY_PROXY_CONFUSION_CHECK", ConfusionCheck::Off)?,
+            confusion_check_period: get_var_parsed("REGISTRY_PROXY_CONFUSION_CHECK_PERIOD", 24 * 60 * 60)?, // 1 day
+        })
+    }
+}
+
+/// The configuration for the read-only maintenance mode
+#[derive(Debug, Default, Serialize, Deserialize, Clone, ToSchema)]
+pub struct MaintenanceConfig {
+    /// Whether mutating operations are rejected, while downloads and the index stay available
+    pub enabled: bool,
+    /// The message explaining the maintenance, returned to clients
+    pub message: Option<String>,
+}
+
+impl MaintenanceConfig {
+    /// Loads the initial state of the maintenance mode from the environment
+    fn from_env() -> Result<Self, ApiError> {
+        Ok(Self {
+            enabled: get_var_opt("REGISTRY_MAINTENANCE_ENABLED")?.is_some_and(|v| v == "true"),
+            message: get_var_opt("REGISTRY_MAINTENANCE_MESSAGE")?,
+        })
+    }
+}
+
+/// The configuration for the scheduled backups of the database
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct BackupConfig {
+    /// Number of seconds between two backups, backups are disabled when 0
+    pub period: u64,
+    /// The number of backups to keep, older ones are pruned
+    pub retention: usize,
+    /// The number of backups in a chain, starting with a full backup followed by incremental ones
+    #[serde(rename = "fullEvery")]
+    pub full_every: usize,
+    /// The base64-encoded 256-bit key to encrypt the backups with, if any
+    #[serde(rename = "encryptionKey")]
+    pub encryption_key: Option<String>,
+}
+
+impl BackupConfig {
+    /// Loads the configuration for the backups from the environment
+    fn from_env() -> Result<Self, ApiError> {
+        let encryption_key = get_var_opt("REGISTRY_BACKUP_ENCRYPTION_KEY")?;
+        if let Some(key) = &encryption_key {
+            let decoded = STANDARD
+                .decode(key)
+                .map_err(|_| error_invalid_var("REGISTRY_BACKUP_ENCRYPTION_KEY", "expected base64"))?;
+            if decoded.len() != 32 {
+                return Err(error_invalid_var("REGISTRY_BACKUP_ENCRYPTION_KEY", "expected 32 bytes"));
+            }
+        }
+        Ok(Self {
+            period: get_var_parsed("REGISTRY_BACKUP_PERIOD", 0)?,
+            retention: get_var_parsed("REGISTRY_BACKUP_RETENTION", 7)?,
+            full_every: get_var_parsed("REGISTRY_BACKUP_FULL_EVERY", 1)?,
+            encryption_key,
+        })
+    }
+
+    /// Gets the decoded key to encrypt the backups with, if any
+    ///
+    /// # Errors
+    ///
+    /// Return an error when the key is not valid base64
+    pub fn get_encryption_key(&self) -> Result<Option<Vec<u8>>, ApiError> {
+        self.encryption_key
+            .as_deref()
+            .map(|key| {
+                STANDARD.decode(key).map_err(|_| {
+                    specialize(
+                        error_invalid_request(),
+                        String::from("the encryption key for the backups must be base64-encoded"),
+                    )
+                })
+            })
+            .transpose()
+    }
+
+    /// Gets whether the scheduled backups are enabled
+    #[must_use]
+    pub fn is_enabled(&self) -> bool {
+        self.period > 0
+    }
+}
+
+/// The default channel used on Redis to propagate the invalidations of the caches
+const REDIS_DEFAULT_CHANNEL: &str = "cratery:invalidations";
+
+/// The configuration for the Redis server shared between the instances of the registry
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RedisConfig {
+    /// The URI of the Redis server, if any
+    pub uri: Option<String>,
+    /// The channel used to propagate the invalidations of the caches
+    pub channel: String,
+}
+
+impl Default for RedisConfig {
+    fn default() -> Self {
+        Self {
+            uri: None,
+            channel: String::from(REDIS_DEFAULT_CHANNEL),
+        }
+    }
+}
+
+impl RedisConfig {
+    /// Loads the configuration for Redis from the environment
+    fn from_env() -> Result<Self, ApiError> {
+        Ok(Self {
+            uri: get_var_opt("REGISTRY_REDIS_URI")?,
+            channel: get_var_for_tenant("REGISTRY_REDIS_CHANNEL", Some(REDIS_DEFAULT_CHANNEL), |main, tenant| {
+                format!("{main}:{tenant}")
+            })?,
+        })
+    }
+}
+
+/// The configuration for the in-memory cache of the data about the crates, as shown on their page
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CrateInfoCacheConfig {
+    /// The maximum number of crates kept in memory, 0 to disable the cache
+    pub capacity: usize,
+    /// Number of seconds after which a cached entry is refreshed,
+    /// which bounds the staleness of the data changed by other instances
+    #[serde(rename = "maxAge")]
+    pub max_age: u64,
+}
+
+impl Default for CrateInfoCacheConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 1024,
+            max_age: 300,
+        }
+    }
+}
+
+impl CrateInfoCacheConfig {
+    /// Loads the configuration for the cache from the environment
+    fn from_env() -> Result<Self, ApiError> {
+        let default = Self::default();
+        Ok(Self {
+            capacity: get_var_parsed("REGISTRY_CRATE_INFO_CACHE_CAPACITY", default.capacity)?,
+            max_age: get_var_parsed("REGISTRY_CRATE_INFO_CACHE_MAX_AGE", default.max_age)?,
+        })
+    }
+}
+
+/// The configuration for the quarantine of the newly published versions
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct QuarantineConfig {
+    /// Number of seconds during which a newly published version is only available to the owners of the crate, disabled when 0
+    pub period: u64,
+    /// Whether a version is released before the end of its quarantine as soon as the analysis of its dependencies passes
+    #[serde(rename = "releaseOnChecks")]
+    pub release_on_checks: bool,
+}
+
+impl QuarantineConfig {
+    /// Loads the configuration for the quarantine from the environment
+    fn from_env() -> Result<Self, ApiError> {
+        Ok(Self {
+            period: get_var_parsed("REGISTRY_QUARANTINE_PERIOD", 0)?,
+            release_on_checks: get_var_opt("REGISTRY_QUARANTINE_RELEASE_ON_CHECKS")?.is_some_and(|v| v == "true"),
+        })
+    }
+
+    /// Gets whether the newly published versions are quarantined
+    #[must_use]
+    pub fn is_enabled(&self) -> bool {
+        self.period > 0
+    }
+}
+
+/// The default quotas on the crates published by each user
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct QuotaConfig {
+    /// The maximum total size in bytes of the `.crate` files uploaded by a user, no limit when 0
+    #[serde(rename = "maxBytes")]
+    pub max_bytes: u64,
+    /// The maximum number of crates owned by a user, no limit when 0
+    #[serde(rename = "maxCrates")]
+    pub max_crates: u64,
+}
+
+impl QuotaConfig {
+    /// Loads the default quotas from the environment
+    fn from_env() -> Result<Self, ApiError> {
+        Ok(Self {
+            max_bytes: get_var_parsed("REGISTRY_QUOTA_MAX_BYTES", 0)?,
+            max_crates: get_var_parsed("REGISTRY_QUOTA_MAX_CRATES", 0)?,
+        })
+    }
+}
+
+/// The action taken when secrets are found in the content of a published crate
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SecretsScan {
+    /// The content is not scanned
+    #[default]
+    Off,
+    /// The publication is accepted with a warning
+    Warn,
+    /// The publication is rejected
+    Reject,
+}
+
+impl FromStr for SecretsScan {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(Self::Off),
+            "warn" => Ok(Self::Warn),
+            "reject" => Ok(Self::Reject),
+            _ => Err(format!("expected off, warn or reject, got {s}")),
+        }
+    }
+}
+
+/// A pattern for a kind of secret to look for in the content of published crates
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SecretPattern {
+    /// The name for the kind of secret, used in the reports
+    pub name: String,
+    /// The regular expression matching a secret on a single line
+    pub regex: String,
+}
+
+impl SecretPattern {
+    /// Creates a pattern
+    fn new(name: &str, regex: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            regex: regex.to_string(),
+        }
+    }
+
+    /// Gets the built-in patterns
+    #[must_use]
+    pub fn builtins() -> Vec<Self> {
+        vec![
+            Self::new("AWS access key", r"\b(AKIA|ASIA)[0-9A-Z]{16}\b"),
+            Self::new(
+                "AWS secret key",
+                r#"(?i)aws_secret_access_key\s*[=:]\s*["']?[A-Za-z0-9/+=]{40}"#,
+            ),
+            Self::new("private key", r"-----BEGIN ([A-Z]+ )?PRIVATE KEY( BLOCK)?-----"),
+            Self::new(
+                "GitHub token",
+                r"\b(gh[pousr]_[A-Za-z0-9]{36,}|github_pat_[A-Za-z0-9_]{22,})\b",
+            ),
+            Self::new("GitLab token", r"\bglpat-[A-Za-z0-9_-]{20,}\b"),
+            Self::new("Slack token", r"\bxox[abposr]-[A-Za-z0-9-]{10,}"),
+        ]
+    }
+}
+
+/// The configuration for scanning the content of published crates for secrets
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SecretsScanConfig {
+    /// The action taken when secrets are found
+    pub mode: SecretsScan,
+    /// The patterns to look for, the built-in ones followed by the custom ones
+    pub patterns: Vec<SecretPattern>,
+}
+
+impl Default for SecretsScanConfig {
+    fn default() -> Self {
+        Self {
+            mode: SecretsScan::Off,
+            patterns: SecretPattern::builtins(),
+        }
+    }
+}
+
+impl SecretsScanConfig {
+    /// Loads the configuration for the scan of secrets from the environment
+    fn from_env() -> Result<Self, ApiError> {
+        let mut patterns = SecretPattern::builtins();
+        let mut index = 1;
+        while let Some(regex) = get_var_opt(format!("REGISTRY_SECRETS_SCAN_PATTERN_{index}"))? {
+            if let Err(error) = regex::Regex::new(&regex) {
+                return Err(error_invalid_var(&format!("REGISTRY_SECRETS_SCAN_PATTERN_{index}"), error));
+            }
+            let name = get_var_opt(format!("REGISTRY_SECRETS_SCAN_PATTERN_{index}_NAME"))?
+                .unwrap_or_else(|| format!("pattern {index}"));
+            patterns.push(SecretPattern { name, regex });
+            index += 1;
+        }
+        Ok(Self {
+            mode: get_var_parsed("REGISTRY_SECRETS_SCAN", SecretsScan::Off)?,
+            patterns,
+        })
+    }
+}
+
+/// The CORS policy applied to the API, for a web frontend hosted on another origin
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct CorsConfig {
+    /// The origins allowed to call the API, `*` for any origin, CORS is disabled when empty
+    #[serde(rename = "allowedOrigins")]
+    pub allowed_origins: Vec<String>,
+    /// The allowed HTTP methods
+    #[serde(rename = "allowedMethods")]
+    pub allowed_methods: Vec<String>,
+    /// The allowed request headers
+    #[serde(rename = "allowedHeaders")]
+    pub allowed_headers: Vec<String>,
+    /// Whether to allow credentials (cookies) in cross-origin requests
+    #[serde(rename = "allowCredentials")]
+    pub allow_credentials: bool,
+}
+
+impl CorsConfig {
+    /// Loads the CORS policy from the environment
+    ///
+    /// # Errors
+    ///
+    /// Return an error when an origin, a method or a header is invalid,
+    /// or when credentials are allowed for all the origins
+    pub(crate) fn from_env() -> Result<Self, ApiError> {
+        let config = Self {
+            allowed_origins: get_var_opt("REGISTRY_WEB_CORS_ORIGINS")?.map_or_else(Vec::new, |s| comma_sep_to_vec(&s)),
+            allowed_methods: get_var_opt("REGISTRY_WEB_CORS_METHODS")?.map_or_else(
+                || {
+                    ["GET", "POST", "PUT", "PATCH", "DELETE"]
+                        .into_iter()
+                        .map(String::from)
+                        .collect()
+                },
+                |s| comma_sep_to_vec(&s),
+            ),
+            allowed_headers: get_var_opt("REGISTRY_WEB_CORS_HEADERS")?.map_or_else(
+                || vec![String::from("authorization"), String::from("content-type")],
+                |s| comma_sep_to_vec(&s),
+            ),
+            allow_credentials: get_var_opt("REGISTRY_WEB_CORS_CREDENTIALS")?.is_some_and(|v| v == "true"),
+        };
+        for origin in &config.allowed_origins {
+            if origin != "*" && !origin.parse::<Uri>().is_ok_and(|uri| uri.scheme().is_some()) {
+                return Err(error_invalid_var(
+                    "REGISTRY_WEB_CORS_ORIGINS",
+                    format!("invalid origin {origin}"),
+                ));
+            }
+        }
+        for method in &config.allowed_methods {
+            method
+                .parse::<Method>()
+                .map_err(|_| error_invalid_var("REGISTRY_WEB_CORS_METHODS", format!("invalid method {method}")))?;
+        }
+        for header in &config.allowed_headers {
+            header
+                .parse::<HeaderName>()
+                .map_err(|_| error_invalid_var("REGISTRY_WEB_CORS_HEADERS", format!("invalid header {header}")))?;
+        }
+        if config.allow_credentials && config.allowed_origins.iter().any(|origin| origin == "*") {
+            return Err(error_invalid_var(
+                "REGISTRY_WEB_CORS_CREDENTIALS",
+                "cannot be used with the `*` origin",
+            ));
+        }
+        Ok(config)
+    }
+
+    /// Gets whether a CORS policy is configured
+    #[must_use]
+    pub fn is_enabled(&self) -> bool {
+        !self.allowed_origins.is_empty()
+    }
+}
+
+/// The default value for the `Strict-Transport-Security` header
+const SECURITY_DEFAULT_HSTS: &str = "max-age=31536000; includeSubDomains";
+/// The default value for the `X-Frame-Options` header
+const SECURITY_DEFAULT_FRAME_OPTIONS: &str = "SAMEORIGIN";
+/// The default content security policy for the web application, which uses inline scripts and resources from CDNs
+const SECURITY_DEFAULT_CSP: &str = "default-src 'self'; \
+    script-src 'self' 'unsafe-inline' https://cdn.tailwindcss.com https://cdn.jsdelivr.net https://cdnjs.cloudflare.com; \
+    style-src 'self' 'unsafe-inline' https://cdnjs.cloudflare.com; \
+    img-src 'self' data: https:; font-src 'self' data:; connect-src 'self'; frame-ancestors 'self'";
+/// The default content security policy for the generated documentation
+///
+/// Rustdoc loads all its resources with script tags, so that connections can be denied
+/// to prevent scripts in doc comments from calling the API with the credentials of the reader.
+const SECURITY_DEFAULT_DOCS_CSP: &str = "default-src 'self'; script-src 'self' 'unsafe-inline'; \
+    style-src 'self' 'unsafe-inline'; img-src 'self' data: https:; font-src 'self' data:; \
+    connect-src 'none'; form-action 'none'; frame-ancestors 'self'";
+
+/// The security headers applied to all the responses, each being omitted when not set
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SecurityHeadersConfig {
+    /// The value of the `Strict-Transport-Security` header
+    pub hsts: Option<String>,
+    /// The value of the `X-Frame-Options` header
+    #[serde(rename = "frameOptions")]
+    pub frame_options: Option<String>,
+    /// The content security policy for the web application and the API
+    #[serde(rename = "contentSecurityPolicy")]
+    pub content_security_policy: Option<String>,
+    /// The content security policy for the generated documentation
+    #[serde(rename = "docsContentSecurityPolicy")]
+    pub docs_content_security_policy: Option<String>,
+    /// Whether to send `X-Content-Type-Options: nosniff`
+    #[serde(rename = "noSniff")]
+    pub no_sniff: bool,
+}
+
+impl Default for SecurityHeadersConfig {
+    fn default() -> Self {
+        Self {
+            hsts: Some(String::from(SECURITY_DEFAULT_HSTS)),
+            frame_options: Some(String::from(SECURITY_DEFAULT_FRAME_OPTIONS)),
+            content_security_policy: Some(String::from(SECURITY_DEFAULT_CSP)),
+            docs_content_security_policy: Some(String::from(SECURITY_DEFAULT_DOCS_CSP)),
+            no_sniff: true,
+        }
+    }
+}
+
+impl SecurityHeadersConfig {
+    /// Loads the security headers from the environment
+    ///
+    /// # Errors
+    ///
+    /// Return an error when a header has an invalid value
+    pub(crate) fn from_env() -> Result<Self, ApiError> {
+        // an empty value removes the header
+        let header = |name: &str, default: &str| {
+            let value = get_var_opt(name)?.unwrap_or_else(|| default.to_string());
+            HeaderValue::from_str(&value).map_err(|e| error_invalid_var(name, e))?;
+            Ok::<_, ApiError>(Some(value).filter(|v| !v.is_empty()))
+        };
+        Ok(Self {
+            hsts: header("REGISTRY_WEB_HSTS", SECURITY_DEFAULT_HSTS)?,
+            frame_options: header("REGISTRY_WEB_FRAME_OPTIONS", SECURITY_DEFAULT_FRAME_OPTIONS)?,
+            content_security_policy: header("REGISTRY_WEB_CSP", SECURITY_DEFAULT_CSP)?,
+            docs_content_security_policy: header("REGISTRY_WEB_DOCS_CSP", SECURITY_DEFAULT_DOCS_CSP)?,
+            no_sniff: get_var_opt("REGISTRY_WEB_NO_SNIFF")?.is_none_or(|v| v == "true"),
+        })
+    }
+}
+
+/// The caching policy announced to the clients and to the caches in front of the registry
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct CacheHeadersConfig {
+    /// Whether shared caches, such as a CDN, may store the responses to authenticated requests
+    pub shared: bool,
+    /// Number of seconds during which shared caches may serve the files of the index without revalidating them
+    #[serde(rename = "indexMaxAge")]
+    pub index_max_age: u64,
+}
+
+impl CacheHeadersConfig {
+    /// Loads the caching policy from the environment
+    fn from_env() -> Result<Self, ApiError> {
+        Ok(Self {
+            shared: get_var_opt("REGISTRY_WEB_CACHE_SHARED")?.is_some_and(|v| v == "true"),
+            index_max_age: get_var_parsed("REGISTRY_WEB_CACHE_INDEX_MAX_AGE", 0)?,
+        })
+    }
+
+    /// Gets the scope of the caches allowed to store the responses
+    fn scope(&self, restricted: bool) -> &'static str {
+        if self.shared && !restricted {
+            "public"
+        } else {
+            "private"
+        }
+    }
+
+    /// Gets the `Cache-Control` value for a content that never changes, such as the content of a crate version
+    ///
+    /// A restricted content is only available to some users and is never stored by shared caches.
+    #[must_use]
+    pub fn immutable(&self, restricted: bool) -> String {
+        format!("{}, max-age=31536000, immutable", self.scope(restricted))
+    }
+
+    /// Gets the `Cache-Control` value for a content that may change, such as the documentation of a crate
+    #[must_use]
+    pub fn revalidated(&self, max_age: u64, restricted: bool) -> String {
+        format!("{}, max-age={max_age}", self.scope(restricted))
+    }
+
+    /// Gets the `Cache-Control` value for the files of the index
+    ///
+    /// Clients always revalidate them so that new versions are seen immediately,
+    /// shared caches may serve them for the configured duration, except for the restricted crates.
+    #[must_use]
+    pub fn index(&self, restricted: bool) -> String {
+        if self.shared && !restricted && self.index_max_age > 0 {
+            format!("public, max-age=0, s-maxage={}", self.index_max_age)
+        } else {
+            format!("{}, no-cache", self.scope(restricted))
+        }
+    }
+}
+
+/// The default minimum size of the responses to compress, in bytes
+const COMPRESSION_DEFAULT_MIN_SIZE: u16 = 1024;
+
+/// The limits of the rate of requests for each client address and each authenticated principal, 0 for no limit
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct RateLimitConfig {
+    /// The maximum number of publications per minute
+    #[serde(rename = "publishPerMinute")]
+    pub publish_per_minute: u32,
+    /// The maximum number of crate downloads per second
+    #[serde(rename = "downloadsPerSecond")]
+    pub downloads_per_second: u32,
+    /// The maximum number of searches per second
+    #[serde(rename = "searchPerSecond")]
+    pub search_per_second: u32,
+}
+
+impl RateLimitConfig {
+    /// Loads the limits of the rate of requests from the environment
+    fn from_env() -> Result<Self, ApiError> {
+        Ok(Self {
+            publish_per_minute: get_var_parsed("REGISTRY_WEB_RATE_LIMIT_PUBLISH", 0)?,
+            downloads_per_second: get_var_parsed("REGISTRY_WEB_RATE_LIMIT_DOWNLOADS", 0)?,
+            search_per_second: get_var_parsed("REGISTRY_WEB_RATE_LIMIT_SEARCH", 0)?,
+        })
+    }
+}
+
+/// The configuration for the compression of the responses
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CompressionConfig {
+    /// Whether the responses are compressed with gzip or brotli when the client accepts it
+    pub enabled: bool,
+    /// The minimum size of the responses to compress, in bytes
+    #[serde(rename = "minSize")]
+    pub min_size: u16,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_size: COMPRESSION_DEFAULT_MIN_SIZE,
+        }
+    }
+}
+
+impl CompressionConfig {
+    /// Loads the configuration for the compression from the environment
+    fn from_env() -> Result<Self, ApiError> {
+        Ok(Self {
+            enabled: get_var_opt("REGISTRY_WEB_COMPRESSION")?.is_none_or(|v| v == "true"),
+            min_size: get_var_parsed("REGISTRY_WEB_COMPRESSION_MIN_SIZE", COMPRESSION_DEFAULT_MIN_SIZE)?,
+        })
+    }
+}
+
 /// The SMTP configuration to use to send emails
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
 pub struct SmtpConfig {
@@ -234,12 +1345,10 @@ pub struct SmtpConfig {
 
 impl SmtpConfig {
     /// Loads the configuration for a registry from the environment
-    fn from_env() -> Result<Self, MissingEnvVar> {
+    fn from_env() -> Result<Self, ApiError> {
         Ok(Self {
             host: get_var("REGISTRY_EMAIL_SMTP_HOST")?,
-            port: get_var("REGISTRY_EMAIL_SMTP_PORT")
-                .map(|s| s.parse().expect("invalid REGISTRY_EMAIL_SMTP_PORT"))
-                .unwrap_or(465),
+            port: get_var_parsed("REGISTRY_EMAIL_SMTP_PORT", 465)?,
             login: get_var("REGISTRY_EMAIL_SMTP_LOGIN")?,
             password: get_var("REGISTRY_EMAIL_SMTP_PASSWORD")?,
         })
@@ -258,15 +1367,42 @@ pub struct EmailConfig {
 
 impl EmailConfig {
     /// Loads the configuration for a registry from the environment
-    fn from_env() -> Result<Self, MissingEnvVar> {
+    fn from_env() -> Result<Self, ApiError> {
         Ok(Self {
             smtp: SmtpConfig::from_env()?,
             sender: get_var("REGISTRY_EMAIL_SENDER")?,
-            cc: get_var("REGISTRY_EMAIL_CC").unwrap_or_default(),
+            cc: get_var_opt("REGISTRY_EMAIL_CC")?.unwrap_or_default(),
         })
     }
 }
 
+/// The configuration for posting notifications to chat services
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct ChatConfig {
+    /// The URI of the Slack incoming webhook to post to, if any
+    #[serde(rename = "slackWebhook")]
+    pub slack_webhook: Option<String>,
+    /// The URI of the Microsoft Teams incoming webhook to post to, if any
+    #[serde(rename = "teamsWebhook")]
+    pub teams_webhook: Option<String>,
+}
+
+impl ChatConfig {
+    /// Loads the configuration for the chat notifications from the environment
+    fn from_env() -> Result<Self, ApiError> {
+        Ok(Self {
+            slack_webhook: get_var_opt("REGISTRY_NOTIFY_SLACK_WEBHOOK")?,
+            teams_webhook: get_var_opt("REGISTRY_NOTIFY_TEAMS_WEBHOOK")?,
+        })
+    }
+
+    /// Gets whether at least one chat service is configured
+    #[must_use]
+    pub fn is_enabled(&self) -> bool {
+        self.slack_webhook.is_some() || self.teams_webhook.is_some()
+    }
+}
+
 /// The configuration specific to master nodes
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
 pub struct NodeRoleMaster {
@@ -303,18 +1439,17 @@ pub enum NodeRole {
 
 impl NodeRole {
     /// Loads the configuration for a registry from the environment
-    fn from_env() -> Result<Self, MissingEnvVar> {
-        let role_name = get_var("REGISTRY_NODE_ROLE").ok();
+    fn from_env() -> Result<Self, EnvVarError> {
+        let role_name = get_var_opt("REGISTRY_NODE_ROLE")?;
         match role_name.as_deref() {
             Some("master") => Ok(Self::Master(NodeRoleMaster {
-                worker_token: get_var("REGISTRY_NODE_WORKER_TOKEN").ok(),
+                worker_token: get_var_opt("REGISTRY_NODE_WORKER_TOKEN")?,
             })),
             Some("worker") => Ok(Self::Worker(NodeRoleWorker {
                 name: get_var("REGISTRY_NODE_WORKER_NAME")?,
                 worker_token: get_var("REGISTRY_NODE_WORKER_TOKEN")?,
                 master_uri: get_var("REGISTRY_NODE_MASTER_URI")?,
-                capabilities: get_var("REGISTRY_NODE_WORKER_CAPABILITIES")
-                    .ok()
+                capabilities: get_var_opt("REGISTRY_NODE_WORKER_CAPABILITIES")?
                     .as_deref()
                     .map(comma_sep_to_vec)
                     .unwrap_or_default(),
@@ -356,6 +1491,30 @@ pub struct Configuration {
     /// The port to bind for the web server
     #[serde(rename = "webListenOnPort")]
     pub web_listenon_port: u16,
+    /// The proxies in front of the web server whose forwarding headers are trusted to get the address of clients
+    #[serde(rename = "webTrustedProxies")]
+    pub web_trusted_proxies: Vec<IpNet>,
+    /// The path to the Unix domain socket to bind for the web server, instead of the IP and port
+    #[serde(rename = "webListenOnSocket")]
+    pub web_listenon_socket: Option<String>,
+    /// The permissions to set on the Unix domain socket, if any
+    #[serde(rename = "webListenOnSocketMode")]
+    pub web_listenon_socket_mode: Option<u32>,
+    /// The CORS policy for the API
+    #[serde(rename = "webCors")]
+    pub web_cors: CorsConfig,
+    /// The security headers applied to all the responses
+    #[serde(rename = "webSecurityHeaders")]
+    pub web_security_headers: SecurityHeadersConfig,
+    /// The caching policy for the downloads, the documentation and the index
+    #[serde(rename = "webCacheHeaders")]
+    pub web_cache_headers: CacheHeadersConfig,
+    /// The compression of the responses
+    #[serde(rename = "webCompression")]
+    pub web_compression: CompressionConfig,
+    /// The limits of the rate of requests
+    #[serde(rename = "webRateLimit")]
+    pub web_rate_limit: RateLimitConfig,
     /// The root uri from which the application is served
     #[serde(rename = "webPublicUri")]
     pub web_public_uri: String,
@@ -368,6 +1527,9 @@ pub struct Configuration {
     /// The path to the local resources to serve as the web app
     #[serde(rename = "webHotReloadPath")]
     pub web_hot_reload_path: Option<String>,
+    /// Number of seconds to wait for the in-flight requests and the running jobs to finish when shutting down
+    #[serde(rename = "webShutdownGracePeriod")]
+    pub web_shutdown_grace_period: u64,
     /// The home directory where the .cargo, .git are expected to be located
     #[serde(rename = "homeDir")]
     pub home_dir: String,
@@ -382,6 +1544,14 @@ pub struct Configuration {
     /// Timeout (in milli-seconds) to use when interacting with the storage
     #[serde(rename = "storageTimeout")]
     pub storage_timeout: u64,
+    /// The configuration for the Redis server shared between instances
+    pub redis: RedisConfig,
+    /// Number of seconds between each write of the buffered download counts to the database, `0` to write them immediately
+    #[serde(rename = "downloadsFlushPeriod")]
+    pub downloads_flush_period: u64,
+    /// The configuration for the in-memory cache of the data about the crates
+    #[serde(rename = "crateInfoCache")]
+    pub crate_info_cache: CrateInfoCacheConfig,
     /// The uri of the OAuth login page
     #[serde(rename = "oauthLoginUri")]
     pub oauth_login_uri: String,
@@ -434,8 +1604,50 @@ pub struct Configuration {
     /// Whether to send a notification by email to the owners of a crate when CVEs are discovered in its dependencies
     #[serde(rename = "depsNotifyCVEs")]
     pub deps_notify_cves: bool,
+    /// Number of seconds between each synchronization of the `RustSec` advisory database, 0 to deactivate
+    #[serde(rename = "depsAdvisoriesSyncPeriod")]
+    pub deps_advisories_sync_period: u64,
+    /// Whether to send a notification by email to the users invited to be owners of a crate
+    #[serde(rename = "emailNotifyOwners")]
+    pub email_notify_owners: bool,
+    /// Whether to send a notification by email to the owners of the crates depending on a yanked version or a deprecated crate
+    #[serde(rename = "emailNotifyYanks")]
+    pub email_notify_yanks: bool,
+    /// Whether to send a notification by email to the owners of a crate when the generation of its documentation fails
+    #[serde(rename = "emailNotifyDocsFailures")]
+    pub email_notify_docs_failures: bool,
+    /// Whether to send a notification by email to the users watching a crate for its events
+    #[serde(rename = "emailNotifyWatchers")]
+    pub email_notify_watchers: bool,
+    /// The weight of the number of downloads of a crate in the ranking of search results
+    #[serde(rename = "searchWeightDownloads")]
+    pub search_weight_downloads: f64,
+    /// The weight of the date of the last publication of a crate in the ranking of search results
+    #[serde(rename = "searchWeightRecency")]
+    pub search_weight_recency: f64,
+    /// The configuration for proxying the upstream registry
+    pub proxy: ProxyConfig,
+    /// The URI of the HTTP proxy to use for all outbound traffic, if any
+    #[serde(rename = "outboundProxy")]
+    pub outbound_proxy: Option<String>,
+    /// The comma-separated hosts for which the outbound HTTP proxy must not be used, if any
+    #[serde(rename = "outboundNoProxy")]
+    pub outbound_no_proxy: Option<String>,
+    /// The initial state of the read-only maintenance mode
+    pub maintenance: MaintenanceConfig,
+    /// The configuration for the scheduled backups of the database
+    pub backup: BackupConfig,
+    /// The configuration for the quarantine of the newly published versions
+    pub quarantine: QuarantineConfig,
+    /// The default quotas on the crates published by each user
+    pub quota: QuotaConfig,
+    /// The configuration for scanning the content of published crates for secrets
+    #[serde(rename = "secretsScan")]
+    pub secrets_scan: SecretsScanConfig,
     /// The configuration for sending emails
     pub email: EmailConfig,
+    /// The configuration for posting notifications to chat services
+    pub chat: ChatConfig,
     /// The name to use for the local registry in cargo and git config
     #[serde(rename = "selfLocalName")]
     pub self_local_name: String,
@@ -466,6 +1678,12 @@ pub struct Configuration {
     /// The role for this node
     #[serde(rename = "selfRole")]
     pub self_role: NodeRole,
+    /// The name of the tenant for this registry, `None` for the main registry
+    #[serde(default)]
+    pub tenant: Option<String>,
+    /// The tenants hosted by the same process as the main registry
+    #[serde(default)]
+    pub tenants: Vec<TenantConfig>,
 }
 
 impl Default for Configuration {
@@ -473,12 +1691,21 @@ impl Default for Configuration {
         Self {
             log_level: String::from("INFO"),
             log_datetime_format: String::from("[%Y-%m-%d %H:%M:%S]"),
-            web_listenon_ip: IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)),
+            web_listenon_ip: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
             web_listenon_port: 80,
+            web_trusted_proxies: Vec::new(),
+            web_listenon_socket: None,
+            web_listenon_socket_mode: None,
+            web_cors: CorsConfig::default(),
+            web_security_headers: SecurityHeadersConfig::default(),
+            web_cache_headers: CacheHeadersConfig::default(),
+            web_compression: CompressionConfig::default(),
+            web_rate_limit: RateLimitConfig::default(),
             web_public_uri: String::from("http://localhost"),
             web_domain: String::from("localhost"),
             web_body_limit: 10 * 1024 * 1024,
             web_hot_reload_path: None,
+            web_shutdown_grace_period: 25,
             home_dir: String::from("/home/cratery"),
             data_dir: String::from("/data"),
             index: IndexConfig {
@@ -491,6 +1718,7 @@ impl Default for Configuration {
                 remote_push_changes: false,
                 user_name: String::from("Cratery"),
                 user_email: String::from("cratery@localhost"),
+                cache_capacity: INDEX_DEFAULT_CACHE_CAPACITY,
                 public: IndexPublicConfig {
                     dl: String::from("http://localhost/api/v1/crates"),
                     api: String::from("http://localhost"),
@@ -499,6 +1727,9 @@ impl Default for Configuration {
             },
             storage: StorageConfig::FileSystem,
             storage_timeout: 3000,
+            redis: RedisConfig::default(),
+            downloads_flush_period: 10,
+            crate_info_cache: CrateInfoCacheConfig::default(),
             oauth_login_uri: String::new(),
             oauth_token_uri: String::new(),
             oauth_callback_uri: String::new(),
@@ -516,7 +1747,23 @@ impl Default for Configuration {
             deps_stale_analysis: 24 * 60,
             deps_notify_outdated: false,
             deps_notify_cves: false,
+            deps_advisories_sync_period: 6 * 60 * 60,
+            email_notify_owners: false,
+            email_notify_yanks: false,
+            email_notify_docs_failures: false,
+            email_notify_watchers: false,
+            search_weight_downloads: 0.2,
+            search_weight_recency: 1.0,
+            proxy: ProxyConfig::default(),
+            outbound_proxy: None,
+            outbound_no_proxy: None,
+            maintenance: MaintenanceConfig::default(),
+            backup: BackupConfig::default(),
+            quarantine: QuarantineConfig::default(),
+            quota: QuotaConfig::default(),
+            secrets_scan: SecretsScanConfig::default(),
             email: EmailConfig::default(),
+            chat: ChatConfig::default(),
             self_local_name: String::from("localhost"),
             self_service_login: String::new(),
             self_service_token: String::new(),
@@ -527,6 +1774,8 @@ impl Default for Configuration {
             self_installed_targets: Vec::new(),
             self_installable_targets: Vec::new(),
             self_role: NodeRole::Master(NodeRoleMaster::default()),
+            tenant: None,
+            tenants: Vec::new(),
         }
     }
 }
@@ -536,103 +1785,224 @@ impl Configuration {
     ///
     /// # Errors
     ///
-    /// Return a `VarError` when an expected environment variable is not present
-    pub async fn from_env() -> Result<Self, MissingEnvVar> {
-        let home_dir = get_var("REGISTRY_HOME_DIR")
-            .or(get_var("HOME"))
-            .unwrap_or_else(|_| String::from("/home/cratery"));
-        let data_dir = get_var("REGISTRY_DATA_DIR")?;
-        let web_public_uri = get_var("REGISTRY_WEB_PUBLIC_URI")?;
-        let web_domain = Uri::from_str(&web_public_uri)
-            .expect("invalid REGISTRY_WEB_PUBLIC_URI")
-            .host()
-            .unwrap_or_default()
-            .to_string();
-        let self_local_name = match get_var("REGISTRY_SELF_LOCAL_NAME") {
-            Ok(value) => value,
-            Err(_) => match web_domain.rfind('.') {
-                Some(index) => web_domain[index..].to_string(),
-                None => web_domain.clone(),
-            },
+    /// Return an error when an expected environment variable is not present or has an invalid value
+    pub async fn from_env() -> Result<Self, ApiError> {
+        let mut configuration = Self::from_env_vars()?;
+        configuration.self_toolchain_version_stable = get_rustc_version(CHANNEL_STABLE).await?;
+        configuration.self_toolchain_version_nightly = get_rustc_version(CHANNEL_NIGHTLY).await?;
+        configuration.self_toolchain_host = get_rustc_host().await?;
+        configuration.self_known_targets = get_known_targets().await?;
+        configuration.self_installed_targets = get_installed_targets(CHANNEL_NIGHTLY).await?;
+        configuration.self_installable_targets = get_installable_targets(CHANNEL_NIGHTLY).await?;
+        Ok(configuration)
+    }
+
+    /// Gets the part of the configuration that only depends on environment variables, without the toolchain
+    fn from_env_vars() -> Result<Self, ApiError> {
+        let home_dir = match get_var_opt("REGISTRY_HOME_DIR")? {
+            Some(home_dir) => home_dir,
+            None => get_var_opt("HOME")?.unwrap_or_else(|| String::from("/home/cratery")),
         };
+        let tenant = get_current_tenant();
+        let data_dir = get_var_for_tenant("REGISTRY_DATA_DIR", None, |main, tenant| format!("{main}/tenants/{tenant}"))?;
+        let (web_public_uri, web_domain) = get_web_public_uri()?;
+        let self_local_name = get_self_local_name(&web_domain)?;
         let index = IndexConfig::from_env(&home_dir, &data_dir, &web_public_uri)?;
-        let storage = StorageConfig::from_env()?;
-        let deps_notify_outdated = get_var("REGISTRY_DEPS_NOTIFY_OUTDATED").map(|v| v == "true").unwrap_or(false);
-        let deps_notify_cves = get_var("REGISTRY_DEPS_NOTIFY_CVES").map(|v| v == "true").unwrap_or(false);
-        let email = if deps_notify_outdated || deps_notify_cves {
-            EmailConfig::from_env()?
-        } else {
-            EmailConfig::default()
-        };
-        let mut external_registries = Vec::new();
-        let mut external_registry_index = 1;
-        while let Some(registry) = ExternalRegistry::from_env(external_registry_index)? {
-            external_registries.push(registry);
-            external_registry_index += 1;
-        }
-        let self_role = NodeRole::from_env()?;
-        Ok(Self {
-            log_level: get_var("REGISTRY_LOG_LEVEL").unwrap_or_else(|_| String::from("INFO")),
-            log_datetime_format: get_var("REGISTRY_LOG_DATE_TIME_FORMAT")
-                .unwrap_or_else(|_| String::from("[%Y-%m-%d %H:%M:%S]")),
-            web_listenon_ip: get_var("REGISTRY_WEB_LISTENON_IP").map_or_else(
-                |_| IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)),
-                |s| IpAddr::from_str(&s).expect("invalid REGISTRY_WEB_LISTENON_IP"),
-            ),
-            web_listenon_port: get_var("REGISTRY_WEB_LISTENON_PORT")
-                .map(|s| s.parse().expect("invalid REGISTRY_WEB_LISTENON_PORT"))
-                .unwrap_or(80),
+        let mut configuration = Self {
+            log_level: get_var_opt("REGISTRY_LOG_LEVEL")?.unwrap_or_else(|| String::from("INFO")),
+            log_datetime_format: get_var_opt("REGISTRY_LOG_DATE_TIME_FORMAT")?
+                .unwrap_or_else(|| String::from("[%Y-%m-%d %H:%M:%S]")),
+            web_listenon_ip: get_var_parsed("REGISTRY_WEB_LISTENON_IP", IpAddr::V4(Ipv4Addr::UNSPECIFIED))?,
+            web_listenon_port: get_var_parsed("REGISTRY_WEB_LISTENON_PORT", 80)?,
+            web_trusted_proxies: get_var_opt("REGISTRY_WEB_TRUSTED_PROXIES")?
+                .map_or_else(|| Ok(Vec::new()), |s| parse_ip_ranges("REGISTRY_WEB_TRUSTED_PROXIES", &s))?,
+            web_listenon_socket: get_var_opt("REGISTRY_WEB_LISTENON_SOCKET")?,
+            web_listenon_socket_mode: get_var_opt("REGISTRY_WEB_LISTENON_SOCKET_MODE")?
+                .map(|s| u32::from_str_radix(&s, 8).map_err(|e| error_invalid_var("REGISTRY_WEB_LISTENON_SOCKET_MODE", e)))
+                .transpose()?,
+            web_cors: CorsConfig::from_env()?,
+            web_security_headers: SecurityHeadersConfig::from_env()?,
+            web_cache_headers: CacheHeadersConfig::from_env()?,
+            web_compression: CompressionConfig::from_env()?,
+            web_rate_limit: RateLimitConfig::from_env()?,
             web_domain,
             web_public_uri,
-            web_body_limit: get_var("REGISTRY_WEB_BODY_LIMIT")
-                .map(|s| s.parse().expect("invalid REGISTRY_WEB_BODY_LIMIT"))
-                .unwrap_or(10 * 1024 * 1024),
-            web_hot_reload_path: get_var("REGISTRY_WEB_HOT_RELOAD_PATH").ok(),
+            web_body_limit: get_var_parsed("REGISTRY_WEB_BODY_LIMIT", 10 * 1024 * 1024)?,
+            web_hot_reload_path: get_var_opt("REGISTRY_WEB_HOT_RELOAD_PATH")?,
+            web_shutdown_grace_period: get_var_parsed("REGISTRY_WEB_SHUTDOWN_GRACE_PERIOD", 25)?,
             home_dir,
             data_dir,
             index,
-            storage,
-            storage_timeout: get_var("REGISTRY_STORAGE_TIMEOUT")
-                .map(|s| s.parse().expect("invalid REGISTRY_STORAGE_TIMEOUT"))
-                .unwrap_or(3000),
+            storage: StorageConfig::from_env()?,
+            storage_timeout: get_var_parsed("REGISTRY_STORAGE_TIMEOUT", 3000)?,
+            redis: RedisConfig::from_env()?,
+            downloads_flush_period: get_var_parsed("REGISTRY_DOWNLOADS_FLUSH_PERIOD", 10)?,
+            crate_info_cache: CrateInfoCacheConfig::from_env()?,
             oauth_login_uri: get_var("REGISTRY_OAUTH_LOGIN_URI")?,
             oauth_token_uri: get_var("REGISTRY_OAUTH_TOKEN_URI")?,
             oauth_callback_uri: get_var("REGISTRY_OAUTH_CALLBACK_URI")?,
             oauth_userinfo_uri: get_var("REGISTRY_OAUTH_USERINFO_URI")?,
-            oauth_userinfo_path_email: get_var("REGISTRY_OAUTH_USERINFO_PATH_EMAIL").unwrap_or_else(|_| String::from("email")),
-            oauth_userinfo_path_fullname: get_var("REGISTRY_OAUTH_USERINFO_PATH_FULLNAME")
-                .unwrap_or_else(|_| String::from("name")),
+            oauth_userinfo_path_email: get_var_opt("REGISTRY_OAUTH_USERINFO_PATH_EMAIL")?
+                .unwrap_or_else(|| String::from("email")),
+            oauth_userinfo_path_fullname: get_var_opt("REGISTRY_OAUTH_USERINFO_PATH_FULLNAME")?
+                .unwrap_or_else(|| String::from("name")),
             oauth_client_id: get_var("REGISTRY_OAUTH_CLIENT_ID")?,
             oauth_client_secret: get_var("REGISTRY_OAUTH_CLIENT_SECRET")?,
             oauth_client_scope: get_var("REGISTRY_OAUTH_CLIENT_SCOPE")?,
-            docs_gen_mock: get_var("REGISTRY_DOCS_GEN_MOCK").map(|v| v == "true").unwrap_or(false),
-            docs_autoinstall_targets: get_var("REGISTRY_DOCS_AUTOINSTALL_TARGETS")
-                .map(|v| v == "true")
-                .unwrap_or(false),
-            deps_check_period: get_var("REGISTRY_DEPS_CHECK_PERIOD")
-                .map(|s| s.parse().expect("invalid REGISTRY_DEPS_CHECK_PERIOD"))
-                .unwrap_or(60), // 1 minute
-            deps_stale_registry: get_var("REGISTRY_DEPS_STALE_REGISTRY")
-                .map(|s| s.parse().expect("invalid REGISTRY_DEPS_STALE_REGISTRY"))
-                .unwrap_or(60 * 1000), // 1 minute
-            deps_stale_analysis: get_var("REGISTRY_DEPS_STALE_ANALYSIS")
-                .map(|s| s.parse().expect("invalid REGISTRY_DEPS_STALE_ANALYSIS"))
-                .unwrap_or(24 * 60), // 24 hours
-            deps_notify_outdated,
-            deps_notify_cves,
-            email,
+            docs_gen_mock: get_var_opt("REGISTRY_DOCS_GEN_MOCK")?.is_some_and(|v| v == "true"),
+            docs_autoinstall_targets: get_var_opt("REGISTRY_DOCS_AUTOINSTALL_TARGETS")?.is_some_and(|v| v == "true"),
+            deps_check_period: get_var_parsed("REGISTRY_DEPS_CHECK_PERIOD", 60)?, // 1 minute
+            deps_stale_registry: get_var_parsed("REGISTRY_DEPS_STALE_REGISTRY", 60 * 1000)?, // 1 minute
+            deps_stale_analysis: get_var_parsed("REGISTRY_DEPS_STALE_ANALYSIS", 24 * 60)?, // 24 hours
+            deps_notify_outdated: get_var_opt("REGISTRY_DEPS_NOTIFY_OUTDATED")?.is_some_and(|v| v == "true"),
+            deps_notify_cves: get_var_opt("REGISTRY_DEPS_NOTIFY_CVES")?.is_some_and(|v| v == "true"),
+            deps_advisories_sync_period: get_var_parsed("REGISTRY_DEPS_ADVISORIES_SYNC_PERIOD", 6 * 60 * 60)?, // 6 hours
+            email_notify_owners: get_var_opt("REGISTRY_EMAIL_NOTIFY_OWNERS")?.is_some_and(|v| v == "true"),
+            email_notify_yanks: get_var_opt("REGISTRY_EMAIL_NOTIFY_YANKS")?.is_some_and(|v| v == "true"),
+            email_notify_docs_failures: get_var_opt("REGISTRY_EMAIL_NOTIFY_DOCS_FAILURES")?.is_some_and(|v| v == "true"),
+            email_notify_watchers: get_var_opt("REGISTRY_EMAIL_NOTIFY_WATCHERS")?.is_some_and(|v| v == "true"),
+            search_weight_downloads: get_var_parsed("REGISTRY_SEARCH_WEIGHT_DOWNLOADS", 0.2)?,
+            search_weight_recency: get_var_parsed("REGISTRY_SEARCH_WEIGHT_RECENCY", 1.0)?,
+            proxy: ProxyConfig::from_env()?,
+            outbound_proxy: get_var_opt("REGISTRY_OUTBOUND_PROXY")?,
+            outbound_no_proxy: get_var_opt("REGISTRY_OUTBOUND_NO_PROXY")?,
+            maintenance: MaintenanceConfig::from_env()?,
+            backup: BackupConfig::from_env()?,
+            quarantine: QuarantineConfig::from_env()?,
+            quota: QuotaConfig::from_env()?,
+            secrets_scan: SecretsScanConfig::from_env()?,
+            email: EmailConfig::default(),
+            chat: ChatConfig::from_env()?,
             self_local_name,
             self_service_login: generate_token(16),
             self_service_token: generate_token(64),
-            self_toolchain_version_stable: get_rustc_version(CHANNEL_STABLE).await,
-            self_toolchain_version_nightly: get_rustc_version(CHANNEL_NIGHTLY).await,
-            self_toolchain_host: get_rustc_host().await,
-            self_known_targets: get_known_targets().await,
-            self_installed_targets: get_installed_targets(CHANNEL_NIGHTLY).await,
-            self_installable_targets: get_installable_targets(CHANNEL_NIGHTLY).await,
-            self_role,
-            external_registries,
-        })
+            self_toolchain_version_stable: semver::Version::new(0, 0, 0),
+            self_toolchain_version_nightly: semver::Version::new(0, 0, 0),
+            self_toolchain_host: String::new(),
+            self_known_targets: Vec::new(),
+            self_installed_targets: Vec::new(),
+            self_installable_targets: Vec::new(),
+            self_role: NodeRole::from_env()?,
+            external_registries: ExternalRegistry::all_from_env()?,
+            tenants: if tenant.is_none() {
+                TenantConfig::all_from_env()?
+            } else {
+                Vec::new()
+            },
+            tenant,
+        };
+        if configuration.has_email_notifications() {
+            configuration.email = EmailConfig::from_env()?;
+        }
+        Ok(configuration)
+    }
+
+    /// Gets a copy of this configuration with the settings that can be changed at runtime reloaded from the environment
+    ///
+    /// Only these settings are read, structural settings (listening address, storage, index, database, OAuth, etc.)
+    /// are kept as is, they require a restart to be changed.
+    /// Notifications by email can only be toggled when the email settings were loaded at startup.
+    ///
+    /// # Errors
+    ///
+    /// Return an error when a reloaded variable is not present or has an invalid value
+    pub fn with_reloaded_from_env(&self) -> Result<Self, ApiError> {
+        let mut result = self.clone();
+        result.log_level = get_var_opt("REGISTRY_LOG_LEVEL")?.unwrap_or_else(|| String::from("INFO"));
+        result.external_registries = ExternalRegistry::all_from_env()?;
+        result.docs_autoinstall_targets = get_var_opt("REGISTRY_DOCS_AUTOINSTALL_TARGETS")?.is_some_and(|v| v == "true");
+        result.deps_stale_registry = get_var_parsed("REGISTRY_DEPS_STALE_REGISTRY", 60 * 1000)?; // 1 minute
+        result.deps_stale_analysis = get_var_parsed("REGISTRY_DEPS_STALE_ANALYSIS", 24 * 60)?; // 24 hours
+        if !self.email.sender.is_empty() {
+            result.deps_notify_outdated = get_var_opt("REGISTRY_DEPS_NOTIFY_OUTDATED")?.is_some_and(|v| v == "true");
+            result.deps_notify_cves = get_var_opt("REGISTRY_DEPS_NOTIFY_CVES")?.is_some_and(|v| v == "true");
+            result.email_notify_owners = get_var_opt("REGISTRY_EMAIL_NOTIFY_OWNERS")?.is_some_and(|v| v == "true");
+            result.email_notify_yanks = get_var_opt("REGISTRY_EMAIL_NOTIFY_YANKS")?.is_some_and(|v| v == "true");
+            result.email_notify_docs_failures =
+                get_var_opt("REGISTRY_EMAIL_NOTIFY_DOCS_FAILURES")?.is_some_and(|v| v == "true");
+            result.email_notify_watchers = get_var_opt("REGISTRY_EMAIL_NOTIFY_WATCHERS")?.is_some_and(|v| v == "true");
+        }
+        result.search_weight_downloads = get_var_parsed("REGISTRY_SEARCH_WEIGHT_DOWNLOADS", 0.2)?;
+        result.search_weight_recency = get_var_parsed("REGISTRY_SEARCH_WEIGHT_RECENCY", 1.0)?;
+        Ok(result)
+    }
+
+    /// Gets whether any notification by email is enabled
+    #[must_use]
+    pub fn has_email_notifications(&self) -> bool {
+        self.deps_notify_outdated
+            || self.deps_notify_cves
+            || self.email_notify_owners
+            || self.email_notify_yanks
+            || self.email_notify_docs_failures
+            || self.email_notify_watchers
+    }
+
+    /// Loads the configuration of a tenant of this registry
+    ///
+    /// # Errors
+    ///
+    /// Return an error when the tenant is not declared or when an expected environment variable is not present
+    pub async fn load_tenant(&self, tenant: &str) -> Result<Self, ApiError> {
+        let Some(tenant_config) = self.tenants.iter().find(|t| t.name == tenant) else {
+            return Err(error_invalid_var("REGISTRY_TENANTS", format!("{tenant} is not declared")));
+        };
+        let mut configuration = with_tenant(Some(tenant), Self::from_env()).await?;
+        // the home folder is shared because the tools are configured through the environment of the process
+        configuration.home_dir.clone_from(&self.home_dir);
+        configuration.index.home_dir.clone_from(&self.home_dir);
+        // the credentials for the registries in the home folder are written by the main registry for all the tenants
+        configuration.self_service_login.clone_from(&tenant_config.self_service_login);
+        configuration.self_service_token.clone_from(&tenant_config.self_service_token);
+        Ok(configuration)
+    }
+
+    /// Gets the prefix of the paths for the routes of this registry, empty for the main registry
+    #[must_use]
+    pub fn get_path_prefix(&self) -> String {
+        self.tenant.as_ref().map_or_else(String::new, |tenant| format!("/{tenant}"))
+    }
+
+    /// Gets the names, index URIs and tokens of the local registries, this one and its tenants, for the cargo configuration
+    fn get_local_registries(&self) -> Vec<(String, String, String)> {
+        let mut registries = Vec::new();
+        let mut push = |name: &str,
+                        web_public_uri: &str,
+                        allow_protocol_git: bool,
+                        allow_protocol_sparse: bool,
+                        login: &str,
+                        token: &str| {
+            let token = STANDARD.encode(format!("{login}:{token}"));
+            if allow_protocol_git {
+                registries.push((name.to_string(), web_public_uri.to_string(), token.clone()));
+                if allow_protocol_sparse {
+                    // both git and sparse
+                    registries.push((format!("{name}sparse"), format!("sparse+{web_public_uri}/"), token));
+                }
+            } else if allow_protocol_sparse {
+                // sparse only
+                registries.push((name.to_string(), format!("sparse+{web_public_uri}/"), token));
+            }
+        };
+        push(
+            &self.self_local_name,
+            &self.web_public_uri,
+            self.index.allow_protocol_git,
+            self.index.allow_protocol_sparse,
+            &self.self_service_login,
+            &self.self_service_token,
+        );
+        for tenant in &self.tenants {
+            push(
+                &tenant.local_name,
+                &tenant.web_public_uri,
+                tenant.allow_protocol_git,
+                tenant.allow_protocol_sparse,
+                &tenant.self_service_login,
+                &tenant.self_service_token,
+            );
+        }
+        registries
     }
 
     /// Gets the path to a file in the home folder
@@ -645,6 +2015,59 @@ impl Configuration {
         result
     }
 
+    /// Gets the environment variables for the spawned processes (git, Cargo, rustup)
+    ///
+    /// When the home directory is not the one of the current user, the location of the generated configuration
+    /// for git and Cargo is given.
+    /// When an outbound HTTP proxy is configured, it is given with the conventional variables.
+    /// When an SSH key is configured for the remote of the index, git is instructed to use it.
+    #[must_use]
+    pub fn get_child_env(&self) -> Vec<(String, String)> {
+        let mut env = Vec::new();
+        if !std::env::var("HOME").is_ok_and(|home| Path::new(&home) == Path::new(&self.home_dir)) {
+            env.push((
+                String::from("GIT_CONFIG_GLOBAL"),
+                self.get_home_path_for(&[".gitconfig"]).display().to_string(),
+            ));
+            env.push((
+                String::from("CARGO_HOME"),
+                self.get_home_path_for(&[".cargo"]).display().to_string(),
+            ));
+        }
+        if let Some(key_path) = self.index.get_remote_ssh_key_path() {
+            env.push((
+                String::from("GIT_SSH_COMMAND"),
+                format!("ssh -i \"{}\" -o IdentitiesOnly=yes", key_path.display()),
+            ));
+        }
+        if let Some(proxy) = &self.outbound_proxy {
+            for name in ["HTTPS_PROXY", "https_proxy", "HTTP_PROXY", "http_proxy"] {
+                env.push((name.to_string(), proxy.clone()));
+            }
+        }
+        if let Some(no_proxy) = &self.outbound_no_proxy {
+            for name in ["NO_PROXY", "no_proxy"] {
+                env.push((name.to_string(), no_proxy.clone()));
+            }
+        }
+        env
+    }
+
+    /// Gets the outbound HTTP proxy for the HTTP clients, if one is configured
+    ///
+    /// # Errors
+    ///
+    /// Return an error when the URI of the proxy is invalid
+    pub fn get_outbound_proxy(&self) -> Result<Option<reqwest::Proxy>, ApiError> {
+        let Some(proxy) = &self.outbound_proxy else {
+            return Ok(None);
+        };
+        let proxy = reqwest::Proxy::all(proxy).map_err(|e| error_invalid_var("REGISTRY_OUTBOUND_PROXY", e))?;
+        Ok(Some(proxy.no_proxy(
+            self.outbound_no_proxy.as_deref().and_then(reqwest::NoProxy::from_string),
+        )))
+    }
+
     /// Gets the name of the file for the database
     #[must_use]
     pub fn get_database_filename(&self) -> String {
@@ -669,7 +2092,12 @@ impl Configuration {
     ///
     /// Return an error when writing fail
     pub async fn write_auth_config(&self) -> Result<(), ApiError> {
-        if self.index.allow_protocol_git {
+        if self.tenant.is_some() {
+            // the files are shared and written by the main registry for all the tenants
+            return Ok(());
+        }
+        tokio::fs::create_dir_all(self.get_home_path_for(&[".cargo"])).await?;
+        if self.index.allow_protocol_git || self.tenants.iter().any(|tenant| tenant.allow_protocol_git) {
             self.write_auth_config_git_config().await?;
             self.write_auth_config_git_credentials().await?;
         }
@@ -682,7 +2110,17 @@ impl Configuration {
     async fn write_auth_config_git_config(&self) -> Result<(), ApiError> {
         let file = File::create(self.get_home_path_for(&[".gitconfig"])).await?;
         let mut writer = BufWriter::new(file);
-        writer.write_all("[credential]\n    helper = store\n".as_bytes()).await?;
+        writer.write_all("[credential]\n".as_bytes()).await?;
+        // the path to the credentials is explicit for when the home directory is not the one of the current user
+        writer
+            .write_all(
+                format!(
+                    "    helper = store --file \\\"{}\\\"\n",
+                    self.get_home_path_for(&[".git-credentials"]).display()
+                )
+                .as_bytes(),
+            )
+            .await?;
         writer.flush().await?;
         Ok(())
     }
@@ -691,19 +2129,19 @@ impl Configuration {
     async fn write_auth_config_git_credentials(&self) -> Result<(), ApiError> {
         let file = File::create(self.get_home_path_for(&[".git-credentials"])).await?;
         let mut writer = BufWriter::new(file);
-        let index = self.web_public_uri.find('/').unwrap() + 2;
-        writer
-            .write_all(
-                format!(
-                    "{}{}:{}@{}\n",
-                    &self.web_public_uri[..index],
-                    self.self_service_login,
-                    self.self_service_token,
-                    &self.web_public_uri[index..]
-                )
-                .as_bytes(),
-            )
-            .await?;
+        let local_registries = std::iter::once((&self.web_public_uri, &self.self_service_login, &self.self_service_token))
+            .chain(
+                self.tenants
+                    .iter()
+                    .filter(|tenant| tenant.allow_protocol_git)
+                    .map(|tenant| (&tenant.web_public_uri, &tenant.self_service_login, &tenant.self_service_token)),
+            );
+        for (web_public_uri, login, token) in local_registries {
+            let index = web_public_uri.find('/').unwrap() + 2;
+            writer
+                .write_all(format!("{}{login}:{token}@{}\n", &web_public_uri[..index], &web_public_uri[index..]).as_bytes())
+                .await?;
+        }
         for registry in &self.external_registries {
             let index = registry.index.find('/').unwrap() + 2;
             writer
@@ -733,32 +2171,9 @@ impl Configuration {
             .await?;
         writer.write_all("\n".as_bytes()).await?;
         writer.write_all("[registries]\n".as_bytes()).await?;
-        if self.index.allow_protocol_git {
+        for (name, index, _) in self.get_local_registries() {
             writer
-                .write_all(format!("{} = {{ index = \"{}\" }}\n", self.self_local_name, self.web_public_uri).as_bytes())
-                .await?;
-            if self.index.allow_protocol_sparse {
-                // both git and sparse
-                writer
-                    .write_all(
-                        format!(
-                            "{}sparse = {{ index = \"sparse+{}/\" }}\n",
-                            self.self_local_name, self.web_public_uri
-                        )
-                        .as_bytes(),
-                    )
-                    .await?;
-            }
-        } else if self.index.allow_protocol_sparse {
-            // sparse only
-            writer
-                .write_all(
-                    format!(
-                        "{} = {{ index = \"sparse+{}/\" }}\n",
-                        self.self_local_name, self.web_public_uri
-                    )
-                    .as_bytes(),
-                )
+                .write_all(format!("{name} = {{ index = \"{index}\" }}\n").as_bytes())
                 .await?;
         }
         for registry in &self.external_registries {
@@ -774,32 +2189,9 @@ impl Configuration {
     async fn write_auth_config_cargo_credentials(&self) -> Result<(), ApiError> {
         let file = File::create(self.get_home_path_for(&[".cargo", "credentials.toml"])).await?;
         let mut writer = BufWriter::new(file);
-        writer
-            .write_all(format!("[registries.{}]\n", self.self_local_name).as_bytes())
-            .await?;
-        writer
-            .write_all(
-                format!(
-                    "token = \"Basic {}\"\n",
-                    STANDARD.encode(format!("{}:{}", self.self_service_login, self.self_service_token))
-                )
-                .as_bytes(),
-            )
-            .await?;
-        if self.index.allow_protocol_git && self.index.allow_protocol_sparse {
-            // add credential for specialized sparse registry
-            writer
-                .write_all(format!("[registries.{}sparse]\n", self.self_local_name).as_bytes())
-                .await?;
-            writer
-                .write_all(
-                    format!(
-                        "token = \"Basic {}\"\n",
-                        STANDARD.encode(format!("{}:{}", self.self_service_login, self.self_service_token))
-                    )
-                    .as_bytes(),
-                )
-                .await?;
+        for (name, _, token) in self.get_local_registries() {
+            writer.write_all(format!("[registries.{name}]\n").as_bytes()).await?;
+            writer.write_all(format!("token = \"Basic {token}\"\n").as_bytes()).await?;
         }
         for registry in &self.external_registries {
             writer
@@ -833,6 +2225,8 @@ impl Configuration {
             docs_root: format!("{}/docs", self.web_public_uri),
             login: self.self_service_login.clone(),
             token: self.self_service_token.clone(),
+            proxy: false,
+            proxy_stale_index: 0,
         }
     }
 
@@ -851,73 +2245,54 @@ impl Configuration {
     }
 }
 
-/// Gets the rustc version
-async fn get_rustc_version(channel: &'static str) -> semver::Version {
-    let child = Command::new("rustc")
-        .args([channel, "--version"])
+/// Gets the standard output of a command of the toolchain
+async fn get_toolchain_output(command: &str, args: &[&str]) -> Result<String, ApiError> {
+    let child = new_command(command)
+        .args(args)
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
         .spawn()
-        .unwrap();
-    let output = child.wait_with_output().await.unwrap();
-    let output = String::from_utf8(output.stdout).unwrap();
-    output.split_ascii_whitespace().nth(1).unwrap().parse().unwrap()
+        .map_err(|e| specialize(error_backend_failure(), format!("failed to run {command}: {e}")))?;
+    let output = child.wait_with_output().await?;
+    Ok(String::from_utf8(output.stdout)?)
 }
 
-async fn get_rustc_host() -> String {
-    let child = Command::new("rustc")
-        .args([CHANNEL_STABLE, "-vV"])
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .spawn()
-        .unwrap();
-    let output = child.wait_with_output().await.unwrap();
-    let output = String::from_utf8(output.stdout).unwrap();
+/// Gets the rustc version
+async fn get_rustc_version(channel: &'static str) -> Result<semver::Version, ApiError> {
+    let output = get_toolchain_output("rustc", &[channel, "--version"]).await?;
+    let version = output
+        .split_ascii_whitespace()
+        .nth(1)
+        .ok_or_else(|| specialize(error_backend_failure(), format!("unexpected rustc version: {output}")))?;
+    Ok(version.parse()?)
+}
+
+async fn get_rustc_host() -> Result<String, ApiError> {
+    let output = get_toolchain_output("rustc", &[CHANNEL_STABLE, "-vV"]).await?;
     output
         .lines()
         .find_map(|line| line.strip_prefix("host: ").map(str::to_string))
-        .unwrap()
+        .ok_or_else(|| specialize(error_backend_failure(), String::from("failed to get the host of rustc")))
 }
 
-async fn get_known_targets() -> Vec<String> {
-    let child = Command::new("rustc")
-        .args([CHANNEL_STABLE, "--print", "target-list"])
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .spawn()
-        .unwrap();
-    let output = child.wait_with_output().await.unwrap();
-    let output = String::from_utf8(output.stdout).unwrap();
-    output.lines().map(str::to_string).collect()
+async fn get_known_targets() -> Result<Vec<String>, ApiError> {
+    let output = get_toolchain_output("rustc", &[CHANNEL_STABLE, "--print", "target-list"]).await?;
+    Ok(output.lines().map(str::to_string).collect())
 }
 
-pub async fn get_installed_targets(channel: &'static str) -> Vec<String> {
-    let child = Command::new("rustup")
-        .args([channel, "target", "list", "--installed"])
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .spawn()
-        .unwrap();
-    let output = child.wait_with_output().await.unwrap();
-    let output = String::from_utf8(output.stdout).unwrap();
-    output.lines().map(str::to_string).collect()
+pub async fn get_installed_targets(channel: &'static str) -> Result<Vec<String>, ApiError> {
+    let output = get_toolchain_output("rustup", &[channel, "target", "list", "--installed"]).await?;
+    Ok(output.lines().map(str::to_string).collect())
 }
 
-async fn get_installable_targets(channel: &'static str) -> Vec<String> {
-    let child = Command::new("rustup")
-        .args([channel, "target", "list"])
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .spawn()
-        .unwrap();
-    let output = child.wait_with_output().await.unwrap();
-    let output = String::from_utf8(output.stdout).unwrap();
-    output.lines().map(str::to_string).collect()
+async fn get_installable_targets(channel: &'static str) -> Result<Vec<String>, ApiError> {
+    let output = get_toolchain_output("rustup", &[channel, "target", "list"]).await?;
+    Ok(output.lines().map(str::to_string).collect())
 }
 
 /// Attempts to install a target
 pub async fn install_target(channel: &'static str, target: &str) -> Result<(), ApiError> {
-    let child = Command::new("rustup")
+    let child = new_command("rustup")
         .args([channel, "target", "add", target])
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
@@ -8,6 +8,7 @@ use chrono::NaiveDateTime;
 use log::error;
 use semver::{Version, VersionReq};
 use serde_derive::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
 use super::cargo::{DependencyKind, IndexCrateDependency, IndexCrateMetadata};
 use super::osv::SimpleAdvisory;
@@ -22,7 +23,7 @@ pub const BUILTIN_CRATES_REGISTRY_URI: &str = "<builtin>";
 pub const BUILTIN_CRATES_LIST: &[&str] = &["core", "alloc", "std"];
 
 /// The specification for a dependency analysis job
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct DepsAnalysisJobSpec {
     /// The name of the crate
     pub package: String,
@@ -43,7 +44,7 @@ impl From<DepsAnalysisState> for DepsAnalysisJobSpec {
 }
 
 /// Metadata about a crate version and its analysis state
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct DepsAnalysisState {
     /// The name of the crate
     pub package: String,
@@ -72,11 +73,14 @@ impl From<DepsAnalysisState> for CrateVersion {
 }
 
 /// The complete dependency analysis
-#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, ToSchema)]
 pub struct DepsAnalysis {
     /// The direct dependencies
     #[serde(rename = "directDependencies")]
     pub direct_dependencies: Vec<DirectDepInfo>,
+    /// All the resolved dependencies, direct and indirect
+    #[serde(default)]
+    pub resolved: Vec<ResolvedDepInfo>,
     /// The advisories against dependencies
     pub advisories: Vec<DepAdvisory>,
 }
@@ -105,13 +109,39 @@ impl DepsAnalysis {
                     }
                 })
                 .collect(),
+            resolved: graph
+                .crates
+                .iter()
+                .filter(|data| data.registry.as_deref() != Some(BUILTIN_CRATES_REGISTRY_URI))
+                .flat_map(|data| {
+                    data.resolutions.iter().enumerate().map(|(resolution_index, res)| {
+                        let version = &data.versions[res.version_index];
+                        ResolvedDepInfo {
+                            registry: data.registry.clone(),
+                            package: data.name.clone(),
+                            version: version.semver.to_string(),
+                            checksum: version.metadata.cksum.clone(),
+                            is_direct: res.origins.iter().any(|o| matches!(o, DepsGraphCrateOrigin::Direct(_))),
+                            is_dev_only: res.origins.iter().all(|o| {
+                                matches!(
+                                    o,
+                                    DepsGraphCrateOrigin::Direct(DependencyKind::Dev)
+                                        | DepsGraphCrateOrigin::DevNormalIndirect
+                                        | DepsGraphCrateOrigin::DevBuildIndirect
+                                )
+                            }),
+                            dependencies: graph.get_resolved_deps_of(data, resolution_index),
+                        }
+                    })
+                })
+                .collect(),
             advisories,
         }
     }
 }
 
 /// The information about a direct dependency, resulting from an analysis
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct DirectDepInfo {
     /// URI for the owning registry, `None` for the local one
     pub registry: Option<String>,
@@ -129,17 +159,123 @@ pub struct DirectDepInfo {
     pub is_outdated: bool,
 }
 
+/// A dependency resolved in the closure of the dependencies of a crate version
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ResolvedDepInfo {
+    /// URI for the owning registry, `None` for the local one
+    pub registry: Option<String>,
+    /// The name of the package
+    pub package: String,
+    /// The resolved version
+    pub version: String,
+    /// The SHA256 checksum of the `.crate` file, as found in the index
+    pub checksum: String,
+    /// Whether this is a direct dependency
+    #[serde(rename = "isDirect")]
+    pub is_direct: bool,
+    /// Whether this dependency is only required for tests and examples
+    #[serde(rename = "isDevOnly")]
+    pub is_dev_only: bool,
+    /// The resolved dependencies of this one, excluding the built-in crates
+    #[serde(default)]
+    pub dependencies: Vec<ResolvedDepRef>,
+}
+
+/// A reference to a dependency resolved in the closure of the dependencies of a crate version
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct ResolvedDepRef {
+    /// URI for the owning registry, `None` for the local one
+    pub registry: Option<String>,
+    /// The name of the package
+    pub package: String,
+    /// The resolved version
+    pub version: String,
+}
+
 /// The advisory against a dependency resolved on crates.io
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct DepAdvisory {
     /// The name of the package
     pub package: String,
     /// The resolved version
+    #[schema(value_type = String)]
     pub version: Version,
     /// The advisory itself
     pub content: SimpleAdvisory,
 }
 
+/// An advisory affecting a hosted crate version, either directly or through its dependencies
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct CrateVersionAdvisory {
+    /// The name of the hosted crate
+    pub package: String,
+    /// The affected version of the hosted crate
+    pub version: String,
+    /// The identifier of the advisory
+    pub advisory: String,
+    /// The summary for the advisory
+    pub summary: String,
+    /// The crate the advisory is against, either the hosted crate itself or one of its dependencies
+    #[serde(rename = "affectedPackage")]
+    pub affected_package: String,
+    /// The affected version of the crate the advisory is against
+    #[serde(rename = "affectedVersion")]
+    pub affected_version: String,
+    /// Whether the advisory is directly against the hosted crate, not one of its dependencies
+    #[serde(rename = "isDirect")]
+    pub is_direct: bool,
+    /// The timestamp when the advisory was first found for this crate version
+    #[serde(rename = "detectedOn")]
+    pub detected_on: NaiveDateTime,
+}
+
+impl CrateVersionAdvisory {
+    /// Creates the record of an advisory against a crate
+    #[must_use]
+    pub fn new(
+        crate_version: &CrateVersion,
+        affected: &str,
+        affected_version: &Version,
+        advisory: &SimpleAdvisory,
+        is_direct: bool,
+    ) -> Self {
+        Self {
+            package: crate_version.package.clone(),
+            version: crate_version.version.clone(),
+            advisory: advisory.id.clone(),
+            summary: advisory.summary.clone(),
+            affected_package: affected.to_string(),
+            affected_version: affected_version.to_string(),
+            is_direct,
+            detected_on: chrono::Local::now().naive_local(),
+        }
+    }
+}
+
+impl From<CrateVersionAdvisory> for SimpleAdvisory {
+    fn from(advisory: CrateVersionAdvisory) -> Self {
+        Self {
+            package: advisory.affected_package,
+            id: advisory.advisory,
+            published: String::new(),
+            modified: String::new(),
+            summary: advisory.summary,
+            ranges: Vec::new(),
+            versions: advisory.affected_version.parse().into_iter().collect(),
+        }
+    }
+}
+
+/// The report about the advisories affecting the hosted crates
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AdvisoriesReport {
+    /// The timestamp of the last synchronization of the `RustSec` advisory database, if any since the start
+    #[serde(rename = "lastSync")]
+    pub last_sync: Option<NaiveDateTime>,
+    /// The advisories affecting the hosted crate versions
+    pub advisories: Vec<CrateVersionAdvisory>,
+}
+
 impl IndexCrateMetadata {
     /// Assumes this is the metadata for a crate in an external registry, including crates.io
     /// Find and rewrite the registry for built-in crates
@@ -159,7 +295,7 @@ impl IndexCrateMetadata {
 }
 
 /// A complete dependency graphs
-#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, ToSchema)]
 pub struct DepsGraph {
     /// The targets for the resolution
     pub targets: Vec<String>,
@@ -189,6 +325,37 @@ impl DepsGraph {
             .find(|(_, c)| c.registry.as_deref() == registry && c.name == name)
     }
 
+    /// Gets the resolved dependencies of a resolution for a crate in this graph, excluding the built-in crates
+    ///
+    /// The development dependencies are not part of the closure and are only considered for the root crate.
+    #[must_use]
+    pub fn get_resolved_deps_of(&self, data: &DepsGraphCrate, resolution_index: usize) -> Vec<ResolvedDepRef> {
+        let mut result = Vec::new();
+        for (dep, _) in data.get_active_deps_in(resolution_index, &self.targets) {
+            if dep.kind == DependencyKind::Dev || dep.registry.as_deref() == Some(BUILTIN_CRATES_REGISTRY_URI) {
+                continue;
+            }
+            let Some(target) = self
+                .crates
+                .iter()
+                .find(|c| c.registry == dep.registry && c.name == dep.get_name())
+            else {
+                continue;
+            };
+            if let Some(version) = target.get_resolved_version(dep) {
+                push_if_not_present(
+                    &mut result,
+                    ResolvedDepRef {
+                        registry: target.registry.clone(),
+                        package: target.name.clone(),
+                        version: version.to_string(),
+                    },
+                );
+            }
+        }
+        result
+    }
+
     /// Gets whether this is a known failing crate
     #[must_use]
     pub fn is_unknown(&self, registry: Option<&str>, name: &str) -> bool {
@@ -260,7 +427,7 @@ impl DepsGraph {
 }
 
 /// Reason why a requirement for a crate is in the closure
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 pub enum DepsGraphCrateOrigin {
     /// This is a direct dependency of a kind
     Direct(DependencyKind),
@@ -300,9 +467,10 @@ impl DepsGraphCrateOrigin {
 }
 
 /// The known version of a crate, coming from the index
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct DepsGraphCrateVersion {
     /// The semver version
+    #[schema(value_type = String)]
     pub semver: Version,
     /// The index metadata
     pub metadata: IndexCrateMetadata,
@@ -312,7 +480,7 @@ pub struct DepsGraphCrateVersion {
 }
 
 /// The resolution of a crate
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct DepsGraphCrateResolution {
     /// The index of the version in the list of all versions
     #[serde(rename = "versionIndex")]
@@ -327,7 +495,7 @@ pub struct DepsGraphCrateResolution {
 }
 
 /// A crate in a graph of dependencies
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct DepsGraphCrate {
     /// URI for the owning registry, `None` for the local one
     pub registry: Option<String>,
@@ -337,10 +505,12 @@ pub struct DepsGraphCrate {
     pub versions: Vec<DepsGraphCrateVersion>,
     /// The version number of the latest stable version
     #[serde(rename = "lastVersion")]
+    #[schema(value_type = String)]
     pub last_version: Version,
     /// The resolved versions of this crate, actually appearing in the dependency graph
     pub resolutions: Vec<DepsGraphCrateResolution>,
     /// The list of unresolved requirements for this crate
+    #[schema(value_type = Vec<String>)]
     pub unresolved: Vec<VersionReq>,
 }
 
@@ -440,6 +610,24 @@ impl DepsGraphCrate {
         }
     }
 
+    /// Gets the version resolved for a dependency, if it appears in the graph
+    #[must_use]
+    pub fn get_resolved_version(&self, dep: &IndexCrateDependency) -> Option<&Version> {
+        let semver = dep.req.parse::<VersionReq>().ok()?;
+        // same selection as for the resolution
+        let selected = self
+            .versions
+            .iter()
+            .enumerate()
+            .filter(|(_, version)| semver.matches(&version.semver))
+            .max_by(|(_, v1), (_, v2)| v1.semver.cmp(&v2.semver))
+            .map(|(i, _)| i)?;
+        self.resolutions
+            .iter()
+            .find(|res| res.version_index == selected)
+            .map(|res| &self.versions[res.version_index].semver)
+    }
+
     /// Gets the active dependencies for a resolution
     pub fn get_active_deps_in<'this: 'targets, 'targets>(
         &'this self,
@@ -6,12 +6,13 @@
 
 use chrono::NaiveDateTime;
 use serde_derive::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
 use super::cargo::RegistryUser;
 use super::worker::WorkerSelector;
 
 /// The specification for a documentation generation job
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct DocGenJobSpec {
     /// The name of the crate
     pub package: String,
@@ -26,7 +27,7 @@ pub struct DocGenJobSpec {
 }
 
 /// The state of a documentation generation job
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 pub enum DocGenJobState {
     /// The job is queued
     Queued,
@@ -69,7 +70,7 @@ impl From<i64> for DocGenJobState {
 }
 
 /// The trigger for the job
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub enum DocGenTrigger {
     /// The upload of the crate version
     Upload { by: RegistryUser },
@@ -115,7 +116,7 @@ impl From<(i64, Option<RegistryUser>)> for DocGenTrigger {
 }
 
 /// A documentation generation job
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct DocGenJob {
     /// The unique identifier
     pub id: i64,
@@ -161,8 +162,46 @@ impl DocGenJob {
     }
 }
 
+/// The documentation coverage, as computed by rustdoc
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct DocsCoverage {
+    /// The total number of items
+    pub total: i64,
+    /// The number of documented items
+    pub documented: i64,
+}
+
+impl DocsCoverage {
+    /// Gets the coverage as a percentage
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn percentage(&self) -> f64 {
+        if self.total == 0 {
+            100.0
+        } else {
+            (self.documented as f64) * 100.0 / (self.total as f64)
+        }
+    }
+
+    /// Aggregates the JSON output of `rustdoc --show-coverage --output-format json`
+    ///
+    /// The output maps each file to its own counters
+    pub fn from_rustdoc_output(output: &str) -> Result<Self, serde_json::Error> {
+        #[derive(Deserialize)]
+        struct FileCoverage {
+            total: i64,
+            with_docs: i64,
+        }
+        let files = serde_json::from_str::<std::collections::HashMap<String, FileCoverage>>(output)?;
+        Ok(files.values().fold(Self::default(), |acc, file| Self {
+            total: acc.total + file.total,
+            documented: acc.documented + file.with_docs,
+        }))
+    }
+}
+
 /// An update to a documentation generation job
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct DocGenJobUpdate {
     /// The unique identifier of the associated job
     #[serde(rename = "jobId")]
@@ -174,13 +213,49 @@ pub struct DocGenJobUpdate {
     pub last_update: NaiveDateTime,
     /// The appended log, if any
     pub log: Option<String>,
+    /// The computed documentation coverage, if any
+    #[serde(default)]
+    pub coverage: Option<DocsCoverage>,
+    /// The total size in bytes of the uploaded documentation, if any
+    #[serde(default)]
+    pub size: Option<i64>,
+}
+
+/// A line of log output for a documentation generation job
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DocGenJobLogLine {
+    /// The unique identifier of the associated job
+    #[serde(rename = "jobId")]
+    pub job_id: i64,
+    /// The line of output
+    pub line: String,
 }
 
 /// An event for the documentation generation service
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub enum DocGenEvent {
     /// An new job was queued
     Queued(Box<DocGenJob>),
     /// A job was updated
     Update(DocGenJobUpdate),
+    /// A job produced a line of output
+    Log(DocGenJobLogLine),
+}
+
+impl DocGenEvent {
+    /// Gets the identifier of the job this event relates to
+    #[must_use]
+    pub fn job_id(&self) -> i64 {
+        match self {
+            Self::Queued(job) => job.id,
+            Self::Update(update) => update.job_id,
+            Self::Log(log) => log.job_id,
+        }
+    }
+
+    /// Gets whether this event marks the end of the job
+    #[must_use]
+    pub fn is_final(&self) -> bool {
+        matches!(self, Self::Update(update) if update.state.is_final())
+    }
 }
@@ -27,3 +27,43 @@ impl std::error::Error for MissingEnvVar {
         Some(&self.original)
     }
 }
+
+/// Error when the value of an environment variable cannot be obtained
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EnvVarError {
+    /// The variable is not set
+    Missing(MissingEnvVar),
+    /// The variable is set by a file that cannot be read
+    UnreadableFile {
+        /// The name of the variable pointing to the file
+        var_name: String,
+        /// The path to the file
+        path: String,
+        /// The reason for the failure
+        details: String,
+    },
+}
+
+impl Display for EnvVarError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Missing(missing) => missing.fmt(f),
+            Self::UnreadableFile { var_name, path, details } => write!(f, "failed to read {path} for {var_name}: {details}"),
+        }
+    }
+}
+
+impl std::error::Error for EnvVarError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Missing(missing) => Some(missing),
+            Self::UnreadableFile { .. } => None,
+        }
+    }
+}
+
+impl From<MissingEnvVar> for EnvVarError {
+    fn from(missing: MissingEnvVar) -> Self {
+        Self::Missing(missing)
+    }
+}
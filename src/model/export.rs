@@ -0,0 +1,132 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Data types for the export and import of the whole registry, to migrate between environments
+
+use std::io::Write;
+use std::path::Path;
+
+use chrono::NaiveDateTime;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde_derive::{Deserialize, Serialize};
+use tar::{Builder, Header};
+use utoipa::ToSchema;
+
+use crate::utils::apierror::ApiError;
+
+/// The version of the format of the export archives
+pub const EXPORT_FORMAT_VERSION: u32 = 1;
+/// The path of the manifest in an export archive
+pub const EXPORT_MANIFEST_PATH: &str = "manifest.json";
+/// The path of the database in an export archive
+pub const EXPORT_DATABASE_PATH: &str = "registry.db";
+/// The folder for the index in an export archive
+pub const EXPORT_INDEX_FOLDER: &str = "index";
+/// The folder for the crates in an export archive
+pub const EXPORT_CRATES_FOLDER: &str = "crates";
+
+/// The manifest of an export archive
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportManifest {
+    /// The version of the format of the archive
+    pub format: u32,
+    /// The public URI of the exported registry
+    pub registry: String,
+    /// The timestamp of the export
+    pub exported_on: NaiveDateTime,
+    /// Whether the `.crate` files and their metadata are included in the archive
+    pub include_crates: bool,
+    /// All the crate versions in the registry
+    pub crates: Vec<ExportedCrate>,
+}
+
+/// A crate version in the manifest of an export archive
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct ExportedCrate {
+    /// The name of the crate
+    pub package: String,
+    /// The version
+    pub version: String,
+    /// The SHA256 checksum of the `.crate` file, as found in the index
+    pub checksum: Option<String>,
+}
+
+impl ExportedCrate {
+    /// Gets the path of the `.crate` file in an export archive
+    #[must_use]
+    pub fn data_path(&self) -> String {
+        format!("{EXPORT_CRATES_FOLDER}/{}/{}/data", self.package, self.version)
+    }
+
+    /// Gets the path of the metadata in an export archive
+    #[must_use]
+    pub fn metadata_path(&self) -> String {
+        format!("{EXPORT_CRATES_FOLDER}/{}/{}/metadata", self.package, self.version)
+    }
+
+    /// Parses the path of a file for a crate in an export archive, returning the crate and whether this is the metadata
+    #[must_use]
+    pub fn parse_path(path: &str) -> Option<(Self, bool)> {
+        let mut parts = path.strip_prefix(EXPORT_CRATES_FOLDER)?.strip_prefix('/')?.split('/');
+        let (package, version, file) = (parts.next()?, parts.next()?, parts.next()?);
+        if parts.next().is_some() || package.is_empty() || version.is_empty() || package == ".." || version == ".." {
+            return None;
+        }
+        let is_metadata = match file {
+            "data" => false,
+            "metadata" => true,
+            _ => return None,
+        };
+        let exported = Self {
+            package: package.to_string(),
+            version: version.to_string(),
+            checksum: None,
+        };
+        Some((exported, is_metadata))
+    }
+}
+
+/// A writer for an export archive
+pub struct ExportArchiveBuilder<W: Write> {
+    /// The inner builder for the tar archive
+    builder: Builder<GzEncoder<W>>,
+}
+
+impl<W: Write> ExportArchiveBuilder<W> {
+    /// Creates a new export archive
+    pub fn new(writer: W) -> Self {
+        Self {
+            builder: Builder::new(GzEncoder::new(writer, Compression::default())),
+        }
+    }
+
+    /// Appends a file with the given content
+    pub fn append_data(&mut self, path: &str, data: &[u8]) -> Result<(), ApiError> {
+        let mut header = Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(chrono::Local::now().timestamp().try_into().unwrap_or_default());
+        self.builder.append_data(&mut header, path, data)?;
+        Ok(())
+    }
+
+    /// Appends a file from the file system
+    pub fn append_file(&mut self, path: &str, file: &Path) -> Result<(), ApiError> {
+        self.builder.append_path_with_name(file, path)?;
+        Ok(())
+    }
+
+    /// Appends the content of a folder, recursively
+    pub fn append_folder(&mut self, path: &str, folder: &Path) -> Result<(), ApiError> {
+        self.builder.append_dir_all(path, folder)?;
+        Ok(())
+    }
+
+    /// Finishes writing the archive
+    pub fn finish(self) -> Result<W, ApiError> {
+        Ok(self.builder.into_inner()?.finish()?)
+    }
+}
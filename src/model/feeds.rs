@@ -0,0 +1,95 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Data types for the Atom feeds of the registry activity
+
+use std::fmt::Write;
+
+use chrono::{Local, NaiveDateTime};
+use quick_xml::escape::escape;
+
+/// The default number of entries in a feed
+pub const FEED_DEFAULT_LIMIT: i64 = 50;
+
+/// The MIME type for Atom feeds
+pub const FEED_CONTENT_TYPE: &str = "application/atom+xml; charset=utf-8";
+
+/// A published crate version, as an entry in a feed
+#[derive(Debug, Clone)]
+pub struct FeedEntry {
+    /// The name of the crate
+    pub package: String,
+    /// The published version
+    pub version: String,
+    /// The description of the crate for this version
+    pub description: String,
+    /// The timestamp of the publication
+    pub upload: NaiveDateTime,
+    /// The name of the user that published the version
+    pub uploaded_by: String,
+    /// Whether the version is yanked
+    pub yanked: bool,
+}
+
+/// Formats a timestamp as expected in Atom feeds
+fn format_timestamp(timestamp: NaiveDateTime) -> String {
+    timestamp
+        .and_local_timezone(Local)
+        .earliest()
+        .map_or_else(|| format!("{}Z", timestamp.format("%Y-%m-%dT%H:%M:%S")), |t| t.to_rfc3339())
+}
+
+/// Builds an Atom feed for published versions
+///
+/// `path` is the path of the feed itself, relative to the public URI of the registry, it is used as the feed identifier.
+#[must_use]
+pub fn build_atom_feed(web_public_uri: &str, path: &str, title: &str, entries: &[FeedEntry]) -> String {
+    let updated = entries
+        .iter()
+        .map(|entry| entry.upload)
+        .max()
+        .unwrap_or_else(|| Local::now().naive_local());
+    let mut feed = String::new();
+    writeln!(feed, r#"<?xml version="1.0" encoding="utf-8"?>"#).unwrap();
+    writeln!(feed, r#"<feed xmlns="http://www.w3.org/2005/Atom">"#).unwrap();
+    writeln!(feed, "  <id>{}{}</id>", escape(web_public_uri), escape(path)).unwrap();
+    writeln!(feed, "  <title>{}</title>", escape(title)).unwrap();
+    writeln!(feed, "  <updated>{}</updated>", format_timestamp(updated)).unwrap();
+    writeln!(
+        feed,
+        r#"  <link rel="self" type="application/atom+xml" href="{}{}"/>"#,
+        escape(web_public_uri),
+        escape(path)
+    )
+    .unwrap();
+    writeln!(
+        feed,
+        r#"  <link rel="alternate" type="text/html" href="{}"/>"#,
+        escape(web_public_uri)
+    )
+    .unwrap();
+    for entry in entries {
+        let link = format!("{web_public_uri}/crates/{}/{}", entry.package, entry.version);
+        let title = if entry.yanked {
+            format!("{} {} (yanked)", entry.package, entry.version)
+        } else {
+            format!("{} {}", entry.package, entry.version)
+        };
+        writeln!(feed, "  <entry>").unwrap();
+        writeln!(feed, "    <id>{}</id>", escape(&link)).unwrap();
+        writeln!(feed, "    <title>{}</title>", escape(&title)).unwrap();
+        writeln!(feed, "    <updated>{}</updated>", format_timestamp(entry.upload)).unwrap();
+        writeln!(
+            feed,
+            r#"    <link rel="alternate" type="text/html" href="{}"/>"#,
+            escape(&link)
+        )
+        .unwrap();
+        writeln!(feed, "    <author><name>{}</name></author>", escape(&entry.uploaded_by)).unwrap();
+        writeln!(feed, "    <summary>{}</summary>", escape(&entry.description)).unwrap();
+        writeln!(feed, "  </entry>").unwrap();
+    }
+    writeln!(feed, "</feed>").unwrap();
+    feed
+}
@@ -0,0 +1,49 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Data types for the health checks of the services the registry depends on
+
+use chrono::NaiveDateTime;
+use serde_derive::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// The name of the check of the database
+pub const HEALTH_CHECK_DATABASE: &str = "database";
+/// The name of the check of the storage
+pub const HEALTH_CHECK_STORAGE: &str = "storage";
+/// The name of the check of the index
+pub const HEALTH_CHECK_INDEX: &str = "index";
+
+/// The result of the check of a single service
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthCheck {
+    /// The name of the checked service
+    pub name: String,
+    /// Whether the service is healthy
+    pub healthy: bool,
+}
+
+/// The report of the health checks
+/// The details of the failures are only logged, not exposed
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthReport {
+    /// Whether all the services are healthy
+    pub healthy: bool,
+    /// The timestamp of the checks
+    pub checked_on: NaiveDateTime,
+    /// The result for each service
+    pub checks: Vec<HealthCheck>,
+}
+
+/// The report of the liveness probe, only telling that the registry is running
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LivenessReport {
+    /// Always `true` when the registry responds
+    pub alive: bool,
+    /// The timestamp of the start of the registry
+    pub started_on: NaiveDateTime,
+}
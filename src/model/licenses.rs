@@ -0,0 +1,136 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Data types for the inventory of the licenses of the hosted crates and their dependencies
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use serde_derive::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::sources::extract_crate_file;
+use crate::utils::apierror::ApiError;
+
+/// The content type for the CSV export of the inventory
+pub const LICENSES_CSV_CONTENT_TYPE: &str = "text/csv; charset=utf-8";
+
+/// A crate version in the inventory of licenses
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LicenseInventoryEntry {
+    /// URI for the owning registry, `None` for the local one
+    pub registry: Option<String>,
+    /// The name of the crate
+    pub package: String,
+    /// The version of the crate
+    pub version: String,
+    /// The SPDX license expression, if known
+    pub license: Option<String>,
+    /// The path to the license file in the crate, when there is no license expression
+    pub license_file: Option<String>,
+    /// The error that prevented reading the license, if any
+    pub error: Option<String>,
+}
+
+/// The number of crate versions using a license expression
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct LicenseSummary {
+    /// The SPDX license expression, `None` for the crate versions without a known one
+    pub license: Option<String>,
+    /// The number of hosted crate versions
+    pub crates: usize,
+    /// The number of external dependencies
+    pub dependencies: usize,
+}
+
+/// The inventory of the licenses of the hosted crate versions, and optionally of their external dependencies
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct LicenseInventory {
+    /// The license expressions, by decreasing number of uses
+    pub licenses: Vec<LicenseSummary>,
+    /// The hosted crate versions, except those that are yanked
+    pub crates: Vec<LicenseInventoryEntry>,
+    /// The external dependencies of the last version of the hosted crates, when requested
+    pub dependencies: Vec<LicenseInventoryEntry>,
+}
+
+impl LicenseInventory {
+    /// Builds the inventory, summarizing the license expressions
+    #[must_use]
+    pub fn new(crates: Vec<LicenseInventoryEntry>, dependencies: Vec<LicenseInventoryEntry>) -> Self {
+        let mut counts = BTreeMap::<Option<String>, (usize, usize)>::new();
+        for entry in &crates {
+            counts.entry(entry.license.clone()).or_default().0 += 1;
+        }
+        for entry in &dependencies {
+            counts.entry(entry.license.clone()).or_default().1 += 1;
+        }
+        let mut licenses = counts
+            .into_iter()
+            .map(|(license, (crates, dependencies))| LicenseSummary {
+                license,
+                crates,
+                dependencies,
+            })
+            .collect::<Vec<_>>();
+        licenses.sort_by_key(|summary| std::cmp::Reverse(summary.crates + summary.dependencies));
+        Self {
+            licenses,
+            crates,
+            dependencies,
+        }
+    }
+
+    /// Writes the inventory in the CSV format, with one line per crate version
+    #[must_use]
+    pub fn to_csv(&self) -> String {
+        let mut buffer = String::from("kind,registry,package,version,license,license_file,error\n");
+        let lines = self
+            .crates
+            .iter()
+            .map(|entry| ("crate", entry))
+            .chain(self.dependencies.iter().map(|entry| ("dependency", entry)));
+        for (kind, entry) in lines {
+            let _ = writeln!(
+                buffer,
+                "{kind},{},{},{},{},{},{}",
+                csv_field(entry.registry.as_deref().unwrap_or_default()),
+                csv_field(&entry.package),
+                csv_field(&entry.version),
+                csv_field(entry.license.as_deref().unwrap_or_default()),
+                csv_field(entry.license_file.as_deref().unwrap_or_default()),
+                csv_field(entry.error.as_deref().unwrap_or_default())
+            );
+        }
+        buffer
+    }
+}
+
+/// Escapes a field for the CSV format
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Extracts the license expression and license file from the manifest in a `.crate` archive
+pub fn extract_crate_license(content: &[u8], name: &str, version: &str) -> Result<(Option<String>, Option<String>), ApiError> {
+    let Some(manifest) = extract_crate_file(content, name, version, "Cargo.toml")? else {
+        return Ok((None, None));
+    };
+    let Ok(manifest) = toml::from_str::<toml::Table>(&String::from_utf8_lossy(&manifest)) else {
+        return Ok((None, None));
+    };
+    let field = |key: &str| {
+        manifest
+            .get("package")
+            .and_then(|package| package.get(key))
+            .and_then(|value| value.as_str())
+            .map(str::to_string)
+    };
+    Ok((field("license"), field("license-file")))
+}
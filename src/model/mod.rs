@@ -4,23 +4,44 @@
 
 //! Data model
 
+pub mod announcements;
+pub mod audit;
 pub mod auth;
+pub mod backup;
+pub mod binstall;
 pub mod cargo;
 pub mod config;
 pub mod deps;
 pub mod docs;
 pub mod errors;
+pub mod export;
+pub mod feeds;
+pub mod health;
+pub mod licenses;
 pub mod namegen;
 pub mod osv;
 pub mod packages;
+pub mod pagination;
+pub mod profile;
+pub mod quotas;
+pub mod sbom;
+pub mod scheduler;
+pub mod search;
+pub mod secrets;
+pub mod sources;
 pub mod stats;
+pub mod vendor;
+pub mod webhooks;
 pub mod worker;
 
-use auth::TokenUsage;
+use std::ops::Range;
+
+use auth::{TokenUsage, TrafficUsage};
 use serde_derive::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
 /// The object representing the application version
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct AppVersion {
     /// The changeset that was used to build the app
     pub commit: String,
@@ -29,16 +50,18 @@ pub struct AppVersion {
 }
 
 /// Information about the registry, as exposed on the web API
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct RegistryInformation {
     /// The name to use for the registry in cargo and git config
     #[serde(rename = "registryName")]
     pub registry_name: String,
     /// The version of the locally installed toolchain
     #[serde(rename = "toolchainVersionStable")]
+    #[schema(value_type = String)]
     pub toolchain_version_stable: semver::Version,
     /// The version of the locally installed toolchain
     #[serde(rename = "toolchainVersionNightly")]
+    #[schema(value_type = String)]
     pub toolchain_version_nightly: semver::Version,
     /// The host target of the locally installed toolchain
     #[serde(rename = "toolchainHost")]
@@ -49,7 +72,7 @@ pub struct RegistryInformation {
 }
 
 /// A couple describing a crate with its name and the associated version
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash, ToSchema)]
 pub struct CrateVersion {
     /// The name of the crate
     pub package: String,
@@ -57,13 +80,127 @@ pub struct CrateVersion {
     pub version: String,
 }
 
+/// A range of bytes requested with the HTTP `Range` header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteRange {
+    /// From a first position to an optional last position, both included
+    FromTo(u64, Option<u64>),
+    /// The given number of bytes at the end
+    Suffix(u64),
+}
+
+impl ByteRange {
+    /// Parses the value of a `Range` header
+    ///
+    /// Only a single range of bytes is supported, `None` is returned for anything else.
+    #[must_use]
+    pub fn parse(value: &str) -> Option<Self> {
+        let spec = value.trim().strip_prefix("bytes=")?;
+        if spec.contains(',') {
+            return None;
+        }
+        let (first, last) = spec.trim().split_once('-')?;
+        if first.is_empty() {
+            return last.parse().ok().map(Self::Suffix);
+        }
+        let first = first.parse().ok()?;
+        let last = if last.is_empty() { None } else { Some(last.parse().ok()?) };
+        if last.is_some_and(|last| last < first) {
+            return None;
+        }
+        Some(Self::FromTo(first, last))
+    }
+
+    /// Gets whether the range starts at the beginning of the content, so that reading it is a new download
+    #[must_use]
+    pub fn is_from_start(self) -> bool {
+        matches!(self, Self::FromTo(0, _))
+    }
+
+    /// Resolves the positions of the bytes in a content of the given length
+    ///
+    /// Returns `None` when the range cannot be satisfied.
+    #[must_use]
+    pub fn resolve(self, length: u64) -> Option<Range<u64>> {
+        match self {
+            Self::FromTo(first, last) if first < length => {
+                Some(first..last.map_or(length, |last| last.saturating_add(1).min(length)))
+            }
+            Self::Suffix(count) if count > 0 && length > 0 => Some(length.saturating_sub(count)..length),
+            _ => None,
+        }
+    }
+}
+
+/// The part of the whole content of a crate that is served
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContentPart {
+    /// The whole content
+    Whole,
+    /// The bytes at the given positions, when only a part was requested
+    Range(Range<u64>),
+    /// The requested range cannot be satisfied
+    Unsatisfiable,
+}
+
+/// The content of a crate, or a part of it
+#[derive(Debug, Clone)]
+pub struct CrateContent {
+    /// The bytes
+    pub content: Vec<u8>,
+    /// The part of the whole content
+    pub part: ContentPart,
+    /// The length of the whole content
+    pub total: u64,
+    /// Whether the content is only available to some users, for example during a quarantine
+    pub restricted: bool,
+}
+
+impl CrateContent {
+    /// Gets the requested part of a whole content
+    #[must_use]
+    pub fn from_content(mut content: Vec<u8>, range: Option<ByteRange>) -> Self {
+        let total = content.len() as u64;
+        let Some(range) = range else {
+            return Self {
+                content,
+                part: ContentPart::Whole,
+                total,
+                restricted: false,
+            };
+        };
+        let Some(range) = range.resolve(total) else {
+            return Self::unsatisfiable(total);
+        };
+        #[allow(clippy::cast_possible_truncation)]
+        let content = content.drain(range.start as usize..range.end as usize).collect();
+        Self {
+            content,
+            part: ContentPart::Range(range),
+            total,
+            restricted: false,
+        }
+    }
+
+    /// Gets the answer to a range that cannot be satisfied in a content of the given length
+    #[must_use]
+    pub fn unsatisfiable(total: u64) -> Self {
+        Self {
+            content: Vec::new(),
+            part: ContentPart::Unsatisfiable,
+            total,
+            restricted: false,
+        }
+    }
+}
+
 /// An event can be handled asynchronously by the application
 #[derive(Debug, Clone)]
 pub enum AppEvent {
     /// The use of a token to authenticate
     TokenUse(TokenUsage),
-    /// The download of a crate
-    CrateDownload(CrateVersion),
+    /// A request served for an authenticated subject
+    Traffic(TrafficUsage),
 }
 
 /// The modifier for the stable channel
@@ -71,3 +208,40 @@ pub const CHANNEL_STABLE: &str = "+stable";
 
 /// The modifier for the nightly channel
 pub const CHANNEL_NIGHTLY: &str = "+nightly";
+
+/// The way to re-synchronize the index with its remote origin
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+pub enum IndexResyncMode {
+    /// The local index is authoritative, the remote origin is overwritten with a force-push
+    #[serde(rename = "forcePush")]
+    ForcePush,
+    /// The remote origin is authoritative, the local index is reset to it
+    #[serde(rename = "resetToRemote")]
+    ResetToRemote,
+}
+
+/// The request to re-synchronize the index with its remote origin
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct IndexResyncQuery {
+    /// The way to re-synchronize
+    pub mode: IndexResyncMode,
+    /// Must be set to confirm the operation, otherwise only the current state is reported
+    #[serde(default)]
+    pub confirm: bool,
+}
+
+/// The result of a re-synchronization of the index with its remote origin
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct IndexResyncResult {
+    /// Whether the operation was performed
+    pub performed: bool,
+    /// The head commit of the local index before the operation
+    #[serde(rename = "localHeadBefore")]
+    pub local_head_before: String,
+    /// The head commit of the remote origin, after fetching
+    #[serde(rename = "remoteHead")]
+    pub remote_head: String,
+    /// The head commit of the local index after the operation
+    #[serde(rename = "localHeadAfter")]
+    pub local_head_after: String,
+}
@@ -6,22 +6,23 @@
 
 use semver::Version;
 use serde_derive::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct AdvisorySeverity {
     #[serde(rename = "type")]
     pub type_value: String,
     pub score: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct AdvisoryAffectedPackage {
     pub ecosystem: String,
     pub name: String,
     pub purl: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct AdvisoryAffectedRangeEvent {
     introduced: Option<String>,
     fixed: Option<String>,
@@ -29,7 +30,7 @@ pub struct AdvisoryAffectedRangeEvent {
     limit: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct AdvisoryAffectedRange {
     #[serde(rename = "type")]
     pub type_value: String,
@@ -37,7 +38,7 @@ pub struct AdvisoryAffectedRange {
     pub events: Vec<AdvisoryAffectedRangeEvent>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct AdvisoryAffected {
     pub package: AdvisoryAffectedPackage,
     #[serde(default)]
@@ -50,14 +51,14 @@ pub struct AdvisoryAffected {
     pub database_specific: Option<serde_json::Value>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct AdvisoryReference {
     #[serde(rename = "type")]
     pub type_value: String,
     pub url: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct AdvisoryCredit {
     pub name: String,
     pub contact: Vec<String>,
@@ -65,7 +66,7 @@ pub struct AdvisoryCredit {
     pub type_value: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct Advisory {
     pub schema_version: Option<String>,
     pub id: String,
@@ -93,13 +94,16 @@ pub struct Advisory {
 }
 
 /// A range of affected versions
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct SimpleAdvisoryRange {
     /// Minimal affected version
+    #[schema(value_type = String)]
     pub introduced: Version,
     /// The minimal fixing version
+    #[schema(value_type = Option<String>)]
     pub fixed: Option<Version>,
     /// The last affected version
+    #[schema(value_type = Option<String>)]
     pub last_affected: Option<Version>,
 }
 
@@ -118,7 +122,7 @@ impl SimpleAdvisoryRange {
 }
 
 /// A simplified advisory against a crate to be used in services
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct SimpleAdvisory {
     /// The affected package
     pub package: String,
@@ -133,6 +137,7 @@ pub struct SimpleAdvisory {
     /// The affected ranges
     pub ranges: Vec<SimpleAdvisoryRange>,
     /// The affected versions
+    #[schema(value_type = Vec<String>)]
     pub versions: Vec<Version>,
 }
 
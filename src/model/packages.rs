@@ -5,18 +5,38 @@
 //! Data types for crate information and description, in addition to Cargo types
 
 use chrono::NaiveDateTime;
+use semver::Version;
 use serde_derive::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-use super::cargo::{CrateMetadata, IndexCrateMetadata, RegistryUser};
+use super::cargo::{CrateMetadata, DependencyKind, IndexCrateMetadata, RegistryUser};
+use super::deps::CrateVersionAdvisory;
+use super::docs::DocsCoverage;
+use super::sources::extract_crate_file;
+use super::CrateVersion;
+use crate::utils::apierror::ApiError;
+use crate::utils::hashes::{sha256, sha512};
 
 /// Gets the last info for a crate
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct CrateInfo {
     /// The last metadata, if any
     pub metadata: Option<CrateMetadata>,
+    /// The total number of downloads for all the versions
+    pub downloads: i64,
+    /// The number of downloads for all the versions over the last days, see `SERIES_LENGTH`
+    pub recent_downloads: i64,
+    /// The number of users that starred the crate
+    pub stars: i64,
     /// Whether the entire package is deprecated
     #[serde(rename = "isDeprecated")]
     pub is_deprecated: bool,
+    /// Whether the package is frozen, preventing publications and changes
+    #[serde(rename = "isFrozen")]
+    pub is_frozen: bool,
+    /// Whether a crate with the same name exists on crates.io, exposing its users to dependency confusion
+    #[serde(rename = "isClaimedUpstream")]
+    pub is_claimed_upstream: bool,
     /// Gets the versions in the index
     pub versions: Vec<CrateInfoVersion>,
     /// The build targets to use (for docs generation and deps analysis)
@@ -25,8 +45,21 @@ pub struct CrateInfo {
     pub capabilities: Vec<String>,
 }
 
+impl CrateInfo {
+    /// Gets the latest version that is not yanked, preferring stable versions over pre-releases
+    #[must_use]
+    pub fn latest_version(&self) -> Option<&CrateInfoVersion> {
+        self.versions
+            .iter()
+            .filter(|version| !version.index.yanked)
+            .filter_map(|version| version.index.vers.parse::<Version>().ok().map(|semver| (version, semver)))
+            .max_by(|(_, a), (_, b)| a.pre.is_empty().cmp(&b.pre.is_empty()).then_with(|| a.cmp(b)))
+            .map(|(version, _)| version)
+    }
+}
+
 /// A build targets to use (for docs generation and deps analysis)
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct CrateInfoTarget {
     /// The target triple
     pub target: String,
@@ -36,7 +69,7 @@ pub struct CrateInfoTarget {
 }
 
 /// The data for a crate version
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct CrateInfoVersion {
     /// The data from the index
     pub index: IndexCrateMetadata,
@@ -46,8 +79,12 @@ pub struct CrateInfoVersion {
     #[serde(rename = "uploadedBy")]
     pub uploaded_by: RegistryUser,
     /// The number of times this version was downloaded
+    pub downloads: i64,
+    /// The number of times this version was downloaded, same as `downloads`, kept for the existing clients
     #[serde(rename = "downloadCount")]
     pub download_count: i64,
+    /// The number of times this version was downloaded over the last days, see `SERIES_LENGTH`
+    pub recent_downloads: i64,
     /// Gets the last time this crate version had its dependencies automatically checked
     #[serde(rename = "depsLastCheck")]
     pub deps_last_check: NaiveDateTime,
@@ -57,12 +94,18 @@ pub struct CrateInfoVersion {
     /// Flag whether CVEs have been filed against dependencies of this crate
     #[serde(rename = "depsHasCVEs")]
     pub deps_has_cves: bool,
+    /// The advisories affecting this crate version, directly or through its dependencies
+    #[serde(default)]
+    pub advisories: Vec<CrateVersionAdvisory>,
     /// The documentation status
     pub docs: Vec<CrateInfoVersionDocs>,
+    /// The provenance of this version, if known
+    #[serde(default)]
+    pub provenance: Option<VersionProvenance>,
 }
 
 /// The documentation status for a crate version
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct CrateInfoVersionDocs {
     /// The corresponding target
     pub target: String,
@@ -72,4 +115,257 @@ pub struct CrateInfoVersionDocs {
     /// Whether documentation was generated for this target
     #[serde(rename = "isPresent")]
     pub is_present: bool,
+    /// The documentation coverage, if it could be computed
+    pub coverage: Option<DocsCoverage>,
+}
+
+/// The query for yanking many crate versions at once
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BulkYankQuery {
+    /// The pattern for the names of the crates, where `*` matches any sequence of characters
+    pub pattern: String,
+    /// The semver requirement for the versions to yank, all versions when not specified
+    pub versions: Option<String>,
+    /// Whether to only report the versions that would be yanked
+    #[serde(rename = "dryRun", default)]
+    pub dry_run: bool,
+    /// The reason for yanking the versions, included in the notifications
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+impl BulkYankQuery {
+    /// Checks whether a crate name matches the pattern, ignoring case
+    #[must_use]
+    pub fn matches_name(&self, name: &str) -> bool {
+        glob_matches(
+            self.pattern.to_ascii_lowercase().as_bytes(),
+            name.to_ascii_lowercase().as_bytes(),
+        )
+    }
+}
+
+/// Matches a name against a pattern where `*` matches any sequence of characters
+///
+/// On a mismatch, only the last `*` is tried again on a longer sequence, which keeps the matching linear in practice.
+fn glob_matches(pattern: &[u8], name: &[u8]) -> bool {
+    let (mut p, mut n) = (0, 0);
+    // the position after the last `*` in the pattern and the position in the name it is matched up to
+    let mut backtrack = None;
+    while n < name.len() {
+        if p < pattern.len() && pattern[p] == b'*' {
+            p += 1;
+            backtrack = Some((p, n));
+        } else if p < pattern.len() && pattern[p] == name[n] {
+            p += 1;
+            n += 1;
+        } else if let Some((star_p, star_n)) = backtrack {
+            p = star_p;
+            n = star_n + 1;
+            backtrack = Some((star_p, n));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
+
+/// The result of yanking many crate versions at once
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BulkYankResult {
+    /// Whether this was a dry run, in which case nothing was yanked
+    #[serde(rename = "dryRun")]
+    pub dry_run: bool,
+    /// The versions that were (or would be) yanked
+    pub yanked: Vec<CrateVersion>,
+}
+
+/// A newly published crate version in quarantine, only available to the owners of the crate
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct QuarantinedVersion {
+    /// The name of the crate
+    pub package: String,
+    /// The quarantined version
+    pub version: String,
+    /// The timestamp of the publication
+    pub upload: NaiveDateTime,
+    /// The timestamp of the end of the quarantine
+    #[serde(rename = "quarantineUntil")]
+    pub quarantine_until: NaiveDateTime,
+}
+
+/// The visibility of a crate
+#[derive(Debug, Default, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CrateVisibility {
+    /// Whether the crate is only visible to its owners, its readers and the administrators
+    #[serde(rename = "isRestricted")]
+    pub is_restricted: bool,
+    /// The users that can read the crate when it is restricted, in addition to its owners
+    pub readers: Vec<RegistryUser>,
+    /// The teams whose members can read the crate when it is restricted, i.e. the roles of the users
+    pub teams: Vec<String>,
+}
+
+/// The query to change the visibility of a crate
+#[derive(Debug, Default, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CrateVisibilityQuery {
+    /// Whether the crate is only visible to its owners, its readers and the administrators
+    #[serde(rename = "isRestricted")]
+    pub is_restricted: bool,
+    /// The emails of the users that can read the crate, replacing the current readers
+    pub readers: Vec<String>,
+    /// The teams whose members can read the crate, i.e. the roles of the users, replacing the current teams
+    #[serde(default)]
+    pub teams: Vec<String>,
+}
+
+/// The provenance of a crate version, i.e. how it was built and published
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct VersionProvenance {
+    /// The SHA of the git commit the version was built from
+    pub commit: Option<String>,
+    /// The URL of the CI run that published the version
+    #[serde(rename = "ciRunUrl")]
+    pub ci_run_url: Option<String>,
+    /// The identity of the builder that published the version
+    pub builder: Option<String>,
+    /// Whether the provenance was verified by the registry
+    ///
+    /// Always `false` for now, as the provenance is declared by the publisher.
+    pub verified: bool,
+}
+
+/// The content of the `.cargo_vcs_info.json` file added by cargo to the `.crate` archives
+#[derive(Debug, Deserialize)]
+struct CargoVcsInfo {
+    /// The information about the git repository, if any
+    git: Option<CargoVcsInfoGit>,
+}
+
+/// The information about the git repository in a `.cargo_vcs_info.json` file
+#[derive(Debug, Deserialize)]
+struct CargoVcsInfoGit {
+    /// The SHA of the commit
+    sha1: String,
+}
+
+impl VersionProvenance {
+    /// Gets the provenance recorded by cargo in the `.crate` archive of a version, i.e. the git commit
+    ///
+    /// Invalid `.cargo_vcs_info.json` files are ignored.
+    pub fn from_crate(content: &[u8], name: &str, version: &str) -> Result<Self, ApiError> {
+        let commit = extract_crate_file(content, name, version, ".cargo_vcs_info.json")?
+            .and_then(|data| serde_json::from_slice::<CargoVcsInfo>(&data).ok())
+            .and_then(|info| info.git)
+            .map(|git| git.sha1);
+        Ok(Self {
+            commit,
+            ..Default::default()
+        })
+    }
+
+    /// Gets whether no provenance is known
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.commit.is_none() && self.ci_run_url.is_none() && self.builder.is_none()
+    }
+}
+
+/// The provenance declared by the publisher of a crate version, after the publication
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct VersionProvenanceDeclaration {
+    /// The SHA of the git commit the version was built from
+    pub commit: Option<String>,
+    /// The URL of the CI run that published the version
+    #[serde(rename = "ciRunUrl")]
+    pub ci_run_url: Option<String>,
+    /// The identity of the builder that published the version
+    pub builder: Option<String>,
+}
+
+/// The checksums of the `.crate` file of a crate version
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct CrateVersionChecksums {
+    /// The SHA-256 digest, in hexadecimal, as found in the index
+    pub sha256: String,
+    /// The SHA-512 digest, in hexadecimal
+    pub sha512: String,
+}
+
+impl CrateVersionChecksums {
+    /// Computes the checksums of the content of a `.crate` file
+    #[must_use]
+    pub fn new(content: &[u8]) -> Self {
+        Self {
+            sha256: sha256(content),
+            sha512: sha512(content),
+        }
+    }
+}
+
+/// The maximum number of crates in a bulk query for metadata
+pub const BULK_METADATA_MAX_CRATES: usize = 1000;
+
+/// The query for the metadata of many crates at once
+#[derive(Debug, Default, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BulkMetadataQuery {
+    /// The names of the crates
+    #[serde(default)]
+    pub names: Vec<String>,
+    /// The content of a `Cargo.lock` file, whose packages from this registry are also queried
+    #[serde(rename = "lockFile", default)]
+    pub lock_file: Option<String>,
+}
+
+/// The metadata of a crate in the result of a bulk query
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BulkCrateMetadata {
+    /// The name of the crate
+    pub name: String,
+    /// The index entry for the latest version, not yanked when possible
+    pub index: IndexCrateMetadata,
+    /// The metadata for the latest version, if any
+    pub metadata: Option<CrateMetadata>,
+    /// The index entry for the version locked in the `Cargo.lock` file, if the crate came from it
+    pub locked: Option<IndexCrateMetadata>,
+}
+
+/// The result of a bulk query for the metadata of crates
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BulkMetadataResult {
+    /// The metadata of the crates that were found
+    pub crates: Vec<BulkCrateMetadata>,
+    /// The names of the requested crates that are not in this registry
+    pub missing: Vec<String>,
+}
+
+/// A crate in the registry that depends on another crate
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ReverseDependency {
+    /// The name of the dependent crate
+    pub package: String,
+    /// The version of the dependent crate
+    pub version: String,
+    /// The version requirement on the dependency
+    pub req: String,
+    /// The dependency kind
+    pub kind: DependencyKind,
+    /// Whether the dependency is optional
+    pub optional: bool,
+}
+
+/// The metadata for the reverse dependencies of a crate
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ReverseDependenciesMeta {
+    /// The total number of dependent crates
+    pub total: usize,
+}
+
+/// The reverse dependencies of a crate, i.e. the last versions of the crates in the registry depending on it
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ReverseDependencies {
+    /// The dependent crates
+    pub dependencies: Vec<ReverseDependency>,
+    /// The associated metadata
+    pub meta: ReverseDependenciesMeta,
 }
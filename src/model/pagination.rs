@@ -0,0 +1,212 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Data types for the pagination, sorting and filtering of lists
+
+use semver::Version;
+use serde_derive::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// The requested page of a list
+#[derive(Debug, Clone, Copy)]
+pub struct Pagination<S> {
+    /// The 1-based index of the page
+    pub page: usize,
+    /// The number of items per page
+    pub per_page: usize,
+    /// The order of the items
+    pub sort: S,
+}
+
+impl<S: Default> Pagination<S> {
+    /// The default number of items per page
+    pub const DEFAULT_PER_PAGE: usize = 10;
+    /// The maximum number of items per page
+    pub const MAX_PER_PAGE: usize = 100;
+
+    /// Builds the pagination from the optional parameters of a request
+    #[must_use]
+    pub fn new(page: Option<usize>, per_page: Option<usize>, sort: Option<S>) -> Self {
+        Self {
+            page: page.unwrap_or(1).max(1),
+            per_page: per_page.unwrap_or(Self::DEFAULT_PER_PAGE).clamp(1, Self::MAX_PER_PAGE),
+            sort: sort.unwrap_or_default(),
+        }
+    }
+}
+
+impl<S: Default> Pagination<S> {
+    /// Gets the first items of a list, without any bound on their number
+    #[must_use]
+    pub fn first(count: usize) -> Self {
+        Self {
+            page: 1,
+            per_page: count,
+            sort: S::default(),
+        }
+    }
+
+    /// Gets all the items of a list at once, in the default order
+    #[must_use]
+    pub fn all() -> Self {
+        Self::first(usize::MAX)
+    }
+}
+
+impl<S> Pagination<S> {
+    /// Gets the number of items to skip before the requested page
+    #[must_use]
+    pub fn offset(&self) -> usize {
+        (self.page - 1).saturating_mul(self.per_page)
+    }
+
+    /// Gets the limit and offset to use in SQL queries
+    #[must_use]
+    pub fn sql_bounds(&self) -> (i64, i64) {
+        (
+            i64::try_from(self.per_page).unwrap_or(i64::MAX),
+            i64::try_from(self.offset()).unwrap_or(i64::MAX),
+        )
+    }
+
+    /// Gets the requested page from all the items, already sorted
+    #[must_use]
+    pub fn paginate<T>(&self, items: Vec<T>) -> Page<T> {
+        let total = items.len();
+        Page {
+            items: items.into_iter().skip(self.offset()).take(self.per_page).collect(),
+            meta: PageMeta { total },
+        }
+    }
+}
+
+/// A page of a list
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Page<T> {
+    /// The items on this page
+    pub items: Vec<T>,
+    /// The metadata
+    pub meta: PageMeta,
+}
+
+/// The metadata for a page of a list
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PageMeta {
+    /// The total number of items in the list, across all pages
+    pub total: usize,
+}
+
+/// Checks whether a value matches a filter, ignoring the case
+#[must_use]
+pub fn matches_filter(value: &str, filter: Option<&str>) -> bool {
+    filter.is_none_or(|filter| value.to_lowercase().contains(&filter.to_lowercase()))
+}
+
+/// Gets the pattern for a SQL `LIKE` matching a filter anywhere in a value
+#[must_use]
+pub fn filter_to_like_pattern(filter: Option<&str>) -> String {
+    filter.map_or_else(
+        || String::from("%"),
+        |filter| {
+            let escaped = filter.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+            format!("%{escaped}%")
+        },
+    )
+}
+
+/// The order of users
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum UserSort {
+    /// By alphabetical order of logins
+    #[default]
+    Login,
+    /// By alphabetical order of names
+    Name,
+    /// By alphabetical order of emails
+    Email,
+}
+
+impl UserSort {
+    /// Gets the key for this order in SQL queries
+    #[must_use]
+    pub fn sql_key(self) -> &'static str {
+        match self {
+            Self::Login => "login",
+            Self::Name => "name",
+            Self::Email => "email",
+        }
+    }
+}
+
+/// The order of tokens
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum TokenSort {
+    /// By order of creation
+    #[default]
+    Oldest,
+    /// By reverse order of creation
+    Newest,
+    /// By alphabetical order of names
+    Name,
+    /// By decreasing date of the last use
+    LastUsed,
+}
+
+impl TokenSort {
+    /// Gets the key for this order in SQL queries
+    #[must_use]
+    pub fn sql_key(self) -> &'static str {
+        match self {
+            Self::Oldest => "oldest",
+            Self::Newest => "newest",
+            Self::Name => "name",
+            Self::LastUsed => "last-used",
+        }
+    }
+}
+
+/// The order of the entries in the audit log
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum AuditLogSort {
+    /// The most recent first
+    #[default]
+    Newest,
+    /// The oldest first
+    Oldest,
+}
+
+/// The order of crate versions in lists
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum CrateListSort {
+    /// By alphabetical order of names, then by version
+    #[default]
+    Alpha,
+    /// By reverse alphabetical order of names, then by reverse version
+    ReverseAlpha,
+}
+
+impl CrateListSort {
+    /// Sorts items in this order, given the name and version of the crate for each item
+    pub fn sort<T>(self, items: &mut [T], key: impl Fn(&T) -> (&str, &str)) {
+        items.sort_by(|a, b| {
+            let (name_a, version_a) = key(a);
+            let (name_b, version_b) = key(b);
+            let ordering =
+                name_a
+                    .cmp(name_b)
+                    .then_with(|| match (version_a.parse::<Version>(), version_b.parse::<Version>()) {
+                        (Ok(version_a), Ok(version_b)) => version_a.cmp(&version_b),
+                        _ => version_a.cmp(version_b),
+                    });
+            match self {
+                Self::Alpha => ordering,
+                Self::ReverseAlpha => ordering.reverse(),
+            }
+        });
+    }
+}
@@ -0,0 +1,99 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Data types for the profile and the preferences of users
+
+use serde_derive::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::utils::apierror::{error_invalid_request, specialize, ApiError};
+
+/// The maximum length of the display name of a user
+pub const USER_NAME_MAX_LENGTH: usize = 100;
+
+/// The kinds of notifications sent by email that users can opt out of
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmailNotification {
+    /// The invitations to be an owner of a crate
+    OwnerInvitations,
+    /// The yanks and deprecations of the dependencies of owned crates
+    Dependencies,
+    /// The failures to generate the documentation of owned crates
+    DocsFailures,
+    /// The events on watched crates
+    Watches,
+}
+
+/// The preferences of a user
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UserPreferences {
+    /// Whether to receive emails when invited to be an owner of a crate
+    pub notify_owner_invitations: bool,
+    /// Whether to receive emails when a dependency of an owned crate is yanked or deprecated
+    pub notify_dependencies: bool,
+    /// Whether to receive emails when the documentation of an owned crate fails to generate
+    pub notify_docs_failures: bool,
+    /// Whether to receive emails about the events on watched crates, in addition to the inbox
+    pub notify_watches: bool,
+    /// Whether new tokens can write when not specified on their creation
+    pub token_can_write: bool,
+    /// Whether new tokens can perform admin operations when not specified on their creation
+    pub token_can_admin: bool,
+}
+
+impl Default for UserPreferences {
+    fn default() -> Self {
+        Self {
+            notify_owner_invitations: true,
+            notify_dependencies: true,
+            notify_docs_failures: true,
+            notify_watches: true,
+            token_can_write: false,
+            token_can_admin: false,
+        }
+    }
+}
+
+impl UserPreferences {
+    /// Gets whether the user wants to receive a kind of notification by email
+    #[must_use]
+    pub fn wants_email(&self, notification: EmailNotification) -> bool {
+        match notification {
+            EmailNotification::OwnerInvitations => self.notify_owner_invitations,
+            EmailNotification::Dependencies => self.notify_dependencies,
+            EmailNotification::DocsFailures => self.notify_docs_failures,
+            EmailNotification::Watches => self.notify_watches,
+        }
+    }
+}
+
+/// The changes to the profile of the current user
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct UserProfileUpdate {
+    /// The new display name
+    pub name: String,
+}
+
+impl UserProfileUpdate {
+    /// Validates the changes and gets the normalized display name
+    ///
+    /// # Errors
+    ///
+    /// Returns an error when the name is empty or too long
+    pub fn validate(&self) -> Result<&str, ApiError> {
+        let name = self.name.trim();
+        if name.is_empty() {
+            return Err(specialize(error_invalid_request(), String::from("the name cannot be empty")));
+        }
+        if name.chars().count() > USER_NAME_MAX_LENGTH {
+            return Err(specialize(
+                error_invalid_request(),
+                format!("the name cannot exceed {USER_NAME_MAX_LENGTH} characters"),
+            ));
+        }
+        Ok(name)
+    }
+}
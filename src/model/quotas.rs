@@ -0,0 +1,158 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Data types for the quotas on the crates published by users
+
+use serde_derive::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::auth::is_valid_role;
+use super::config::QuotaConfig;
+use crate::utils::apierror::{error_forbidden, error_invalid_request, specialize, ApiError};
+
+/// The quotas set for a specific user, overriding the defaults of the registry
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UserQuota {
+    /// The maximum total size in bytes of the `.crate` files uploaded by the user, 0 for no limit, `None` for the default
+    pub max_bytes: Option<i64>,
+    /// The maximum number of crates owned by the user, 0 for no limit, `None` for the default
+    pub max_crates: Option<i64>,
+}
+
+impl UserQuota {
+    /// Validates the quotas
+    ///
+    /// # Errors
+    ///
+    /// Returns an error when a limit is negative
+    pub fn validate(&self) -> Result<(), ApiError> {
+        if self.max_bytes.is_some_and(|v| v < 0) || self.max_crates.is_some_and(|v| v < 0) {
+            return Err(specialize(
+                error_invalid_request(),
+                String::from("quotas cannot be negative, use 0 for no limit"),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// The quotas shared by the members of a team, i.e. the users having the team as a role
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TeamQuota {
+    /// The maximum total size in bytes of the `.crate` files uploaded by the members, 0 for no limit
+    pub max_bytes: i64,
+    /// The maximum number of crates owned by the members, 0 for no limit
+    pub max_crates: i64,
+}
+
+impl TeamQuota {
+    /// Validates the quotas for a team
+    ///
+    /// # Errors
+    ///
+    /// Returns an error when the name of the team cannot be a role, or when a limit is negative
+    pub fn validate(&self, team: &str) -> Result<(), ApiError> {
+        if !is_valid_role(team) {
+            return Err(specialize(error_invalid_request(), format!("invalid team {team}")));
+        }
+        if self.max_bytes < 0 || self.max_crates < 0 {
+            return Err(specialize(
+                error_invalid_request(),
+                String::from("quotas cannot be negative, use 0 for no limit"),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// The usage of a user, or of a team, against the applicable quotas
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct QuotaUsage {
+    /// The team whose members share the quotas, `None` for the quotas of a user
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub team: Option<String>,
+    /// The total size in bytes of the `.crate` files uploaded by the user, or by the members of the team
+    pub bytes: i64,
+    /// The number of crates owned by the user, or by the members of the team
+    pub crates: i64,
+    /// The applicable maximum total size in bytes, `None` when there is no limit
+    pub max_bytes: Option<i64>,
+    /// The applicable maximum number of crates, `None` when there is no limit
+    pub max_crates: Option<i64>,
+}
+
+impl QuotaUsage {
+    /// Builds the usage from the defaults of the registry and the quotas set for the user
+    #[must_use]
+    pub fn new(bytes: i64, crates: i64, defaults: &QuotaConfig, quota: &UserQuota) -> Self {
+        let limit = |specific: Option<i64>, default: u64| {
+            Some(specific.unwrap_or_else(|| i64::try_from(default).unwrap_or(i64::MAX))).filter(|&v| v > 0)
+        };
+        Self {
+            team: None,
+            bytes,
+            crates,
+            max_bytes: limit(quota.max_bytes, defaults.max_bytes),
+            max_crates: limit(quota.max_crates, defaults.max_crates),
+        }
+    }
+
+    /// Builds the usage of the members of a team from the quotas set for the team
+    #[must_use]
+    pub fn for_team(team: &str, bytes: i64, crates: i64, quota: &TeamQuota) -> Self {
+        Self {
+            team: Some(team.to_string()),
+            bytes,
+            crates,
+            max_bytes: Some(quota.max_bytes).filter(|&v| v > 0),
+            max_crates: Some(quota.max_crates).filter(|&v| v > 0),
+        }
+    }
+
+    /// Checks that publishing a version of the given size, possibly of a new crate, stays within the quotas
+    ///
+    /// # Errors
+    ///
+    /// Returns a `403` error explaining the exceeded quota
+    pub fn check_publish(&self, size: i64, is_new_crate: bool) -> Result<(), ApiError> {
+        let (owner, usage, quota) = match &self.team {
+            None => (
+                String::from("you already own"),
+                String::from("your usage"),
+                String::from("your quota"),
+            ),
+            Some(team) => (
+                format!("your team {team} already owns"),
+                format!("the usage of your team {team}"),
+                format!("the quota of your team {team}"),
+            ),
+        };
+        if let Some(max_crates) = self.max_crates {
+            if is_new_crate && self.crates >= max_crates {
+                return Err(specialize(
+                    error_forbidden(),
+                    format!(
+                        "quota exceeded: {owner} {} crates out of {max_crates} allowed, ask an administrator to raise {quota}",
+                        self.crates
+                    ),
+                ));
+            }
+        }
+        if let Some(max_bytes) = self.max_bytes {
+            if self.bytes.saturating_add(size) > max_bytes {
+                return Err(specialize(
+                    error_forbidden(),
+                    format!(
+                        "quota exceeded: this upload of {size} bytes would bring {usage} to {} bytes out of {max_bytes} allowed, ask an administrator to raise {quota}",
+                        self.bytes.saturating_add(size)
+                    ),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,323 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Data types for the Software Bill of Materials (SBOM) of crate versions, in the `CycloneDX` format
+
+use chrono::{NaiveDateTime, SecondsFormat};
+use serde_derive::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::cargo::{CrateMetadata, IndexCrateMetadata};
+use super::deps::{DepsAnalysis, ResolvedDepInfo};
+use crate::utils::hashes::sha256_bytes;
+
+/// The version of the `CycloneDX` specification for the produced documents
+pub const SBOM_SPEC_VERSION: &str = "1.5";
+/// The URI of the registry for crates.io, as found in the dependencies
+pub const CRATES_IO_REGISTRY_URI: &str = "https://github.com/rust-lang/crates.io-index";
+
+/// A `CycloneDX` SBOM for a crate version
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Sbom {
+    /// The format of the document, always `CycloneDX`
+    pub bom_format: String,
+    /// The version of the specification
+    pub spec_version: String,
+    /// The unique identifier of the document
+    pub serial_number: String,
+    /// The version of the document
+    pub version: u32,
+    /// The metadata about the document and the crate version it describes
+    pub metadata: SbomMetadata,
+    /// The resolved dependencies of the crate version
+    pub components: Vec<SbomComponent>,
+    /// The dependency relationships
+    pub dependencies: Vec<SbomDependency>,
+    /// The known vulnerabilities affecting the dependencies
+    pub vulnerabilities: Vec<SbomVulnerability>,
+}
+
+/// The metadata of a SBOM
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SbomMetadata {
+    /// The timestamp of the generation of the document
+    pub timestamp: String,
+    /// The tools used to produce the document
+    pub tools: SbomTools,
+    /// The component described by the document, i.e. the crate version
+    pub component: SbomComponent,
+}
+
+/// The tools that produced a SBOM
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SbomTools {
+    /// The tools as components
+    pub components: Vec<SbomTool>,
+}
+
+/// A tool that produced a SBOM
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SbomTool {
+    /// The type of component
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// The name of the tool
+    pub name: String,
+    /// The version of the tool
+    pub version: String,
+}
+
+/// A component in a SBOM, i.e. a crate version
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SbomComponent {
+    /// The type of component
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// The reference of the component within the document
+    #[serde(rename = "bom-ref")]
+    pub bom_ref: String,
+    /// The name of the crate
+    pub name: String,
+    /// The version of the crate
+    pub version: String,
+    /// The description of the crate, if known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// The authors of the crate, if known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    /// The licenses of the crate, if known
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub licenses: Vec<SbomLicense>,
+    /// The package URL for the crate
+    pub purl: String,
+    /// The hashes of the `.crate` file
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub hashes: Vec<SbomHash>,
+    /// The scope for a dependency, `required`, or `excluded` for development dependencies
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    /// The references to external resources
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub external_references: Vec<SbomExternalReference>,
+}
+
+/// A license in a SBOM, as an SPDX expression
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SbomLicense {
+    /// The SPDX expression for the license
+    pub expression: String,
+}
+
+/// The hash of a component in a SBOM
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SbomHash {
+    /// The hash algorithm
+    pub alg: String,
+    /// The hexadecimal value of the hash
+    pub content: String,
+}
+
+/// A reference to an external resource for a component in a SBOM
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SbomExternalReference {
+    /// The type of reference
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// The URL for the resource
+    pub url: String,
+}
+
+/// The dependencies of a component in a SBOM
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SbomDependency {
+    /// The reference of the dependent component
+    #[serde(rename = "ref")]
+    pub reference: String,
+    /// The references of the components it depends on
+    pub depends_on: Vec<String>,
+}
+
+/// A vulnerability affecting a component in a SBOM
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SbomVulnerability {
+    /// The identifier of the advisory
+    pub id: String,
+    /// The source of the advisory
+    pub source: SbomVulnerabilitySource,
+    /// The summary for the advisory
+    pub description: String,
+    /// The affected components
+    pub affects: Vec<SbomVulnerabilityAffect>,
+}
+
+/// The source of a vulnerability in a SBOM
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SbomVulnerabilitySource {
+    /// The name of the source
+    pub name: String,
+    /// The URL for the advisory
+    pub url: String,
+}
+
+/// A component affected by a vulnerability in a SBOM
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SbomVulnerabilityAffect {
+    /// The reference of the affected component
+    #[serde(rename = "ref")]
+    pub reference: String,
+}
+
+/// Gets the package URL for a crate version
+///
+/// The registry is `None` for the local one and the qualifier is omitted for crates.io.
+#[must_use]
+pub fn crate_purl(registry: Option<&str>, local_registry: &str, package: &str, version: &str) -> String {
+    if registry == Some(CRATES_IO_REGISTRY_URI) {
+        format!("pkg:cargo/{package}@{version}")
+    } else {
+        let repository = urlencoding::encode(registry.unwrap_or(local_registry));
+        format!("pkg:cargo/{package}@{version}?repository_url={repository}")
+    }
+}
+
+impl Sbom {
+    /// Builds the SBOM for a crate version, from its metadata and the analysis of its dependencies
+    ///
+    /// The `local_registry` is the public URI of this registry.
+    /// The serial number and the timestamp are derived from the crate version and its publication date (`upload`),
+    /// so that the same document is produced for the same resolution of the dependencies.
+    #[must_use]
+    pub fn new(
+        local_registry: &str,
+        index: &IndexCrateMetadata,
+        metadata: Option<&CrateMetadata>,
+        upload: NaiveDateTime,
+        analysis: &DepsAnalysis,
+    ) -> Self {
+        let root_ref = crate_purl(None, local_registry, &index.name, &index.vers);
+        let dep_ref = |dep: &ResolvedDepInfo| crate_purl(dep.registry.as_deref(), local_registry, &dep.package, &dep.version);
+        let mut serial = [0_u8; 16];
+        serial.copy_from_slice(&sha256_bytes(format!("{root_ref}#{}", index.cksum).as_bytes())[..16]);
+        let component = Self::crate_component(local_registry, index, metadata);
+        let components = analysis
+            .resolved
+            .iter()
+            .map(|dep| SbomComponent {
+                kind: String::from("library"),
+                bom_ref: dep_ref(dep),
+                name: dep.package.clone(),
+                version: dep.version.clone(),
+                description: None,
+                author: None,
+                licenses: Vec::new(),
+                purl: dep_ref(dep),
+                hashes: vec![SbomHash {
+                    alg: String::from("SHA-256"),
+                    content: dep.checksum.clone(),
+                }],
+                scope: Some(String::from(if dep.is_dev_only { "excluded" } else { "required" })),
+                external_references: Vec::new(),
+            })
+            .collect();
+        let dependencies = std::iter::once(SbomDependency {
+            reference: root_ref,
+            depends_on: analysis.resolved.iter().filter(|dep| dep.is_direct).map(dep_ref).collect(),
+        })
+        .chain(analysis.resolved.iter().map(|dep| {
+            SbomDependency {
+                reference: dep_ref(dep),
+                depends_on: dep
+                    .dependencies
+                    .iter()
+                    .map(|sub| crate_purl(sub.registry.as_deref(), local_registry, &sub.package, &sub.version))
+                    .collect(),
+            }
+        }))
+        .collect();
+        let vulnerabilities = analysis
+            .advisories
+            .iter()
+            .map(|advisory| SbomVulnerability {
+                id: advisory.content.id.clone(),
+                source: SbomVulnerabilitySource {
+                    name: String::from("RustSec"),
+                    url: format!("https://rustsec.org/advisories/{}.html", advisory.content.id),
+                },
+                description: advisory.content.summary.clone(),
+                affects: analysis
+                    .resolved
+                    .iter()
+                    .filter(|dep| dep.package == advisory.package && dep.version == advisory.version.to_string())
+                    .map(|dep| SbomVulnerabilityAffect { reference: dep_ref(dep) })
+                    .collect(),
+            })
+            .collect();
+        Self {
+            bom_format: String::from("CycloneDX"),
+            spec_version: String::from(SBOM_SPEC_VERSION),
+            serial_number: format!("urn:uuid:{}", uuid::Builder::from_custom_bytes(serial).into_uuid()),
+            version: 1,
+            metadata: SbomMetadata {
+                timestamp: upload.and_utc().to_rfc3339_opts(SecondsFormat::Secs, true),
+                tools: SbomTools {
+                    components: vec![SbomTool {
+                        kind: String::from("application"),
+                        name: crate::CRATE_NAME.to_string(),
+                        version: crate::GIT_TAG.to_string(),
+                    }],
+                },
+                component,
+            },
+            components,
+            dependencies,
+            vulnerabilities,
+        }
+    }
+
+    /// Builds the component describing the crate version itself
+    fn crate_component(local_registry: &str, index: &IndexCrateMetadata, metadata: Option<&CrateMetadata>) -> SbomComponent {
+        let purl = crate_purl(None, local_registry, &index.name, &index.vers);
+        let mut external_references = vec![SbomExternalReference {
+            kind: String::from("distribution"),
+            url: format!("{local_registry}/api/v1/crates/{}/{}/download", index.name, index.vers),
+        }];
+        if let Some(metadata) = metadata {
+            for (kind, url) in [
+                ("website", &metadata.homepage),
+                ("vcs", &metadata.repository),
+                ("documentation", &metadata.documentation),
+            ] {
+                if let Some(url) = url {
+                    external_references.push(SbomExternalReference {
+                        kind: kind.to_string(),
+                        url: url.clone(),
+                    });
+                }
+            }
+        }
+        SbomComponent {
+            kind: String::from("library"),
+            bom_ref: purl.clone(),
+            name: index.name.clone(),
+            version: index.vers.clone(),
+            description: metadata.and_then(|m| m.description.clone()),
+            author: metadata.filter(|m| !m.authors.is_empty()).map(|m| m.authors.join(", ")),
+            licenses: metadata
+                .and_then(|m| m.license.clone())
+                .map(|expression| vec![SbomLicense { expression }])
+                .unwrap_or_default(),
+            purl,
+            hashes: vec![SbomHash {
+                alg: String::from("SHA-256"),
+                content: index.cksum.clone(),
+            }],
+            scope: None,
+            external_references,
+        }
+    }
+}
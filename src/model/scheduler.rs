@@ -0,0 +1,35 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Data types for the background jobs run on a schedule
+
+use chrono::NaiveDateTime;
+use serde_derive::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// The status of a background job run on a schedule
+#[derive(Debug, Default, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduledJobStatus {
+    /// The name of the job
+    pub name: String,
+    /// A description of what the job does
+    pub description: String,
+    /// Whether the feature of the job is activated in the configuration, a disabled job cannot be run
+    pub enabled: bool,
+    /// The number of seconds between two runs, `None` when the job is only run on demand
+    pub period: Option<u64>,
+    /// Whether the job is currently running
+    pub is_running: bool,
+    /// The timestamp of the start of the last run
+    pub last_started_on: Option<NaiveDateTime>,
+    /// The timestamp of the end of the last run
+    pub last_finished_on: Option<NaiveDateTime>,
+    /// The error of the last run, if it failed
+    pub last_error: Option<String>,
+    /// The number of runs since the start of the registry
+    pub runs: u64,
+    /// The timestamp of the next scheduled run, if any
+    pub next_run_on: Option<NaiveDateTime>,
+}
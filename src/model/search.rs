@@ -0,0 +1,137 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Data types for the full-text search of crates
+
+use chrono::NaiveDateTime;
+use serde_derive::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::cargo::CrateMetadata;
+use super::pagination::Pagination;
+
+/// The data indexed for a crate in the full-text search
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CrateSearchDocument {
+    /// The name of the crate
+    pub name: String,
+    /// The textual description of the crate
+    pub description: String,
+    /// The keywords for the crate
+    pub keywords: Vec<String>,
+    /// The categories for the crate
+    pub categories: Vec<String>,
+    /// The content of the README
+    pub readme: String,
+}
+
+impl CrateSearchDocument {
+    /// Builds the document for a version of a crate, from its metadata and README
+    #[must_use]
+    pub fn from_metadata(metadata: &CrateMetadata, readme: &[u8]) -> Self {
+        Self {
+            name: metadata.name.clone(),
+            description: metadata.description.clone().unwrap_or_default(),
+            keywords: metadata.keywords.clone(),
+            categories: metadata.categories.clone(),
+            readme: String::from_utf8_lossy(readme).into_owned(),
+        }
+    }
+}
+
+/// The filters that can be applied to a search, in addition to the text query
+#[derive(Debug, Default, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SearchFilters {
+    /// Only retain crates with this keyword
+    pub keyword: Option<String>,
+    /// Only retain crates in this category
+    pub category: Option<String>,
+    /// Only retain crates owned by the user with this login
+    pub owner: Option<String>,
+    /// Only retain crates that are deprecated, or not
+    pub deprecated: Option<bool>,
+    /// Whether to include crates for which all versions are yanked
+    pub include_yanked: bool,
+}
+
+/// The order of search results, with the same values as crates.io
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum SearchSort {
+    /// By decreasing relevance, boosted by popularity and activity
+    #[default]
+    Relevance,
+    /// By decreasing number of downloads
+    Downloads,
+    /// By decreasing number of downloads over the last days
+    RecentDownloads,
+    /// By decreasing date of the last publication
+    RecentUpdates,
+    /// By decreasing date of the first publication
+    New,
+    /// By alphabetical order of names
+    Alpha,
+}
+
+/// The requested page of search results
+pub type SearchPagination = Pagination<SearchSort>;
+
+/// A crate matched by the full-text search
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CrateSearchHit {
+    /// The name of the crate
+    pub name: String,
+    /// The relevance of the crate for the query
+    pub relevance: f32,
+    /// The keywords for the crate, in lowercase
+    pub keywords: Vec<String>,
+    /// The categories for the crate, in lowercase
+    pub categories: Vec<String>,
+}
+
+/// The number of days after which the boost for the recency of a crate is halved
+const RECENCY_HALF_LIFE_DAYS: f64 = 180.0;
+
+/// The weights of the popularity and activity of crates in the ranking of search results
+#[derive(Debug, Clone, Copy)]
+pub struct SearchRankingWeights {
+    /// The weight of the number of downloads
+    pub downloads: f64,
+    /// The weight of the date of the last publication
+    pub recency: f64,
+}
+
+impl SearchRankingWeights {
+    /// Computes the final score of a crate in search results
+    ///
+    /// The relevance is multiplied by a boost for the downloads that grows logarithmically
+    /// and by a boost for the last publication that decays with a half-life of 6 months.
+    /// Both boosts are neutral when their weight is 0.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn score(&self, relevance: f32, downloads: i64, last_publish: NaiveDateTime, now: NaiveDateTime) -> f64 {
+        let downloads_boost = 1.0 + self.downloads * (downloads.max(0) as f64).ln_1p();
+        let age_days = (now - last_publish).num_days().max(0) as f64;
+        let recency_boost = 1.0 + self.recency * 0.5_f64.powf(age_days / RECENCY_HALF_LIFE_DAYS);
+        f64::from(relevance) * downloads_boost * recency_boost
+    }
+}
+
+/// The progress of a job rebuilding the whole search index
+#[derive(Debug, Default, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchReindexProgress {
+    /// Whether the job is running
+    pub is_running: bool,
+    /// The total number of crates to index
+    pub total: usize,
+    /// The number of crates that have been processed so far
+    pub done: usize,
+    /// The timestamp when the job started
+    pub started_on: Option<NaiveDateTime>,
+    /// The timestamp when the job finished
+    pub finished_on: Option<NaiveDateTime>,
+    /// The error that stopped the job, if any
+    pub error: Option<String>,
+}
@@ -0,0 +1,117 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Scan of the content of published crates for committed secrets
+
+use std::fmt::{Display, Formatter};
+use std::io::Read;
+use std::sync::{Arc, LazyLock, Mutex};
+
+use flate2::read::GzDecoder;
+use regex::Regex;
+use tar::{Archive, EntryType};
+
+use super::config::SecretPattern;
+use super::sources::CRATE_FILE_MAX_SIZE;
+use crate::utils::apierror::{error_backend_failure, specialize, ApiError};
+
+/// The maximum number of findings reported for a crate version
+pub const SECRETS_MAX_FINDINGS: usize = 20;
+
+/// The compiled patterns for secrets, with their names
+pub type SecretRegexes = Vec<(String, Regex)>;
+
+/// The configured patterns with their compiled form
+type CompiledPatterns = (Vec<SecretPattern>, Arc<SecretRegexes>);
+
+/// The last compiled patterns, reused for as long as the configured patterns do not change
+static COMPILED_PATTERNS: LazyLock<Mutex<Option<CompiledPatterns>>> = LazyLock::new(|| Mutex::new(None));
+
+/// Gets the compiled form of the patterns, only compiling them when they changed since the last call
+pub fn compile_secret_patterns(patterns: &[SecretPattern]) -> Result<Arc<SecretRegexes>, ApiError> {
+    let mut compiled = COMPILED_PATTERNS.lock().unwrap_or_else(std::sync::PoisonError::into_inner);
+    if let Some((source, regexes)) = compiled.as_ref() {
+        if source.as_slice() == patterns {
+            return Ok(regexes.clone());
+        }
+    }
+    let regexes = Arc::new(
+        patterns
+            .iter()
+            .map(|pattern| {
+                Regex::new(&pattern.regex)
+                    .map(|regex| (pattern.name.clone(), regex))
+                    .map_err(|error| specialize(error_backend_failure(), format!("invalid pattern {}: {error}", pattern.name)))
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+    );
+    *compiled = Some((patterns.to_vec(), regexes.clone()));
+    Ok(regexes)
+}
+
+/// A possible secret found in the content of a crate
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecretFinding {
+    /// The path of the file, relative to the root of the crate
+    pub path: String,
+    /// The line in the file, starting at 1
+    pub line: usize,
+    /// The name of the pattern that matched
+    pub pattern: String,
+}
+
+impl Display for SecretFinding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} found in {}:{}", self.pattern, self.path, self.line)
+    }
+}
+
+/// Scans the files in a `.crate` archive for secrets matching the patterns
+///
+/// Binary files and files larger than `CRATE_FILE_MAX_SIZE` are skipped.
+/// The matched values are never reported, only their location and the kind of secret.
+pub fn scan_crate_secrets(
+    content: &[u8],
+    name: &str,
+    version: &str,
+    patterns: &[(String, Regex)],
+) -> Result<Vec<SecretFinding>, ApiError> {
+    let prefix = format!("{name}-{version}");
+    let mut findings = Vec::new();
+    let mut archive = Archive::new(GzDecoder::new(content));
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if entry.header().entry_type() != EntryType::Regular || entry.header().size()? > CRATE_FILE_MAX_SIZE {
+            continue;
+        }
+        let path = entry.path()?.into_owned();
+        let path = path
+            .strip_prefix(&prefix)
+            .unwrap_or(&path)
+            .to_string_lossy()
+            .replace('\\', "/");
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data)?;
+        if data.contains(&0) {
+            // binary file
+            continue;
+        }
+        let text = String::from_utf8_lossy(&data);
+        for (index, line) in text.lines().enumerate() {
+            for (pattern, regex) in patterns {
+                if regex.is_match(line) {
+                    findings.push(SecretFinding {
+                        path: path.clone(),
+                        line: index + 1,
+                        pattern: pattern.clone(),
+                    });
+                    if findings.len() >= SECRETS_MAX_FINDINGS {
+                        return Ok(findings);
+                    }
+                }
+            }
+        }
+    }
+    Ok(findings)
+}
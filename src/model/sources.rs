@@ -0,0 +1,265 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Data types for browsing and comparing the files in the `.crate` archives of crate versions
+
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::time::{Duration, Instant};
+
+use flate2::read::GzDecoder;
+use serde_derive::{Deserialize, Serialize};
+use similar::TextDiff;
+use tar::{Archive, EntryType};
+use utoipa::ToSchema;
+
+use crate::utils::apierror::{error_invalid_request, specialize, ApiError};
+use crate::utils::hashes::{sha256, sha256_reader};
+
+/// The maximum size of a single file that can be fetched from a `.crate` archive, in bytes
+pub const CRATE_FILE_MAX_SIZE: u64 = 1024 * 1024;
+
+/// The maximum total length of the unified diffs between two versions, in bytes
+pub const CRATE_DIFF_MAX_LENGTH: usize = 4 * 1024 * 1024;
+
+/// The maximum total size of the files in a `.crate` archive that are decompressed to compare two versions, in bytes
+pub const CRATE_UNPACKED_MAX_SIZE: u64 = 64 * 1024 * 1024;
+
+/// The number of lines of context around changes in unified diffs
+const CRATE_DIFF_CONTEXT: usize = 3;
+
+/// The maximum time spent computing the differences for a single file, after which a coarser diff is produced
+const CRATE_DIFF_DEADLINE: Duration = Duration::from_millis(500);
+
+/// A file in the `.crate` archive of a crate version
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct CrateFile {
+    /// The path of the file, relative to the root of the crate
+    pub path: String,
+    /// The size of the file, in bytes
+    pub size: u64,
+}
+
+/// Lists the regular files in a `.crate` archive, by order of paths
+///
+/// The paths are relative to the root of the crate, without the `<name>-<version>/` prefix.
+pub fn list_crate_files(content: &[u8], name: &str, version: &str) -> Result<Vec<CrateFile>, ApiError> {
+    let prefix = format!("{name}-{version}");
+    let mut files = Vec::new();
+    let mut archive = Archive::new(GzDecoder::new(content));
+    for entry in archive.entries()? {
+        let entry = entry?;
+        if entry.header().entry_type() != EntryType::Regular {
+            continue;
+        }
+        let path = entry.path()?.into_owned();
+        let Ok(relative) = path.strip_prefix(&prefix) else {
+            continue;
+        };
+        files.push(CrateFile {
+            path: relative.to_string_lossy().replace('\\', "/"),
+            size: entry.header().size()?,
+        });
+    }
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(files)
+}
+
+/// Extracts a single file from a `.crate` archive, given its path relative to the root of the crate
+///
+/// Files larger than `CRATE_FILE_MAX_SIZE` are rejected, the whole crate should be downloaded instead.
+pub fn extract_crate_file(content: &[u8], name: &str, version: &str, path: &str) -> Result<Option<Vec<u8>>, ApiError> {
+    let prefix = format!("{name}-{version}");
+    let mut archive = Archive::new(GzDecoder::new(content));
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if entry.header().entry_type() != EntryType::Regular {
+            continue;
+        }
+        let entry_path = entry.path()?.into_owned();
+        let is_match = entry_path
+            .strip_prefix(&prefix)
+            .is_ok_and(|relative| relative.to_string_lossy().replace('\\', "/") == path);
+        if !is_match {
+            continue;
+        }
+        let size = entry.header().size()?;
+        if size > CRATE_FILE_MAX_SIZE {
+            return Err(specialize(
+                error_invalid_request(),
+                format!("file {path} is too large ({size} bytes, at most {CRATE_FILE_MAX_SIZE}), download the crate instead"),
+            ));
+        }
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data)?;
+        return Ok(Some(data));
+    }
+    Ok(None)
+}
+
+/// The kind of change to a file between two versions
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum CrateFileChange {
+    /// The file only exists in the new version
+    Added,
+    /// The file only exists in the old version
+    Removed,
+    /// The content of the file changed
+    Modified,
+}
+
+/// The difference for a single file between two versions
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CrateFileDiff {
+    /// The path of the file, relative to the root of the crate
+    pub path: String,
+    /// The kind of change
+    pub change: CrateFileChange,
+    /// The size of the file in the old version, if present
+    #[serde(rename = "oldSize")]
+    pub old_size: Option<u64>,
+    /// The size of the file in the new version, if present
+    #[serde(rename = "newSize")]
+    pub new_size: Option<u64>,
+    /// The unified diff for text files,
+    /// not given for binary files, files larger than `CRATE_FILE_MAX_SIZE` or when the diff was truncated
+    pub diff: Option<String>,
+}
+
+/// The differences between the files of two versions of a crate
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CrateDiff {
+    /// The old version
+    pub from: String,
+    /// The new version
+    pub to: String,
+    /// The files that changed, by order of paths
+    pub files: Vec<CrateFileDiff>,
+    /// Whether unified diffs were omitted for some files because the total exceeded `CRATE_DIFF_MAX_LENGTH`
+    pub truncated: bool,
+}
+
+/// The content of a file read from a `.crate` archive, when small enough
+struct CrateFileContent {
+    /// The size of the file
+    size: u64,
+    /// The SHA256 checksum of the content
+    checksum: String,
+    /// The content, when not larger than `CRATE_FILE_MAX_SIZE`
+    data: Option<Vec<u8>>,
+}
+
+/// Reads all the regular files in a `.crate` archive
+///
+/// Only the files not larger than `CRATE_FILE_MAX_SIZE` are kept in memory, the others are only hashed.
+/// Archives whose files total more than `CRATE_UNPACKED_MAX_SIZE` are rejected.
+fn read_crate_files(content: &[u8], name: &str, version: &str) -> Result<BTreeMap<String, CrateFileContent>, ApiError> {
+    let prefix = format!("{name}-{version}");
+    let mut files = BTreeMap::new();
+    let mut total_size = 0;
+    let mut archive = Archive::new(GzDecoder::new(content));
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if entry.header().entry_type() != EntryType::Regular {
+            continue;
+        }
+        let size = entry.header().size()?;
+        total_size += size;
+        if total_size > CRATE_UNPACKED_MAX_SIZE {
+            return Err(specialize(
+                error_invalid_request(),
+                format!(
+                    "the files of {name} {version} are too large to be compared (more than {CRATE_UNPACKED_MAX_SIZE} bytes)"
+                ),
+            ));
+        }
+        let path = entry.path()?.into_owned();
+        let Ok(relative) = path.strip_prefix(&prefix) else {
+            continue;
+        };
+        let relative = relative.to_string_lossy().replace('\\', "/");
+        let file = if size > CRATE_FILE_MAX_SIZE {
+            CrateFileContent {
+                size,
+                checksum: sha256_reader(&mut entry)?,
+                data: None,
+            }
+        } else {
+            let mut data = Vec::new();
+            entry.read_to_end(&mut data)?;
+            CrateFileContent {
+                size,
+                checksum: sha256(&data),
+                data: Some(data),
+            }
+        };
+        files.insert(relative, file);
+    }
+    Ok(files)
+}
+
+/// Computes the differences between the `.crate` archives of two versions of a crate
+pub fn diff_crates(name: &str, from: (&str, &[u8]), to: (&str, &[u8])) -> Result<CrateDiff, ApiError> {
+    let (from_version, from_content) = from;
+    let (to_version, to_content) = to;
+    let old_files = read_crate_files(from_content, name, from_version)?;
+    let new_files = read_crate_files(to_content, name, to_version)?;
+    let mut paths = old_files.keys().chain(new_files.keys()).collect::<Vec<_>>();
+    paths.sort();
+    paths.dedup();
+
+    let mut files = Vec::new();
+    let mut total_length = 0;
+    let mut truncated = false;
+    for path in paths {
+        let old = old_files.get(path);
+        let new = new_files.get(path);
+        let change = match (old, new) {
+            (None, Some(_)) => CrateFileChange::Added,
+            (Some(_), None) => CrateFileChange::Removed,
+            (Some(old), Some(new)) if old.checksum == new.checksum => continue,
+            _ => CrateFileChange::Modified,
+        };
+        let old_text = old.map_or(Some(""), |file| {
+            file.data.as_deref().and_then(|data| std::str::from_utf8(data).ok())
+        });
+        let new_text = new.map_or(Some(""), |file| {
+            file.data.as_deref().and_then(|data| std::str::from_utf8(data).ok())
+        });
+        let mut diff = match (old_text, new_text) {
+            (Some(old_text), Some(new_text)) => Some(
+                TextDiff::configure()
+                    .deadline(Instant::now() + CRATE_DIFF_DEADLINE)
+                    .diff_lines(old_text, new_text)
+                    .unified_diff()
+                    .context_radius(CRATE_DIFF_CONTEXT)
+                    .header(&format!("a/{path}"), &format!("b/{path}"))
+                    .to_string(),
+            ),
+            _ => None,
+        };
+        if let Some(text) = &diff {
+            if truncated || total_length + text.len() > CRATE_DIFF_MAX_LENGTH {
+                truncated = true;
+                diff = None;
+            } else {
+                total_length += text.len();
+            }
+        }
+        files.push(CrateFileDiff {
+            path: path.clone(),
+            change,
+            old_size: old.map(|file| file.size),
+            new_size: new.map(|file| file.size),
+            diff,
+        });
+    }
+    Ok(CrateDiff {
+        from: from_version.to_string(),
+        to: to_version.to_string(),
+        files,
+        truncated,
+    })
+}
@@ -5,14 +5,16 @@
 //! Data types for global statistics
 
 use byteorder::ByteOrder;
-use chrono::{Datelike, Days, Local, NaiveDate};
+use chrono::{Datelike, Days, Local, NaiveDate, NaiveDateTime};
 use semver::Version;
 use serde_derive::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
+use super::cargo::RegistryUser;
 use super::CrateVersion;
 
 /// The global stats for the registry
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct GlobalStats {
     /// Total number of downloads
     #[serde(rename = "totalDownloads")]
@@ -26,16 +28,164 @@ pub struct GlobalStats {
     /// The most downloaded crates in the registry
     #[serde(rename = "cratesMostDownloaded")]
     pub crates_most_downloaded: Vec<CrateVersion>,
+    /// The most starred crates in the registry
+    #[serde(rename = "cratesMostStarred")]
+    pub crates_most_starred: Vec<CrateVersion>,
+    /// The most downloaded crates over the last days, see `TRENDING_DAYS`
+    #[serde(rename = "cratesTrending")]
+    pub crates_trending: Vec<CrateVersion>,
     /// the last updated crates in the registry
     #[serde(rename = "cratesLastUpdated")]
     pub crates_last_updated: Vec<CrateVersion>,
 }
 
+/// The registry-wide statistics, for administrators
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct RegistryStats {
+    /// Total number of crates
+    #[serde(rename = "totalCrates")]
+    pub total_crates: i64,
+    /// Total number of crate versions
+    #[serde(rename = "totalVersions")]
+    pub total_versions: i64,
+    /// Total size in bytes of the stored `.crate` files
+    #[serde(rename = "storageUsed")]
+    pub storage_used: i64,
+    /// Number of versions published before sizes were recorded, not accounted in `storage_used` until they are computed
+    #[serde(rename = "storageUnknownVersions")]
+    pub storage_unknown_versions: i64,
+    /// Total number of active users
+    #[serde(rename = "totalUsers")]
+    pub total_users: i64,
+    /// Total number of tokens, for users and global
+    #[serde(rename = "totalTokens")]
+    pub total_tokens: i64,
+    /// The days in the data series for publications
+    pub days: Vec<NaiveDate>,
+    /// The number of published versions for each day
+    #[serde(rename = "publishesPerDay")]
+    pub publishes_per_day: Vec<u32>,
+    /// Number of documentation generation jobs waiting to be executed
+    #[serde(rename = "docsQueued")]
+    pub docs_queued: i64,
+    /// Number of documentation generation jobs being executed
+    #[serde(rename = "docsWorking")]
+    pub docs_working: i64,
+}
+
+/// The storage used by a crate
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct CrateStorageUsage {
+    /// The name of the crate
+    pub package: String,
+    /// The number of versions
+    pub versions: i64,
+    /// The total size in bytes of the `.crate` files
+    #[serde(rename = "cratesSize")]
+    pub crates_size: i64,
+    /// The total size in bytes of the generated documentation
+    #[serde(rename = "docsSize")]
+    pub docs_size: i64,
+}
+
+/// The storage used by the versions published by a user
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct UserStorageUsage {
+    /// The publishing user
+    pub user: RegistryUser,
+    /// The number of published versions
+    pub versions: i64,
+    /// The total size in bytes of the `.crate` files
+    #[serde(rename = "cratesSize")]
+    pub crates_size: i64,
+    /// The total size in bytes of the generated documentation
+    #[serde(rename = "docsSize")]
+    pub docs_size: i64,
+}
+
+/// The report of the storage used by crates and publishing users, largest first
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct StorageUsageReport {
+    /// The storage used by each crate
+    pub crates: Vec<CrateStorageUsage>,
+    /// The storage used by each publishing user
+    pub users: Vec<UserStorageUsage>,
+}
+
+/// The publish activity of a user
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct UserPublishActivity {
+    /// The user
+    pub user: RegistryUser,
+    /// The total number of versions published by the user
+    pub publishes: i64,
+    /// The number of versions published by the user over the last days, see `SERIES_LENGTH`
+    #[serde(rename = "publishesRecent")]
+    pub publishes_recent: i64,
+    /// The timestamp of the last publication by the user, if any
+    #[serde(rename = "lastPublish")]
+    pub last_publish: Option<NaiveDateTime>,
+    /// The number of crates owned by the user
+    #[serde(rename = "cratesOwned")]
+    pub crates_owned: i64,
+}
+
+/// The publish activity across the registry, for administrators
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct PublishActivityReport {
+    /// The activity of each user, the most recently active first
+    pub users: Vec<UserPublishActivity>,
+    /// The crates that have no active owner
+    #[serde(rename = "cratesWithoutActiveOwner")]
+    pub crates_without_active_owner: Vec<String>,
+}
+
+/// The requests served for a token
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct TokenTraffic {
+    /// The unique identifier of the token
+    pub id: i64,
+    /// The token name
+    pub name: String,
+    /// The user owning the token, none for registry-wide tokens
+    pub user: Option<RegistryUser>,
+    /// The last time the token was used
+    #[serde(rename = "lastUsed")]
+    pub last_used: NaiveDateTime,
+    /// The number of requests served
+    pub requests: i64,
+    /// The number of bytes served
+    pub bytes: i64,
+}
+
+/// The requests served for a user, through the web application and all the user's tokens
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct UserTraffic {
+    /// The user
+    pub user: RegistryUser,
+    /// The number of requests served
+    pub requests: i64,
+    /// The number of bytes served
+    pub bytes: i64,
+}
+
+/// The requests served for each user and token, for administrators
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct TrafficReport {
+    /// The traffic for each user, largest first
+    pub users: Vec<UserTraffic>,
+    /// The traffic for each token, largest first
+    pub tokens: Vec<TokenTraffic>,
+}
+
 /// The length of a series, i.e. the maximum number of days in the series
 pub const SERIES_LENGTH: usize = 90;
 
+/// The number of days over which the downloads are counted for trending crates
+pub const TRENDING_DAYS: usize = 7;
+
 /// The download counters for a specific version
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct DownloadStatsForVersion {
     /// The version
     pub version: String,
@@ -49,7 +199,7 @@ pub struct DownloadStatsForVersion {
 }
 
 /// The download stats for a crate, for each version
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct DownloadStats {
     /// The days in the data series
     pub days: Vec<NaiveDate>,
@@ -76,21 +226,70 @@ impl Default for DownloadStats {
     }
 }
 
-impl DownloadStats {
-    /// Adds the data for a version
-    pub fn add_version(&mut self, version: String, data: Option<&[u8]>) {
-        let mut counts = vec![0; SERIES_LENGTH];
-        let mut total = 0;
-        if let Some(data) = data {
-            let today = Local::now().naive_local().date();
+/// The size of a stored series of daily download counts
+///
+/// The series is stored as the counts for the `SERIES_LENGTH` days up to the day of the last download, oldest first,
+/// followed by this day, as the number of days from the common era.
+const SERIES_DATA_LENGTH: usize = (SERIES_LENGTH + 1) * size_of::<u32>();
+
+/// Reads the stored series of daily download counts, from the oldest day to today
+#[must_use]
+pub fn read_download_series(data: Option<&[u8]>) -> Vec<u32> {
+    read_download_series_on(data, Local::now().naive_local().date())
+}
+
+/// Reads the stored series of daily download counts, from the oldest day to the specified one
+///
+/// The days without downloads since the last one are counted as zero.
+#[must_use]
+pub fn read_download_series_on(data: Option<&[u8]>, today: NaiveDate) -> Vec<u32> {
+    let mut counts = vec![0; SERIES_LENGTH];
+    match data {
+        Some(data) if data.len() == SERIES_DATA_LENGTH => {
+            let last_day = byteorder::NativeEndian::read_i32(&data[SERIES_LENGTH * size_of::<u32>()..]);
+            let elapsed = usize::try_from(today.num_days_from_ce() - last_day).unwrap_or(0);
+            for (i, count) in counts.iter_mut().take(SERIES_LENGTH.saturating_sub(elapsed)).enumerate() {
+                *count = byteorder::NativeEndian::read_u32(&data[(i + elapsed) * size_of::<u32>()..]);
+            }
+        }
+        Some(data) if data.len() == SERIES_LENGTH * size_of::<u32>() => {
+            // previous format, a ring indexed by the day in the year, without the day of the last download
             let mut index = ((today.ordinal0() + 1) as usize % SERIES_LENGTH) * size_of::<u32>();
             for count in &mut counts {
-                let v = byteorder::NativeEndian::read_u32(&data[index..]);
-                total += v;
-                *count = v;
+                *count = byteorder::NativeEndian::read_u32(&data[index..]);
                 index = (index + size_of::<u32>()) % data.len();
             }
         }
+        _ => {}
+    }
+    counts
+}
+
+/// Adds downloads for the specified day to a stored series of daily download counts
+#[must_use]
+pub fn add_download_series(data: Option<&[u8]>, today: NaiveDate, count: u32) -> Vec<u8> {
+    let mut counts = read_download_series_on(data, today);
+    counts[SERIES_LENGTH - 1] = counts[SERIES_LENGTH - 1].saturating_add(count);
+    let mut data = vec![0; SERIES_DATA_LENGTH];
+    for (i, count) in counts.into_iter().enumerate() {
+        byteorder::NativeEndian::write_u32(&mut data[i * size_of::<u32>()..], count);
+    }
+    byteorder::NativeEndian::write_i32(&mut data[SERIES_LENGTH * size_of::<u32>()..], today.num_days_from_ce());
+    data
+}
+
+/// Counts the downloads in the stored series of daily download counts,
+/// i.e. the downloads over the last days, as the `recent_downloads` of the crates.io API
+#[must_use]
+pub fn count_recent_downloads(data: Option<&[u8]>) -> i64 {
+    read_download_series(data).into_iter().map(i64::from).sum()
+}
+
+impl DownloadStats {
+    /// Adds the data for a version
+    pub fn add_version(&mut self, version: String, data: Option<&[u8]>) {
+        let counts = read_download_series(data);
+        let total = counts.iter().sum();
         self.versions.push(DownloadStatsForVersion {
             version_semver: version.parse().unwrap(),
             version,
@@ -116,3 +315,41 @@ impl DownloadStats {
         }
     }
 }
+
+/// The number of downloads of a crate version on a day, as in the crates.io API
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct VersionDownloads {
+    /// The identifier of the version
+    pub version: i64,
+    /// The version number
+    pub num: String,
+    /// The number of downloads on this day
+    pub downloads: u32,
+    /// The day
+    pub date: NaiveDate,
+}
+
+/// Additional downloads that are not attributed to a version, as in the crates.io API
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ExtraDownloads {
+    /// The day
+    pub date: NaiveDate,
+    /// The number of downloads on this day
+    pub downloads: u32,
+}
+
+/// The metadata for the daily downloads of a crate
+#[derive(Debug, Default, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CrateDownloadsMeta {
+    /// Additional downloads that are not attributed to a version
+    pub extra_downloads: Vec<ExtraDownloads>,
+}
+
+/// The daily downloads of each version of a crate over the last days, compatible with the crates.io API
+#[derive(Debug, Default, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CrateDownloads {
+    /// The downloads for each version and day, only for days with downloads
+    pub version_downloads: Vec<VersionDownloads>,
+    /// The associated metadata
+    pub meta: CrateDownloadsMeta,
+}
@@ -0,0 +1,192 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Data types for the export of vendored crates, for air-gapped builds
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::Read;
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde_derive::{Deserialize, Serialize};
+use tar::{Archive, Builder, EntryType, Header};
+use utoipa::ToSchema;
+
+use super::osv::SimpleAdvisory;
+use crate::utils::apierror::ApiError;
+use crate::utils::hashes::sha256;
+
+/// The source of crates.io in lock files, using the git protocol
+pub const CRATES_IO_SOURCE_GIT: &str = "registry+https://github.com/rust-lang/crates.io-index";
+/// The source of crates.io in lock files, using the sparse protocol
+pub const CRATES_IO_SOURCE_SPARSE: &str = "sparse+https://index.crates.io/";
+
+/// A package referenced in a `Cargo.lock` file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct LockedPackage {
+    /// The name of the package
+    pub name: String,
+    /// The locked version
+    pub version: String,
+    /// The source of the package, `None` for local packages
+    pub source: Option<String>,
+    /// The checksum of the `.crate` file, if known
+    pub checksum: Option<String>,
+}
+
+impl LockedPackage {
+    /// Parses the packages in a `Cargo.lock` file
+    ///
+    /// Only the `[[package]]` tables are considered, and only their `name`, `version`, `source` and `checksum` keys.
+    #[must_use]
+    pub fn parse_lock_file(content: &str) -> Vec<LockedPackage> {
+        let mut packages = Vec::new();
+        let mut current: Option<LockedPackage> = None;
+        for line in content.lines().map(str::trim) {
+            if line.starts_with('[') {
+                packages.extend(current.take());
+                if line == "[[package]]" {
+                    current = Some(LockedPackage {
+                        name: String::new(),
+                        version: String::new(),
+                        source: None,
+                        checksum: None,
+                    });
+                }
+                continue;
+            }
+            let (Some(package), Some((key, value))) = (current.as_mut(), line.split_once('=')) else {
+                continue;
+            };
+            let Some(value) = value.trim().strip_prefix('"').and_then(|v| v.strip_suffix('"')) else {
+                // arrays, such as dependencies
+                continue;
+            };
+            match key.trim() {
+                "name" => package.name = value.to_string(),
+                "version" => package.version = value.to_string(),
+                "source" => package.source = Some(value.to_string()),
+                "checksum" => package.checksum = Some(value.to_string()),
+                _ => {}
+            }
+        }
+        packages.extend(current);
+        packages.retain(|package| !package.name.is_empty() && !package.version.is_empty());
+        packages
+    }
+}
+
+/// The audit of a package locked in a `Cargo.lock` file
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct LockFileAuditEntry {
+    /// The name of the package
+    pub name: String,
+    /// The locked version
+    pub version: String,
+    /// The source of the package
+    pub source: Option<String>,
+    /// Whether the locked version is yanked in this registry
+    pub yanked: bool,
+    /// Whether the crate is deprecated in this registry
+    pub deprecated: bool,
+    /// Whether the package is expected from this registry, but the locked version is not in it
+    pub missing: bool,
+    /// The known advisories against the locked version
+    pub advisories: Vec<SimpleAdvisory>,
+}
+
+impl LockFileAuditEntry {
+    /// Gets whether an issue was found for this package
+    #[must_use]
+    pub fn has_issues(&self) -> bool {
+        self.yanked || self.deprecated || self.missing || !self.advisories.is_empty()
+    }
+}
+
+/// The audit of a `Cargo.lock` file against the registry
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct LockFileAudit {
+    /// Whether no issue was found for any of the packages
+    pub passed: bool,
+    /// The number of packages that were checked
+    pub checked: usize,
+    /// The packages with at least an issue
+    pub entries: Vec<LockFileAuditEntry>,
+}
+
+/// A crate to put in a vendor archive
+#[derive(Debug, Clone)]
+pub struct VendoredCrate {
+    /// The name of the crate
+    pub name: String,
+    /// The version of the crate
+    pub version: String,
+    /// The content of the `.crate` file
+    pub content: Vec<u8>,
+}
+
+/// A package of a lock file that could not be vendored
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct VendorMissing {
+    /// The name of the package
+    pub name: String,
+    /// The locked version
+    pub version: String,
+    /// The reason why it could not be vendored
+    pub reason: String,
+}
+
+/// Builds a vendor archive (`.tar.gz`) in the layout produced by `cargo vendor`
+///
+/// Each crate is extracted into `vendor/<name>-<version>/` along with the `.cargo-checksum.json` file expected by cargo.
+/// The packages that could not be vendored are listed in a `vendor-missing.txt` file at the root of the archive.
+pub fn build_vendor_archive(crates: &[VendoredCrate], missing: &[VendorMissing]) -> Result<Vec<u8>, ApiError> {
+    let mut builder = Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+    for vendored in crates {
+        let prefix = format!("{}-{}", vendored.name, vendored.version);
+        let mut files = BTreeMap::new();
+        let mut archive = Archive::new(GzDecoder::new(vendored.content.as_slice()));
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            if entry.header().entry_type() != EntryType::Regular {
+                continue;
+            }
+            let path = entry.path()?.into_owned();
+            let Ok(relative) = path.strip_prefix(&prefix) else {
+                continue;
+            };
+            let relative = relative.to_string_lossy().replace('\\', "/");
+            let mut data = Vec::new();
+            entry.read_to_end(&mut data)?;
+            let mut header = entry.header().clone();
+            builder.append_data(&mut header, format!("vendor/{prefix}/{relative}"), data.as_slice())?;
+            files.insert(relative, sha256(&data));
+        }
+        let checksum = serde_json::to_vec(&serde_json::json!({
+            "files": files,
+            "package": sha256(&vendored.content),
+        }))?;
+        append_file(&mut builder, &format!("vendor/{prefix}/.cargo-checksum.json"), &checksum)?;
+    }
+    if !missing.is_empty() {
+        let mut report = String::new();
+        for item in missing {
+            writeln!(report, "{} {}: {}", item.name, item.version, item.reason).unwrap();
+        }
+        append_file(&mut builder, "vendor-missing.txt", report.as_bytes())?;
+    }
+    Ok(builder.into_inner()?.finish()?)
+}
+
+/// Appends a generated file to an archive
+fn append_file(builder: &mut Builder<GzEncoder<Vec<u8>>>, path: &str, data: &[u8]) -> Result<(), ApiError> {
+    let mut header = Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    header.set_entry_type(EntryType::Regular);
+    builder.append_data(&mut header, path, data)?;
+    Ok(())
+}
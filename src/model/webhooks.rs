@@ -0,0 +1,351 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Data types for the outgoing webhooks
+
+use std::fmt::{Display, Formatter};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+
+use chrono::{Local, NaiveDateTime};
+use serde_derive::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::utils::apierror::{error_invalid_request, specialize, ApiError};
+
+/// The default number of deliveries when getting the delivery log of a webhook
+pub const WEBHOOK_DELIVERIES_DEFAULT_LIMIT: i64 = 50;
+
+/// The default number of notifications when getting the inbox of a user
+pub const USER_NOTIFICATIONS_DEFAULT_LIMIT: i64 = 50;
+
+/// The maximum number of attempts to deliver a payload
+pub const WEBHOOK_MAX_ATTEMPTS: i64 = 5;
+
+/// Gets whether an address must not be the target of a webhook
+///
+/// This excludes the loopback, private, link-local, shared and otherwise non-global addresses,
+/// so that webhooks cannot be used to reach the services next to the registry.
+#[must_use]
+pub fn is_webhook_address_forbidden(address: IpAddr) -> bool {
+    match address {
+        IpAddr::V4(address) => is_webhook_v4_forbidden(address),
+        IpAddr::V6(address) => match address.to_ipv4_mapped() {
+            Some(address) => is_webhook_v4_forbidden(address),
+            None => is_webhook_v6_forbidden(address),
+        },
+    }
+}
+
+/// Gets whether an IPv4 address must not be the target of a webhook
+fn is_webhook_v4_forbidden(address: Ipv4Addr) -> bool {
+    let [first, second, ..] = address.octets();
+    address.is_loopback()
+        || address.is_private()
+        || address.is_link_local()
+        || address.is_unspecified()
+        || address.is_broadcast()
+        || address.is_multicast()
+        || address.is_documentation()
+        || first == 0
+        || (first == 100 && (second & 0xc0) == 64) // shared address space, 100.64.0.0/10
+}
+
+/// Gets whether an IPv6 address must not be the target of a webhook
+fn is_webhook_v6_forbidden(address: Ipv6Addr) -> bool {
+    let first = address.segments()[0];
+    address.is_loopback()
+        || address.is_unspecified()
+        || address.is_multicast()
+        || (first & 0xfe00) == 0xfc00 // unique local, fc00::/7
+        || (first & 0xffc0) == 0xfe80 // link-local, fe80::/10
+}
+
+/// An event in the registry that can be notified to webhooks and to the users watching a crate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+pub enum WebhookEvent {
+    /// A crate version was published
+    #[serde(rename = "publish")]
+    Publish,
+    /// A crate version was yanked
+    #[serde(rename = "yank")]
+    Yank,
+    /// A crate version was unyanked
+    #[serde(rename = "unyank")]
+    Unyank,
+    /// The owners of a crate changed
+    #[serde(rename = "ownersChange")]
+    OwnersChange,
+    /// The documentation for a crate version was generated
+    #[serde(rename = "docsBuilt")]
+    DocsBuilt,
+    /// The generation of the documentation for a crate version failed
+    #[serde(rename = "docsFailed")]
+    DocsFailed,
+    /// A crate was marked as deprecated
+    #[serde(rename = "deprecation")]
+    Deprecation,
+    /// Vulnerable dependencies were found for a crate version
+    #[serde(rename = "advisory")]
+    Advisory,
+}
+
+impl WebhookEvent {
+    /// Gets the name of the event
+    #[must_use]
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Publish => "publish",
+            Self::Yank => "yank",
+            Self::Unyank => "unyank",
+            Self::OwnersChange => "ownersChange",
+            Self::DocsBuilt => "docsBuilt",
+            Self::DocsFailed => "docsFailed",
+            Self::Deprecation => "deprecation",
+            Self::Advisory => "advisory",
+        }
+    }
+
+    /// Parses a comma-separated list of events, as stored in the database
+    #[must_use]
+    pub fn parse_list(input: &str) -> Vec<Self> {
+        input.split(',').filter_map(|name| name.trim().parse().ok()).collect()
+    }
+
+    /// Serializes a list of events for storage in the database
+    #[must_use]
+    pub fn to_list(events: &[Self]) -> String {
+        events.iter().map(|event| event.as_str()).collect::<Vec<_>>().join(",")
+    }
+}
+
+impl FromStr for WebhookEvent {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "publish" => Ok(Self::Publish),
+            "yank" => Ok(Self::Yank),
+            "unyank" => Ok(Self::Unyank),
+            "ownersChange" => Ok(Self::OwnersChange),
+            "docsBuilt" => Ok(Self::DocsBuilt),
+            "docsFailed" => Ok(Self::DocsFailed),
+            "deprecation" => Ok(Self::Deprecation),
+            "advisory" => Ok(Self::Advisory),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Display for WebhookEvent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A webhook registered to receive events
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Webhook {
+    /// The unique identifier
+    pub id: i64,
+    /// The crate this webhook is restricted to, or `None` for all the crates in the registry
+    pub package: Option<String>,
+    /// The URL to post the payloads to
+    pub url: String,
+    /// The events to be notified
+    pub events: Vec<WebhookEvent>,
+    /// The uid of the user that created the webhook
+    #[serde(rename = "createdBy")]
+    pub created_by: i64,
+    /// The timestamp of the creation
+    #[serde(rename = "createdOn")]
+    pub created_on: NaiveDateTime,
+}
+
+/// A webhook with its secret, only returned on creation
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct WebhookWithSecret {
+    /// The webhook
+    #[serde(flatten)]
+    pub webhook: Webhook,
+    /// The secret used to sign the payloads
+    pub secret: String,
+}
+
+/// The query to create a webhook
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct WebhookCreateQuery {
+    /// The crate to restrict the webhook to, or `None` for all the crates in the registry (administrators only)
+    #[serde(default)]
+    pub package: Option<String>,
+    /// The URL to post the payloads to
+    pub url: String,
+    /// The events to be notified
+    pub events: Vec<WebhookEvent>,
+}
+
+impl WebhookCreateQuery {
+    /// Validates the query
+    pub fn validate(&self) -> Result<(), ApiError> {
+        let url = reqwest::Url::parse(&self.url)
+            .map_err(|e| specialize(error_invalid_request(), format!("invalid URL for the webhook: {e}")))?;
+        if url.scheme() != "https" && url.scheme() != "http" {
+            return Err(specialize(
+                error_invalid_request(),
+                String::from("the URL of a webhook must use http or https"),
+            ));
+        }
+        let is_forbidden = url.host_str().is_none_or(|host| {
+            host.eq_ignore_ascii_case("localhost")
+                || host
+                    .trim_start_matches('[')
+                    .trim_end_matches(']')
+                    .parse::<IpAddr>()
+                    .is_ok_and(is_webhook_address_forbidden)
+        });
+        if is_forbidden {
+            return Err(specialize(
+                error_invalid_request(),
+                String::from("the URL of a webhook must target a public host"),
+            ));
+        }
+        if self.events.is_empty() {
+            return Err(specialize(
+                error_invalid_request(),
+                String::from("at least one event is required"),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// The payload posted to a webhook
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct WebhookPayload {
+    /// The event
+    pub event: WebhookEvent,
+    /// The timestamp of the event
+    pub timestamp: NaiveDateTime,
+    /// The crate concerned by the event
+    pub package: String,
+    /// The version concerned by the event, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// The target of the documentation, for documentation events
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    /// The current owners (logins) of the crate, for owner changes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owners: Option<Vec<String>>,
+}
+
+impl WebhookPayload {
+    /// Creates the payload for an event on a crate version
+    #[must_use]
+    pub fn new(event: WebhookEvent, package: &str, version: Option<&str>) -> Self {
+        Self {
+            event,
+            timestamp: Local::now().naive_local(),
+            package: package.to_string(),
+            version: version.map(str::to_string),
+            target: None,
+            owners: None,
+        }
+    }
+
+    /// Gets a human-readable description of the event
+    #[must_use]
+    pub fn describe(&self) -> String {
+        let subject = match &self.version {
+            Some(version) => format!("{} {version}", self.package),
+            None => self.package.clone(),
+        };
+        match self.event {
+            WebhookEvent::Publish => format!("{subject} was published"),
+            WebhookEvent::Yank => format!("{subject} was yanked"),
+            WebhookEvent::Unyank => format!("{subject} was unyanked"),
+            WebhookEvent::OwnersChange => format!("the owners of {subject} changed"),
+            WebhookEvent::DocsBuilt => format!("the documentation for {subject} was generated"),
+            WebhookEvent::DocsFailed => format!("the generation of the documentation for {subject} failed"),
+            WebhookEvent::Deprecation => format!("{subject} was deprecated"),
+            WebhookEvent::Advisory => format!("vulnerable dependencies were found for {subject}"),
+        }
+    }
+
+    /// Gets whether this event is notified to the users watching the crate
+    #[must_use]
+    pub fn is_for_watchers(&self) -> bool {
+        matches!(
+            self.event,
+            WebhookEvent::Publish | WebhookEvent::Yank | WebhookEvent::Deprecation | WebhookEvent::Advisory
+        )
+    }
+}
+
+/// A delivery to be attempted, with the webhook it targets
+#[derive(Debug, Clone)]
+pub struct WebhookDeliveryJob {
+    /// The identifier of the delivery
+    pub delivery_id: i64,
+    /// The number of attempts so far
+    pub attempts: i64,
+    /// The name of the event
+    pub event: String,
+    /// The serialized payload
+    pub payload: String,
+    /// The URL to post the payload to
+    pub url: String,
+    /// The secret used to sign the payload
+    pub secret: String,
+}
+
+/// A notification in the inbox of a user, about a watched crate
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct UserNotification {
+    /// The unique identifier
+    pub id: i64,
+    /// The crate concerned by the event
+    pub package: String,
+    /// The version concerned by the event, if any
+    pub version: Option<String>,
+    /// The event
+    pub event: String,
+    /// A human-readable description of the event
+    pub details: String,
+    /// The timestamp of the event
+    #[serde(rename = "createdOn")]
+    pub created_on: NaiveDateTime,
+    /// Whether the notification was read
+    #[serde(rename = "isRead")]
+    pub is_read: bool,
+}
+
+/// A delivery of a payload to a webhook
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct WebhookDelivery {
+    /// The unique identifier
+    pub id: i64,
+    /// The identifier of the webhook
+    pub webhook: i64,
+    /// The event
+    pub event: String,
+    /// The serialized payload
+    pub payload: String,
+    /// The number of attempts so far
+    pub attempts: i64,
+    /// Whether the payload was successfully delivered
+    #[serde(rename = "isDelivered")]
+    pub is_delivered: bool,
+    /// The HTTP status code of the last attempt, if a response was received
+    #[serde(rename = "statusCode")]
+    pub status_code: Option<i64>,
+    /// The error for the last attempt, if any
+    pub error: Option<String>,
+    /// The timestamp of the creation
+    #[serde(rename = "createdOn")]
+    pub created_on: NaiveDateTime,
+    /// The timestamp of the last attempt
+    #[serde(rename = "lastAttempt")]
+    pub last_attempt: Option<NaiveDateTime>,
+}
@@ -14,14 +14,15 @@ use log::{error, info};
 use serde_derive::{Deserialize, Serialize};
 use tokio::sync::mpsc::{Receiver, Sender};
 use tokio::sync::Mutex;
+use utoipa::ToSchema;
 
-use super::docs::{DocGenJob, DocGenJobUpdate};
+use super::docs::{DocGenJob, DocGenJobLogLine, DocGenJobUpdate};
 use crate::model::config::{Configuration, NodeRole};
 use crate::utils::apierror::ApiError;
 use crate::utils::token::generate_token;
 
 /// The descriptor of a worker and its capabilities
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct WorkerDescriptor {
     /// The unique identifier for the worker
     pub identifier: String,
@@ -29,9 +30,11 @@ pub struct WorkerDescriptor {
     pub name: String,
     /// The version of the locally installed toolchain
     #[serde(rename = "toolchainVersionStable")]
+    #[schema(value_type = String)]
     pub toolchain_version_stable: semver::Version,
     /// The version of the locally installed toolchain
     #[serde(rename = "toolchainVersionNightly")]
+    #[schema(value_type = String)]
     pub toolchain_version_nightly: semver::Version,
     /// The host target of the locally installed toolchain
     #[serde(rename = "toolchainHost")]
@@ -125,7 +128,7 @@ impl WorkerState {
 }
 
 /// The state of a worker
-#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
 pub enum WorkerPublicState {
     /// Available for jobs
     Available,
@@ -133,7 +136,7 @@ pub enum WorkerPublicState {
     InUse(JobIdentifier),
 }
 
-impl<'a> From<&'a WorkerState> for WorkerPublicState {
+impl From<&WorkerState> for WorkerPublicState {
     fn from(value: &WorkerState) -> Self {
         match value {
             WorkerState::Available(_) => Self::Available,
@@ -171,7 +174,7 @@ impl WorkerData {
 }
 
 /// The data for a worker
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct WorkerPublicData {
     /// The worker's description
     pub descriptor: WorkerDescriptor,
@@ -535,33 +538,36 @@ impl WorkersManager {
     }
 
     /// Send an event to listeners
+    ///
+    /// The lock on the listeners is not held while sending, so that a slow listener does not block the registration of new ones.
     async fn do_send_event(&self, event: WorkerEvent) -> Result<(), ApiError> {
-        let mut listeners = self.listeners.lock().await;
-        let mut index = if listeners.is_empty() {
-            None
-        } else {
-            Some(listeners.len() - 1)
-        };
-        while let Some(i) = index {
-            if listeners[i].send(event.clone()).await.is_err() {
-                // remove
-                listeners.swap_remove(i);
+        let listeners = self.listeners.lock().await.clone();
+        let mut closed = Vec::new();
+        for listener in listeners {
+            if listener.send(event.clone()).await.is_err() {
+                closed.push(listener);
             }
-            index = if i == 0 { None } else { Some(i - 1) };
+        }
+        if !closed.is_empty() {
+            // remove
+            self.listeners
+                .lock()
+                .await
+                .retain(|listener| !closed.iter().any(|closed| closed.same_channel(listener)));
         }
         Ok(())
     }
 }
 
 /// The identifier of a job for a worker
-#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
 pub enum JobIdentifier {
     /// A documentation generation job
     DocGen(i64),
 }
 
 /// An specification of an job to be executed
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub enum JobSpecification {
     /// A documentation generation job
     DocGen(DocGenJob),
@@ -578,14 +584,16 @@ impl JobSpecification {
 }
 
 /// An update about the execution of a job, for the client
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub enum JobUpdate {
     /// An update about a documentation generation job
     DocGen(DocGenJobUpdate),
+    /// A line of output for a documentation generation job
+    DocGenLog(DocGenJobLogLine),
 }
 
 /// An event about workers
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub enum WorkerEvent {
     /// A worker just connected
     WorkerConnected(Box<WorkerPublicData>),
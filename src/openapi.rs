@@ -0,0 +1,228 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! `OpenAPI` specification of the web API
+
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::openapi::{RefOr, ResponseBuilder};
+use utoipa::{Modify, OpenApi};
+
+use crate::model::search::SearchSort;
+use crate::routes;
+use crate::utils::apierror::ApiError;
+
+/// The path to the Swagger UI
+pub const DOCS_PATH: &str = "/api/docs";
+/// The path to the `OpenAPI` document
+pub const DOCS_SPEC_PATH: &str = "/api/docs/openapi.json";
+
+/// The `OpenAPI` specification of the web API
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Cratery", description = "The web API of Cratery, a private cargo registry"),
+    paths(
+        routes::get_version,
+        routes::get_liveness,
+        routes::get_readiness,
+        routes::api_v1_get_registry_information,
+        routes::api_v1_get_maintenance,
+        routes::api_v1_get_current_announcement,
+        routes::api_v1_get_current_user,
+        routes::api_v1_get_current_user_quota,
+        routes::api_v1_get_current_user_team_quotas,
+        routes::api_v1_update_current_user,
+        routes::api_v1_get_current_user_preferences,
+        routes::api_v1_set_current_user_preferences,
+        routes::api_v1_get_watched_crates,
+        routes::api_v1_get_starred_crates,
+        routes::api_v1_get_user_notifications,
+        routes::api_v1_mark_user_notifications_read,
+        routes::api_v1_get_user_tokens,
+        routes::api_v2_get_user_tokens,
+        routes::api_v1_create_user_token,
+        routes::api_v1_revoke_user_token,
+        routes::api_v1_login_with_oauth_code,
+        routes::api_v1_logout,
+        routes::api_v1_export_vendor,
+        routes::api_v1_audit_lock_file,
+        routes::api_v1_download_proxied_crate,
+        routes::api_v1_graphql,
+        routes::api_v1_get_feed_versions,
+        routes::api_v1_get_feed_owner,
+        routes::api_v1_get_webhooks,
+        routes::api_v1_create_webhook,
+        routes::api_v1_delete_webhook,
+        routes::api_v1_get_webhook_deliveries,
+        routes::api_v1_redeliver_webhook_delivery,
+        routes::api_v1_get_doc_gen_job_updates_for,
+        routes::api_v1_get_users,
+        routes::api_v2_get_users,
+        routes::api_v1_update_user,
+        routes::api_v1_delete_user,
+        routes::api_v1_get_user_quota,
+        routes::api_v1_set_user_quota,
+        routes::api_v1_get_team_quota,
+        routes::api_v1_set_team_quota,
+        routes::api_v1_deactivate_user,
+        routes::api_v1_reactivate_user,
+        routes::api_v1_impersonate_user,
+        routes::api_v1_end_impersonation,
+        routes::api_v1_get_audit_log,
+        routes::api_v2_get_audit_log,
+        routes::api_v1_set_maintenance,
+        routes::api_v1_get_announcement,
+        routes::api_v1_set_announcement,
+        routes::api_v1_clear_announcement,
+        routes::api_v1_reload_configuration,
+        routes::api_v1_export_registry,
+        routes::api_v1_get_registry_stats,
+        routes::api_v1_get_storage_usage,
+        routes::api_v1_get_publish_activity,
+        routes::api_v1_get_traffic_report,
+        routes::api_v1_get_advisories_report,
+        routes::api_v1_get_license_inventory,
+        routes::api_v1_get_quarantined_versions,
+        routes::api_v1_release_quarantined_version,
+        routes::api_v1_resync_index_remote,
+        routes::api_v1_bulk_yank,
+        routes::api_v1_get_provisioning_rules,
+        routes::api_v1_set_provisioning_rules,
+        routes::api_v1_get_global_tokens,
+        routes::api_v2_get_global_tokens,
+        routes::api_v1_create_global_token,
+        routes::api_v1_revoke_global_token,
+        routes::api_v1_get_doc_gen_jobs,
+        routes::api_v1_get_doc_gen_job_updates,
+        routes::api_v1_get_doc_gen_job_log,
+        routes::api_v1_get_search_reindex_progress,
+        routes::api_v1_launch_search_reindex,
+        routes::api_v1_get_backup_status,
+        routes::api_v1_launch_backup,
+        routes::api_v1_get_scheduled_jobs,
+        routes::api_v1_run_scheduled_job,
+        routes::api_v1_get_workers,
+        routes::api_v1_get_workers_updates,
+        routes::api_v1_worker_connect,
+        routes::api_v1_cargo_search,
+        routes::api_v1_suggest_crates,
+        routes::api_v1_get_crates_stats,
+        routes::api_v1_get_crates_undocumented,
+        routes::api_v2_get_crates_undocumented,
+        routes::api_v1_get_crates_outdated_heads,
+        routes::api_v2_get_crates_outdated_heads,
+        routes::api_v1_get_crates_metadata,
+        routes::api_v1_cargo_publish_crate_version,
+        routes::api_v1_get_crate_info,
+        routes::api_v1_get_crate_index_file,
+        routes::api_v1_get_crate_last_readme,
+        routes::api_v1_get_crate_last_readme_html,
+        routes::api_v1_get_crate_feed,
+        routes::api_v1_get_crate_versions,
+        routes::api_v1_get_crate_version,
+        routes::api_v1_watch_crate,
+        routes::api_v1_unwatch_crate,
+        routes::api_v1_star_crate,
+        routes::api_v1_unstar_crate,
+        routes::api_v1_get_crate_readme,
+        routes::api_v1_get_crate_readme_html,
+        routes::api_v1_get_crate_files,
+        routes::api_v1_get_crate_file,
+        routes::api_v1_get_crate_diff,
+        routes::api_v1_get_crate_binstall,
+        routes::api_v1_upload_crate_binary,
+        routes::api_v1_download_crate_binary,
+        routes::api_v1_download_crate,
+        routes::api_v1_cargo_yank,
+        routes::api_v1_cargo_unyank,
+        routes::api_v1_regen_crate_version_doc,
+        routes::api_v1_check_crate_version,
+        routes::api_v1_get_crate_version_sbom,
+        routes::api_v1_get_crate_version_checksums,
+        routes::api_v1_set_crate_version_provenance,
+        routes::api_v1_get_crate_docs_coverage,
+        routes::api_v1_get_crate_dl_stats,
+        routes::api_v1_get_crate_downloads,
+        routes::api_v1_get_crate_reverse_dependencies,
+        routes::api_v1_cargo_get_crate_owners,
+        routes::api_v1_cargo_add_crate_owners,
+        routes::api_v1_cargo_remove_crate_owners,
+        routes::api_v1_get_crate_targets,
+        routes::api_v1_set_crate_targets,
+        routes::api_v1_get_crate_required_capabilities,
+        routes::api_v1_set_crate_required_capabilities,
+        routes::api_v1_set_crate_deprecation,
+        routes::api_v1_set_crate_frozen,
+        routes::api_v1_get_crate_visibility,
+        routes::api_v1_set_crate_visibility,
+    ),
+    // schemas that are only referenced by query parameters are not collected automatically
+    components(schemas(ApiError, SearchSort)),
+    modifiers(&Security, &ErrorResponses),
+    security(("basic" = [])),
+    tags(
+        (name = "registry", description = "Information about the registry"),
+        (name = "users", description = "The current user and its notifications"),
+        (name = "tokens", description = "Authentication tokens"),
+        (name = "crates", description = "Crates and their versions"),
+        (name = "feeds", description = "Atom feeds of the published versions"),
+        (name = "webhooks", description = "Outgoing webhooks"),
+        (name = "graphql", description = "GraphQL API over the registry data"),
+        (name = "jobs", description = "Background jobs"),
+        (name = "workers", description = "Worker nodes for the generation of documentation"),
+        (name = "admin", description = "Administration of the registry"),
+    )
+)]
+pub struct ApiDoc;
+
+/// Declares the authentication with tokens, given as HTTP basic authentication
+struct Security;
+
+impl Modify for Security {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "basic",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Basic)
+                    .description(Some("The login of the user and a token as password"))
+                    .build(),
+            ),
+        );
+    }
+}
+
+/// Adds the error response, common to all operations
+struct ErrorResponses;
+
+impl Modify for ErrorResponses {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let response = ResponseBuilder::new()
+            .description("The error that occurred")
+            .content(
+                "application/json",
+                utoipa::openapi::ContentBuilder::new()
+                    .schema(Some(RefOr::Ref(utoipa::openapi::Ref::from_schema_name("ApiError"))))
+                    .build(),
+            )
+            .build();
+        for item in openapi.paths.paths.values_mut() {
+            for operation in [
+                &mut item.get,
+                &mut item.put,
+                &mut item.post,
+                &mut item.delete,
+                &mut item.patch,
+            ]
+            .into_iter()
+            .flatten()
+            {
+                operation
+                    .responses
+                    .responses
+                    .insert(String::from("default"), RefOr::T(response.clone()));
+            }
+        }
+    }
+}
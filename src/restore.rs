@@ -0,0 +1,417 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Restoration of the registry from a backup of the database or from an export archive, before starting it
+
+use std::collections::{HashMap, HashSet};
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use chrono::Local;
+use flate2::read::GzDecoder;
+use log::{info, warn};
+use tar::Archive;
+
+use crate::model::backup::{apply_incremental_backup, backup_chain, latest_backup, open_backup};
+use crate::model::cargo::{CrateMetadata, CrateUploadData, IndexCrateMetadata};
+use crate::model::config::Configuration;
+use crate::model::export::{
+    ExportManifest, ExportedCrate, EXPORT_CRATES_FOLDER, EXPORT_DATABASE_PATH, EXPORT_FORMAT_VERSION, EXPORT_INDEX_FOLDER,
+    EXPORT_MANIFEST_PATH,
+};
+use crate::model::CrateVersion;
+use crate::services::database::{db_transaction_read, db_transaction_write};
+use crate::services::storage::Storage;
+use crate::services::{ServiceProvider, StandardServiceProvider};
+use crate::utils::apierror::{error_backend_failure, error_invalid_request, error_not_found, specialize, ApiError};
+use crate::utils::db::RwSqlitePool;
+use crate::utils::execute_git;
+use crate::utils::hashes::sha256;
+
+/// The format of the suffix for the previous database and index, kept aside
+const PREVIOUS_SUFFIX_FORMAT: &str = "%Y%m%dT%H%M%S";
+
+/// Restores the database from a backup in the storage, the most recent one when no name is given
+///
+/// The backup is verified and migrated before replacing the current database, which is kept aside.
+/// The index can then be rebuilt from the restored database and the crates in the storage.
+pub async fn main_restore_backup(name: Option<String>, rebuild_index: bool) -> Result<(), ApiError> {
+    let configuration = StandardServiceProvider::get_configuration().await?;
+    let service_storage = StandardServiceProvider::get_storage(&configuration);
+    restore_backup(&configuration, service_storage, name, rebuild_index).await?;
+    Ok(())
+}
+
+/// Restores the database from a backup in a storage, the most recent one when no name is given
+///
+/// Returns the name of the restored backup.
+pub(crate) async fn restore_backup(
+    configuration: &Configuration,
+    service_storage: Arc<dyn Storage + Send + Sync>,
+    name: Option<String>,
+    rebuild_index: bool,
+) -> Result<String, ApiError> {
+    let existing = service_storage.list_backups().await?;
+    let name = match name {
+        Some(name) => name,
+        None => latest_backup(&existing)
+            .ok_or_else(|| specialize(error_not_found(), String::from("no backup found in the storage")))?,
+    };
+    let chain = backup_chain(&existing, &name).ok_or_else(|| {
+        specialize(
+            error_not_found(),
+            format!("backup {name} or the full backup it is based on not found in the storage"),
+        )
+    })?;
+    let key = configuration.backup.get_encryption_key()?;
+    let mut content = Vec::new();
+    let mut previous: Option<String> = None;
+    for backup in chain {
+        info!("restore: downloading {backup}");
+        let data = service_storage.download_backup(&backup).await?;
+        let key = key.clone();
+        content = tokio::task::spawn_blocking({
+            let (backup, previous) = (backup.clone(), previous.clone());
+            move || {
+                let data = open_backup(&backup, &data, key.as_deref())?;
+                match previous {
+                    Some(previous) => {
+                        apply_incremental_backup(&mut content, &previous, &data)?;
+                        Ok::<_, ApiError>(content)
+                    }
+                    None => Ok(data),
+                }
+            }
+        })
+        .await??;
+        previous = Some(backup);
+    }
+
+    let db_filename = configuration.get_database_filename();
+    let staging = format!("{db_filename}.restore");
+    tokio::fs::write(&staging, content).await?;
+    if let Err(e) = verify_database(&staging).await {
+        let _ = tokio::fs::remove_file(&staging).await;
+        return Err(e);
+    }
+
+    info!("restore: restored {name}");
+    if let Some(previous) = replace_database(&db_filename, &staging).await? {
+        info!("restore: the previous database is kept as {previous}");
+    }
+
+    if rebuild_index {
+        rebuild_index_from_database(configuration, service_storage).await?;
+    }
+    Ok(name)
+}
+
+/// Replaces the current database with a restored one, keeping the current one aside with its journal
+///
+/// Returns the name of the previous database, if any.
+async fn replace_database(db_filename: &str, restored: &str) -> Result<Option<String>, ApiError> {
+    let previous = format!("{db_filename}.{}", Local::now().format(PREVIOUS_SUFFIX_FORMAT));
+    let exists = Path::new(db_filename).exists();
+    for suffix in ["", "-wal", "-shm"] {
+        let current = format!("{db_filename}{suffix}");
+        if Path::new(&current).exists() {
+            tokio::fs::rename(&current, format!("{previous}{suffix}")).await?;
+        }
+    }
+    tokio::fs::rename(restored, db_filename).await?;
+    Ok(exists.then_some(previous))
+}
+
+/// Verifies the consistency of a restored database and migrates it to the current schema
+async fn verify_database(filename: &str) -> Result<(), ApiError> {
+    let pool = RwSqlitePool::new(&format!("sqlite://{filename}"))?;
+    let result = verify_and_migrate(&pool).await;
+    // checkpoint the journal before moving the file
+    pool.close().await;
+    result?;
+    let journal = format!("{filename}-wal");
+    if tokio::fs::metadata(&journal).await.is_ok_and(|metadata| metadata.len() > 0) {
+        return Err(specialize(
+            error_backend_failure(),
+            String::from("the journal of the restored database could not be checkpointed"),
+        ));
+    }
+    for suffix in ["-wal", "-shm"] {
+        let _ = tokio::fs::remove_file(format!("{filename}{suffix}")).await;
+    }
+    Ok(())
+}
+
+/// Checks the consistency of a database and migrates it to the current schema
+async fn verify_and_migrate(pool: &RwSqlitePool) -> Result<(), ApiError> {
+    // the first connection must be able to write to switch the journal mode
+    let problems = db_transaction_write(
+        pool,
+        "restore_check",
+        |database| async move { database.check_consistency().await },
+    )
+    .await?;
+    if !problems.is_empty() {
+        for problem in &problems {
+            warn!("restore: {problem}");
+        }
+        return Err(specialize(
+            error_backend_failure(),
+            format!("the backup is inconsistent, {} problems found", problems.len()),
+        ));
+    }
+    db_transaction_write(pool, "restore_migrate", |database| async move {
+        crate::migrations::migrate_to_last(database.transaction).await
+    })
+    .await?;
+    info!("restore: the database is consistent");
+    Ok(())
+}
+
+/// Rebuilds the index from the crates in the database and their data in the storage
+pub async fn rebuild_index_from_database(
+    configuration: &Configuration,
+    service_storage: Arc<dyn Storage + Send + Sync>,
+) -> Result<(), ApiError> {
+    let pool = RwSqlitePool::new(&configuration.get_database_url())?;
+    let versions = db_transaction_read(&pool, |database| async move { database.get_all_crate_versions().await }).await;
+    pool.close().await;
+    let versions = versions?;
+    info!("index: rebuilding the index with {} crate versions", versions.len());
+    let mut crates = Vec::<IndexCrateMetadata>::with_capacity(versions.len());
+    let mut missing = 0;
+    for CrateVersion { package, version } in &versions {
+        let Some(metadata) = service_storage.download_crate_metadata(package, version).await? else {
+            warn!("index: missing metadata for {package} {version} in the storage");
+            missing += 1;
+            continue;
+        };
+        let content = match service_storage.download_crate(package, version).await {
+            Ok(content) => content,
+            Err(e) => {
+                warn!("index: missing content for {package} {version} in the storage: {e}");
+                missing += 1;
+                continue;
+            }
+        };
+        crates.push(CrateUploadData { metadata, content }.build_index_data());
+    }
+    if missing > 0 {
+        return Err(specialize(
+            error_backend_failure(),
+            format!("{missing} crate versions are missing in the storage, the index was not rebuilt"),
+        ));
+    }
+    let service_index = StandardServiceProvider::get_index(configuration, versions.is_empty()).await?;
+    service_index.rebuild(&crates).await?;
+    info!("index: rebuilt the index");
+    Ok(())
+}
+
+/// Imports an export archive of another registry, replacing the database and the index
+///
+/// The `.crate` files included in the archive are uploaded to the storage,
+/// the others are expected to be already present in the storage.
+pub async fn main_import_archive(archive: &str) -> Result<(), ApiError> {
+    let configuration = StandardServiceProvider::get_configuration().await?;
+    let service_storage = StandardServiceProvider::get_storage(&configuration);
+    let staging = PathBuf::from(format!("{}/import", configuration.data_dir));
+    if staging.exists() {
+        tokio::fs::remove_dir_all(&staging).await?;
+    }
+    tokio::fs::create_dir_all(&staging).await?;
+    let result = import_archive(&configuration, archive, &staging, service_storage).await;
+    let _ = tokio::fs::remove_dir_all(&staging).await;
+    result
+}
+
+/// Imports an export archive, using a staging folder
+pub(crate) async fn import_archive(
+    configuration: &Configuration,
+    archive: &str,
+    staging: &Path,
+    service_storage: Arc<dyn Storage + Send + Sync>,
+) -> Result<(), ApiError> {
+    info!("import: reading {archive}");
+    let manifest = tokio::task::spawn_blocking({
+        let archive = archive.to_string();
+        let staging = staging.to_path_buf();
+        move || extract_import_archive(&archive, &staging)
+    })
+    .await??;
+    let staging_db = staging.join(EXPORT_DATABASE_PATH);
+    let staging_index = staging.join(EXPORT_INDEX_FOLDER);
+    if !staging_db.exists() || !staging_index.exists() {
+        return Err(specialize(
+            error_invalid_request(),
+            String::from("the archive does not contain the database and the index"),
+        ));
+    }
+    verify_database(&staging_db.to_string_lossy()).await?;
+
+    // upload the included crates and check that the others are present
+    let uploaded = if manifest.include_crates {
+        let runtime = tokio::runtime::Handle::current();
+        let archive = archive.to_string();
+        let service_storage = service_storage.clone();
+        let checksums = manifest
+            .crates
+            .iter()
+            .map(|exported| {
+                (
+                    (exported.package.clone(), exported.version.clone()),
+                    exported.checksum.clone(),
+                )
+            })
+            .collect::<HashMap<_, _>>();
+        tokio::task::spawn_blocking(move || upload_import_crates(&archive, &checksums, service_storage.as_ref(), &runtime))
+            .await??
+    } else {
+        HashSet::new()
+    };
+    let mut missing = 0;
+    for exported in &manifest.crates {
+        let key = (exported.package.clone(), exported.version.clone());
+        if !uploaded.contains(&key)
+            && service_storage
+                .download_crate_metadata(&exported.package, &exported.version)
+                .await?
+                .is_none()
+        {
+            warn!("import: {} {} is not in the storage", exported.package, exported.version);
+            missing += 1;
+        }
+    }
+    if missing > 0 {
+        return Err(specialize(
+            error_backend_failure(),
+            format!("{missing} crate versions are missing in the storage, export them with includeCrates=true"),
+        ));
+    }
+
+    if let Some(previous) = replace_database(&configuration.get_database_filename(), &staging_db.to_string_lossy()).await? {
+        info!("import: the previous database is kept as {previous}");
+    }
+    let location = &configuration.index.location;
+    if Path::new(location).exists() {
+        let previous = format!("{location}.{}", Local::now().format(PREVIOUS_SUFFIX_FORMAT));
+        tokio::fs::rename(location, &previous).await?;
+        info!("import: the previous index is kept as {previous}");
+    }
+    tokio::fs::rename(&staging_index, location).await?;
+    adapt_imported_index(configuration).await?;
+    info!(
+        "import: imported {} crate versions from {} ({} uploaded)",
+        manifest.crates.len(),
+        manifest.registry,
+        uploaded.len()
+    );
+    Ok(())
+}
+
+/// Adapts an imported index to this registry, for its public configuration, git remote and user
+async fn adapt_imported_index(configuration: &Configuration) -> Result<(), ApiError> {
+    let location = PathBuf::from(&configuration.index.location);
+    let _ = execute_git(&location, &["remote", "remove", "origin"]).await;
+    if let Some(remote_origin) = &configuration.index.remote_origin {
+        execute_git(&location, &["remote", "add", "origin", remote_origin]).await?;
+    }
+    execute_git(&location, &["config", "user.name", &configuration.index.user_name]).await?;
+    execute_git(&location, &["config", "user.email", &configuration.index.user_email]).await?;
+    let index_config = serde_json::to_vec(&configuration.index.public)?;
+    let file_name = location.join("config.json");
+    if tokio::fs::read(&file_name).await.ok().as_deref() != Some(index_config.as_slice()) {
+        tokio::fs::write(&file_name, index_config).await?;
+        execute_git(&location, &["add", "config.json"]).await?;
+        execute_git(
+            &location,
+            &["commit", "-m", "Update the configuration for the imported index"],
+        )
+        .await?;
+        execute_git(&location, &["update-server-info"]).await?;
+    }
+    Ok(())
+}
+
+/// Extracts the manifest, the database and the index from an export archive into a staging folder
+fn extract_import_archive(archive: &str, staging: &Path) -> Result<ExportManifest, ApiError> {
+    let mut manifest = None;
+    let mut reader = Archive::new(GzDecoder::new(BufReader::new(std::fs::File::open(archive)?)));
+    for entry in reader.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.to_string_lossy().into_owned();
+        if path == EXPORT_MANIFEST_PATH {
+            let mut data = Vec::new();
+            entry.read_to_end(&mut data)?;
+            let value: ExportManifest = serde_json::from_slice(&data)?;
+            if value.format != EXPORT_FORMAT_VERSION {
+                return Err(specialize(
+                    error_invalid_request(),
+                    format!("unsupported format {} for the archive", value.format),
+                ));
+            }
+            manifest = Some(value);
+        } else if path == EXPORT_DATABASE_PATH || Path::new(&path).starts_with(EXPORT_INDEX_FOLDER) {
+            entry.unpack_in(staging)?;
+        } else if Path::new(&path).starts_with(EXPORT_CRATES_FOLDER) {
+            // the crates come last
+            break;
+        }
+    }
+    manifest.ok_or_else(|| {
+        specialize(
+            error_invalid_request(),
+            String::from("the archive does not contain a manifest"),
+        )
+    })
+}
+
+/// Uploads the crates included in an export archive to the storage, to be called on a blocking thread
+///
+/// Each `.crate` file is verified against the checksum in the manifest before being stored.
+fn upload_import_crates(
+    archive: &str,
+    checksums: &HashMap<(String, String), Option<String>>,
+    service_storage: &(dyn Storage + Send + Sync),
+    runtime: &tokio::runtime::Handle,
+) -> Result<HashSet<(String, String)>, ApiError> {
+    let mut pending = HashMap::new();
+    let mut uploaded = HashSet::new();
+    let mut reader = Archive::new(GzDecoder::new(BufReader::new(std::fs::File::open(archive)?)));
+    for entry in reader.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.to_string_lossy().into_owned();
+        let Some((exported, is_metadata)) = ExportedCrate::parse_path(&path) else {
+            continue;
+        };
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data)?;
+        let key = (exported.package, exported.version);
+        if is_metadata {
+            pending.insert(key, serde_json::from_slice::<CrateMetadata>(&data)?);
+        } else if let Some(metadata) = pending.remove(&key) {
+            match checksums.get(&key) {
+                None => {
+                    return Err(specialize(
+                        error_invalid_request(),
+                        format!("{} {} is not in the manifest of the archive", key.0, key.1),
+                    ));
+                }
+                Some(None) => warn!("import: no checksum to verify {} {}", key.0, key.1),
+                Some(Some(checksum)) => {
+                    if &sha256(&data) != checksum {
+                        return Err(specialize(
+                            error_invalid_request(),
+                            format!("the content of {} {} does not match its checksum", key.0, key.1),
+                        ));
+                    }
+                }
+            }
+            runtime.block_on(service_storage.store_crate(&metadata, data))?;
+            uploaded.insert(key);
+        }
+    }
+    Ok(uploaded)
+}
@@ -15,14 +15,15 @@ use std::time::Duration;
 
 use axum::body::{Body, Bytes};
 use axum::extract::ws::{Message, WebSocket};
-use axum::extract::{FromRequest, Path, Query, State, WebSocketUpgrade};
+use axum::extract::{FromRequest, Path, Query, RawPathParams, State, WebSocketUpgrade};
 use axum::http::header::{HeaderName, SET_COOKIE};
-use axum::http::{header, HeaderValue, Request, StatusCode};
+use axum::http::{header, HeaderMap, HeaderValue, Request, StatusCode};
+use axum::middleware::Next;
 use axum::response::{IntoResponse, Response};
-use axum::{BoxError, Json};
+use axum::Json;
 use cookie::Key;
 use futures::future::select_all;
-use futures::{SinkExt, Stream, StreamExt};
+use futures::{SinkExt, StreamExt};
 use log::error;
 use serde::Deserialize;
 use tokio::fs::File;
@@ -30,28 +31,70 @@ use tokio::sync::mpsc::channel;
 use tokio::sync::Mutex;
 use tokio_stream::wrappers::ReceiverStream;
 use tokio_util::io::ReaderStream;
+use utoipa::IntoParams;
 
 use crate::application::Application;
-use crate::model::auth::{Authentication, RegistryUserToken, RegistryUserTokenWithSecret};
+use crate::graphql::RegistrySchema;
+use crate::model::announcements::Announcement;
+use crate::model::audit::{AuditLogEntry, AUDIT_LOG_DEFAULT_LIMIT};
+use crate::model::auth::{
+    Authentication, AuthenticationPrincipal, ImpersonationRequest, ImpersonationToken, ProvisioningRule, RegistryUserToken,
+    RegistryUserTokenWithSecret, TrafficUsage,
+};
+use crate::model::backup::BackupStatus;
+use crate::model::binstall::{BinstallInfo, CrateBinary};
 use crate::model::cargo::{
-    CrateUploadResult, OwnersChangeQuery, OwnersQueryResult, RegistryUser, SearchResults, YesNoMsgResult, YesNoResult,
+    CrateUploadResult, CratesIoVersionResult, CratesIoVersions, OwnersChangeQuery, OwnersQueryResult, RegistryUser,
+    SearchResults, YesNoMsgResult, YesNoResult,
+};
+use crate::model::config::{CacheHeadersConfig, MaintenanceConfig};
+use crate::model::deps::{AdvisoriesReport, DepsAnalysis};
+use crate::model::docs::{DocGenJob, DocGenJobSpec, DocsCoverage};
+use crate::model::feeds::FEED_CONTENT_TYPE;
+use crate::model::health::{HealthReport, LivenessReport};
+use crate::model::licenses::{LicenseInventory, LICENSES_CSV_CONTENT_TYPE};
+use crate::model::packages::{
+    BulkMetadataQuery, BulkMetadataResult, BulkYankQuery, BulkYankResult, CrateInfo, CrateInfoTarget, CrateVersionChecksums,
+    CrateVisibility, CrateVisibilityQuery, QuarantinedVersion, ReverseDependencies, VersionProvenance,
+    VersionProvenanceDeclaration,
 };
-use crate::model::deps::DepsAnalysis;
-use crate::model::docs::{DocGenJob, DocGenJobSpec};
-use crate::model::packages::{CrateInfo, CrateInfoTarget};
-use crate::model::stats::{DownloadStats, GlobalStats};
+use crate::model::pagination::{AuditLogSort, CrateListSort, Page, Pagination, TokenSort, UserSort};
+use crate::model::profile::{UserPreferences, UserProfileUpdate};
+use crate::model::quotas::{QuotaUsage, TeamQuota, UserQuota};
+use crate::model::sbom::Sbom;
+use crate::model::scheduler::ScheduledJobStatus;
+use crate::model::search::{SearchFilters, SearchPagination, SearchReindexProgress, SearchSort};
+use crate::model::sources::{CrateDiff, CrateFile};
+use crate::model::stats::{
+    CrateDownloads, DownloadStats, GlobalStats, PublishActivityReport, RegistryStats, StorageUsageReport, TrafficReport,
+};
+use crate::model::vendor::LockFileAudit;
+use crate::model::webhooks::{UserNotification, Webhook, WebhookCreateQuery, WebhookDelivery, WebhookWithSecret};
 use crate::model::worker::{JobSpecification, JobUpdate, WorkerDescriptor, WorkerPublicData, WorkerRegistrationData};
-use crate::model::{AppVersion, CrateVersion, RegistryInformation};
+use crate::model::{
+    AppVersion, ByteRange, ContentPart, CrateContent, CrateVersion, IndexResyncQuery, IndexResyncResult, RegistryInformation,
+};
 use crate::services::index::Index;
+use crate::services::proxy::{Proxy, CRATES_IO_NAME};
 use crate::utils::apierror::{
-    error_backend_failure, error_invalid_request, error_not_found, error_unauthorized, specialize, ApiError,
+    error_backend_failure, error_invalid_request, error_not_found, error_range_not_satisfiable, error_unauthorized, specialize,
+    ApiError,
 };
-use crate::utils::axum::auth::{AuthData, AxumStateForCookies};
+use crate::utils::axum::auth::{AuthData, AxumStateForCookies, Token};
 use crate::utils::axum::embedded::{EmbeddedResources, WebappResource};
+use crate::utils::axum::etag::compute_etag;
 use crate::utils::axum::extractors::Base64;
+use crate::utils::axum::ratelimit::AxumStateForRateLimits;
 use crate::utils::axum::sse::{Event, ServerSentEventStream};
-use crate::utils::axum::{response, response_error, ApiResult};
+use crate::utils::axum::{response, response_error, response_ok_http, ApiResult};
+use crate::utils::badges::{
+    format_count, render_badge, COLOR_BLUE, COLOR_GREEN, COLOR_GREY, COLOR_ORANGE, COLOR_RED, COLOR_YELLOW,
+};
+use crate::utils::context::with_request_context;
+use crate::utils::markdown::render_markdown;
 use crate::utils::token::generate_token;
+use crate::utils::FaillibleFuture;
+use crate::webapp::set_base_path;
 
 /// The state of this application for axum
 pub struct AxumState {
@@ -61,15 +104,20 @@ pub struct AxumState {
     pub cookie_key: Key,
     /// The static resources for the web app
     pub webapp_resources: EmbeddedResources,
+    /// The schema for the GraphQL API
+    pub graphql_schema: RegistrySchema,
 }
 
 impl AxumStateForCookies for AxumState {
     fn get_domain(&self) -> Cow<'static, str> {
-        Cow::Owned(self.application.configuration.web_domain.clone())
+        Cow::Owned(self.application.configuration.load().web_domain.clone())
     }
 
     fn get_id_cookie_name(&self) -> Cow<'static, str> {
-        Cow::Borrowed("cratery-user")
+        match &self.application.configuration.load().tenant {
+            Some(tenant) => Cow::Owned(format!("cratery-user-{tenant}")),
+            None => Cow::Borrowed("cratery-user"),
+        }
     }
 
     fn get_cookie_key(&self) -> &Key {
@@ -77,10 +125,22 @@ impl AxumStateForCookies for AxumState {
     }
 }
 
+impl AxumStateForRateLimits for AxumState {
+    fn get_token_principal<'a>(&'a self, token: &'a Token) -> FaillibleFuture<'a, String> {
+        Box::pin(async move {
+            Ok(match self.application.verify_token(token).await? {
+                AuthenticationPrincipal::User { uid, .. } => format!("user:{uid}"),
+                AuthenticationPrincipal::Service { token_id } => format!("service:{token_id}"),
+                AuthenticationPrincipal::SelfAuth => String::from("self"),
+            })
+        })
+    }
+}
+
 impl AxumState {
     /// Gets the resource in the web app for the specified path
     async fn get_webapp_resource(&self, path: &str) -> Option<WebappResource> {
-        if let Some(hot_reload_path) = self.application.configuration.web_hot_reload_path.as_ref() {
+        if let Some(hot_reload_path) = self.application.configuration.load().web_hot_reload_path.as_ref() {
             let mut final_path = PathBuf::from(hot_reload_path);
             for element in path.split('/') {
                 final_path.push(element);
@@ -96,7 +156,45 @@ impl AxumState {
             let resource = self.webapp_resources.get(path).cloned()?;
             Some(WebappResource::Embedded(resource))
         }
+        .map(|resource| self.rewrite_webapp_resource(resource))
+    }
+
+    /// Rewrites a page of the web app for the prefix of this registry, if any
+    fn rewrite_webapp_resource(&self, resource: WebappResource) -> WebappResource {
+        let prefix = self.application.configuration.load().get_path_prefix();
+        if prefix.is_empty() || resource.content_type() != "text/html" {
+            return resource;
+        }
+        let content_type = resource.content_type().to_string();
+        let data = set_base_path(&resource.into_data(), &prefix);
+        WebappResource::Generated { content_type, data }
+    }
+}
+
+/// Middleware that accounts the served requests and bytes to the authenticated token or user
+pub async fn account_traffic(State(state): State<Arc<AxumState>>, request: Request<Body>, next: Next) -> Response {
+    let response = next.run(request).await;
+    if let Some(Some(subject)) = with_request_context(|context| *context.traffic_subject.lock().unwrap()) {
+        let bytes = http_body::Body::size_hint(response.body()).exact().unwrap_or_default();
+        state.application.record_traffic(TrafficUsage { subject, bytes }).await;
+    }
+    response
+}
+
+/// Middleware that hides the restricted crates designated by the `package` parameter of the path from the users that cannot read them
+pub async fn check_crate_visibility(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    params: RawPathParams,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    if let Some((_, package)) = params.iter().find(|(name, _)| *name == "package") {
+        if let Err(error) = state.application.check_can_read_crate(&auth_data, package).await {
+            return response_error(error).into_response();
+        }
     }
+    next.run(request).await
 }
 
 #[derive(Deserialize)]
@@ -110,10 +208,24 @@ pub struct PathInfoCrateVersion {
     version: String,
 }
 
+#[derive(Deserialize)]
+pub struct PathInfoCrateVersionTarget {
+    package: String,
+    version: String,
+    target: String,
+}
+
+#[derive(Deserialize)]
+pub struct PathInfoCrateVersionFile {
+    package: String,
+    version: String,
+    path: String,
+}
+
 /// Response for a GET on the root
 /// Redirect to the web app
 pub async fn get_root(State(state): State<Arc<AxumState>>) -> (StatusCode, [(HeaderName, HeaderValue); 2]) {
-    let target = format!("{}/webapp/index.html", state.application.configuration.web_public_uri);
+    let target = format!("{}/webapp/index.html", state.application.configuration.load().web_public_uri);
     (
         StatusCode::FOUND,
         [
@@ -141,16 +253,14 @@ fn get_auth_redirect(state: &AxumState) -> (StatusCode, [(HeaderName, HeaderValu
     // redirect to login
     let nonce = generate_token(64);
     let oauth_state = generate_token(32);
+    let configuration = state.application.configuration.load();
     let target = format!(
         "{}?response_type={}&redirect_uri={}&client_id={}&scope={}&nonce={}&state={}",
-        state.application.configuration.oauth_login_uri,
+        configuration.oauth_login_uri,
         "code",
-        urlencoding::encode(&format!(
-            "{}/webapp/oauthcallback.html",
-            state.application.configuration.web_public_uri
-        )),
-        urlencoding::encode(&state.application.configuration.oauth_client_id),
-        urlencoding::encode(&state.application.configuration.oauth_client_scope),
+        urlencoding::encode(&format!("{}/webapp/oauthcallback.html", configuration.web_public_uri)),
+        urlencoding::encode(&configuration.oauth_client_id),
+        urlencoding::encode(&configuration.oauth_client_scope),
         nonce,
         oauth_state
     );
@@ -165,9 +275,11 @@ fn get_auth_redirect(state: &AxumState) -> (StatusCode, [(HeaderName, HeaderValu
 
 /// Gets the redirection for a crates shortcut
 pub async fn get_redirection_crate(
+    State(state): State<Arc<AxumState>>,
     Path(PathInfoCrate { package }): Path<PathInfoCrate>,
 ) -> (StatusCode, [(HeaderName, HeaderValue); 2]) {
-    let target = format!("/webapp/crate.html?crate={package}");
+    let prefix = state.application.configuration.load().get_path_prefix();
+    let target = format!("{prefix}/webapp/crate.html?crate={package}");
     (
         StatusCode::FOUND,
         [
@@ -182,9 +294,11 @@ pub async fn get_redirection_crate(
 
 /// Gets the redirection for a crates shortcut
 pub async fn get_redirection_crate_version(
+    State(state): State<Arc<AxumState>>,
     Path(PathInfoCrateVersion { package, version }): Path<PathInfoCrateVersion>,
 ) -> (StatusCode, [(HeaderName, HeaderValue); 2]) {
-    let target = format!("/webapp/crate.html?crate={package}&version={version}");
+    let prefix = state.application.configuration.load().get_path_prefix();
+    let target = format!("{prefix}/webapp/crate.html?crate={package}&version={version}");
     (
         StatusCode::FOUND,
         [
@@ -208,7 +322,8 @@ pub async fn get_webapp_resource(
 
     if let Some(crate_name) = path.strip_prefix("crates/") {
         // URL shortcut for crates
-        let target = format!("/webapp/crate.html?crate={crate_name}");
+        let prefix = state.application.configuration.load().get_path_prefix();
+        let target = format!("{prefix}/webapp/crate.html?crate={crate_name}");
         return Ok((
             StatusCode::FOUND,
             [
@@ -249,7 +364,7 @@ pub async fn get_webapp_resource(
 
 /// Redirects to the login page
 pub async fn webapp_me(State(state): State<Arc<AxumState>>) -> (StatusCode, [(HeaderName, HeaderValue); 2]) {
-    let target = format!("{}/webapp/index.html", state.application.configuration.web_public_uri);
+    let target = format!("{}/webapp/index.html", state.application.configuration.load().web_public_uri);
     (
         StatusCode::FOUND,
         [
@@ -259,16 +374,19 @@ pub async fn webapp_me(State(state): State<Arc<AxumState>>) -> (StatusCode, [(He
     )
 }
 
+/// Number of seconds during which the pages of the documentation can be used without revalidating them
+const DOCS_MAX_AGE: u64 = 3600;
+
 /// Gets a file from the documentation
 pub async fn get_docs_resource(
     auth_data: AuthData,
     State(state): State<Arc<AxumState>>,
     request: Request<Body>,
-) -> Result<(StatusCode, [(HeaderName, HeaderValue); 2], Body), (StatusCode, [(HeaderName, HeaderValue); 1], Body)> {
+) -> Result<Response, (StatusCode, [(HeaderName, HeaderValue); 1], Body)> {
     let is_authenticated = state.application.authenticate(&auth_data).await.is_ok();
     if !is_authenticated {
         let (code, headers) = get_auth_redirect(&state);
-        return Ok((code, headers, Body::empty()));
+        return Ok((code, headers, Body::empty()).into_response());
     }
 
     let elements = request.uri().path().split('/').filter(|e| !e.is_empty()).collect::<Vec<_>>();
@@ -286,18 +404,23 @@ pub async fn get_docs_resource(
             Body::empty(),
         ));
     }
+    let restricted = match state.application.check_can_read_crate(&auth_data, elements[1]).await {
+        Ok(restricted) => restricted,
+        Err(error) => {
+            let (code, _) = response_error(error);
+            return Err((
+                code,
+                [(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"))],
+                Body::empty(),
+            ));
+        }
+    };
     // build the key
-    let (target, rest_index) = if elements.len() >= 5
-        && state
-            .application
-            .configuration
-            .self_known_targets
-            .iter()
-            .any(|t| elements[3] == t)
-    {
+    let configuration = state.application.configuration.load();
+    let (target, rest_index) = if elements.len() >= 5 && configuration.self_known_targets.iter().any(|t| elements[3] == t) {
         (elements[3], 4)
     } else {
-        (state.application.configuration.self_toolchain_host.as_str(), 3)
+        (configuration.self_toolchain_host.as_str(), 3)
     };
     let key = format!(
         "{}/{}/{}/{}",
@@ -308,18 +431,24 @@ pub async fn get_docs_resource(
     );
 
     let extension = get_content_type(&key);
+    // the static files of rustdoc have the hash of their content in their name
+    let cache_control = if elements.contains(&"static.files") {
+        configuration.web_cache_headers.immutable(restricted)
+    } else {
+        // the documentation may be generated again
+        configuration.web_cache_headers.revalidated(DOCS_MAX_AGE, restricted)
+    };
     match state.application.get_service_storage().download_doc_file(&key).await {
         Ok(content) => Ok((
             StatusCode::OK,
             [
                 (header::CONTENT_TYPE, HeaderValue::from_static(extension)),
-                (
-                    header::CACHE_CONTROL,
-                    HeaderValue::from_static("public, max-age=3600, immutable"),
-                ),
+                (header::CACHE_CONTROL, HeaderValue::from_str(&cache_control).unwrap()),
+                (header::VARY, VARY_AUTHENTICATION),
             ],
             Body::from(content),
-        )),
+        )
+            .into_response()),
         Err(e) => {
             let message = e.to_string();
             Err((
@@ -349,6 +478,14 @@ fn get_content_type(name: &str) -> &'static str {
 }
 
 /// Get server configuration
+#[utoipa::path(
+    get,
+    path = "/api/v1/registry-information",
+    tag = "registry",
+    responses(
+        (status = 200, description = "The information about the registry", body = RegistryInformation),
+    )
+)]
 pub async fn api_v1_get_registry_information(
     auth_data: AuthData,
     State(state): State<Arc<AxumState>>,
@@ -357,11 +494,109 @@ pub async fn api_v1_get_registry_information(
 }
 
 /// Get the current user
+#[utoipa::path(
+    get,
+    path = "/api/v1/me",
+    tag = "users",
+    responses(
+        (status = 200, description = "The current user", body = RegistryUser),
+    )
+)]
 pub async fn api_v1_get_current_user(auth_data: AuthData, State(state): State<Arc<AxumState>>) -> ApiResult<RegistryUser> {
     response(state.application.get_current_user(&auth_data).await)
 }
 
+/// Gets the usage of the current user against their quotas
+#[utoipa::path(
+    get,
+    path = "/api/v1/me/quota",
+    tag = "users",
+    responses(
+        (status = 200, description = "The usage against the quotas", body = QuotaUsage),
+    )
+)]
+pub async fn api_v1_get_current_user_quota(auth_data: AuthData, State(state): State<Arc<AxumState>>) -> ApiResult<QuotaUsage> {
+    response(state.application.get_current_user_quota(&auth_data).await)
+}
+
+/// Gets the usage of the teams of the current user against their quotas
+#[utoipa::path(
+    get,
+    path = "/api/v1/me/quota/teams",
+    tag = "users",
+    responses(
+        (status = 200, description = "The usage against the quotas of each team of the user that has quotas", body = [QuotaUsage]),
+    )
+)]
+pub async fn api_v1_get_current_user_team_quotas(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+) -> ApiResult<Vec<QuotaUsage>> {
+    response(state.application.get_current_user_team_quotas(&auth_data).await)
+}
+
+/// Updates the profile of the current user
+#[utoipa::path(
+    patch,
+    path = "/api/v1/me",
+    tag = "users",
+    request_body = UserProfileUpdate,
+    responses(
+        (status = 200, description = "The updated user", body = RegistryUser),
+    )
+)]
+pub async fn api_v1_update_current_user(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    input: Json<UserProfileUpdate>,
+) -> ApiResult<RegistryUser> {
+    response(state.application.update_current_user(&auth_data, &input).await)
+}
+
+/// Gets the preferences of the current user
+#[utoipa::path(
+    get,
+    path = "/api/v1/me/preferences",
+    tag = "users",
+    responses(
+        (status = 200, description = "The preferences of the user", body = UserPreferences),
+    )
+)]
+pub async fn api_v1_get_current_user_preferences(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+) -> ApiResult<UserPreferences> {
+    response(state.application.get_current_user_preferences(&auth_data).await)
+}
+
+/// Sets the preferences of the current user
+#[utoipa::path(
+    put,
+    path = "/api/v1/me/preferences",
+    tag = "users",
+    request_body = UserPreferences,
+    responses(
+        (status = 200, description = "The updated preferences", body = UserPreferences),
+    )
+)]
+pub async fn api_v1_set_current_user_preferences(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    input: Json<UserPreferences>,
+) -> ApiResult<UserPreferences> {
+    response(state.application.set_current_user_preferences(&auth_data, &input).await)
+}
+
 /// Attempts to login using an OAuth code
+#[utoipa::path(
+    post,
+    path = "/api/v1/oauth/code",
+    tag = "users",
+    request_body(content = String, description = "The OAuth authorization code", content_type = "text/plain"),
+    responses(
+        (status = 200, description = "The logged-in user", body = RegistryUser),
+    )
+)]
 pub async fn api_v1_login_with_oauth_code(
     mut auth_data: AuthData,
     State(state): State<Arc<AxumState>>,
@@ -378,6 +613,14 @@ pub async fn api_v1_login_with_oauth_code(
 }
 
 /// Logout a user
+#[utoipa::path(
+    post,
+    path = "/api/v1/logout",
+    tag = "users",
+    responses(
+        (status = 200, description = "The user was logged out"),
+    )
+)]
 pub async fn api_v1_logout(mut auth_data: AuthData) -> (StatusCode, [(HeaderName, HeaderValue); 1]) {
     let cookie = auth_data.create_expired_id_cookie();
     (
@@ -386,23 +629,95 @@ pub async fn api_v1_logout(mut auth_data: AuthData) -> (StatusCode, [(HeaderName
     )
 }
 
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ListQuery<S> {
+    q: Option<String>,
+    page: Option<usize>,
+    #[serde(alias = "limit")]
+    per_page: Option<usize>,
+    #[param(value_type = Option<String>)]
+    sort: Option<S>,
+}
+
+impl<S: Default + Copy> ListQuery<S> {
+    /// Gets the requested pagination
+    fn pagination(&self) -> Pagination<S> {
+        Pagination::new(self.page, self.per_page, self.sort)
+    }
+}
+
 /// Gets the tokens for a user
+#[utoipa::path(
+    get,
+    path = "/api/v1/me/tokens",
+    tag = "tokens",
+    responses(
+        (status = 200, description = "The tokens of the current user", body = Vec<RegistryUserToken>),
+    )
+)]
 pub async fn api_v1_get_user_tokens(
     auth_data: AuthData,
     State(state): State<Arc<AxumState>>,
 ) -> ApiResult<Vec<RegistryUserToken>> {
-    response(state.application.get_tokens(&auth_data).await)
+    response(
+        state
+            .application
+            .get_tokens(&auth_data, None, Pagination::all())
+            .await
+            .map(|page| page.items),
+    )
 }
 
-#[derive(Deserialize)]
+/// Gets a page of the tokens for a user
+#[utoipa::path(
+    get,
+    path = "/api/v2/me/tokens",
+    tag = "tokens",
+    params(
+        ListQuery<TokenSort>,
+    ),
+    responses(
+        (status = 200, description = "The tokens of the current user", body = Page<RegistryUserToken>),
+    )
+)]
+pub async fn api_v2_get_user_tokens(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Query(query): Query<ListQuery<TokenSort>>,
+) -> ApiResult<Page<RegistryUserToken>> {
+    response(
+        state
+            .application
+            .get_tokens(&auth_data, query.q.as_deref(), query.pagination())
+            .await,
+    )
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct CreateTokenQuery {
+    /// Whether the token can write, defaults to the preferences of the user
     #[serde(rename = "canWrite")]
-    can_write: bool,
+    can_write: Option<bool>,
+    /// Whether the token can perform admin operations, defaults to the preferences of the user
     #[serde(rename = "canAdmin")]
-    can_admin: bool,
+    can_admin: Option<bool>,
 }
 
 /// Creates a token for the current user
+#[utoipa::path(
+    put,
+    path = "/api/v1/me/tokens",
+    tag = "tokens",
+    params(
+        CreateTokenQuery,
+    ),
+    request_body(content = String, description = "The name of the token", content_type = "text/plain"),
+    responses(
+        (status = 200, description = "The new token, with its secret", body = RegistryUserTokenWithSecret),
+    )
+)]
 pub async fn api_v1_create_user_token(
     auth_data: AuthData,
     State(state): State<Arc<AxumState>>,
@@ -413,6 +728,17 @@ pub async fn api_v1_create_user_token(
 }
 
 /// Revoke a previous token
+#[utoipa::path(
+    delete,
+    path = "/api/v1/me/tokens/{token_id}",
+    tag = "tokens",
+    params(
+        ("token_id" = i64, Path, description = "The identifier of the token"),
+    ),
+    responses(
+        (status = 200, description = "The token was revoked"),
+    )
+)]
 pub async fn api_v1_revoke_user_token(
     auth_data: AuthData,
     State(state): State<Arc<AxumState>>,
@@ -422,14 +748,62 @@ pub async fn api_v1_revoke_user_token(
 }
 
 /// Gets the global tokens for the registry, usually for CI purposes
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/tokens",
+    tag = "tokens",
+    responses(
+        (status = 200, description = "The global tokens", body = Vec<RegistryUserToken>),
+    )
+)]
 pub async fn api_v1_get_global_tokens(
     auth_data: AuthData,
     State(state): State<Arc<AxumState>>,
 ) -> ApiResult<Vec<RegistryUserToken>> {
-    response(state.application.get_global_tokens(&auth_data).await)
+    response(
+        state
+            .application
+            .get_global_tokens(&auth_data, None, Pagination::all())
+            .await
+            .map(|page| page.items),
+    )
+}
+
+/// Gets a page of the global tokens for the registry, usually for CI purposes
+#[utoipa::path(
+    get,
+    path = "/api/v2/admin/tokens",
+    tag = "tokens",
+    params(
+        ListQuery<TokenSort>,
+    ),
+    responses(
+        (status = 200, description = "The global tokens", body = Page<RegistryUserToken>),
+    )
+)]
+pub async fn api_v2_get_global_tokens(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Query(query): Query<ListQuery<TokenSort>>,
+) -> ApiResult<Page<RegistryUserToken>> {
+    response(
+        state
+            .application
+            .get_global_tokens(&auth_data, query.q.as_deref(), query.pagination())
+            .await,
+    )
 }
 
 /// Creates a global token for the registry
+#[utoipa::path(
+    put,
+    path = "/api/v1/admin/tokens",
+    tag = "tokens",
+    request_body(content = String, description = "The name of the token", content_type = "text/plain"),
+    responses(
+        (status = 200, description = "The new token, with its secret", body = RegistryUserTokenWithSecret),
+    )
+)]
 pub async fn api_v1_create_global_token(
     auth_data: AuthData,
     State(state): State<Arc<AxumState>>,
@@ -439,6 +813,17 @@ pub async fn api_v1_create_global_token(
 }
 
 /// Revokes a globel token for the registry
+#[utoipa::path(
+    delete,
+    path = "/api/v1/admin/tokens/{token_id}",
+    tag = "tokens",
+    params(
+        ("token_id" = i64, Path, description = "The identifier of the token"),
+    ),
+    responses(
+        (status = 200, description = "The token was revoked"),
+    )
+)]
 pub async fn api_v1_revoke_global_token(
     auth_data: AuthData,
     State(state): State<Arc<AxumState>>,
@@ -448,11 +833,30 @@ pub async fn api_v1_revoke_global_token(
 }
 
 /// Gets the documentation jobs
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/jobs/docgen",
+    tag = "jobs",
+    responses(
+        (status = 200, description = "The documentation generation jobs", body = Vec<DocGenJob>),
+    )
+)]
 pub async fn api_v1_get_doc_gen_jobs(auth_data: AuthData, State(state): State<Arc<AxumState>>) -> ApiResult<Vec<DocGenJob>> {
     response(state.application.get_doc_gen_jobs(&auth_data).await)
 }
 
 /// Gets the log for a documentation generation job
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/jobs/docgen/{job_id}/log",
+    tag = "jobs",
+    params(
+        ("job_id" = i64, Path, description = "The identifier of the job"),
+    ),
+    responses(
+        (status = 200, description = "The log of the job", body = String),
+    )
+)]
 pub async fn api_v1_get_doc_gen_job_log(
     auth_data: AuthData,
     State(state): State<Arc<AxumState>>,
@@ -461,7 +865,460 @@ pub async fn api_v1_get_doc_gen_job_log(
     response(state.application.get_doc_gen_job_log(&auth_data, job_id).await)
 }
 
+/// Launches the rebuild of the whole search index
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/jobs/reindex",
+    tag = "jobs",
+    responses(
+        (status = 200, description = "The progress of the rebuild", body = SearchReindexProgress),
+    )
+)]
+pub async fn api_v1_launch_search_reindex(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+) -> ApiResult<SearchReindexProgress> {
+    response(state.application.launch_search_reindex(&auth_data).await)
+}
+
+/// Yanks all the versions of the crates matching a pattern and a version requirement
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/yank",
+    tag = "admin",
+    request_body = BulkYankQuery,
+    responses(
+        (status = 200, description = "The yanked versions", body = BulkYankResult),
+    )
+)]
+pub async fn api_v1_bulk_yank(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    input: Json<BulkYankQuery>,
+) -> ApiResult<BulkYankResult> {
+    response(state.application.bulk_yank(&auth_data, &input).await)
+}
+
+/// Re-synchronizes the index with its remote origin
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/index/resync",
+    tag = "admin",
+    request_body = IndexResyncQuery,
+    responses(
+        (status = 200, description = "The result of the resynchronization", body = IndexResyncResult),
+    )
+)]
+pub async fn api_v1_resync_index_remote(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    input: Json<IndexResyncQuery>,
+) -> ApiResult<IndexResyncResult> {
+    response(state.application.resync_index_remote(&auth_data, &input).await)
+}
+
+/// Gets the storage used by each crate and each publishing user
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/storage",
+    tag = "admin",
+    responses(
+        (status = 200, description = "The storage usage", body = StorageUsageReport),
+    )
+)]
+pub async fn api_v1_get_storage_usage(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+) -> ApiResult<StorageUsageReport> {
+    response(state.application.get_storage_usage(&auth_data).await)
+}
+
+/// Gets the report of the publication activity in the registry
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/activity",
+    tag = "admin",
+    responses(
+        (status = 200, description = "The publication activity", body = PublishActivityReport),
+    )
+)]
+pub async fn api_v1_get_publish_activity(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+) -> ApiResult<PublishActivityReport> {
+    response(state.application.get_publish_activity(&auth_data).await)
+}
+
+/// Gets the report of the requests and bytes served per token and user
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/traffic",
+    tag = "admin",
+    responses(
+        (status = 200, description = "The traffic report", body = TrafficReport),
+    )
+)]
+pub async fn api_v1_get_traffic_report(auth_data: AuthData, State(state): State<Arc<AxumState>>) -> ApiResult<TrafficReport> {
+    response(state.application.get_traffic_report(&auth_data).await)
+}
+
+/// Gets the report about the advisories affecting the hosted crates, directly or through their dependencies
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/advisories",
+    tag = "admin",
+    responses(
+        (status = 200, description = "The advisories report", body = AdvisoriesReport),
+    )
+)]
+pub async fn api_v1_get_advisories_report(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+) -> ApiResult<AdvisoriesReport> {
+    response(state.application.get_advisories_report(&auth_data).await)
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct LicenseInventoryQuery {
+    /// Whether to include the external dependencies of the last version of the hosted crates
+    #[serde(default)]
+    dependencies: bool,
+    /// The format of the inventory, `json` (the default) or `csv`
+    format: Option<String>,
+}
+
+/// Gets the inventory of the licenses of the hosted crate versions, and optionally of their external dependencies
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/licenses",
+    tag = "admin",
+    params(LicenseInventoryQuery),
+    responses(
+        (status = 200, description = "The inventory of the licenses", body = LicenseInventory),
+        (status = 200, description = "The inventory of the licenses, as CSV", body = String, content_type = "text/csv"),
+    )
+)]
+pub async fn api_v1_get_license_inventory(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Query(LicenseInventoryQuery { dependencies, format }): Query<LicenseInventoryQuery>,
+) -> Result<(StatusCode, [(HeaderName, HeaderValue); 1], String), (StatusCode, Json<ApiError>)> {
+    let is_csv = match format.as_deref() {
+        None | Some("json") => false,
+        Some("csv") => true,
+        Some(format) => {
+            return Err(response_error(specialize(
+                error_invalid_request(),
+                format!("expected json or csv, got {format}"),
+            )))
+        }
+    };
+    let inventory = state
+        .application
+        .get_license_inventory(&auth_data, dependencies)
+        .await
+        .map_err(response_error)?;
+    if is_csv {
+        Ok((
+            StatusCode::OK,
+            [(header::CONTENT_TYPE, HeaderValue::from_static(LICENSES_CSV_CONTENT_TYPE))],
+            inventory.to_csv(),
+        ))
+    } else {
+        Ok((
+            StatusCode::OK,
+            [(header::CONTENT_TYPE, HeaderValue::from_static("application/json"))],
+            serde_json::to_string(&inventory).map_err(|e| response_error(e.into()))?,
+        ))
+    }
+}
+
+/// Gets the crate versions in quarantine
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/quarantine",
+    tag = "admin",
+    responses(
+        (status = 200, description = "The quarantined versions", body = Vec<QuarantinedVersion>),
+    )
+)]
+pub async fn api_v1_get_quarantined_versions(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+) -> ApiResult<Vec<QuarantinedVersion>> {
+    response(state.application.get_quarantined_versions(&auth_data).await)
+}
+
+/// Releases a crate version from quarantine, adding it to the index
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/quarantine/{package}/{version}/release",
+    tag = "admin",
+    params(
+        ("package" = String, Path, description = "The name of the crate"),
+        ("version" = String, Path, description = "The version of the crate"),
+    ),
+    responses(
+        (status = 200, description = "The version was released"),
+        (status = 400, description = "The version is not in quarantine", body = ApiError),
+    )
+)]
+pub async fn api_v1_release_quarantined_version(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Path(PathInfoCrateVersion { package, version }): Path<PathInfoCrateVersion>,
+) -> ApiResult<()> {
+    response(
+        state
+            .application
+            .release_quarantined_version(&auth_data, &package, &version)
+            .await,
+    )
+}
+
+/// Gets the rules for provisioning new users
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/provisioning",
+    tag = "admin",
+    responses(
+        (status = 200, description = "The provisioning rules", body = Vec<ProvisioningRule>),
+    )
+)]
+pub async fn api_v1_get_provisioning_rules(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+) -> ApiResult<Vec<ProvisioningRule>> {
+    response(state.application.get_provisioning_rules(&auth_data).await)
+}
+
+/// Replaces the rules for provisioning new users
+#[utoipa::path(
+    put,
+    path = "/api/v1/admin/provisioning",
+    tag = "admin",
+    request_body = Vec<ProvisioningRule>,
+    responses(
+        (status = 200, description = "The provisioning rules were updated"),
+    )
+)]
+pub async fn api_v1_set_provisioning_rules(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    input: Json<Vec<ProvisioningRule>>,
+) -> ApiResult<()> {
+    response(state.application.set_provisioning_rules(&auth_data, &input).await)
+}
+
+/// Gets the registry-wide statistics, for administrators
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/stats",
+    tag = "admin",
+    responses(
+        (status = 200, description = "The statistics", body = RegistryStats),
+    )
+)]
+pub async fn api_v1_get_registry_stats(auth_data: AuthData, State(state): State<Arc<AxumState>>) -> ApiResult<RegistryStats> {
+    response(state.application.get_registry_stats(&auth_data).await)
+}
+
+/// Gets the current state of the read-only maintenance mode
+#[utoipa::path(
+    get,
+    path = "/api/v1/maintenance",
+    tag = "registry",
+    responses(
+        (status = 200, description = "The maintenance status", body = MaintenanceConfig),
+    )
+)]
+pub async fn api_v1_get_maintenance(auth_data: AuthData, State(state): State<Arc<AxumState>>) -> ApiResult<MaintenanceConfig> {
+    response(state.application.get_maintenance(&auth_data).await)
+}
+
+/// Gets the announcement currently displayed to the users, if any
+#[utoipa::path(
+    get,
+    path = "/api/v1/announcement",
+    tag = "registry",
+    responses(
+        (status = 200, description = "The current announcement, if any", body = Option<Announcement>),
+    )
+)]
+pub async fn api_v1_get_current_announcement(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+) -> ApiResult<Option<Announcement>> {
+    response(state.application.get_current_announcement(&auth_data).await)
+}
+
+/// Sets the state of the read-only maintenance mode
+#[utoipa::path(
+    put,
+    path = "/api/v1/admin/maintenance",
+    tag = "admin",
+    request_body = MaintenanceConfig,
+    responses(
+        (status = 200, description = "The maintenance status was updated"),
+    )
+)]
+pub async fn api_v1_set_maintenance(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    input: Json<MaintenanceConfig>,
+) -> ApiResult<()> {
+    response(state.application.set_maintenance(&auth_data, input.0).await)
+}
+
+/// Gets the announcement set by the administrators, even when it is not currently displayed
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/announcement",
+    tag = "admin",
+    responses(
+        (status = 200, description = "The announcement, if any", body = Option<Announcement>),
+    )
+)]
+pub async fn api_v1_get_announcement(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+) -> ApiResult<Option<Announcement>> {
+    response(state.application.get_announcement(&auth_data).await)
+}
+
+/// Sets the announcement displayed to the users, replacing the previous one
+#[utoipa::path(
+    put,
+    path = "/api/v1/admin/announcement",
+    tag = "admin",
+    request_body = Announcement,
+    responses(
+        (status = 200, description = "The announcement was set"),
+    )
+)]
+pub async fn api_v1_set_announcement(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    input: Json<Announcement>,
+) -> ApiResult<()> {
+    response(state.application.set_announcement(&auth_data, &input).await)
+}
+
+/// Clears the announcement displayed to the users
+#[utoipa::path(
+    delete,
+    path = "/api/v1/admin/announcement",
+    tag = "admin",
+    responses(
+        (status = 200, description = "The announcement was cleared"),
+    )
+)]
+pub async fn api_v1_clear_announcement(auth_data: AuthData, State(state): State<Arc<AxumState>>) -> ApiResult<()> {
+    response(state.application.clear_announcement(&auth_data).await)
+}
+
+/// Reloads the settings of the configuration that can be changed at runtime
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/config/reload",
+    tag = "admin",
+    responses(
+        (status = 200, description = "The configuration was reloaded"),
+    )
+)]
+pub async fn api_v1_reload_configuration(auth_data: AuthData, State(state): State<Arc<AxumState>>) -> ApiResult<()> {
+    response(state.application.reload_configuration(&auth_data).await)
+}
+
+/// Gets the progress of the last rebuild of the search index
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/jobs/reindex",
+    tag = "jobs",
+    responses(
+        (status = 200, description = "The progress of the rebuild", body = SearchReindexProgress),
+    )
+)]
+pub async fn api_v1_get_search_reindex_progress(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+) -> ApiResult<SearchReindexProgress> {
+    response(state.application.get_search_reindex_progress(&auth_data).await)
+}
+
+/// Launches a backup of the database
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/jobs/backup",
+    tag = "jobs",
+    responses(
+        (status = 200, description = "The status of the backups", body = BackupStatus),
+    )
+)]
+pub async fn api_v1_launch_backup(auth_data: AuthData, State(state): State<Arc<AxumState>>) -> ApiResult<BackupStatus> {
+    response(state.application.launch_backup(&auth_data).await)
+}
+
+/// Gets the status of the backups of the database
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/jobs/backup",
+    tag = "jobs",
+    responses(
+        (status = 200, description = "The status of the backups", body = BackupStatus),
+    )
+)]
+pub async fn api_v1_get_backup_status(auth_data: AuthData, State(state): State<Arc<AxumState>>) -> ApiResult<BackupStatus> {
+    response(state.application.get_backup_status(&auth_data).await)
+}
+
+/// Gets the status of the background jobs run on a schedule
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/jobs/scheduled",
+    tag = "jobs",
+    responses(
+        (status = 200, description = "The status of the background jobs", body = [ScheduledJobStatus]),
+    )
+)]
+pub async fn api_v1_get_scheduled_jobs(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+) -> ApiResult<Vec<ScheduledJobStatus>> {
+    response(state.application.get_scheduled_jobs(&auth_data).await)
+}
+
+/// Triggers a run of a background job now
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/jobs/scheduled/{name}",
+    tag = "jobs",
+    params(
+        ("name" = String, Path, description = "The name of the job"),
+    ),
+    responses(
+        (status = 200, description = "The status of the job before the run", body = ScheduledJobStatus),
+        (status = 404, description = "The job is unknown"),
+    )
+)]
+pub async fn api_v1_run_scheduled_job(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Path(name): Path<String>,
+) -> ApiResult<ScheduledJobStatus> {
+    response(state.application.run_scheduled_job(&auth_data, &name).await)
+}
+
 /// Gets a stream of updates for documentation generation jobs
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/jobs/docgen/updates",
+    tag = "jobs",
+    responses(
+        (status = 200, description = "The stream of updates", content_type = "text/event-stream"),
+    )
+)]
 pub async fn api_v1_get_doc_gen_job_updates(
     auth_data: AuthData,
     State(state): State<Arc<AxumState>>,
@@ -474,12 +1331,53 @@ pub async fn api_v1_get_doc_gen_job_updates(
     Ok(stream.into_response())
 }
 
+/// Gets a stream of updates for a single documentation generation job
+#[utoipa::path(
+    get,
+    path = "/api/v1/jobs/docgen/{job_id}/updates",
+    tag = "jobs",
+    params(
+        ("job_id" = i64, Path, description = "The identifier of the job"),
+    ),
+    responses(
+        (status = 200, description = "The stream of updates", content_type = "text/event-stream"),
+    )
+)]
+pub async fn api_v1_get_doc_gen_job_updates_for(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Path(job_id): Path<i64>,
+) -> Result<Response, (StatusCode, Json<ApiError>)> {
+    let receiver = match state.application.get_doc_gen_job_updates_for(&auth_data, job_id).await {
+        Ok(r) => r,
+        Err(e) => return Err(response_error(e)),
+    };
+    let stream = ServerSentEventStream::new(ReceiverStream::new(receiver).map(Event::from_data));
+    Ok(stream.into_response())
+}
+
 /// Gets the connected worker nodes
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/workers",
+    tag = "workers",
+    responses(
+        (status = 200, description = "The connected workers", body = Vec<WorkerPublicData>),
+    )
+)]
 pub async fn api_v1_get_workers(auth_data: AuthData, State(state): State<Arc<AxumState>>) -> ApiResult<Vec<WorkerPublicData>> {
     response(state.application.get_workers(&auth_data).await)
 }
 
 /// Adds a listener to workers updates
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/workers/updates",
+    tag = "workers",
+    responses(
+        (status = 200, description = "The stream of updates", content_type = "text/event-stream"),
+    )
+)]
 pub async fn api_v1_get_workers_updates(
     auth_data: AuthData,
     State(state): State<Arc<AxumState>>,
@@ -493,13 +1391,21 @@ pub async fn api_v1_get_workers_updates(
 }
 
 /// Endpoint for worker to connect to this host
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/workers/connect",
+    tag = "workers",
+    responses(
+        (status = 101, description = "The connection was upgraded to a WebSocket"),
+    )
+)]
 pub async fn api_v1_worker_connect(
     auth_data: AuthData,
     State(state): State<Arc<AxumState>>,
     request: Request<Body>,
 ) -> Result<Response, (StatusCode, Json<ApiError>)> {
     let token = auth_data.token.as_ref().ok_or_else(|| response_error(error_unauthorized()))?;
-    if Some(token.secret.as_str()) != state.application.configuration.self_role.get_worker_token() {
+    if Some(token.secret.as_str()) != state.application.configuration.load().self_role.get_worker_token() {
         return Err(response_error(error_unauthorized()));
     }
     let ws_upgrade = WebSocketUpgrade::from_request(request, &state)
@@ -556,7 +1462,7 @@ async fn worker_connect_handle_inner(web_socket: WebSocket, state: Arc<AxumState
         .lock()
         .await
         .send(Message::Text(serde_json::to_string(
-            &state.application.configuration.get_self_as_external(),
+            &state.application.configuration.load().get_self_as_external(),
         )?))
         .await?;
 
@@ -636,11 +1542,62 @@ async fn worker_connect_handle_inner(web_socket: WebSocket, state: Arc<AxumState
 }
 
 /// Gets the known users
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/users",
+    tag = "admin",
+    responses(
+        (status = 200, description = "The users", body = Vec<RegistryUser>),
+    )
+)]
 pub async fn api_v1_get_users(auth_data: AuthData, State(state): State<Arc<AxumState>>) -> ApiResult<Vec<RegistryUser>> {
-    response(state.application.get_users(&auth_data).await)
+    response(
+        state
+            .application
+            .get_users(&auth_data, None, Pagination::all())
+            .await
+            .map(|page| page.items),
+    )
+}
+
+/// Gets a page of the known users
+#[utoipa::path(
+    get,
+    path = "/api/v2/admin/users",
+    tag = "admin",
+    params(
+        ListQuery<UserSort>,
+    ),
+    responses(
+        (status = 200, description = "The users", body = Page<RegistryUser>),
+    )
+)]
+pub async fn api_v2_get_users(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Query(query): Query<ListQuery<UserSort>>,
+) -> ApiResult<Page<RegistryUser>> {
+    response(
+        state
+            .application
+            .get_users(&auth_data, query.q.as_deref(), query.pagination())
+            .await,
+    )
 }
 
 /// Updates the information of a user
+#[utoipa::path(
+    patch,
+    path = "/api/v1/admin/users/{target}",
+    tag = "admin",
+    params(
+        ("target" = String, Path, description = "The email of the user, encoded in base64"),
+    ),
+    request_body = RegistryUser,
+    responses(
+        (status = 200, description = "The updated user", body = RegistryUser),
+    )
+)]
 pub async fn api_v1_update_user(
     auth_data: AuthData,
     State(state): State<Arc<AxumState>>,
@@ -657,6 +1614,17 @@ pub async fn api_v1_update_user(
 }
 
 /// Attempts to delete a user
+#[utoipa::path(
+    delete,
+    path = "/api/v1/admin/users/{target}",
+    tag = "admin",
+    params(
+        ("target" = String, Path, description = "The email of the user, encoded in base64"),
+    ),
+    responses(
+        (status = 200, description = "The user was deleted"),
+    )
+)]
 pub async fn api_v1_delete_user(
     auth_data: AuthData,
     State(state): State<Arc<AxumState>>,
@@ -665,8 +1633,103 @@ pub async fn api_v1_delete_user(
     response(state.application.delete_user(&auth_data, &email).await)
 }
 
-/// Attempts to deactivate a user
-pub async fn api_v1_deactivate_user(
+/// Gets the usage of a user against their quotas
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/users/{target}/quota",
+    tag = "admin",
+    params(
+        ("target" = String, Path, description = "The email of the user, encoded in base64"),
+    ),
+    responses(
+        (status = 200, description = "The usage against the quotas", body = QuotaUsage),
+    )
+)]
+pub async fn api_v1_get_user_quota(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Path(Base64(email)): Path<Base64>,
+) -> ApiResult<QuotaUsage> {
+    response(state.application.get_user_quota(&auth_data, &email).await)
+}
+
+/// Sets the quotas of a user, overriding the defaults of the registry
+#[utoipa::path(
+    put,
+    path = "/api/v1/admin/users/{target}/quota",
+    tag = "admin",
+    params(
+        ("target" = String, Path, description = "The email of the user, encoded in base64"),
+    ),
+    request_body = UserQuota,
+    responses(
+        (status = 200, description = "The usage against the new quotas", body = QuotaUsage),
+    )
+)]
+pub async fn api_v1_set_user_quota(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Path(Base64(email)): Path<Base64>,
+    quota: Json<UserQuota>,
+) -> ApiResult<QuotaUsage> {
+    response(state.application.set_user_quota(&auth_data, &email, &quota).await)
+}
+
+/// Gets the usage of the members of a team against the quotas of the team
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/teams/{team}/quota",
+    tag = "admin",
+    params(
+        ("team" = String, Path, description = "The name of the team, i.e. the role of its members"),
+    ),
+    responses(
+        (status = 200, description = "The usage against the quotas", body = QuotaUsage),
+    )
+)]
+pub async fn api_v1_get_team_quota(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Path(team): Path<String>,
+) -> ApiResult<QuotaUsage> {
+    response(state.application.get_team_quota(&auth_data, &team).await)
+}
+
+/// Sets the quotas shared by the members of a team
+#[utoipa::path(
+    put,
+    path = "/api/v1/admin/teams/{team}/quota",
+    tag = "admin",
+    params(
+        ("team" = String, Path, description = "The name of the team, i.e. the role of its members"),
+    ),
+    request_body = TeamQuota,
+    responses(
+        (status = 200, description = "The usage against the new quotas", body = QuotaUsage),
+    )
+)]
+pub async fn api_v1_set_team_quota(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Path(team): Path<String>,
+    quota: Json<TeamQuota>,
+) -> ApiResult<QuotaUsage> {
+    response(state.application.set_team_quota(&auth_data, &team, &quota).await)
+}
+
+/// Attempts to deactivate a user
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/users/{target}/deactivate",
+    tag = "admin",
+    params(
+        ("target" = String, Path, description = "The email of the user, encoded in base64"),
+    ),
+    responses(
+        (status = 200, description = "The user was deactivated"),
+    )
+)]
+pub async fn api_v1_deactivate_user(
     auth_data: AuthData,
     State(state): State<Arc<AxumState>>,
     Path(Base64(email)): Path<Base64>,
@@ -675,6 +1738,17 @@ pub async fn api_v1_deactivate_user(
 }
 
 /// Attempts to deactivate a user
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/users/{target}/reactivate",
+    tag = "admin",
+    params(
+        ("target" = String, Path, description = "The email of the user, encoded in base64"),
+    ),
+    responses(
+        (status = 200, description = "The user was reactivated"),
+    )
+)]
 pub async fn api_v1_reactivate_user(
     auth_data: AuthData,
     State(state): State<Arc<AxumState>>,
@@ -683,47 +1757,687 @@ pub async fn api_v1_reactivate_user(
     response(state.application.reactivate_user(&auth_data, &email).await)
 }
 
-#[derive(Deserialize)]
+/// Starts impersonating a user, for troubleshooting
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/users/{target}/impersonate",
+    tag = "admin",
+    params(
+        ("target" = String, Path, description = "The email of the user, encoded in base64"),
+    ),
+    request_body = ImpersonationRequest,
+    responses(
+        (status = 200, description = "The token to act as the user", body = ImpersonationToken),
+    )
+)]
+pub async fn api_v1_impersonate_user(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Path(Base64(email)): Path<Base64>,
+    input: Json<ImpersonationRequest>,
+) -> ApiResult<ImpersonationToken> {
+    response(state.application.impersonate_user(&auth_data, &email, &input).await)
+}
+
+/// Ends an impersonation before it expires
+#[utoipa::path(
+    delete,
+    path = "/api/v1/admin/impersonations/{impersonation_id}",
+    tag = "admin",
+    params(
+        ("impersonation_id" = i64, Path, description = "The identifier of the impersonation"),
+    ),
+    responses(
+        (status = 200, description = "The impersonation was ended"),
+    )
+)]
+pub async fn api_v1_end_impersonation(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Path(impersonation_id): Path<i64>,
+) -> ApiResult<()> {
+    response(state.application.end_impersonation(&auth_data, impersonation_id).await)
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AuditLogQuery {
+    limit: Option<usize>,
+}
+
+/// Gets the most recent entries in the audit log
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/audit",
+    tag = "admin",
+    params(
+        AuditLogQuery,
+    ),
+    responses(
+        (status = 200, description = "The entries, the most recent first", body = Vec<AuditLogEntry>),
+    )
+)]
+pub async fn api_v1_get_audit_log(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Query(AuditLogQuery { limit }): Query<AuditLogQuery>,
+) -> ApiResult<Vec<AuditLogEntry>> {
+    response(
+        state
+            .application
+            .get_audit_log(&auth_data, None, Pagination::first(limit.unwrap_or(AUDIT_LOG_DEFAULT_LIMIT)))
+            .await
+            .map(|page| page.items),
+    )
+}
+
+/// Gets a page of the entries in the audit log
+#[utoipa::path(
+    get,
+    path = "/api/v2/admin/audit",
+    tag = "admin",
+    params(
+        ListQuery<AuditLogSort>,
+    ),
+    responses(
+        (status = 200, description = "The entries, the most recent first by default", body = Page<AuditLogEntry>),
+    )
+)]
+pub async fn api_v2_get_audit_log(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Query(query): Query<ListQuery<AuditLogSort>>,
+) -> ApiResult<Page<AuditLogEntry>> {
+    response(
+        state
+            .application
+            .get_audit_log(&auth_data, query.q.as_deref(), query.pagination())
+            .await,
+    )
+}
+
+/// Gets the webhooks visible to the user
+#[utoipa::path(
+    get,
+    path = "/api/v1/webhooks",
+    tag = "webhooks",
+    responses(
+        (status = 200, description = "The webhooks visible to the user", body = Vec<Webhook>),
+    )
+)]
+pub async fn api_v1_get_webhooks(auth_data: AuthData, State(state): State<Arc<AxumState>>) -> ApiResult<Vec<Webhook>> {
+    response(state.application.get_webhooks(&auth_data).await)
+}
+
+/// Creates a webhook
+#[utoipa::path(
+    put,
+    path = "/api/v1/webhooks",
+    tag = "webhooks",
+    request_body = WebhookCreateQuery,
+    responses(
+        (status = 200, description = "The new webhook, with its secret", body = WebhookWithSecret),
+    )
+)]
+pub async fn api_v1_create_webhook(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    input: Json<WebhookCreateQuery>,
+) -> ApiResult<WebhookWithSecret> {
+    response(state.application.create_webhook(&auth_data, &input).await)
+}
+
+/// Deletes a webhook
+#[utoipa::path(
+    delete,
+    path = "/api/v1/webhooks/{webhook_id}",
+    tag = "webhooks",
+    params(
+        ("webhook_id" = i64, Path, description = "The identifier of the webhook"),
+    ),
+    responses(
+        (status = 200, description = "The webhook was deleted"),
+    )
+)]
+pub async fn api_v1_delete_webhook(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Path(webhook_id): Path<i64>,
+) -> ApiResult<()> {
+    response(state.application.delete_webhook(&auth_data, webhook_id).await)
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct WebhookDeliveriesQuery {
+    limit: Option<i64>,
+}
+
+/// Gets the most recent deliveries for a webhook
+#[utoipa::path(
+    get,
+    path = "/api/v1/webhooks/{webhook_id}/deliveries",
+    tag = "webhooks",
+    params(
+        ("webhook_id" = i64, Path, description = "The identifier of the webhook"),
+        WebhookDeliveriesQuery,
+    ),
+    responses(
+        (status = 200, description = "The deliveries, the most recent first", body = Vec<WebhookDelivery>),
+    )
+)]
+pub async fn api_v1_get_webhook_deliveries(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Path(webhook_id): Path<i64>,
+    Query(WebhookDeliveriesQuery { limit }): Query<WebhookDeliveriesQuery>,
+) -> ApiResult<Vec<WebhookDelivery>> {
+    response(state.application.get_webhook_deliveries(&auth_data, webhook_id, limit).await)
+}
+
+/// Delivers again the payload of a previous delivery of a webhook and returns the identifier of the new delivery
+#[utoipa::path(
+    post,
+    path = "/api/v1/webhooks/{webhook_id}/deliveries/{delivery_id}/redeliver",
+    tag = "webhooks",
+    params(
+        ("webhook_id" = i64, Path, description = "The identifier of the webhook"),
+        ("delivery_id" = i64, Path, description = "The identifier of the delivery to send again"),
+    ),
+    responses(
+        (status = 200, description = "The identifier of the new delivery", body = i64),
+    )
+)]
+pub async fn api_v1_redeliver_webhook_delivery(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Path((webhook_id, delivery_id)): Path<(i64, i64)>,
+) -> ApiResult<i64> {
+    response(
+        state
+            .application
+            .redeliver_webhook_delivery(&auth_data, webhook_id, delivery_id)
+            .await,
+    )
+}
+
+/// Executes a GraphQL query over the registry data
+#[utoipa::path(
+    post,
+    path = "/api/v1/graphql",
+    tag = "graphql",
+    request_body(content = Object, description = "The GraphQL request, with the `query`, `variables` and `operationName`", content_type = "application/json"),
+    responses(
+        (status = 200, description = "The GraphQL response", body = Object),
+    )
+)]
+pub async fn api_v1_graphql(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Json(request): Json<async_graphql::Request>,
+) -> Json<async_graphql::Response> {
+    let request = request.data(state.application.clone()).data(auth_data);
+    Json(state.graphql_schema.execute(request).await)
+}
+
+/// Gets the crates watched by the current user
+#[utoipa::path(
+    get,
+    path = "/api/v1/me/watches",
+    tag = "users",
+    responses(
+        (status = 200, description = "The names of the watched crates", body = Vec<String>),
+    )
+)]
+pub async fn api_v1_get_watched_crates(auth_data: AuthData, State(state): State<Arc<AxumState>>) -> ApiResult<Vec<String>> {
+    response(state.application.get_watched_crates(&auth_data).await)
+}
+
+/// Starts watching a crate
+#[utoipa::path(
+    put,
+    path = "/api/v1/crates/{package}/watch",
+    tag = "users",
+    params(
+        ("package" = String, Path, description = "The name of the crate"),
+    ),
+    responses(
+        (status = 200, description = "The crate is watched"),
+    )
+)]
+pub async fn api_v1_watch_crate(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Path(PathInfoCrate { package }): Path<PathInfoCrate>,
+) -> ApiResult<()> {
+    response(state.application.watch_crate(&auth_data, &package).await)
+}
+
+/// Stops watching a crate
+#[utoipa::path(
+    delete,
+    path = "/api/v1/crates/{package}/watch",
+    tag = "users",
+    params(
+        ("package" = String, Path, description = "The name of the crate"),
+    ),
+    responses(
+        (status = 200, description = "The crate is no longer watched"),
+    )
+)]
+pub async fn api_v1_unwatch_crate(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Path(PathInfoCrate { package }): Path<PathInfoCrate>,
+) -> ApiResult<()> {
+    response(state.application.unwatch_crate(&auth_data, &package).await)
+}
+
+/// Gets the crates starred by the current user
+#[utoipa::path(
+    get,
+    path = "/api/v1/me/stars",
+    tag = "users",
+    responses(
+        (status = 200, description = "The names of the starred crates", body = Vec<String>),
+    )
+)]
+pub async fn api_v1_get_starred_crates(auth_data: AuthData, State(state): State<Arc<AxumState>>) -> ApiResult<Vec<String>> {
+    response(state.application.get_starred_crates(&auth_data).await)
+}
+
+/// Stars a crate
+#[utoipa::path(
+    put,
+    path = "/api/v1/crates/{package}/star",
+    tag = "users",
+    params(
+        ("package" = String, Path, description = "The name of the crate"),
+    ),
+    responses(
+        (status = 200, description = "The crate is starred"),
+    )
+)]
+pub async fn api_v1_star_crate(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Path(PathInfoCrate { package }): Path<PathInfoCrate>,
+) -> ApiResult<()> {
+    response(state.application.star_crate(&auth_data, &package).await)
+}
+
+/// Removes the star on a crate
+#[utoipa::path(
+    delete,
+    path = "/api/v1/crates/{package}/star",
+    tag = "users",
+    params(
+        ("package" = String, Path, description = "The name of the crate"),
+    ),
+    responses(
+        (status = 200, description = "The crate is no longer starred"),
+    )
+)]
+pub async fn api_v1_unstar_crate(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Path(PathInfoCrate { package }): Path<PathInfoCrate>,
+) -> ApiResult<()> {
+    response(state.application.unstar_crate(&auth_data, &package).await)
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct UserNotificationsQuery {
+    #[serde(default)]
+    unread: bool,
+    limit: Option<i64>,
+}
+
+/// Gets the most recent notifications about watched crates for the current user
+#[utoipa::path(
+    get,
+    path = "/api/v1/me/notifications",
+    tag = "users",
+    params(
+        UserNotificationsQuery,
+    ),
+    responses(
+        (status = 200, description = "The notifications, the most recent first", body = Vec<UserNotification>),
+    )
+)]
+pub async fn api_v1_get_user_notifications(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Query(UserNotificationsQuery { unread, limit }): Query<UserNotificationsQuery>,
+) -> ApiResult<Vec<UserNotification>> {
+    response(state.application.get_user_notifications(&auth_data, unread, limit).await)
+}
+
+/// Marks all the notifications of the current user as read
+#[utoipa::path(
+    post,
+    path = "/api/v1/me/notifications/read",
+    tag = "users",
+    responses(
+        (status = 200, description = "The notifications were marked as read"),
+    )
+)]
+pub async fn api_v1_mark_user_notifications_read(auth_data: AuthData, State(state): State<Arc<AxumState>>) -> ApiResult<()> {
+    response(state.application.mark_user_notifications_read(&auth_data).await)
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct SearchForm {
+    #[serde(default)]
     q: String,
+    page: Option<usize>,
     per_page: Option<usize>,
+    sort: Option<SearchSort>,
     deprecated: Option<bool>,
-}
-
+    include_yanked: Option<bool>,
+    keyword: Option<String>,
+    category: Option<String>,
+    owner: Option<String>,
+}
+
+/// Searches for crates
+#[utoipa::path(
+    get,
+    path = "/api/v1/crates",
+    tag = "crates",
+    params(
+        SearchForm,
+    ),
+    responses(
+        (status = 200, description = "The matching crates", body = SearchResults),
+    )
+)]
 pub async fn api_v1_cargo_search(
     auth_data: AuthData,
     State(state): State<Arc<AxumState>>,
-    form: Query<SearchForm>,
+    Query(form): Query<SearchForm>,
 ) -> ApiResult<SearchResults> {
     response(
         state
             .application
-            .search_crates(&auth_data, &form.q, form.per_page, form.deprecated)
+            .search_crates(
+                &auth_data,
+                &form.q,
+                &SearchFilters {
+                    keyword: form.keyword,
+                    category: form.category,
+                    owner: form.owner,
+                    deprecated: form.deprecated,
+                    include_yanked: form.include_yanked.unwrap_or_default(),
+                },
+                SearchPagination::new(form.page, form.per_page, form.sort),
+            )
             .await,
     )
 }
 
+/// The default number of suggestions
+const SUGGEST_DEFAULT_LIMIT: usize = 10;
+/// The maximum number of suggestions
+const SUGGEST_MAX_LIMIT: usize = 50;
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SuggestForm {
+    q: String,
+    limit: Option<usize>,
+}
+
+/// Suggests the names of crates that start with a prefix
+#[utoipa::path(
+    get,
+    path = "/api/v1/crates/suggest",
+    tag = "crates",
+    params(
+        SuggestForm,
+    ),
+    responses(
+        (status = 200, description = "The names of the matching crates", body = Vec<String>),
+    )
+)]
+pub async fn api_v1_suggest_crates(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Query(form): Query<SuggestForm>,
+) -> ApiResult<Vec<String>> {
+    let limit = form.limit.unwrap_or(SUGGEST_DEFAULT_LIMIT).min(SUGGEST_MAX_LIMIT);
+    response(state.application.suggest_crates(&auth_data, &form.q, limit).await)
+}
+
+/// Audits the packages locked in the `Cargo.lock` file given as body against the registry
+#[utoipa::path(
+    post,
+    path = "/api/v1/audit/lockfile",
+    tag = "crates",
+    request_body(content = String, description = "The content of a `Cargo.lock` file", content_type = "text/plain"),
+    responses(
+        (status = 200, description = "The result of the audit", body = LockFileAudit),
+    )
+)]
+pub async fn api_v1_audit_lock_file(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    body: Bytes,
+) -> ApiResult<LockFileAudit> {
+    let lock_file = String::from_utf8_lossy(&body);
+    response(state.application.audit_lock_file(&auth_data, &lock_file).await)
+}
+
+/// Builds a vendor archive for the crates locked in the `Cargo.lock` file given as body
+#[utoipa::path(
+    post,
+    path = "/api/v1/vendor",
+    tag = "crates",
+    request_body(content = String, description = "The content of a `Cargo.lock` file", content_type = "text/plain"),
+    responses(
+        (status = 200, description = "The vendor archive", body = Vec<u8>, content_type = "application/gzip"),
+    )
+)]
+pub async fn api_v1_export_vendor(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    body: Bytes,
+) -> Result<(StatusCode, [(HeaderName, HeaderValue); 2], Vec<u8>), (StatusCode, Json<ApiError>)> {
+    let lock_file = String::from_utf8_lossy(&body);
+    match state.application.export_vendor(&auth_data, &lock_file).await {
+        Ok(data) => Ok((
+            StatusCode::OK,
+            [
+                (header::CONTENT_TYPE, HeaderValue::from_static("application/gzip")),
+                (
+                    header::CONTENT_DISPOSITION,
+                    HeaderValue::from_static("attachment; filename=\"vendor.tar.gz\""),
+                ),
+            ],
+            data,
+        )),
+        Err(error) => Err(response_error(error)),
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ExportQuery {
+    /// Whether to include the `.crate` files and their metadata
+    #[serde(rename = "includeCrates", default)]
+    include_crates: bool,
+}
+
+/// Exports the whole registry in an archive, to migrate to another environment
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/export",
+    tag = "admin",
+    params(
+        ExportQuery,
+    ),
+    responses(
+        (status = 200, description = "The export archive", body = Vec<u8>, content_type = "application/gzip"),
+    )
+)]
+pub async fn api_v1_export_registry(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Query(ExportQuery { include_crates }): Query<ExportQuery>,
+) -> Result<(StatusCode, [(HeaderName, HeaderValue); 2], Body), (StatusCode, Json<ApiError>)> {
+    match state.application.export_registry(&auth_data, include_crates).await {
+        Ok(file) => Ok((
+            StatusCode::OK,
+            [
+                (header::CONTENT_TYPE, HeaderValue::from_static("application/gzip")),
+                (
+                    header::CONTENT_DISPOSITION,
+                    HeaderValue::from_static("attachment; filename=\"registry-export.tar.gz\""),
+                ),
+            ],
+            Body::from_stream(ReaderStream::new(file)),
+        )),
+        Err(error) => Err(response_error(error)),
+    }
+}
+
+/// Gets the metadata for the latest version of many crates at once
+#[utoipa::path(
+    post,
+    path = "/api/v1/crates/bulk",
+    tag = "crates",
+    request_body = BulkMetadataQuery,
+    responses(
+        (status = 200, description = "The metadata of the crates", body = BulkMetadataResult),
+    )
+)]
+pub async fn api_v1_get_crates_metadata(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    query: Json<BulkMetadataQuery>,
+) -> ApiResult<BulkMetadataResult> {
+    response(state.application.get_crates_metadata(&auth_data, &query).await)
+}
+
 /// Gets the global statistics for the registry
+#[utoipa::path(
+    get,
+    path = "/api/v1/crates/stats",
+    tag = "crates",
+    responses(
+        (status = 200, description = "The statistics", body = GlobalStats),
+    )
+)]
 pub async fn api_v1_get_crates_stats(auth_data: AuthData, State(state): State<Arc<AxumState>>) -> ApiResult<GlobalStats> {
     response(state.application.get_crates_stats(&auth_data).await)
 }
 
 /// Gets the packages that need documentation generation
+#[utoipa::path(
+    get,
+    path = "/api/v1/crates/undocumented",
+    tag = "crates",
+    responses(
+        (status = 200, description = "The crate versions without documentation", body = Vec<DocGenJobSpec>),
+    )
+)]
 pub async fn api_v1_get_crates_undocumented(
     auth_data: AuthData,
     State(state): State<Arc<AxumState>>,
 ) -> ApiResult<Vec<DocGenJobSpec>> {
-    response(state.application.get_undocumented_crates(&auth_data).await)
+    response(
+        state
+            .application
+            .get_undocumented_crates(&auth_data, None, Pagination::all())
+            .await
+            .map(|page| page.items),
+    )
+}
+
+/// Gets a page of the packages that need documentation generation
+#[utoipa::path(
+    get,
+    path = "/api/v2/crates/undocumented",
+    tag = "crates",
+    params(
+        ListQuery<CrateListSort>,
+    ),
+    responses(
+        (status = 200, description = "The crate versions without documentation", body = Page<DocGenJobSpec>),
+    )
+)]
+pub async fn api_v2_get_crates_undocumented(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Query(query): Query<ListQuery<CrateListSort>>,
+) -> ApiResult<Page<DocGenJobSpec>> {
+    response(
+        state
+            .application
+            .get_undocumented_crates(&auth_data, query.q.as_deref(), query.pagination())
+            .await,
+    )
 }
 
 /// Gets all the packages that are outdated while also being the latest version
+#[utoipa::path(
+    get,
+    path = "/api/v1/crates/outdated",
+    tag = "crates",
+    responses(
+        (status = 200, description = "The crate versions with outdated dependencies", body = Vec<CrateVersion>),
+    )
+)]
 pub async fn api_v1_get_crates_outdated_heads(
     auth_data: AuthData,
     State(state): State<Arc<AxumState>>,
 ) -> ApiResult<Vec<CrateVersion>> {
-    response(state.application.get_crates_outdated_heads(&auth_data).await)
+    response(
+        state
+            .application
+            .get_crates_outdated_heads(&auth_data, None, Pagination::all())
+            .await
+            .map(|page| page.items),
+    )
+}
+
+/// Gets a page of the packages that are outdated while also being the latest version
+#[utoipa::path(
+    get,
+    path = "/api/v2/crates/outdated",
+    tag = "crates",
+    params(
+        ListQuery<CrateListSort>,
+    ),
+    responses(
+        (status = 200, description = "The crate versions with outdated dependencies", body = Page<CrateVersion>),
+    )
+)]
+pub async fn api_v2_get_crates_outdated_heads(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Query(query): Query<ListQuery<CrateListSort>>,
+) -> ApiResult<Page<CrateVersion>> {
+    response(
+        state
+            .application
+            .get_crates_outdated_heads(&auth_data, query.q.as_deref(), query.pagination())
+            .await,
+    )
 }
 
+/// Publishes a crate version
+#[utoipa::path(
+    put,
+    path = "/api/v1/crates/new",
+    tag = "crates",
+    request_body(content = Vec<u8>, description = "The publication payload, as sent by cargo", content_type = "application/octet-stream"),
+    responses(
+        (status = 200, description = "The result of the publication", body = CrateUploadResult),
+    )
+)]
 pub async fn api_v1_cargo_publish_crate_version(
     auth_data: AuthData,
     State(state): State<Arc<AxumState>>,
@@ -732,6 +2446,18 @@ pub async fn api_v1_cargo_publish_crate_version(
     response(state.application.publish_crate_version(&auth_data, &body).await)
 }
 
+/// Gets the information about a crate
+#[utoipa::path(
+    get,
+    path = "/api/v1/crates/{package}",
+    tag = "crates",
+    params(
+        ("package" = String, Path, description = "The name of the crate"),
+    ),
+    responses(
+        (status = 200, description = "The information about the crate", body = CrateInfo),
+    )
+)]
 pub async fn api_v1_get_crate_info(
     auth_data: AuthData,
     State(state): State<Arc<AxumState>>,
@@ -740,53 +2466,557 @@ pub async fn api_v1_get_crate_info(
     response(state.application.get_crate_info(&auth_data, &package).await)
 }
 
+/// Gets the versions of a crate, in the format of the crates.io web API
+#[utoipa::path(
+    get,
+    path = "/api/v1/crates/{package}/versions",
+    tag = "crates",
+    params(
+        ("package" = String, Path, description = "The name of the crate"),
+    ),
+    responses(
+        (status = 200, description = "The versions of the crate", body = CratesIoVersions),
+    )
+)]
+pub async fn api_v1_get_crate_versions(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Path(PathInfoCrate { package }): Path<PathInfoCrate>,
+) -> ApiResult<CratesIoVersions> {
+    response(state.application.get_crates_io_versions(&auth_data, &package).await)
+}
+
+/// Gets a crate version, in the format of the crates.io web API
+#[utoipa::path(
+    get,
+    path = "/api/v1/crates/{package}/{version}",
+    tag = "crates",
+    params(
+        ("package" = String, Path, description = "The name of the crate"),
+        ("version" = String, Path, description = "The version of the crate"),
+    ),
+    responses(
+        (status = 200, description = "The crate version", body = CratesIoVersionResult),
+    )
+)]
+pub async fn api_v1_get_crate_version(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Path(PathInfoCrateVersion { package, version }): Path<PathInfoCrateVersion>,
+) -> ApiResult<CratesIoVersionResult> {
+    response(state.application.get_crates_io_version(&auth_data, &package, &version).await)
+}
+
+/// Gets the entries of a crate in the index, exactly as seen by cargo
+#[utoipa::path(
+    get,
+    path = "/api/v1/crates/{package}/index",
+    tag = "crates",
+    params(
+        ("package" = String, Path, description = "The name of the crate"),
+    ),
+    responses(
+        (status = 200, description = "The JSON entry for each version, one per line", body = String, content_type = "application/x-ndjson"),
+    )
+)]
+pub async fn api_v1_get_crate_index_file(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Path(PathInfoCrate { package }): Path<PathInfoCrate>,
+) -> Result<(StatusCode, [(HeaderName, HeaderValue); 1], Vec<u8>), (StatusCode, Json<ApiError>)> {
+    let data = state
+        .application
+        .get_crate_index_file(&auth_data, &package)
+        .await
+        .map_err(response_error)?;
+    Ok((
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, HeaderValue::from_static("application/x-ndjson"))],
+        data,
+    ))
+}
+
+/// Gets the README of the last version of a crate, rendered as HTML
+#[utoipa::path(
+    get,
+    path = "/api/v1/crates/{package}/readme/html",
+    tag = "crates",
+    params(
+        ("package" = String, Path, description = "The name of the crate"),
+    ),
+    responses(
+        (status = 200, description = "The rendered README", body = String, content_type = "text/html"),
+    )
+)]
+pub async fn api_v1_get_crate_last_readme_html(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Path(PathInfoCrate { package }): Path<PathInfoCrate>,
+) -> Result<(StatusCode, [(HeaderName, HeaderValue); 1], String), (StatusCode, Json<ApiError>)> {
+    let data = state
+        .application
+        .get_crate_last_readme(&auth_data, &package)
+        .await
+        .map_err(response_error)?;
+    Ok(readme_html_response(&data))
+}
+
+/// Gets the README of the last version of a crate
+#[utoipa::path(
+    get,
+    path = "/api/v1/crates/{package}/readme",
+    tag = "crates",
+    params(
+        ("package" = String, Path, description = "The name of the crate"),
+    ),
+    responses(
+        (status = 200, description = "The README", body = String, content_type = "text/markdown"),
+    )
+)]
 pub async fn api_v1_get_crate_last_readme(
     auth_data: AuthData,
     State(state): State<Arc<AxumState>>,
-    Path(PathInfoCrate { package }): Path<PathInfoCrate>,
-) -> Result<(StatusCode, [(HeaderName, HeaderValue); 1], Vec<u8>), (StatusCode, Json<ApiError>)> {
-    let data = state
-        .application
-        .get_crate_last_readme(&auth_data, &package)
-        .await
-        .map_err(response_error)?;
-
-    Ok((
-        StatusCode::OK,
-        [(header::CONTENT_TYPE, HeaderValue::from_static("text/markdown"))],
-        data,
-    ))
+    Path(PathInfoCrate { package }): Path<PathInfoCrate>,
+) -> Result<(StatusCode, [(HeaderName, HeaderValue); 1], Vec<u8>), (StatusCode, Json<ApiError>)> {
+    let data = state
+        .application
+        .get_crate_last_readme(&auth_data, &package)
+        .await
+        .map_err(response_error)?;
+    Ok(readme_raw_response(data))
+}
+
+/// Produces the response for a README rendered as HTML
+fn readme_html_response(data: &[u8]) -> (StatusCode, [(HeaderName, HeaderValue); 1], String) {
+    (
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, HeaderValue::from_static("text/html; charset=utf-8"))],
+        render_markdown(&String::from_utf8_lossy(data)),
+    )
+}
+
+/// Produces the response for a raw Markdown README
+fn readme_raw_response(data: Vec<u8>) -> (StatusCode, [(HeaderName, HeaderValue); 1], Vec<u8>) {
+    (
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, HeaderValue::from_static("text/markdown"))],
+        data,
+    )
+}
+
+/// Gets the Atom feed of the version history of a crate
+#[utoipa::path(
+    get,
+    path = "/api/v1/crates/{package}/feed",
+    tag = "feeds",
+    params(
+        ("package" = String, Path, description = "The name of the crate"),
+    ),
+    responses(
+        (status = 200, description = "The Atom feed", body = String, content_type = "application/atom+xml"),
+    )
+)]
+pub async fn api_v1_get_crate_feed(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Path(PathInfoCrate { package }): Path<PathInfoCrate>,
+) -> Result<(StatusCode, [(HeaderName, HeaderValue); 1], String), (StatusCode, Json<ApiError>)> {
+    let feed = state
+        .application
+        .get_feed_crate(&auth_data, &package)
+        .await
+        .map_err(response_error)?;
+    Ok((
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, HeaderValue::from_static(FEED_CONTENT_TYPE))],
+        feed,
+    ))
+}
+
+/// Gets the Atom feed of the last published versions in the registry
+#[utoipa::path(
+    get,
+    path = "/api/v1/feeds/versions",
+    tag = "feeds",
+    responses(
+        (status = 200, description = "The Atom feed", body = String, content_type = "application/atom+xml"),
+    )
+)]
+pub async fn api_v1_get_feed_versions(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+) -> Result<(StatusCode, [(HeaderName, HeaderValue); 1], String), (StatusCode, Json<ApiError>)> {
+    let feed = state
+        .application
+        .get_feed_versions(&auth_data)
+        .await
+        .map_err(response_error)?;
+    Ok((
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, HeaderValue::from_static(FEED_CONTENT_TYPE))],
+        feed,
+    ))
+}
+
+/// Gets the Atom feed of the versions published for the crates owned by a user
+#[utoipa::path(
+    get,
+    path = "/api/v1/feeds/owners/{login}",
+    tag = "feeds",
+    params(
+        ("login" = String, Path, description = "The login of the owner"),
+    ),
+    responses(
+        (status = 200, description = "The Atom feed", body = String, content_type = "application/atom+xml"),
+    )
+)]
+pub async fn api_v1_get_feed_owner(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Path(login): Path<String>,
+) -> Result<(StatusCode, [(HeaderName, HeaderValue); 1], String), (StatusCode, Json<ApiError>)> {
+    let feed = state
+        .application
+        .get_feed_owner(&auth_data, &login)
+        .await
+        .map_err(response_error)?;
+    Ok((
+        StatusCode::OK,
+        [(header::CONTENT_TYPE, HeaderValue::from_static(FEED_CONTENT_TYPE))],
+        feed,
+    ))
+}
+
+/// Gets the README of a crate version, rendered as HTML
+#[utoipa::path(
+    get,
+    path = "/api/v1/crates/{package}/{version}/readme/html",
+    tag = "crates",
+    params(
+        ("package" = String, Path, description = "The name of the crate"),
+        ("version" = String, Path, description = "The version of the crate"),
+    ),
+    responses(
+        (status = 200, description = "The rendered README", body = String, content_type = "text/html"),
+    )
+)]
+pub async fn api_v1_get_crate_readme_html(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Path(PathInfoCrateVersion { package, version }): Path<PathInfoCrateVersion>,
+) -> Result<(StatusCode, [(HeaderName, HeaderValue); 1], String), (StatusCode, Json<ApiError>)> {
+    let data = state
+        .application
+        .get_crate_readme(&auth_data, &package, &version)
+        .await
+        .map_err(response_error)?;
+    Ok(readme_html_response(&data))
+}
+
+/// Gets the README of a crate version
+#[utoipa::path(
+    get,
+    path = "/api/v1/crates/{package}/{version}/readme",
+    tag = "crates",
+    params(
+        ("package" = String, Path, description = "The name of the crate"),
+        ("version" = String, Path, description = "The version of the crate"),
+    ),
+    responses(
+        (status = 200, description = "The README", body = String, content_type = "text/markdown"),
+    )
+)]
+pub async fn api_v1_get_crate_readme(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Path(PathInfoCrateVersion { package, version }): Path<PathInfoCrateVersion>,
+) -> Result<(StatusCode, [(HeaderName, HeaderValue); 1], Vec<u8>), (StatusCode, Json<ApiError>)> {
+    let data = state
+        .application
+        .get_crate_readme(&auth_data, &package, &version)
+        .await
+        .map_err(response_error)?;
+    Ok(readme_raw_response(data))
+}
+
+/// Lists the files in the `.crate` archive of a crate version
+#[utoipa::path(
+    get,
+    path = "/api/v1/crates/{package}/{version}/files",
+    tag = "crates",
+    params(
+        ("package" = String, Path, description = "The name of the crate"),
+        ("version" = String, Path, description = "The version of the crate"),
+    ),
+    responses(
+        (status = 200, description = "The files, by order of paths", body = Vec<CrateFile>),
+    )
+)]
+pub async fn api_v1_get_crate_files(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Path(PathInfoCrateVersion { package, version }): Path<PathInfoCrateVersion>,
+) -> ApiResult<Vec<CrateFile>> {
+    response(state.application.get_crate_files(&auth_data, &package, &version).await)
+}
+
+/// Gets the content of a single file in the `.crate` archive of a crate version
+#[utoipa::path(
+    get,
+    path = "/api/v1/crates/{package}/{version}/files/{path}",
+    tag = "crates",
+    params(
+        ("package" = String, Path, description = "The name of the crate"),
+        ("version" = String, Path, description = "The version of the crate"),
+        ("path" = String, Path, description = "The path of the file, relative to the root of the crate"),
+    ),
+    responses(
+        (status = 200, description = "The content of the file", body = String, content_type = "text/plain"),
+        (status = 400, description = "The file is too large", body = ApiError),
+    )
+)]
+pub async fn api_v1_get_crate_file(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Path(PathInfoCrateVersionFile { package, version, path }): Path<PathInfoCrateVersionFile>,
+) -> Result<(StatusCode, [(HeaderName, HeaderValue); 1], Vec<u8>), (StatusCode, Json<ApiError>)> {
+    let data = state
+        .application
+        .get_crate_file(&auth_data, &package, &version, &path)
+        .await
+        .map_err(response_error)?;
+    let content_type = if std::str::from_utf8(&data).is_ok() {
+        HeaderValue::from_static("text/plain; charset=utf-8")
+    } else {
+        HeaderValue::from_static("application/octet-stream")
+    };
+    Ok((StatusCode::OK, [(header::CONTENT_TYPE, content_type)], data))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CrateDiffQuery {
+    /// The old version
+    from: String,
+    /// The new version
+    to: String,
+}
+
+/// Gets the differences between the files of two versions of a crate
+#[utoipa::path(
+    get,
+    path = "/api/v1/crates/{package}/diff",
+    tag = "crates",
+    params(
+        ("package" = String, Path, description = "The name of the crate"),
+        CrateDiffQuery,
+    ),
+    responses(
+        (status = 200, description = "The files that changed, with unified diffs for text files", body = CrateDiff),
+    )
+)]
+pub async fn api_v1_get_crate_diff(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Path(PathInfoCrate { package }): Path<PathInfoCrate>,
+    Query(CrateDiffQuery { from, to }): Query<CrateDiffQuery>,
+) -> ApiResult<CrateDiff> {
+    response(state.application.get_crate_diff(&auth_data, &package, &from, &to).await)
+}
+
+/// Gets the data for cargo-binstall about a crate version
+#[utoipa::path(
+    get,
+    path = "/api/v1/crates/{package}/{version}/binstall",
+    tag = "crates",
+    params(
+        ("package" = String, Path, description = "The name of the crate"),
+        ("version" = String, Path, description = "The version of the crate"),
+    ),
+    responses(
+        (status = 200, description = "The binstall metadata from the manifest and the hosted binaries", body = BinstallInfo),
+    )
+)]
+pub async fn api_v1_get_crate_binstall(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Path(PathInfoCrateVersion { package, version }): Path<PathInfoCrateVersion>,
+) -> ApiResult<BinstallInfo> {
+    response(state.application.get_crate_binstall(&auth_data, &package, &version).await)
+}
+
+/// Uploads a prebuilt binary artifact for a crate version and a target
+#[utoipa::path(
+    put,
+    path = "/api/v1/crates/{package}/{version}/binaries/{target}",
+    tag = "crates",
+    params(
+        ("package" = String, Path, description = "The name of the crate"),
+        ("version" = String, Path, description = "The version of the crate"),
+        ("target" = String, Path, description = "The target triple"),
+    ),
+    request_body(content = Vec<u8>, content_type = "application/octet-stream"),
+    responses(
+        (status = 200, description = "The uploaded binary", body = CrateBinary),
+    )
+)]
+pub async fn api_v1_upload_crate_binary(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Path(PathInfoCrateVersionTarget {
+        package,
+        version,
+        target,
+    }): Path<PathInfoCrateVersionTarget>,
+    body: Bytes,
+) -> ApiResult<CrateBinary> {
+    response(
+        state
+            .application
+            .upload_crate_binary(&auth_data, &package, &version, &target, body.to_vec())
+            .await,
+    )
 }
 
-pub async fn api_v1_get_crate_readme(
+/// Downloads a prebuilt binary artifact for a crate version and a target
+#[utoipa::path(
+    get,
+    path = "/api/v1/crates/{package}/{version}/binaries/{target}",
+    tag = "crates",
+    params(
+        ("package" = String, Path, description = "The name of the crate"),
+        ("version" = String, Path, description = "The version of the crate"),
+        ("target" = String, Path, description = "The target triple"),
+    ),
+    responses(
+        (status = 200, description = "The binary artifact", body = Vec<u8>, content_type = "application/octet-stream"),
+    )
+)]
+pub async fn api_v1_download_crate_binary(
     auth_data: AuthData,
     State(state): State<Arc<AxumState>>,
-    Path(PathInfoCrateVersion { package, version }): Path<PathInfoCrateVersion>,
+    Path(PathInfoCrateVersionTarget {
+        package,
+        version,
+        target,
+    }): Path<PathInfoCrateVersionTarget>,
 ) -> Result<(StatusCode, [(HeaderName, HeaderValue); 1], Vec<u8>), (StatusCode, Json<ApiError>)> {
     let data = state
         .application
-        .get_crate_readme(&auth_data, &package, &version)
+        .get_crate_binary(&auth_data, &package, &version, &target)
         .await
         .map_err(response_error)?;
-
     Ok((
         StatusCode::OK,
-        [(header::CONTENT_TYPE, HeaderValue::from_static("text/markdown"))],
+        [(header::CONTENT_TYPE, HeaderValue::from_static("application/octet-stream"))],
         data,
     ))
 }
 
+/// Downloads the content of a crate version
+#[utoipa::path(
+    get,
+    path = "/api/v1/crates/{package}/{version}/download",
+    tag = "crates",
+    params(
+        ("package" = String, Path, description = "The name of the crate"),
+        ("version" = String, Path, description = "The version of the crate"),
+    ),
+    responses(
+        (status = 200, description = "The content of the crate", body = Vec<u8>, content_type = "application/octet-stream"),
+        (status = 206, description = "The requested range of bytes in the content of the crate", body = Vec<u8>, content_type = "application/octet-stream"),
+    )
+)]
 pub async fn api_v1_download_crate(
     auth_data: AuthData,
     State(state): State<Arc<AxumState>>,
     Path(PathInfoCrateVersion { package, version }): Path<PathInfoCrateVersion>,
-) -> Result<(StatusCode, [(HeaderName, HeaderValue); 1], Vec<u8>), (StatusCode, Json<ApiError>)> {
-    match state.application.get_crate_content(&auth_data, &package, &version).await {
-        Ok(data) => Ok((
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, Json<ApiError>)> {
+    crate_content_response(
+        state
+            .application
+            .get_crate_content(&auth_data, &package, &version, requested_range(&headers))
+            .await,
+        &state.application.configuration.load().web_cache_headers,
+    )
+}
+
+/// Gets the single range of bytes requested with the `Range` header, if any
+///
+/// Conditional range requests (`If-Range`) are answered with the whole content.
+fn requested_range(headers: &HeaderMap) -> Option<ByteRange> {
+    if headers.contains_key(header::IF_RANGE) {
+        return None;
+    }
+    headers
+        .get(header::RANGE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(ByteRange::parse)
+}
+
+/// The value of the `Vary` header for the responses that depend on the authentication of the client
+const VARY_AUTHENTICATION: HeaderValue = HeaderValue::from_static("Authorization, Cookie");
+
+/// Builds the response for the download of the content of a crate
+///
+/// The content of a crate version never changes, so that it can be stored for a long time.
+fn crate_content_response(
+    result: Result<CrateContent, ApiError>,
+    cache_headers: &CacheHeadersConfig,
+) -> Result<Response, (StatusCode, Json<ApiError>)> {
+    match result {
+        Ok(CrateContent {
+            content,
+            part: ContentPart::Range(range),
+            total,
+            restricted,
+        }) => Ok((
+            StatusCode::PARTIAL_CONTENT,
+            [
+                (header::CONTENT_TYPE, HeaderValue::from_static("application/octet-stream")),
+                (header::ACCEPT_RANGES, HeaderValue::from_static("bytes")),
+                (
+                    header::CONTENT_RANGE,
+                    HeaderValue::from_str(&format!("bytes {}-{}/{total}", range.start, range.end - 1)).unwrap(),
+                ),
+                (
+                    header::CACHE_CONTROL,
+                    HeaderValue::from_str(&cache_headers.immutable(restricted)).unwrap(),
+                ),
+                (header::VARY, VARY_AUTHENTICATION),
+            ],
+            content,
+        )
+            .into_response()),
+        Ok(CrateContent {
+            part: ContentPart::Unsatisfiable,
+            total,
+            ..
+        }) => Ok((
+            StatusCode::RANGE_NOT_SATISFIABLE,
+            [(
+                header::CONTENT_RANGE,
+                HeaderValue::from_str(&format!("bytes */{total}")).unwrap(),
+            )],
+            response_error(error_range_not_satisfiable(total)).1,
+        )
+            .into_response()),
+        Ok(CrateContent { content, restricted, .. }) => Ok((
             StatusCode::OK,
-            [(header::CONTENT_TYPE, HeaderValue::from_static("application/octet-stream"))],
-            data,
-        )),
+            [
+                (header::CONTENT_TYPE, HeaderValue::from_static("application/octet-stream")),
+                (header::ACCEPT_RANGES, HeaderValue::from_static("bytes")),
+                (
+                    header::CACHE_CONTROL,
+                    HeaderValue::from_str(&cache_headers.immutable(restricted)).unwrap(),
+                ),
+                (header::VARY, VARY_AUTHENTICATION),
+                (header::ETAG, HeaderValue::from_str(&compute_etag(&content)).unwrap()),
+            ],
+            content,
+        )
+            .into_response()),
         Err(mut error) => {
             if error.http == 401 {
                 // map to 403
@@ -797,14 +3027,90 @@ pub async fn api_v1_download_crate(
     }
 }
 
+/// Downloads a crate from a proxied registry
+#[utoipa::path(
+    get,
+    path = "/api/v1/proxy/{registry}/crates/{package}/{version}/download",
+    tag = "crates",
+    params(
+        ("registry" = String, Path, description = "The name of the external registry"),
+        ("package" = String, Path, description = "The name of the crate"),
+        ("version" = String, Path, description = "The version of the crate"),
+    ),
+    responses(
+        (status = 200, description = "The content of the crate", body = Vec<u8>, content_type = "application/octet-stream"),
+        (status = 206, description = "The requested range of bytes in the content of the crate", body = Vec<u8>, content_type = "application/octet-stream"),
+    )
+)]
+pub async fn api_v1_download_proxied_crate(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Path((registry, package, version)): Path<(String, String, String)>,
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, Json<ApiError>)> {
+    crate_content_response(
+        state
+            .application
+            .get_proxied_crate_content(
+                &auth_data,
+                proxied_registry(&registry),
+                &package,
+                &version,
+                requested_range(&headers),
+            )
+            .await,
+        &state.application.configuration.load().web_cache_headers,
+    )
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ReasonQuery {
+    /// The reason for the change, included in the notifications to the owners of the dependent crates
+    reason: Option<String>,
+}
+
+/// Yanks a crate version
+#[utoipa::path(
+    delete,
+    path = "/api/v1/crates/{package}/{version}/yank",
+    tag = "crates",
+    params(
+        ("package" = String, Path, description = "The name of the crate"),
+        ("version" = String, Path, description = "The version of the crate"),
+        ReasonQuery,
+    ),
+    responses(
+        (status = 200, description = "The version was yanked", body = YesNoResult),
+    )
+)]
 pub async fn api_v1_cargo_yank(
     auth_data: AuthData,
     State(state): State<Arc<AxumState>>,
     Path(PathInfoCrateVersion { package, version }): Path<PathInfoCrateVersion>,
+    Query(ReasonQuery { reason }): Query<ReasonQuery>,
 ) -> ApiResult<YesNoResult> {
-    response(state.application.yank_crate_version(&auth_data, &package, &version).await)
+    response(
+        state
+            .application
+            .yank_crate_version(&auth_data, &package, &version, reason.as_deref())
+            .await,
+    )
 }
 
+/// Unyanks a crate version
+#[utoipa::path(
+    put,
+    path = "/api/v1/crates/{package}/{version}/unyank",
+    tag = "crates",
+    params(
+        ("package" = String, Path, description = "The name of the crate"),
+        ("version" = String, Path, description = "The version of the crate"),
+    ),
+    responses(
+        (status = 200, description = "The version was unyanked", body = YesNoResult),
+    )
+)]
 pub async fn api_v1_cargo_unyank(
     auth_data: AuthData,
     State(state): State<Arc<AxumState>>,
@@ -813,6 +3119,19 @@ pub async fn api_v1_cargo_unyank(
     response(state.application.unyank_crate_version(&auth_data, &package, &version).await)
 }
 
+/// Queues the generation of the documentation for a crate version
+#[utoipa::path(
+    post,
+    path = "/api/v1/crates/{package}/{version}/docsregen",
+    tag = "crates",
+    params(
+        ("package" = String, Path, description = "The name of the crate"),
+        ("version" = String, Path, description = "The version of the crate"),
+    ),
+    responses(
+        (status = 200, description = "The queued documentation generation jobs", body = Vec<DocGenJob>),
+    )
+)]
 pub async fn api_v1_regen_crate_version_doc(
     auth_data: AuthData,
     State(state): State<Arc<AxumState>>,
@@ -826,6 +3145,19 @@ pub async fn api_v1_regen_crate_version_doc(
     )
 }
 
+/// Analyzes the dependencies of a crate version
+#[utoipa::path(
+    get,
+    path = "/api/v1/crates/{package}/{version}/checkdeps",
+    tag = "crates",
+    params(
+        ("package" = String, Path, description = "The name of the crate"),
+        ("version" = String, Path, description = "The version of the crate"),
+    ),
+    responses(
+        (status = 200, description = "The analysis of the dependencies", body = DepsAnalysis),
+    )
+)]
 pub async fn api_v1_check_crate_version(
     auth_data: AuthData,
     State(state): State<Arc<AxumState>>,
@@ -839,7 +3171,228 @@ pub async fn api_v1_check_crate_version(
     )
 }
 
+/// Gets the Software Bill of Materials (SBOM) of a crate version, in the `CycloneDX` format
+#[utoipa::path(
+    get,
+    path = "/api/v1/crates/{package}/{version}/sbom",
+    tag = "crates",
+    params(
+        ("package" = String, Path, description = "The name of the crate"),
+        ("version" = String, Path, description = "The version of the crate"),
+    ),
+    responses(
+        (status = 200, description = "The SBOM of the crate version", body = Sbom),
+    )
+)]
+pub async fn api_v1_get_crate_version_sbom(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Path(PathInfoCrateVersion { package, version }): Path<PathInfoCrateVersion>,
+) -> ApiResult<Sbom> {
+    response(state.application.get_crate_version_sbom(&auth_data, &package, &version).await)
+}
+
+/// Gets the checksums (SHA-256 and SHA-512) of the `.crate` file of a crate version
+#[utoipa::path(
+    get,
+    path = "/api/v1/crates/{package}/{version}/checksums",
+    tag = "crates",
+    params(
+        ("package" = String, Path, description = "The name of the crate"),
+        ("version" = String, Path, description = "The version of the crate"),
+    ),
+    responses(
+        (status = 200, description = "The checksums of the crate version", body = CrateVersionChecksums),
+    )
+)]
+pub async fn api_v1_get_crate_version_checksums(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Path(PathInfoCrateVersion { package, version }): Path<PathInfoCrateVersion>,
+) -> ApiResult<CrateVersionChecksums> {
+    response(
+        state
+            .application
+            .get_crate_version_checksums(&auth_data, &package, &version)
+            .await,
+    )
+}
+
+/// Declares the provenance of a crate version, once after its publication
+#[utoipa::path(
+    put,
+    path = "/api/v1/crates/{package}/{version}/provenance",
+    tag = "crates",
+    params(
+        ("package" = String, Path, description = "The name of the crate"),
+        ("version" = String, Path, description = "The version of the crate"),
+    ),
+    request_body = VersionProvenanceDeclaration,
+    responses(
+        (status = 200, description = "The recorded provenance", body = VersionProvenance),
+        (status = 400, description = "The commit does not match the one recorded by cargo", body = ApiError),
+    )
+)]
+pub async fn api_v1_set_crate_version_provenance(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Path(PathInfoCrateVersion { package, version }): Path<PathInfoCrateVersion>,
+    input: Json<VersionProvenanceDeclaration>,
+) -> ApiResult<VersionProvenance> {
+    response(
+        state
+            .application
+            .set_crate_version_provenance(&auth_data, &package, &version, &input)
+            .await,
+    )
+}
+
+/// Gets the documentation coverage for a crate version
+#[utoipa::path(
+    get,
+    path = "/api/v1/crates/{package}/{version}/docscoverage",
+    tag = "crates",
+    params(
+        ("package" = String, Path, description = "The name of the crate"),
+        ("version" = String, Path, description = "The version of the crate"),
+    ),
+    responses(
+        (status = 200, description = "The documentation coverage, if known", body = Option<DocsCoverage>),
+    )
+)]
+pub async fn api_v1_get_crate_docs_coverage(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Path(PathInfoCrateVersion { package, version }): Path<PathInfoCrateVersion>,
+) -> ApiResult<Option<DocsCoverage>> {
+    response(
+        state
+            .application
+            .get_crate_docs_coverage(&auth_data, &package, Some(&version))
+            .await,
+    )
+}
+
+/// Builds the response for a badge
+fn badge_response(svg: String) -> (StatusCode, [(HeaderName, HeaderValue); 2], String) {
+    (
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, HeaderValue::from_static("image/svg+xml")),
+            (header::CACHE_CONTROL, HeaderValue::from_static("no-cache")),
+        ],
+        svg,
+    )
+}
+
+/// Gets the badge for the documentation coverage of the last version of a crate
+pub async fn get_badge_docs_coverage(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Path(PathInfoCrate { package }): Path<PathInfoCrate>,
+) -> Result<(StatusCode, [(HeaderName, HeaderValue); 2], String), (StatusCode, Json<ApiError>)> {
+    let coverage = state
+        .application
+        .get_crate_docs_coverage(&auth_data, &package, None)
+        .await
+        .map_err(response_error)?;
+    let svg = match coverage {
+        None => render_badge("docs coverage", "unknown", COLOR_GREY),
+        Some(coverage) => {
+            let percentage = coverage.percentage();
+            let color = if percentage >= 90.0 {
+                COLOR_GREEN
+            } else if percentage >= 75.0 {
+                COLOR_YELLOW
+            } else if percentage >= 50.0 {
+                COLOR_ORANGE
+            } else {
+                COLOR_RED
+            };
+            render_badge("docs coverage", &format!("{percentage:.0}%"), color)
+        }
+    };
+    Ok(badge_response(svg))
+}
+
+/// Gets the badge for the latest version of a crate
+pub async fn get_badge_version(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Path(PathInfoCrate { package }): Path<PathInfoCrate>,
+) -> Result<(StatusCode, [(HeaderName, HeaderValue); 2], String), (StatusCode, Json<ApiError>)> {
+    let info = state
+        .application
+        .get_crate_info(&auth_data, &package)
+        .await
+        .map_err(response_error)?;
+    let label = &state.application.configuration.load().self_local_name;
+    let svg = match info.latest_version() {
+        None => render_badge(label, "yanked", COLOR_GREY),
+        Some(version) if info.is_deprecated => {
+            render_badge(label, &format!("v{} (deprecated)", version.index.vers), COLOR_ORANGE)
+        }
+        Some(version) => render_badge(label, &format!("v{}", version.index.vers), COLOR_BLUE),
+    };
+    Ok(badge_response(svg))
+}
+
+/// Gets the badge for the status of the documentation of the latest version of a crate
+pub async fn get_badge_docs_status(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Path(PathInfoCrate { package }): Path<PathInfoCrate>,
+) -> Result<(StatusCode, [(HeaderName, HeaderValue); 2], String), (StatusCode, Json<ApiError>)> {
+    let info = state
+        .application
+        .get_crate_info(&auth_data, &package)
+        .await
+        .map_err(response_error)?;
+    let svg = match info.latest_version() {
+        None => render_badge("docs", "unknown", COLOR_GREY),
+        Some(version) => {
+            if version.docs.iter().any(|docs| docs.is_attempted && !docs.is_present) {
+                render_badge("docs", "failing", COLOR_RED)
+            } else if !version.docs.is_empty() && version.docs.iter().all(|docs| docs.is_present) {
+                render_badge("docs", "passing", COLOR_GREEN)
+            } else {
+                render_badge("docs", "pending", COLOR_GREY)
+            }
+        }
+    };
+    Ok(badge_response(svg))
+}
+
+/// Gets the badge for the total number of downloads of a crate
+pub async fn get_badge_downloads(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Path(PathInfoCrate { package }): Path<PathInfoCrate>,
+) -> Result<(StatusCode, [(HeaderName, HeaderValue); 2], String), (StatusCode, Json<ApiError>)> {
+    let info = state
+        .application
+        .get_crate_info(&auth_data, &package)
+        .await
+        .map_err(response_error)?;
+    Ok(badge_response(render_badge(
+        "downloads",
+        &format_count(info.downloads),
+        COLOR_BLUE,
+    )))
+}
+
 /// Gets the download statistics for a crate
+#[utoipa::path(
+    get,
+    path = "/api/v1/crates/{package}/dlstats",
+    tag = "crates",
+    params(
+        ("package" = String, Path, description = "The name of the crate"),
+    ),
+    responses(
+        (status = 200, description = "The download statistics", body = DownloadStats),
+    )
+)]
 pub async fn api_v1_get_crate_dl_stats(
     auth_data: AuthData,
     State(state): State<Arc<AxumState>>,
@@ -848,6 +3401,58 @@ pub async fn api_v1_get_crate_dl_stats(
     response(state.application.get_crate_dl_stats(&auth_data, &package).await)
 }
 
+/// Gets the daily downloads of each version of a crate, compatible with the crates.io API
+#[utoipa::path(
+    get,
+    path = "/api/v1/crates/{package}/downloads",
+    tag = "crates",
+    params(
+        ("package" = String, Path, description = "The name of the crate"),
+    ),
+    responses(
+        (status = 200, description = "The daily downloads of each version", body = CrateDownloads),
+    )
+)]
+pub async fn api_v1_get_crate_downloads(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Path(PathInfoCrate { package }): Path<PathInfoCrate>,
+) -> ApiResult<CrateDownloads> {
+    response(state.application.get_crate_downloads(&auth_data, &package).await)
+}
+
+/// Gets the crates of the registry that depend on a crate
+#[utoipa::path(
+    get,
+    path = "/api/v1/crates/{package}/reverse_dependencies",
+    tag = "crates",
+    params(
+        ("package" = String, Path, description = "The name of the crate"),
+    ),
+    responses(
+        (status = 200, description = "The crates depending on this crate", body = ReverseDependencies),
+    )
+)]
+pub async fn api_v1_get_crate_reverse_dependencies(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Path(PathInfoCrate { package }): Path<PathInfoCrate>,
+) -> ApiResult<ReverseDependencies> {
+    response(state.application.get_crate_reverse_dependencies(&auth_data, &package).await)
+}
+
+/// Gets the owners of a crate
+#[utoipa::path(
+    get,
+    path = "/api/v1/crates/{package}/owners",
+    tag = "crates",
+    params(
+        ("package" = String, Path, description = "The name of the crate"),
+    ),
+    responses(
+        (status = 200, description = "The owners of the crate", body = OwnersQueryResult),
+    )
+)]
 pub async fn api_v1_cargo_get_crate_owners(
     auth_data: AuthData,
     State(state): State<Arc<AxumState>>,
@@ -856,6 +3461,19 @@ pub async fn api_v1_cargo_get_crate_owners(
     response(state.application.get_crate_owners(&auth_data, &package).await)
 }
 
+/// Adds owners to a crate
+#[utoipa::path(
+    put,
+    path = "/api/v1/crates/{package}/owners",
+    tag = "crates",
+    params(
+        ("package" = String, Path, description = "The name of the crate"),
+    ),
+    request_body = OwnersChangeQuery,
+    responses(
+        (status = 200, description = "The owners were added", body = YesNoMsgResult),
+    )
+)]
 pub async fn api_v1_cargo_add_crate_owners(
     auth_data: AuthData,
     State(state): State<Arc<AxumState>>,
@@ -865,6 +3483,19 @@ pub async fn api_v1_cargo_add_crate_owners(
     response(state.application.add_crate_owners(&auth_data, &package, &input.users).await)
 }
 
+/// Removes owners from a crate
+#[utoipa::path(
+    delete,
+    path = "/api/v1/crates/{package}/owners",
+    tag = "crates",
+    params(
+        ("package" = String, Path, description = "The name of the crate"),
+    ),
+    request_body = OwnersChangeQuery,
+    responses(
+        (status = 200, description = "The owners were removed", body = YesNoResult),
+    )
+)]
 pub async fn api_v1_cargo_remove_crate_owners(
     auth_data: AuthData,
     State(state): State<Arc<AxumState>>,
@@ -880,6 +3511,17 @@ pub async fn api_v1_cargo_remove_crate_owners(
 }
 
 /// Gets the targets for a crate
+#[utoipa::path(
+    get,
+    path = "/api/v1/crates/{package}/targets",
+    tag = "crates",
+    params(
+        ("package" = String, Path, description = "The name of the crate"),
+    ),
+    responses(
+        (status = 200, description = "The targets of the crate", body = Vec<CrateInfoTarget>),
+    )
+)]
 pub async fn api_v1_get_crate_targets(
     auth_data: AuthData,
     State(state): State<Arc<AxumState>>,
@@ -889,6 +3531,18 @@ pub async fn api_v1_get_crate_targets(
 }
 
 /// Sets the targets for a crate
+#[utoipa::path(
+    patch,
+    path = "/api/v1/crates/{package}/targets",
+    tag = "crates",
+    params(
+        ("package" = String, Path, description = "The name of the crate"),
+    ),
+    request_body = Vec<CrateInfoTarget>,
+    responses(
+        (status = 200, description = "The targets were updated"),
+    )
+)]
 pub async fn api_v1_set_crate_targets(
     auth_data: AuthData,
     State(state): State<Arc<AxumState>>,
@@ -899,6 +3553,17 @@ pub async fn api_v1_set_crate_targets(
 }
 
 /// Gets the required capabilities for a crate
+#[utoipa::path(
+    get,
+    path = "/api/v1/crates/{package}/capabilities",
+    tag = "crates",
+    params(
+        ("package" = String, Path, description = "The name of the crate"),
+    ),
+    responses(
+        (status = 200, description = "The required capabilities", body = Vec<String>),
+    )
+)]
 pub async fn api_v1_get_crate_required_capabilities(
     auth_data: AuthData,
     State(state): State<Arc<AxumState>>,
@@ -908,6 +3573,18 @@ pub async fn api_v1_get_crate_required_capabilities(
 }
 
 /// Sets the required capabilities for a crate
+#[utoipa::path(
+    patch,
+    path = "/api/v1/crates/{package}/capabilities",
+    tag = "crates",
+    params(
+        ("package" = String, Path, description = "The name of the crate"),
+    ),
+    request_body = Vec<String>,
+    responses(
+        (status = 200, description = "The required capabilities were updated"),
+    )
+)]
 pub async fn api_v1_set_crate_required_capabilities(
     auth_data: AuthData,
     State(state): State<Arc<AxumState>>,
@@ -923,32 +3600,125 @@ pub async fn api_v1_set_crate_required_capabilities(
 }
 
 /// Sets the deprecation status on a crate
+#[utoipa::path(
+    patch,
+    path = "/api/v1/crates/{package}/deprecated",
+    tag = "crates",
+    params(
+        ("package" = String, Path, description = "The name of the crate"),
+        ReasonQuery,
+    ),
+    request_body = bool,
+    responses(
+        (status = 200, description = "The deprecation status was updated"),
+    )
+)]
 pub async fn api_v1_set_crate_deprecation(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Path(PathInfoCrate { package }): Path<PathInfoCrate>,
+    Query(ReasonQuery { reason }): Query<ReasonQuery>,
+    input: Json<bool>,
+) -> ApiResult<()> {
+    response(
+        state
+            .application
+            .set_crate_deprecation(&auth_data, &package, input.0, reason.as_deref())
+            .await,
+    )
+}
+
+/// Gets the visibility of a crate
+#[utoipa::path(
+    get,
+    path = "/api/v1/crates/{package}/visibility",
+    tag = "crates",
+    params(
+        ("package" = String, Path, description = "The name of the crate"),
+    ),
+    responses(
+        (status = 200, description = "The visibility of the crate", body = CrateVisibility),
+    )
+)]
+pub async fn api_v1_get_crate_visibility(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Path(PathInfoCrate { package }): Path<PathInfoCrate>,
+) -> ApiResult<CrateVisibility> {
+    response(state.application.get_crate_visibility(&auth_data, &package).await)
+}
+
+/// Sets the visibility of a crate, either visible to all the users or restricted to its owners and readers
+#[utoipa::path(
+    patch,
+    path = "/api/v1/crates/{package}/visibility",
+    tag = "crates",
+    params(
+        ("package" = String, Path, description = "The name of the crate"),
+    ),
+    request_body = CrateVisibilityQuery,
+    responses(
+        (status = 200, description = "The new visibility of the crate", body = CrateVisibility),
+    )
+)]
+pub async fn api_v1_set_crate_visibility(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Path(PathInfoCrate { package }): Path<PathInfoCrate>,
+    input: Json<CrateVisibilityQuery>,
+) -> ApiResult<CrateVisibility> {
+    response(state.application.set_crate_visibility(&auth_data, &package, &input).await)
+}
+
+/// Sets the frozen status on a crate
+#[utoipa::path(
+    patch,
+    path = "/api/v1/crates/{package}/frozen",
+    tag = "crates",
+    params(
+        ("package" = String, Path, description = "The name of the crate"),
+    ),
+    request_body = bool,
+    responses(
+        (status = 200, description = "The frozen status was updated"),
+    )
+)]
+pub async fn api_v1_set_crate_frozen(
     auth_data: AuthData,
     State(state): State<Arc<AxumState>>,
     Path(PathInfoCrate { package }): Path<PathInfoCrate>,
     input: Json<bool>,
 ) -> ApiResult<()> {
-    response(state.application.set_crate_deprecation(&auth_data, &package, input.0).await)
+    response(state.application.set_crate_frozen(&auth_data, &package, input.0).await)
 }
 
 pub async fn index_serve_inner(
     index: &(dyn Index + Send + Sync),
+    proxy: &(dyn Proxy + Send + Sync),
     path: &str,
-) -> Result<(impl Stream<Item = Result<impl Into<Bytes>, impl Into<BoxError>>>, HeaderValue), ApiError> {
+) -> Result<(Body, HeaderValue), ApiError> {
     let file_path: PathBuf = path.parse()?;
-    let file_path = index.get_index_file(&file_path).await?.ok_or_else(error_not_found)?;
+    if let Some(content) = index.get_index_file_content(&file_path).await? {
+        return Ok((Body::from(content), index_content_type(path)));
+    }
+    // not a local crate, try the proxied registry
+    let file_path = proxy.get_index_file(None, &file_path).await?.ok_or_else(error_not_found)?;
     let file = File::open(file_path).await.map_err(|_e| error_not_found())?;
     let stream = ReaderStream::new(file);
+    Ok((Body::from_stream(stream), index_content_type(path)))
+}
+
+/// Gets the content type for a file in the index
+fn index_content_type(path: &str) -> HeaderValue {
     if std::path::Path::new(path)
         .extension()
-        .map_or(false, |ext| ext.eq_ignore_ascii_case("json"))
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
     {
-        Ok((stream, HeaderValue::from_static("application/json")))
+        HeaderValue::from_static("application/json")
     } else if path == "/HEAD" || path.starts_with("/info") {
-        Ok((stream, HeaderValue::from_static("text/plain; charset=utf-8")))
+        HeaderValue::from_static("text/plain; charset=utf-8")
     } else {
-        Ok((stream, HeaderValue::from_static("application/octet-stream")))
+        HeaderValue::from_static("application/octet-stream")
     }
 }
 
@@ -974,7 +3744,7 @@ pub async fn index_serve_check_auth(
     application
         .authenticate(auth_data)
         .await
-        .map_err(|e| index_serve_map_err(e, &application.configuration.web_domain))?;
+        .map_err(|e| index_serve_map_err(e, &application.configuration.load().web_domain))?;
     Ok(())
 }
 
@@ -982,23 +3752,113 @@ pub async fn index_serve(
     auth_data: AuthData,
     State(state): State<Arc<AxumState>>,
     request: Request<Body>,
-) -> Result<(StatusCode, [(HeaderName, HeaderValue); 2], Body), (StatusCode, [(HeaderName, HeaderValue); 2], Json<ApiError>)> {
-    let map_err = |e| index_serve_map_err(e, &state.application.configuration.web_domain);
+) -> Result<(StatusCode, [(HeaderName, HeaderValue); 3], Body), (StatusCode, [(HeaderName, HeaderValue); 2], Json<ApiError>)> {
+    let configuration = state.application.configuration.load_full();
+    let map_err = |e| index_serve_map_err(e, &configuration.web_domain);
     let path = request.uri().path();
-    if path != "/config.json" && !state.application.configuration.index.allow_protocol_sparse {
+    if path != "/config.json" && !configuration.index.allow_protocol_sparse {
         // config.json is always allowed because it is always checked first by cargo
         return Err(map_err(error_not_found()));
     }
     index_serve_check_auth(&state.application, &auth_data).await?;
-    let (stream, content_type) = index_serve_inner(state.application.get_service_index(), path)
-        .await
-        .map_err(map_err)?;
-    let body = Body::from_stream(stream);
+    let restricted = if let Some(package) = path.rsplit('/').next().filter(|_| path != "/config.json") {
+        // the files of the restricted crates are hidden from the users that cannot read them
+        state
+            .application
+            .check_can_read_crate(&auth_data, package)
+            .await
+            .map_err(map_err)?
+    } else {
+        false
+    };
+    let (body, content_type) = index_serve_inner(
+        state.application.get_service_index(),
+        state.application.get_service_proxy(),
+        path,
+    )
+    .await
+    .map_err(map_err)?;
     Ok((
         StatusCode::OK,
         [
             (header::CONTENT_TYPE, content_type),
-            (header::CACHE_CONTROL, HeaderValue::from_static("no-cache")),
+            (
+                header::CACHE_CONTROL,
+                HeaderValue::from_str(&configuration.web_cache_headers.index(restricted)).unwrap(),
+            ),
+            (header::VARY, VARY_AUTHENTICATION),
+        ],
+        body,
+    ))
+}
+
+/// Serves the sparse index of crates.io, proxied and cached locally
+pub async fn index_serve_crates_io(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Path(path): Path<String>,
+) -> Result<(StatusCode, [(HeaderName, HeaderValue); 3], Body), (StatusCode, [(HeaderName, HeaderValue); 2], Json<ApiError>)> {
+    index_serve_proxied(&state, &auth_data, None, &path).await
+}
+
+/// Serves the sparse index of a proxied external registry
+pub async fn index_serve_proxy(
+    auth_data: AuthData,
+    State(state): State<Arc<AxumState>>,
+    Path((registry, path)): Path<(String, String)>,
+) -> Result<(StatusCode, [(HeaderName, HeaderValue); 3], Body), (StatusCode, [(HeaderName, HeaderValue); 2], Json<ApiError>)> {
+    index_serve_proxied(&state, &auth_data, proxied_registry(&registry), &path).await
+}
+
+/// Gets the proxied registry designated by a name in a path, `None` for crates.io
+fn proxied_registry(name: &str) -> Option<&str> {
+    (name != CRATES_IO_NAME).then_some(name)
+}
+
+/// Serves the sparse index of a proxied registry
+async fn index_serve_proxied(
+    state: &AxumState,
+    auth_data: &AuthData,
+    registry: Option<&str>,
+    path: &str,
+) -> Result<(StatusCode, [(HeaderName, HeaderValue); 3], Body), (StatusCode, [(HeaderName, HeaderValue); 2], Json<ApiError>)> {
+    let configuration = state.application.configuration.load();
+    let map_err = |e| index_serve_map_err(e, &configuration.web_domain);
+    let proxy = state.application.get_service_proxy();
+    if !configuration.index.allow_protocol_sparse || !proxy.is_proxied(registry) {
+        return Err(map_err(error_not_found()));
+    }
+    index_serve_check_auth(&state.application, auth_data).await?;
+    let path = format!("/{path}");
+    let body = if path == "/config.json" {
+        // downloads go through this registry, publishing is not possible
+        let config = serde_json::json!({
+            "dl": format!(
+                "{}/api/v1/proxy/{}/crates",
+                configuration.web_public_uri,
+                registry.unwrap_or(CRATES_IO_NAME)
+            ),
+            "auth-required": true,
+        });
+        Body::from(config.to_string())
+    } else {
+        let file_path = proxy
+            .get_index_file(registry, std::path::Path::new(&path))
+            .await
+            .map_err(map_err)?
+            .ok_or_else(|| map_err(error_not_found()))?;
+        let file = File::open(file_path).await.map_err(|_e| map_err(error_not_found()))?;
+        Body::from_stream(ReaderStream::new(file))
+    };
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, index_content_type(&path)),
+            (
+                header::CACHE_CONTROL,
+                HeaderValue::from_str(&configuration.web_cache_headers.index(false)).unwrap(),
+            ),
+            (header::VARY, VARY_AUTHENTICATION),
         ],
         body,
     ))
@@ -1010,8 +3870,9 @@ pub async fn index_serve_info_refs(
     State(state): State<Arc<AxumState>>,
     Query(query): Query<HashMap<String, String>>,
 ) -> Result<(StatusCode, [(HeaderName, HeaderValue); 2], Body), (StatusCode, [(HeaderName, HeaderValue); 2], Json<ApiError>)> {
-    let map_err = |e| index_serve_map_err(e, &state.application.configuration.web_domain);
-    if !state.application.configuration.index.allow_protocol_git {
+    let configuration = state.application.configuration.load_full();
+    let map_err = |e| index_serve_map_err(e, &configuration.web_domain);
+    if !configuration.index.allow_protocol_git {
         return Err(map_err(error_not_found()));
     }
     index_serve_check_auth(&state.application, &auth_data).await?;
@@ -1046,8 +3907,9 @@ pub async fn index_serve_git_upload_pack(
     State(state): State<Arc<AxumState>>,
     body: Bytes,
 ) -> Result<(StatusCode, [(HeaderName, HeaderValue); 2], Body), (StatusCode, [(HeaderName, HeaderValue); 2], Json<ApiError>)> {
-    let map_err = |e| index_serve_map_err(e, &state.application.configuration.web_domain);
-    if !state.application.configuration.index.allow_protocol_git {
+    let configuration = state.application.configuration.load_full();
+    let map_err = |e| index_serve_map_err(e, &configuration.web_domain);
+    if !configuration.index.allow_protocol_git {
         return Err(map_err(error_not_found()));
     }
     index_serve_check_auth(&state.application, &auth_data).await?;
@@ -1075,9 +3937,50 @@ pub async fn index_serve_git_upload_pack(
 /// # Errors
 ///
 /// Always return the `Ok` variant, but use `Result` for possible future usage.
+#[utoipa::path(
+    get,
+    path = "/api/v1/version",
+    tag = "registry",
+    responses(
+        (status = 200, description = "The version of the registry", body = AppVersion),
+    )
+)]
 pub async fn get_version() -> ApiResult<AppVersion> {
     response(Ok(AppVersion {
         commit: crate::GIT_HASH.to_string(),
         tag: crate::GIT_TAG.to_string(),
     }))
 }
+
+/// Gets whether the registry is alive, without checking the services it depends on
+///
+/// # Errors
+///
+/// Always return the `Ok` variant, but use `Result` for possible future usage.
+#[utoipa::path(
+    get,
+    path = "/healthz",
+    tag = "registry",
+    responses(
+        (status = 200, description = "The registry is running", body = LivenessReport),
+    )
+)]
+pub async fn get_liveness(State(state): State<Arc<AxumState>>) -> ApiResult<LivenessReport> {
+    response(Ok(state.application.get_liveness()))
+}
+
+/// Gets whether the registry is ready to serve requests, after checking the database, the storage and the index
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    tag = "registry",
+    responses(
+        (status = 200, description = "All the services are healthy", body = HealthReport),
+        (status = 503, description = "At least one service is unhealthy", body = HealthReport),
+    )
+)]
+pub async fn get_readiness(State(state): State<Arc<AxumState>>) -> (StatusCode, Json<HealthReport>) {
+    let report = state.application.get_readiness().await;
+    let http = if report.healthy { 200 } else { 503 };
+    response_ok_http(http, report)
+}
@@ -5,7 +5,7 @@ CREATE TABLE IF NOT EXISTS SchemaMetadata (
 
 CREATE INDEX IF NOT EXISTS SchemaMetadataIndex ON SchemaMetadata(name);
 
-INSERT INTO SchemaMetadata VALUES ('version', '1.10.0');
+INSERT INTO SchemaMetadata VALUES ('version', '1.40.0');
 
 CREATE TABLE RegistryUser (
     id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
@@ -13,7 +13,9 @@ CREATE TABLE RegistryUser (
     email TEXT NOT NULL,
     login TEXT NOT NULL,
     name TEXT NOT NULL,
-    roles TEXT NOT NULL
+    roles TEXT NOT NULL,
+    requestCount INTEGER NOT NULL DEFAULT 0,
+    bytesServed INTEGER NOT NULL DEFAULT 0
 );
 
 CREATE INDEX IndexRegistryUserByEmail ON RegistryUser (email);
@@ -26,16 +28,75 @@ CREATE TABLE RegistryUserToken (
     token TEXT NOT NULL,
     lastUsed TIMESTAMP NOT NULL,
     canWrite BOOLEAN NOT NULL,
-    canAdmin BOOLEAN NOT NULL
+    canAdmin BOOLEAN NOT NULL,
+    requestCount INTEGER NOT NULL DEFAULT 0,
+    bytesServed INTEGER NOT NULL DEFAULT 0
 );
 
 CREATE INDEX IndexRegistryUserToken ON RegistryUserToken (user);
 
+CREATE TABLE ProvisioningRule (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    domain TEXT NOT NULL UNIQUE,
+    roles TEXT NOT NULL
+);
+
+CREATE TABLE Impersonation (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    impersonator INTEGER NOT NULL REFERENCES RegistryUser(id),
+    user INTEGER NOT NULL REFERENCES RegistryUser(id),
+    token TEXT NOT NULL,
+    canWrite BOOLEAN NOT NULL,
+    reason TEXT NOT NULL,
+    createdOn TIMESTAMP NOT NULL,
+    expiresOn TIMESTAMP NOT NULL
+);
+
+CREATE INDEX IndexImpersonation ON Impersonation (user);
+
+CREATE TABLE UserQuota (
+    user INTEGER NOT NULL PRIMARY KEY REFERENCES RegistryUser(id),
+    maxBytes INTEGER,
+    maxCrates INTEGER
+);
+
+CREATE TABLE TeamQuota (
+    team TEXT NOT NULL PRIMARY KEY,
+    maxBytes INTEGER NOT NULL,
+    maxCrates INTEGER NOT NULL
+);
+
+CREATE TABLE UserPreferences (
+    user INTEGER NOT NULL PRIMARY KEY REFERENCES RegistryUser(id),
+    notifyOwnerInvitations BOOLEAN NOT NULL,
+    notifyDependencies BOOLEAN NOT NULL,
+    notifyDocsFailures BOOLEAN NOT NULL,
+    notifyWatches BOOLEAN NOT NULL,
+    tokenCanWrite BOOLEAN NOT NULL,
+    tokenCanAdmin BOOLEAN NOT NULL
+);
+
+CREATE TABLE AuditLog (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    timestamp TIMESTAMP NOT NULL,
+    actor INTEGER,
+    impersonator INTEGER,
+    action TEXT NOT NULL,
+    details TEXT NOT NULL,
+    clientIp TEXT,
+    requestId TEXT,
+    clientRequestId TEXT
+);
+
+CREATE INDEX IndexAuditLog ON AuditLog (timestamp);
+
 CREATE TABLE RegistryGlobalToken (
     id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
     name TEXT NOT NULL,
     token TEXT NOT NULL,
-    lastUsed TIMESTAMP NOT NULL
+    lastUsed TIMESTAMP NOT NULL,
+    requestCount INTEGER NOT NULL DEFAULT 0,
+    bytesServed INTEGER NOT NULL DEFAULT 0
 );
 
 CREATE TABLE Package (
@@ -44,7 +105,11 @@ CREATE TABLE Package (
     targets TEXT NOT NULL,
     nativeTargets TEXT NOT NULL,
     capabilities TEXT NOT NULL,
-    isDeprecated BOOLEAN NOT NULL
+    isDeprecated BOOLEAN NOT NULL,
+    isFrozen BOOLEAN NOT NULL,
+    isClaimedUpstream BOOLEAN NOT NULL,
+    isRestricted BOOLEAN NOT NULL DEFAULT FALSE,
+    deprecationReason TEXT
 );
 
 CREATE INDEX IndexPackage ON Package (name);
@@ -57,6 +122,22 @@ CREATE TABLE PackageOwner (
 
 CREATE INDEX IndexPackageOwner ON PackageOwner (package);
 
+CREATE TABLE PackageReader (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    package TEXT NOT NULL REFERENCES Package(name),
+    user INTEGER NOT NULL REFERENCES RegistryUser(id)
+);
+
+CREATE INDEX IndexPackageReader ON PackageReader (package);
+
+CREATE TABLE PackageReaderTeam (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    package TEXT NOT NULL REFERENCES Package(name),
+    team TEXT NOT NULL
+);
+
+CREATE INDEX IndexPackageReaderTeam ON PackageReaderTeam (package);
+
 CREATE TABLE PackageVersion (
     id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
     package TEXT NOT NULL REFERENCES Package(name),
@@ -69,7 +150,16 @@ CREATE TABLE PackageVersion (
     downloads BLOB,
     depsLastCheck TIMESTAMP NOT NULL,
     depsHasOutdated BOOLEAN NOT NULL,
-    depsHasCVEs BOOLEAN NOT NULL
+    depsHasCVEs BOOLEAN NOT NULL,
+    size INTEGER,
+    quarantineUntil TIMESTAMP,
+    provenanceCommit TEXT,
+    provenanceCiRunUrl TEXT,
+    provenanceBuilder TEXT,
+    provenanceVerified BOOLEAN NOT NULL DEFAULT FALSE,
+    checksumSha256 TEXT,
+    checksumSha512 TEXT,
+    yankReason TEXT
 );
 
 CREATE INDEX IndexPackageVersion ON PackageVersion(package);
@@ -80,11 +170,53 @@ CREATE TABLE PackageVersionDocs (
     version TEXT NOT NULL,
     target TEXT NOT NULL,
     isAttempted BOOLEAN NOT NULL,
-    isPresent BOOLEAN NOT NULL
+    isPresent BOOLEAN NOT NULL,
+    coverageTotal INTEGER,
+    coverageDocumented INTEGER,
+    size INTEGER
 );
 
 CREATE INDEX IndexPackageVersionDocs ON PackageVersionDocs(package);
 
+CREATE TABLE PackageVersionBinary (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    package TEXT NOT NULL REFERENCES Package(name),
+    version TEXT NOT NULL,
+    target TEXT NOT NULL,
+    size INTEGER NOT NULL,
+    checksum TEXT NOT NULL,
+    uploadedBy INTEGER NOT NULL REFERENCES RegistryUser(id),
+    upload TIMESTAMP NOT NULL
+);
+
+CREATE UNIQUE INDEX IndexPackageVersionBinary ON PackageVersionBinary(package, version, target);
+
+CREATE TABLE PackageVersionAdvisory (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    package TEXT NOT NULL REFERENCES Package(name),
+    version TEXT NOT NULL,
+    advisory TEXT NOT NULL,
+    summary TEXT NOT NULL,
+    affectedPackage TEXT NOT NULL,
+    affectedVersion TEXT NOT NULL,
+    isDirect BOOLEAN NOT NULL,
+    detectedOn TIMESTAMP NOT NULL
+);
+
+CREATE INDEX IndexPackageVersionAdvisory ON PackageVersionAdvisory(package, version);
+
+CREATE TABLE PackageDependency (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    package TEXT NOT NULL REFERENCES Package(name),
+    version TEXT NOT NULL,
+    dependency TEXT NOT NULL,
+    versionReq TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    optional BOOLEAN NOT NULL
+);
+
+CREATE INDEX IndexPackageDependency ON PackageDependency (dependency);
+
 CREATE TABLE DocGenJob (
     id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
     package TEXT NOT NULL REFERENCES Package(name),
@@ -103,3 +235,70 @@ CREATE TABLE DocGenJob (
 );
 
 CREATE INDEX IndexDocGenJob ON DocGenJob (package);
+
+CREATE TABLE Webhook (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    package TEXT REFERENCES Package(name),
+    url TEXT NOT NULL,
+    secret TEXT NOT NULL,
+    events TEXT NOT NULL,
+    createdBy INTEGER NOT NULL REFERENCES RegistryUser(id),
+    createdOn TIMESTAMP NOT NULL
+);
+
+CREATE INDEX IndexWebhook ON Webhook (package);
+
+CREATE TABLE WebhookDelivery (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    webhook INTEGER NOT NULL REFERENCES Webhook(id),
+    event TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    attempts INTEGER NOT NULL,
+    isDelivered BOOLEAN NOT NULL,
+    statusCode INTEGER,
+    error TEXT,
+    createdOn TIMESTAMP NOT NULL,
+    lastAttempt TIMESTAMP
+);
+
+CREATE INDEX IndexWebhookDelivery ON WebhookDelivery (webhook);
+
+CREATE TABLE PackageWatch (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    package TEXT NOT NULL REFERENCES Package(name),
+    user INTEGER NOT NULL REFERENCES RegistryUser(id)
+);
+
+CREATE INDEX IndexPackageWatch ON PackageWatch (package);
+CREATE INDEX IndexPackageWatchUser ON PackageWatch (user);
+
+CREATE TABLE PackageStar (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    package TEXT NOT NULL REFERENCES Package(name),
+    user INTEGER NOT NULL REFERENCES RegistryUser(id)
+);
+
+CREATE INDEX IndexPackageStar ON PackageStar (package);
+CREATE INDEX IndexPackageStarUser ON PackageStar (user);
+CREATE UNIQUE INDEX IndexPackageStarUnique ON PackageStar (package, user);
+
+CREATE TABLE UserNotification (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    user INTEGER NOT NULL REFERENCES RegistryUser(id),
+    package TEXT NOT NULL REFERENCES Package(name),
+    version TEXT,
+    event TEXT NOT NULL,
+    details TEXT NOT NULL,
+    createdOn TIMESTAMP NOT NULL,
+    isRead BOOLEAN NOT NULL
+);
+
+CREATE INDEX IndexUserNotification ON UserNotification (user);
+
+CREATE TABLE Announcement (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    message TEXT NOT NULL,
+    level TEXT NOT NULL,
+    startsOn TIMESTAMP,
+    endsOn TIMESTAMP
+);
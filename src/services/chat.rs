@@ -0,0 +1,152 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Service to post notifications to chat services (Slack and Microsoft Teams)
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::error;
+use serde_json::{json, Value};
+
+use crate::model::config::Configuration;
+use crate::utils::apierror::{error_backend_failure, specialize, ApiError};
+use crate::utils::FaillibleFuture;
+
+/// The timeout for posting a message
+const POST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A message to be posted to the chat services
+#[derive(Debug, Clone)]
+pub struct ChatMessage {
+    /// The title of the message
+    pub title: String,
+    /// The details, as lines of text
+    pub lines: Vec<String>,
+    /// The link to the related page in the registry
+    pub link: String,
+}
+
+/// The service to post notifications to chat services
+pub trait ChatNotifier {
+    /// Posts a message to all the configured chat services
+    fn notify<'a>(&'a self, message: &'a ChatMessage) -> FaillibleFuture<'a, ()>;
+}
+
+/// Gets the chat notifier service
+///
+/// # Panics
+///
+/// Panics when the HTTP client cannot be built
+#[must_use]
+pub fn get_service(config: Arc<Configuration>) -> Arc<dyn ChatNotifier + Send + Sync> {
+    Arc::new(ChatNotifierImpl {
+        config,
+        client: crate::utils::http_client_builder()
+            .build()
+            .expect("failed to build the HTTP client for chat notifications"),
+    })
+}
+
+/// The service to post notifications to chat services
+#[derive(Debug, Clone)]
+struct ChatNotifierImpl {
+    /// The configuration
+    config: Arc<Configuration>,
+    /// The HTTP client
+    client: reqwest::Client,
+}
+
+impl ChatNotifier for ChatNotifierImpl {
+    fn notify<'a>(&'a self, message: &'a ChatMessage) -> FaillibleFuture<'a, ()> {
+        Box::pin(async move {
+            if let Some(uri) = &self.config.chat.slack_webhook {
+                if let Err(e) = self.post(uri, &slack_payload(message)).await {
+                    error!("failed to post to Slack: {e}");
+                }
+            }
+            if let Some(uri) = &self.config.chat.teams_webhook {
+                if let Err(e) = self.post(uri, &teams_payload(message)).await {
+                    error!("failed to post to Microsoft Teams: {e}");
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+impl ChatNotifierImpl {
+    /// Posts a payload to an incoming webhook
+    async fn post(&self, uri: &str, payload: &Value) -> Result<(), ApiError> {
+        let response = self
+            .client
+            .post(uri)
+            .timeout(POST_TIMEOUT)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(payload.to_string())
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(specialize(
+                error_backend_failure(),
+                format!("the webhook responded with status {}", response.status()),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Builds the payload for a Slack incoming webhook, using blocks
+fn slack_payload(message: &ChatMessage) -> Value {
+    let mut text = format!("*<{}|{}>*", message.link, message.title);
+    for line in &message.lines {
+        text.push('\n');
+        text.push_str(line);
+    }
+    json!({
+        "text": message.title,
+        "blocks": [
+            {
+                "type": "section",
+                "text": { "type": "mrkdwn", "text": text }
+            }
+        ]
+    })
+}
+
+/// Builds the payload for a Microsoft Teams incoming webhook, using an adaptive card
+fn teams_payload(message: &ChatMessage) -> Value {
+    let mut body = vec![json!({
+        "type": "TextBlock",
+        "text": message.title,
+        "weight": "Bolder",
+        "size": "Medium",
+        "wrap": true
+    })];
+    body.extend(message.lines.iter().map(|line| {
+        json!({
+            "type": "TextBlock",
+            "text": line,
+            "wrap": true,
+            "spacing": "Small"
+        })
+    }));
+    json!({
+        "type": "message",
+        "attachments": [
+            {
+                "contentType": "application/vnd.microsoft.card.adaptive",
+                "content": {
+                    "$schema": "http://adaptivecards.io/schemas/adaptive-card.json",
+                    "type": "AdaptiveCard",
+                    "version": "1.4",
+                    "body": body,
+                    "actions": [
+                        { "type": "Action.OpenUrl", "title": "Open in Cratery", "url": message.link }
+                    ]
+                }
+            }
+        ]
+    })
+}
@@ -8,26 +8,74 @@
 use chrono::Local;
 
 use super::Database;
-use crate::model::auth::{RegistryUserToken, RegistryUserTokenWithSecret};
+use crate::model::auth::{ProvisioningRule, RegistryUserToken, RegistryUserTokenWithSecret};
+use crate::model::pagination::{filter_to_like_pattern, Page, PageMeta, Pagination, TokenSort};
 use crate::utils::apierror::{error_invalid_request, specialize, ApiError};
 use crate::utils::token::{generate_token, hash_token};
 
 impl Database {
-    /// Gets the global tokens for the registry, usually for CI purposes
-    pub async fn get_global_tokens(&self) -> Result<Vec<RegistryUserToken>, ApiError> {
-        let rows = sqlx::query!("SELECT id, name, lastUsed AS last_used FROM RegistryGlobalToken ORDER BY id",)
+    /// Checks the integrity of the database and of its foreign keys, returning the problems
+    pub async fn check_consistency(&self) -> Result<Vec<String>, ApiError> {
+        let mut problems = sqlx::query_scalar::<_, String>("PRAGMA integrity_check")
             .fetch_all(&mut *self.transaction.borrow().await)
             .await?;
-        Ok(rows
-            .into_iter()
-            .map(|row| RegistryUserToken {
-                id: row.id,
-                name: row.name,
-                last_used: row.last_used,
-                can_write: false,
-                can_admin: false,
-            })
-            .collect())
+        problems.retain(|problem| problem != "ok");
+        let rows =
+            sqlx::query_as::<_, (String, Option<i64>, String)>("SELECT \"table\", rowid, parent FROM pragma_foreign_key_check")
+                .fetch_all(&mut *self.transaction.borrow().await)
+                .await?;
+        problems.extend(rows.into_iter().map(|(table, rowid, parent)| {
+            format!(
+                "row {} in {table} references a missing row in {parent}",
+                rowid.unwrap_or_default()
+            )
+        }));
+        Ok(problems)
+    }
+
+    /// Gets a page of the global tokens for the registry, usually for CI purposes
+    pub async fn get_global_tokens(
+        &self,
+        filter: Option<&str>,
+        pagination: Pagination<TokenSort>,
+    ) -> Result<Page<RegistryUserToken>, ApiError> {
+        let pattern = filter_to_like_pattern(filter);
+        let total = sqlx::query!(
+            "SELECT COUNT(id) AS total FROM RegistryGlobalToken WHERE name LIKE $1 ESCAPE '\\'",
+            pattern
+        )
+        .fetch_one(&mut *self.transaction.borrow().await)
+        .await?
+        .total;
+        let sort = pagination.sort.sql_key();
+        let (limit, offset) = pagination.sql_bounds();
+        let rows = sqlx::query!(
+            "SELECT id, name, lastUsed AS last_used FROM RegistryGlobalToken
+            WHERE name LIKE $1 ESCAPE '\\'
+            ORDER BY CASE WHEN $2 = 'name' THEN name END, CASE WHEN $2 = 'last-used' THEN lastUsed END DESC, CASE WHEN $2 = 'newest' THEN id END DESC, id
+            LIMIT $3 OFFSET $4",
+            pattern,
+            sort,
+            limit,
+            offset
+        )
+        .fetch_all(&mut *self.transaction.borrow().await)
+        .await?;
+        Ok(Page {
+            items: rows
+                .into_iter()
+                .map(|row| RegistryUserToken {
+                    id: row.id,
+                    name: row.name,
+                    last_used: row.last_used,
+                    can_write: false,
+                    can_admin: false,
+                })
+                .collect(),
+            meta: PageMeta {
+                total: usize::try_from(total).unwrap_or_default(),
+            },
+        })
     }
 
     /// Creates a global token for the registry
@@ -70,4 +118,46 @@ impl Database {
             .await?;
         Ok(())
     }
+
+    /// Gets the rules for provisioning new users
+    pub async fn get_provisioning_rules(&self) -> Result<Vec<ProvisioningRule>, ApiError> {
+        let rules = sqlx::query_as!(ProvisioningRule, "SELECT domain, roles FROM ProvisioningRule ORDER BY domain")
+            .fetch_all(&mut *self.transaction.borrow().await)
+            .await?;
+        Ok(rules)
+    }
+
+    /// Replaces the rules for provisioning new users
+    pub async fn set_provisioning_rules(&self, rules: &[ProvisioningRule]) -> Result<(), ApiError> {
+        sqlx::query!("DELETE FROM ProvisioningRule")
+            .execute(&mut *self.transaction.borrow().await)
+            .await?;
+        for rule in rules {
+            let rule = rule.normalized();
+            if rule.domain.is_empty() || rule.domain.contains('@') {
+                return Err(specialize(
+                    error_invalid_request(),
+                    format!("invalid domain for a provisioning rule: {}", rule.domain),
+                ));
+            }
+            let exists = sqlx::query!("SELECT id FROM ProvisioningRule WHERE domain = $1 LIMIT 1", rule.domain)
+                .fetch_optional(&mut *self.transaction.borrow().await)
+                .await?
+                .is_some();
+            if exists {
+                return Err(specialize(
+                    error_invalid_request(),
+                    format!("duplicate provisioning rule for {}", rule.domain),
+                ));
+            }
+            sqlx::query!(
+                "INSERT INTO ProvisioningRule (domain, roles) VALUES ($1, $2)",
+                rule.domain,
+                rule.roles
+            )
+            .execute(&mut *self.transaction.borrow().await)
+            .await?;
+        }
+        Ok(())
+    }
 }
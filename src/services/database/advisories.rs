@@ -0,0 +1,132 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Service for persisting information in the database
+//! API related to the advisories affecting hosted crates
+
+use super::Database;
+use crate::model::deps::CrateVersionAdvisory;
+use crate::utils::apierror::ApiError;
+
+impl Database {
+    /// Gets the advisories affecting the versions of a crate
+    pub async fn get_crate_advisories(&self, package: &str) -> Result<Vec<CrateVersionAdvisory>, ApiError> {
+        let rows = sqlx::query!(
+            "SELECT package, version, advisory, summary, affectedPackage AS affected_package, affectedVersion AS affected_version,
+                    isDirect AS is_direct, detectedOn AS detected_on
+            FROM PackageVersionAdvisory
+            WHERE package = $1
+            ORDER BY id",
+            package
+        )
+        .fetch_all(&mut *self.transaction.borrow().await)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| CrateVersionAdvisory {
+                package: row.package,
+                version: row.version,
+                advisory: row.advisory,
+                summary: row.summary,
+                affected_package: row.affected_package,
+                affected_version: row.affected_version,
+                is_direct: row.is_direct,
+                detected_on: row.detected_on,
+            })
+            .collect())
+    }
+
+    /// Gets the advisories affecting all the hosted crates
+    pub async fn get_all_advisories(&self) -> Result<Vec<CrateVersionAdvisory>, ApiError> {
+        let rows = sqlx::query!(
+            "SELECT package, version, advisory, summary, affectedPackage AS affected_package, affectedVersion AS affected_version,
+                    isDirect AS is_direct, detectedOn AS detected_on
+            FROM PackageVersionAdvisory
+            ORDER BY package, id"
+        )
+        .fetch_all(&mut *self.transaction.borrow().await)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| CrateVersionAdvisory {
+                package: row.package,
+                version: row.version,
+                advisory: row.advisory,
+                summary: row.summary,
+                affected_package: row.affected_package,
+                affected_version: row.affected_version,
+                is_direct: row.is_direct,
+                detected_on: row.detected_on,
+            })
+            .collect())
+    }
+
+    /// Saves the advisories found for a crate version, either directly against it or against its dependencies
+    ///
+    /// The advisories that are no longer found are removed and the ones that were already known keep their detection time.
+    /// Returns the newly found advisories.
+    pub async fn set_crate_version_advisories(
+        &self,
+        package: &str,
+        version: &str,
+        is_direct: bool,
+        advisories: Vec<CrateVersionAdvisory>,
+    ) -> Result<Vec<CrateVersionAdvisory>, ApiError> {
+        let existing = sqlx::query!(
+            "SELECT id, advisory, affectedPackage AS affected_package, affectedVersion AS affected_version
+            FROM PackageVersionAdvisory
+            WHERE package = $1 AND version = $2 AND isDirect = $3",
+            package,
+            version,
+            is_direct
+        )
+        .fetch_all(&mut *self.transaction.borrow().await)
+        .await?;
+        for row in &existing {
+            if !advisories.iter().any(|advisory| {
+                advisory.advisory == row.advisory
+                    && advisory.affected_package == row.affected_package
+                    && advisory.affected_version == row.affected_version
+            }) {
+                sqlx::query!("DELETE FROM PackageVersionAdvisory WHERE id = $1", row.id)
+                    .execute(&mut *self.transaction.borrow().await)
+                    .await?;
+            }
+        }
+        let mut found = Vec::new();
+        for advisory in advisories {
+            if existing.iter().any(|row| {
+                advisory.advisory == row.advisory
+                    && advisory.affected_package == row.affected_package
+                    && advisory.affected_version == row.affected_version
+            }) {
+                continue;
+            }
+            sqlx::query!(
+                "INSERT INTO PackageVersionAdvisory (package, version, advisory, summary, affectedPackage, affectedVersion, isDirect, detectedOn)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+                package,
+                version,
+                advisory.advisory,
+                advisory.summary,
+                advisory.affected_package,
+                advisory.affected_version,
+                is_direct,
+                advisory.detected_on
+            )
+            .execute(&mut *self.transaction.borrow().await)
+            .await?;
+            found.push(advisory);
+        }
+        Ok(found)
+    }
+
+    /// Marks the dependency analysis of all the crate versions as stale, so that they are analyzed again
+    pub async fn reset_deps_analysis(&self) -> Result<(), ApiError> {
+        sqlx::query!("UPDATE PackageVersion SET depsLastCheck = 0")
+            .execute(&mut *self.transaction.borrow().await)
+            .await?;
+        Ok(())
+    }
+}
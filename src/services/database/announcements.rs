@@ -0,0 +1,50 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Service for persisting information in the database
+//! API related to the announcement published by the administrators
+
+use super::Database;
+use crate::model::announcements::Announcement;
+use crate::utils::apierror::ApiError;
+
+impl Database {
+    /// Gets the announcement set by the administrators, if any, whether it is currently displayed or not
+    pub async fn get_announcement(&self) -> Result<Option<Announcement>, ApiError> {
+        let row = sqlx::query!("SELECT message, level, startsOn AS starts_on, endsOn AS ends_on FROM Announcement LIMIT 1")
+            .fetch_optional(&mut *self.transaction.borrow().await)
+            .await?;
+        Ok(row.map(|row| Announcement {
+            message: row.message,
+            level: row.level.parse().unwrap_or_default(),
+            starts_on: row.starts_on,
+            ends_on: row.ends_on,
+        }))
+    }
+
+    /// Sets the announcement, replacing the previous one
+    pub async fn set_announcement(&self, announcement: &Announcement) -> Result<(), ApiError> {
+        announcement.validate()?;
+        self.clear_announcement().await?;
+        let level = announcement.level.as_str();
+        sqlx::query!(
+            "INSERT INTO Announcement (message, level, startsOn, endsOn) VALUES ($1, $2, $3, $4)",
+            announcement.message,
+            level,
+            announcement.starts_on,
+            announcement.ends_on
+        )
+        .execute(&mut *self.transaction.borrow().await)
+        .await?;
+        Ok(())
+    }
+
+    /// Clears the announcement
+    pub async fn clear_announcement(&self) -> Result<(), ApiError> {
+        sqlx::query!("DELETE FROM Announcement")
+            .execute(&mut *self.transaction.borrow().await)
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,101 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Service for persisting information in the database
+//! API related to the audit log
+
+use chrono::Local;
+
+use super::Database;
+use crate::model::audit::AuditLogEntry;
+use crate::model::pagination::{filter_to_like_pattern, AuditLogSort, Page, PageMeta, Pagination};
+use crate::utils::apierror::ApiError;
+use crate::utils::context::with_request_context;
+
+impl Database {
+    /// Records an entry in the audit log
+    pub async fn add_audit_entry(
+        &self,
+        actor: Option<i64>,
+        impersonator: Option<i64>,
+        action: &str,
+        details: &str,
+    ) -> Result<(), ApiError> {
+        let now = Local::now().naive_local();
+        // the client and the identifier of the HTTP request being handled, if any
+        let client_ip = with_request_context(|context| context.client_ip)
+            .flatten()
+            .map(|ip| ip.to_string());
+        let (request_id, client_request_id) =
+            with_request_context(|context| (Some(context.request_id.clone()), context.client_request_id.clone()))
+                .unwrap_or_default();
+        sqlx::query!(
+            "INSERT INTO AuditLog (timestamp, actor, impersonator, action, details, clientIp, requestId, clientRequestId) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+            now,
+            actor,
+            impersonator,
+            action,
+            details,
+            client_ip,
+            request_id,
+            client_request_id
+        )
+        .execute(&mut *self.transaction.borrow().await)
+        .await?;
+        Ok(())
+    }
+
+    /// Gets a page of the entries in the audit log, optionally only those whose action, details or request identifiers match a filter
+    pub async fn get_audit_log(
+        &self,
+        filter: Option<&str>,
+        pagination: Pagination<AuditLogSort>,
+    ) -> Result<Page<AuditLogEntry>, ApiError> {
+        let pattern = filter_to_like_pattern(filter);
+        let total = sqlx::query!(
+            "SELECT COUNT(id) AS total FROM AuditLog WHERE action LIKE $1 ESCAPE '\\' OR details LIKE $1 ESCAPE '\\' OR requestId LIKE $1 ESCAPE '\\' OR clientRequestId LIKE $1 ESCAPE '\\'",
+            pattern
+        )
+        .fetch_one(&mut *self.transaction.borrow().await)
+        .await?
+        .total;
+        let (limit, offset) = pagination.sql_bounds();
+        let items = match pagination.sort {
+            AuditLogSort::Newest => {
+                sqlx::query_as!(
+                    AuditLogEntry,
+                    "SELECT id, timestamp, actor, impersonator, action, details, clientIp AS client_ip, requestId AS request_id, clientRequestId AS client_request_id FROM AuditLog
+                    WHERE action LIKE $1 ESCAPE '\\' OR details LIKE $1 ESCAPE '\\' OR requestId LIKE $1 ESCAPE '\\' OR clientRequestId LIKE $1 ESCAPE '\\'
+                    ORDER BY id DESC
+                    LIMIT $2 OFFSET $3",
+                    pattern,
+                    limit,
+                    offset
+                )
+                .fetch_all(&mut *self.transaction.borrow().await)
+                .await?
+            }
+            AuditLogSort::Oldest => {
+                sqlx::query_as!(
+                    AuditLogEntry,
+                    "SELECT id, timestamp, actor, impersonator, action, details, clientIp AS client_ip, requestId AS request_id, clientRequestId AS client_request_id FROM AuditLog
+                    WHERE action LIKE $1 ESCAPE '\\' OR details LIKE $1 ESCAPE '\\' OR requestId LIKE $1 ESCAPE '\\' OR clientRequestId LIKE $1 ESCAPE '\\'
+                    ORDER BY id
+                    LIMIT $2 OFFSET $3",
+                    pattern,
+                    limit,
+                    offset
+                )
+                .fetch_all(&mut *self.transaction.borrow().await)
+                .await?
+            }
+        };
+        Ok(Page {
+            items,
+            meta: PageMeta {
+                total: usize::try_from(total).unwrap_or_default(),
+            },
+        })
+    }
+}
@@ -0,0 +1,94 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Service for persisting information in the database
+//! API related to the prebuilt binaries of crate versions
+
+use chrono::Local;
+
+use super::Database;
+use crate::model::binstall::CrateBinary;
+use crate::model::cargo::RegistryUser;
+use crate::utils::apierror::{error_not_found, ApiError};
+
+impl Database {
+    /// Gets the prebuilt binaries for a crate version, by order of targets
+    pub async fn get_crate_binaries(&self, package: &str, version: &str) -> Result<Vec<CrateBinary>, ApiError> {
+        let rows = sqlx::query!(
+            "SELECT target, size, checksum, upload,
+                RegistryUser.id AS uid, isActive AS is_active, email, login, name, roles
+            FROM PackageVersionBinary INNER JOIN RegistryUser ON PackageVersionBinary.uploadedBy = RegistryUser.id
+            WHERE package = $1 AND version = $2
+            ORDER BY target",
+            package,
+            version
+        )
+        .fetch_all(&mut *self.transaction.borrow().await)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| CrateBinary {
+                target: row.target,
+                size: row.size,
+                checksum: row.checksum,
+                uploaded_by: RegistryUser {
+                    id: row.uid,
+                    is_active: row.is_active,
+                    email: row.email,
+                    login: row.login,
+                    name: row.name,
+                    roles: row.roles,
+                },
+                upload: row.upload,
+            })
+            .collect())
+    }
+
+    /// Checks that a prebuilt binary exists for a crate version and a target
+    pub async fn check_crate_binary_exists(&self, package: &str, version: &str, target: &str) -> Result<(), ApiError> {
+        sqlx::query!(
+            "SELECT id FROM PackageVersionBinary WHERE package = $1 AND version = $2 AND target = $3 LIMIT 1",
+            package,
+            version,
+            target
+        )
+        .fetch_optional(&mut *self.transaction.borrow().await)
+        .await?
+        .ok_or_else(error_not_found)?;
+        Ok(())
+    }
+
+    /// Records the upload of a prebuilt binary for a crate version and a target, replacing the previous one
+    pub async fn set_crate_binary(
+        &self,
+        uid: i64,
+        package: &str,
+        version: &str,
+        target: &str,
+        size: i64,
+        checksum: &str,
+    ) -> Result<CrateBinary, ApiError> {
+        let now = Local::now().naive_local();
+        sqlx::query!(
+            "INSERT INTO PackageVersionBinary (package, version, target, size, checksum, uploadedBy, upload) VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (package, version, target) DO UPDATE SET size = excluded.size, checksum = excluded.checksum, uploadedBy = excluded.uploadedBy, upload = excluded.upload",
+            package,
+            version,
+            target,
+            size,
+            checksum,
+            uid,
+            now
+        )
+        .execute(&mut *self.transaction.borrow().await)
+        .await?;
+        Ok(CrateBinary {
+            target: target.to_string(),
+            size,
+            checksum: checksum.to_string(),
+            uploaded_by: self.get_user_profile(uid).await?,
+            upload: now,
+        })
+    }
+}
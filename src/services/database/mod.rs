@@ -5,15 +5,28 @@
 //! Service for persisting information in the database
 
 pub mod admin;
+pub mod advisories;
+pub mod announcements;
+pub mod audit;
+pub mod binaries;
 pub mod jobs;
 pub mod packages;
+pub mod profile;
+pub mod quotas;
+pub mod stars;
 pub mod stats;
 pub mod users;
+pub mod visibility;
+pub mod watches;
+pub mod webhooks;
 
 use std::future::Future;
 
-use crate::model::auth::ROLE_ADMIN;
-use crate::utils::apierror::{error_forbidden, error_not_found, error_unauthorized, specialize, ApiError};
+use crate::model::auth::{has_role, ROLE_ADMIN, ROLE_READONLY};
+use crate::model::cargo::parse_team_owner;
+use crate::utils::apierror::{
+    error_forbidden, error_invalid_request, error_not_found, error_unauthorized, specialize, ApiError,
+};
 use crate::utils::db::{AppTransaction, RwSqlitePool};
 
 /// Executes a piece of work in the context of a transaction
@@ -23,6 +36,7 @@ use crate::utils::db::{AppTransaction, RwSqlitePool};
 /// # Errors
 ///
 /// Returns an instance of the `E` type argument
+#[tracing::instrument(skip_all)]
 pub async fn db_transaction_read<F, FUT, T, E>(pool: &RwSqlitePool, workload: F) -> Result<T, E>
 where
     F: FnOnce(Database) -> FUT,
@@ -56,6 +70,7 @@ where
 /// # Errors
 ///
 /// Returns an instance of the `E` type argument
+#[tracing::instrument(skip_all, fields(operation = operation))]
 pub async fn db_transaction_write<F, FUT, T, E>(pool: &RwSqlitePool, operation: &'static str, workload: F) -> Result<T, E>
 where
     F: FnOnce(Database) -> FUT,
@@ -98,6 +113,41 @@ impl Database {
         Ok(row.id)
     }
 
+    /// Resolves the owners given by cargo into the emails of users
+    ///
+    /// The teams of crates.io, `github:<org>:<team>`, are resolved to the active users having the team as a role.
+    pub async fn resolve_owners(&self, owners: &[String]) -> Result<Vec<String>, ApiError> {
+        let mut emails = Vec::new();
+        for owner in owners {
+            let Some((_org, team)) = parse_team_owner(owner) else {
+                if !emails.contains(owner) {
+                    emails.push(owner.clone());
+                }
+                continue;
+            };
+            let rows = sqlx::query!("SELECT email, roles FROM RegistryUser WHERE isActive = TRUE")
+                .fetch_all(&mut *self.transaction.borrow().await)
+                .await?;
+            let members = rows
+                .into_iter()
+                .filter(|row| has_role(&row.roles, team))
+                .map(|row| row.email)
+                .collect::<Vec<_>>();
+            if members.is_empty() {
+                return Err(specialize(
+                    error_invalid_request(),
+                    format!("no user has the role {team} for the team {owner}"),
+                ));
+            }
+            for member in members {
+                if !emails.contains(&member) {
+                    emails.push(member);
+                }
+            }
+        }
+        Ok(emails)
+    }
+
     /// Checks that a user is an admin
     pub async fn get_is_admin(&self, uid: i64) -> Result<bool, ApiError> {
         let roles = sqlx::query!("SELECT roles FROM RegistryUser WHERE id = $1", uid)
@@ -105,7 +155,17 @@ impl Database {
             .await?
             .ok_or_else(error_forbidden)?
             .roles;
-        Ok(roles.split(',').any(|role| role.trim() == ROLE_ADMIN))
+        Ok(has_role(&roles, ROLE_ADMIN))
+    }
+
+    /// Gets whether a user is restricted to read-only access
+    pub async fn get_is_readonly(&self, uid: i64) -> Result<bool, ApiError> {
+        let roles = sqlx::query!("SELECT roles FROM RegistryUser WHERE id = $1", uid)
+            .fetch_optional(&mut *self.transaction.borrow().await)
+            .await?
+            .ok_or_else(error_unauthorized)?
+            .roles;
+        Ok(has_role(&roles, ROLE_READONLY))
     }
 
     /// Checks that a user is an admin
@@ -151,4 +211,19 @@ impl Database {
             )),
         }
     }
+
+    /// Checks that a package is not frozen, so that it can be modified
+    pub async fn check_crate_not_frozen(&self, package: &str) -> Result<(), ApiError> {
+        let row = sqlx::query!("SELECT isFrozen AS is_frozen FROM Package WHERE name = $1 LIMIT 1", package)
+            .fetch_optional(&mut *self.transaction.borrow().await)
+            .await?
+            .ok_or_else(error_not_found)?;
+        if row.is_frozen {
+            return Err(specialize(
+                error_forbidden(),
+                format!("Package {package} is frozen, it cannot be modified"),
+            ));
+        }
+        Ok(())
+    }
 }
@@ -5,74 +5,279 @@
 //! Service for persisting information in the database
 //! API related to the management of packages (crates)
 
+use std::cmp::Reverse;
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 
-use byteorder::ByteOrder;
-use chrono::{Datelike, Duration, Local, NaiveDateTime};
+use chrono::{Days, Duration, Local, NaiveDateTime};
 use futures::StreamExt;
 use semver::Version;
 
 use super::Database;
 use crate::model::cargo::{
-    CrateUploadData, CrateUploadResult, IndexCrateMetadata, OwnersQueryResult, RegistryUser, SearchResultCrate, SearchResults,
-    SearchResultsMeta, YesNoMsgResult, YesNoResult,
+    CrateUploadData, CrateUploadResult, CratesIoVersion, IndexCrateMetadata, OwnersQueryResult, RegistryUser, SearchFacetValue,
+    SearchFacets, SearchResultCrate, SearchResults, SearchResultsMeta, YesNoMsgResult, YesNoResult,
 };
 use crate::model::deps::{DepsAnalysisJobSpec, DepsAnalysisState};
-use crate::model::docs::DocGenJobSpec;
-use crate::model::packages::{CrateInfo, CrateInfoTarget, CrateInfoVersion, CrateInfoVersionDocs};
-use crate::model::stats::{DownloadStats, SERIES_LENGTH};
+use crate::model::docs::{DocGenJobSpec, DocsCoverage};
+use crate::model::feeds::FeedEntry;
+use crate::model::packages::{
+    CrateInfo, CrateInfoTarget, CrateInfoVersion, CrateInfoVersionDocs, CrateVersionChecksums, QuarantinedVersion,
+    ReverseDependencies, ReverseDependenciesMeta, ReverseDependency, VersionProvenance,
+};
+use crate::model::search::{CrateSearchHit, SearchFilters, SearchPagination, SearchRankingWeights, SearchSort};
+use crate::model::stats::{
+    add_download_series, count_recent_downloads, read_download_series, CrateDownloads, CrateDownloadsMeta, DownloadStats,
+    VersionDownloads, SERIES_LENGTH,
+};
 use crate::model::CrateVersion;
 use crate::utils::apierror::{error_invalid_request, error_not_found, specialize, ApiError};
 use crate::utils::comma_sep_to_vec;
 
+/// A crate in search results, with the data to sort them
+struct SearchEntry {
+    /// The final score for the relevance
+    score: f64,
+    /// The total number of downloads
+    downloads: i64,
+    /// The number of downloads over the last days
+    recent_downloads: i64,
+    /// The date of the first publication
+    created: NaiveDateTime,
+    /// The date of the last publication
+    updated: NaiveDateTime,
+    /// The result to return
+    result: SearchResultCrate,
+}
+
+/// A version of a crate, to build the summary for search results
+struct SummaryVersion {
+    /// The crate
+    package: String,
+    /// The version number
+    version: String,
+    /// The description
+    description: String,
+    /// The date of the publication
+    upload: NaiveDateTime,
+    /// Whether the version is yanked
+    yanked: bool,
+    /// The total number of downloads
+    download_count: i64,
+    /// The stored series of daily download counts
+    downloads: Option<Vec<u8>>,
+}
+
+/// The summary of the versions of a crate for search results
+struct CrateVersionsSummary {
+    /// The last version that is not yanked or, when all are yanked, the last version
+    max_version: String,
+    /// The highest stable version that is not yanked, if any
+    max_stable_version: Option<String>,
+    /// Whether all the versions are yanked
+    is_yanked: bool,
+    /// The description of the last version
+    description: String,
+    /// The total number of downloads
+    downloads: i64,
+    /// The number of downloads over the last days
+    recent_downloads: i64,
+    /// The date of the first publication
+    created: NaiveDateTime,
+    /// The date of the last publication
+    updated: NaiveDateTime,
+}
+
+impl CrateVersionsSummary {
+    /// Builds the summary of the versions of a crate, given from the last one
+    fn new(rows: &[SummaryVersion]) -> Option<Self> {
+        // the last version that is not yanked or, when all are yanked, the last version
+        let last = rows.iter().find(|row| !row.yanked).or_else(|| rows.first())?;
+        let max_stable_version = rows
+            .iter()
+            .filter(|row| !row.yanked)
+            .filter_map(|row| row.version.parse::<Version>().ok())
+            .filter(|version| version.pre.is_empty())
+            .max()
+            .map(|version| version.to_string());
+        Some(Self {
+            max_version: last.version.clone(),
+            max_stable_version,
+            is_yanked: last.yanked,
+            description: last.description.clone(),
+            downloads: rows.iter().map(|row| row.download_count).sum(),
+            recent_downloads: rows.iter().map(|row| count_recent_downloads(row.downloads.as_deref())).sum(),
+            created: rows.iter().map(|row| row.upload).min().unwrap_or(last.upload),
+            updated: rows.iter().map(|row| row.upload).max().unwrap_or(last.upload),
+        })
+    }
+}
+
+impl SearchEntry {
+    /// Sorts entries in the requested order
+    fn sort(entries: &mut [Self], sort: SearchSort) {
+        match sort {
+            SearchSort::Relevance => entries.sort_by(|a, b| b.score.total_cmp(&a.score)),
+            SearchSort::Downloads => entries.sort_by_key(|entry| Reverse(entry.downloads)),
+            SearchSort::RecentDownloads => entries.sort_by_key(|entry| Reverse(entry.recent_downloads)),
+            SearchSort::RecentUpdates => entries.sort_by_key(|entry| Reverse(entry.updated)),
+            SearchSort::New => entries.sort_by_key(|entry| Reverse(entry.created)),
+            SearchSort::Alpha => entries.sort_by(|a, b| a.result.name.cmp(&b.result.name)),
+        }
+    }
+}
+
 impl Database {
-    /// Search for crates
+    /// Gets the search results for crates found by the full-text search
+    /// The crates are expected in order of relevance
     pub async fn search_crates(
         &self,
-        query: &str,
-        per_page: Option<usize>,
-        deprecated: Option<bool>,
+        hits: &[CrateSearchHit],
+        filters: &SearchFilters,
+        pagination: SearchPagination,
+        weights: SearchRankingWeights,
     ) -> Result<SearchResults, ApiError> {
-        let per_page = match per_page {
-            None => 10,
-            Some(value) if value > 100 => 100,
-            Some(value) => value,
-        };
-        let pattern = format!("%{query}%");
-        let deprecated_value = deprecated.unwrap_or_default();
-        let deprecated_short_circuit = deprecated.is_none(); // short-cirtcuit to true if no input
-        let rows = sqlx::query!(
-            "SELECT name, isDeprecated AS is_deprecated From Package WHERE name LIKE $1 AND (isDeprecated = $2 OR $3)",
-            pattern,
-            deprecated_value,
-            deprecated_short_circuit
+        let now = Local::now().naive_local();
+        // load the data for all the hits at once, the names being passed as a JSON array
+        let names = serde_json::to_string(&hits.iter().map(|hit| &hit.name).collect::<Vec<_>>())?;
+        let packages = sqlx::query!(
+            "SELECT name, isDeprecated AS is_deprecated FROM Package WHERE name IN (SELECT value FROM json_each($1))",
+            names
         )
         .fetch_all(&mut *self.transaction.borrow().await)
-        .await?;
-        let mut crates = Vec::new();
-        for row_name in rows {
-            let row = sqlx::query!("SELECT version, description FROM PackageVersion WHERE package = $1 AND yanked = FALSE ORDER BY id DESC LIMIT 1", row_name.name).fetch_optional(&mut *self.transaction.borrow().await).await?;
-            if let Some(row) = row {
-                crates.push(SearchResultCrate {
-                    name: row_name.name,
-                    max_version: row.version,
-                    is_deprecated: row_name.is_deprecated,
-                    description: row.description,
-                });
+        .await?
+        .into_iter()
+        .map(|row| (row.name, row.is_deprecated))
+        .collect::<HashMap<_, _>>();
+        let mut all_owners = self.get_crates_owners_logins(&names).await?;
+        let mut summaries = self.get_crates_versions_summaries(&names).await?;
+
+        let mut entries = Vec::new();
+        let mut facet_keywords = HashMap::new();
+        let mut facet_categories = HashMap::new();
+        let mut facet_owners = HashMap::new();
+        for hit in hits {
+            let Some(&is_deprecated) = packages.get(&hit.name) else {
+                continue;
+            };
+            if filters.deprecated.is_some_and(|deprecated| deprecated != is_deprecated) {
+                continue;
+            }
+            let owners = all_owners.remove(&hit.name).unwrap_or_default();
+            if filters
+                .owner
+                .as_ref()
+                .is_some_and(|owner| !owners.iter().any(|login| login == owner))
+            {
+                continue;
+            }
+            let Some(summary) = summaries.remove(&hit.name) else {
+                continue;
+            };
+            if summary.is_yanked && !filters.include_yanked {
+                continue;
+            }
+            for keyword in &hit.keywords {
+                *facet_keywords.entry(keyword.clone()).or_insert(0) += 1;
+            }
+            for category in &hit.categories {
+                *facet_categories.entry(category.clone()).or_insert(0) += 1;
             }
+            for login in owners {
+                *facet_owners.entry(login).or_insert(0) += 1;
+            }
+            entries.push(SearchEntry {
+                score: weights.score(hit.relevance, summary.downloads, summary.updated, now),
+                downloads: summary.downloads,
+                recent_downloads: summary.recent_downloads,
+                created: summary.created,
+                updated: summary.updated,
+                result: SearchResultCrate {
+                    name: hit.name.clone(),
+                    max_version: summary.max_version,
+                    max_stable_version: summary.max_stable_version,
+                    is_deprecated,
+                    is_yanked: summary.is_yanked,
+                    description: summary.description,
+                    downloads: summary.downloads,
+                    recent_downloads: summary.recent_downloads,
+                    updated_at: summary.updated,
+                },
+            });
         }
-        let total = crates.len();
+        SearchEntry::sort(&mut entries, pagination.sort);
+        let total = entries.len();
         Ok(SearchResults {
-            crates: if per_page > crates.len() {
-                crates.into_iter().take(per_page).collect()
-            } else {
-                crates
+            crates: entries
+                .into_iter()
+                .skip(pagination.offset())
+                .take(pagination.per_page)
+                .map(|entry| entry.result)
+                .collect(),
+            meta: SearchResultsMeta {
+                total,
+                facets: SearchFacets {
+                    keywords: SearchFacetValue::from_counts(facet_keywords),
+                    categories: SearchFacetValue::from_counts(facet_categories),
+                    owners: SearchFacetValue::from_counts(facet_owners),
+                },
             },
-            meta: SearchResultsMeta { total },
         })
     }
 
+    /// Gets the logins of the owners of crates, given as a JSON array of names
+    async fn get_crates_owners_logins(&self, names: &str) -> Result<HashMap<String, Vec<String>>, ApiError> {
+        let rows = sqlx::query!(
+            "SELECT package, login FROM RegistryUser INNER JOIN PackageOwner ON PackageOwner.owner = RegistryUser.id
+            WHERE package IN (SELECT value FROM json_each($1))",
+            names
+        )
+        .fetch_all(&mut *self.transaction.borrow().await)
+        .await?;
+        let mut owners = HashMap::<String, Vec<String>>::new();
+        for row in rows {
+            owners.entry(row.package).or_default().push(row.login);
+        }
+        Ok(owners)
+    }
+
+    /// Gets the summaries of the versions of crates for search results, given as a JSON array of names
+    async fn get_crates_versions_summaries(&self, names: &str) -> Result<HashMap<String, CrateVersionsSummary>, ApiError> {
+        let rows = sqlx::query_as!(
+            SummaryVersion,
+            "SELECT package, version, description, upload, yanked, downloadCount AS download_count, downloads
+            FROM PackageVersion WHERE package IN (SELECT value FROM json_each($1)) ORDER BY id DESC",
+            names
+        )
+        .fetch_all(&mut *self.transaction.borrow().await)
+        .await?;
+        let mut versions = HashMap::<String, Vec<SummaryVersion>>::new();
+        for row in rows {
+            versions.entry(row.package.clone()).or_default().push(row);
+        }
+        Ok(versions
+            .into_iter()
+            .filter_map(|(package, versions)| CrateVersionsSummary::new(&versions).map(|summary| (package, summary)))
+            .collect())
+    }
+
+    /// Gets the last version of all the crates, preferring versions that are not yanked
+    pub async fn get_crates_last_versions(&self) -> Result<Vec<CrateVersion>, ApiError> {
+        let rows = sqlx::query!(
+            "SELECT package, version FROM PackageVersion
+            WHERE id IN (SELECT COALESCE(MAX(CASE WHEN yanked = FALSE THEN id END), MAX(id)) FROM PackageVersion GROUP BY package)"
+        )
+        .fetch_all(&mut *self.transaction.borrow().await)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| CrateVersion {
+                package: row.package,
+                version: row.version,
+            })
+            .collect())
+    }
+
     /// Gets whether the database does not contain any package at all
     pub async fn get_is_empty(&self) -> Result<bool, ApiError> {
         Ok(sqlx::query!("SELECT id FROM PackageVersion LIMIT 1")
@@ -100,21 +305,25 @@ impl Database {
         versions_in_index: Vec<IndexCrateMetadata>,
     ) -> Result<CrateInfo, ApiError> {
         let row = sqlx::query!(
-            "SELECT isDeprecated AS is_deprecated, targets, nativeTargets AS nativetargets, capabilities FROM Package WHERE name = $1 LIMIT 1",
+            "SELECT isDeprecated AS is_deprecated, isFrozen AS is_frozen, isClaimedUpstream AS is_claimed_upstream, targets, nativeTargets AS nativetargets, capabilities FROM Package WHERE name = $1 LIMIT 1",
             package
         )
         .fetch_optional(&mut *self.transaction.borrow().await)
         .await?
         .ok_or_else(error_not_found)?;
         let is_deprecated = row.is_deprecated;
+        let is_frozen = row.is_frozen;
+        let is_claimed_upstream = row.is_claimed_upstream;
         let targets = comma_sep_to_vec(&row.targets);
         let native_targets = comma_sep_to_vec(&row.nativetargets);
         let capabilities = comma_sep_to_vec(&row.capabilities);
 
         let rows = sqlx::query!(
             "SELECT version, upload, uploadedBy AS uploaded_by,
-                    downloadCount AS download_count,
-                    depsLastCheck AS deps_last_check, depsHasOutdated AS deps_has_outdated, depsHasCVEs AS deps_has_cves
+                    downloadCount AS download_count, downloads,
+                    depsLastCheck AS deps_last_check, depsHasOutdated AS deps_has_outdated, depsHasCVEs AS deps_has_cves,
+                    provenanceCommit AS provenance_commit, provenanceCiRunUrl AS provenance_ci_run_url,
+                    provenanceBuilder AS provenance_builder, provenanceVerified AS provenance_verified
             FROM PackageVersion WHERE package = $1 ORDER BY id",
             package
         )
@@ -124,20 +333,31 @@ impl Database {
         for index_data in versions_in_index {
             if let Some(row) = rows.iter().find(|row| row.version == index_data.vers) {
                 let uploaded_by = self.get_user_profile(row.uploaded_by).await?;
+                let provenance = VersionProvenance {
+                    commit: row.provenance_commit.clone(),
+                    ci_run_url: row.provenance_ci_run_url.clone(),
+                    builder: row.provenance_builder.clone(),
+                    verified: row.provenance_verified,
+                };
                 versions.push(CrateInfoVersion {
                     index: index_data,
                     upload: row.upload,
                     uploaded_by,
+                    downloads: row.download_count,
                     download_count: row.download_count,
+                    recent_downloads: count_recent_downloads(row.downloads.as_deref()),
                     deps_last_check: row.deps_last_check,
                     deps_has_outdated: row.deps_has_outdated,
                     deps_has_cves: row.deps_has_cves,
+                    advisories: Vec::new(),
                     docs: Vec::new(),
+                    provenance: Some(provenance).filter(|provenance| !provenance.is_empty()),
                 });
             }
         }
         let rows = sqlx::query!(
-            "SELECT version, target, isAttempted AS is_attempted, isPresent AS is_present
+            "SELECT version, target, isAttempted AS is_attempted, isPresent AS is_present,
+                    coverageTotal AS coverage_total, coverageDocumented AS coverage_documented
             FROM PackageVersionDocs
             WHERE package = $1 ORDER BY id",
             package
@@ -150,12 +370,26 @@ impl Database {
                     target: row.target,
                     is_attempted: row.is_attempted,
                     is_present: row.is_present,
+                    coverage: match (row.coverage_total, row.coverage_documented) {
+                        (Some(total), Some(documented)) => Some(DocsCoverage { total, documented }),
+                        _ => None,
+                    },
                 });
             }
         }
+        for advisory in self.get_crate_advisories(package).await? {
+            if let Some(version) = versions.iter_mut().find(|v| v.index.vers == advisory.version) {
+                version.advisories.push(advisory);
+            }
+        }
         Ok(CrateInfo {
             metadata: None,
+            downloads: versions.iter().map(|version| version.downloads).sum(),
+            recent_downloads: versions.iter().map(|version| version.recent_downloads).sum(),
+            stars: self.get_crate_stars(package).await?,
             is_deprecated,
+            is_frozen,
+            is_claimed_upstream,
             versions,
             targets: targets
                 .into_iter()
@@ -168,6 +402,48 @@ impl Database {
         })
     }
 
+    /// Gets the versions of a crate in the format of the crates.io web API, the most recent first
+    pub async fn get_crates_io_versions(
+        &self,
+        package: &str,
+        versions_in_index: Vec<IndexCrateMetadata>,
+    ) -> Result<Vec<CratesIoVersion>, ApiError> {
+        let rows = sqlx::query!(
+            "SELECT id, version, upload, uploadedBy AS uploaded_by, downloadCount AS download_count, size
+            FROM PackageVersion WHERE package = $1 ORDER BY id",
+            package
+        )
+        .fetch_all(&mut *self.transaction.borrow().await)
+        .await?;
+        if rows.is_empty() {
+            return Err(error_not_found());
+        }
+        let mut versions = Vec::new();
+        for index_data in versions_in_index.into_iter().rev() {
+            if let Some(row) = rows.iter().find(|row| row.version == index_data.vers) {
+                let published_by = self.get_user_profile(row.uploaded_by).await?;
+                versions.push(CratesIoVersion {
+                    id: row.id,
+                    dl_path: format!("/api/v1/crates/{package}/{}/download", index_data.vers),
+                    readme_path: format!("/api/v1/crates/{package}/{}/readme", index_data.vers),
+                    package: index_data.name,
+                    num: index_data.vers,
+                    checksum: index_data.cksum,
+                    crate_size: row.size,
+                    created_at: row.upload,
+                    updated_at: row.upload,
+                    downloads: row.download_count,
+                    features: index_data.features,
+                    yanked: index_data.yanked,
+                    links: index_data.links,
+                    rust_version: index_data.rust_version,
+                    published_by: Some(published_by.into()),
+                });
+            }
+        }
+        Ok(versions)
+    }
+
     /// Publish a crate
     #[allow(clippy::similar_names)]
     pub async fn publish_crate_version(&self, uid: i64, package: &CrateUploadData) -> Result<CrateUploadResult, ApiError> {
@@ -203,10 +479,11 @@ impl Database {
             }
             // check the ownership
             self.check_is_crate_manager(uid, &package.metadata.name).await?;
+            self.check_crate_not_frozen(&package.metadata.name).await?;
         } else {
             // create the package
             sqlx::query!(
-                "INSERT INTO Package (name, lowercase, targets, nativeTargets, capabilities, isDeprecated) VALUES ($1, $2, '', '', '', FALSE)",
+                "INSERT INTO Package (name, lowercase, targets, nativeTargets, capabilities, isDeprecated, isFrozen, isClaimedUpstream) VALUES ($1, $2, '', '', '', FALSE, FALSE, FALSE)",
                 package.metadata.name,
                 lowercase
             )
@@ -224,21 +501,288 @@ impl Database {
         let now = Local::now().naive_local();
         // create the version
         let description = package.metadata.description.as_ref().map_or("", String::as_str);
+        let size = i64::try_from(package.content.len())?;
+        let checksums = CrateVersionChecksums::new(&package.content);
         sqlx::query!(
-            "INSERT INTO PackageVersion (package, version, description, upload, uploadedBy, yanked, downloadCount, downloads, depsLastCheck, depsHasOutdated, depsHasCVEs) VALUES ($1, $2, $3, $4, $5, false, 0, NULL, 0, false, false)",
+            "INSERT INTO PackageVersion (package, version, description, upload, uploadedBy, yanked, downloadCount, downloads, depsLastCheck, depsHasOutdated, depsHasCVEs, size, checksumSha256, checksumSha512) VALUES ($1, $2, $3, $4, $5, false, 0, NULL, 0, false, false, $6, $7, $8)",
             package.metadata.name,
             package.metadata.vers,
             description,
             now,
             uid,
+            size,
+            checksums.sha256,
+            checksums.sha512,
         )
         .execute(&mut *self.transaction.borrow().await)
         .await?;
         Ok(warnings)
     }
 
-    /// Yank a crate version
-    pub async fn yank_crate_version(&self, package: &str, version: &str) -> Result<YesNoResult, ApiError> {
+    /// Gets the end of the quarantine of a crate version, if it is quarantined
+    pub async fn get_crate_version_quarantine(&self, package: &str, version: &str) -> Result<Option<NaiveDateTime>, ApiError> {
+        let row = sqlx::query!(
+            "SELECT quarantineUntil AS quarantine_until FROM PackageVersion WHERE package = $1 AND version = $2 LIMIT 1",
+            package,
+            version
+        )
+        .fetch_optional(&mut *self.transaction.borrow().await)
+        .await?
+        .ok_or_else(error_not_found)?;
+        Ok(row.quarantine_until)
+    }
+
+    /// Sets the end of the quarantine of a crate version, `None` to release it
+    pub async fn set_crate_version_quarantine(
+        &self,
+        package: &str,
+        version: &str,
+        quarantine_until: Option<NaiveDateTime>,
+    ) -> Result<(), ApiError> {
+        sqlx::query!(
+            "UPDATE PackageVersion SET quarantineUntil = $3 WHERE package = $1 AND version = $2",
+            package,
+            version,
+            quarantine_until
+        )
+        .execute(&mut *self.transaction.borrow().await)
+        .await?;
+        Ok(())
+    }
+
+    /// Releases a crate version from its quarantine, only if it is still quarantined
+    ///
+    /// Gets the end of the quarantine that was lifted, `None` when the version was not quarantined,
+    /// for example because it was released concurrently.
+    pub async fn release_crate_version_quarantine(
+        &self,
+        package: &str,
+        version: &str,
+    ) -> Result<Option<NaiveDateTime>, ApiError> {
+        let quarantine_until = self.get_crate_version_quarantine(package, version).await?;
+        let result = sqlx::query!(
+            "UPDATE PackageVersion SET quarantineUntil = NULL WHERE package = $1 AND version = $2 AND quarantineUntil IS NOT NULL",
+            package,
+            version
+        )
+        .execute(&mut *self.transaction.borrow().await)
+        .await?;
+        Ok(quarantine_until.filter(|_| result.rows_affected() > 0))
+    }
+
+    /// Gets the user that published a crate version
+    pub async fn get_crate_version_uploader(&self, package: &str, version: &str) -> Result<RegistryUser, ApiError> {
+        let maybe_row = sqlx::query_as!(
+            RegistryUser,
+            "SELECT RegistryUser.id, isActive AS is_active, email, login, name, roles
+            FROM PackageVersion INNER JOIN RegistryUser ON PackageVersion.uploadedBy = RegistryUser.id
+            WHERE package = $1 AND version = $2 LIMIT 1",
+            package,
+            version
+        )
+        .fetch_optional(&mut *self.transaction.borrow().await)
+        .await?;
+        maybe_row.ok_or_else(error_not_found)
+    }
+
+    /// Gets the timestamp of the publication of a crate version
+    pub async fn get_crate_version_upload(&self, package: &str, version: &str) -> Result<NaiveDateTime, ApiError> {
+        let row = sqlx::query!(
+            "SELECT upload FROM PackageVersion WHERE package = $1 AND version = $2 LIMIT 1",
+            package,
+            version
+        )
+        .fetch_optional(&mut *self.transaction.borrow().await)
+        .await?
+        .ok_or_else(error_not_found)?;
+        Ok(row.upload)
+    }
+
+    /// Gets the provenance of a crate version
+    pub async fn get_crate_version_provenance(&self, package: &str, version: &str) -> Result<VersionProvenance, ApiError> {
+        let row = sqlx::query!(
+            "SELECT provenanceCommit AS provenance_commit, provenanceCiRunUrl AS provenance_ci_run_url,
+                    provenanceBuilder AS provenance_builder, provenanceVerified AS provenance_verified
+            FROM PackageVersion WHERE package = $1 AND version = $2 LIMIT 1",
+            package,
+            version
+        )
+        .fetch_optional(&mut *self.transaction.borrow().await)
+        .await?
+        .ok_or_else(error_not_found)?;
+        Ok(VersionProvenance {
+            commit: row.provenance_commit,
+            ci_run_url: row.provenance_ci_run_url,
+            builder: row.provenance_builder,
+            verified: row.provenance_verified,
+        })
+    }
+
+    /// Sets the provenance of a crate version
+    pub async fn set_crate_version_provenance(
+        &self,
+        package: &str,
+        version: &str,
+        provenance: &VersionProvenance,
+    ) -> Result<(), ApiError> {
+        sqlx::query!(
+            "UPDATE PackageVersion
+            SET provenanceCommit = $3, provenanceCiRunUrl = $4, provenanceBuilder = $5, provenanceVerified = $6
+            WHERE package = $1 AND version = $2",
+            package,
+            version,
+            provenance.commit,
+            provenance.ci_run_url,
+            provenance.builder,
+            provenance.verified
+        )
+        .execute(&mut *self.transaction.borrow().await)
+        .await?;
+        Ok(())
+    }
+
+    /// Gets the stored checksums of a crate version, if they are known
+    ///
+    /// The checksums are not known for the versions published before they were stored.
+    pub async fn get_crate_version_checksums(
+        &self,
+        package: &str,
+        version: &str,
+    ) -> Result<Option<CrateVersionChecksums>, ApiError> {
+        let row = sqlx::query!(
+            "SELECT checksumSha256 AS checksum_sha256, checksumSha512 AS checksum_sha512
+            FROM PackageVersion WHERE package = $1 AND version = $2 LIMIT 1",
+            package,
+            version
+        )
+        .fetch_optional(&mut *self.transaction.borrow().await)
+        .await?
+        .ok_or_else(error_not_found)?;
+        Ok(match (row.checksum_sha256, row.checksum_sha512) {
+            (Some(sha256), Some(sha512)) => Some(CrateVersionChecksums { sha256, sha512 }),
+            _ => None,
+        })
+    }
+
+    /// Gets the crate versions whose checksums or size are not known yet
+    pub async fn get_crate_versions_without_checksums(&self) -> Result<Vec<(String, String)>, ApiError> {
+        let rows = sqlx::query!(
+            "SELECT package, version FROM PackageVersion WHERE checksumSha256 IS NULL OR checksumSha512 IS NULL OR size IS NULL ORDER BY id"
+        )
+        .fetch_all(&mut *self.transaction.borrow().await)
+        .await?;
+        Ok(rows.into_iter().map(|row| (row.package, row.version)).collect())
+    }
+
+    /// Gets the crate versions uploaded by some users whose size is not known yet
+    pub async fn get_users_crate_versions_without_size(&self, uids: &[i64]) -> Result<Vec<(String, String)>, ApiError> {
+        let uids = serde_json::to_string(uids)?;
+        let rows = sqlx::query!(
+            "SELECT package, version FROM PackageVersion WHERE uploadedBy IN (SELECT value FROM json_each($1)) AND size IS NULL ORDER BY id",
+            uids
+        )
+        .fetch_all(&mut *self.transaction.borrow().await)
+        .await?;
+        Ok(rows.into_iter().map(|row| (row.package, row.version)).collect())
+    }
+
+    /// Sets the checksums and the size of a crate version
+    pub async fn set_crate_version_checksums(
+        &self,
+        package: &str,
+        version: &str,
+        checksums: &CrateVersionChecksums,
+        size: i64,
+    ) -> Result<(), ApiError> {
+        sqlx::query!(
+            "UPDATE PackageVersion SET checksumSha256 = $3, checksumSha512 = $4, size = $5 WHERE package = $1 AND version = $2",
+            package,
+            version,
+            checksums.sha256,
+            checksums.sha512,
+            size
+        )
+        .execute(&mut *self.transaction.borrow().await)
+        .await?;
+        Ok(())
+    }
+
+    /// Gets all the crate versions in quarantine
+    pub async fn get_quarantined_versions(&self) -> Result<Vec<QuarantinedVersion>, ApiError> {
+        let rows = sqlx::query!(
+            "SELECT package, version, upload, quarantineUntil AS \"quarantine_until!: NaiveDateTime\"
+            FROM PackageVersion
+            WHERE quarantineUntil IS NOT NULL
+            ORDER BY quarantineUntil"
+        )
+        .fetch_all(&mut *self.transaction.borrow().await)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| QuarantinedVersion {
+                package: row.package,
+                version: row.version,
+                upload: row.upload,
+                quarantine_until: row.quarantine_until,
+            })
+            .collect())
+    }
+
+    /// Sets the size of the generated documentation for a crate version and a target
+    pub async fn set_crate_documentation_size(
+        &self,
+        package: &str,
+        version: &str,
+        target: &str,
+        size: i64,
+    ) -> Result<(), ApiError> {
+        sqlx::query!(
+            "UPDATE PackageVersionDocs SET size = $4 WHERE package = $1 AND version = $2 AND target = $3",
+            package,
+            version,
+            target,
+            size
+        )
+        .execute(&mut *self.transaction.borrow().await)
+        .await?;
+        Ok(())
+    }
+
+    /// Gets all the versions that are not yanked
+    pub async fn get_unyanked_crate_versions(&self) -> Result<Vec<CrateVersion>, ApiError> {
+        let rows = sqlx::query!("SELECT package, version FROM PackageVersion WHERE yanked = FALSE ORDER BY package, id")
+            .fetch_all(&mut *self.transaction.borrow().await)
+            .await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| CrateVersion {
+                package: row.package,
+                version: row.version,
+            })
+            .collect())
+    }
+
+    /// Gets all the versions of all the crates, in the order of their publication
+    pub async fn get_all_crate_versions(&self) -> Result<Vec<CrateVersion>, ApiError> {
+        let rows = sqlx::query!("SELECT package, version FROM PackageVersion ORDER BY id")
+            .fetch_all(&mut *self.transaction.borrow().await)
+            .await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| CrateVersion {
+                package: row.package,
+                version: row.version,
+            })
+            .collect())
+    }
+
+    /// Yank a crate version, recording the reason, if any
+    pub async fn yank_crate_version(
+        &self,
+        package: &str,
+        version: &str,
+        reason: Option<&str>,
+    ) -> Result<YesNoResult, ApiError> {
         let row = sqlx::query!(
             "SELECT yanked FROM PackageVersion WHERE package = $1 AND version = $2 LIMIT 1",
             package,
@@ -259,9 +803,10 @@ impl Database {
                     ))
                 } else {
                     sqlx::query!(
-                        "UPDATE PackageVersion SET yanked = TRUE WHERE package = $1 AND version = $2",
+                        "UPDATE PackageVersion SET yanked = TRUE, yankReason = $3 WHERE package = $1 AND version = $2",
                         package,
-                        version
+                        version,
+                        reason
                     )
                     .execute(&mut *self.transaction.borrow().await)
                     .await?;
@@ -288,7 +833,7 @@ impl Database {
             Some(row) => {
                 if row.yanked {
                     sqlx::query!(
-                        "UPDATE PackageVersion SET yanked = FALSE WHERE package = $1 AND version = $2",
+                        "UPDATE PackageVersion SET yanked = FALSE, yankReason = NULL WHERE package = $1 AND version = $2",
                         package,
                         version
                     )
@@ -430,6 +975,52 @@ impl Database {
         Ok(())
     }
 
+    /// Sets the documentation coverage for a package version and target
+    pub async fn set_crate_documentation_coverage(
+        &self,
+        package: &str,
+        version: &str,
+        target: &str,
+        coverage: DocsCoverage,
+    ) -> Result<(), ApiError> {
+        sqlx::query!(
+            "UPDATE PackageVersionDocs SET coverageTotal = $4, coverageDocumented = $5 WHERE package = $1 AND version = $2 AND target = $3",
+            package,
+            version,
+            target,
+            coverage.total,
+            coverage.documented
+        )
+        .execute(&mut *self.transaction.borrow().await)
+        .await?;
+        Ok(())
+    }
+
+    /// Gets the documentation coverage for a package version and target
+    pub async fn get_crate_documentation_coverage(
+        &self,
+        package: &str,
+        version: &str,
+        target: &str,
+    ) -> Result<Option<DocsCoverage>, ApiError> {
+        let row = sqlx::query!(
+            "SELECT coverageTotal AS coverage_total, coverageDocumented AS coverage_documented
+            FROM PackageVersionDocs
+            WHERE package = $1 AND version = $2 AND target = $3
+            LIMIT 1",
+            package,
+            version,
+            target
+        )
+        .fetch_optional(&mut *self.transaction.borrow().await)
+        .await?
+        .ok_or_else(error_not_found)?;
+        Ok(match (row.coverage_total, row.coverage_documented) {
+            (Some(total), Some(documented)) => Some(DocsCoverage { total, documented }),
+            _ => None,
+        })
+    }
+
     /// Force the re-generation for the documentation of a package
     pub async fn regen_crate_version_doc(
         &self,
@@ -520,7 +1111,7 @@ impl Database {
             "SELECT package, version, isDeprecated AS is_deprecated, depsHasOutdated AS has_outdated, depsLastCheck AS last_check, targets
             FROM PackageVersion
             INNER JOIN Package ON PackageVersion.package = Package.name
-            WHERE yanked = FALSE"
+            WHERE yanked = FALSE AND quarantineUntil IS NULL"
         )
         .fetch(transaction);
         while let Some(row) = stream.next().await {
@@ -604,25 +1195,27 @@ impl Database {
         Ok((deps_has_outdated, deps_has_cves))
     }
 
-    /// Increments the counter of downloads for a crate version
-    pub async fn increment_crate_version_dl_count(&self, package: &str, version: &str) -> Result<(), ApiError> {
-        let row = sqlx::query!(
+    /// Increments the counter of downloads for a crate version by the specified count
+    ///
+    /// Downloads for versions that do not exist (anymore) are ignored.
+    pub async fn increment_crate_version_dl_count(&self, package: &str, version: &str, count: u32) -> Result<(), ApiError> {
+        let Some(row) = sqlx::query!(
             "SELECT downloads FROM PackageVersion WHERE package = $1 AND version = $2 LIMIT 1",
             package,
             version
         )
         .fetch_optional(&mut *self.transaction.borrow().await)
         .await?
-        .ok_or_else(error_not_found)?;
-        let mut downloads = row.downloads.unwrap_or_else(|| vec![0; size_of::<u32>() * SERIES_LENGTH]);
-        let day_index = (Local::now().naive_local().ordinal0() as usize % SERIES_LENGTH) * size_of::<u32>();
-        let count = byteorder::NativeEndian::read_u32(&downloads[day_index..]);
-        byteorder::NativeEndian::write_u32(&mut downloads[day_index..], count + 1);
+        else {
+            return Ok(());
+        };
+        let downloads = add_download_series(row.downloads.as_deref(), Local::now().naive_local().date(), count);
 
         sqlx::query!(
-            "UPDATE PackageVersion SET downloadCount = downloadCount + 1, downloads = $3 WHERE package = $1 AND version = $2",
+            "UPDATE PackageVersion SET downloadCount = downloadCount + $3, downloads = $4 WHERE package = $1 AND version = $2",
             package,
             version,
+            count,
             downloads
         )
         .execute(&mut *self.transaction.borrow().await)
@@ -630,6 +1223,40 @@ impl Database {
         Ok(())
     }
 
+    /// Gets the daily downloads of each version of a crate over the last days
+    pub async fn get_crate_downloads(&self, package: &str) -> Result<CrateDownloads, ApiError> {
+        let rows = sqlx::query!(
+            "SELECT id, version, downloads FROM PackageVersion WHERE package = $1 ORDER BY id DESC",
+            package
+        )
+        .fetch_all(&mut *self.transaction.borrow().await)
+        .await?;
+        if rows.is_empty() {
+            return Err(error_not_found());
+        }
+        let today = Local::now().naive_local().date();
+        let first = today.checked_sub_days(Days::new(SERIES_LENGTH as u64 - 1)).unwrap();
+        let mut version_downloads = Vec::new();
+        for row in rows {
+            let counts = read_download_series(row.downloads.as_deref());
+            for (date, downloads) in first.iter_days().zip(counts) {
+                if downloads > 0 {
+                    version_downloads.push(VersionDownloads {
+                        version: row.id,
+                        num: row.version.clone(),
+                        downloads,
+                        date,
+                    });
+                }
+            }
+        }
+        version_downloads.sort_by_key(|entry| std::cmp::Reverse(entry.date));
+        Ok(CrateDownloads {
+            version_downloads,
+            meta: CrateDownloadsMeta::default(),
+        })
+    }
+
     /// Gets the download statistics for a crate
     pub async fn get_crate_dl_stats(&self, package: &str) -> Result<DownloadStats, ApiError> {
         let rows = sqlx::query!("SELECT version, downloads FROM PackageVersion WHERE package = $1", package)
@@ -802,11 +1429,167 @@ impl Database {
         Ok(())
     }
 
-    /// Sets the deprecation status on a crate
-    pub async fn set_crate_deprecation(&self, package: &str, deprecated: bool) -> Result<(), ApiError> {
-        sqlx::query!("UPDATE Package SET isDeprecated = $2 WHERE name = $1", package, deprecated)
+    /// Gets whether a crate already exists in this registry
+    pub async fn get_crate_exists(&self, package: &str) -> Result<bool, ApiError> {
+        let row = sqlx::query!("SELECT name FROM Package WHERE name = $1 LIMIT 1", package)
+            .fetch_optional(&mut *self.transaction.borrow().await)
+            .await?;
+        Ok(row.is_some())
+    }
+
+    /// Gets the deprecation status of a crate, `None` when the crate does not exist
+    pub async fn get_crate_deprecation(&self, package: &str) -> Result<Option<bool>, ApiError> {
+        let row = sqlx::query!(
+            "SELECT isDeprecated AS is_deprecated FROM Package WHERE name = $1 LIMIT 1",
+            package
+        )
+        .fetch_optional(&mut *self.transaction.borrow().await)
+        .await?;
+        Ok(row.map(|row| row.is_deprecated))
+    }
+
+    /// Sets the deprecation status on a crate, with the reason for deprecating it, if any
+    ///
+    /// Returns whether the crate was not deprecated before and now is.
+    pub async fn set_crate_deprecation(&self, package: &str, deprecated: bool, reason: Option<&str>) -> Result<bool, ApiError> {
+        let was_deprecated = self.get_crate_deprecation(package).await?.ok_or_else(error_not_found)?;
+        let reason = if deprecated { reason } else { None };
+        sqlx::query!(
+            "UPDATE Package SET isDeprecated = $2, deprecationReason = $3 WHERE name = $1",
+            package,
+            deprecated,
+            reason
+        )
+        .execute(&mut *self.transaction.borrow().await)
+        .await?;
+        Ok(deprecated && !was_deprecated)
+    }
+
+    /// Gets the names of all the crates, with whether they are claimed on crates.io
+    pub async fn get_crates_claimed_upstream(&self) -> Result<Vec<(String, bool)>, ApiError> {
+        let rows = sqlx::query!("SELECT name, isClaimedUpstream AS is_claimed_upstream FROM Package ORDER BY name")
+            .fetch_all(&mut *self.transaction.borrow().await)
+            .await?;
+        Ok(rows.into_iter().map(|row| (row.name, row.is_claimed_upstream)).collect())
+    }
+
+    /// Sets whether a crate is claimed on crates.io
+    pub async fn set_crate_claimed_upstream(&self, package: &str, claimed: bool) -> Result<(), ApiError> {
+        sqlx::query!("UPDATE Package SET isClaimedUpstream = $2 WHERE name = $1", package, claimed)
             .execute(&mut *self.transaction.borrow().await)
             .await?;
         Ok(())
     }
+
+    /// Sets the frozen status on a crate
+    pub async fn set_crate_frozen(&self, package: &str, frozen: bool) -> Result<(), ApiError> {
+        let result = sqlx::query!("UPDATE Package SET isFrozen = $2 WHERE name = $1", package, frozen)
+            .execute(&mut *self.transaction.borrow().await)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(error_not_found());
+        }
+        Ok(())
+    }
+
+    /// Records the dependencies of a crate version on other crates of this registry, replacing the previous ones
+    pub async fn set_crate_version_dependencies(&self, metadata: &IndexCrateMetadata) -> Result<(), ApiError> {
+        sqlx::query!(
+            "DELETE FROM PackageDependency WHERE package = $1 AND version = $2",
+            metadata.name,
+            metadata.vers
+        )
+        .execute(&mut *self.transaction.borrow().await)
+        .await?;
+        // dependencies to other registries are not tracked
+        for dep in metadata.deps.iter().filter(|dep| dep.registry.is_none()) {
+            let dependency = dep.get_name();
+            let kind = dep.kind.to_string();
+            sqlx::query!(
+                "INSERT INTO PackageDependency (package, version, dependency, versionReq, kind, optional) VALUES ($1, $2, $3, $4, $5, $6)",
+                metadata.name,
+                metadata.vers,
+                dependency,
+                dep.req,
+                kind,
+                dep.optional
+            )
+            .execute(&mut *self.transaction.borrow().await)
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Gets whether dependencies were recorded for at least one crate version
+    pub async fn get_has_dependencies(&self) -> Result<bool, ApiError> {
+        Ok(sqlx::query!("SELECT id FROM PackageDependency LIMIT 1")
+            .fetch_optional(&mut *self.transaction.borrow().await)
+            .await?
+            .is_some())
+    }
+
+    /// Gets the last versions of the crates that depend on a crate, preferring versions that are not yanked
+    pub async fn get_reverse_dependencies(&self, package: &str) -> Result<ReverseDependencies, ApiError> {
+        let _ = self.get_crate_targets(package).await?;
+        let rows = sqlx::query!(
+            "SELECT PackageDependency.package AS package, PackageDependency.version AS version, versionReq AS version_req, kind, optional
+            FROM PackageDependency INNER JOIN PackageVersion
+                ON PackageVersion.package = PackageDependency.package AND PackageVersion.version = PackageDependency.version
+            WHERE dependency = $1
+                AND PackageVersion.id IN (SELECT COALESCE(MAX(CASE WHEN yanked = FALSE THEN id END), MAX(id)) FROM PackageVersion GROUP BY package)
+            ORDER BY PackageDependency.package",
+            package
+        )
+        .fetch_all(&mut *self.transaction.borrow().await)
+        .await?;
+        let dependencies = rows
+            .into_iter()
+            .map(|row| ReverseDependency {
+                package: row.package,
+                version: row.version,
+                req: row.version_req,
+                kind: row.kind.parse().unwrap_or_default(),
+                optional: row.optional,
+            })
+            .collect::<Vec<_>>();
+        Ok(ReverseDependencies {
+            meta: ReverseDependenciesMeta {
+                total: dependencies.len(),
+            },
+            dependencies,
+        })
+    }
+
+    /// Gets the last published versions for a feed, optionally restricted to a crate or to the crates of an owner
+    pub async fn get_versions_feed(
+        &self,
+        package: Option<&str>,
+        owner: Option<i64>,
+        limit: i64,
+    ) -> Result<Vec<FeedEntry>, ApiError> {
+        let rows = sqlx::query!(
+            "SELECT package, version, description, upload, RegistryUser.name AS uploaded_by, yanked
+            FROM PackageVersion INNER JOIN RegistryUser ON PackageVersion.uploadedBy = RegistryUser.id
+            WHERE ($1 IS NULL OR package = $1)
+                AND ($2 IS NULL OR package IN (SELECT package FROM PackageOwner WHERE owner = $2))
+            ORDER BY upload DESC
+            LIMIT $3",
+            package,
+            owner,
+            limit
+        )
+        .fetch_all(&mut *self.transaction.borrow().await)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| FeedEntry {
+                package: row.package,
+                version: row.version,
+                description: row.description,
+                upload: row.upload,
+                uploaded_by: row.uploaded_by,
+                yanked: row.yanked,
+            })
+            .collect())
+    }
 }
@@ -0,0 +1,117 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Service for persisting information in the database
+//! API related to the profile and the preferences of users
+
+use std::collections::HashMap;
+
+use super::Database;
+use crate::model::cargo::RegistryUser;
+use crate::model::profile::{EmailNotification, UserPreferences, UserProfileUpdate};
+use crate::utils::apierror::ApiError;
+
+impl Database {
+    /// Gets the preferences of a user
+    pub async fn get_user_preferences(&self, uid: i64) -> Result<UserPreferences, ApiError> {
+        let row = sqlx::query_as!(
+            UserPreferences,
+            "SELECT notifyOwnerInvitations AS notify_owner_invitations, notifyDependencies AS notify_dependencies,
+                notifyDocsFailures AS notify_docs_failures, notifyWatches AS notify_watches,
+                tokenCanWrite AS token_can_write, tokenCanAdmin AS token_can_admin
+            FROM UserPreferences WHERE user = $1 LIMIT 1",
+            uid
+        )
+        .fetch_optional(&mut *self.transaction.borrow().await)
+        .await?;
+        Ok(row.unwrap_or_default())
+    }
+
+    /// Sets the preferences of a user
+    pub async fn set_user_preferences(&self, uid: i64, preferences: &UserPreferences) -> Result<(), ApiError> {
+        sqlx::query!("DELETE FROM UserPreferences WHERE user = $1", uid)
+            .execute(&mut *self.transaction.borrow().await)
+            .await?;
+        if preferences != &UserPreferences::default() {
+            sqlx::query!(
+                "INSERT INTO UserPreferences (user, notifyOwnerInvitations, notifyDependencies, notifyDocsFailures, notifyWatches, tokenCanWrite, tokenCanAdmin)
+                VALUES ($1, $2, $3, $4, $5, $6, $7)",
+                uid,
+                preferences.notify_owner_invitations,
+                preferences.notify_dependencies,
+                preferences.notify_docs_failures,
+                preferences.notify_watches,
+                preferences.token_can_write,
+                preferences.token_can_admin
+            )
+            .execute(&mut *self.transaction.borrow().await)
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Updates the profile of a user
+    pub async fn update_user_profile(&self, uid: i64, update: &UserProfileUpdate) -> Result<RegistryUser, ApiError> {
+        let name = update.validate()?;
+        sqlx::query!("UPDATE RegistryUser SET name = $2 WHERE id = $1", uid, name)
+            .execute(&mut *self.transaction.borrow().await)
+            .await?;
+        self.get_user_profile(uid).await
+    }
+
+    /// Keeps only the users that want to receive a kind of notification by email
+    pub async fn filter_users_notified(
+        &self,
+        users: Vec<RegistryUser>,
+        notification: EmailNotification,
+    ) -> Result<Vec<RegistryUser>, ApiError> {
+        // load the preferences of all the users at once, the identifiers being passed as a JSON array
+        let uids = serde_json::to_string(&users.iter().map(|user| user.id).collect::<Vec<_>>())?;
+        let preferences = sqlx::query!(
+            "SELECT user, notifyOwnerInvitations AS notify_owner_invitations, notifyDependencies AS notify_dependencies,
+                notifyDocsFailures AS notify_docs_failures, notifyWatches AS notify_watches,
+                tokenCanWrite AS token_can_write, tokenCanAdmin AS token_can_admin
+            FROM UserPreferences WHERE user IN (SELECT value FROM json_each($1))",
+            uids
+        )
+        .fetch_all(&mut *self.transaction.borrow().await)
+        .await?
+        .into_iter()
+        .map(|row| {
+            (
+                row.user,
+                UserPreferences {
+                    notify_owner_invitations: row.notify_owner_invitations,
+                    notify_dependencies: row.notify_dependencies,
+                    notify_docs_failures: row.notify_docs_failures,
+                    notify_watches: row.notify_watches,
+                    token_can_write: row.token_can_write,
+                    token_can_admin: row.token_can_admin,
+                },
+            )
+        })
+        .collect::<HashMap<_, _>>();
+        Ok(users
+            .into_iter()
+            .filter(|user| {
+                preferences.get(&user.id).map_or_else(
+                    || UserPreferences::default().wants_email(notification),
+                    |p| p.wants_email(notification),
+                )
+            })
+            .collect())
+    }
+
+    /// Gets the crates whose data show a user, as one of their owners or as the uploader of one of their versions
+    pub async fn get_crates_showing_user(&self, uid: i64) -> Result<Vec<String>, ApiError> {
+        let rows = sqlx::query!(
+            "SELECT package AS \"package!\" FROM PackageOwner WHERE owner = $1
+            UNION SELECT package AS \"package!\" FROM PackageVersion WHERE uploadedBy = $1",
+            uid
+        )
+        .fetch_all(&mut *self.transaction.borrow().await)
+        .await?;
+        Ok(rows.into_iter().map(|row| row.package).collect())
+    }
+}
@@ -0,0 +1,200 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Service for persisting information in the database
+//! API related to the quotas of users and teams
+
+use super::Database;
+use crate::model::auth::has_role;
+use crate::model::config::QuotaConfig;
+use crate::model::quotas::{QuotaUsage, TeamQuota, UserQuota};
+use crate::utils::apierror::{error_not_found, ApiError};
+
+impl Database {
+    /// Gets the identifier of the user targeted by an administrator, from their email
+    pub async fn get_quota_target(&self, target: &str) -> Result<i64, ApiError> {
+        Ok(sqlx::query!("SELECT id FROM RegistryUser WHERE email = $1", target)
+            .fetch_optional(&mut *self.transaction.borrow().await)
+            .await?
+            .ok_or_else(error_not_found)?
+            .id)
+    }
+
+    /// Gets the quotas set for a user, overriding the defaults
+    pub async fn get_user_quota(&self, uid: i64) -> Result<UserQuota, ApiError> {
+        let row = sqlx::query!(
+            "SELECT maxBytes AS max_bytes, maxCrates AS max_crates FROM UserQuota WHERE user = $1 LIMIT 1",
+            uid
+        )
+        .fetch_optional(&mut *self.transaction.borrow().await)
+        .await?;
+        Ok(row.map_or_else(UserQuota::default, |row| UserQuota {
+            max_bytes: row.max_bytes,
+            max_crates: row.max_crates,
+        }))
+    }
+
+    /// Sets the quotas for a user, identified by their email
+    pub async fn set_user_quota(&self, target: &str, quota: &UserQuota) -> Result<i64, ApiError> {
+        quota.validate()?;
+        let uid = self.get_quota_target(target).await?;
+        sqlx::query!("DELETE FROM UserQuota WHERE user = $1", uid)
+            .execute(&mut *self.transaction.borrow().await)
+            .await?;
+        if quota != &UserQuota::default() {
+            sqlx::query!(
+                "INSERT INTO UserQuota (user, maxBytes, maxCrates) VALUES ($1, $2, $3)",
+                uid,
+                quota.max_bytes,
+                quota.max_crates
+            )
+            .execute(&mut *self.transaction.borrow().await)
+            .await?;
+        }
+        Ok(uid)
+    }
+
+    /// Gets the usage of a user against the applicable quotas
+    pub async fn get_quota_usage(&self, uid: i64, defaults: &QuotaConfig) -> Result<QuotaUsage, ApiError> {
+        let bytes = sqlx::query!(
+            "SELECT SUM(size) AS \"bytes: i64\" FROM PackageVersion WHERE uploadedBy = $1",
+            uid
+        )
+        .fetch_one(&mut *self.transaction.borrow().await)
+        .await?
+        .bytes;
+        let crates = sqlx::query!("SELECT COUNT(id) AS crates FROM PackageOwner WHERE owner = $1", uid)
+            .fetch_one(&mut *self.transaction.borrow().await)
+            .await?
+            .crates;
+        let quota = self.get_user_quota(uid).await?;
+        Ok(QuotaUsage::new(bytes.unwrap_or_default(), crates, defaults, &quota))
+    }
+
+    /// Gets the identifiers of the members of a team, i.e. the active users having the team as a role
+    pub async fn get_team_members(&self, team: &str) -> Result<Vec<i64>, ApiError> {
+        let rows = sqlx::query!("SELECT id, roles FROM RegistryUser WHERE isActive = TRUE")
+            .fetch_all(&mut *self.transaction.borrow().await)
+            .await?;
+        Ok(rows
+            .into_iter()
+            .filter(|row| has_role(&row.roles, team))
+            .map(|row| row.id)
+            .collect())
+    }
+
+    /// Gets the quotas set for a team
+    pub async fn get_team_quota(&self, team: &str) -> Result<TeamQuota, ApiError> {
+        let row = sqlx::query!(
+            "SELECT maxBytes AS max_bytes, maxCrates AS max_crates FROM TeamQuota WHERE team = $1 LIMIT 1",
+            team
+        )
+        .fetch_optional(&mut *self.transaction.borrow().await)
+        .await?;
+        Ok(row.map_or_else(TeamQuota::default, |row| TeamQuota {
+            max_bytes: row.max_bytes,
+            max_crates: row.max_crates,
+        }))
+    }
+
+    /// Sets the quotas for a team
+    pub async fn set_team_quota(&self, team: &str, quota: &TeamQuota) -> Result<(), ApiError> {
+        quota.validate(team)?;
+        sqlx::query!("DELETE FROM TeamQuota WHERE team = $1", team)
+            .execute(&mut *self.transaction.borrow().await)
+            .await?;
+        if quota != &TeamQuota::default() {
+            sqlx::query!(
+                "INSERT INTO TeamQuota (team, maxBytes, maxCrates) VALUES ($1, $2, $3)",
+                team,
+                quota.max_bytes,
+                quota.max_crates
+            )
+            .execute(&mut *self.transaction.borrow().await)
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Gets the teams of a user that have quotas
+    pub async fn get_user_quota_teams(&self, uid: i64) -> Result<Vec<String>, ApiError> {
+        let roles = sqlx::query!("SELECT roles FROM RegistryUser WHERE id = $1", uid)
+            .fetch_optional(&mut *self.transaction.borrow().await)
+            .await?
+            .ok_or_else(error_not_found)?
+            .roles;
+        let rows = sqlx::query!("SELECT team FROM TeamQuota ORDER BY team")
+            .fetch_all(&mut *self.transaction.borrow().await)
+            .await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| row.team)
+            .filter(|team| has_role(&roles, team))
+            .collect())
+    }
+
+    /// Gets the users whose uploads count in the quotas applying to a user: the user and the members of their teams with quotas
+    pub async fn get_quota_scope(&self, uid: i64) -> Result<Vec<i64>, ApiError> {
+        let mut uids = vec![uid];
+        for team in self.get_user_quota_teams(uid).await? {
+            for member in self.get_team_members(&team).await? {
+                if !uids.contains(&member) {
+                    uids.push(member);
+                }
+            }
+        }
+        Ok(uids)
+    }
+
+    /// Gets the usage of the members of a team against the quotas of the team
+    pub async fn get_team_quota_usage(&self, team: &str) -> Result<QuotaUsage, ApiError> {
+        let members = serde_json::to_string(&self.get_team_members(team).await?)?;
+        let bytes = sqlx::query!(
+            "SELECT SUM(size) AS \"bytes: i64\" FROM PackageVersion WHERE uploadedBy IN (SELECT value FROM json_each($1))",
+            members
+        )
+        .fetch_one(&mut *self.transaction.borrow().await)
+        .await?
+        .bytes;
+        let crates = sqlx::query!(
+            "SELECT COUNT(DISTINCT package) AS crates FROM PackageOwner WHERE owner IN (SELECT value FROM json_each($1))",
+            members
+        )
+        .fetch_one(&mut *self.transaction.borrow().await)
+        .await?
+        .crates;
+        let quota = self.get_team_quota(team).await?;
+        Ok(QuotaUsage::for_team(team, bytes.unwrap_or_default(), crates, &quota))
+    }
+
+    /// Checks that a user can publish a version of a crate with the given size within the quotas,
+    /// their own and those of their teams
+    pub async fn check_publish_quota(
+        &self,
+        uid: i64,
+        package: &str,
+        size: i64,
+        defaults: &QuotaConfig,
+    ) -> Result<(), ApiError> {
+        let teams = self.get_user_quota_teams(uid).await?;
+        if defaults.max_bytes == 0
+            && defaults.max_crates == 0
+            && teams.is_empty()
+            && self.get_user_quota(uid).await? == UserQuota::default()
+        {
+            // no quota applies
+            return Ok(());
+        }
+        let lowercase = package.to_ascii_lowercase();
+        let is_new_crate = sqlx::query!("SELECT name FROM Package WHERE lowercase = $1 LIMIT 1", lowercase)
+            .fetch_optional(&mut *self.transaction.borrow().await)
+            .await?
+            .is_none();
+        self.get_quota_usage(uid, defaults).await?.check_publish(size, is_new_crate)?;
+        for team in teams {
+            self.get_team_quota_usage(&team).await?.check_publish(size, is_new_crate)?;
+        }
+        Ok(())
+    }
+}
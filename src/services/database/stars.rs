@@ -0,0 +1,85 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Service for persisting information in the database
+//! API related to the crates starred by users
+
+use std::collections::HashSet;
+
+use super::Database;
+use crate::model::CrateVersion;
+use crate::utils::apierror::{error_not_found, ApiError};
+
+/// The number of crates in the list of the most starred ones
+const MOST_STARRED_LENGTH: usize = 10;
+
+impl Database {
+    /// Gets the names of the crates starred by a user
+    pub async fn get_starred_crates(&self, uid: i64) -> Result<Vec<String>, ApiError> {
+        let rows = sqlx::query!("SELECT package FROM PackageStar WHERE user = $1 ORDER BY package", uid)
+            .fetch_all(&mut *self.transaction.borrow().await)
+            .await?;
+        Ok(rows.into_iter().map(|row| row.package).collect())
+    }
+
+    /// Stars a crate for a user
+    pub async fn star_crate(&self, uid: i64, package: &str) -> Result<(), ApiError> {
+        sqlx::query!("SELECT name FROM Package WHERE name = $1 LIMIT 1", package)
+            .fetch_optional(&mut *self.transaction.borrow().await)
+            .await?
+            .ok_or_else(error_not_found)?;
+        sqlx::query!(
+            "INSERT OR IGNORE INTO PackageStar (package, user) VALUES ($1, $2)",
+            package,
+            uid
+        )
+        .execute(&mut *self.transaction.borrow().await)
+        .await?;
+        Ok(())
+    }
+
+    /// Removes the star of a user on a crate
+    pub async fn unstar_crate(&self, uid: i64, package: &str) -> Result<(), ApiError> {
+        sqlx::query!("DELETE FROM PackageStar WHERE package = $1 AND user = $2", package, uid)
+            .execute(&mut *self.transaction.borrow().await)
+            .await?;
+        Ok(())
+    }
+
+    /// Gets the number of users that starred a crate
+    pub async fn get_crate_stars(&self, package: &str) -> Result<i64, ApiError> {
+        Ok(
+            sqlx::query!("SELECT COUNT(id) AS stars FROM PackageStar WHERE package = $1", package)
+                .fetch_one(&mut *self.transaction.borrow().await)
+                .await?
+                .stars,
+        )
+    }
+
+    /// Gets the most starred crates, without the given hidden crates
+    pub async fn get_most_starred_crates(&self, hidden: &HashSet<String>) -> Result<Vec<CrateVersion>, ApiError> {
+        // request more crates to make up for the hidden ones
+        #[allow(clippy::cast_possible_wrap)]
+        let limit = (MOST_STARRED_LENGTH + hidden.len()) as i64;
+        let rows = sqlx::query!(
+            "SELECT package, COUNT(id) AS stars
+            FROM PackageStar
+            GROUP BY package
+            ORDER BY stars DESC, package
+            LIMIT $1",
+            limit
+        )
+        .fetch_all(&mut *self.transaction.borrow().await)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .filter(|row| !hidden.contains(&row.package))
+            .take(MOST_STARRED_LENGTH)
+            .map(|row| CrateVersion {
+                package: row.package,
+                version: String::new(),
+            })
+            .collect())
+    }
+}
@@ -5,18 +5,36 @@
 //! Service for persisting information in the database
 //! API related to statistics
 
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+
+use chrono::{Days, Local, NaiveDateTime};
+
 use super::Database;
-use crate::model::stats::GlobalStats;
+use crate::model::cargo::RegistryUser;
+use crate::model::docs::DocGenJobState;
+use crate::model::stats::{
+    read_download_series, CrateStorageUsage, GlobalStats, PublishActivityReport, RegistryStats, StorageUsageReport,
+    TokenTraffic, TrafficReport, UserPublishActivity, UserStorageUsage, UserTraffic, SERIES_LENGTH, TRENDING_DAYS,
+};
 use crate::model::CrateVersion;
 use crate::utils::apierror::ApiError;
 
+/// The number of crates in each list of the global statistics
+const STATS_LIST_LENGTH: usize = 10;
+
 impl Database {
-    /// Gets the global statistics for the registry
-    pub async fn get_crates_stats(&self) -> Result<GlobalStats, ApiError> {
+    /// Gets the global statistics for the registry, without the given hidden crates
+    pub async fn get_crates_stats(&self, hidden: &HashSet<String>) -> Result<GlobalStats, ApiError> {
+        // request more crates to make up for the hidden ones
+        #[allow(clippy::cast_possible_wrap)]
+        let limit = (STATS_LIST_LENGTH + hidden.len()) as i64;
         let total_crates = sqlx::query!("SELECT COUNT(name) AS total_crates FROM Package")
             .fetch_one(&mut *self.transaction.borrow().await)
             .await?
             .total_crates;
+        #[allow(clippy::cast_possible_wrap)]
+        let total_crates = total_crates - hidden.len() as i64;
         let total_downloads = sqlx::query!("SELECT SUM(downloadCount) AS total_downloads FROM PackageVersion")
             .fetch_one(&mut *self.transaction.borrow().await)
             .await?
@@ -28,12 +46,15 @@ impl Database {
             FROM Package INNER JOIN PackageVersion ON package = name
             WHERE (SELECT COUNT(version) FROM PackageVersion WHERE package = name) = 1
             ORDER BY upload DESC
-            LIMIT 10"
+            LIMIT $1",
+            limit
         )
         .fetch_all(&mut *self.transaction.borrow().await)
         .await?;
         let crates_newest = rows
             .into_iter()
+            .filter(|row| !hidden.contains(&row.name))
+            .take(STATS_LIST_LENGTH)
             .map(|row| CrateVersion {
                 package: row.name,
                 version: row.version,
@@ -45,28 +66,38 @@ impl Database {
             FROM Package INNER JOIN PackageVersion ON package = name
             GROUP BY package
             ORDER BY download_count DESC
-            LIMIT 10"
+            LIMIT $1",
+            limit
         )
         .fetch_all(&mut *self.transaction.borrow().await)
         .await?;
         let crates_most_downloaded = rows
             .into_iter()
+            .filter(|row| !hidden.contains(&row.name))
+            .take(STATS_LIST_LENGTH)
             .map(|row| CrateVersion {
                 package: row.name,
                 version: String::new(),
             })
             .collect::<Vec<_>>();
 
+        let crates_most_starred = self.get_most_starred_crates(hidden).await?;
+
+        let crates_trending = self.get_trending_crates(hidden).await?;
+
         let rows = sqlx::query!(
             "SELECT package, version, upload
             FROM PackageVersion
             ORDER BY upload DESC
-            LIMIT 10"
+            LIMIT $1",
+            limit
         )
         .fetch_all(&mut *self.transaction.borrow().await)
         .await?;
         let crates_last_updated = rows
             .into_iter()
+            .filter(|row| !hidden.contains(&row.package))
+            .take(STATS_LIST_LENGTH)
             .map(|row| CrateVersion {
                 package: row.package,
                 version: row.version,
@@ -78,7 +109,270 @@ impl Database {
             total_crates,
             crates_newest,
             crates_most_downloaded,
+            crates_most_starred,
+            crates_trending,
             crates_last_updated,
         })
     }
+
+    /// Gets the most downloaded crates over the last days, see `TRENDING_DAYS`, without the given hidden crates
+    async fn get_trending_crates(&self, hidden: &HashSet<String>) -> Result<Vec<CrateVersion>, ApiError> {
+        let rows = sqlx::query!("SELECT package, downloads FROM PackageVersion WHERE downloads IS NOT NULL")
+            .fetch_all(&mut *self.transaction.borrow().await)
+            .await?;
+        let mut recent_downloads = HashMap::<String, u32>::new();
+        for row in rows {
+            let series = read_download_series(row.downloads.as_deref());
+            let count = series[(SERIES_LENGTH - TRENDING_DAYS)..].iter().sum::<u32>();
+            if count > 0 && !hidden.contains(&row.package) {
+                *recent_downloads.entry(row.package).or_default() += count;
+            }
+        }
+        let mut recent_downloads = recent_downloads.into_iter().collect::<Vec<_>>();
+        recent_downloads.sort_by(|(name_a, count_a), (name_b, count_b)| count_b.cmp(count_a).then_with(|| name_a.cmp(name_b)));
+        Ok(recent_downloads
+            .into_iter()
+            .take(STATS_LIST_LENGTH)
+            .map(|(package, _)| CrateVersion {
+                package,
+                version: String::new(),
+            })
+            .collect())
+    }
+
+    /// Gets the registry-wide statistics, for administrators
+    pub async fn get_registry_stats(&self) -> Result<RegistryStats, ApiError> {
+        let total_crates = sqlx::query!("SELECT COUNT(name) AS total_crates FROM Package")
+            .fetch_one(&mut *self.transaction.borrow().await)
+            .await?
+            .total_crates;
+        let row = sqlx::query!(
+            "SELECT COUNT(id) AS total_versions, SUM(size) AS storage_used, COUNT(id) - COUNT(size) AS storage_unknown_versions FROM PackageVersion"
+        )
+        .fetch_one(&mut *self.transaction.borrow().await)
+        .await?;
+        let total_users = sqlx::query!("SELECT COUNT(id) AS total_users FROM RegistryUser WHERE isActive = TRUE")
+            .fetch_one(&mut *self.transaction.borrow().await)
+            .await?
+            .total_users;
+        let total_tokens = sqlx::query!(
+            "SELECT (SELECT COUNT(id) FROM RegistryUserToken) + (SELECT COUNT(id) FROM RegistryGlobalToken) AS total_tokens"
+        )
+        .fetch_one(&mut *self.transaction.borrow().await)
+        .await?
+        .total_tokens;
+
+        let today = Local::now().naive_local().date();
+        let first = today.checked_sub_days(Days::new(SERIES_LENGTH as u64 - 1)).unwrap();
+        let days = first.iter_days().take(SERIES_LENGTH).collect::<Vec<_>>();
+        let mut publishes_per_day = vec![0; SERIES_LENGTH];
+        let from = first.and_hms_opt(0, 0, 0).unwrap();
+        let rows = sqlx::query!("SELECT upload FROM PackageVersion WHERE upload >= $1", from)
+            .fetch_all(&mut *self.transaction.borrow().await)
+            .await?;
+        for row in rows {
+            if let Ok(index) = usize::try_from((row.upload.date() - first).num_days()) {
+                if let Some(count) = publishes_per_day.get_mut(index) {
+                    *count += 1;
+                }
+            }
+        }
+
+        let state_queued = DocGenJobState::Queued.value();
+        let state_working = DocGenJobState::Working.value();
+        let docs = sqlx::query!(
+            "SELECT SUM(CASE WHEN state = $1 THEN 1 ELSE 0 END) AS queued, SUM(CASE WHEN state = $2 THEN 1 ELSE 0 END) AS working FROM DocGenJob",
+            state_queued,
+            state_working
+        )
+        .fetch_one(&mut *self.transaction.borrow().await)
+        .await?;
+
+        Ok(RegistryStats {
+            total_crates,
+            total_versions: row.total_versions,
+            storage_used: row.storage_used.unwrap_or_default(),
+            storage_unknown_versions: row.storage_unknown_versions,
+            total_users,
+            total_tokens,
+            days,
+            publishes_per_day,
+            docs_queued: docs.queued.unwrap_or_default(),
+            docs_working: docs.working.unwrap_or_default(),
+        })
+    }
+
+    /// Gets the storage used by each crate and each publishing user
+    pub async fn get_storage_usage(&self) -> Result<StorageUsageReport, ApiError> {
+        let rows = sqlx::query!(
+            "SELECT PackageVersion.package AS \"package!\", COUNT(PackageVersion.id) AS versions, SUM(PackageVersion.size) AS crates_size,
+                    MAX(Docs.size) AS \"docs_size: i64\"
+            FROM PackageVersion
+            LEFT JOIN (SELECT package, SUM(size) AS size FROM PackageVersionDocs GROUP BY package) AS Docs
+                ON Docs.package = PackageVersion.package
+            GROUP BY PackageVersion.package"
+        )
+        .fetch_all(&mut *self.transaction.borrow().await)
+        .await?;
+        let mut crates = rows
+            .into_iter()
+            .map(|row| CrateStorageUsage {
+                package: row.package,
+                versions: row.versions,
+                crates_size: row.crates_size.unwrap_or_default(),
+                docs_size: row.docs_size.unwrap_or_default(),
+            })
+            .collect::<Vec<_>>();
+        crates.sort_by_key(|usage| Reverse(usage.crates_size + usage.docs_size));
+
+        let rows = sqlx::query!(
+            "SELECT RegistryUser.id AS uid, isActive AS is_active, email, login, name, roles,
+                    COUNT(PackageVersion.id) AS versions, SUM(PackageVersion.size) AS crates_size,
+                    SUM(Docs.size) AS \"docs_size: i64\"
+            FROM PackageVersion
+            INNER JOIN RegistryUser ON RegistryUser.id = PackageVersion.uploadedBy
+            LEFT JOIN (SELECT package, version, SUM(size) AS size FROM PackageVersionDocs GROUP BY package, version) AS Docs
+                ON Docs.package = PackageVersion.package AND Docs.version = PackageVersion.version
+            GROUP BY RegistryUser.id"
+        )
+        .fetch_all(&mut *self.transaction.borrow().await)
+        .await?;
+        let mut users = rows
+            .into_iter()
+            .map(|row| UserStorageUsage {
+                user: RegistryUser {
+                    id: row.uid,
+                    is_active: row.is_active,
+                    email: row.email,
+                    login: row.login,
+                    name: row.name,
+                    roles: row.roles,
+                },
+                versions: row.versions,
+                crates_size: row.crates_size.unwrap_or_default(),
+                docs_size: row.docs_size.unwrap_or_default(),
+            })
+            .collect::<Vec<_>>();
+        users.sort_by_key(|usage| Reverse(usage.crates_size + usage.docs_size));
+        Ok(StorageUsageReport { crates, users })
+    }
+
+    /// Gets the publish activity of each user and the crates without active owner
+    pub async fn get_publish_activity(&self) -> Result<PublishActivityReport, ApiError> {
+        let since = Local::now()
+            .naive_local()
+            .checked_sub_days(Days::new(SERIES_LENGTH as u64))
+            .unwrap();
+        let rows = sqlx::query!(
+            "SELECT id, isActive AS is_active, email, login, name, roles,
+                    (SELECT COUNT(id) FROM PackageVersion WHERE uploadedBy = RegistryUser.id) AS \"publishes!: i64\",
+                    (SELECT COUNT(id) FROM PackageVersion WHERE uploadedBy = RegistryUser.id AND upload >= $1) AS \"publishes_recent!: i64\",
+                    (SELECT MAX(upload) FROM PackageVersion WHERE uploadedBy = RegistryUser.id) AS \"last_publish: NaiveDateTime\",
+                    (SELECT COUNT(id) FROM PackageOwner WHERE owner = RegistryUser.id) AS \"crates_owned!: i64\"
+            FROM RegistryUser",
+            since
+        )
+        .fetch_all(&mut *self.transaction.borrow().await)
+        .await?;
+        let mut users = rows
+            .into_iter()
+            .map(|row| UserPublishActivity {
+                user: RegistryUser {
+                    id: row.id,
+                    is_active: row.is_active,
+                    email: row.email,
+                    login: row.login,
+                    name: row.name,
+                    roles: row.roles,
+                },
+                publishes: row.publishes,
+                publishes_recent: row.publishes_recent,
+                last_publish: row.last_publish,
+                crates_owned: row.crates_owned,
+            })
+            .collect::<Vec<_>>();
+        users.sort_by_key(|activity| Reverse(activity.last_publish));
+
+        let crates_without_active_owner = sqlx::query!(
+            "SELECT name FROM Package
+            WHERE NOT EXISTS (
+                SELECT PackageOwner.id FROM PackageOwner INNER JOIN RegistryUser ON RegistryUser.id = PackageOwner.owner
+                WHERE PackageOwner.package = Package.name AND RegistryUser.isActive = TRUE
+            )
+            ORDER BY name"
+        )
+        .fetch_all(&mut *self.transaction.borrow().await)
+        .await?
+        .into_iter()
+        .map(|row| row.name)
+        .collect();
+        Ok(PublishActivityReport {
+            users,
+            crates_without_active_owner,
+        })
+    }
+
+    /// Gets the requests served for each user and token
+    pub async fn get_traffic_report(&self) -> Result<TrafficReport, ApiError> {
+        let rows = sqlx::query!(
+            "SELECT id, isActive AS is_active, email, login, name, roles,
+                    requestCount + COALESCE((SELECT SUM(requestCount) FROM RegistryUserToken WHERE user = RegistryUser.id), 0) AS \"requests!: i64\",
+                    bytesServed + COALESCE((SELECT SUM(bytesServed) FROM RegistryUserToken WHERE user = RegistryUser.id), 0) AS \"bytes!: i64\"
+            FROM RegistryUser"
+        )
+        .fetch_all(&mut *self.transaction.borrow().await)
+        .await?;
+        let mut users = rows
+            .into_iter()
+            .map(|row| UserTraffic {
+                user: RegistryUser {
+                    id: row.id,
+                    is_active: row.is_active,
+                    email: row.email,
+                    login: row.login,
+                    name: row.name,
+                    roles: row.roles,
+                },
+                requests: row.requests,
+                bytes: row.bytes,
+            })
+            .collect::<Vec<_>>();
+        users.sort_by_key(|traffic| Reverse(traffic.bytes));
+
+        let rows = sqlx::query!(
+            "SELECT id, user, name, lastUsed AS last_used, requestCount AS request_count, bytesServed AS bytes_served FROM RegistryUserToken"
+        )
+        .fetch_all(&mut *self.transaction.borrow().await)
+        .await?;
+        let mut tokens = Vec::with_capacity(rows.len());
+        for row in rows {
+            let user = users
+                .iter()
+                .find(|traffic| traffic.user.id == row.user)
+                .map(|traffic| traffic.user.clone());
+            tokens.push(TokenTraffic {
+                id: row.id,
+                name: row.name,
+                user,
+                last_used: row.last_used,
+                requests: row.request_count,
+                bytes: row.bytes_served,
+            });
+        }
+        let rows = sqlx::query!(
+            "SELECT id, name, lastUsed AS last_used, requestCount AS request_count, bytesServed AS bytes_served FROM RegistryGlobalToken"
+        )
+        .fetch_all(&mut *self.transaction.borrow().await)
+        .await?;
+        tokens.extend(rows.into_iter().map(|row| TokenTraffic {
+            id: row.id,
+            name: row.name,
+            user: None,
+            last_used: row.last_used,
+            requests: row.request_count,
+            bytes: row.bytes_served,
+        }));
+        tokens.sort_by_key(|traffic| Reverse(traffic.bytes));
+        Ok(TrafficReport { users, tokens })
+    }
 }
@@ -7,16 +7,18 @@
 
 use std::future::Future;
 
-use chrono::Local;
+use chrono::{Duration, Local};
 
 use super::Database;
 use crate::model::auth::{
-    find_field_in_blob, Authentication, AuthenticationPrincipal, OAuthToken, RegistryUserToken, RegistryUserTokenWithSecret,
-    TokenKind, TokenUsage, ROLE_ADMIN,
+    find_field_in_blob, Authentication, AuthenticationPrincipal, ImpersonationRequest, ImpersonationToken, OAuthToken,
+    ProvisioningRule, RegistryUserToken, RegistryUserTokenWithSecret, TokenKind, TokenUsage, TrafficSubject,
+    IMPERSONATION_MAX_DURATION, ROLE_ADMIN,
 };
 use crate::model::cargo::RegistryUser;
 use crate::model::config::Configuration;
 use crate::model::namegen::generate_name;
+use crate::model::pagination::{filter_to_like_pattern, Page, PageMeta, Pagination, TokenSort, UserSort};
 use crate::utils::apierror::{
     error_conflict, error_forbidden, error_invalid_request, error_not_found, error_unauthorized, specialize, ApiError,
 };
@@ -35,9 +37,21 @@ impl Database {
         maybe_row.ok_or_else(error_not_found)
     }
 
+    /// Gets the profile of a user by its login
+    pub async fn get_user_profile_by_login(&self, login: &str) -> Result<RegistryUser, ApiError> {
+        let maybe_row = sqlx::query_as!(
+            RegistryUser,
+            "SELECT id, isActive AS is_active, email, login, name, roles FROM RegistryUser WHERE login = $1",
+            login
+        )
+        .fetch_optional(&mut *self.transaction.borrow().await)
+        .await?;
+        maybe_row.ok_or_else(error_not_found)
+    }
+
     /// Attempts to login using an OAuth code
     pub async fn login_with_oauth_code(&self, configuration: &Configuration, code: &str) -> Result<RegistryUser, ApiError> {
-        let client = reqwest::Client::new();
+        let client = crate::utils::http_client_builder().build()?;
         // retrieve the token
         let response = client
             .post(&configuration.oauth_token_uri)
@@ -96,17 +110,14 @@ impl Database {
             .fetch_one(&mut *self.transaction.borrow().await)
             .await?
             .count;
-        let mut login = email[..email.find('@').unwrap()].to_string();
-        while sqlx::query!("SELECT COUNT(id) AS count FROM RegistryUser WHERE login = $1", login)
-            .fetch_one(&mut *self.transaction.borrow().await)
-            .await?
-            .count
-            != 0
-        {
-            login = generate_name();
-        }
+        let login = self.get_available_login(email).await?;
         let full_name = find_field_in_blob(&user_info, &configuration.oauth_userinfo_path_fullname).unwrap_or(&login);
-        let roles = if count == 0 { ROLE_ADMIN } else { "" };
+        let provisioning = self.get_provisioning_rules().await?;
+        let roles = if count == 0 {
+            ROLE_ADMIN
+        } else {
+            ProvisioningRule::resolve_roles(&provisioning, email).unwrap_or_default()
+        };
         let id = sqlx::query!(
             "INSERT INTO RegistryUser (isActive, email, login, name, roles) VALUES (TRUE, $1, $2, $3, $4) RETURNING id",
             email,
@@ -121,21 +132,121 @@ impl Database {
             id,
             is_active: true,
             email: email.to_string(),
-            name: login.to_string(),
+            name: login.clone(),
             login,
             roles: roles.to_string(),
         })
     }
 
-    /// Gets the known users
-    pub async fn get_users(&self) -> Result<Vec<RegistryUser>, ApiError> {
-        let rows = sqlx::query_as!(
+    /// Gets a login that is not used yet for a new user, from the local part of the email when possible
+    async fn get_available_login(&self, email: &str) -> Result<String, ApiError> {
+        let mut login = email.split('@').next().unwrap_or_default().to_string();
+        while login.is_empty()
+            || sqlx::query!("SELECT COUNT(id) AS count FROM RegistryUser WHERE login = $1", login)
+                .fetch_one(&mut *self.transaction.borrow().await)
+                .await?
+                .count
+                != 0
+        {
+            login = generate_name();
+        }
+        Ok(login)
+    }
+
+    /// Creates an active user with the admin role, or gives the admin role to an existing user and reactivates them
+    pub async fn create_or_promote_admin(&self, email: &str, name: Option<&str>) -> Result<RegistryUser, ApiError> {
+        let row = sqlx::query!(
+            "SELECT id, login, name, roles FROM RegistryUser WHERE email = $1 LIMIT 1",
+            email
+        )
+        .fetch_optional(&mut *self.transaction.borrow().await)
+        .await?;
+        if let Some(row) = row {
+            let mut roles = row.roles;
+            if roles.split(',').all(|role| role.trim() != ROLE_ADMIN) {
+                roles = if roles.trim().is_empty() {
+                    ROLE_ADMIN.to_string()
+                } else {
+                    format!("{roles},{ROLE_ADMIN}")
+                };
+            }
+            sqlx::query!(
+                "UPDATE RegistryUser SET isActive = TRUE, roles = $2 WHERE id = $1",
+                row.id,
+                roles
+            )
+            .execute(&mut *self.transaction.borrow().await)
+            .await?;
+            return Ok(RegistryUser {
+                id: row.id,
+                is_active: true,
+                email: email.to_string(),
+                login: row.login,
+                name: row.name,
+                roles,
+            });
+        }
+        if !email.contains('@') {
+            return Err(specialize(error_invalid_request(), format!("invalid email {email}")));
+        }
+        let login = self.get_available_login(email).await?;
+        let name = name.unwrap_or(&login).to_string();
+        let id = sqlx::query!(
+            "INSERT INTO RegistryUser (isActive, email, login, name, roles) VALUES (TRUE, $1, $2, $3, $4) RETURNING id",
+            email,
+            login,
+            name,
+            ROLE_ADMIN
+        )
+        .fetch_one(&mut *self.transaction.borrow().await)
+        .await?
+        .id;
+        Ok(RegistryUser {
+            id,
+            is_active: true,
+            email: email.to_string(),
+            login,
+            name,
+            roles: ROLE_ADMIN.to_string(),
+        })
+    }
+
+    /// Gets a page of the known users, optionally only those whose login, name or email match a filter
+    pub async fn get_users(
+        &self,
+        filter: Option<&str>,
+        pagination: Pagination<UserSort>,
+    ) -> Result<Page<RegistryUser>, ApiError> {
+        let pattern = filter_to_like_pattern(filter);
+        let total = sqlx::query!(
+            "SELECT COUNT(id) AS total FROM RegistryUser
+            WHERE login LIKE $1 ESCAPE '\\' OR name LIKE $1 ESCAPE '\\' OR email LIKE $1 ESCAPE '\\'",
+            pattern
+        )
+        .fetch_one(&mut *self.transaction.borrow().await)
+        .await?
+        .total;
+        let sort = pagination.sort.sql_key();
+        let (limit, offset) = pagination.sql_bounds();
+        let items = sqlx::query_as!(
             RegistryUser,
-            "SELECT id, isActive AS is_active, email, login, name, roles FROM RegistryUser ORDER BY login",
+            "SELECT id, isActive AS is_active, email, login, name, roles FROM RegistryUser
+            WHERE login LIKE $1 ESCAPE '\\' OR name LIKE $1 ESCAPE '\\' OR email LIKE $1 ESCAPE '\\'
+            ORDER BY CASE $2 WHEN 'name' THEN name WHEN 'email' THEN email ELSE login END, id
+            LIMIT $3 OFFSET $4",
+            pattern,
+            sort,
+            limit,
+            offset
         )
         .fetch_all(&mut *self.transaction.borrow().await)
         .await?;
-        Ok(rows)
+        Ok(Page {
+            items,
+            meta: PageMeta {
+                total: usize::try_from(total).unwrap_or_default(),
+            },
+        })
     }
 
     /// Updates the information of a user
@@ -221,33 +332,76 @@ impl Database {
         sqlx::query!("DELETE FROM RegistryUserToken WHERE user = $1", target_uid)
             .execute(&mut *self.transaction.borrow().await)
             .await?;
+        sqlx::query!("DELETE FROM Impersonation WHERE user = $1 OR impersonator = $1", target_uid)
+            .execute(&mut *self.transaction.borrow().await)
+            .await?;
         sqlx::query!("DELETE FROM PackageOwner WHERE owner = $1", target_uid)
             .execute(&mut *self.transaction.borrow().await)
             .await?;
+        sqlx::query!("DELETE FROM UserQuota WHERE user = $1", target_uid)
+            .execute(&mut *self.transaction.borrow().await)
+            .await?;
+        sqlx::query!("DELETE FROM UserPreferences WHERE user = $1", target_uid)
+            .execute(&mut *self.transaction.borrow().await)
+            .await?;
+        sqlx::query!("DELETE FROM PackageReader WHERE user = $1", target_uid)
+            .execute(&mut *self.transaction.borrow().await)
+            .await?;
+        sqlx::query!("DELETE FROM PackageStar WHERE user = $1", target_uid)
+            .execute(&mut *self.transaction.borrow().await)
+            .await?;
         sqlx::query!("DELETE FROM RegistryUser WHERE id = $1", target_uid)
             .execute(&mut *self.transaction.borrow().await)
             .await?;
         Ok(())
     }
 
-    /// Gets the tokens for a user
-    pub async fn get_tokens(&self, uid: i64) -> Result<Vec<RegistryUserToken>, ApiError> {
+    /// Gets a page of the tokens for a user
+    pub async fn get_tokens(
+        &self,
+        uid: i64,
+        filter: Option<&str>,
+        pagination: Pagination<TokenSort>,
+    ) -> Result<Page<RegistryUserToken>, ApiError> {
+        let pattern = filter_to_like_pattern(filter);
+        let total = sqlx::query!(
+            "SELECT COUNT(id) AS total FROM RegistryUserToken WHERE user = $1 AND name LIKE $2 ESCAPE '\\'",
+            uid,
+            pattern
+        )
+        .fetch_one(&mut *self.transaction.borrow().await)
+        .await?
+        .total;
+        let sort = pagination.sort.sql_key();
+        let (limit, offset) = pagination.sql_bounds();
         let rows = sqlx::query!(
-            "SELECT id, name, lastUsed AS last_used, canWrite AS can_write, canAdmin AS can_admin FROM RegistryUserToken WHERE user = $1 ORDER BY id",
-            uid
+            "SELECT id, name, lastUsed AS last_used, canWrite AS can_write, canAdmin AS can_admin FROM RegistryUserToken
+            WHERE user = $1 AND name LIKE $2 ESCAPE '\\'
+            ORDER BY CASE WHEN $3 = 'name' THEN name END, CASE WHEN $3 = 'last-used' THEN lastUsed END DESC, CASE WHEN $3 = 'newest' THEN id END DESC, id
+            LIMIT $4 OFFSET $5",
+            uid,
+            pattern,
+            sort,
+            limit,
+            offset
         )
         .fetch_all(&mut *self.transaction.borrow().await)
         .await?;
-        Ok(rows
-            .into_iter()
-            .map(|row| RegistryUserToken {
-                id: row.id,
-                name: row.name,
-                last_used: row.last_used,
-                can_write: row.can_write,
-                can_admin: row.can_admin,
-            })
-            .collect())
+        Ok(Page {
+            items: rows
+                .into_iter()
+                .map(|row| RegistryUserToken {
+                    id: row.id,
+                    name: row.name,
+                    last_used: row.last_used,
+                    can_write: row.can_write,
+                    can_admin: row.can_admin,
+                })
+                .collect(),
+            meta: PageMeta {
+                total: usize::try_from(total).unwrap_or_default(),
+            },
+        })
     }
 
     /// Creates a token for the current user
@@ -303,9 +457,109 @@ impl Database {
         if let Some(auth) = self.check_token_user(login, token_secret, &on_usage).await? {
             return Ok(auth);
         }
+        if let Some(auth) = self.check_token_impersonation(login, token_secret).await? {
+            return Ok(auth);
+        }
         Err(error_unauthorized())
     }
 
+    /// Checks whether the information provided is an impersonation token that has not expired
+    async fn check_token_impersonation(&self, login: &str, token_secret: &str) -> Result<Option<Authentication>, ApiError> {
+        let now = Local::now().naive_local();
+        let rows = sqlx::query!(
+            "SELECT RegistryUser.id AS uid, email, impersonator, token, canWrite AS can_write
+            FROM RegistryUser INNER JOIN Impersonation ON RegistryUser.id = Impersonation.user
+            WHERE isActive = TRUE AND login = $1 AND expiresOn > $2",
+            login,
+            now
+        )
+        .fetch_all(&mut *self.transaction.borrow().await)
+        .await?;
+        for row in rows {
+            if check_hash(token_secret, &row.token).is_ok() {
+                return Ok(Some(Authentication {
+                    principal: AuthenticationPrincipal::User {
+                        uid: row.uid,
+                        email: row.email,
+                    },
+                    can_write: row.can_write,
+                    can_admin: false,
+                    impersonator: Some(row.impersonator),
+                }));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Starts the impersonation of a user by an administrator
+    pub async fn create_impersonation(
+        &self,
+        impersonator: i64,
+        target: &str,
+        request: &ImpersonationRequest,
+    ) -> Result<(i64, ImpersonationToken), ApiError> {
+        if request.duration_minutes <= 0 || request.duration_minutes > IMPERSONATION_MAX_DURATION {
+            return Err(specialize(
+                error_invalid_request(),
+                format!("the duration must be between 1 and {IMPERSONATION_MAX_DURATION} minutes"),
+            ));
+        }
+        if request.reason.trim().is_empty() {
+            return Err(specialize(error_invalid_request(), String::from("a reason must be given")));
+        }
+        let row = sqlx::query!(
+            "SELECT id, login FROM RegistryUser WHERE email = $1 AND isActive = TRUE",
+            target
+        )
+        .fetch_optional(&mut *self.transaction.borrow().await)
+        .await?
+        .ok_or_else(error_not_found)?;
+        if row.id == impersonator {
+            return Err(specialize(error_forbidden(), String::from("cannot impersonate self")));
+        }
+        let token_secret = generate_token(64);
+        let token_hash = hash_token(&token_secret);
+        let now = Local::now().naive_local();
+        let expires_on = now + Duration::minutes(request.duration_minutes);
+        let id = sqlx::query!(
+            "INSERT INTO Impersonation (impersonator, user, token, canWrite, reason, createdOn, expiresOn) VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING id",
+            impersonator,
+            row.id,
+            token_hash,
+            request.can_write,
+            request.reason,
+            now,
+            expires_on
+        )
+        .fetch_one(&mut *self.transaction.borrow().await)
+        .await?
+        .id;
+        Ok((
+            row.id,
+            ImpersonationToken {
+                id,
+                login: row.login,
+                secret: token_secret,
+                can_write: request.can_write,
+                expires_on,
+            },
+        ))
+    }
+
+    /// Ends an impersonation before its expiration, returns the uid of the impersonated user
+    pub async fn end_impersonation(&self, impersonation_id: i64) -> Result<i64, ApiError> {
+        let now = Local::now().naive_local();
+        let row = sqlx::query!(
+            "UPDATE Impersonation SET expiresOn = $2 WHERE id = $1 AND expiresOn > $2 RETURNING user",
+            impersonation_id,
+            now
+        )
+        .fetch_optional(&mut *self.transaction.borrow().await)
+        .await?
+        .ok_or_else(error_not_found)?;
+        Ok(row.user)
+    }
+
     /// Checks whether the information provided is a user token
     async fn check_token_user<F, FUT>(
         &self,
@@ -341,6 +595,7 @@ impl Database {
                     },
                     can_write: row.can_write,
                     can_admin: row.can_admin,
+                    impersonator: None,
                 }));
             }
         }
@@ -397,4 +652,41 @@ impl Database {
         }
         Ok(())
     }
+
+    /// Adds served requests to the traffic of a subject
+    pub async fn add_traffic(&self, subject: TrafficSubject, requests: i64, bytes: i64) -> Result<(), ApiError> {
+        match subject {
+            TrafficSubject::Token(TokenKind::User, token_id) => {
+                sqlx::query!(
+                    "UPDATE RegistryUserToken SET requestCount = requestCount + $2, bytesServed = bytesServed + $3 WHERE id = $1",
+                    token_id,
+                    requests,
+                    bytes
+                )
+                .execute(&mut *self.transaction.borrow().await)
+                .await?;
+            }
+            TrafficSubject::Token(TokenKind::Registry, token_id) => {
+                sqlx::query!(
+                    "UPDATE RegistryGlobalToken SET requestCount = requestCount + $2, bytesServed = bytesServed + $3 WHERE id = $1",
+                    token_id,
+                    requests,
+                    bytes
+                )
+                .execute(&mut *self.transaction.borrow().await)
+                .await?;
+            }
+            TrafficSubject::User(uid) => {
+                sqlx::query!(
+                    "UPDATE RegistryUser SET requestCount = requestCount + $2, bytesServed = bytesServed + $3 WHERE id = $1",
+                    uid,
+                    requests,
+                    bytes
+                )
+                .execute(&mut *self.transaction.borrow().await)
+                .await?;
+            }
+        }
+        Ok(())
+    }
 }
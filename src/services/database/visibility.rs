@@ -0,0 +1,179 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Service for persisting information in the database
+//! API related to the visibility of crates
+
+use std::collections::HashSet;
+
+use super::Database;
+use crate::model::auth::{has_role, is_valid_role};
+use crate::model::cargo::RegistryUser;
+use crate::model::packages::{CrateVisibility, CrateVisibilityQuery};
+use crate::utils::apierror::{error_invalid_request, error_not_found, specialize, ApiError};
+
+impl Database {
+    /// Gets whether a crate is restricted, looking it up by its name in any case
+    pub async fn get_crate_is_restricted(&self, package: &str) -> Result<bool, ApiError> {
+        let lowercase = package.to_ascii_lowercase();
+        let row = sqlx::query!(
+            "SELECT isRestricted AS is_restricted FROM Package WHERE lowercase = $1 LIMIT 1",
+            lowercase
+        )
+        .fetch_optional(&mut *self.transaction.borrow().await)
+        .await?;
+        Ok(row.is_some_and(|row| row.is_restricted))
+    }
+
+    /// Checks that a user can read a crate
+    ///
+    /// A restricted crate can only be read by its owners, its readers, the members of its teams and the administrators.
+    /// For the other users, the crate is reported as not found so that its existence is not disclosed.
+    pub async fn check_can_read_crate(&self, uid: i64, package: &str) -> Result<(), ApiError> {
+        if !self.get_crate_is_restricted(package).await? || self.get_is_admin(uid).await? {
+            return Ok(());
+        }
+        let lowercase = package.to_ascii_lowercase();
+        let row = sqlx::query!(
+            "SELECT name FROM Package WHERE lowercase = $1 AND (
+                EXISTS (SELECT id FROM PackageOwner WHERE PackageOwner.package = Package.name AND owner = $2)
+                OR EXISTS (SELECT id FROM PackageReader WHERE PackageReader.package = Package.name AND user = $2)
+            ) LIMIT 1",
+            lowercase,
+            uid
+        )
+        .fetch_optional(&mut *self.transaction.borrow().await)
+        .await?;
+        if row.is_some() {
+            return Ok(());
+        }
+        let roles = self.get_user_roles(uid).await?;
+        let teams = sqlx::query!(
+            "SELECT team FROM PackageReaderTeam INNER JOIN Package ON PackageReaderTeam.package = Package.name WHERE lowercase = $1",
+            lowercase
+        )
+        .fetch_all(&mut *self.transaction.borrow().await)
+        .await?;
+        if teams.iter().any(|row| has_role(&roles, &row.team)) {
+            return Ok(());
+        }
+        Err(error_not_found())
+    }
+
+    /// Gets the names of the restricted crates that a user cannot read
+    pub async fn get_hidden_crates(&self, uid: i64) -> Result<HashSet<String>, ApiError> {
+        if self.get_is_admin(uid).await? {
+            return Ok(HashSet::new());
+        }
+        let rows = sqlx::query!(
+            "SELECT name FROM Package WHERE isRestricted = TRUE
+                AND NOT EXISTS (SELECT id FROM PackageOwner WHERE PackageOwner.package = Package.name AND owner = $1)
+                AND NOT EXISTS (SELECT id FROM PackageReader WHERE PackageReader.package = Package.name AND user = $1)",
+            uid
+        )
+        .fetch_all(&mut *self.transaction.borrow().await)
+        .await?;
+        let mut hidden = rows.into_iter().map(|row| row.name).collect::<HashSet<_>>();
+        if hidden.is_empty() {
+            return Ok(hidden);
+        }
+        // the crates readable by the teams of the user
+        let roles = self.get_user_roles(uid).await?;
+        let teams = sqlx::query!("SELECT package, team FROM PackageReaderTeam")
+            .fetch_all(&mut *self.transaction.borrow().await)
+            .await?;
+        for row in teams {
+            if has_role(&roles, &row.team) {
+                hidden.remove(&row.package);
+            }
+        }
+        Ok(hidden)
+    }
+
+    /// Gets the roles of a user, as a comma-separated list
+    async fn get_user_roles(&self, uid: i64) -> Result<String, ApiError> {
+        Ok(sqlx::query!("SELECT roles FROM RegistryUser WHERE id = $1", uid)
+            .fetch_optional(&mut *self.transaction.borrow().await)
+            .await?
+            .ok_or_else(error_not_found)?
+            .roles)
+    }
+
+    /// Gets the visibility of a crate
+    pub async fn get_crate_visibility(&self, package: &str) -> Result<CrateVisibility, ApiError> {
+        let is_restricted = sqlx::query!(
+            "SELECT isRestricted AS is_restricted FROM Package WHERE name = $1 LIMIT 1",
+            package
+        )
+        .fetch_optional(&mut *self.transaction.borrow().await)
+        .await?
+        .ok_or_else(error_not_found)?
+        .is_restricted;
+        let readers = sqlx::query_as!(
+            RegistryUser,
+            "SELECT RegistryUser.id, isActive AS is_active, email, login, name, roles FROM RegistryUser INNER JOIN PackageReader ON PackageReader.user = RegistryUser.id WHERE package = $1 ORDER BY login",
+            package
+        )
+        .fetch_all(&mut *self.transaction.borrow().await)
+        .await?;
+        let teams = sqlx::query!("SELECT team FROM PackageReaderTeam WHERE package = $1 ORDER BY team", package)
+            .fetch_all(&mut *self.transaction.borrow().await)
+            .await?
+            .into_iter()
+            .map(|row| row.team)
+            .collect();
+        Ok(CrateVisibility {
+            is_restricted,
+            readers,
+            teams,
+        })
+    }
+
+    /// Sets the visibility of a crate, replacing its readers and its teams
+    pub async fn set_crate_visibility(&self, package: &str, query: &CrateVisibilityQuery) -> Result<(), ApiError> {
+        if let Some(team) = query.teams.iter().find(|team| !is_valid_role(team)) {
+            return Err(specialize(error_invalid_request(), format!("invalid team {team}")));
+        }
+        let result = sqlx::query!(
+            "UPDATE Package SET isRestricted = $2 WHERE name = $1",
+            package,
+            query.is_restricted
+        )
+        .execute(&mut *self.transaction.borrow().await)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(error_not_found());
+        }
+        let mut readers = Vec::with_capacity(query.readers.len());
+        for email in &query.readers {
+            let uid = self
+                .check_is_user(email)
+                .await
+                .map_err(|_| specialize(error_invalid_request(), format!("no active user with email {email}")))?;
+            if !readers.contains(&uid) {
+                readers.push(uid);
+            }
+        }
+        sqlx::query!("DELETE FROM PackageReader WHERE package = $1", package)
+            .execute(&mut *self.transaction.borrow().await)
+            .await?;
+        for uid in readers {
+            sqlx::query!("INSERT INTO PackageReader (package, user) VALUES ($1, $2)", package, uid)
+                .execute(&mut *self.transaction.borrow().await)
+                .await?;
+        }
+        sqlx::query!("DELETE FROM PackageReaderTeam WHERE package = $1", package)
+            .execute(&mut *self.transaction.borrow().await)
+            .await?;
+        let mut teams = query.teams.iter().collect::<Vec<_>>();
+        teams.sort();
+        teams.dedup();
+        for team in teams {
+            sqlx::query!("INSERT INTO PackageReaderTeam (package, team) VALUES ($1, $2)", package, team)
+                .execute(&mut *self.transaction.borrow().await)
+                .await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,120 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Service for persisting information in the database
+//! API related to the crates watched by users and their notifications
+
+use super::Database;
+use crate::model::cargo::RegistryUser;
+use crate::model::webhooks::{UserNotification, WebhookPayload};
+use crate::utils::apierror::{error_not_found, ApiError};
+
+impl Database {
+    /// Gets the names of the crates watched by a user
+    pub async fn get_watched_crates(&self, uid: i64) -> Result<Vec<String>, ApiError> {
+        let rows = sqlx::query!("SELECT package FROM PackageWatch WHERE user = $1 ORDER BY package", uid)
+            .fetch_all(&mut *self.transaction.borrow().await)
+            .await?;
+        Ok(rows.into_iter().map(|row| row.package).collect())
+    }
+
+    /// Starts watching a crate for a user
+    pub async fn watch_crate(&self, uid: i64, package: &str) -> Result<(), ApiError> {
+        sqlx::query!("SELECT name FROM Package WHERE name = $1 LIMIT 1", package)
+            .fetch_optional(&mut *self.transaction.borrow().await)
+            .await?
+            .ok_or_else(error_not_found)?;
+        let existing = sqlx::query!(
+            "SELECT id FROM PackageWatch WHERE package = $1 AND user = $2 LIMIT 1",
+            package,
+            uid
+        )
+        .fetch_optional(&mut *self.transaction.borrow().await)
+        .await?;
+        if existing.is_none() {
+            sqlx::query!("INSERT INTO PackageWatch (package, user) VALUES ($1, $2)", package, uid)
+                .execute(&mut *self.transaction.borrow().await)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Stops watching a crate for a user
+    pub async fn unwatch_crate(&self, uid: i64, package: &str) -> Result<(), ApiError> {
+        sqlx::query!("DELETE FROM PackageWatch WHERE package = $1 AND user = $2", package, uid)
+            .execute(&mut *self.transaction.borrow().await)
+            .await?;
+        Ok(())
+    }
+
+    /// Records a notification for all the active users watching the crate of an event
+    /// Returns the notified users
+    pub async fn add_watchers_notification(&self, payload: &WebhookPayload) -> Result<Vec<RegistryUser>, ApiError> {
+        let candidates = sqlx::query_as!(
+            RegistryUser,
+            "SELECT RegistryUser.id, isActive AS is_active, email, login, name, roles
+            FROM RegistryUser INNER JOIN PackageWatch ON PackageWatch.user = RegistryUser.id
+            WHERE package = $1 AND isActive = TRUE",
+            payload.package
+        )
+        .fetch_all(&mut *self.transaction.borrow().await)
+        .await?;
+        // the watchers that can no longer read the crate since it was restricted are not notified
+        let mut watchers = Vec::with_capacity(candidates.len());
+        for watcher in candidates {
+            match self.check_can_read_crate(watcher.id, &payload.package).await {
+                Ok(()) => watchers.push(watcher),
+                Err(error) if error.http == 404 => {}
+                Err(error) => return Err(error),
+            }
+        }
+        let event = payload.event.as_str();
+        let details = payload.describe();
+        for watcher in &watchers {
+            sqlx::query!(
+                "INSERT INTO UserNotification (user, package, version, event, details, createdOn, isRead) VALUES ($1, $2, $3, $4, $5, $6, FALSE)",
+                watcher.id,
+                payload.package,
+                payload.version,
+                event,
+                details,
+                payload.timestamp
+            )
+            .execute(&mut *self.transaction.borrow().await)
+            .await?;
+        }
+        Ok(watchers)
+    }
+
+    /// Gets the most recent notifications of a user
+    pub async fn get_user_notifications(
+        &self,
+        uid: i64,
+        unread_only: bool,
+        limit: i64,
+    ) -> Result<Vec<UserNotification>, ApiError> {
+        let rows = sqlx::query_as!(
+            UserNotification,
+            "SELECT id, package, version, event, details, createdOn AS created_on, isRead AS is_read
+            FROM UserNotification
+            WHERE user = $1 AND ($2 = FALSE OR isRead = FALSE)
+            ORDER BY id DESC
+            LIMIT $3",
+            uid,
+            unread_only,
+            limit
+        )
+        .fetch_all(&mut *self.transaction.borrow().await)
+        .await?;
+        Ok(rows)
+    }
+
+    /// Marks all the notifications of a user as read
+    pub async fn mark_user_notifications_read(&self, uid: i64) -> Result<(), ApiError> {
+        sqlx::query!("UPDATE UserNotification SET isRead = TRUE WHERE user = $1", uid)
+            .execute(&mut *self.transaction.borrow().await)
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,251 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Service for persisting information in the database
+//! API related to the outgoing webhooks
+
+use chrono::Local;
+
+use super::Database;
+use crate::model::webhooks::{
+    Webhook, WebhookCreateQuery, WebhookDelivery, WebhookDeliveryJob, WebhookEvent, WebhookWithSecret,
+};
+use crate::utils::apierror::{error_not_found, ApiError};
+use crate::utils::token::generate_token;
+
+impl Database {
+    /// Gets all the webhooks
+    pub async fn get_webhooks(&self) -> Result<Vec<Webhook>, ApiError> {
+        let rows = sqlx::query!(
+            "SELECT id, package, url, events, createdBy AS created_by, createdOn AS created_on FROM Webhook ORDER BY id"
+        )
+        .fetch_all(&mut *self.transaction.borrow().await)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| Webhook {
+                id: row.id,
+                package: row.package,
+                url: row.url,
+                events: WebhookEvent::parse_list(&row.events),
+                created_by: row.created_by,
+                created_on: row.created_on,
+            })
+            .collect())
+    }
+
+    /// Gets the webhooks for the crates owned by a user
+    pub async fn get_webhooks_for_owner(&self, uid: i64) -> Result<Vec<Webhook>, ApiError> {
+        let rows = sqlx::query!(
+            "SELECT Webhook.id, Webhook.package, url, events, createdBy AS created_by, createdOn AS created_on
+            FROM Webhook INNER JOIN PackageOwner ON PackageOwner.package = Webhook.package
+            WHERE PackageOwner.owner = $1
+            ORDER BY Webhook.id",
+            uid
+        )
+        .fetch_all(&mut *self.transaction.borrow().await)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| Webhook {
+                id: row.id,
+                package: row.package,
+                url: row.url,
+                events: WebhookEvent::parse_list(&row.events),
+                created_by: row.created_by,
+                created_on: row.created_on,
+            })
+            .collect())
+    }
+
+    /// Gets a single webhook
+    pub async fn get_webhook(&self, webhook_id: i64) -> Result<Webhook, ApiError> {
+        let row = sqlx::query!(
+            "SELECT id, package, url, events, createdBy AS created_by, createdOn AS created_on FROM Webhook WHERE id = $1 LIMIT 1",
+            webhook_id
+        )
+        .fetch_optional(&mut *self.transaction.borrow().await)
+        .await?
+        .ok_or_else(error_not_found)?;
+        Ok(Webhook {
+            id: row.id,
+            package: row.package,
+            url: row.url,
+            events: WebhookEvent::parse_list(&row.events),
+            created_by: row.created_by,
+            created_on: row.created_on,
+        })
+    }
+
+    /// Gets the webhooks, with their secrets, to be notified of an event on a crate
+    pub async fn get_webhooks_for_event(&self, package: &str, event: WebhookEvent) -> Result<Vec<WebhookWithSecret>, ApiError> {
+        let rows = sqlx::query!(
+            "SELECT id, package, url, secret, events, createdBy AS created_by, createdOn AS created_on
+            FROM Webhook
+            WHERE package IS NULL OR package = $1",
+            package
+        )
+        .fetch_all(&mut *self.transaction.borrow().await)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| WebhookWithSecret {
+                webhook: Webhook {
+                    id: row.id,
+                    package: row.package,
+                    url: row.url,
+                    events: WebhookEvent::parse_list(&row.events),
+                    created_by: row.created_by,
+                    created_on: row.created_on,
+                },
+                secret: row.secret,
+            })
+            .filter(|webhook| webhook.webhook.events.contains(&event))
+            .collect())
+    }
+
+    /// Creates a webhook
+    pub async fn create_webhook(&self, uid: i64, query: &WebhookCreateQuery) -> Result<WebhookWithSecret, ApiError> {
+        if let Some(package) = &query.package {
+            sqlx::query!("SELECT name FROM Package WHERE name = $1 LIMIT 1", package)
+                .fetch_optional(&mut *self.transaction.borrow().await)
+                .await?
+                .ok_or_else(error_not_found)?;
+        }
+        let secret = generate_token(32);
+        let events = WebhookEvent::to_list(&query.events);
+        let now = Local::now().naive_local();
+        let id = sqlx::query!(
+            "INSERT INTO Webhook (package, url, secret, events, createdBy, createdOn) VALUES ($1, $2, $3, $4, $5, $6) RETURNING id",
+            query.package,
+            query.url,
+            secret,
+            events,
+            uid,
+            now
+        )
+        .fetch_one(&mut *self.transaction.borrow().await)
+        .await?
+        .id;
+        Ok(WebhookWithSecret {
+            webhook: Webhook {
+                id,
+                package: query.package.clone(),
+                url: query.url.clone(),
+                events: query.events.clone(),
+                created_by: uid,
+                created_on: now,
+            },
+            secret,
+        })
+    }
+
+    /// Deletes a webhook and its delivery log
+    pub async fn delete_webhook(&self, webhook_id: i64) -> Result<(), ApiError> {
+        sqlx::query!("DELETE FROM WebhookDelivery WHERE webhook = $1", webhook_id)
+            .execute(&mut *self.transaction.borrow().await)
+            .await?;
+        sqlx::query!("DELETE FROM Webhook WHERE id = $1", webhook_id)
+            .execute(&mut *self.transaction.borrow().await)
+            .await?;
+        Ok(())
+    }
+
+    /// Records a new delivery to be attempted for a webhook
+    pub async fn create_webhook_delivery(&self, webhook_id: i64, event: &str, payload: &str) -> Result<i64, ApiError> {
+        let now = Local::now().naive_local();
+        let id = sqlx::query!(
+            "INSERT INTO WebhookDelivery (webhook, event, payload, attempts, isDelivered, createdOn) VALUES ($1, $2, $3, 0, FALSE, $4) RETURNING id",
+            webhook_id,
+            event,
+            payload,
+            now
+        )
+        .fetch_one(&mut *self.transaction.borrow().await)
+        .await?
+        .id;
+        Ok(id)
+    }
+
+    /// Records the result of an attempt to deliver a payload
+    pub async fn update_webhook_delivery(
+        &self,
+        delivery_id: i64,
+        attempts: i64,
+        status_code: Option<i64>,
+        error: Option<&str>,
+    ) -> Result<(), ApiError> {
+        let is_delivered = status_code.is_some_and(|code| (200..300).contains(&code));
+        let now = Local::now().naive_local();
+        sqlx::query!(
+            "UPDATE WebhookDelivery SET attempts = $2, isDelivered = $3, statusCode = $4, error = $5, lastAttempt = $6 WHERE id = $1",
+            delivery_id,
+            attempts,
+            is_delivered,
+            status_code,
+            error,
+            now
+        )
+        .execute(&mut *self.transaction.borrow().await)
+        .await?;
+        Ok(())
+    }
+
+    /// Gets the most recent deliveries for a webhook
+    pub async fn get_webhook_deliveries(&self, webhook_id: i64, limit: i64) -> Result<Vec<WebhookDelivery>, ApiError> {
+        let rows = sqlx::query_as!(
+            WebhookDelivery,
+            "SELECT id, webhook, event, payload, attempts, isDelivered AS is_delivered, statusCode AS status_code, error,
+            createdOn AS created_on, lastAttempt AS last_attempt
+            FROM WebhookDelivery
+            WHERE webhook = $1
+            ORDER BY id DESC
+            LIMIT $2",
+            webhook_id,
+            limit
+        )
+        .fetch_all(&mut *self.transaction.borrow().await)
+        .await?;
+        Ok(rows)
+    }
+
+    /// Gets the deliveries that are neither delivered nor exhausted
+    pub async fn get_pending_webhook_deliveries(&self, max_attempts: i64) -> Result<Vec<WebhookDeliveryJob>, ApiError> {
+        let rows = sqlx::query_as!(
+            WebhookDeliveryJob,
+            "SELECT WebhookDelivery.id AS delivery_id, attempts, event, payload, url, secret
+            FROM WebhookDelivery INNER JOIN Webhook ON Webhook.id = WebhookDelivery.webhook
+            WHERE isDelivered = FALSE AND attempts < $1
+            ORDER BY WebhookDelivery.id",
+            max_attempts
+        )
+        .fetch_all(&mut *self.transaction.borrow().await)
+        .await?;
+        Ok(rows)
+    }
+
+    /// Records a new delivery for the same payload as a previous delivery of a webhook
+    pub async fn redeliver_webhook_delivery(&self, webhook_id: i64, delivery_id: i64) -> Result<WebhookDeliveryJob, ApiError> {
+        let row = sqlx::query!(
+            "SELECT event, payload, url, secret
+            FROM WebhookDelivery INNER JOIN Webhook ON Webhook.id = WebhookDelivery.webhook
+            WHERE WebhookDelivery.id = $1 AND webhook = $2
+            LIMIT 1",
+            delivery_id,
+            webhook_id
+        )
+        .fetch_optional(&mut *self.transaction.borrow().await)
+        .await?
+        .ok_or_else(error_not_found)?;
+        let new_id = self.create_webhook_delivery(webhook_id, &row.event, &row.payload).await?;
+        Ok(WebhookDeliveryJob {
+            delivery_id: new_id,
+            attempts: 0,
+            event: row.event,
+            payload: row.payload,
+            url: row.url,
+            secret: row.secret,
+        })
+    }
+}
@@ -11,6 +11,7 @@ use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+use arc_swap::ArcSwap;
 use base64::engine::general_purpose::STANDARD;
 use base64::Engine;
 use futures::lock::Mutex;
@@ -21,93 +22,80 @@ use tokio::io::AsyncBufReadExt;
 use crate::model::cargo::{IndexCrateDependency, IndexCrateMetadata};
 use crate::model::config::{Configuration, ExternalRegistryProtocol};
 use crate::model::deps::{
-    DepAdvisory, DepsAnalysis, DepsAnalysisJobSpec, DepsGraph, DepsGraphCrateOrigin, BUILTIN_CRATES_REGISTRY_URI,
+    CrateVersionAdvisory, DepAdvisory, DepsAnalysis, DepsAnalysisJobSpec, DepsGraph, DepsGraphCrateOrigin,
+    BUILTIN_CRATES_REGISTRY_URI,
 };
+use crate::model::webhooks::{WebhookEvent, WebhookPayload};
+use crate::model::CrateVersion;
+use crate::services::chat::{ChatMessage, ChatNotifier};
 use crate::services::database::{db_transaction_read, db_transaction_write};
 use crate::services::emails::EmailSender;
 use crate::services::index::Index;
+use crate::services::notifications::notify_watchers;
 use crate::services::rustsec::RustSecChecker;
+use crate::services::webhooks::WebhookSender;
 use crate::utils::apierror::{error_backend_failure, error_not_found, specialize, ApiError};
 use crate::utils::db::RwSqlitePool;
-use crate::utils::{stale_instant, FaillibleFuture};
-
-/// Creates a worker for the continuous check of dependencies for head crates
-pub fn create_deps_worker(
-    configuration: Arc<Configuration>,
-    service_deps_checker: Arc<dyn DepsChecker + Send + Sync>,
-    service_email_sender: Arc<dyn EmailSender + Send + Sync>,
-    pool: RwSqlitePool,
-) {
-    let _handle = tokio::spawn({
-        let service_deps_checker = service_deps_checker.clone();
-        async move {
-            info!("precaching crates.io index");
-            if let Err(e) = service_deps_checker.precache_crate_io().await {
-                error!("{e}");
-                if let Some(backtrace) = &e.backtrace {
-                    error!("{backtrace}");
-                }
-            }
-        }
-    });
-
-    let deps_check_period = configuration.deps_check_period;
+use crate::utils::{push_if_not_present, stale_instant, FaillibleFuture};
 
+/// Spawns the precaching of the local copy of the crates.io index, in the background
+pub fn precache_crates_io_spawn(service_deps_checker: Arc<dyn DepsChecker + Send + Sync>) {
     let _handle = tokio::spawn(async move {
-        // every minute
-        let mut interval = tokio::time::interval(Duration::from_secs(deps_check_period));
-        loop {
-            let _instant = interval.tick().await;
-            if let Err(e) = deps_worker_job(
-                &configuration,
-                service_deps_checker.clone(),
-                service_email_sender.clone(),
-                &pool,
-            )
-            .await
-            {
-                error!("{e}");
-                if let Some(backtrace) = &e.backtrace {
-                    error!("{backtrace}");
-                }
+        info!("precaching crates.io index");
+        if let Err(e) = service_deps_checker.precache_crate_io().await {
+            error!("{e}");
+            if let Some(backtrace) = &e.backtrace {
+                error!("{backtrace}");
             }
         }
     });
 }
 
-/// A job for the worker
-async fn deps_worker_job(
+/// The job checking the dependencies of the head crates whose analysis is stale, gets the analyzed crates
+///
+/// # Errors
+///
+/// Returns an error when the analysis of a crate fails
+pub async fn deps_worker_job(
     configuration: &Configuration,
     service_deps_checker: Arc<dyn DepsChecker + Send + Sync>,
     service_email_sender: Arc<dyn EmailSender + Send + Sync>,
+    service_chat_notifier: Arc<dyn ChatNotifier + Send + Sync>,
+    service_webhook_sender: Arc<dyn WebhookSender + Send + Sync>,
     pool: &RwSqlitePool,
-) -> Result<(), ApiError> {
+) -> Result<Vec<String>, ApiError> {
     if configuration.deps_stale_analysis <= 0 {
         // deactivated
-        return Ok(());
+        return Ok(Vec::new());
     }
 
     let jobs = db_transaction_read(pool, |database| async move {
         database.get_unanalyzed_crates(configuration.deps_stale_analysis).await
     })
     .await?;
+    let mut packages = Vec::new();
     for job in jobs {
         deps_worker_job_on_crate_version(
             configuration,
             service_deps_checker.as_ref(),
-            service_email_sender.as_ref(),
+            &service_email_sender,
+            service_chat_notifier.as_ref(),
+            &service_webhook_sender,
             pool,
             &job,
         )
         .await?;
+        push_if_not_present(&mut packages, job.package);
     }
-    Ok(())
+    Ok(packages)
 }
 
 async fn deps_worker_job_on_crate_version(
     configuration: &Configuration,
     service_deps_checker: &(dyn DepsChecker + Send + Sync),
-    service_email_sender: &(dyn EmailSender + Send + Sync),
+    service_email_sender: &Arc<dyn EmailSender + Send + Sync>,
+    service_chat_notifier: &(dyn ChatNotifier + Send + Sync),
+    service_webhook_sender: &Arc<dyn WebhookSender + Send + Sync>,
     pool: &RwSqlitePool,
     job: &DepsAnalysisJobSpec,
 ) -> Result<(), ApiError> {
@@ -117,12 +105,19 @@ async fn deps_worker_job_on_crate_version(
         .await?;
     let has_outdated = analysis.direct_dependencies.iter().any(|info| info.is_outdated);
     let has_cves = !analysis.advisories.is_empty();
-    let (old_has_outdated, old_has_cves) = db_transaction_write(pool, "set_crate_deps_analysis", |database| async move {
-        database
-            .set_crate_deps_analysis(&job.package, &job.version, has_outdated, has_cves)
-            .await
-    })
-    .await?;
+    let (old_has_outdated, old_has_cves) = save_deps_analysis(pool, job, &analysis, has_outdated, has_cves).await?;
+    if has_cves && !old_has_cves {
+        notify_new_advisories(
+            configuration,
+            service_email_sender,
+            service_chat_notifier,
+            service_webhook_sender,
+            pool,
+            job,
+            &analysis,
+        )
+        .await?;
+    }
     if (has_outdated != old_has_outdated && configuration.deps_notify_outdated)
         || (has_cves != old_has_cves && configuration.deps_notify_cves)
     {
@@ -200,6 +195,61 @@ async fn deps_worker_job_on_crate_version(
     Ok(())
 }
 
+/// Saves the dependency analysis of a crate version, with the advisories against its dependencies
+/// Returns the previous values of the flags
+async fn save_deps_analysis(
+    pool: &RwSqlitePool,
+    job: &DepsAnalysisJobSpec,
+    analysis: &DepsAnalysis,
+    has_outdated: bool,
+    has_cves: bool,
+) -> Result<(bool, bool), ApiError> {
+    let crate_version = CrateVersion {
+        package: job.package.clone(),
+        version: job.version.clone(),
+    };
+    let advisories = analysis
+        .advisories
+        .iter()
+        .map(|adv| CrateVersionAdvisory::new(&crate_version, &adv.package, &adv.version, &adv.content, false))
+        .collect::<Vec<_>>();
+    db_transaction_write(pool, "set_crate_deps_analysis", |database| async move {
+        database
+            .set_crate_version_advisories(&job.package, &job.version, false, advisories)
+            .await?;
+        database
+            .set_crate_deps_analysis(&job.package, &job.version, has_outdated, has_cves)
+            .await
+    })
+    .await
+}
+
+/// Notifies about new vulnerable dependencies for a crate version, to the chat services, the webhooks and the watchers
+async fn notify_new_advisories(
+    configuration: &Configuration,
+    service_email_sender: &Arc<dyn EmailSender + Send + Sync>,
+    service_chat_notifier: &(dyn ChatNotifier + Send + Sync),
+    service_webhook_sender: &Arc<dyn WebhookSender + Send + Sync>,
+    pool: &RwSqlitePool,
+    job: &DepsAnalysisJobSpec,
+    analysis: &DepsAnalysis,
+) -> Result<(), ApiError> {
+    service_chat_notifier
+        .notify(&ChatMessage {
+            title: format!("Vulnerable dependencies found for {} {}", job.package, job.version),
+            lines: analysis
+                .advisories
+                .iter()
+                .map(|adv| format!("{} {}: {} {}", adv.package, adv.version, adv.content.id, adv.content.summary))
+                .collect(),
+            link: format!("{}/crates/{}/{}", configuration.web_public_uri, job.package, job.version),
+        })
+        .await?;
+    let payload = WebhookPayload::new(WebhookEvent::Advisory, &job.package, Some(&job.version));
+    crate::services::webhooks::dispatch(pool.clone(), service_webhook_sender.clone(), payload.clone());
+    notify_watchers(configuration, service_email_sender.clone(), pool, &payload).await
+}
+
 /// Service to check the dependencies of a crate
 pub trait DepsChecker {
     /// Ensures that a local cache for crates.io exists
@@ -212,11 +262,25 @@ pub trait DepsChecker {
         version: &'a str,
         targets: &'a [String],
     ) -> FaillibleFuture<'a, DepsAnalysis>;
+
+    /// Checks the given dependencies of a local crate version, when it is not in the index yet
+    fn check_crate_deps<'a>(
+        &'a self,
+        deps: &'a [IndexCrateDependency],
+        targets: &'a [String],
+    ) -> FaillibleFuture<'a, DepsAnalysis>;
+
+    /// Gets the versions of a crate in a registry, `None` for the local one
+    fn get_crate_versions<'a>(
+        &'a self,
+        registry: Option<&'a str>,
+        package: &'a str,
+    ) -> FaillibleFuture<'a, Vec<IndexCrateMetadata>>;
 }
 
 /// Gets the dependencies checker service
 pub fn get_service(
-    configuration: Arc<Configuration>,
+    configuration: Arc<ArcSwap<Configuration>>,
     service_index: Arc<dyn Index + Send + Sync>,
     service_rustsec: Arc<dyn RustSecChecker + Send + Sync>,
 ) -> Arc<dyn DepsChecker + Send + Sync> {
@@ -240,7 +304,7 @@ struct DepsCheckerImpl {
     /// The data for the service
     data: Mutex<DepsCheckerData>,
     /// The app configuration
-    configuration: Arc<Configuration>,
+    configuration: Arc<ArcSwap<Configuration>>,
     /// Access to the index
     service_index: Arc<dyn Index + Send + Sync>,
     /// The `RustSec` service
@@ -271,6 +335,24 @@ impl DepsChecker for DepsCheckerImpl {
     ) -> FaillibleFuture<'a, DepsAnalysis> {
         Box::pin(async move { self.do_check_crate(package, version, targets).await })
     }
+
+    /// Checks the given dependencies of a local crate version, when it is not in the index yet
+    fn check_crate_deps<'a>(
+        &'a self,
+        deps: &'a [IndexCrateDependency],
+        targets: &'a [String],
+    ) -> FaillibleFuture<'a, DepsAnalysis> {
+        Box::pin(async move { self.do_check_crate_deps(deps, targets).await })
+    }
+
+    /// Gets the versions of a crate in a registry, `None` for the local one
+    fn get_crate_versions<'a>(
+        &'a self,
+        registry: Option<&'a str>,
+        package: &'a str,
+    ) -> FaillibleFuture<'a, Vec<IndexCrateMetadata>> {
+        Box::pin(async move { self.get_dependency_versions(registry, package).await })
+    }
 }
 
 impl DepsCheckerImpl {
@@ -288,8 +370,12 @@ impl DepsCheckerImpl {
             .iter()
             .find(|meta| meta.vers == version)
             .ok_or_else(error_not_found)?;
+        self.do_check_crate_deps(&metadata.deps, targets).await
+    }
 
-        let graph = self.get_dependencies_closure(&metadata.deps, targets).await?;
+    /// Checks the given dependencies of a local crate
+    async fn do_check_crate_deps(&self, deps: &[IndexCrateDependency], targets: &[String]) -> Result<DepsAnalysis, ApiError> {
+        let graph = self.get_dependencies_closure(deps, targets).await?;
         let mut advisories = Vec::new();
         for dep in &graph.crates {
             for resolution in &dep.resolutions {
@@ -309,7 +395,7 @@ impl DepsCheckerImpl {
                 }
             }
         }
-        Ok(DepsAnalysis::new(&graph, &metadata.deps, advisories))
+        Ok(DepsAnalysis::new(&graph, deps, advisories))
     }
 
     /// Gets the transitive closure of dependencies
@@ -320,7 +406,7 @@ impl DepsCheckerImpl {
     ) -> Result<DepsGraph, ApiError> {
         let mut graph = if targets.is_empty() {
             // use the host as default target
-            DepsGraph::new(&[self.configuration.self_toolchain_host.clone()])
+            DepsGraph::new(std::slice::from_ref(&self.configuration.load().self_toolchain_host))
         } else {
             DepsGraph::new(targets)
         };
@@ -344,13 +430,14 @@ impl DepsCheckerImpl {
             if registry == BUILTIN_CRATES_REGISTRY_URI {
                 Ok(Self::generate_for_built_in(
                     name,
-                    &self.configuration.self_toolchain_version_stable,
+                    &self.configuration.load().self_toolchain_version_stable,
                 ))
             } else if registry == CRATES_IO_REGISTRY_URI {
                 self.get_dependency_info_git(name, CRATES_IO_NAME, CRATES_IO_REGISTRY_URI)
                     .await
             } else if let Some(registry) = self
                 .configuration
+                .load()
                 .external_registries
                 .iter()
                 .find(|reg| reg.index == registry)
@@ -393,12 +480,14 @@ impl DepsCheckerImpl {
         index_uri: &str,
     ) -> Result<Vec<IndexCrateMetadata>, ApiError> {
         let mut data = self.data.lock().await;
+        let configuration = self.configuration.load_full();
 
         let last_touch = data.last_touch.get(reg_name).copied().unwrap_or_else(stale_instant);
         let now = Instant::now();
-        let is_stale = now.duration_since(last_touch) > Duration::from_millis(self.configuration.deps_stale_registry);
+        let is_stale = now.duration_since(last_touch) > Duration::from_millis(configuration.deps_stale_registry);
 
-        let mut reg_location = PathBuf::from(&self.configuration.data_dir);
+        crate::services::index::validate_crate_name(dep_name)?;
+        let mut reg_location = PathBuf::from(&configuration.data_dir);
         reg_location.push(DATA_SUB_DIR);
         reg_location.push(reg_name);
         if is_stale {
@@ -426,7 +515,7 @@ impl DepsCheckerImpl {
 
     /// Builds the path in the storage to the local file
     async fn get_dependency_info_file_path(&self, dep_name: &str, reg_name: &str) -> Result<PathBuf, ApiError> {
-        let mut reg_location = PathBuf::from(&self.configuration.data_dir);
+        let mut reg_location = PathBuf::from(&self.configuration.load().data_dir);
         reg_location.push(DATA_SUB_DIR);
         reg_location.push(reg_name);
         let file_path = crate::services::index::build_package_file_path(reg_location, dep_name);
@@ -457,8 +546,8 @@ impl DepsCheckerImpl {
         index_uri: &str,
         credentials: Option<(&str, &str)>,
     ) -> Result<Vec<IndexCrateMetadata>, ApiError> {
-        let target_uri = Self::get_dependency_info_sparse_target_uri(dep_name, index_uri);
         let file_path = self.get_dependency_info_file_path(dep_name, reg_name).await?;
+        let target_uri = Self::get_dependency_info_sparse_target_uri(dep_name, index_uri);
 
         let mut data = self.data.lock().await;
 
@@ -466,7 +555,8 @@ impl DepsCheckerImpl {
             // is it stale?
             let last_touch = data.last_touch.get(&target_uri).copied().unwrap_or_else(stale_instant);
             let now = Instant::now();
-            let is_stale = now.duration_since(last_touch) > Duration::from_millis(self.configuration.deps_stale_registry);
+            let is_stale =
+                now.duration_since(last_touch) > Duration::from_millis(self.configuration.load().deps_stale_registry);
             if is_stale {
                 self.get_dependency_info_sparse_fetch(&file_path, target_uri, credentials, &mut data)
                     .await
@@ -496,7 +586,7 @@ impl DepsCheckerImpl {
         credentials: Option<(&str, &str)>,
         data: &mut DepsCheckerData,
     ) -> Result<Vec<IndexCrateMetadata>, ApiError> {
-        let mut request = reqwest::Client::new().get(&target_uri);
+        let mut request = crate::utils::http_client_builder().build()?.get(&target_uri);
         if let Some((login, password)) = credentials {
             let value = STANDARD.encode(format!("{login}:{password}"));
             request = request.header("Authorization", format!("Basic {value}"));
@@ -10,17 +10,20 @@ use std::process::Stdio;
 use std::sync::Arc;
 use std::time::Duration;
 
+use arc_swap::ArcSwap;
 use chrono::Local;
 use flate2::bufread::GzDecoder;
-use log::{error, info};
+use log::{error, info, warn};
 use tar::Archive;
-use tokio::process::Command;
-use tokio::sync::mpsc::Sender;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::sync::mpsc::{channel, Sender};
 use tokio::sync::Mutex;
 use tokio::time::interval;
 
 use crate::model::config::Configuration;
-use crate::model::docs::{DocGenEvent, DocGenJob, DocGenJobSpec, DocGenJobState, DocGenJobUpdate, DocGenTrigger};
+use crate::model::docs::{
+    DocGenEvent, DocGenJob, DocGenJobLogLine, DocGenJobSpec, DocGenJobState, DocGenJobUpdate, DocGenTrigger, DocsCoverage,
+};
 use crate::model::worker::{JobIdentifier, JobSpecification, JobUpdate, WorkersManager};
 use crate::model::CHANNEL_NIGHTLY;
 use crate::services::database::{db_transaction_read, db_transaction_write};
@@ -28,7 +31,10 @@ use crate::services::storage::Storage;
 use crate::utils::apierror::{error_backend_failure, error_invalid_request, specialize, ApiError};
 use crate::utils::concurrent::n_at_a_time;
 use crate::utils::db::RwSqlitePool;
-use crate::utils::FaillibleFuture;
+use crate::utils::{new_command, FaillibleFuture};
+
+/// The number of lines of output that can be buffered before the generation waits for listeners
+const LOG_LINES_BUFFER: usize = 64;
 
 /// Service to generate documentation for a crate
 pub trait DocsGenerator {
@@ -47,7 +53,7 @@ pub trait DocsGenerator {
 
 /// Gets the documentation generation service
 pub fn get_service(
-    configuration: Arc<Configuration>,
+    configuration: Arc<ArcSwap<Configuration>>,
     service_db_pool: RwSqlitePool,
     service_storage: Arc<dyn Storage + Send + Sync>,
     worker_nodes: WorkersManager,
@@ -73,7 +79,7 @@ pub fn get_service(
 #[derive(Clone)]
 struct DocsGeneratorImpl {
     /// The configuration
-    configuration: Arc<Configuration>,
+    configuration: Arc<ArcSwap<Configuration>>,
     /// The database pool
     service_db_pool: RwSqlitePool,
     /// The storage layer
@@ -149,7 +155,14 @@ impl DocsGeneratorImpl {
     }
 
     /// Update a job
-    async fn update_job(&self, job: &DocGenJob, state: DocGenJobState, log: Option<&str>) -> Result<(), ApiError> {
+    async fn update_job(
+        &self,
+        job: &DocGenJob,
+        state: DocGenJobState,
+        log: Option<&str>,
+        coverage: Option<DocsCoverage>,
+        size: Option<i64>,
+    ) -> Result<(), ApiError> {
         db_transaction_write(&self.service_db_pool, "update_job", |database| async move {
             database.update_docgen_job(job.id, state).await?;
             database
@@ -161,6 +174,16 @@ impl DocsGeneratorImpl {
                     state == DocGenJobState::Success,
                 )
                 .await?;
+            if let Some(coverage) = coverage {
+                database
+                    .set_crate_documentation_coverage(&job.package, &job.version, &job.target, coverage)
+                    .await?;
+            }
+            if let Some(size) = size {
+                database
+                    .set_crate_documentation_size(&job.package, &job.version, &job.target, size)
+                    .await?;
+            }
             Ok::<_, ApiError>(())
         })
         .await?;
@@ -172,6 +195,8 @@ impl DocsGeneratorImpl {
             state,
             last_update: now,
             log: log.map(str::to_string),
+            coverage,
+            size,
         }))
         .await?;
         Ok(())
@@ -214,7 +239,8 @@ impl DocsGeneratorImpl {
     /// Executes a documentation generation job
     async fn docs_worker_on_job(&self, job: &DocGenJob) -> Result<(), ApiError> {
         if let Err(e) = self.docs_worker_execute_job(job).await {
-            self.update_job(job, DocGenJobState::Failure, Some(&e.to_string())).await?;
+            self.update_job(job, DocGenJobState::Failure, Some(&e.to_string()), None, None)
+                .await?;
             // upload the error as log
             let mut log = e.to_string();
             if let Some(backtrace) = &e.backtrace {
@@ -243,13 +269,20 @@ impl DocsGeneratorImpl {
             .worker_nodes
             .get_worker_for(job.get_worker_selector(), JobIdentifier::DocGen(job.id))?
             .await?;
-        self.update_job(job, DocGenJobState::Working, None).await?;
+        self.update_job(job, DocGenJobState::Working, None, None, None).await?;
         worker.sender().send(JobSpecification::DocGen(job.clone())).await?;
         while let Some(update) = worker.update_receiver().recv().await {
-            let JobUpdate::DocGen(update) = update;
-            self.update_job(job, update.state, update.log.as_deref()).await?;
-            if update.state.is_final() {
-                return Ok(());
+            match update {
+                JobUpdate::DocGen(update) => {
+                    self.update_job(job, update.state, update.log.as_deref(), update.coverage, update.size)
+                        .await?;
+                    if update.state.is_final() {
+                        return Ok(());
+                    }
+                }
+                JobUpdate::DocGenLog(line) => {
+                    self.send_event(DocGenEvent::Log(line)).await?;
+                }
             }
         }
         Err(specialize(
@@ -260,9 +293,23 @@ impl DocsGeneratorImpl {
 
     /// Executes a documentation generation job
     async fn docs_worker_execute_job_local(&self, job: &DocGenJob) -> Result<(), ApiError> {
-        self.update_job(job, DocGenJobState::Working, None).await?;
-        let (final_state, output) = generate_doc_for_job(&self.configuration, self.service_storage.clone(), job).await?;
-        self.update_job(job, final_state, Some(&output)).await?;
+        self.update_job(job, DocGenJobState::Working, None, None, None).await?;
+        let (log_sender, mut log_receiver) = channel(LOG_LINES_BUFFER);
+        let forward = async {
+            while let Some(line) = log_receiver.recv().await {
+                self.send_event(DocGenEvent::Log(DocGenJobLogLine { job_id: job.id, line }))
+                    .await?;
+            }
+            Ok::<_, ApiError>(())
+        };
+        let configuration = self.configuration.load_full();
+        let (result, forwarded) = tokio::join!(
+            generate_doc_for_job(&configuration, self.service_storage.clone(), job, log_sender),
+            forward
+        );
+        forwarded?;
+        let (final_state, output, coverage, size) = result?;
+        self.update_job(job, final_state, Some(&output), coverage, size).await?;
         Ok(())
     }
 }
@@ -273,37 +320,46 @@ fn job_log_location(job: &DocGenJob) -> String {
 }
 
 /// Executes a documentation generation job
+/// Returns the final state, the output log, the documentation coverage, if it could be computed, and the size of the uploaded documentation
+/// Each line of output from `rustdoc` is also sent to `log_sender` as it is produced
 pub async fn generate_doc_for_job(
     configuration: &Configuration,
     service_storage: Arc<dyn Storage + Send + Sync>,
     job: &DocGenJob,
-) -> Result<(DocGenJobState, String), ApiError> {
+    log_sender: Sender<String>,
+) -> Result<(DocGenJobState, String, Option<DocsCoverage>, Option<i64>), ApiError> {
     info!("generating doc for {} {}", job.package, job.version);
     on_job_check_target(configuration, job).await?;
     let content = service_storage.download_crate(&job.package, &job.version).await?;
     let temp_folder = extract_content(&job.package, &job.version, &content)?;
     let project_folder = get_project_folder_in(&temp_folder).await?;
 
-    let (final_state, output) = if configuration.docs_gen_mock {
-        (DocGenJobState::Success, String::from("mocked"))
+    let (final_state, output, coverage, size) = if configuration.docs_gen_mock {
+        (DocGenJobState::Success, String::from("mocked"), None, None)
     } else {
-        match do_generate_doc(configuration, &project_folder, &job.target).await {
+        match do_generate_doc(configuration, &project_folder, &job.target, log_sender).await {
             Ok(log) => {
                 service_storage
                     .store_doc_data(&job_log_location(job), log.as_bytes().to_vec())
                     .await?;
-                let mut project_folder = project_folder.clone();
-                project_folder.push("target");
-                project_folder.push(&job.target);
-                project_folder.push("doc");
-                let doc_folder = project_folder;
-                upload_package(
+                let mut doc_folder = project_folder.clone();
+                doc_folder.push("target");
+                doc_folder.push(&job.target);
+                doc_folder.push("doc");
+                let size = upload_package(
                     service_storage.clone(),
                     &doc_folder,
                     &format!("{}/{}/{}", &job.package, &job.version, &job.target),
                 )
                 .await?;
-                (DocGenJobState::Success, log)
+                let coverage = match do_compute_coverage(&project_folder, &job.target).await {
+                    Ok(coverage) => Some(coverage),
+                    Err(e) => {
+                        warn!("failed to compute docs coverage for {} {}: {e}", job.package, job.version);
+                        None
+                    }
+                };
+                (DocGenJobState::Success, log, coverage, Some(size))
             }
             Err(e) => {
                 // upload the log
@@ -311,12 +367,12 @@ pub async fn generate_doc_for_job(
                 service_storage
                     .store_doc_data(&job_log_location(job), log.as_bytes().to_vec())
                     .await?;
-                (DocGenJobState::Failure, log)
+                (DocGenJobState::Failure, log, None, None)
             }
         }
     };
     tokio::fs::remove_dir_all(&temp_folder).await?;
-    Ok((final_state, output))
+    Ok((final_state, output, coverage, size))
 }
 
 /// Checks for a target to be present when generating documentation
@@ -340,7 +396,7 @@ async fn on_job_check_target(configuration: &Configuration, job: &DocGenJob) ->
         ));
     }
     // check whether it was actually installed after launch
-    let actually_installed = crate::model::config::get_installed_targets(CHANNEL_NIGHTLY).await;
+    let actually_installed = crate::model::config::get_installed_targets(CHANNEL_NIGHTLY).await?;
     if actually_installed.iter().any(|t| t == &job.target) {
         // the target was actually installed (after the app launched)
         return Ok(());
@@ -368,8 +424,13 @@ async fn get_project_folder_in(temp_folder: &Path) -> Result<PathBuf, ApiError>
 }
 
 /// Generate the documentation for the package in a specific folder
-async fn do_generate_doc(configuration: &Configuration, project_folder: &Path, target: &str) -> Result<String, ApiError> {
-    let mut command = Command::new("cargo");
+async fn do_generate_doc(
+    configuration: &Configuration,
+    project_folder: &Path,
+    target: &str,
+    log_sender: Sender<String>,
+) -> Result<String, ApiError> {
+    let mut command = new_command("cargo");
     command
         .current_dir(project_folder)
         .arg(CHANNEL_NIGHTLY)
@@ -405,27 +466,78 @@ async fn do_generate_doc(configuration: &Configuration, project_folder: &Path, t
         .stderr(Stdio::piped())
         .spawn()?;
     drop(child.stdin.take()); // close stdin
-    let output = child.wait_with_output().await?;
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let stderr = String::from_utf8_lossy(&output.stderr);
+    let stdout = child.stdout.take().unwrap();
+    let stderr = child.stderr.take().unwrap();
+    let (stdout, stderr, status) = tokio::join!(
+        read_output_lines(stdout, &log_sender),
+        read_output_lines(stderr, &log_sender),
+        child.wait()
+    );
+    let (stdout, stderr, status) = (stdout?, stderr?, status?);
     let log = format!("-- stdout\n{stdout}\n\n-- stderr\n{stderr}");
 
-    if output.status.success() {
+    if status.success() {
         Ok(log)
     } else {
         Err(specialize(error_backend_failure(), log))
     }
 }
 
+/// Reads the output of a process line by line, forwarding each line as it is read
+/// Returns the complete output
+async fn read_output_lines<R: AsyncRead + Unpin>(reader: R, log_sender: &Sender<String>) -> Result<String, std::io::Error> {
+    let mut output = String::new();
+    let mut lines = BufReader::new(reader).lines();
+    while let Some(line) = lines.next_line().await? {
+        output.push_str(&line);
+        output.push('\n');
+        // the listener may be gone, the output is still collected
+        let _ = log_sender.send(line).await;
+    }
+    Ok(output)
+}
+
+/// Computes the documentation coverage for the package in a specific folder
+async fn do_compute_coverage(project_folder: &Path, target: &str) -> Result<DocsCoverage, ApiError> {
+    let mut child = new_command("cargo")
+        .current_dir(project_folder)
+        .arg(CHANNEL_NIGHTLY)
+        .arg("rustdoc")
+        .arg("--all-features")
+        .arg("--target")
+        .arg(target)
+        .arg("--")
+        .arg("-Zunstable-options")
+        .arg("--show-coverage")
+        .arg("--output-format")
+        .arg("json")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    drop(child.stdin.take()); // close stdin
+    let output = child.wait_with_output().await?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(specialize(error_backend_failure(), stderr.to_string()));
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    // rustdoc may output other lines before the JSON blob
+    let json = stdout.lines().rev().find(|line| line.starts_with('{')).unwrap_or_default();
+    Ok(DocsCoverage::from_rustdoc_output(json)?)
+}
+
 /// Uploads the documentation for package
+/// Returns the total size in bytes of the uploaded files
 async fn upload_package(
     service_storage: Arc<dyn Storage + Send + Sync>,
     doc_folder: &Path,
     key_prefix: &str,
-) -> Result<(), ApiError> {
+) -> Result<i64, ApiError> {
     let files = upload_package_find_files(doc_folder, key_prefix).await?;
+    let size = files.iter().map(|(_, _, size)| size).sum::<u64>();
     let results = n_at_a_time(
-        files.into_iter().map(|(key, path)| {
+        files.into_iter().map(|(key, path, _)| {
             let service_storage = service_storage.clone();
             Box::pin(async move { service_storage.store_doc_file(&key, &path).await })
         }),
@@ -436,11 +548,11 @@ async fn upload_package(
     for result in results {
         result?;
     }
-    Ok(())
+    Ok(i64::try_from(size)?)
 }
 
-/// Find target to upload in a folder and its sub-folders
-async fn upload_package_find_files(folder: &Path, key_prefix: &str) -> Result<Vec<(String, PathBuf)>, std::io::Error> {
+/// Find target to upload in a folder and its sub-folders, with their size
+async fn upload_package_find_files(folder: &Path, key_prefix: &str) -> Result<Vec<(String, PathBuf, u64)>, std::io::Error> {
     let mut results = Vec::new();
     let mut to_explore = vec![(folder.to_path_buf(), key_prefix.to_string())];
     while let Some((folder, prefix)) = to_explore.pop() {
@@ -449,7 +561,8 @@ async fn upload_package_find_files(folder: &Path, key_prefix: &str) -> Result<Ve
             let entry_path = entry.path();
             let entry_type = entry.file_type().await?;
             if entry_type.is_file() {
-                results.push((format!("{prefix}/{}", entry.file_name().to_str().unwrap()), entry_path));
+                let size = entry.metadata().await?.len();
+                results.push((format!("{prefix}/{}", entry.file_name().to_str().unwrap()), entry_path, size));
             } else if entry_type.is_dir() {
                 to_explore.push((entry_path, format!("{prefix}/{}", entry.file_name().to_str().unwrap())));
             }
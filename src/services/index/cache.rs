@@ -0,0 +1,310 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! In-memory cache for the hot reads of the index
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use bytes::Bytes;
+use futures::StreamExt;
+use log::{error, info};
+use redis::aio::MultiplexedConnection;
+use redis::AsyncCommands;
+
+use super::{build_package_file_path, validate_crate_name, Index};
+use crate::model::cargo::IndexCrateMetadata;
+use crate::model::config::RedisConfig;
+use crate::model::{IndexResyncMode, IndexResyncResult};
+use crate::utils::apierror::ApiError;
+use crate::utils::FaillibleFuture;
+
+/// The message on the bus to invalidate all the entries
+const INVALIDATE_ALL: &str = "index:*";
+/// The prefix of the messages on the bus to invalidate the entries for a crate
+const INVALIDATE_CRATE_PREFIX: &str = "index:crate:";
+/// The delay before subscribing again to the bus after a failure
+const BUS_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// An index that keeps the content of its files and the metadata of the crates in memory
+///
+/// The entries for a crate are invalidated when a version is published.
+/// The whole cache is cleared when the index is rebuilt or re-synchronized.
+/// When a bus is set, the invalidations are propagated to the other instances sharing it.
+pub struct CachedIndex {
+    /// The cached index
+    inner: Arc<dyn Index + Send + Sync>,
+    /// The maximum number of entries in each part of the cache
+    capacity: usize,
+    /// The cached data
+    cache: Mutex<IndexCache>,
+    /// The bus shared with the other instances, if any
+    bus: Option<InvalidationBus>,
+}
+
+/// A bus on Redis to propagate the invalidations between the instances
+struct InvalidationBus {
+    /// The client for the Redis server
+    client: redis::Client,
+    /// The channel for the invalidations
+    channel: String,
+    /// The connection used to publish, opened on first use
+    connection: tokio::sync::Mutex<Option<MultiplexedConnection>>,
+}
+
+impl InvalidationBus {
+    /// Publishes an invalidation, failures are only logged because the local cache is already invalidated
+    async fn publish(&self, message: &str) {
+        if let Err(e) = self.try_publish(message).await {
+            error!("failed to publish the invalidation {message} on redis: {e}");
+            *self.connection.lock().await = None;
+        }
+    }
+
+    /// Publishes an invalidation
+    async fn try_publish(&self, message: &str) -> Result<(), ApiError> {
+        let mut connection = self.connection.lock().await;
+        if connection.is_none() {
+            *connection = Some(self.client.get_multiplexed_async_connection().await?);
+        }
+        if let Some(connection) = connection.as_mut() {
+            connection.publish::<_, _, ()>(&self.channel, message).await?;
+        }
+        Ok(())
+    }
+}
+
+/// The data cached for an index
+#[derive(Default)]
+struct IndexCache {
+    /// Incremented on each invalidation so that reads started before it are not cached
+    generation: u64,
+    /// The content of files, by their path relative to the root of the index
+    files: HashMap<PathBuf, Bytes>,
+    /// The metadata of crates, by their lowercase name
+    crates: HashMap<String, Vec<IndexCrateMetadata>>,
+}
+
+impl CachedIndex {
+    /// Wraps an index with a cache of the given capacity
+    #[must_use]
+    pub fn new(inner: Arc<dyn Index + Send + Sync>, capacity: usize) -> Self {
+        Self {
+            inner,
+            capacity,
+            cache: Mutex::new(IndexCache::default()),
+            bus: None,
+        }
+    }
+
+    /// Propagates the invalidations to the other instances through the configured Redis server, if any
+    ///
+    /// # Errors
+    ///
+    /// Returns an error when the URI of the Redis server is invalid
+    pub fn with_bus(mut self, config: &RedisConfig) -> Result<Self, ApiError> {
+        if let Some(uri) = &config.uri {
+            self.bus = Some(InvalidationBus {
+                client: redis::Client::open(uri.as_str())?,
+                channel: config.channel.clone(),
+                connection: tokio::sync::Mutex::new(None),
+            });
+        }
+        Ok(self)
+    }
+
+    /// Spawns the worker listening to the invalidations from the other instances, when a bus is set
+    pub fn listen_spawn(self: &Arc<Self>) {
+        if self.bus.is_none() {
+            return;
+        }
+        let this = self.clone();
+        let _handle = tokio::spawn(async move {
+            loop {
+                if let Err(e) = this.listen().await {
+                    error!("failed to listen to the invalidations on redis: {e}");
+                }
+                tokio::time::sleep(BUS_RETRY_DELAY).await;
+            }
+        });
+    }
+
+    /// Listens to the invalidations from the other instances until the connection is lost
+    async fn listen(&self) -> Result<(), ApiError> {
+        let Some(bus) = &self.bus else {
+            return Ok(());
+        };
+        let mut pubsub = bus.client.get_async_pubsub().await?;
+        pubsub.subscribe(&bus.channel).await?;
+        info!("listening to the invalidations on redis channel {}", bus.channel);
+        // invalidations may have been missed while not subscribed
+        self.invalidate_all();
+        let mut messages = pubsub.on_message();
+        while let Some(message) = messages.next().await {
+            self.on_remote_invalidation(&message.get_payload::<String>()?);
+        }
+        Ok(())
+    }
+
+    /// Applies an invalidation received from another instance
+    pub fn on_remote_invalidation(&self, message: &str) {
+        if message == INVALIDATE_ALL {
+            self.invalidate_all();
+        } else if let Some(package) = message.strip_prefix(INVALIDATE_CRATE_PREFIX).filter(|p| !p.is_empty()) {
+            self.invalidate_crate(package);
+        }
+    }
+
+    /// Gets the current generation of the cache
+    fn generation(&self) -> u64 {
+        self.cache.lock().unwrap().generation
+    }
+
+    /// Caches the content of a file, if the cache was not invalidated since the read started
+    fn cache_file(&self, generation: u64, file_path: PathBuf, content: Bytes) {
+        let mut cache = self.cache.lock().unwrap();
+        if cache.generation == generation {
+            if cache.files.len() >= self.capacity {
+                cache.files.clear();
+            }
+            cache.files.insert(file_path, content);
+        }
+    }
+
+    /// Caches the metadata of a crate, if the cache was not invalidated since the read started
+    fn cache_crate(&self, generation: u64, package: String, data: Vec<IndexCrateMetadata>) {
+        let mut cache = self.cache.lock().unwrap();
+        if cache.generation == generation {
+            if cache.crates.len() >= self.capacity {
+                cache.crates.clear();
+            }
+            cache.crates.insert(package, data);
+        }
+    }
+
+    /// Invalidates the cached entries for a crate
+    fn invalidate_crate(&self, package: &str) {
+        let mut cache = self.cache.lock().unwrap();
+        cache.generation += 1;
+        cache.files.remove(&build_package_file_path(PathBuf::from("/"), package));
+        cache.crates.remove(&package.to_ascii_lowercase());
+    }
+
+    /// Invalidates all the cached entries
+    fn invalidate_all(&self) {
+        let mut cache = self.cache.lock().unwrap();
+        cache.generation += 1;
+        cache.files.clear();
+        cache.crates.clear();
+    }
+
+    /// Gets the content of a file in the index
+    async fn do_get_index_file_content(&self, file_path: &Path) -> Result<Option<Bytes>, ApiError> {
+        if let Some(content) = self.cache.lock().unwrap().files.get(file_path) {
+            return Ok(Some(content.clone()));
+        }
+        let generation = self.generation();
+        let content = self.inner.get_index_file_content(file_path).await?;
+        if let Some(content) = &content {
+            self.cache_file(generation, file_path.to_path_buf(), content.clone());
+        }
+        Ok(content)
+    }
+
+    /// Gets the data for a crate
+    async fn do_get_crate_data(&self, package: &str) -> Result<Vec<IndexCrateMetadata>, ApiError> {
+        validate_crate_name(package)?;
+        let key = package.to_ascii_lowercase();
+        if let Some(data) = self.cache.lock().unwrap().crates.get(&key) {
+            return Ok(data.clone());
+        }
+        let generation = self.generation();
+        let data = self.inner.get_crate_data(package).await?;
+        self.cache_crate(generation, key, data.clone());
+        Ok(data)
+    }
+
+    /// Gets the raw content of the index file for a crate
+    async fn do_get_crate_index_file(&self, package: &str) -> Result<Vec<u8>, ApiError> {
+        validate_crate_name(package)?;
+        let file_path = build_package_file_path(PathBuf::from("/"), package);
+        if let Some(content) = self.cache.lock().unwrap().files.get(&file_path) {
+            return Ok(content.to_vec());
+        }
+        let generation = self.generation();
+        let content = self.inner.get_crate_index_file(package).await?;
+        self.cache_file(generation, file_path, Bytes::from(content.clone()));
+        Ok(content)
+    }
+}
+
+impl Index for CachedIndex {
+    fn get_index_file<'a>(&'a self, file_path: &'a Path) -> FaillibleFuture<'a, Option<PathBuf>> {
+        self.inner.get_index_file(file_path)
+    }
+
+    fn get_index_file_content<'a>(&'a self, file_path: &'a Path) -> FaillibleFuture<'a, Option<Bytes>> {
+        Box::pin(async move { self.do_get_index_file_content(file_path).await })
+    }
+
+    fn get_upload_pack_info_refs(&self) -> FaillibleFuture<'_, Vec<u8>> {
+        self.inner.get_upload_pack_info_refs()
+    }
+
+    fn get_upload_pack_for<'a>(&'a self, input: &'a [u8]) -> FaillibleFuture<'a, Vec<u8>> {
+        self.inner.get_upload_pack_for(input)
+    }
+
+    fn publish_crate_version<'a>(&'a self, metadata: &'a IndexCrateMetadata) -> FaillibleFuture<'a, ()> {
+        Box::pin(async move {
+            let result = self.inner.publish_crate_version(metadata).await;
+            // invalidate even on failure, the file may have been partially written
+            self.invalidate_crate(&metadata.name);
+            if let Some(bus) = &self.bus {
+                bus.publish(&format!("{INVALIDATE_CRATE_PREFIX}{}", metadata.name)).await;
+            }
+            result
+        })
+    }
+
+    fn get_crate_data<'a>(&'a self, package: &'a str) -> FaillibleFuture<'a, Vec<IndexCrateMetadata>> {
+        Box::pin(async move { self.do_get_crate_data(package).await })
+    }
+
+    fn get_crate_index_file<'a>(&'a self, package: &'a str) -> FaillibleFuture<'a, Vec<u8>> {
+        Box::pin(async move { self.do_get_crate_index_file(package).await })
+    }
+
+    fn rebuild<'a>(&'a self, crates: &'a [IndexCrateMetadata]) -> FaillibleFuture<'a, ()> {
+        Box::pin(async move {
+            let result = self.inner.rebuild(crates).await;
+            self.invalidate_all();
+            if let Some(bus) = &self.bus {
+                bus.publish(INVALIDATE_ALL).await;
+            }
+            result
+        })
+    }
+
+    fn resync_remote(&self, mode: IndexResyncMode, confirm: bool) -> FaillibleFuture<'_, IndexResyncResult> {
+        Box::pin(async move {
+            let result = self.inner.resync_remote(mode, confirm).await;
+            self.invalidate_all();
+            if let Some(bus) = &self.bus {
+                bus.publish(INVALIDATE_ALL).await;
+            }
+            result
+        })
+    }
+
+    fn check_health(&self) -> FaillibleFuture<'_, ()> {
+        self.inner.check_health()
+    }
+
+    fn snapshot_into<'a>(&'a self, target: &'a Path) -> FaillibleFuture<'a, ()> {
+        self.inner.snapshot_into(target)
+    }
+}
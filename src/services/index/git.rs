@@ -4,17 +4,20 @@
 
 //! Implementation of an index using a local git repository
 
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
+use bytes::Bytes;
 use log::{error, info};
 use tokio::fs::{create_dir_all, File, OpenOptions};
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 use tokio::sync::Mutex;
 
-use super::{build_package_file_path, Index};
+use super::{build_package_file_path, validate_crate_name, Index};
 use crate::model::cargo::IndexCrateMetadata;
 use crate::model::config::IndexConfig;
-use crate::utils::apierror::{error_backend_failure, error_not_found, specialize, ApiError};
+use crate::model::{IndexResyncMode, IndexResyncResult};
+use crate::utils::apierror::{error_backend_failure, error_invalid_request, error_not_found, specialize, ApiError};
 use crate::utils::{execute_at_location, execute_git, FaillibleFuture};
 
 /// Manages the index on git
@@ -37,6 +40,16 @@ impl Index for GitIndex {
         Box::pin(async move { Ok(self.inner.lock().await.get_index_file(file_path)) })
     }
 
+    fn get_index_file_content<'a>(&'a self, file_path: &'a Path) -> FaillibleFuture<'a, Option<Bytes>> {
+        Box::pin(async move {
+            let Some(full_path) = self.inner.lock().await.get_index_file(file_path) else {
+                return Ok(None);
+            };
+            // directories and files removed in the meantime are not found
+            Ok(tokio::fs::read(&full_path).await.ok().map(Bytes::from))
+        })
+    }
+
     fn get_upload_pack_info_refs(&self) -> FaillibleFuture<'_, Vec<u8>> {
         Box::pin(async move { self.inner.lock().await.get_upload_pack_info_refs().await })
     }
@@ -52,6 +65,62 @@ impl Index for GitIndex {
     fn get_crate_data<'a>(&'a self, package: &'a str) -> FaillibleFuture<'a, Vec<IndexCrateMetadata>> {
         Box::pin(async move { self.inner.lock().await.get_crate_data(package).await })
     }
+
+    fn get_crate_index_file<'a>(&'a self, package: &'a str) -> FaillibleFuture<'a, Vec<u8>> {
+        Box::pin(async move { self.inner.lock().await.get_crate_index_file(package).await })
+    }
+
+    fn rebuild<'a>(&'a self, crates: &'a [IndexCrateMetadata]) -> FaillibleFuture<'a, ()> {
+        Box::pin(async move { self.inner.lock().await.rebuild(crates).await })
+    }
+
+    fn resync_remote(&self, mode: IndexResyncMode, confirm: bool) -> FaillibleFuture<'_, IndexResyncResult> {
+        Box::pin(async move { self.inner.lock().await.resync_remote(mode, confirm).await })
+    }
+
+    fn check_health(&self) -> FaillibleFuture<'_, ()> {
+        Box::pin(async move {
+            // do not hold the lock while contacting the remote
+            let (location, has_remote) = {
+                let inner = self.inner.lock().await;
+                (PathBuf::from(&inner.config.location), inner.config.remote_origin.is_some())
+            };
+            if has_remote {
+                execute_git(&location, &["ls-remote", "--heads", "origin"]).await?;
+            }
+            Ok(())
+        })
+    }
+
+    fn snapshot_into<'a>(&'a self, target: &'a Path) -> FaillibleFuture<'a, ()> {
+        Box::pin(async move {
+            // hold the lock while copying so that no change is made in the meantime
+            let inner = self.inner.lock().await;
+            let source = PathBuf::from(&inner.config.location);
+            let target = target.to_path_buf();
+            tokio::task::spawn_blocking(move || copy_folder(&source, &target)).await??;
+            drop(inner);
+            Ok(())
+        })
+    }
+}
+
+/// Copies a folder and all its content, to be called on a blocking thread
+fn copy_folder(source: &Path, target: &Path) -> std::io::Result<()> {
+    let mut pending = vec![(source.to_path_buf(), target.to_path_buf())];
+    while let Some((source, target)) = pending.pop() {
+        std::fs::create_dir_all(&target)?;
+        for entry in std::fs::read_dir(&source)? {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                pending.push((entry.path(), target.join(entry.file_name())));
+            } else if file_type.is_file() {
+                std::fs::copy(entry.path(), target.join(entry.file_name()))?;
+            }
+        }
+    }
+    Ok(())
 }
 
 /// Manages the index on git
@@ -66,14 +135,11 @@ impl GitIndexImpl {
         let index = Self { config };
 
         // check for the SSH key
-        if let Some(file_name) = &index.config.remote_ssh_key_file_name {
-            let mut key_filename = PathBuf::from(&index.config.home_dir);
-            key_filename.push(".ssh");
-            key_filename.push(file_name);
+        if let Some(key_filename) = index.config.get_remote_ssh_key_path() {
             if !key_filename.exists() {
                 return Err(specialize(
                     error_backend_failure(),
-                    format!("Missing key file: {key_filename:?}"),
+                    format!("Missing key file: {}", key_filename.display()),
                 ));
             }
         }
@@ -187,6 +253,7 @@ impl GitIndexImpl {
     }
 
     /// Gets the upload pack advertisement for /info/refs
+    #[tracing::instrument(skip_all)]
     async fn get_upload_pack_info_refs(&self) -> Result<Vec<u8>, ApiError> {
         let location = PathBuf::from(&self.config.location);
         let mut data = execute_at_location(&location, "git-upload-pack", &["--http-backend-info-refs", ".git"], &[]).await?;
@@ -197,12 +264,14 @@ impl GitIndexImpl {
     }
 
     /// Gets the response for an upload pack request
+    #[tracing::instrument(skip_all)]
     async fn get_upload_pack_for(&self, input: &[u8]) -> Result<Vec<u8>, ApiError> {
         let location = PathBuf::from(&self.config.location);
         execute_at_location(&location, "git-upload-pack", &["--stateless-rpc", ".git"], input).await
     }
 
     /// Publish a new version for a crate
+    #[tracing::instrument(skip_all, fields(package = metadata.name, version = metadata.vers))]
     async fn publish_crate_version(&self, metadata: &IndexCrateMetadata) -> Result<(), ApiError> {
         let file_name = build_package_file_path(PathBuf::from(&self.config.location), &metadata.name);
         create_dir_all(file_name.parent().unwrap()).await?;
@@ -227,8 +296,50 @@ impl GitIndexImpl {
         Ok(())
     }
 
+    /// Rewrites the files for all the crates from their metadata, in a single commit
+    ///
+    /// The git repository and the configuration of the index are kept.
+    async fn rebuild(&self, crates: &[IndexCrateMetadata]) -> Result<(), ApiError> {
+        let location = PathBuf::from(&self.config.location);
+        let mut entries = tokio::fs::read_dir(&location).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name();
+            if name == ".git" || name == "config.json" {
+                continue;
+            }
+            if entry.file_type().await?.is_dir() {
+                tokio::fs::remove_dir_all(entry.path()).await?;
+            } else {
+                tokio::fs::remove_file(entry.path()).await?;
+            }
+        }
+        let mut files = HashMap::<PathBuf, Vec<u8>>::new();
+        for metadata in crates {
+            let buffer = files
+                .entry(build_package_file_path(location.clone(), &metadata.name))
+                .or_default();
+            serde_json::to_writer(&mut *buffer, metadata)?;
+            buffer.push(0x0A); // add line end
+        }
+        for (file_name, content) in files {
+            create_dir_all(file_name.parent().unwrap()).await?;
+            tokio::fs::write(file_name, content).await?;
+        }
+        // commit and update
+        let message = format!("Rebuild the index with {} crate versions", crates.len());
+        execute_git(&location, &["add", "--all", "."]).await?;
+        execute_git(&location, &["commit", "--allow-empty", "-m", &message]).await?;
+        execute_git(&location, &["update-server-info"]).await?;
+        if let (Some(_), true) = (self.config.remote_origin.as_ref(), self.config.remote_push_changes) {
+            execute_git(&location, &["push", "origin", "master"]).await?;
+        }
+        Ok(())
+    }
+
     ///  Gets the data for a crate
+    #[tracing::instrument(skip(self))]
     async fn get_crate_data(&self, package: &str) -> Result<Vec<IndexCrateMetadata>, ApiError> {
+        validate_crate_name(package)?;
         let file_name = build_package_file_path(PathBuf::from(&self.config.location), package);
         if !file_name.exists() {
             return Err(specialize(
@@ -245,4 +356,60 @@ impl GitIndexImpl {
         }
         Ok(results)
     }
+
+    /// Gets the raw content of the index file for a crate
+    #[tracing::instrument(skip(self))]
+    async fn get_crate_index_file(&self, package: &str) -> Result<Vec<u8>, ApiError> {
+        validate_crate_name(package)?;
+        let file_name = build_package_file_path(PathBuf::from(&self.config.location), package);
+        if !file_name.exists() {
+            return Err(specialize(
+                error_not_found(),
+                format!("package {package} is not in this registry"),
+            ));
+        }
+        Ok(tokio::fs::read(&file_name).await?)
+    }
+
+    /// Fetches the remote origin and re-synchronizes with it, only when confirmed
+    #[tracing::instrument(skip(self))]
+    async fn resync_remote(&self, mode: IndexResyncMode, confirm: bool) -> Result<IndexResyncResult, ApiError> {
+        if self.config.remote_origin.is_none() {
+            return Err(specialize(
+                error_invalid_request(),
+                String::from("the index has no remote origin"),
+            ));
+        }
+        let location = PathBuf::from(&self.config.location);
+        info!("index: fetching from origin");
+        execute_git(&location, &["fetch", "origin", "master"]).await?;
+        let local_head_before = get_git_head(&location, "HEAD").await?;
+        let remote_head = get_git_head(&location, "origin/master").await?;
+        if confirm && local_head_before != remote_head {
+            match mode {
+                IndexResyncMode::ForcePush => {
+                    info!("index: force-pushing to origin");
+                    execute_git(&location, &["push", "--force", "origin", "master"]).await?;
+                }
+                IndexResyncMode::ResetToRemote => {
+                    info!("index: resetting to origin");
+                    execute_git(&location, &["reset", "--hard", "origin/master"]).await?;
+                    execute_git(&location, &["update-server-info"]).await?;
+                }
+            }
+        }
+        let local_head_after = get_git_head(&location, "HEAD").await?;
+        Ok(IndexResyncResult {
+            performed: confirm,
+            local_head_before,
+            remote_head,
+            local_head_after,
+        })
+    }
+}
+
+/// Gets the commit for a git reference
+async fn get_git_head(location: &Path, reference: &str) -> Result<String, ApiError> {
+    let output = execute_at_location(location, "git", &["rev-parse", reference], &[]).await?;
+    Ok(String::from_utf8_lossy(&output).trim().to_string())
 }
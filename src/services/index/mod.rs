@@ -4,14 +4,19 @@
 
 //! API for index manipulation
 
+mod cache;
 mod git;
 
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
+use bytes::Bytes;
+
+pub use self::cache::CachedIndex;
 use crate::model::cargo::IndexCrateMetadata;
 use crate::model::config::Configuration;
-use crate::utils::apierror::ApiError;
+use crate::model::{IndexResyncMode, IndexResyncResult};
+use crate::utils::apierror::{error_invalid_request, specialize, ApiError};
 use crate::utils::FaillibleFuture;
 
 /// Index implementations
@@ -19,6 +24,9 @@ pub trait Index {
     /// Gets the full path to a file in the index
     fn get_index_file<'a>(&'a self, file_path: &'a Path) -> FaillibleFuture<'a, Option<PathBuf>>;
 
+    /// Gets the content of a file in the index
+    fn get_index_file_content<'a>(&'a self, file_path: &'a Path) -> FaillibleFuture<'a, Option<Bytes>>;
+
     /// Gets the upload pack advertisement for /info/refs
     fn get_upload_pack_info_refs(&self) -> FaillibleFuture<'_, Vec<u8>>;
 
@@ -30,6 +38,41 @@ pub trait Index {
 
     ///  Gets the data for a crate
     fn get_crate_data<'a>(&'a self, package: &'a str) -> FaillibleFuture<'a, Vec<IndexCrateMetadata>>;
+
+    /// Gets the raw content of the index file for a crate, with one JSON document per version on each line
+    fn get_crate_index_file<'a>(&'a self, package: &'a str) -> FaillibleFuture<'a, Vec<u8>>;
+
+    /// Rewrites the files for all the crates from their metadata, in a single commit
+    fn rebuild<'a>(&'a self, crates: &'a [IndexCrateMetadata]) -> FaillibleFuture<'a, ()>;
+
+    /// Fetches the remote origin and re-synchronizes with it, only when confirmed
+    fn resync_remote(&self, mode: IndexResyncMode, confirm: bool) -> FaillibleFuture<'_, IndexResyncResult>;
+
+    /// Checks that the remote origin, if any, is accessible
+    fn check_health(&self) -> FaillibleFuture<'_, ()>;
+
+    /// Copies the whole index into a folder, while no change is made to it
+    fn snapshot_into<'a>(&'a self, target: &'a Path) -> FaillibleFuture<'a, ()>;
+}
+
+/// The maximum length of a crate name
+const CRATE_NAME_MAX_LENGTH: usize = 64;
+
+/// Checks whether a name follows the grammar of crate names, so that it can be used to build a path in the index
+#[must_use]
+pub fn is_valid_crate_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= CRATE_NAME_MAX_LENGTH
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Checks that a name follows the grammar of crate names, before any path is built from it
+pub fn validate_crate_name(name: &str) -> Result<(), ApiError> {
+    if is_valid_crate_name(name) {
+        Ok(())
+    } else {
+        Err(specialize(error_invalid_request(), format!("invalid crate name {name}")))
+    }
 }
 
 /// Gets path elements for a package in the file system
@@ -61,6 +104,11 @@ pub fn build_package_file_path(mut root: PathBuf, name: &str) -> PathBuf {
 
 /// Gets the index service
 pub async fn get_service(config: &Configuration, expect_empty: bool) -> Result<Arc<dyn Index + Send + Sync>, ApiError> {
-    let index = git::GitIndex::new(config.get_index_git_config(), expect_empty).await?;
-    Ok(Arc::new(index))
+    let index = Arc::new(git::GitIndex::new(config.get_index_git_config(), expect_empty).await?);
+    if config.index.cache_capacity == 0 {
+        return Ok(index);
+    }
+    let cached = Arc::new(CachedIndex::new(index, config.index.cache_capacity).with_bus(&config.redis)?);
+    cached.listen_spawn();
+    Ok(cached)
 }
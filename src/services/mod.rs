@@ -6,18 +6,25 @@
 
 use std::sync::Arc;
 
+use arc_swap::ArcSwap;
+
 use crate::model::config::Configuration;
 use crate::model::worker::WorkersManager;
 use crate::utils::apierror::ApiError;
 use crate::utils::db::RwSqlitePool;
 
+pub mod chat;
 pub mod database;
 pub mod deps;
 pub mod docs;
 pub mod emails;
 pub mod index;
+pub mod notifications;
+pub mod proxy;
 pub mod rustsec;
+pub mod search;
 pub mod storage;
+pub mod webhooks;
 
 /// Factory responsible for building services
 #[allow(async_fn_in_trait)]
@@ -34,9 +41,15 @@ pub trait ServiceProvider {
     /// Gets the rustsec service
     fn get_rustsec(config: &Configuration) -> Arc<dyn rustsec::RustSecChecker + Send + Sync>;
 
+    /// Gets the service proxying the upstream registry
+    fn get_proxy(config: &Configuration) -> Result<Arc<dyn proxy::Proxy + Send + Sync>, ApiError>;
+
+    /// Gets the full-text search service
+    fn get_search(config: &Configuration) -> Result<Arc<dyn search::Search + Send + Sync>, ApiError>;
+
     /// Gets the dependencies checker service
     fn get_deps_checker(
-        configuration: Arc<Configuration>,
+        configuration: Arc<ArcSwap<Configuration>>,
         service_index: Arc<dyn index::Index + Send + Sync>,
         service_rustsec: Arc<dyn rustsec::RustSecChecker + Send + Sync>,
     ) -> Arc<dyn deps::DepsChecker + Send + Sync>;
@@ -44,9 +57,15 @@ pub trait ServiceProvider {
     /// Gets the email sender service
     fn get_email_sender(config: Arc<Configuration>) -> Arc<dyn emails::EmailSender + Send + Sync>;
 
+    /// Gets the webhook sender service
+    fn get_webhook_sender() -> Arc<dyn webhooks::WebhookSender + Send + Sync>;
+
+    /// Gets the service posting notifications to chat services
+    fn get_chat_notifier(config: Arc<Configuration>) -> Arc<dyn chat::ChatNotifier + Send + Sync>;
+
     /// Gets the documentation generation service
     fn get_docs_generator(
-        configuration: Arc<Configuration>,
+        configuration: Arc<ArcSwap<Configuration>>,
         service_db_pool: RwSqlitePool,
         service_storage: Arc<dyn storage::Storage + Send + Sync>,
         worker_nodes: WorkersManager,
@@ -61,6 +80,10 @@ impl ServiceProvider for StandardServiceProvider {
     async fn get_configuration() -> Result<Configuration, ApiError> {
         let configuration = Configuration::from_env().await?;
         configuration.write_auth_config().await?;
+        crate::utils::set_child_env(configuration.get_child_env());
+        if let Some(proxy) = configuration.get_outbound_proxy()? {
+            crate::utils::set_outbound_proxy(proxy);
+        }
         Ok(configuration)
     }
 
@@ -79,9 +102,19 @@ impl ServiceProvider for StandardServiceProvider {
         rustsec::get_service(config)
     }
 
+    /// Gets the service proxying the upstream registry
+    fn get_proxy(config: &Configuration) -> Result<Arc<dyn proxy::Proxy + Send + Sync>, ApiError> {
+        proxy::get_service(config)
+    }
+
+    /// Gets the full-text search service
+    fn get_search(config: &Configuration) -> Result<Arc<dyn search::Search + Send + Sync>, ApiError> {
+        search::get_service(config)
+    }
+
     /// Gets the dependencies checker service
     fn get_deps_checker(
-        configuration: Arc<Configuration>,
+        configuration: Arc<ArcSwap<Configuration>>,
         service_index: Arc<dyn index::Index + Send + Sync>,
         service_rustsec: Arc<dyn rustsec::RustSecChecker + Send + Sync>,
     ) -> Arc<dyn deps::DepsChecker + Send + Sync> {
@@ -93,9 +126,19 @@ impl ServiceProvider for StandardServiceProvider {
         emails::get_service(config)
     }
 
+    /// Gets the webhook sender service
+    fn get_webhook_sender() -> Arc<dyn webhooks::WebhookSender + Send + Sync> {
+        webhooks::get_service()
+    }
+
+    /// Gets the service posting notifications to chat services
+    fn get_chat_notifier(config: Arc<Configuration>) -> Arc<dyn chat::ChatNotifier + Send + Sync> {
+        chat::get_service(config)
+    }
+
     /// Gets the documentation generation service
     fn get_docs_generator(
-        configuration: Arc<Configuration>,
+        configuration: Arc<ArcSwap<Configuration>>,
         service_db_pool: RwSqlitePool,
         service_storage: Arc<dyn storage::Storage + Send + Sync>,
         worker_nodes: WorkersManager,
@@ -0,0 +1,237 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Notifications sent by email to the users about events in the registry
+
+use std::fmt::Write;
+use std::sync::Arc;
+
+use semver::{Version, VersionReq};
+
+use crate::model::cargo::RegistryUser;
+use crate::model::config::Configuration;
+use crate::model::docs::DocGenJob;
+use crate::model::profile::EmailNotification;
+use crate::model::webhooks::WebhookPayload;
+use crate::services::database::{db_transaction_read, db_transaction_write};
+use crate::services::emails::EmailSender;
+use crate::utils::apierror::ApiError;
+use crate::utils::db::RwSqlitePool;
+
+/// Notifies the users invited to be owners of a crate
+pub async fn notify_owners_invited(
+    configuration: &Configuration,
+    service_email_sender: Arc<dyn EmailSender + Send + Sync>,
+    pool: &RwSqlitePool,
+    package: &str,
+    invited: Vec<RegistryUser>,
+) -> Result<(), ApiError> {
+    if !configuration.email_notify_owners || invited.is_empty() {
+        return Ok(());
+    }
+    let invited = db_transaction_read(pool, |database| async move {
+        database
+            .filter_users_notified(invited, EmailNotification::OwnerInvitations)
+            .await
+    })
+    .await?;
+    if invited.is_empty() {
+        return Ok(());
+    }
+    let to = invited.into_iter().map(|user| user.email).collect::<Vec<_>>();
+    let mut body = String::new();
+    writeln!(body, "You have been invited to be an owner of crate {package}").unwrap();
+    writeln!(body, "See {}/crates/{package}", configuration.web_public_uri).unwrap();
+    service_email_sender
+        .send_email(&to, &format!("Cratery - you are now an owner of {package}"), body)
+        .await
+}
+
+/// Notifies the owners of the crates that depend on a version that was yanked
+pub async fn notify_yank(
+    configuration: &Configuration,
+    service_email_sender: Arc<dyn EmailSender + Send + Sync>,
+    pool: &RwSqlitePool,
+    package: &str,
+    version: &str,
+    reason: Option<&str>,
+) -> Result<(), ApiError> {
+    if !configuration.email_notify_yanks {
+        return Ok(());
+    }
+    let yanked = Version::parse(version)?;
+    // only notify when the yanked version may be selected for the dependent crate
+    let dependents = get_dependents(pool, package, |req| req.matches(&yanked)).await?;
+    for (dependent, dependent_version, to) in dependents {
+        let mut body = String::new();
+        writeln!(
+            body,
+            "Version {version} of crate {package} has been yanked, {dependent} {dependent_version} depends on it"
+        )
+        .unwrap();
+        if let Some(reason) = reason {
+            writeln!(body, "Reason: {reason}").unwrap();
+        }
+        writeln!(body, "See {}/crates/{package}/{version}", configuration.web_public_uri).unwrap();
+        service_email_sender
+            .send_email(
+                &to,
+                &format!("Cratery - {package} {version}, a dependency of {dependent}, was yanked"),
+                body,
+            )
+            .await?;
+    }
+    Ok(())
+}
+
+/// Notifies the owners of the crates that depend on a crate that was deprecated
+pub async fn notify_deprecation(
+    configuration: &Configuration,
+    service_email_sender: Arc<dyn EmailSender + Send + Sync>,
+    pool: &RwSqlitePool,
+    package: &str,
+    reason: Option<&str>,
+) -> Result<(), ApiError> {
+    if !configuration.email_notify_yanks {
+        return Ok(());
+    }
+    let dependents = get_dependents(pool, package, |_| true).await?;
+    for (dependent, dependent_version, to) in dependents {
+        let mut body = String::new();
+        writeln!(
+            body,
+            "Crate {package} has been deprecated by its owners, {dependent} {dependent_version} depends on it"
+        )
+        .unwrap();
+        if let Some(reason) = reason {
+            writeln!(body, "Reason: {reason}").unwrap();
+        }
+        writeln!(body, "See {}/crates/{package}", configuration.web_public_uri).unwrap();
+        service_email_sender
+            .send_email(
+                &to,
+                &format!("Cratery - {package}, a dependency of {dependent}, was deprecated"),
+                body,
+            )
+            .await?;
+    }
+    Ok(())
+}
+
+/// Gets the crates depending on a crate with a matching requirement, with the emails of their active owners
+async fn get_dependents(
+    pool: &RwSqlitePool,
+    package: &str,
+    matches: impl Fn(&VersionReq) -> bool,
+) -> Result<Vec<(String, String, Vec<String>)>, ApiError> {
+    let matches = &matches;
+    db_transaction_read(pool, |database| async move {
+        let reverse = database.get_reverse_dependencies(package).await?;
+        let mut dependents = Vec::new();
+        for dependency in reverse.dependencies {
+            if VersionReq::parse(&dependency.req).is_ok_and(|req| matches(&req)) {
+                let owners = database
+                    .get_crate_owners(&dependency.package)
+                    .await?
+                    .users
+                    .into_iter()
+                    .filter(|owner| owner.is_active)
+                    .collect::<Vec<_>>();
+                let to = database
+                    .filter_users_notified(owners, EmailNotification::Dependencies)
+                    .await?
+                    .into_iter()
+                    .map(|owner| owner.email)
+                    .collect::<Vec<_>>();
+                if !to.is_empty() {
+                    dependents.push((dependency.package, dependency.version, to));
+                }
+            }
+        }
+        Ok::<_, ApiError>(dependents)
+    })
+    .await
+}
+
+/// Notifies the owners of a crate that the generation of its documentation failed
+pub async fn notify_docs_failure(
+    configuration: &Configuration,
+    service_email_sender: Arc<dyn EmailSender + Send + Sync>,
+    pool: &RwSqlitePool,
+    job: &DocGenJob,
+) -> Result<(), ApiError> {
+    if !configuration.email_notify_docs_failures {
+        return Ok(());
+    }
+    let owners = db_transaction_read(pool, |database| async move {
+        let owners = database
+            .get_crate_owners(&job.package)
+            .await?
+            .users
+            .into_iter()
+            .filter(|owner| owner.is_active)
+            .collect::<Vec<_>>();
+        database.filter_users_notified(owners, EmailNotification::DocsFailures).await
+    })
+    .await?;
+    let to = owners.into_iter().map(|owner| owner.email).collect::<Vec<_>>();
+    if to.is_empty() {
+        return Ok(());
+    }
+    let mut body = String::new();
+    writeln!(
+        body,
+        "The generation of the documentation for {} {} on target {} failed",
+        job.package, job.version, job.target
+    )
+    .unwrap();
+    writeln!(
+        body,
+        "See {}/crates/{}/{}",
+        configuration.web_public_uri, job.package, job.version
+    )
+    .unwrap();
+    service_email_sender
+        .send_email(
+            &to,
+            &format!("Cratery - documentation failed for {} {}", job.package, job.version),
+            body,
+        )
+        .await
+}
+
+/// Notifies the users watching the crate of an event, in their inbox and by email
+pub async fn notify_watchers(
+    configuration: &Configuration,
+    service_email_sender: Arc<dyn EmailSender + Send + Sync>,
+    pool: &RwSqlitePool,
+    payload: &WebhookPayload,
+) -> Result<(), ApiError> {
+    let watchers = db_transaction_write(pool, "notify_watchers", |database| async move {
+        database.add_watchers_notification(payload).await
+    })
+    .await?;
+    if !configuration.email_notify_watchers {
+        return Ok(());
+    }
+    // the notifications are always in the inbox, the emails depend on the preferences of the watchers
+    let watchers = db_transaction_read(pool, |database| async move {
+        database.filter_users_notified(watchers, EmailNotification::Watches).await
+    })
+    .await?;
+    let details = payload.describe();
+    let link = match &payload.version {
+        Some(version) => format!("{}/crates/{}/{version}", configuration.web_public_uri, payload.package),
+        None => format!("{}/crates/{}", configuration.web_public_uri, payload.package),
+    };
+    for watcher in watchers {
+        let mut body = String::new();
+        writeln!(body, "A crate you are watching changed: {details}").unwrap();
+        writeln!(body, "See {link}").unwrap();
+        service_email_sender
+            .send_email(&[watcher.email], &format!("Cratery - {details}"), body)
+            .await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,431 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Service to proxy and cache upstream registries, crates.io and the configured external registries
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use log::{error, info, warn};
+
+use crate::model::config::{Configuration, ExternalRegistryProtocol};
+use crate::model::vendor::{LockedPackage, CRATES_IO_SOURCE_GIT, CRATES_IO_SOURCE_SPARSE};
+use crate::services::index::{build_package_file_path, is_valid_crate_name, package_file_path};
+use crate::utils::apierror::{error_backend_failure, specialize, ApiError};
+use crate::utils::hashes::sha256;
+use crate::utils::FaillibleFuture;
+
+/// The markers that can be used in the `dl` field of the configuration of an index
+const DL_MARKERS: [&str; 5] = ["{crate}", "{version}", "{prefix}", "{lowerprefix}", "{sha256-checksum}"];
+
+/// The name designating crates.io in the paths to proxied registries
+pub const CRATES_IO_NAME: &str = "crates-io";
+
+/// Service to proxy and cache upstream registries
+///
+/// The `registry` parameter is the name of a proxied external registry, `None` designates crates.io.
+pub trait Proxy {
+    /// Gets whether a registry is proxied
+    fn is_proxied(&self, registry: Option<&str>) -> bool;
+
+    /// Gets the full path to the cached file for a crate in the upstream sparse index
+    /// Returns `None` when the registry is not proxied or when the upstream registry does not know the crate
+    fn get_index_file<'a>(&'a self, registry: Option<&'a str>, file_path: &'a Path) -> FaillibleFuture<'a, Option<PathBuf>>;
+
+    /// Gets the content of a crate from the upstream registry
+    /// Returns `None` when the registry is not proxied or when the upstream registry does not have this version
+    fn download_crate<'a>(
+        &'a self,
+        registry: Option<&'a str>,
+        name: &'a str,
+        version: &'a str,
+    ) -> FaillibleFuture<'a, Option<Vec<u8>>>;
+
+    /// Fetches again the index file for a crate from the upstream registry and gets the versions that are not yanked
+    /// Returns `None` when the registry is not proxied or when the upstream registry does not know the crate
+    fn refresh_versions<'a>(&'a self, registry: Option<&'a str>, name: &'a str) -> FaillibleFuture<'a, Option<Vec<String>>>;
+
+    /// Gets the versions of a crate on crates.io that are not yanked, even when it is not proxied
+    /// Returns `None` when crates.io does not know the crate
+    fn get_crates_io_versions<'a>(&'a self, name: &'a str) -> FaillibleFuture<'a, Option<Vec<String>>>;
+}
+
+/// Gets whether crates are configured to be mirrored from the upstream registry
+#[must_use]
+pub fn is_mirror_configured(configuration: &Configuration) -> bool {
+    configuration.proxy.enabled
+        && (!configuration.proxy.mirror_crates.is_empty() || configuration.proxy.mirror_lock_file.is_some())
+}
+
+/// The job keeping the configured upstream crates mirrored locally, prefetching all the configured crate versions
+///
+/// # Errors
+///
+/// Returns an error when the lock file to mirror cannot be read
+pub async fn mirror_worker_job(
+    configuration: &Configuration,
+    service_proxy: &(dyn Proxy + Send + Sync),
+) -> Result<(), ApiError> {
+    let mut targets = Vec::new();
+    for name in &configuration.proxy.mirror_crates {
+        match service_proxy.refresh_versions(None, name).await {
+            Ok(Some(versions)) => targets.extend(versions.into_iter().map(|version| (name.clone(), version))),
+            Ok(None) => warn!("mirror: crate {name} is unknown upstream"),
+            Err(e) => error!("mirror: failed to refresh {name}: {e}"),
+        }
+    }
+    if let Some(lock_file) = &configuration.proxy.mirror_lock_file {
+        let content = tokio::fs::read_to_string(lock_file).await?;
+        targets.extend(
+            LockedPackage::parse_lock_file(&content)
+                .into_iter()
+                .filter(|package| {
+                    matches!(
+                        package.source.as_deref(),
+                        Some(CRATES_IO_SOURCE_GIT | CRATES_IO_SOURCE_SPARSE)
+                    )
+                })
+                .map(|package| (package.name, package.version)),
+        );
+    }
+    let mut synchronized = 0;
+    for (name, version) in &targets {
+        // already cached versions are not fetched again
+        match service_proxy.download_crate(None, name, version).await {
+            Ok(Some(_)) => synchronized += 1,
+            Ok(None) => warn!("mirror: {name} {version} is not available upstream"),
+            Err(e) => error!("mirror: failed to fetch {name} {version}: {e}"),
+        }
+    }
+    info!("mirror: {synchronized}/{} crate versions are mirrored", targets.len());
+    Ok(())
+}
+
+/// Gets the proxy service
+///
+/// # Errors
+///
+/// Returns an error when the HTTP client cannot be built
+pub fn get_service(config: &Configuration) -> Result<Arc<dyn Proxy + Send + Sync>, ApiError> {
+    // shared by all upstreams so that connections are reused
+    let client = crate::utils::http_client_builder().build()?;
+    let crates_io = Upstream {
+        client: client.clone(),
+        index_uri: config.proxy.index_uri.clone(),
+        download_template: Some(format!("{}{{crate}}/{{crate}}-{{version}}.crate", config.proxy.download_uri)),
+        credentials: None,
+        stale_index: Duration::from_millis(config.proxy.stale_index),
+        location: PathBuf::from(format!("{}/proxy", config.data_dir)),
+    };
+    let externals = config
+        .external_registries
+        .iter()
+        .filter(|registry| registry.proxy && registry.protocol == ExternalRegistryProtocol::Sparse)
+        .map(|registry| {
+            let mut index_uri = registry.index.clone();
+            if !index_uri.ends_with('/') {
+                index_uri.push('/');
+            }
+            let upstream = Upstream {
+                client: client.clone(),
+                index_uri,
+                // resolved from the config.json of the upstream index
+                download_template: None,
+                credentials: Some((registry.login.clone(), registry.token.clone())),
+                stale_index: Duration::from_millis(registry.proxy_stale_index),
+                location: PathBuf::from(format!("{}/proxy-external/{}", config.data_dir, registry.name)),
+            };
+            (registry.name.clone(), upstream)
+        })
+        .collect();
+    Ok(Arc::new(ProxyImpl {
+        crates_io,
+        crates_io_proxied: config.proxy.enabled,
+        externals,
+    }))
+}
+
+/// The proxy implementation
+struct ProxyImpl {
+    /// The upstream for crates.io
+    crates_io: Upstream,
+    /// Whether crates.io is proxied
+    crates_io_proxied: bool,
+    /// The proxied external registries, by name
+    externals: HashMap<String, Upstream>,
+}
+
+impl Proxy for ProxyImpl {
+    fn is_proxied(&self, registry: Option<&str>) -> bool {
+        self.get_upstream(registry).is_some()
+    }
+
+    fn get_index_file<'a>(&'a self, registry: Option<&'a str>, file_path: &'a Path) -> FaillibleFuture<'a, Option<PathBuf>> {
+        Box::pin(async move {
+            match self.get_upstream(registry) {
+                Some(upstream) => upstream.get_index_file(file_path).await,
+                None => Ok(None),
+            }
+        })
+    }
+
+    fn download_crate<'a>(
+        &'a self,
+        registry: Option<&'a str>,
+        name: &'a str,
+        version: &'a str,
+    ) -> FaillibleFuture<'a, Option<Vec<u8>>> {
+        Box::pin(async move {
+            match self.get_upstream(registry) {
+                Some(upstream) => upstream.download_crate(name, version).await,
+                None => Ok(None),
+            }
+        })
+    }
+
+    fn refresh_versions<'a>(&'a self, registry: Option<&'a str>, name: &'a str) -> FaillibleFuture<'a, Option<Vec<String>>> {
+        Box::pin(async move {
+            match self.get_upstream(registry) {
+                Some(upstream) => upstream.get_versions(name, true).await,
+                None => Ok(None),
+            }
+        })
+    }
+
+    fn get_crates_io_versions<'a>(&'a self, name: &'a str) -> FaillibleFuture<'a, Option<Vec<String>>> {
+        Box::pin(async move { self.crates_io.get_versions(name, false).await })
+    }
+}
+
+impl ProxyImpl {
+    /// Gets the upstream for a registry, if it is proxied
+    fn get_upstream(&self, registry: Option<&str>) -> Option<&Upstream> {
+        match registry {
+            None => self.crates_io_proxied.then_some(&self.crates_io),
+            Some(name) => self.externals.get(name),
+        }
+    }
+}
+
+/// A proxied upstream registry
+struct Upstream {
+    /// The HTTP client to use
+    client: reqwest::Client,
+    /// The URI to the upstream sparse index, ending with a trailing /
+    index_uri: String,
+    /// The template for the URI to download crates, as the `dl` field of the index configuration
+    /// When `None`, it is read from the `config.json` file of the upstream index
+    download_template: Option<String>,
+    /// The login and token to use for authenticating to the upstream registry
+    credentials: Option<(String, String)>,
+    /// The duration after which a cached index file must be fetched again
+    stale_index: Duration,
+    /// The folder for the cached data
+    location: PathBuf,
+}
+
+impl Upstream {
+    /// Gets the full path to the cached file for a crate in the upstream sparse index
+    async fn get_index_file(&self, file_path: &Path) -> Result<Option<PathBuf>, ApiError> {
+        let Some(name) = file_path.file_name().and_then(|name| name.to_str()) else {
+            return Ok(None);
+        };
+        // only accept the canonical path for the crate, this also prevents escaping the cache folder
+        if !is_valid_crate_name(name) || build_package_file_path(PathBuf::from("/"), name) != file_path {
+            return Ok(None);
+        }
+        let relative = build_package_file_path(PathBuf::new(), name);
+        self.get_cached_index_file(&relative.to_string_lossy()).await
+    }
+
+    /// Gets the versions of a crate that are not yanked, fetching again its index file when stale or when forced to
+    async fn get_versions(&self, name: &str, force: bool) -> Result<Option<Vec<String>>, ApiError> {
+        if !is_valid_crate_name(name) {
+            return Ok(None);
+        }
+        let relative = build_package_file_path(PathBuf::new(), name);
+        let Some(index_file) = self.fetch_index_file(&relative.to_string_lossy(), force).await? else {
+            return Ok(None);
+        };
+        let content = tokio::fs::read_to_string(&index_file).await?;
+        let mut versions = Vec::new();
+        for line in content.lines().filter(|line| !line.trim().is_empty()) {
+            let entry = serde_json::from_str::<serde_json::Value>(line)?;
+            let is_yanked = entry.get("yanked").and_then(serde_json::Value::as_bool).unwrap_or(false);
+            if let (Some(version), false) = (entry.get("vers").and_then(serde_json::Value::as_str), is_yanked) {
+                versions.push(version.to_string());
+            }
+        }
+        Ok(Some(versions))
+    }
+
+    /// Gets a file of the upstream sparse index, fetching it again when stale
+    async fn get_cached_index_file(&self, relative: &str) -> Result<Option<PathBuf>, ApiError> {
+        self.fetch_index_file(relative, false).await
+    }
+
+    /// Gets a file of the upstream sparse index, fetching it again when stale or when forced to
+    async fn fetch_index_file(&self, relative: &str, force: bool) -> Result<Option<PathBuf>, ApiError> {
+        let cached = self.location.join("index").join(relative);
+        if !force && tokio::fs::try_exists(&cached).await? && !self.is_stale(&cached).await? {
+            return Ok(Some(cached));
+        }
+        let target_uri = format!("{}{relative}", self.index_uri);
+        match self.fetch(&target_uri).await {
+            Ok(Some(content)) => {
+                write_atomic(&cached, &content).await?;
+                Ok(Some(cached))
+            }
+            Ok(None) => Ok(None),
+            Err(e) => {
+                if tokio::fs::try_exists(&cached).await? {
+                    // upstream is not available, serve what we have
+                    warn!("failed to refresh {target_uri}, serving cached data: {e}");
+                    Ok(Some(cached))
+                } else {
+                    Err(e)
+                }
+            }
+        }
+    }
+
+    /// Gets the content of a crate from the upstream registry
+    async fn download_crate(&self, name: &str, version: &str) -> Result<Option<Vec<u8>>, ApiError> {
+        if [name, version]
+            .iter()
+            .any(|part| part.contains(['/', '\\']) || part.starts_with('.'))
+        {
+            return Ok(None);
+        }
+        let cached = self
+            .location
+            .join("crates")
+            .join(name.to_ascii_lowercase())
+            .join(format!("{version}.crate"));
+        if tokio::fs::try_exists(&cached).await? {
+            // crate contents are immutable
+            return Ok(Some(tokio::fs::read(&cached).await?));
+        }
+        // never cache or serve content that does not match the upstream index
+        let Some(expected) = self.get_checksum(name, version).await? else {
+            return Ok(None);
+        };
+        let Some(target_uri) = self.get_download_uri(name, version).await? else {
+            return Ok(None);
+        };
+        let Some(content) = self.fetch(&target_uri).await? else {
+            return Ok(None);
+        };
+        let actual = sha256(&content);
+        if !actual.eq_ignore_ascii_case(&expected) {
+            return Err(specialize(
+                error_backend_failure(),
+                format!("checksum mismatch for {name} {version} from {target_uri}: expected {expected}, got {actual}"),
+            ));
+        }
+        write_atomic(&cached, &content).await?;
+        Ok(Some(content))
+    }
+
+    /// Gets the URI to download a crate, following the conventions for the `dl` field of the index configuration
+    async fn get_download_uri(&self, name: &str, version: &str) -> Result<Option<String>, ApiError> {
+        let template = if let Some(template) = &self.download_template {
+            template.clone()
+        } else {
+            let Some(config_file) = self.get_cached_index_file("config.json").await? else {
+                return Ok(None);
+            };
+            let config = serde_json::from_slice::<serde_json::Value>(&tokio::fs::read(&config_file).await?)?;
+            let Some(dl) = config.get("dl").and_then(serde_json::Value::as_str) else {
+                return Err(specialize(
+                    error_backend_failure(),
+                    format!("missing dl in {}config.json", self.index_uri),
+                ));
+            };
+            dl.to_string()
+        };
+        if !DL_MARKERS.iter().any(|marker| template.contains(marker)) {
+            return Ok(Some(format!("{template}/{name}/{version}/download")));
+        }
+        let prefix = |name: &str| match package_file_path(name) {
+            (first, Some(second)) => format!("{first}/{second}"),
+            (first, None) => first.to_string(),
+        };
+        let mut uri = template
+            .replace("{crate}", name)
+            .replace("{version}", version)
+            .replace("{prefix}", &prefix(name))
+            .replace("{lowerprefix}", &prefix(&name.to_ascii_lowercase()));
+        if uri.contains("{sha256-checksum}") {
+            let Some(checksum) = self.get_checksum(name, version).await? else {
+                return Ok(None);
+            };
+            uri = uri.replace("{sha256-checksum}", &checksum);
+        }
+        Ok(Some(uri))
+    }
+
+    /// Gets the checksum of a crate version from the upstream index
+    async fn get_checksum(&self, name: &str, version: &str) -> Result<Option<String>, ApiError> {
+        if !is_valid_crate_name(name) {
+            return Ok(None);
+        }
+        let relative = build_package_file_path(PathBuf::new(), name);
+        let Some(index_file) = self.get_cached_index_file(&relative.to_string_lossy()).await? else {
+            return Ok(None);
+        };
+        let content = tokio::fs::read_to_string(&index_file).await?;
+        for line in content.lines().filter(|line| !line.trim().is_empty()) {
+            let entry = serde_json::from_str::<serde_json::Value>(line)?;
+            if entry.get("vers").and_then(serde_json::Value::as_str) == Some(version) {
+                return Ok(entry.get("cksum").and_then(serde_json::Value::as_str).map(str::to_string));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Gets whether a cached index file must be fetched again
+    async fn is_stale(&self, file: &Path) -> Result<bool, ApiError> {
+        let modified = tokio::fs::metadata(file).await?.modified()?;
+        Ok(SystemTime::now()
+            .duration_since(modified)
+            .is_ok_and(|age| age > self.stale_index))
+    }
+
+    /// Fetches a resource on the upstream registry
+    /// Returns `None` when the resource does not exist
+    async fn fetch(&self, target_uri: &str) -> Result<Option<Vec<u8>>, ApiError> {
+        let mut request = self.client.get(target_uri);
+        if let Some((login, token)) = &self.credentials {
+            // the client drops the authorization header when following a redirection to another host
+            request = request.basic_auth(login, Some(token));
+        }
+        let response = request.send().await?;
+        let status = response.status().as_u16();
+        if status == 404 || status == 410 || status == 451 {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(specialize(
+                error_backend_failure(),
+                format!("failed to get {target_uri}: error code {status}"),
+            ));
+        }
+        Ok(Some(response.bytes().await?.to_vec()))
+    }
+}
+
+/// Writes the content of a file so that readers never see a partial file
+async fn write_atomic(file: &Path, content: &[u8]) -> Result<(), ApiError> {
+    if let Some(parent) = file.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let mut temp = file.as_os_str().to_owned();
+    temp.push(format!(".{}.tmp", uuid::Uuid::new_v4()));
+    tokio::fs::write(&temp, content).await?;
+    tokio::fs::rename(&temp, file).await?;
+    Ok(())
+}
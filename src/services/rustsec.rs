@@ -19,12 +19,16 @@ use crate::model::config::Configuration;
 use crate::model::osv::{Advisory, SimpleAdvisory};
 use crate::utils::apierror::ApiError;
 use crate::utils::concurrent::n_at_a_time_stream;
-use crate::utils::{stale_instant, FaillibleFuture};
+use crate::utils::{execute_at_location, stale_instant, FaillibleFuture};
 
 /// Service to use the [RustSec](https://github.com/rustsec) data about crates
 pub trait RustSecChecker {
     /// Gets the advisories against a crate
     fn check_crate<'a>(&'a self, package: &'a str, version: &'a Version) -> FaillibleFuture<'a, Vec<SimpleAdvisory>>;
+
+    /// Synchronizes the local copy of the advisory database, even when it is not stale
+    /// Returns whether the advisories changed
+    fn sync(&self) -> FaillibleFuture<'_, bool>;
 }
 
 /// Gets the rustsec service
@@ -59,6 +63,17 @@ impl RustSecChecker for RustSecCheckerImpl {
                 .unwrap_or_default())
         })
     }
+
+    /// Synchronizes the local copy of the advisory database, even when it is not stale
+    fn sync(&self) -> FaillibleFuture<'_, bool> {
+        Box::pin(async move {
+            let mut data = self.data.lock().await;
+            let before = data.get_head().await;
+            data.last_touch = stale_instant();
+            data.update_data().await?;
+            Ok(data.get_head().await != before)
+        })
+    }
 }
 
 /// Service to use the [RustSec](https://github.com/rustsec) data about crates
@@ -91,12 +106,25 @@ impl RustSecData {
         }
     }
 
+    /// Gets the location of the local copy of the database
+    fn get_location(&self) -> PathBuf {
+        let mut reg_location = PathBuf::from(&self.data_dir);
+        reg_location.push(DATA_SUB_DIR);
+        reg_location
+    }
+
+    /// Gets the current commit of the local copy of the database, if any
+    async fn get_head(&self) -> Option<Vec<u8>> {
+        execute_at_location(&self.get_location(), "git", &["rev-parse", "HEAD"], &[])
+            .await
+            .ok()
+    }
+
     /// Updates the data
     async fn update_data(&mut self) -> Result<(), ApiError> {
         let now = Instant::now();
         let is_stale = now.duration_since(self.last_touch) > Duration::from_millis(self.stale_registry);
-        let mut reg_location = PathBuf::from(&self.data_dir);
-        reg_location.push(DATA_SUB_DIR);
+        let mut reg_location = self.get_location();
         if is_stale {
             if tokio::fs::try_exists(&reg_location).await? {
                 crate::utils::execute_git(&reg_location, &["fetch", "origin", RUSTSEC_DB_GIT_BRANCH]).await?;
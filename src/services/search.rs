@@ -0,0 +1,376 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Service for the full-text search of crates, backed by a tantivy index
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use log::warn;
+use tantivy::collector::TopDocs;
+use tantivy::directory::MmapDirectory;
+use tantivy::query::{AllQuery, BooleanQuery, BoostQuery, FuzzyTermQuery, Occur, Query, QueryParser, RegexQuery, TermQuery};
+use tantivy::schema::{Field, IndexRecordOption, Schema, Value, STORED, STRING, TEXT};
+use tantivy::{Index, IndexReader, IndexWriter, ReloadPolicy, TantivyDocument, Term};
+
+use crate::model::config::Configuration;
+use crate::model::search::{CrateSearchDocument, CrateSearchHit, SearchFilters};
+use crate::utils::apierror::ApiError;
+use crate::utils::FaillibleFuture;
+
+/// The maximum number of hits for a single search
+const MAX_HITS: usize = 1000;
+/// The memory budget for the index writer, in bytes
+const WRITER_MEMORY_BUDGET: usize = 15_000_000;
+/// The boost for an exact match on the name of a crate
+const BOOST_EXACT_NAME: f32 = 10.0;
+/// The boost for an approximate match on the name of a crate
+const BOOST_FUZZY_NAME: f32 = 5.0;
+/// The boost for an approximate match of a term in the name or keywords
+const BOOST_FUZZY_TERM: f32 = 0.5;
+/// The minimum length of a term for approximate matches
+const FUZZY_MIN_LENGTH: usize = 3;
+/// The version of the content of the index, stored as the payload of commits
+/// An index with another version is re-created
+const INDEX_VERSION: &str = "2";
+
+/// Service for the full-text search of crates
+pub trait Search {
+    /// Gets whether the index does not contain any crate at all
+    fn is_empty(&self) -> FaillibleFuture<'_, bool>;
+
+    /// Indexes a crate, replacing any previous data for it
+    fn index_crate<'a>(&'a self, document: &'a CrateSearchDocument) -> FaillibleFuture<'a, ()>;
+
+    /// Searches for crates, filtering them by keyword and category
+    /// Returns the matching crates, by decreasing relevance
+    fn search<'a>(&'a self, query: &'a str, filters: &'a SearchFilters) -> FaillibleFuture<'a, Vec<CrateSearchHit>>;
+
+    /// Suggests the names of crates that start with a prefix
+    /// Returns at most `limit` names, shortest first
+    fn suggest<'a>(&'a self, prefix: &'a str, limit: usize) -> FaillibleFuture<'a, Vec<String>>;
+
+    /// Replaces the whole content of the index with the specified crates, at once
+    fn rebuild(&self, documents: Vec<CrateSearchDocument>) -> FaillibleFuture<'_, ()>;
+}
+
+/// Gets the search service
+pub fn get_service(config: &Configuration) -> Result<Arc<dyn Search + Send + Sync>, ApiError> {
+    Ok(Arc::new(SearchImpl::new(config)?))
+}
+
+/// The fields in the search index
+#[derive(Debug, Clone, Copy)]
+struct Fields {
+    /// The name of the crate, as tokenized text
+    name: Field,
+    /// The normalized name of the crate, as a single term
+    name_raw: Field,
+    /// The description
+    description: Field,
+    /// The keywords
+    keywords: Field,
+    /// The lowercase keywords, as single terms
+    keywords_raw: Field,
+    /// The categories
+    categories: Field,
+    /// The lowercase categories, as single terms
+    categories_raw: Field,
+    /// The README
+    readme: Field,
+}
+
+impl Fields {
+    /// Builds the schema for the index
+    fn build() -> (Schema, Self) {
+        let mut builder = Schema::builder();
+        let fields = Self {
+            name: builder.add_text_field("name", TEXT | STORED),
+            name_raw: builder.add_text_field("name_raw", STRING),
+            description: builder.add_text_field("description", TEXT),
+            keywords: builder.add_text_field("keywords", TEXT),
+            keywords_raw: builder.add_text_field("keywords_raw", STRING | STORED),
+            categories: builder.add_text_field("categories", TEXT),
+            categories_raw: builder.add_text_field("categories_raw", STRING | STORED),
+            readme: builder.add_text_field("readme", TEXT),
+        };
+        (builder.build(), fields)
+    }
+}
+
+/// The search service implementation
+struct SearchImpl {
+    /// The tantivy index
+    index: Index,
+    /// The fields in the index
+    fields: Fields,
+    /// The reader for the index
+    reader: IndexReader,
+    /// The writer for the index
+    writer: Arc<Mutex<IndexWriter>>,
+}
+
+impl Search for SearchImpl {
+    fn is_empty(&self) -> FaillibleFuture<'_, bool> {
+        Box::pin(async move { Ok(self.reader.searcher().num_docs() == 0) })
+    }
+
+    fn index_crate<'a>(&'a self, document: &'a CrateSearchDocument) -> FaillibleFuture<'a, ()> {
+        Box::pin(async move { self.index_crate(document).await })
+    }
+
+    fn search<'a>(&'a self, query: &'a str, filters: &'a SearchFilters) -> FaillibleFuture<'a, Vec<CrateSearchHit>> {
+        Box::pin(async move { self.search(query, filters) })
+    }
+
+    fn suggest<'a>(&'a self, prefix: &'a str, limit: usize) -> FaillibleFuture<'a, Vec<String>> {
+        Box::pin(async move { self.suggest(prefix, limit) })
+    }
+
+    fn rebuild(&self, documents: Vec<CrateSearchDocument>) -> FaillibleFuture<'_, ()> {
+        Box::pin(async move { self.rebuild(documents).await })
+    }
+}
+
+impl SearchImpl {
+    /// Opens or creates the index
+    fn new(config: &Configuration) -> Result<Self, ApiError> {
+        let location = PathBuf::from(format!("{}/search", config.data_dir));
+        std::fs::create_dir_all(&location)?;
+        let (schema, fields) = Fields::build();
+        let index = match Self::open(&location, schema.clone()) {
+            Ok(Some(index)) => index,
+            Ok(None) => {
+                // the index is outdated, re-create it, it will be re-filled
+                warn!("the search index is outdated, re-creating it");
+                std::fs::remove_dir_all(&location)?;
+                std::fs::create_dir_all(&location)?;
+                Index::create_in_dir(&location, schema)?
+            }
+            Err(e) => {
+                // the index is incompatible, re-create it, it will be re-filled
+                warn!("failed to open the search index, re-creating it: {e}");
+                std::fs::remove_dir_all(&location)?;
+                std::fs::create_dir_all(&location)?;
+                Index::create_in_dir(&location, schema)?
+            }
+        };
+        let reader = index.reader_builder().reload_policy(ReloadPolicy::Manual).try_into()?;
+        let writer = index.writer(WRITER_MEMORY_BUDGET)?;
+        Ok(Self {
+            index,
+            fields,
+            reader,
+            writer: Arc::new(Mutex::new(writer)),
+        })
+    }
+
+    /// Opens or creates the index in a folder
+    /// Returns `None` when the existing index has another version
+    fn open(location: &Path, schema: Schema) -> Result<Option<Index>, ApiError> {
+        let index = Index::open_or_create(MmapDirectory::open(location)?, schema)?;
+        let metas = index.load_metas()?;
+        if metas.opstamp > 0 && metas.payload.as_deref() != Some(INDEX_VERSION) {
+            return Ok(None);
+        }
+        Ok(Some(index))
+    }
+
+    /// Indexes a crate, replacing any previous data for it
+    async fn index_crate(&self, document: &CrateSearchDocument) -> Result<(), ApiError> {
+        let doc = self.build_document(document);
+        let name_term = Term::from_field_text(self.fields.name_raw, &normalize_name(&document.name));
+        let writer = self.writer.clone();
+        // committing is blocking
+        tokio::task::spawn_blocking(move || {
+            let mut writer = writer.lock().unwrap();
+            writer.delete_term(name_term);
+            writer.add_document(doc)?;
+            Self::commit(&mut writer)
+        })
+        .await??;
+        self.reader.reload()?;
+        Ok(())
+    }
+
+    /// Replaces the whole content of the index with the specified crates, at once
+    async fn rebuild(&self, documents: Vec<CrateSearchDocument>) -> Result<(), ApiError> {
+        let docs = documents
+            .iter()
+            .map(|document| self.build_document(document))
+            .collect::<Vec<_>>();
+        let writer = self.writer.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut writer = writer.lock().unwrap();
+            writer.delete_all_documents()?;
+            for doc in docs {
+                writer.add_document(doc)?;
+            }
+            Self::commit(&mut writer)
+        })
+        .await??;
+        self.reader.reload()?;
+        Ok(())
+    }
+
+    /// Commits the pending changes, marking the index with the current version
+    fn commit(writer: &mut IndexWriter) -> Result<(), ApiError> {
+        let mut commit = writer.prepare_commit()?;
+        commit.set_payload(INDEX_VERSION);
+        commit.commit()?;
+        Ok(())
+    }
+
+    /// Builds the tantivy document for a crate
+    fn build_document(&self, document: &CrateSearchDocument) -> TantivyDocument {
+        let fields = self.fields;
+        let mut doc = TantivyDocument::default();
+        doc.add_text(fields.name, &document.name);
+        doc.add_text(fields.name_raw, normalize_name(&document.name));
+        doc.add_text(fields.description, &document.description);
+        for keyword in &document.keywords {
+            doc.add_text(fields.keywords, keyword);
+            doc.add_text(fields.keywords_raw, keyword.to_lowercase());
+        }
+        for category in &document.categories {
+            doc.add_text(fields.categories, category);
+            doc.add_text(fields.categories_raw, category.to_lowercase());
+        }
+        doc.add_text(fields.readme, &document.readme);
+        doc
+    }
+
+    /// Searches for crates
+    fn search(&self, query: &str, filters: &SearchFilters) -> Result<Vec<CrateSearchHit>, ApiError> {
+        let fields = self.fields;
+        let query = query.trim();
+        let query: Box<dyn Query> = if query.is_empty() {
+            Box::new(AllQuery)
+        } else {
+            let mut parser = QueryParser::for_index(
+                &self.index,
+                vec![
+                    fields.name,
+                    fields.description,
+                    fields.keywords,
+                    fields.categories,
+                    fields.readme,
+                ],
+            );
+            parser.set_field_boost(fields.name, 5.0);
+            parser.set_field_boost(fields.keywords, 3.0);
+            parser.set_field_boost(fields.categories, 2.0);
+            parser.set_field_boost(fields.description, 2.0);
+            let (text_query, _errors) = parser.parse_query_lenient(query);
+            let name = normalize_name(query);
+            let mut clauses = vec![
+                (Occur::Should, text_query),
+                (
+                    Occur::Should,
+                    Box::new(BoostQuery::new(exact_term_query(fields.name_raw, &name), BOOST_EXACT_NAME)) as Box<dyn Query>,
+                ),
+                (
+                    Occur::Should,
+                    Box::new(BoostQuery::new(fuzzy_term_query(fields.name_raw, &name), BOOST_FUZZY_NAME)),
+                ),
+            ];
+            // tolerate typos in the individual terms
+            let mut analyzer = self.index.tokenizer_for_field(fields.name)?;
+            let mut terms = Vec::new();
+            analyzer
+                .token_stream(query)
+                .process(&mut |token| terms.push(token.text.clone()));
+            for term in terms.iter().filter(|term| term.chars().count() >= FUZZY_MIN_LENGTH) {
+                for field in [fields.name, fields.keywords] {
+                    clauses.push((
+                        Occur::Should,
+                        Box::new(BoostQuery::new(fuzzy_term_query(field, term), BOOST_FUZZY_TERM)),
+                    ));
+                }
+            }
+            Box::new(BooleanQuery::new(clauses))
+        };
+        let mut clauses = vec![(Occur::Must, query)];
+        if let Some(keyword) = &filters.keyword {
+            clauses.push((Occur::Must, exact_term_query(fields.keywords_raw, keyword)));
+        }
+        if let Some(category) = &filters.category {
+            clauses.push((Occur::Must, exact_term_query(fields.categories_raw, category)));
+        }
+        let query = BooleanQuery::new(clauses);
+
+        let searcher = self.reader.searcher();
+        let hits = searcher.search(&query, &TopDocs::with_limit(MAX_HITS))?;
+        let mut results = Vec::with_capacity(hits.len());
+        for (relevance, address) in hits {
+            let doc = searcher.doc::<TantivyDocument>(address)?;
+            let Some(name) = doc.get_first(fields.name).and_then(|v| v.as_str()) else {
+                continue;
+            };
+            let get_all = |field| {
+                doc.get_all(field)
+                    .filter_map(|v| v.as_str())
+                    .map(str::to_string)
+                    .collect::<Vec<_>>()
+            };
+            results.push(CrateSearchHit {
+                name: name.to_string(),
+                relevance,
+                keywords: get_all(fields.keywords_raw),
+                categories: get_all(fields.categories_raw),
+            });
+        }
+        Ok(results)
+    }
+
+    /// Suggests the names of crates that start with a prefix
+    fn suggest(&self, prefix: &str, limit: usize) -> Result<Vec<String>, ApiError> {
+        let fields = self.fields;
+        let prefix = normalize_name(prefix.trim());
+        if prefix.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut pattern = String::with_capacity(prefix.len() + 2);
+        for c in prefix.chars() {
+            if !c.is_alphanumeric() && c != '_' {
+                pattern.push('\\');
+            }
+            pattern.push(c);
+        }
+        pattern.push_str(".*");
+        let query = RegexQuery::from_pattern(&pattern, fields.name_raw)?;
+        let searcher = self.reader.searcher();
+        let hits = searcher.search(&query, &TopDocs::with_limit(MAX_HITS))?;
+        let mut names = Vec::with_capacity(hits.len());
+        for (_score, address) in hits {
+            let doc = searcher.doc::<TantivyDocument>(address)?;
+            if let Some(name) = doc.get_first(fields.name).and_then(|v| v.as_str()) {
+                names.push(name.to_string());
+            }
+        }
+        names.sort_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)));
+        names.truncate(limit);
+        Ok(names)
+    }
+}
+
+/// Builds a query for the exact match of a value on a raw field, ignoring case
+fn exact_term_query(field: Field, value: &str) -> Box<dyn Query> {
+    Box::new(TermQuery::new(
+        Term::from_field_text(field, &value.to_lowercase()),
+        IndexRecordOption::Basic,
+    ))
+}
+
+/// Builds a query for the approximate match of a term, with an edit distance depending on its length
+fn fuzzy_term_query(field: Field, term: &str) -> Box<dyn Query> {
+    let distance = if term.chars().count() <= 4 { 1 } else { 2 };
+    Box::new(FuzzyTermQuery::new(Term::from_field_text(field, term), distance, true))
+}
+
+/// Normalizes the name of a crate for exact matches
+/// Names are compared in lowercase, and `-` and `_` are equivalent
+fn normalize_name(name: &str) -> String {
+    name.to_lowercase().replace('-', "_")
+}
@@ -7,17 +7,43 @@
 use std::io::Read;
 use std::path::Path;
 use std::sync::Arc;
+use std::time::Duration;
 
+use async_trait::async_trait;
+use axum::http::header::{AUTHORIZATION, ETAG, HOST};
+use axum::http::{HeaderMap, HeaderValue, Method, Request, Response};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
 use flate2::bufread::GzDecoder;
-use opendal::layers::LoggingLayer;
-use opendal::{ErrorKind, Operator};
+use log::warn;
+use opendal::layers::{LoggingLayer, TracingLayer};
+use opendal::raw::{HttpBody, HttpClient, HttpFetch};
+use opendal::{Buffer, ErrorKind, Operator};
+use reqsign::{AwsAssumeRoleLoader, AwsConfig, AwsCredential, AwsCredentialLoad, AwsDefaultLoader, AwsV4Signer};
 use tar::Archive;
 
 use crate::model::cargo::CrateMetadata;
-use crate::model::config::{Configuration, StorageConfig};
+use crate::model::config::{Configuration, S3AddressingStyle, S3Params, StorageConfig};
+use crate::model::{ByteRange, ContentPart, CrateContent};
 use crate::utils::apierror::ApiError;
+use crate::utils::hashes::{md5, sha256_bytes};
 use crate::utils::FaillibleFuture;
 
+/// The folder in the storage for the backups of the database
+const BACKUPS_FOLDER: &str = "backups";
+
+/// The path in the storage of the file written to check that it is writable
+const HEALTH_PROBE_PATH: &str = "health/probe";
+
+/// The header for the SHA-256 checksum of the content of the uploads
+const X_AMZ_CHECKSUM_SHA256: &str = "x-amz-checksum-sha256";
+
+/// The header for the date of the signature of the requests to S3
+const X_AMZ_DATE: &str = "x-amz-date";
+
+/// The header for the source of the copies in S3
+const X_AMZ_COPY_SOURCE: &str = "x-amz-copy-source";
+
 /// Backing storage implementations
 pub trait Storage {
     /// Stores the data for a crate
@@ -26,12 +52,32 @@ pub trait Storage {
     /// Downloads a crate
     fn download_crate<'a>(&'a self, name: &'a str, version: &'a str) -> FaillibleFuture<'a, Vec<u8>>;
 
+    /// Downloads a range of bytes in the content of a crate
+    fn download_crate_range<'a>(
+        &'a self,
+        name: &'a str,
+        version: &'a str,
+        range: ByteRange,
+    ) -> FaillibleFuture<'a, CrateContent>;
+
     /// Downloads the last metadata for a crate
     fn download_crate_metadata<'a>(&'a self, name: &'a str, version: &'a str) -> FaillibleFuture<'a, Option<CrateMetadata>>;
 
     /// Downloads the last README for a crate
     fn download_crate_readme<'a>(&'a self, name: &'a str, version: &'a str) -> FaillibleFuture<'a, Vec<u8>>;
 
+    /// Stores a prebuilt binary artifact for a crate version and a target
+    fn store_crate_binary<'a>(
+        &'a self,
+        name: &'a str,
+        version: &'a str,
+        target: &'a str,
+        content: Vec<u8>,
+    ) -> FaillibleFuture<'a, ()>;
+
+    /// Downloads a prebuilt binary artifact for a crate version and a target
+    fn download_crate_binary<'a>(&'a self, name: &'a str, version: &'a str, target: &'a str) -> FaillibleFuture<'a, Vec<u8>>;
+
     /// Stores a documentation file
     fn store_doc_file<'a>(&'a self, path: &'a str, file: &'a Path) -> FaillibleFuture<'a, ()>;
 
@@ -40,6 +86,21 @@ pub trait Storage {
 
     /// Gets the content of a documentation file
     fn download_doc_file<'a>(&'a self, path: &'a str) -> FaillibleFuture<'a, Vec<u8>>;
+
+    /// Stores a backup of the database
+    fn store_backup<'a>(&'a self, name: &'a str, content: Vec<u8>) -> FaillibleFuture<'a, ()>;
+
+    /// Downloads a backup of the database
+    fn download_backup<'a>(&'a self, name: &'a str) -> FaillibleFuture<'a, Vec<u8>>;
+
+    /// Lists the names of the stored backups of the database
+    fn list_backups(&self) -> FaillibleFuture<'_, Vec<String>>;
+
+    /// Deletes a backup of the database
+    fn delete_backup<'a>(&'a self, name: &'a str) -> FaillibleFuture<'a, ()>;
+
+    /// Checks that the storage is reachable and writable
+    fn check_health(&self) -> FaillibleFuture<'_, ()>;
 }
 
 /// Gets the backing storage for the documentation
@@ -48,6 +109,181 @@ pub fn get_service(config: &Configuration) -> Arc<dyn Storage + Send + Sync> {
     Arc::new(StorageImpl::from(config))
 }
 
+/// Builds the HTTP client shared by all the requests to S3
+///
+/// Connections are kept alive and reused between requests, and HTTP/2 is used when the server supports it,
+/// so that the TLS handshakes are not repeated for each publication or download.
+/// The storage timeout applies to establishing a connection and to each read.
+fn build_s3_client(params: &S3Params, storage_timeout: u64) -> reqwest::Client {
+    crate::utils::http_client_builder()
+        .connect_timeout(Duration::from_millis(storage_timeout))
+        .read_timeout(Duration::from_millis(storage_timeout))
+        .timeout(Duration::from_secs(params.request_timeout))
+        .pool_max_idle_per_host(params.pool_max_idle)
+        .pool_idle_timeout(Duration::from_secs(params.pool_idle_timeout))
+        .tcp_keepalive(Duration::from_secs(params.pool_idle_timeout))
+        .http2_adaptive_window(true)
+        .build()
+        .expect("failed to build the HTTP client for S3")
+}
+
+/// Builds the HTTP client for loading the credentials for S3
+fn build_credentials_client() -> reqwest::Client {
+    crate::utils::http_client_builder()
+        .build()
+        .expect("failed to build the HTTP client for the S3 credentials")
+}
+
+/// Builds the loader of the credentials for S3, shared between the operator and the [`S3Fetcher`]
+///
+/// Without access keys, the credentials are looked for in the environment (AWS_* variables and profile files,
+/// web identity token for IRSA, then the instance metadata) and refreshed before they expire.
+fn build_s3_credential_loader(params: &S3Params) -> Arc<dyn AwsCredentialLoad> {
+    let mut config = AwsConfig::default().from_profile().from_env();
+    config.region = Some(params.region.clone());
+    if !params.access_key.is_empty() {
+        config.access_key_id = Some(params.access_key.clone());
+    }
+    if !params.secret_key.is_empty() {
+        config.secret_access_key = Some(params.secret_key.clone());
+    }
+    let mut default_loader = AwsDefaultLoader::new(build_credentials_client(), config);
+    if !params.instance_metadata {
+        default_loader = default_loader.with_disable_ec2_metadata();
+    }
+    let Some(role_arn) = &params.role_arn else {
+        return Arc::new(default_loader);
+    };
+    let mut assume_role_config = AwsConfig {
+        region: Some(params.region.clone()),
+        role_arn: Some(role_arn.clone()),
+        external_id: params.external_id.clone(),
+        sts_regional_endpoints: "regional".to_string(),
+        ..Default::default()
+    };
+    if let Some(role_session_name) = &params.role_session_name {
+        assume_role_config.role_session_name.clone_from(role_session_name);
+    }
+    let loader = AwsAssumeRoleLoader::new(build_credentials_client(), assume_role_config, Box::new(default_loader))
+        .expect("the region is set for the assume role loader");
+    Arc::new(loader)
+}
+
+/// Credential loader given to the operator, that shares the loader of the [`S3Fetcher`]
+struct SharedCredentialLoader(Arc<dyn AwsCredentialLoad>);
+
+#[async_trait]
+impl AwsCredentialLoad for SharedCredentialLoader {
+    async fn load_credential(&self, client: reqwest::Client) -> anyhow::Result<Option<AwsCredential>> {
+        self.0.load_credential(client).await
+    }
+}
+
+/// Sends the requests of the operator to S3, adding the checksums to the uploads and verifying the responses
+///
+/// The operator only supports CRC32C for the additional checksums.
+/// The uploads are instead signed again with the `x-amz-checksum-sha256` header,
+/// so that S3 rejects those whose content does not match.
+/// The `ETag` in the response to the uploads is compared to the MD5 digest of the content,
+/// which does not require another request to read the metadata of the object.
+pub struct S3Fetcher {
+    client: reqwest::Client,
+    signer: AwsV4Signer,
+    loader: Arc<dyn AwsCredentialLoad>,
+    /// Whether a SHA-256 checksum is sent with the uploads
+    checksum_uploads: bool,
+    /// Whether the response to the uploads is verified against the content
+    verify_etag: bool,
+}
+
+impl S3Fetcher {
+    /// Adds the SHA-256 checksum of the content to an upload and signs it again
+    async fn add_checksum(&self, request: &mut Request<Buffer>, checksum: &str) -> opendal::Result<()> {
+        let Some(credential) = self.loader.load_credential(self.client.clone()).await.map_err(|e| {
+            opendal::Error::new(ErrorKind::PermissionDenied, "failed to load the credentials for S3").set_source(e)
+        })?
+        else {
+            // anonymous access, the request is not signed
+            request
+                .headers_mut()
+                .insert(X_AMZ_CHECKSUM_SHA256, HeaderValue::from_str(checksum).unwrap());
+            return Ok(());
+        };
+        let headers = request.headers_mut();
+        headers.remove(AUTHORIZATION);
+        headers.remove(X_AMZ_DATE);
+        headers.insert(X_AMZ_CHECKSUM_SHA256, HeaderValue::from_str(checksum).unwrap());
+        self.signer
+            .sign(request, &credential)
+            .map_err(|e| opendal::Error::new(ErrorKind::Unexpected, "failed to sign the upload to S3").set_source(e))?;
+        // as for the operator, the host is set by the client depending on the HTTP version
+        request.headers_mut().remove(HOST);
+        Ok(())
+    }
+}
+
+impl HttpFetch for S3Fetcher {
+    async fn fetch(&self, mut request: Request<Buffer>) -> opendal::Result<Response<HttpBody>> {
+        // uploads of objects and parts, but not the copies
+        let is_upload = request.method() == Method::PUT && !request.headers().contains_key(X_AMZ_COPY_SOURCE);
+        if !is_upload || !(self.checksum_uploads || self.verify_etag) {
+            return self.client.fetch(request).await;
+        }
+        let content = request.body().to_bytes();
+        let path = request.uri().path().to_string();
+        // the parts of multipart uploads are checked against the algorithm given when the upload is created
+        let is_part = request.uri().query().is_some_and(|query| query.contains("uploadId="));
+        let checksum = (self.checksum_uploads && !is_part).then(|| STANDARD.encode(sha256_bytes(&content)));
+        if let Some(checksum) = &checksum {
+            self.add_checksum(&mut request, checksum).await?;
+        }
+        let response = self.client.fetch(request).await?;
+        if self.verify_etag && response.status().is_success() {
+            verify_upload_response(&path, &content, checksum.as_deref(), response.headers())
+                .map_err(|details| opendal::Error::new(ErrorKind::Unexpected, details))?;
+        }
+        Ok(response)
+    }
+}
+
+/// Verifies the response to an upload against its content
+///
+/// A plain `ETag` is the MD5 digest of the content.
+/// This is not the case for the multipart uploads (suffixed by the number of parts) and the objects encrypted
+/// with SSE-KMS or SSE-C, for which the checksum returned by S3 is compared instead.
+///
+/// # Errors
+///
+/// Returns the description of the mismatch for a corrupted upload
+pub fn verify_upload_response(path: &str, content: &[u8], checksum: Option<&str>, headers: &HeaderMap) -> Result<(), String> {
+    let corrupted = |details: String| format!("corrupted upload of {path}: {details}");
+    let etag = headers
+        .get(ETAG)
+        .and_then(|value| value.to_str().ok())
+        .map(|etag| etag.trim_matches('"'));
+    if let Some(etag) = etag.filter(|etag| etag.len() == 32 && etag.bytes().all(|b| b.is_ascii_hexdigit())) {
+        let digest = md5(content);
+        if !etag.eq_ignore_ascii_case(&digest) {
+            return Err(corrupted(format!("expected ETag {digest}, got {etag}")));
+        }
+        return Ok(());
+    }
+    let returned = headers.get(X_AMZ_CHECKSUM_SHA256).and_then(|value| value.to_str().ok());
+    match (checksum, returned) {
+        (Some(expected), Some(returned)) if expected != returned => {
+            Err(corrupted(format!("expected checksum {expected}, got {returned}")))
+        }
+        (Some(_), Some(_)) => Ok(()),
+        _ => {
+            warn!(
+                "upload of {path} could not be verified: ETag {} is not an MD5 digest and no checksum was returned",
+                etag.unwrap_or_default()
+            );
+            Ok(())
+        }
+    }
+}
+
 /// Backing storage
 pub struct StorageImpl {
     opendal_operator: Operator,
@@ -62,20 +298,34 @@ impl From<&Configuration> for StorageImpl {
                 opendal::Operator::new(builder)
                     .unwrap()
                     .layer(LoggingLayer::default())
+                    .layer(TracingLayer)
                     .finish()
             }
             StorageConfig::S3 { params, bucket } => {
-                let builder = opendal::services::S3::default()
+                let loader = build_s3_credential_loader(params);
+                let fetcher = S3Fetcher {
+                    client: build_s3_client(params, config.storage_timeout),
+                    signer: AwsV4Signer::new("s3", &params.region),
+                    loader: loader.clone(),
+                    checksum_uploads: params.checksum_uploads,
+                    verify_etag: params.verify_etag,
+                };
+                let mut builder = opendal::services::S3::default()
                     .bucket(bucket)
                     .root(&params.root)
                     .region(&params.region)
                     .endpoint(&params.endpoint)
-                    .access_key_id(&params.access_key)
-                    .secret_access_key(&params.secret_key);
+                    .customized_credential_load(Box::new(SharedCredentialLoader(loader)))
+                    .http_client(HttpClient::with(fetcher));
+                if params.addressing_style == S3AddressingStyle::VirtualHosted {
+                    // the requests are then signed for the host name of the bucket
+                    builder = builder.enable_virtual_host_style();
+                }
 
                 opendal::Operator::new(builder)
                     .unwrap()
                     .layer(LoggingLayer::default())
+                    .layer(TracingLayer)
                     .finish()
             }
         };
@@ -93,6 +343,15 @@ impl Storage for StorageImpl {
         Box::pin(async move { self.download_crate(name, version).await })
     }
 
+    fn download_crate_range<'a>(
+        &'a self,
+        name: &'a str,
+        version: &'a str,
+        range: ByteRange,
+    ) -> FaillibleFuture<'a, CrateContent> {
+        Box::pin(async move { self.download_crate_range(name, version, range).await })
+    }
+
     fn download_crate_metadata<'a>(&'a self, name: &'a str, version: &'a str) -> FaillibleFuture<'a, Option<CrateMetadata>> {
         Box::pin(async move { self.download_crate_metadata(name, version).await })
     }
@@ -101,6 +360,20 @@ impl Storage for StorageImpl {
         Box::pin(async move { self.download_crate_readme(name, version).await })
     }
 
+    fn store_crate_binary<'a>(
+        &'a self,
+        name: &'a str,
+        version: &'a str,
+        target: &'a str,
+        content: Vec<u8>,
+    ) -> FaillibleFuture<'a, ()> {
+        Box::pin(async move { self.store_crate_binary(name, version, target, content).await })
+    }
+
+    fn download_crate_binary<'a>(&'a self, name: &'a str, version: &'a str, target: &'a str) -> FaillibleFuture<'a, Vec<u8>> {
+        Box::pin(async move { self.download_crate_binary(name, version, target).await })
+    }
+
     fn store_doc_file<'a>(&'a self, path: &'a str, file: &'a Path) -> FaillibleFuture<'a, ()> {
         Box::pin(async move { self.store_doc_file(path, file).await })
     }
@@ -112,9 +385,43 @@ impl Storage for StorageImpl {
     fn download_doc_file<'a>(&'a self, path: &'a str) -> FaillibleFuture<'a, Vec<u8>> {
         Box::pin(async move { self.download_doc_file(path).await })
     }
+
+    fn store_backup<'a>(&'a self, name: &'a str, content: Vec<u8>) -> FaillibleFuture<'a, ()> {
+        Box::pin(async move { self.write_to_file(&Self::backup_path(name), content).await })
+    }
+
+    fn download_backup<'a>(&'a self, name: &'a str) -> FaillibleFuture<'a, Vec<u8>> {
+        Box::pin(async move { self.read_from_file(&Self::backup_path(name)).await.map_err(ApiError::from) })
+    }
+
+    fn list_backups(&self) -> FaillibleFuture<'_, Vec<String>> {
+        Box::pin(async move { self.list_backups().await })
+    }
+
+    fn delete_backup<'a>(&'a self, name: &'a str) -> FaillibleFuture<'a, ()> {
+        Box::pin(async move { self.delete_backup(name).await })
+    }
+
+    fn check_health(&self) -> FaillibleFuture<'_, ()> {
+        Box::pin(async move { self.check_health().await })
+    }
 }
 
 impl StorageImpl {
+    /// Checks that the storage can be accessed by listing its root
+    pub async fn check_access(&self) -> Result<(), ApiError> {
+        self.opendal_operator.check().await?;
+        Ok(())
+    }
+
+    /// Checks that the storage is reachable and writable, by writing and deleting a probe file
+    async fn check_health(&self) -> Result<(), ApiError> {
+        self.check_access().await?;
+        self.write_to_file(HEALTH_PROBE_PATH, b"ok".to_vec()).await?;
+        self.opendal_operator.delete(HEALTH_PROBE_PATH).await?;
+        Ok(())
+    }
+
     /// Stores the data for a crate
     async fn store_crate(&self, metadata: &CrateMetadata, content: Vec<u8>) -> Result<(), ApiError> {
         let readme = extract_readme(&content)?;
@@ -148,6 +455,34 @@ impl StorageImpl {
         }
     }
 
+    /// Downloads a range of bytes in the content of a crate, only reading the requested bytes from the storage
+    async fn download_crate_range(&self, name: &str, version: &str, range: ByteRange) -> Result<CrateContent, ApiError> {
+        let mut path = Self::data_path(name, version);
+        let metadata = match self.opendal_operator.stat(&path).await {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                if matches!(e.kind(), ErrorKind::NotFound | ErrorKind::Unexpected) {
+                    // legacy alternative path when not found
+                    path = format!("crates/{name}/{version}");
+                    self.opendal_operator.stat(&path).await?
+                } else {
+                    return Err(e.into());
+                }
+            }
+        };
+        let total = metadata.content_length();
+        let Some(range) = range.resolve(total) else {
+            return Ok(CrateContent::unsatisfiable(total));
+        };
+        let content = self.opendal_operator.read_with(&path).range(range.clone()).await?.to_vec();
+        Ok(CrateContent {
+            content,
+            part: ContentPart::Range(range),
+            total,
+            restricted: false,
+        })
+    }
+
     /// Downloads the last metadata for a crate
     async fn download_crate_metadata(&self, name: &str, version: &str) -> Result<Option<CrateMetadata>, ApiError> {
         if let Ok(data) = self.read_from_file(&Self::metadata_path(name, version)).await {
@@ -164,6 +499,18 @@ impl StorageImpl {
             .map_err(ApiError::from)
     }
 
+    /// Stores a prebuilt binary artifact for a crate version and a target
+    async fn store_crate_binary(&self, name: &str, version: &str, target: &str, content: Vec<u8>) -> Result<(), ApiError> {
+        self.write_to_file(&Self::binary_path(name, version, target), content).await
+    }
+
+    /// Downloads a prebuilt binary artifact for a crate version and a target
+    async fn download_crate_binary(&self, name: &str, version: &str, target: &str) -> Result<Vec<u8>, ApiError> {
+        self.read_from_file(&Self::binary_path(name, version, target))
+            .await
+            .map_err(ApiError::from)
+    }
+
     /// Stores a documentation file
     async fn store_doc_file(&self, path: &str, file: &Path) -> Result<(), ApiError> {
         let content = tokio::fs::read(file).await?;
@@ -182,6 +529,26 @@ impl StorageImpl {
         self.read_from_file(&format!("docs/{path}")).await.map_err(ApiError::from)
     }
 
+    /// Lists the names of the stored backups of the database
+    async fn list_backups(&self) -> Result<Vec<String>, ApiError> {
+        let entries = match self.opendal_operator.list(&format!("{BACKUPS_FOLDER}/")).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(entries
+            .into_iter()
+            .filter(|entry| entry.metadata().is_file())
+            .map(|entry| entry.name().to_string())
+            .collect())
+    }
+
+    /// Deletes a backup of the database
+    async fn delete_backup(&self, name: &str) -> Result<(), ApiError> {
+        self.opendal_operator.delete(&Self::backup_path(name)).await?;
+        Ok(())
+    }
+
     /// Write to a file
     async fn write_to_file(&self, path: &str, content: Vec<u8>) -> Result<(), ApiError> {
         self.opendal_operator.write(path, content).await?;
@@ -198,6 +565,10 @@ impl StorageImpl {
         format!("crates/{name}/{version}/{filename}")
     }
 
+    fn backup_path(name: &str) -> String {
+        format!("{BACKUPS_FOLDER}/{name}")
+    }
+
     fn data_path(name: &str, version: &str) -> String {
         Self::crate_file_key(name, version, "data")
     }
@@ -209,6 +580,10 @@ impl StorageImpl {
     fn readme_path(name: &str, version: &str) -> String {
         Self::crate_file_key(name, version, "readme")
     }
+
+    fn binary_path(name: &str, version: &str, target: &str) -> String {
+        Self::crate_file_key(name, version, &format!("binaries/{target}"))
+    }
 }
 
 /// Extract the content of the README from the
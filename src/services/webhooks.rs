@@ -0,0 +1,275 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Service to deliver the payloads of outgoing webhooks
+
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use data_encoding::HEXLOWER;
+use log::error;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::redirect::Policy;
+use ring::hmac;
+
+use crate::model::webhooks::{is_webhook_address_forbidden, WebhookDeliveryJob, WebhookPayload, WEBHOOK_MAX_ATTEMPTS};
+use crate::services::database::{db_transaction_read, db_transaction_write};
+use crate::utils::apierror::{error_invalid_request, specialize, ApiError};
+use crate::utils::db::RwSqlitePool;
+use crate::utils::FaillibleFuture;
+
+/// The header for the signature of the payload
+pub const HEADER_SIGNATURE: &str = "X-Cratery-Signature";
+/// The header for the signature of the payload, explicitly naming the algorithm
+pub const HEADER_SIGNATURE_256: &str = "X-Cratery-Signature-256";
+/// The header for the name of the event
+pub const HEADER_EVENT: &str = "X-Cratery-Event";
+/// The header for the identifier of the delivery
+pub const HEADER_DELIVERY: &str = "X-Cratery-Delivery";
+
+/// The timeout for a single delivery attempt
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+/// The unit of the exponential backoff between the attempts of a delivery
+const DELIVERY_BACKOFF_UNIT: Duration = Duration::from_secs(1);
+
+/// The service to deliver the payloads of webhooks
+pub trait WebhookSender {
+    /// Posts a signed payload to a webhook and returns the HTTP status code of the response
+    fn deliver<'a>(
+        &'a self,
+        url: &'a str,
+        secret: &'a str,
+        event: &'a str,
+        delivery_id: i64,
+        body: &'a str,
+    ) -> FaillibleFuture<'a, u16>;
+}
+
+/// Gets the webhook sender service
+///
+/// # Panics
+///
+/// Panics when the HTTP client cannot be built
+#[must_use]
+pub fn get_service() -> Arc<dyn WebhookSender + Send + Sync> {
+    get_service_from(reqwest::Client::builder())
+}
+
+/// Gets the webhook sender service from a builder for its HTTP client
+///
+/// The payloads are always sent directly, ignoring the proxies of the builder, including the outbound proxy:
+/// a proxy would resolve the targets itself, bypassing the check that they are public addresses.
+///
+/// # Panics
+///
+/// Panics when the HTTP client cannot be built
+#[must_use]
+pub fn get_service_from(builder: reqwest::ClientBuilder) -> Arc<dyn WebhookSender + Send + Sync> {
+    Arc::new(WebhookSenderImpl {
+        client: builder
+            .no_proxy()
+            .redirect(Policy::none())
+            .dns_resolver(Arc::new(PublicResolver))
+            .build()
+            .expect("failed to build the HTTP client for webhooks"),
+    })
+}
+
+/// A resolver of host names that only yields public addresses
+///
+/// Resolving the names when connecting, instead of checking them beforehand, also protects against DNS rebinding.
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addresses = tokio::net::lookup_host((name.as_str(), 0))
+                .await?
+                .filter(|address| !is_webhook_address_forbidden(address.ip()))
+                .collect::<Vec<_>>();
+            if addresses.is_empty() {
+                return Err(format!("{} does not resolve to a public address", name.as_str()).into());
+            }
+            Ok(Box::new(addresses.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Checks that the host of a webhook is not given as a forbidden address
+/// The names are checked by the resolver of the client when connecting.
+fn check_webhook_url(url: &str) -> Result<(), ApiError> {
+    let url = reqwest::Url::parse(url).map_err(|e| specialize(error_invalid_request(), e.to_string()))?;
+    let is_forbidden = url.host_str().is_none_or(|host| {
+        host.trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<IpAddr>()
+            .is_ok_and(is_webhook_address_forbidden)
+    });
+    if is_forbidden {
+        return Err(specialize(
+            error_invalid_request(),
+            format!("{url} does not target a public address"),
+        ));
+    }
+    Ok(())
+}
+
+/// Computes the signature of a payload, as sent in the `X-Cratery-Signature` and `X-Cratery-Signature-256` headers
+#[must_use]
+pub fn sign_payload(secret: &str, body: &str) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    let tag = hmac::sign(&key, body.as_bytes());
+    format!("sha256={}", HEXLOWER.encode(tag.as_ref()))
+}
+
+/// The service to deliver the payloads of webhooks
+#[derive(Debug, Clone)]
+struct WebhookSenderImpl {
+    /// The HTTP client
+    client: reqwest::Client,
+}
+
+impl WebhookSender for WebhookSenderImpl {
+    fn deliver<'a>(
+        &'a self,
+        url: &'a str,
+        secret: &'a str,
+        event: &'a str,
+        delivery_id: i64,
+        body: &'a str,
+    ) -> FaillibleFuture<'a, u16> {
+        Box::pin(async move {
+            check_webhook_url(url)?;
+            let signature = sign_payload(secret, body);
+            let response = self
+                .client
+                .post(url)
+                .timeout(DELIVERY_TIMEOUT)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header(HEADER_SIGNATURE, &signature)
+                .header(HEADER_SIGNATURE_256, &signature)
+                .header(HEADER_EVENT, event)
+                .header(HEADER_DELIVERY, delivery_id.to_string())
+                .body(body.to_string())
+                .send()
+                .await?;
+            Ok(response.status().as_u16())
+        })
+    }
+}
+
+/// Dispatches an event to the matching webhooks in the background
+///
+/// Each delivery is attempted up to `WEBHOOK_MAX_ATTEMPTS` times with an exponential backoff.
+/// The outcome of each attempt is recorded in the delivery log.
+pub fn dispatch(pool: RwSqlitePool, sender: Arc<dyn WebhookSender + Send + Sync>, payload: WebhookPayload) {
+    let _handle = tokio::spawn(async move {
+        if let Err(e) = dispatch_inner(&pool, sender, &payload).await {
+            error!("{e}");
+            if let Some(backtrace) = &e.backtrace {
+                error!("{backtrace}");
+            }
+        }
+    });
+}
+
+/// Records the deliveries for an event and spawns their delivery
+async fn dispatch_inner(
+    pool: &RwSqlitePool,
+    sender: Arc<dyn WebhookSender + Send + Sync>,
+    payload: &WebhookPayload,
+) -> Result<(), ApiError> {
+    let body = serde_json::to_string(payload)?;
+    let jobs = db_transaction_write(pool, "webhooks_dispatch", |database| {
+        let body = &body;
+        async move {
+            let webhooks = database.get_webhooks_for_event(&payload.package, payload.event).await?;
+            let mut jobs = Vec::with_capacity(webhooks.len());
+            for webhook in webhooks {
+                let delivery_id = database
+                    .create_webhook_delivery(webhook.webhook.id, payload.event.as_str(), body)
+                    .await?;
+                jobs.push(WebhookDeliveryJob {
+                    delivery_id,
+                    attempts: 0,
+                    event: payload.event.as_str().to_string(),
+                    payload: body.clone(),
+                    url: webhook.webhook.url,
+                    secret: webhook.secret,
+                });
+            }
+            Ok::<_, ApiError>(jobs)
+        }
+    })
+    .await?;
+    for job in jobs {
+        spawn_delivery(pool.clone(), sender.clone(), job);
+    }
+    Ok(())
+}
+
+/// Resumes in the background the deliveries that were interrupted, for example by a restart of the registry
+pub fn resume_pending(pool: RwSqlitePool, sender: Arc<dyn WebhookSender + Send + Sync>) {
+    let _handle = tokio::spawn(async move {
+        let jobs = db_transaction_read(&pool, |database| async move {
+            database.get_pending_webhook_deliveries(WEBHOOK_MAX_ATTEMPTS).await
+        })
+        .await;
+        match jobs {
+            Ok(jobs) => {
+                for job in jobs {
+                    spawn_delivery(pool.clone(), sender.clone(), job);
+                }
+            }
+            Err(e) => error!("{e}"),
+        }
+    });
+}
+
+/// Attempts a delivery in the background, until it succeeds or the maximum number of attempts is reached
+pub fn spawn_delivery(pool: RwSqlitePool, sender: Arc<dyn WebhookSender + Send + Sync>, job: WebhookDeliveryJob) {
+    let _handle = tokio::spawn(async move {
+        deliver_with_retries(&pool, sender, job, DELIVERY_BACKOFF_UNIT).await;
+    });
+}
+
+/// Attempts a delivery until it succeeds or the maximum number of attempts is reached
+///
+/// After the n-th failed attempt, waits for `2^n` times the `backoff_unit` before the next one.
+pub async fn deliver_with_retries(
+    pool: &RwSqlitePool,
+    sender: Arc<dyn WebhookSender + Send + Sync>,
+    job: WebhookDeliveryJob,
+    backoff_unit: Duration,
+) {
+    for attempt in (job.attempts + 1)..=WEBHOOK_MAX_ATTEMPTS {
+        let (status_code, error) = match sender
+            .deliver(&job.url, &job.secret, &job.event, job.delivery_id, &job.payload)
+            .await
+        {
+            Ok(code) => (Some(i64::from(code)), None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        let is_delivered = status_code.is_some_and(|code| (200..300).contains(&code));
+        if let Err(e) = db_transaction_write(pool, "webhooks_delivery", |database| {
+            let error = error.as_deref();
+            async move {
+                database
+                    .update_webhook_delivery(job.delivery_id, attempt, status_code, error)
+                    .await
+            }
+        })
+        .await
+        {
+            error!("{e}");
+        }
+        if is_delivered {
+            break;
+        }
+        if attempt < WEBHOOK_MAX_ATTEMPTS {
+            tokio::time::sleep(backoff_unit * (1_u32 << attempt)).await;
+        }
+    }
+}
@@ -0,0 +1,41 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Tests about the administrative commands
+
+use super::{async_test, setup_create_user_inactive};
+use crate::utils::apierror::ApiError;
+
+#[test]
+fn test_create_or_promote_admin() -> Result<(), ApiError> {
+    async_test(|application, _admin_auth| async move {
+        let created = application
+            .db_transaction_write("test", |app| async move {
+                app.database.create_or_promote_admin("ops@acme.com", Some("Ops")).await
+            })
+            .await?;
+        assert_eq!(created.login, "ops");
+        assert_eq!(created.name, "Ops");
+        assert_eq!(created.roles, "admin");
+
+        // an inactive user with another role is promoted and reactivated
+        setup_create_user_inactive(&application, "dev@acme.com", "readonly").await?;
+        let promoted = application
+            .db_transaction_write("test", |app| async move {
+                app.database.create_or_promote_admin("dev@acme.com", None).await
+            })
+            .await?;
+        assert!(promoted.is_active);
+        assert_eq!(promoted.roles, "readonly,admin");
+
+        // promoting twice does not duplicate the role
+        let again = application
+            .db_transaction_write("test", |app| async move {
+                app.database.create_or_promote_admin("dev@acme.com", None).await
+            })
+            .await?;
+        assert_eq!(again.roles, "readonly,admin");
+        Ok(())
+    })
+}
@@ -0,0 +1,135 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Tests about the advisories affecting the hosted crates
+
+use semver::Version;
+
+use super::{async_test, build_payload};
+use crate::application::Application;
+use crate::model::deps::CrateVersionAdvisory;
+use crate::model::osv::SimpleAdvisory;
+use crate::model::CrateVersion;
+use crate::utils::apierror::ApiError;
+
+/// Builds an advisory against a crate
+fn advisory(id: &str, package: &str) -> SimpleAdvisory {
+    SimpleAdvisory {
+        package: package.to_string(),
+        id: id.to_string(),
+        published: String::from("2024-01-01T00:00:00Z"),
+        modified: String::from("2024-01-01T00:00:00Z"),
+        summary: format!("summary of {id}"),
+        ranges: Vec::new(),
+        versions: Vec::new(),
+    }
+}
+
+/// Records the advisories found for the version 0.1.0 of the `hosted` crate and returns the newly found ones
+async fn flag(
+    application: &Application,
+    is_direct: bool,
+    advisories: &[(&str, &str)],
+) -> Result<Vec<CrateVersionAdvisory>, ApiError> {
+    let crate_version = CrateVersion {
+        package: String::from("hosted"),
+        version: String::from("0.1.0"),
+    };
+    let advisories = advisories
+        .iter()
+        .map(|(id, affected)| {
+            CrateVersionAdvisory::new(
+                &crate_version,
+                affected,
+                &Version::new(1, 0, 0),
+                &advisory(id, affected),
+                is_direct,
+            )
+        })
+        .collect::<Vec<_>>();
+    application
+        .db_transaction_write("flag", |app| async move {
+            app.database
+                .set_crate_version_advisories("hosted", "0.1.0", is_direct, advisories)
+                .await
+        })
+        .await
+}
+
+/// Gets the advisories recorded against the `hosted` crate
+async fn get_flagged(application: &Application) -> Result<Vec<CrateVersionAdvisory>, ApiError> {
+    application
+        .db_transaction_read(|app| async move { app.database.get_crate_advisories("hosted").await })
+        .await
+}
+
+#[test]
+fn test_advisories_flagging() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        application
+            .publish_crate_version(&admin_auth, &build_payload("hosted", "0.1.0"))
+            .await?;
+
+        let found = flag(&application, false, &[("RUSTSEC-1", "dep"), ("RUSTSEC-2", "dep")]).await?;
+        assert_eq!(found.len(), 2);
+        let flagged = get_flagged(&application).await?;
+        assert_eq!(flagged.len(), 2);
+        assert!(flagged.iter().all(|advisory| !advisory.is_direct));
+
+        // known advisories are not reported again and keep their detection time
+        let found = flag(&application, false, &[("RUSTSEC-1", "dep"), ("RUSTSEC-2", "dep")]).await?;
+        assert!(found.is_empty());
+        assert_eq!(
+            get_flagged(&application)
+                .await?
+                .iter()
+                .map(|a| a.detected_on)
+                .collect::<Vec<_>>(),
+            flagged.iter().map(|a| a.detected_on).collect::<Vec<_>>()
+        );
+
+        // the advisories against the crate itself are kept apart
+        let found = flag(&application, true, &[("RUSTSEC-3", "hosted")]).await?;
+        assert_eq!(found.len(), 1);
+        assert_eq!(get_flagged(&application).await?.len(), 3);
+
+        // the advisories that are no longer found are removed
+        let found = flag(&application, false, &[("RUSTSEC-2", "dep")]).await?;
+        assert!(found.is_empty());
+        let flagged = get_flagged(&application).await?;
+        assert_eq!(
+            flagged.iter().map(|a| a.advisory.as_str()).collect::<Vec<_>>(),
+            vec!["RUSTSEC-2", "RUSTSEC-3"]
+        );
+        Ok(())
+    })
+}
+
+#[test]
+fn test_advisories_reset_deps_analysis() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        application
+            .publish_crate_version(&admin_auth, &build_payload("hosted", "0.1.0"))
+            .await?;
+        application
+            .db_transaction_write("analyzed", |app| async move {
+                app.database.set_crate_deps_analysis("hosted", "0.1.0", false, false).await
+            })
+            .await?;
+        let unanalyzed = application
+            .db_transaction_read(|app| async move { app.database.get_unanalyzed_crates(60).await })
+            .await?;
+        assert!(unanalyzed.is_empty());
+
+        application
+            .db_transaction_write("reset", |app| async move { app.database.reset_deps_analysis().await })
+            .await?;
+        let unanalyzed = application
+            .db_transaction_read(|app| async move { app.database.get_unanalyzed_crates(60).await })
+            .await?;
+        assert_eq!(unanalyzed.len(), 1);
+        assert_eq!(unanalyzed[0].package, "hosted");
+        Ok(())
+    })
+}
@@ -0,0 +1,54 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Tests about the announcement published by the administrators
+
+use chrono::{Local, TimeDelta};
+
+use super::async_test;
+use crate::model::announcements::{Announcement, AnnouncementLevel};
+use crate::utils::apierror::ApiError;
+
+#[test]
+fn test_announcement_window() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        assert_eq!(application.get_current_announcement(&admin_auth).await?, None);
+
+        let now = Local::now().naive_local();
+        let mut announcement = Announcement {
+            message: String::from("Maintenance on Saturday"),
+            level: AnnouncementLevel::Warning,
+            starts_on: Some(now + TimeDelta::hours(1)),
+            ends_on: Some(now + TimeDelta::hours(2)),
+        };
+        application.set_announcement(&admin_auth, &announcement).await?;
+        // scheduled, but not yet displayed
+        assert_eq!(application.get_current_announcement(&admin_auth).await?, None);
+        assert_eq!(application.get_announcement(&admin_auth).await?, Some(announcement.clone()));
+
+        announcement.starts_on = Some(now - TimeDelta::hours(1));
+        application.set_announcement(&admin_auth, &announcement).await?;
+        assert_eq!(application.get_current_announcement(&admin_auth).await?, Some(announcement));
+
+        application.clear_announcement(&admin_auth).await?;
+        assert_eq!(application.get_announcement(&admin_auth).await?, None);
+        Ok(())
+    })
+}
+
+#[test]
+fn test_announcement_invalid() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        let now = Local::now().naive_local();
+        let announcement = Announcement {
+            message: String::from("Policy change"),
+            level: AnnouncementLevel::Info,
+            starts_on: Some(now),
+            ends_on: Some(now - TimeDelta::hours(1)),
+        };
+        let error = application.set_announcement(&admin_auth, &announcement).await.unwrap_err();
+        assert_eq!(error.http, 400);
+        Ok(())
+    })
+}
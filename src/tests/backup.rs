@@ -0,0 +1,241 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Tests about the scheduled backups of the database
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{TimeZone, Utc};
+
+use super::{async_test, build_payload};
+use crate::application::Application;
+use crate::model::backup::{
+    apply_incremental_backup, backup_chain, backup_name, backups_to_prune, latest_backup, open_backup, seal_backup,
+    BackupChain, BackupKind, BackupStatus, BACKUP_BLOCK_SIZE,
+};
+use crate::model::config::Configuration;
+use crate::restore::restore_backup;
+use crate::services::database::db_transaction_read;
+use crate::utils::apierror::ApiError;
+use crate::utils::axum::auth::AuthData;
+use crate::utils::db::RwSqlitePool;
+
+/// The kind of a full backup, not encrypted
+const FULL: BackupKind = BackupKind {
+    incremental: false,
+    encrypted: false,
+};
+/// The kind of an incremental backup, not encrypted
+const INCREMENTAL: BackupKind = BackupKind {
+    incremental: true,
+    encrypted: false,
+};
+
+#[test]
+fn test_backups_to_prune() {
+    let names = [
+        backup_name(Utc.with_ymd_and_hms(2024, 3, 2, 0, 0, 0).unwrap(), FULL),
+        backup_name(Utc.with_ymd_and_hms(2024, 3, 1, 12, 30, 0).unwrap(), FULL),
+        String::from("notes.txt"),
+        backup_name(Utc.with_ymd_and_hms(2024, 3, 3, 0, 0, 0).unwrap(), FULL),
+    ];
+    assert_eq!(names[0], "registry-20240302T000000Z.db.gz");
+    assert_eq!(
+        backups_to_prune(&names, 2),
+        vec![String::from("registry-20240301T123000Z.db.gz")]
+    );
+    assert!(backups_to_prune(&names, 3).is_empty());
+    assert_eq!(backups_to_prune(&names, 0).len(), 2);
+    assert_eq!(latest_backup(&names).as_deref(), Some("registry-20240303T000000Z.db.gz"));
+    assert_eq!(latest_backup(&[String::from("notes.txt")]), None);
+}
+
+#[test]
+fn test_backup_chains() {
+    let names = [
+        String::from("registry-20240301T000000Z.db.gz"),
+        backup_name(Utc.with_ymd_and_hms(2024, 3, 2, 0, 0, 0).unwrap(), INCREMENTAL),
+        String::from("registry-20240303T000000Z.inc.gz"),
+        String::from("registry-20240304T000000Z.db.gz.enc"),
+        String::from("registry-20240305T000000Z.inc.gz.enc"),
+    ];
+    assert_eq!(
+        backups_to_prune(&names, 2),
+        vec![names[0].clone(), names[1].clone(), names[2].clone()]
+    );
+    // the full backup of the oldest kept incremental backup is kept
+    assert!(backups_to_prune(&names, 3).is_empty());
+    assert_eq!(backups_to_prune(&names, 1).len(), 3);
+    assert_eq!(latest_backup(&names).as_deref(), Some("registry-20240305T000000Z.inc.gz.enc"));
+    assert_eq!(backup_chain(&names, &names[2]), Some(names[..3].to_vec()));
+    assert_eq!(backup_chain(&names, &names[3]), Some(vec![names[3].clone()]));
+    assert_eq!(backup_chain(&names[1..], &names[2]), None);
+    assert_eq!(backup_chain(&names, "registry-20240306T000000Z.db.gz"), None);
+}
+
+#[test]
+fn test_incremental_backup() -> Result<(), ApiError> {
+    let key = [7_u8; 32];
+    let base = (0..3 * BACKUP_BLOCK_SIZE)
+        .map(|i| u8::try_from(i % 251).unwrap())
+        .collect::<Vec<_>>();
+    let full = seal_backup(base.as_slice(), base.len() as u64, None, Some(&key))?;
+    assert_eq!(full.digests.len(), 3);
+    assert!(seal_backup(base.as_slice(), base.len() as u64 + 1, None, None).is_err());
+    let chain = BackupChain {
+        last_name: backup_name(Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap(), FULL),
+        length: 1,
+        digests: full.digests,
+    };
+    // change the second block and shrink the database
+    let mut changed = base.clone();
+    changed[BACKUP_BLOCK_SIZE + 10] ^= 0xFF;
+    changed.truncate(2 * BACKUP_BLOCK_SIZE + 100);
+    let incremental = seal_backup(changed.as_slice(), changed.len() as u64, Some(&chain), None)?;
+    assert!(incremental.content.len() < 2 * BACKUP_BLOCK_SIZE);
+
+    let mut restored = open_backup("registry-20240301T000000Z.db.gz.enc", &full.content, Some(&key))?;
+    assert_eq!(restored, base);
+    assert!(open_backup("registry-20240301T000000Z.db.gz.enc", &full.content, Some(&[8_u8; 32])).is_err());
+    assert!(open_backup("registry-20240301T000000Z.db.gz.enc", &full.content, None).is_err());
+    let content = open_backup("registry-20240302T000000Z.inc.gz", &incremental.content, None)?;
+    assert!(apply_incremental_backup(&mut restored.clone(), "registry-20240229T000000Z.db.gz", &content).is_err());
+    apply_incremental_backup(&mut restored, &chain.last_name, &content)?;
+    assert_eq!(restored, changed);
+
+    // grow the database, the new blocks are appended
+    let mut grown = changed.clone();
+    grown.resize(grown.len() + 2 * BACKUP_BLOCK_SIZE, 3);
+    let chain = BackupChain {
+        last_name: backup_name(Utc.with_ymd_and_hms(2024, 3, 2, 0, 0, 0).unwrap(), INCREMENTAL),
+        length: 2,
+        digests: incremental.digests,
+    };
+    let grown_backup = seal_backup(grown.as_slice(), grown.len() as u64, Some(&chain), None)?;
+    let content = open_backup("registry-20240303T000000Z.inc.gz", &grown_backup.content, None)?;
+    // the blocks cannot be applied onto a database that is too small for them
+    assert!(apply_incremental_backup(&mut base[..BACKUP_BLOCK_SIZE].to_vec(), &chain.last_name, &content).is_err());
+    apply_incremental_backup(&mut restored, &chain.last_name, &content)?;
+    assert_eq!(restored, grown);
+    Ok(())
+}
+
+/// Launches a backup and waits for it to finish
+async fn run_backup(application: &Arc<Application>, admin_auth: &AuthData) -> Result<BackupStatus, ApiError> {
+    let status = application.launch_backup(admin_auth).await?;
+    assert!(status.is_running);
+    loop {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let status = application.get_backup_status(admin_auth).await?;
+        if !status.is_running {
+            return Ok(status);
+        }
+    }
+}
+
+#[test]
+fn test_backup_run_incremental() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        let mut configuration = (**application.configuration.load()).clone();
+        configuration.backup.full_every = 2;
+        configuration.backup.encryption_key = Some(String::from("AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA="));
+        application.configuration.store(Arc::new(configuration));
+
+        let status = run_backup(&application, &admin_auth).await?;
+        assert_eq!(status.last_error, None);
+        assert!(status.last_name.is_some_and(|name| name.ends_with(".db.gz.enc")));
+        let status = run_backup(&application, &admin_auth).await?;
+        assert_eq!(status.last_error, None);
+        assert!(status.last_name.is_some_and(|name| name.ends_with(".inc.gz.enc")));
+        Ok(())
+    })
+}
+
+#[test]
+fn test_backup_run() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        let status = run_backup(&application, &admin_auth).await?;
+        assert_eq!(status.last_error, None);
+        assert!(status.last_success.is_some());
+        assert!(status.last_name.is_some_and(|name| name.ends_with(".db.gz")));
+        assert!(status.last_duration.is_some());
+        assert!(status.last_database_size.is_some_and(|size| size > 0));
+        assert!(status.last_size.is_some_and(|size| size > 0));
+        Ok(())
+    })
+}
+
+/// Gets the versions of a crate in a restored database
+async fn get_restored_versions(configuration: &Configuration, package: &str) -> Result<Vec<String>, ApiError> {
+    let pool = RwSqlitePool::new(&configuration.get_database_url())?;
+    let versions = db_transaction_read(&pool, |database| async move { database.get_all_crate_versions().await }).await;
+    pool.close().await;
+    Ok(versions?
+        .into_iter()
+        .filter(|version| version.package == package)
+        .map(|version| version.version)
+        .collect())
+}
+
+#[test]
+fn test_backup_restore_round_trip() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        let mut configuration = (**application.configuration.load()).clone();
+        configuration.backup.full_every = 2;
+        application.configuration.store(Arc::new(configuration));
+        application
+            .publish_crate_version(&admin_auth, &build_payload("restored", "0.1.0"))
+            .await?;
+        let full = run_backup(&application, &admin_auth).await?;
+        assert_eq!(full.last_error, None);
+        application
+            .publish_crate_version(&admin_auth, &build_payload("restored", "0.2.0"))
+            .await?;
+        let incremental = run_backup(&application, &admin_auth).await?;
+        assert!(incremental.last_name.as_ref().is_some_and(|name| name.ends_with(".inc.gz")));
+        // not in the backups
+        application
+            .publish_crate_version(&admin_auth, &build_payload("restored", "0.3.0"))
+            .await?;
+
+        let mut configuration = (**application.configuration.load()).clone();
+        configuration.data_dir = format!("{}/restored", configuration.data_dir);
+        configuration.index.location = format!("{}/index", configuration.data_dir);
+        tokio::fs::create_dir_all(&configuration.data_dir).await?;
+        let storage = application.get_service_storage();
+
+        // the most recent backup is restored by default, through its chain
+        let name = restore_backup(&configuration, storage.clone(), None, true).await?;
+        assert_eq!(Some(name), incremental.last_name);
+        assert_eq!(get_restored_versions(&configuration, "restored").await?, ["0.1.0", "0.2.0"]);
+        let index_file = format!("{}/re/st/restored", configuration.index.location);
+        assert_eq!(tokio::fs::read_to_string(&index_file).await?.lines().count(), 2);
+
+        // an older backup can be chosen, the current database is kept aside
+        restore_backup(&configuration, storage.clone(), full.last_name.clone(), false).await?;
+        assert_eq!(get_restored_versions(&configuration, "restored").await?, ["0.1.0"]);
+        let mut kept = 0;
+        let mut entries = tokio::fs::read_dir(&configuration.data_dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            // without the journal of the kept database
+            if name.starts_with("registry.db.") && !name.ends_with("-wal") && !name.ends_with("-shm") {
+                kept += 1;
+            }
+        }
+        assert_eq!(kept, 1);
+
+        let error = restore_backup(
+            &configuration,
+            storage,
+            Some(String::from("registry-20000101T000000Z.db.gz")),
+            false,
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(error.http, 404);
+        Ok(())
+    })
+}
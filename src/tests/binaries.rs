@@ -0,0 +1,108 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Tests about the prebuilt binaries hosted for cargo-binstall
+
+use axum::body::{to_bytes, Body};
+use axum::http::{header, Method, Request, StatusCode};
+use axum::Router;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use cookie::Key;
+use tower::ServiceExt;
+
+use super::{async_test, build_payload_with_files, setup_create_token, setup_create_user};
+use crate::model::binstall::BinstallInfo;
+use crate::registry_router;
+use crate::utils::apierror::ApiError;
+use crate::utils::axum::auth::Token;
+use crate::utils::hashes::sha256;
+
+/// The manifest of the published crate, with the metadata for cargo-binstall
+const MANIFEST: &str = r#"[package]
+name = "tool"
+version = "0.1.0"
+
+[package.metadata.binstall]
+pkg-fmt = "bin"
+"#;
+
+/// Sends a request to the router and returns the status and the body of the response
+async fn send(app: &Router, method: Method, uri: &str, token: &Token, body: Vec<u8>) -> (StatusCode, Vec<u8>) {
+    let credentials = STANDARD.encode(format!("{}:{}", token.id, token.secret));
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(header::AUTHORIZATION, format!("Basic {credentials}"))
+        .body(Body::from(body))
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, body.to_vec())
+}
+
+#[test]
+fn test_binaries_routes() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        application
+            .publish_crate_version(
+                &admin_auth,
+                &build_payload_with_files("tool", "0.1.0", &[("Cargo.toml", MANIFEST)]),
+            )
+            .await?;
+        setup_create_user(&application, "other", "").await?;
+        let other = Token {
+            id: String::from("other"),
+            secret: setup_create_token(&application, 2, true, false).await?,
+        };
+        let admin = admin_auth.token.unwrap();
+        let app = registry_router(application.clone(), Key::generate());
+        let uri = "/api/v1/crates/tool/0.1.0/binaries/x86_64-unknown-linux-gnu";
+
+        // only the owners can upload binaries, for existing versions and valid targets
+        let (status, _) = send(&app, Method::PUT, uri, &other, b"v1".to_vec()).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, _) = send(
+            &app,
+            Method::PUT,
+            "/api/v1/crates/tool/0.2.0/binaries/x86_64",
+            &admin,
+            b"v1".to_vec(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, _) = send(
+            &app,
+            Method::PUT,
+            "/api/v1/crates/tool/0.1.0/binaries/.hidden",
+            &admin,
+            b"v1".to_vec(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _) = send(&app, Method::GET, uri, &admin, Vec::new()).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        // uploading again replaces the binary
+        for content in [b"v1", b"v2"] {
+            let (status, _) = send(&app, Method::PUT, uri, &admin, content.to_vec()).await;
+            assert_eq!(status, StatusCode::OK);
+        }
+        let (status, body) = send(&app, Method::GET, uri, &other, Vec::new()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, b"v2");
+
+        let (status, body) = send(&app, Method::GET, "/api/v1/crates/tool/0.1.0/binstall", &other, Vec::new()).await;
+        assert_eq!(status, StatusCode::OK);
+        let info = serde_json::from_slice::<BinstallInfo>(&body)?;
+        assert_eq!(info.metadata, Some(serde_json::json!({ "pkg-fmt": "bin" })));
+        assert_eq!(info.binaries.len(), 1);
+        assert_eq!(info.binaries[0].target, "x86_64-unknown-linux-gnu");
+        assert_eq!(info.binaries[0].size, 2);
+        assert_eq!(info.binaries[0].checksum, sha256(b"v2"));
+        assert_eq!(info.binaries[0].uploaded_by.login, "admin");
+        Ok(())
+    })
+}
@@ -0,0 +1,60 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Tests about the caching policy announced to the clients and the caches in front of the registry
+
+use std::sync::Arc;
+
+use super::{async_test, build_payload};
+use crate::model::config::CacheHeadersConfig;
+use crate::utils::apierror::ApiError;
+
+#[test]
+fn test_cache_headers_private_by_default() {
+    let config = CacheHeadersConfig::default();
+    assert_eq!(config.immutable(false), "private, max-age=31536000, immutable");
+    assert_eq!(config.revalidated(3600, false), "private, max-age=3600");
+    assert_eq!(config.index(false), "private, no-cache");
+}
+
+#[test]
+fn test_cache_headers_shared() {
+    let config = CacheHeadersConfig {
+        shared: true,
+        index_max_age: 0,
+    };
+    assert_eq!(config.immutable(false), "public, max-age=31536000, immutable");
+    assert_eq!(config.immutable(true), "private, max-age=31536000, immutable");
+    assert_eq!(config.revalidated(3600, false), "public, max-age=3600");
+    assert_eq!(config.revalidated(3600, true), "private, max-age=3600");
+    assert_eq!(config.index(false), "public, no-cache");
+    assert_eq!(config.index(true), "private, no-cache");
+    let config = CacheHeadersConfig {
+        shared: true,
+        index_max_age: 30,
+    };
+    assert_eq!(config.index(false), "public, max-age=0, s-maxage=30");
+    assert_eq!(config.index(true), "private, no-cache");
+}
+
+#[test]
+fn test_quarantined_content_is_restricted() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        application
+            .publish_crate_version(&admin_auth, &build_payload("cached", "0.1.0"))
+            .await?;
+        let content = application.get_crate_content(&admin_auth, "cached", "0.1.0", None).await?;
+        assert!(!content.restricted);
+
+        let mut configuration = (**application.configuration.load()).clone();
+        configuration.quarantine.period = 3600;
+        application.configuration.store(Arc::new(configuration));
+        application
+            .publish_crate_version(&admin_auth, &build_payload("cached", "0.2.0"))
+            .await?;
+        let content = application.get_crate_content(&admin_auth, "cached", "0.2.0", None).await?;
+        assert!(content.restricted);
+        Ok(())
+    })
+}
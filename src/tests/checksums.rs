@@ -0,0 +1,92 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Tests about the checksums of crate versions
+
+use super::{async_test, build_payload, build_payload_with_files};
+use crate::application::Application;
+use crate::model::cargo::{CrateMetadata, CrateUploadData};
+use crate::model::packages::CrateVersionChecksums;
+use crate::utils::apierror::ApiError;
+use crate::utils::hashes::md5;
+
+/// Forgets the checksums and the sizes of all the crate versions, as for those published before they were stored
+async fn clear_checksums(application: &Application) -> Result<(), ApiError> {
+    application
+        .db_transaction_write("clear_checksums", |app| async move {
+            sqlx::query("UPDATE PackageVersion SET checksumSha256 = NULL, checksumSha512 = NULL, size = NULL")
+                .execute(&mut *app.database.transaction.borrow().await)
+                .await?;
+            Ok::<(), ApiError>(())
+        })
+        .await
+}
+
+#[test]
+fn test_crate_version_checksums() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        let payload = build_payload("digested", "0.1.0");
+        let content = CrateUploadData::new(&payload)?.content;
+        application.publish_crate_version(&admin_auth, &payload).await?;
+        let checksums = application
+            .get_crate_version_checksums(&admin_auth, "digested", "0.1.0")
+            .await?;
+        assert_eq!(checksums, CrateVersionChecksums::new(&content));
+        assert_eq!(checksums.sha512.len(), 128);
+
+        // versions published before the checksums were stored get them computed by the job
+        clear_checksums(&application).await?;
+        let error = application
+            .get_crate_version_checksums(&admin_auth, "digested", "0.1.0")
+            .await
+            .unwrap_err();
+        assert_eq!(error.http, 404);
+        assert_eq!(application.get_registry_stats(&admin_auth).await?.storage_unknown_versions, 1);
+        application.checksums_backfill().await?;
+        let checksums = application
+            .get_crate_version_checksums(&admin_auth, "digested", "0.1.0")
+            .await?;
+        assert_eq!(checksums, CrateVersionChecksums::new(&content));
+        // the sizes are computed at the same time
+        let stats = application.get_registry_stats(&admin_auth).await?;
+        assert_eq!(stats.storage_unknown_versions, 0);
+        assert_eq!(stats.storage_used, i64::try_from(content.len()).unwrap());
+        Ok(())
+    })
+}
+
+#[test]
+fn test_crate_version_checksums_verified() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        application
+            .publish_crate_version(&admin_auth, &build_payload("tampered", "0.1.0"))
+            .await?;
+        clear_checksums(&application).await?;
+        // the stored content no longer matches the checksum in the index
+        let other = CrateUploadData::new(&build_payload_with_files("tampered", "0.1.0", &[("src/lib.rs", "")]))?;
+        let metadata = CrateMetadata {
+            name: String::from("tampered"),
+            vers: String::from("0.1.0"),
+            ..Default::default()
+        };
+        application
+            .get_service_storage()
+            .store_crate(&metadata, other.content)
+            .await?;
+        application.checksums_backfill().await?;
+        let error = application
+            .get_crate_version_checksums(&admin_auth, "tampered", "0.1.0")
+            .await
+            .unwrap_err();
+        assert_eq!(error.http, 404);
+        Ok(())
+    })
+}
+
+#[test]
+fn test_md5_for_etag_verification() {
+    // the ETag that S3 returns for the single part uploads of these contents
+    assert_eq!(md5(b""), "d41d8cd98f00b204e9800998ecf8427e");
+    assert_eq!(md5(b"hello world"), "5eb63bbbe01eeed093cb22bb8f5acdc3");
+}
@@ -0,0 +1,53 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Tests about resolving the address of clients behind proxies
+
+use std::net::IpAddr;
+
+use axum::http::{HeaderMap, HeaderValue};
+use ipnet::IpNet;
+
+use crate::utils::axum::extractors::resolve_client_ip;
+
+fn ip(value: &str) -> IpAddr {
+    value.parse().unwrap()
+}
+
+#[test]
+fn test_resolve_client_ip() {
+    let trusted = vec!["10.0.0.0/8".parse::<IpNet>().unwrap()];
+    let mut headers = HeaderMap::new();
+    headers.insert("x-forwarded-for", HeaderValue::from_static("1.2.3.4, 5.6.7.8, 10.0.0.2"));
+
+    // the header is ignored when not coming from a trusted proxy
+    assert_eq!(
+        resolve_client_ip(Some(ip("5.5.5.5")), &headers, &trusted),
+        Some(ip("5.5.5.5"))
+    );
+    assert_eq!(resolve_client_ip(Some(ip("10.0.0.1")), &headers, &[]), Some(ip("10.0.0.1")));
+    // the first untrusted address from the right, the leftmost one may be spoofed by the client
+    assert_eq!(
+        resolve_client_ip(Some(ip("10.0.0.1")), &headers, &trusted),
+        Some(ip("5.6.7.8"))
+    );
+    // through a Unix domain socket
+    assert_eq!(resolve_client_ip(None, &headers, &trusted), Some(ip("5.6.7.8")));
+    // an invalid entry sent by the client does not hide its address
+    let mut junk = HeaderMap::new();
+    junk.insert("x-forwarded-for", HeaderValue::from_static("x, 1.2.3.4"));
+    assert_eq!(resolve_client_ip(Some(ip("10.0.0.1")), &junk, &trusted), Some(ip("1.2.3.4")));
+    junk.insert("x-forwarded-for", HeaderValue::from_static("1.2.3.4, x, 10.0.0.2"));
+    assert_eq!(resolve_client_ip(Some(ip("10.0.0.1")), &junk, &trusted), Some(ip("10.0.0.2")));
+    // the header sent on multiple lines
+    let mut lines = HeaderMap::new();
+    lines.append("x-forwarded-for", HeaderValue::from_static("1.2.3.4"));
+    lines.append("x-forwarded-for", HeaderValue::from_static("10.0.0.2"));
+    assert_eq!(resolve_client_ip(Some(ip("10.0.0.1")), &lines, &trusted), Some(ip("1.2.3.4")));
+    // without the header
+    assert_eq!(
+        resolve_client_ip(Some(ip("10.0.0.1")), &HeaderMap::new(), &trusted),
+        Some(ip("10.0.0.1"))
+    );
+}
@@ -0,0 +1,70 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Tests about the compression of the responses
+
+use axum::body::Body;
+use axum::http::{header, HeaderValue, Request};
+use axum::routing::get;
+use axum::Router;
+use tokio::runtime::Builder;
+use tower::ServiceExt;
+
+use crate::model::config::CompressionConfig;
+use crate::utils::axum::compression::compression_layer;
+
+fn build_app(config: &CompressionConfig) -> Router {
+    let text = "a".repeat(4096);
+    let binary = vec![0_u8; 4096];
+    Router::new()
+        .route("/docs", get(move || async move { text }))
+        .route("/small", get(|| async { "small" }))
+        .route(
+            "/download",
+            get(move || async move {
+                (
+                    [(header::CONTENT_TYPE, HeaderValue::from_static("application/octet-stream"))],
+                    binary,
+                )
+            }),
+        )
+        .layer(compression_layer(config))
+}
+
+async fn content_encoding(app: &Router, uri: &str, accept: &str) -> Option<String> {
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(uri)
+                .header(header::ACCEPT_ENCODING, accept)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    response
+        .headers()
+        .get(header::CONTENT_ENCODING)
+        .map(|value| value.to_str().unwrap().to_string())
+}
+
+#[test]
+fn test_compression() {
+    let runtime = Builder::new_current_thread().enable_all().build().unwrap();
+    runtime.block_on(async move {
+        let app = build_app(&CompressionConfig::default());
+        assert_eq!(content_encoding(&app, "/docs", "gzip").await.as_deref(), Some("gzip"));
+        assert_eq!(content_encoding(&app, "/docs", "br, gzip").await.as_deref(), Some("br"));
+        assert_eq!(content_encoding(&app, "/docs", "identity").await, None);
+        assert_eq!(content_encoding(&app, "/small", "gzip").await, None);
+        assert_eq!(content_encoding(&app, "/download", "gzip").await, None);
+
+        let app = build_app(&CompressionConfig {
+            enabled: false,
+            ..Default::default()
+        });
+        assert_eq!(content_encoding(&app, "/docs", "gzip").await, None);
+    });
+}
@@ -0,0 +1,251 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Tests about loading and reloading the configuration
+
+use std::env::temp_dir;
+
+use axum::Router;
+use tokio::net::TcpListener;
+
+use crate::model::config::{
+    get_var, get_var_opt, is_valid_tenant_name, parse_config_file, with_tenant, Configuration, S3AddressingStyle,
+};
+use crate::model::errors::EnvVarError;
+use crate::utils::{new_command, set_child_env};
+use crate::webapp::set_base_path;
+
+#[test]
+fn test_parse_config_file() {
+    let values = parse_config_file(
+        "REGISTRY_LOG_LEVEL = \"debug\"\n\n[web]\npublic_uri = \"https://cratery.acme.com\"\nlistenon_port = 8080\n\n[index]\nprotocol_git = true\n\n[external.1]\nname = \"crates-io\"\n\n[docs]\nautoinstall_targets = [\"x86_64-unknown-linux-gnu\", \"wasm32-unknown-unknown\"]\n",
+    )
+    .unwrap();
+    assert_eq!(values.get("REGISTRY_LOG_LEVEL").map(String::as_str), Some("debug"));
+    assert_eq!(
+        values.get("REGISTRY_WEB_PUBLIC_URI").map(String::as_str),
+        Some("https://cratery.acme.com")
+    );
+    assert_eq!(values.get("REGISTRY_WEB_LISTENON_PORT").map(String::as_str), Some("8080"));
+    assert_eq!(values.get("REGISTRY_INDEX_PROTOCOL_GIT").map(String::as_str), Some("true"));
+    assert_eq!(values.get("REGISTRY_EXTERNAL_1_NAME").map(String::as_str), Some("crates-io"));
+    assert_eq!(
+        values.get("REGISTRY_DOCS_AUTOINSTALL_TARGETS").map(String::as_str),
+        Some("x86_64-unknown-linux-gnu,wasm32-unknown-unknown")
+    );
+    assert!(parse_config_file("[web\n").is_err());
+}
+
+#[tokio::test]
+async fn test_configuration_with_reloaded_from_env() {
+    let id = uuid::Uuid::new_v4().simple().to_string();
+    let tenant = format!("reload-{id}");
+    let prefix = format!("REGISTRY_TENANT_RELOAD_{}", id.to_uppercase());
+    let vars = [
+        (format!("{prefix}_WEB_PUBLIC_URI"), "https://other.acme.com"),
+        (format!("{prefix}_LOG_LEVEL"), "DEBUG"),
+        (format!("{prefix}_SEARCH_WEIGHT_RECENCY"), "0.5"),
+        (format!("{prefix}_EMAIL_NOTIFY_YANKS"), "true"),
+    ];
+    for (name, value) in &vars {
+        std::env::set_var(name, value);
+    }
+    let current = Configuration {
+        web_public_uri: String::from("https://cratery.acme.com"),
+        log_level: String::from("INFO"),
+        ..Default::default()
+    };
+    let configuration = with_tenant(Some(&tenant), async { current.with_reloaded_from_env() })
+        .await
+        .unwrap();
+    assert_eq!(configuration.web_public_uri, "https://cratery.acme.com");
+    assert_eq!(configuration.log_level, "DEBUG");
+    assert!((configuration.search_weight_recency - 0.5).abs() < f64::EPSILON);
+    // emails were not configured at startup
+    assert!(!configuration.email_notify_yanks);
+
+    // invalid values are reported instead of panicking
+    std::env::set_var(format!("{prefix}_SEARCH_WEIGHT_RECENCY"), "high");
+    let result = with_tenant(Some(&tenant), async { current.with_reloaded_from_env() }).await;
+    assert_eq!(result.unwrap_err().http, 400);
+    for (name, _) in &vars {
+        std::env::remove_var(name);
+    }
+}
+
+#[test]
+fn test_get_var_from_file() {
+    let id = uuid::Uuid::new_v4().simple().to_string();
+    let name = format!("REGISTRY_TEST_SECRET_{}", id.to_uppercase());
+    assert!(get_var(&name).is_err());
+    let mut path = temp_dir();
+    path.push(format!("cratery-test-{id}"));
+    std::fs::write(&path, "secret\n").unwrap();
+    std::env::set_var(format!("{name}_FILE"), &path);
+    assert_eq!(get_var(&name).unwrap(), "secret");
+    // the variable itself takes precedence
+    std::env::set_var(&name, "direct");
+    assert_eq!(get_var(&name).unwrap(), "direct");
+    std::env::remove_var(&name);
+    std::fs::remove_file(&path).unwrap();
+    // an unreadable file is reported instead of panicking
+    assert!(matches!(get_var(&name), Err(EnvVarError::UnreadableFile { .. })));
+    assert!(matches!(get_var_opt(&name), Err(EnvVarError::UnreadableFile { .. })));
+    std::env::remove_var(format!("{name}_FILE"));
+    assert_eq!(get_var_opt(&name).unwrap(), None);
+}
+
+#[test]
+fn test_parse_s3_addressing_style() {
+    assert_eq!("path".parse::<S3AddressingStyle>(), Ok(S3AddressingStyle::Path));
+    assert_eq!(
+        "virtual-hosted".parse::<S3AddressingStyle>(),
+        Ok(S3AddressingStyle::VirtualHosted)
+    );
+    assert_eq!("virtual".parse::<S3AddressingStyle>(), Ok(S3AddressingStyle::VirtualHosted));
+    assert!("vhost".parse::<S3AddressingStyle>().is_err());
+}
+
+#[tokio::test]
+async fn test_get_var_for_tenant() {
+    let id = uuid::Uuid::new_v4().simple().to_string().to_uppercase();
+    let name = format!("REGISTRY_TEST_TENANT_{id}");
+    std::env::set_var(&name, "main");
+    // tenants inherit the value of the main registry
+    assert_eq!(
+        with_tenant(Some("acme-corp"), async { get_var(&name) }).await.unwrap(),
+        "main"
+    );
+    let tenant_name = format!("REGISTRY_TENANT_ACME_CORP_TEST_TENANT_{id}");
+    std::env::set_var(&tenant_name, "tenant");
+    assert_eq!(
+        with_tenant(Some("acme-corp"), async { get_var(&name) }).await.unwrap(),
+        "tenant"
+    );
+    assert_eq!(with_tenant(Some("other"), async { get_var(&name) }).await.unwrap(), "main");
+    assert_eq!(get_var(&name).unwrap(), "main");
+    std::env::remove_var(&tenant_name);
+    std::env::remove_var(&name);
+}
+
+#[test]
+fn test_tenant_names() {
+    assert!(is_valid_tenant_name("acme"));
+    assert!(is_valid_tenant_name("acme-corp2"));
+    // could be mistaken for a folder of the sparse index
+    assert!(!is_valid_tenant_name("ac"));
+    assert!(!is_valid_tenant_name("Acme"));
+    assert!(!is_valid_tenant_name("2acme"));
+    assert!(!is_valid_tenant_name("acme/corp"));
+    // first segment of a route
+    assert!(!is_valid_tenant_name("api"));
+    assert!(!is_valid_tenant_name("webapp"));
+}
+
+#[test]
+fn test_set_base_path_for_tenant() {
+    let content = b"<head>\n  <base href=\"/\">\n  <link rel=\"icon\" href=\"webapp/favicon.png\">";
+    let rewritten = String::from_utf8(set_base_path(content, "/acme")).unwrap();
+    assert_eq!(
+        rewritten,
+        "<head>\n  <base href=\"/acme/\">\n  <link rel=\"icon\" href=\"webapp/favicon.png\">"
+    );
+}
+
+#[test]
+fn test_child_env() {
+    let mut home_dir = temp_dir();
+    home_dir.push(format!("cratery-test-{}", uuid::Uuid::new_v4().simple()));
+    let configuration = Configuration {
+        home_dir: home_dir.to_str().unwrap().to_string(),
+        ..Default::default()
+    };
+    let env = configuration.get_child_env();
+    assert_eq!(
+        env,
+        vec![
+            (
+                String::from("GIT_CONFIG_GLOBAL"),
+                home_dir.join(".gitconfig").display().to_string()
+            ),
+            (String::from("CARGO_HOME"), home_dir.join(".cargo").display().to_string()),
+        ]
+    );
+    // nothing to give when this is the home directory of the current user
+    if let Ok(home) = std::env::var("HOME") {
+        let configuration = Configuration {
+            home_dir: home,
+            ..Default::default()
+        };
+        assert!(configuration.get_child_env().is_empty());
+    }
+
+    // the environment is given to the spawned processes, not set for the current one
+    set_child_env(env);
+    let command = new_command("git");
+    let envs = command
+        .as_std()
+        .get_envs()
+        .map(|(name, value)| (name.to_str().unwrap(), value.and_then(|v| v.to_str())))
+        .collect::<Vec<_>>();
+    assert!(envs.contains(&("CARGO_HOME", Some(home_dir.join(".cargo").to_str().unwrap()))));
+    assert!(std::env::var("CARGO_HOME").map_or(true, |value| value != home_dir.join(".cargo").to_str().unwrap()));
+
+    // the SSH key for the remote of the index is given to git
+    let mut configuration = Configuration {
+        home_dir: std::env::var("HOME").unwrap_or_default(),
+        ..Default::default()
+    };
+    configuration.index.home_dir = home_dir.to_str().unwrap().to_string();
+    configuration.index.remote_ssh_key_file_name = Some(String::from("id_index"));
+    let key_path = home_dir.join(".ssh").join("id_index");
+    assert_eq!(configuration.index.get_remote_ssh_key_path(), Some(key_path.clone()));
+    assert!(configuration.get_child_env().contains(&(
+        String::from("GIT_SSH_COMMAND"),
+        format!("ssh -i \"{}\" -o IdentitiesOnly=yes", key_path.display())
+    )));
+}
+
+#[test]
+fn test_outbound_proxy() {
+    let configuration = Configuration {
+        home_dir: std::env::var("HOME").unwrap_or_default(),
+        outbound_proxy: Some(String::from("http://proxy.acme.com:3128")),
+        outbound_no_proxy: Some(String::from("localhost,.internal.acme.com")),
+        ..Default::default()
+    };
+    let env = configuration.get_child_env();
+    assert!(env.contains(&(String::from("HTTPS_PROXY"), String::from("http://proxy.acme.com:3128"))));
+    assert!(env.contains(&(String::from("no_proxy"), String::from("localhost,.internal.acme.com"))));
+    assert!(configuration.get_outbound_proxy().unwrap().is_some());
+    assert!(Configuration::default().get_outbound_proxy().unwrap().is_none());
+
+    let configuration = Configuration {
+        outbound_proxy: Some(String::from("not a uri")),
+        ..Default::default()
+    };
+    assert_eq!(configuration.get_outbound_proxy().unwrap_err().http, 400);
+}
+
+#[tokio::test]
+async fn test_outbound_proxy_is_used() {
+    // the mock proxy answers all the requests forwarded to it
+    let app = Router::new().fallback(|| async { "proxied" });
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    let configuration = Configuration {
+        outbound_proxy: Some(format!("http://{address}")),
+        outbound_no_proxy: Some(String::from("no-proxy.invalid")),
+        ..Default::default()
+    };
+    let client = reqwest::Client::builder()
+        .proxy(configuration.get_outbound_proxy().unwrap().unwrap())
+        .build()
+        .unwrap();
+    let response = client.get("http://registry.invalid/").send().await.unwrap();
+    assert_eq!(response.text().await.unwrap(), "proxied");
+    // the excluded hosts are reached directly
+    assert!(client.get("http://no-proxy.invalid/").send().await.is_err());
+}
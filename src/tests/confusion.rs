@@ -0,0 +1,94 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Tests about the protection against dependency confusion
+
+use std::sync::Arc;
+
+use super::mocks::{MOCK_CRATES_IO_CRATE, MOCK_CRATES_IO_FAILURE};
+use super::{async_test, build_payload};
+use crate::model::config::ConfusionCheck;
+use crate::utils::apierror::ApiError;
+
+#[test]
+fn test_publish_confusion_reject() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        let mut configuration = (**application.configuration.load()).clone();
+        configuration.proxy.confusion_check = ConfusionCheck::Reject;
+        application.configuration.store(Arc::new(configuration));
+
+        let error = application
+            .publish_crate_version(&admin_auth, &build_payload(MOCK_CRATES_IO_CRATE, "0.1.0"))
+            .await
+            .unwrap_err();
+        assert_eq!(error.http, 403);
+        let result = application
+            .publish_crate_version(&admin_auth, &build_payload("internal-only", "0.1.0"))
+            .await?;
+        assert!(result.warnings.other.is_empty());
+        Ok(())
+    })
+}
+
+#[test]
+fn test_publish_confusion_warn() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        let mut configuration = (**application.configuration.load()).clone();
+        configuration.proxy.confusion_check = ConfusionCheck::Warn;
+        application.configuration.store(Arc::new(configuration));
+
+        let result = application
+            .publish_crate_version(&admin_auth, &build_payload(MOCK_CRATES_IO_CRATE, "0.1.0"))
+            .await?;
+        assert_eq!(result.warnings.other.len(), 1);
+        let crates = application
+            .db_transaction_read(|app| async move { app.database.get_crates_claimed_upstream().await })
+            .await?;
+        assert_eq!(crates, vec![(String::from(MOCK_CRATES_IO_CRATE), true)]);
+        Ok(())
+    })
+}
+
+#[test]
+fn test_publish_confusion_lookup_failure() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        let mut configuration = (**application.configuration.load()).clone();
+        configuration.proxy.confusion_check = ConfusionCheck::Warn;
+        application.configuration.store(Arc::new(configuration.clone()));
+
+        // the crate is deemed unknown upstream
+        let result = application
+            .publish_crate_version(&admin_auth, &build_payload(MOCK_CRATES_IO_FAILURE, "0.1.0"))
+            .await?;
+        assert!(result.warnings.other.is_empty());
+
+        configuration.proxy.confusion_check = ConfusionCheck::Reject;
+        application.configuration.store(Arc::new(configuration));
+        let error = application
+            .publish_crate_version(&admin_auth, &build_payload(MOCK_CRATES_IO_FAILURE, "0.2.0"))
+            .await
+            .unwrap_err();
+        assert_eq!(error.http, 500);
+        Ok(())
+    })
+}
+
+#[test]
+fn test_publish_confusion_reject_existing() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        application
+            .publish_crate_version(&admin_auth, &build_payload(MOCK_CRATES_IO_CRATE, "0.1.0"))
+            .await?;
+        let mut configuration = (**application.configuration.load()).clone();
+        configuration.proxy.confusion_check = ConfusionCheck::Reject;
+        application.configuration.store(Arc::new(configuration));
+
+        // crates that existed before are still published, with the warning
+        let result = application
+            .publish_crate_version(&admin_auth, &build_payload(MOCK_CRATES_IO_CRATE, "0.2.0"))
+            .await?;
+        assert_eq!(result.warnings.other.len(), 1);
+        Ok(())
+    })
+}
@@ -0,0 +1,112 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Tests about the CORS policy for the API
+
+use std::sync::Arc;
+
+use axum::body::Body;
+use axum::http::{header, Method, Request};
+use axum::routing::get;
+use axum::Router;
+use cookie::Key;
+use tokio::runtime::Builder;
+use tower::ServiceExt;
+
+use super::async_test;
+use crate::model::config::{with_tenant, CorsConfig};
+use crate::registry_router;
+use crate::utils::apierror::ApiError;
+use crate::utils::axum::cors::cors_layer;
+
+#[test]
+fn test_cors_layer() {
+    assert!(cors_layer(&CorsConfig::default()).is_none());
+    let config = CorsConfig {
+        allowed_origins: vec![String::from("https://app.acme.com/")],
+        allowed_methods: vec![String::from("GET"), String::from("PATCH")],
+        allowed_headers: vec![String::from("authorization")],
+        allow_credentials: true,
+    };
+    let app = Router::new()
+        .route("/api", get(|| async { "ok" }))
+        .layer(cors_layer(&config).unwrap());
+    let runtime = Builder::new_current_thread().enable_all().build().unwrap();
+    runtime.block_on(async move {
+        let preflight = Request::builder()
+            .method(Method::OPTIONS)
+            .uri("/api")
+            .header(header::ORIGIN, "https://app.acme.com")
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "PATCH")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(preflight).await.unwrap();
+        let headers = response.headers();
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_ORIGIN], "https://app.acme.com");
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_METHODS], "GET,PATCH");
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+
+        let other = Request::builder()
+            .uri("/api")
+            .header(header::ORIGIN, "https://evil.com")
+            .body(Body::empty())
+            .unwrap();
+        let response = app.oneshot(other).await.unwrap();
+        assert!(!response.headers().contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+    });
+}
+
+#[test]
+fn test_cors_api_versions() -> Result<(), ApiError> {
+    async_test(|application, _admin_auth| async move {
+        let mut configuration = (**application.configuration.load()).clone();
+        configuration.web_cors = CorsConfig {
+            allowed_origins: vec![String::from("https://app.acme.com")],
+            allowed_methods: vec![String::from("GET")],
+            allowed_headers: vec![String::from("authorization")],
+            allow_credentials: false,
+        };
+        application.configuration.store(Arc::new(configuration));
+        let app = registry_router(application, Key::generate());
+        for uri in ["/api/v1/me", "/api/v2/me/tokens"] {
+            let preflight = Request::builder()
+                .method(Method::OPTIONS)
+                .uri(uri)
+                .header(header::ORIGIN, "https://app.acme.com")
+                .header(header::ACCESS_CONTROL_REQUEST_METHOD, "GET")
+                .body(Body::empty())
+                .unwrap();
+            let response = app.clone().oneshot(preflight).await.unwrap();
+            assert_eq!(
+                response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+                "https://app.acme.com",
+                "{uri}"
+            );
+        }
+        Ok(())
+    })
+}
+
+#[tokio::test]
+async fn test_cors_config_from_env() {
+    let id = uuid::Uuid::new_v4().simple().to_string();
+    let tenant = format!("cors-{id}");
+    let prefix = format!("REGISTRY_TENANT_CORS_{}", id.to_uppercase());
+    let origins = format!("{prefix}_WEB_CORS_ORIGINS");
+    let credentials = format!("{prefix}_WEB_CORS_CREDENTIALS");
+    std::env::set_var(&origins, "https://app.acme.com");
+    std::env::set_var(&credentials, "true");
+    let config = with_tenant(Some(&tenant), async { CorsConfig::from_env() }).await.unwrap();
+    assert_eq!(config.allowed_origins, vec![String::from("https://app.acme.com")]);
+    assert!(config.allow_credentials);
+
+    // invalid values are reported instead of panicking
+    for value in ["app.acme.com", "*"] {
+        std::env::set_var(&origins, value);
+        let result = with_tenant(Some(&tenant), async { CorsConfig::from_env() }).await;
+        assert_eq!(result.unwrap_err().http, 400);
+    }
+    std::env::remove_var(&origins);
+    std::env::remove_var(&credentials);
+}
@@ -0,0 +1,45 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Tests about the in-memory cache of the data about the crates
+
+use std::time::Duration;
+
+use crate::utils::cache::TimedCache;
+
+#[test]
+fn test_timed_cache_invalidation() {
+    let cache = TimedCache::<u32>::new(2, Duration::from_secs(60));
+    cache.insert("a", 1, cache.generation("a"));
+    assert_eq!(cache.get("a"), Some(1));
+
+    // a value computed before an invalidation is not stored
+    let generation = cache.generation("a");
+    cache.invalidate("a");
+    cache.insert("a", 2, generation);
+    assert_eq!(cache.get("a"), None);
+
+    // the invalidation of another key does not prevent storing a value
+    let generation = cache.generation("a");
+    cache.invalidate("b");
+    cache.insert("a", 2, generation);
+    assert_eq!(cache.get("a"), Some(2));
+
+    // but invalidating all the values does
+    let generation = cache.generation("a");
+    cache.invalidate_all();
+    cache.insert("a", 3, generation);
+    assert_eq!(cache.get("a"), None);
+
+    // the cache is emptied when full
+    cache.insert("a", 1, cache.generation("a"));
+    cache.insert("b", 2, cache.generation("b"));
+    cache.insert("c", 3, cache.generation("c"));
+    assert_eq!(cache.get("a"), None);
+    assert_eq!(cache.get("c"), Some(3));
+
+    let cache = TimedCache::<u32>::new(2, Duration::ZERO);
+    cache.insert("a", 1, cache.generation("a"));
+    assert_eq!(cache.get("a"), None);
+}
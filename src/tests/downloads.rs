@@ -0,0 +1,81 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Tests about the counting of the downloads
+
+use byteorder::ByteOrder;
+use chrono::{Datelike, NaiveDate};
+
+use super::{async_test, build_payload};
+use crate::model::stats::{add_download_series, read_download_series_on, SERIES_LENGTH};
+use crate::utils::apierror::ApiError;
+
+#[test]
+fn test_downloads_flushed_in_batches() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        application
+            .publish_crate_version(&admin_auth, &build_payload("counted", "0.1.0"))
+            .await?;
+        for _ in 0..3 {
+            application.get_crate_content(&admin_auth, "counted", "0.1.0", None).await?;
+        }
+        let downloads = application.get_crate_downloads(&admin_auth, "counted").await?;
+        assert!(downloads.version_downloads.is_empty());
+
+        application.flush_downloads().await?;
+        let downloads = application.get_crate_downloads(&admin_auth, "counted").await?;
+        let total: u32 = downloads.version_downloads.iter().map(|day| day.downloads).sum();
+        assert_eq!(total, 3);
+        let info = serde_json::to_value(application.get_crate_info(&admin_auth, "counted").await?)?;
+        assert_eq!(info["downloads"], 3);
+        // the previous name of the field is kept for the existing clients
+        assert_eq!(info["versions"][0]["downloads"], 3);
+        assert_eq!(info["versions"][0]["downloadCount"], 3);
+
+        // nothing left to write
+        application.flush_downloads().await?;
+        let downloads = application.get_crate_downloads(&admin_auth, "counted").await?;
+        let total: u32 = downloads.version_downloads.iter().map(|day| day.downloads).sum();
+        assert_eq!(total, 3);
+        Ok(())
+    })
+}
+
+#[test]
+fn test_download_series_skipped_days() {
+    let day = |month, day| NaiveDate::from_ymd_opt(2024, month, day).unwrap();
+    let data = add_download_series(None, day(12, 30), 2);
+    let data = add_download_series(Some(&data), day(12, 30), 1);
+    let series = read_download_series_on(Some(&data), day(12, 30));
+    assert_eq!(series.len(), SERIES_LENGTH);
+    assert_eq!(series[SERIES_LENGTH - 1], 3);
+    assert_eq!(series.iter().sum::<u32>(), 3);
+
+    // the days without downloads, across the end of the year, are empty
+    let data = add_download_series(Some(&data), NaiveDate::from_ymd_opt(2025, 1, 2).unwrap(), 4);
+    let series = read_download_series_on(Some(&data), NaiveDate::from_ymd_opt(2025, 1, 3).unwrap());
+    assert_eq!(series[SERIES_LENGTH - 5], 3);
+    assert_eq!(series[SERIES_LENGTH - 2], 4);
+    assert_eq!(series.iter().sum::<u32>(), 7);
+
+    // the downloads older than the series are dropped
+    let data = add_download_series(Some(&data), NaiveDate::from_ymd_opt(2025, 4, 2).unwrap(), 1);
+    let series = read_download_series_on(Some(&data), NaiveDate::from_ymd_opt(2025, 4, 2).unwrap());
+    assert_eq!(series.iter().sum::<u32>(), 1);
+    let series = read_download_series_on(Some(&data), NaiveDate::from_ymd_opt(2026, 1, 1).unwrap());
+    assert_eq!(series.iter().sum::<u32>(), 0);
+}
+
+#[test]
+fn test_download_series_previous_format() {
+    let today = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+    let mut data = vec![0; SERIES_LENGTH * size_of::<u32>()];
+    let index = (today.ordinal0() as usize % SERIES_LENGTH) * size_of::<u32>();
+    byteorder::NativeEndian::write_u32(&mut data[index..], 5);
+    assert_eq!(read_download_series_on(Some(&data), today)[SERIES_LENGTH - 1], 5);
+    let data = add_download_series(Some(&data), today, 1);
+    let series = read_download_series_on(Some(&data), today);
+    assert_eq!(series[SERIES_LENGTH - 1], 6);
+    assert_eq!(series.iter().sum::<u32>(), 6);
+}
@@ -0,0 +1,65 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Tests about the conditional requests with entity tags
+
+use axum::body::{to_bytes, Body};
+use axum::http::{header, Request, StatusCode};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use cookie::Key;
+use tower::ServiceExt;
+
+use super::{async_test, build_payload};
+use crate::registry_router;
+use crate::utils::apierror::ApiError;
+use crate::utils::axum::etag::{compute_etag, etag_matches};
+
+#[test]
+fn test_etag_matches() {
+    let etag = compute_etag(b"{\"name\":\"cratery\"}");
+    assert!(etag.starts_with('"') && etag.ends_with('"'));
+    assert_eq!(etag, compute_etag(b"{\"name\":\"cratery\"}"));
+    assert_ne!(etag, compute_etag(b"{\"name\":\"other\"}"));
+    assert!(etag_matches(&etag, &etag));
+    assert!(etag_matches(&format!("W/{etag}"), &etag));
+    assert!(etag_matches(&format!("\"other\", {etag}"), &etag));
+    assert!(etag_matches("*", &etag));
+    assert!(!etag_matches("\"other\"", &etag));
+}
+
+#[test]
+fn test_download_is_not_conditional() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        application
+            .publish_crate_version(&admin_auth, &build_payload("counted", "0.1.0"))
+            .await?;
+        let token = admin_auth.token.clone().unwrap();
+        let credentials = STANDARD.encode(format!("{}:{}", token.id, token.secret));
+        let app = registry_router(application.clone(), Key::generate());
+        let download = |if_none_match: Option<String>| {
+            let mut request = Request::builder()
+                .uri("/api/v1/crates/counted/0.1.0/download")
+                .header(header::AUTHORIZATION, format!("Basic {credentials}"));
+            if let Some(if_none_match) = if_none_match {
+                request = request.header(header::IF_NONE_MATCH, if_none_match);
+            }
+            app.clone().oneshot(request.body(Body::empty()).unwrap())
+        };
+        let response = download(None).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let etag = response.headers()[header::ETAG].to_str().unwrap().to_string();
+        let content = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+
+        // the content is sent again, so that each download is counted
+        let response = download(Some(etag)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(to_bytes(response.into_body(), usize::MAX).await.unwrap(), content);
+        application.flush_downloads().await?;
+        let downloads = application.get_crate_downloads(&admin_auth, "counted").await?;
+        let total: u32 = downloads.version_downloads.iter().map(|day| day.downloads).sum();
+        assert_eq!(total, 2);
+        Ok(())
+    })
+}
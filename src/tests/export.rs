@@ -0,0 +1,127 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Tests about the export of the whole registry
+
+use std::io::Read;
+use std::sync::Arc;
+
+use flate2::read::GzDecoder;
+use tar::Archive;
+use tokio::io::AsyncReadExt;
+
+use super::mocks::MockStorage;
+use super::{async_test, build_payload, build_payload_with_files};
+use crate::application::Application;
+use crate::model::cargo::{CrateMetadata, CrateUploadData};
+use crate::model::export::{ExportManifest, ExportedCrate, EXPORT_FORMAT_VERSION};
+use crate::restore::import_archive;
+use crate::services::storage::Storage;
+use crate::utils::apierror::ApiError;
+use crate::utils::axum::auth::AuthData;
+
+#[test]
+fn test_exported_crate_paths() {
+    let exported = ExportedCrate {
+        package: String::from("foo"),
+        version: String::from("1.0.0"),
+        checksum: None,
+    };
+    assert_eq!(
+        ExportedCrate::parse_path(&exported.data_path()),
+        Some((exported.clone(), false))
+    );
+    assert_eq!(ExportedCrate::parse_path(&exported.metadata_path()), Some((exported, true)));
+    assert_eq!(ExportedCrate::parse_path("crates/foo/1.0.0/readme"), None);
+    assert_eq!(ExportedCrate::parse_path("crates/../1.0.0/data"), None);
+    assert_eq!(ExportedCrate::parse_path("index/fo/o/foo"), None);
+}
+
+#[test]
+fn test_export_registry() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        let mut file = application.export_registry(&admin_auth, false).await?;
+        let mut content = Vec::new();
+        file.read_to_end(&mut content).await?;
+        let mut manifest = None;
+        let mut paths = Vec::new();
+        for entry in Archive::new(GzDecoder::new(content.as_slice())).entries()? {
+            let mut entry = entry?;
+            let path = entry.path()?.to_string_lossy().into_owned();
+            if path == "manifest.json" {
+                let mut data = Vec::new();
+                entry.read_to_end(&mut data)?;
+                manifest = Some(serde_json::from_slice::<ExportManifest>(&data)?);
+            }
+            paths.push(path);
+        }
+        assert!(paths.contains(&String::from("registry.db")));
+        assert!(paths.contains(&String::from("index/config.json")));
+        let manifest = manifest.unwrap();
+        assert_eq!(manifest.format, EXPORT_FORMAT_VERSION);
+        assert!(!manifest.include_crates);
+        assert!(manifest.crates.is_empty());
+        Ok(())
+    })
+}
+
+/// Exports the registry with the crates and imports the archive in a new data folder with an empty storage
+async fn export_then_import(
+    application: &Application,
+    admin_auth: &AuthData,
+) -> Result<(Result<(), ApiError>, Arc<MockStorage>), ApiError> {
+    let mut file = application.export_registry(admin_auth, true).await?;
+    let mut content = Vec::new();
+    file.read_to_end(&mut content).await?;
+    let mut configuration = (**application.configuration.load()).clone();
+    let archive = format!("{}/export.tar.gz", configuration.data_dir);
+    tokio::fs::write(&archive, content).await?;
+    configuration.data_dir = format!("{}/target", configuration.data_dir);
+    configuration.index.location = format!("{}/index", configuration.data_dir);
+    let staging = std::path::PathBuf::from(format!("{}/import", configuration.data_dir));
+    tokio::fs::create_dir_all(&staging).await?;
+    let storage = Arc::new(MockStorage::default());
+    let result = import_archive(&configuration, &archive, &staging, storage.clone()).await;
+    Ok((result, storage))
+}
+
+#[test]
+fn test_export_import_registry() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        let payload = build_payload_with_files("exported", "0.1.0", &[("src/lib.rs", "")]);
+        application.publish_crate_version(&admin_auth, &payload).await?;
+        let (result, storage) = export_then_import(&application, &admin_auth).await?;
+        result?;
+        let expected = CrateUploadData::new(&payload)?.content;
+        assert_eq!(storage.download_crate("exported", "0.1.0").await?, expected);
+        let data_dir = format!("{}/target", application.configuration.load().data_dir);
+        assert!(tokio::fs::try_exists(format!("{data_dir}/registry.db")).await?);
+        assert!(tokio::fs::try_exists(format!("{data_dir}/index/config.json")).await?);
+        Ok(())
+    })
+}
+
+#[test]
+fn test_import_rejects_tampered_crates() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        application
+            .publish_crate_version(&admin_auth, &build_payload("tampered", "0.1.0"))
+            .await?;
+        // the exported content no longer matches the checksum in the index
+        let other = CrateUploadData::new(&build_payload_with_files("tampered", "0.1.0", &[("src/lib.rs", "")]))?;
+        let metadata = CrateMetadata {
+            name: String::from("tampered"),
+            vers: String::from("0.1.0"),
+            ..Default::default()
+        };
+        application
+            .get_service_storage()
+            .store_crate(&metadata, other.content)
+            .await?;
+        let (result, storage) = export_then_import(&application, &admin_auth).await?;
+        assert_eq!(result.unwrap_err().http, 400);
+        assert!(storage.download_crate_metadata("tampered", "0.1.0").await?.is_none());
+        Ok(())
+    })
+}
@@ -0,0 +1,57 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Tests about the frozen crates
+
+use super::{async_test, build_payload, setup_create_user};
+use crate::model::pagination::Pagination;
+use crate::utils::apierror::ApiError;
+
+#[test]
+fn test_frozen_crate_rejects_changes() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        setup_create_user(&application, "other", "").await?;
+        application
+            .publish_crate_version(&admin_auth, &build_payload("frozen", "0.1.0"))
+            .await?;
+        application.set_crate_frozen(&admin_auth, "frozen", true).await?;
+
+        // publishing, yanking and changing the owners are rejected
+        let error = application
+            .publish_crate_version(&admin_auth, &build_payload("frozen", "0.2.0"))
+            .await
+            .unwrap_err();
+        assert_eq!(error.http, 403);
+        let error = application
+            .yank_crate_version(&admin_auth, "frozen", "0.1.0", None)
+            .await
+            .unwrap_err();
+        assert_eq!(error.http, 403);
+        let error = application
+            .add_crate_owners(&admin_auth, "frozen", &[String::from("other")])
+            .await
+            .unwrap_err();
+        assert_eq!(error.http, 403);
+
+        // the changes are allowed again after unfreezing
+        application.set_crate_frozen(&admin_auth, "frozen", false).await?;
+        application
+            .publish_crate_version(&admin_auth, &build_payload("frozen", "0.2.0"))
+            .await?;
+        application.yank_crate_version(&admin_auth, "frozen", "0.1.0", None).await?;
+        application
+            .add_crate_owners(&admin_auth, "frozen", &[String::from("other")])
+            .await?;
+
+        let pagination = Pagination::new(None, None, None);
+        let log = application
+            .get_audit_log(&admin_auth, Some("freeze"), pagination)
+            .await?
+            .items;
+        let actions = log.iter().map(|entry| entry.action.as_str()).collect::<Vec<_>>();
+        assert_eq!(actions, vec!["unfreeze_crate", "freeze_crate"]);
+        assert!(log.iter().all(|entry| entry.details == "frozen"));
+        Ok(())
+    })
+}
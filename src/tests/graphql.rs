@@ -0,0 +1,100 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Tests about the GraphQL API
+
+use async_graphql::Request;
+use serde_json::json;
+
+use super::{async_test, build_payload, setup_create_token, setup_create_user, ADMIN_NAME};
+use crate::graphql::build_schema;
+use crate::utils::apierror::ApiError;
+use crate::utils::axum::auth::{AuthData, Token};
+
+#[test]
+fn test_graphql_me() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        let schema = build_schema();
+        let request = Request::new("{ me { login } }").data(application).data(admin_auth);
+        let response = schema.execute(request).await;
+        assert!(response.errors.is_empty());
+        assert_eq!(response.data.into_json()?, json!({ "me": { "login": ADMIN_NAME } }));
+        Ok(())
+    })
+}
+
+#[test]
+fn test_graphql_unknown_crate() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        let schema = build_schema();
+        let request = Request::new(r#"{ crate(name: "unknown") { name } }"#)
+            .data(application)
+            .data(admin_auth);
+        let response = schema.execute(request).await;
+        assert_eq!(response.errors.len(), 1);
+        Ok(())
+    })
+}
+
+#[test]
+fn test_graphql_email_visibility() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        setup_create_user(&application, "other", "").await?;
+        let other_secret = setup_create_token(&application, 2, false, false).await?;
+        let other_auth = || {
+            AuthData::from(Token {
+                id: String::from("other"),
+                secret: other_secret.clone(),
+            })
+        };
+        application
+            .publish_crate_version(&admin_auth, &build_payload("hosted", "0.1.0"))
+            .await?;
+        let schema = build_schema();
+        let query = r#"{ me { email } crate(name: "hosted") { owners { login email } } }"#;
+
+        // the administrator sees all the emails
+        let response = schema
+            .execute(Request::new(query).data(application.clone()).data(admin_auth))
+            .await;
+        assert!(response.errors.is_empty());
+        let data = response.data.into_json()?;
+        assert!(data["crate"]["owners"][0]["email"].is_string());
+
+        // the other users only see their own email
+        let response = schema
+            .execute(Request::new(query).data(application.clone()).data(other_auth()))
+            .await;
+        assert!(response.errors.is_empty());
+        let data = response.data.into_json()?;
+        assert!(data["me"]["email"].is_string());
+        assert_eq!(data["crate"]["owners"][0]["email"], json!(null));
+        Ok(())
+    })
+}
+
+#[test]
+fn test_graphql_limits() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        let admin_token = admin_auth.token.unwrap();
+        let admin_auth = || AuthData::from(admin_token.clone());
+        let schema = build_schema();
+        // too deep
+        let query = "{ __schema { types { fields { type { ofType { ofType { ofType { ofType { name } } } } } } } } }";
+        let response = schema
+            .execute(Request::new(query).data(application.clone()).data(admin_auth()))
+            .await;
+        assert_eq!(response.errors.len(), 1);
+        // too complex
+        let query = (0..300)
+            .map(|i| format!("a{i}: me {{ login }} "))
+            .collect::<Vec<_>>()
+            .concat();
+        let response = schema
+            .execute(Request::new(format!("{{ {query} }}")).data(application).data(admin_auth()))
+            .await;
+        assert_eq!(response.errors.len(), 1);
+        Ok(())
+    })
+}
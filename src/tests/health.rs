@@ -0,0 +1,33 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Tests about the health checks of the services
+
+use super::async_test;
+use crate::model::health::{HEALTH_CHECK_DATABASE, HEALTH_CHECK_INDEX, HEALTH_CHECK_STORAGE};
+use crate::utils::apierror::ApiError;
+
+#[test]
+fn test_liveness_report() -> Result<(), ApiError> {
+    async_test(|application, _admin_auth| async move {
+        let report = application.get_liveness();
+        assert!(report.alive);
+        Ok(())
+    })
+}
+
+#[test]
+fn test_readiness_reports_all_services() -> Result<(), ApiError> {
+    async_test(|application, _admin_auth| async move {
+        let report = application.get_readiness().await;
+        assert!(report.healthy);
+        let names = report.checks.iter().map(|check| check.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, [HEALTH_CHECK_DATABASE, HEALTH_CHECK_STORAGE, HEALTH_CHECK_INDEX]);
+
+        // the report is reused for the next probes
+        let again = application.get_readiness().await;
+        assert_eq!(again.checked_on, report.checked_on);
+        Ok(())
+    })
+}
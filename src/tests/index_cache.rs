@@ -0,0 +1,175 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Tests for the in-memory cache of the index
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use bytes::Bytes;
+use tokio::runtime::Builder;
+
+use super::async_test;
+
+use crate::model::cargo::IndexCrateMetadata;
+use crate::model::{IndexResyncMode, IndexResyncResult};
+use crate::services::index::{CachedIndex, Index};
+use crate::utils::apierror::{error_invalid_request, ApiError};
+use crate::utils::FaillibleFuture;
+
+/// An index that counts the reads that reach it
+#[derive(Default)]
+struct CountingIndex {
+    reads: AtomicUsize,
+}
+
+impl CountingIndex {
+    fn read(&self) -> usize {
+        self.reads.fetch_add(1, Ordering::SeqCst) + 1
+    }
+}
+
+impl Index for CountingIndex {
+    fn get_index_file<'a>(&'a self, _file_path: &'a Path) -> FaillibleFuture<'a, Option<PathBuf>> {
+        Box::pin(async move { Ok(None) })
+    }
+
+    fn get_index_file_content<'a>(&'a self, file_path: &'a Path) -> FaillibleFuture<'a, Option<Bytes>> {
+        Box::pin(async move {
+            if file_path == Path::new("/missing") {
+                return Ok(None);
+            }
+            Ok(Some(Bytes::from(self.read().to_string())))
+        })
+    }
+
+    fn get_upload_pack_info_refs(&self) -> FaillibleFuture<'_, Vec<u8>> {
+        Box::pin(async move { Ok(Vec::new()) })
+    }
+
+    fn get_upload_pack_for<'a>(&'a self, _input: &'a [u8]) -> FaillibleFuture<'a, Vec<u8>> {
+        Box::pin(async move { Ok(Vec::new()) })
+    }
+
+    fn publish_crate_version<'a>(&'a self, _metadata: &'a IndexCrateMetadata) -> FaillibleFuture<'a, ()> {
+        Box::pin(async move { Ok(()) })
+    }
+
+    fn get_crate_data<'a>(&'a self, _package: &'a str) -> FaillibleFuture<'a, Vec<IndexCrateMetadata>> {
+        Box::pin(async move {
+            self.read();
+            Ok(Vec::new())
+        })
+    }
+
+    fn get_crate_index_file<'a>(&'a self, _package: &'a str) -> FaillibleFuture<'a, Vec<u8>> {
+        Box::pin(async move { Ok(self.read().to_string().into_bytes()) })
+    }
+
+    fn rebuild<'a>(&'a self, _crates: &'a [IndexCrateMetadata]) -> FaillibleFuture<'a, ()> {
+        Box::pin(async move { Ok(()) })
+    }
+
+    fn resync_remote(&self, _mode: IndexResyncMode, _confirm: bool) -> FaillibleFuture<'_, IndexResyncResult> {
+        Box::pin(async move { Err(error_invalid_request()) })
+    }
+
+    fn check_health(&self) -> FaillibleFuture<'_, ()> {
+        Box::pin(async move { Ok(()) })
+    }
+
+    fn snapshot_into<'a>(&'a self, _target: &'a Path) -> FaillibleFuture<'a, ()> {
+        Box::pin(async move { Ok(()) })
+    }
+}
+
+fn metadata(name: &str) -> IndexCrateMetadata {
+    IndexCrateMetadata {
+        name: name.to_string(),
+        vers: String::from("0.1.0"),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_index_cache_invalidation() -> Result<(), ApiError> {
+    let runtime = Builder::new_current_thread().enable_all().build()?;
+    runtime.block_on(async {
+        let inner = Arc::new(CountingIndex::default());
+        let index = CachedIndex::new(inner.clone(), 16);
+        let serde = Path::new("/se/rd/serde");
+
+        // repeated reads are served from memory
+        assert_eq!(index.get_index_file_content(serde).await?, Some(Bytes::from("1")));
+        assert_eq!(index.get_index_file_content(serde).await?, Some(Bytes::from("1")));
+        assert_eq!(index.get_crate_index_file("serde").await?, b"1");
+        assert_eq!(
+            index.get_index_file_content(Path::new("/config.json")).await?,
+            Some(Bytes::from("2"))
+        );
+        index.get_crate_data("serde").await?;
+        index.get_crate_data("Serde").await?;
+        assert_eq!(inner.reads.load(Ordering::SeqCst), 3);
+
+        // missing files are not cached
+        assert!(index.get_index_file_content(Path::new("/missing")).await?.is_none());
+
+        // publishing invalidates only the published crate
+        index.publish_crate_version(&metadata("Serde")).await?;
+        assert_eq!(index.get_index_file_content(serde).await?, Some(Bytes::from("4")));
+        assert_eq!(
+            index.get_index_file_content(Path::new("/config.json")).await?,
+            Some(Bytes::from("2"))
+        );
+        index.get_crate_data("serde").await?;
+        assert_eq!(inner.reads.load(Ordering::SeqCst), 5);
+
+        // rebuilding invalidates everything
+        index.rebuild(&[]).await?;
+        assert_eq!(
+            index.get_index_file_content(Path::new("/config.json")).await?,
+            Some(Bytes::from("6"))
+        );
+
+        // invalidations from the other instances
+        assert_eq!(index.get_index_file_content(serde).await?, Some(Bytes::from("7")));
+        index.on_remote_invalidation("index:crate:serde");
+        index.on_remote_invalidation("index:crate:");
+        assert_eq!(index.get_index_file_content(serde).await?, Some(Bytes::from("8")));
+        index.on_remote_invalidation("index:*");
+        assert_eq!(
+            index.get_index_file_content(Path::new("/config.json")).await?,
+            Some(Bytes::from("9"))
+        );
+        Ok(())
+    })
+}
+
+#[test]
+fn test_index_rejects_invalid_crate_names() -> Result<(), ApiError> {
+    let runtime = Builder::new_current_thread().enable_all().build()?;
+    runtime.block_on(async {
+        let inner = Arc::new(CountingIndex::default());
+        let index = CachedIndex::new(inner.clone(), 16);
+        for name in ["../../etc/passwd", "..%2F..%2Fetc", "crée", "日本語", ""] {
+            assert_eq!(index.get_crate_index_file(name).await.unwrap_err().http, 400);
+            assert_eq!(index.get_crate_data(name).await.unwrap_err().http, 400);
+        }
+        // nothing reached the inner index
+        assert_eq!(inner.reads.load(Ordering::SeqCst), 0);
+        Ok(())
+    })
+}
+
+#[test]
+fn test_crate_index_file_rejects_traversal() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        for name in ["../../etc/passwd", "../config.json", "crée"] {
+            let error = application.get_crate_index_file(&admin_auth, name).await.unwrap_err();
+            assert_eq!(error.http, 400);
+        }
+        Ok(())
+    })
+}
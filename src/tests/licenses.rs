@@ -0,0 +1,61 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Tests about the inventory of licenses
+
+use crate::model::licenses::{LicenseInventory, LicenseInventoryEntry, LicenseSummary};
+
+fn entry(registry: Option<&str>, package: &str, license: Option<&str>) -> LicenseInventoryEntry {
+    LicenseInventoryEntry {
+        registry: registry.map(str::to_string),
+        package: package.to_string(),
+        version: String::from("1.0.0"),
+        license: license.map(str::to_string),
+        license_file: None,
+        error: None,
+    }
+}
+
+#[test]
+fn test_license_inventory() {
+    let inventory = LicenseInventory::new(
+        vec![
+            entry(None, "alpha", Some("MIT OR Apache-2.0")),
+            entry(None, "beta", Some("MIT OR Apache-2.0")),
+            entry(None, "gamma", None),
+            LicenseInventoryEntry {
+                error: Some(String::from("not found")),
+                ..entry(None, "delta", None)
+            },
+        ],
+        vec![entry(
+            Some("https://github.com/rust-lang/crates.io-index"),
+            "serde",
+            Some("MIT, \"custom\""),
+        )],
+    );
+    assert!(inventory.licenses.contains(&LicenseSummary {
+        license: Some(String::from("MIT OR Apache-2.0")),
+        crates: 2,
+        dependencies: 0,
+    }));
+    // including the crate whose license could not be read
+    assert!(inventory.licenses.contains(&LicenseSummary {
+        license: None,
+        crates: 2,
+        dependencies: 0,
+    }));
+    assert_eq!(inventory.licenses.len(), 3);
+    let csv = inventory.to_csv();
+    let lines = csv.lines().collect::<Vec<_>>();
+    assert_eq!(lines[0], "kind,registry,package,version,license,license_file,error");
+    assert_eq!(lines[1], "crate,,alpha,1.0.0,MIT OR Apache-2.0,,");
+    assert_eq!(lines[3], "crate,,gamma,1.0.0,,,");
+    // the crates whose license cannot be read are reported with the error
+    assert_eq!(lines[4], "crate,,delta,1.0.0,,,not found");
+    assert_eq!(
+        lines[5],
+        "dependency,https://github.com/rust-lang/crates.io-index,serde,1.0.0,\"MIT, \"\"custom\"\"\",,"
+    );
+}
@@ -0,0 +1,67 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Tests about the rendering of READMEs
+
+use axum::body::Body;
+use axum::http::{header, Request, StatusCode};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use cookie::Key;
+use tower::ServiceExt;
+
+use super::{async_test, build_payload};
+use crate::registry_router;
+use crate::utils::apierror::ApiError;
+use crate::utils::markdown::render_markdown;
+
+#[test]
+fn test_render_markdown() {
+    let html = render_markdown(
+        "# My crate\n\nSome *text* with `code` and a [link](https://example.com).\n\n- [x] done\n\n| a | b |\n|---|---|\n| 1 | 2 |\n",
+    );
+    assert!(html.contains("<h1>My crate</h1>"));
+    assert!(html.contains("<p>Some <em>text</em> with <code>code</code> and a <a href=\"https://example.com\">link</a>.</p>"));
+    assert!(html.contains("<input disabled=\"\" type=\"checkbox\" checked=\"\"/>"));
+    assert!(html.contains("<td>1</td><td>2</td>"));
+}
+
+#[test]
+fn test_render_markdown_is_safe() {
+    let html = render_markdown("<script>alert(1)</script>\n\n[click](javascript:alert(1))");
+    assert!(!html.contains("<script>"));
+    assert!(html.contains("&lt;script&gt;"));
+    assert!(html.contains("<a href=\"#\">click</a>"));
+}
+
+#[test]
+fn test_readme_routes_content_types() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        application
+            .publish_crate_version(&admin_auth, &build_payload("documented", "0.1.0"))
+            .await?;
+        let configuration = application.configuration.load_full();
+        let credentials = STANDARD.encode(format!(
+            "{}:{}",
+            configuration.self_service_login, configuration.self_service_token
+        ));
+        let app = registry_router(application.clone(), Key::generate());
+        for (uri, content_type) in [
+            ("/api/v1/crates/documented/readme", "text/markdown"),
+            ("/api/v1/crates/documented/0.1.0/readme", "text/markdown"),
+            ("/api/v1/crates/documented/readme/html", "text/html; charset=utf-8"),
+            ("/api/v1/crates/documented/0.1.0/readme/html", "text/html; charset=utf-8"),
+        ] {
+            let request = Request::builder()
+                .uri(uri)
+                .header(header::AUTHORIZATION, format!("Basic {credentials}"))
+                .body(Body::empty())
+                .unwrap();
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK, "{uri}");
+            assert_eq!(response.headers()[header::CONTENT_TYPE], content_type, "{uri}");
+        }
+        Ok(())
+    })
+}
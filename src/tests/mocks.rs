@@ -4,35 +4,78 @@
 
 //! Types and utilities for mocking services
 
+use std::collections::{BTreeMap, HashMap};
 use std::env::temp_dir;
 use std::path::PathBuf;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
+use arc_swap::ArcSwap;
+use bytes::Bytes;
 use chrono::NaiveDateTime;
 use semver::Version;
 use tokio::sync::mpsc::Sender;
 
-use crate::model::cargo::{CrateMetadata, IndexCrateMetadata};
+use crate::model::cargo::{CrateMetadata, IndexCrateDependency, IndexCrateMetadata};
 use crate::model::config::Configuration;
 use crate::model::deps::DepsAnalysis;
 use crate::model::docs::{DocGenEvent, DocGenJob, DocGenJobSpec, DocGenJobState, DocGenTrigger};
 use crate::model::osv::SimpleAdvisory;
 use crate::model::worker::WorkersManager;
+use crate::model::{ByteRange, CrateContent, IndexResyncMode, IndexResyncResult};
+use crate::services::chat::{ChatMessage, ChatNotifier};
 use crate::services::deps::DepsChecker;
 use crate::services::docs::DocsGenerator;
 use crate::services::emails::EmailSender;
 use crate::services::index::Index;
+use crate::services::proxy::Proxy;
 use crate::services::rustsec::RustSecChecker;
+use crate::services::search::Search;
 use crate::services::storage::Storage;
+use crate::services::webhooks::WebhookSender;
 use crate::services::ServiceProvider;
-use crate::utils::apierror::ApiError;
+use crate::utils::apierror::{error_backend_failure, error_invalid_request, error_not_found, specialize, ApiError};
 use crate::utils::db::RwSqlitePool;
+use crate::utils::execute_git;
 use crate::utils::token::generate_token;
 use crate::utils::FaillibleFuture;
 
+/// The name of the only crate known on crates.io for the mocking proxy
+pub const MOCK_CRATES_IO_CRATE: &str = "serde";
+
+/// The name of a crate for which the mocking proxy fails to reach crates.io
+pub const MOCK_CRATES_IO_FAILURE: &str = "upstream-failure";
+
 /// A mocking service
 pub struct MockService;
 
+/// A crate stored in the mocking storage, its content and its metadata
+type MockStoredCrate = (Vec<u8>, CrateMetadata);
+
+/// A mocking storage, only keeping the crates, their binaries and the backups of the database in memory
+#[derive(Default)]
+pub struct MockStorage {
+    /// The content and the metadata of the stored crates, by name and version
+    crates: Mutex<HashMap<(String, String), MockStoredCrate>>,
+    /// The prebuilt binaries of the crates, by name, version and target
+    binaries: Mutex<HashMap<(String, String, String), Vec<u8>>>,
+    /// The stored backups
+    backups: Mutex<BTreeMap<String, Vec<u8>>>,
+}
+
+/// A mocking documentation generator, only keeping the queued jobs in memory
+#[derive(Default)]
+pub struct MockDocsGenerator {
+    /// The queued jobs
+    jobs: Mutex<Vec<DocGenJob>>,
+}
+
+/// A mocking index, only keeping the metadata of the published versions in memory
+#[derive(Default)]
+pub struct MockIndex {
+    /// The published versions, by lowercase name of the crate
+    crates: Mutex<HashMap<String, Vec<IndexCrateMetadata>>>,
+}
+
 fn resolved_default<T: Default + Send>() -> FaillibleFuture<'static, T> {
     Box::pin(async { Ok(T::default()) })
 }
@@ -45,24 +88,35 @@ impl ServiceProvider for MockService {
         tokio::fs::create_dir_all(&temp_dir).await?;
         Ok(Configuration {
             data_dir: temp_dir.to_str().unwrap().to_string(),
+            // the synchronization of the advisories is triggered by the tests
+            deps_advisories_sync_period: 0,
             ..Default::default()
         })
     }
 
     fn get_storage(_config: &Configuration) -> Arc<dyn Storage + Send + Sync> {
-        Arc::new(MockService)
+        Arc::new(MockStorage::default())
     }
 
     async fn get_index(_config: &Configuration, _expect_empty: bool) -> Result<Arc<dyn Index + Send + Sync>, ApiError> {
-        Ok(Arc::new(MockService))
+        Ok(Arc::new(MockIndex::default()))
     }
 
     fn get_rustsec(_config: &Configuration) -> Arc<dyn RustSecChecker + Send + Sync> {
         Arc::new(MockService)
     }
 
+    fn get_proxy(_config: &Configuration) -> Result<Arc<dyn Proxy + Send + Sync>, ApiError> {
+        Ok(Arc::new(MockService))
+    }
+
+    fn get_search(config: &Configuration) -> Result<Arc<dyn Search + Send + Sync>, ApiError> {
+        // the search index is local to the data directory, use the real implementation
+        crate::services::search::get_service(config)
+    }
+
     fn get_deps_checker(
-        _configuration: Arc<Configuration>,
+        _configuration: Arc<ArcSwap<Configuration>>,
         _service_index: Arc<dyn Index + Send + Sync>,
         _service_rustsec: Arc<dyn RustSecChecker + Send + Sync>,
     ) -> Arc<dyn DepsChecker + Send + Sync> {
@@ -73,21 +127,33 @@ impl ServiceProvider for MockService {
         Arc::new(MockService)
     }
 
+    fn get_webhook_sender() -> Arc<dyn WebhookSender + Send + Sync> {
+        Arc::new(MockService)
+    }
+
+    fn get_chat_notifier(_config: Arc<Configuration>) -> Arc<dyn ChatNotifier + Send + Sync> {
+        Arc::new(MockService)
+    }
+
     fn get_docs_generator(
-        _configuration: Arc<Configuration>,
+        _configuration: Arc<ArcSwap<Configuration>>,
         _service_db_pool: RwSqlitePool,
         _service_storage: Arc<dyn Storage + Send + Sync>,
         _worker_nodes: WorkersManager,
     ) -> Arc<dyn DocsGenerator + Send + Sync> {
-        Arc::new(MockService)
+        Arc::new(MockDocsGenerator::default())
     }
 }
 
-impl Index for MockService {
+impl Index for MockIndex {
     fn get_index_file<'a>(&'a self, _file_path: &'a std::path::Path) -> FaillibleFuture<'a, Option<PathBuf>> {
         resolved_default()
     }
 
+    fn get_index_file_content<'a>(&'a self, _file_path: &'a std::path::Path) -> FaillibleFuture<'a, Option<Bytes>> {
+        resolved_default()
+    }
+
     fn get_upload_pack_info_refs(&self) -> FaillibleFuture<'_, Vec<u8>> {
         resolved_default()
     }
@@ -96,13 +162,79 @@ impl Index for MockService {
         resolved_default()
     }
 
-    fn publish_crate_version<'a>(&'a self, _metadata: &'a IndexCrateMetadata) -> FaillibleFuture<'a, ()> {
+    fn publish_crate_version<'a>(&'a self, metadata: &'a IndexCrateMetadata) -> FaillibleFuture<'a, ()> {
+        let mut crates = self.crates.lock().unwrap();
+        let versions = crates.entry(metadata.name.to_ascii_lowercase()).or_default();
+        versions.retain(|version| version.vers != metadata.vers);
+        versions.push(metadata.clone());
+        resolved_default()
+    }
+
+    fn get_crate_data<'a>(&'a self, package: &'a str) -> FaillibleFuture<'a, Vec<IndexCrateMetadata>> {
+        let versions = self.crates.lock().unwrap().get(&package.to_ascii_lowercase()).cloned();
+        Box::pin(async move { Ok(versions.unwrap_or_default()) })
+    }
+
+    fn get_crate_index_file<'a>(&'a self, _package: &'a str) -> FaillibleFuture<'a, Vec<u8>> {
         resolved_default()
     }
 
-    fn get_crate_data<'a>(&'a self, _package: &'a str) -> FaillibleFuture<'a, Vec<IndexCrateMetadata>> {
+    fn rebuild<'a>(&'a self, _crates: &'a [IndexCrateMetadata]) -> FaillibleFuture<'a, ()> {
         resolved_default()
     }
+
+    fn resync_remote(&self, _mode: IndexResyncMode, _confirm: bool) -> FaillibleFuture<'_, IndexResyncResult> {
+        Box::pin(async move { Err(error_invalid_request()) })
+    }
+
+    fn check_health(&self) -> FaillibleFuture<'_, ()> {
+        resolved_default()
+    }
+
+    fn snapshot_into<'a>(&'a self, target: &'a std::path::Path) -> FaillibleFuture<'a, ()> {
+        Box::pin(async move {
+            // an empty git index, with its configuration
+            tokio::fs::create_dir_all(target).await?;
+            execute_git(target, &["init", "--quiet"]).await?;
+            tokio::fs::write(target.join("config.json"), b"{}").await?;
+            Ok(())
+        })
+    }
+}
+
+impl Proxy for MockService {
+    fn is_proxied(&self, _registry: Option<&str>) -> bool {
+        false
+    }
+
+    fn get_index_file<'a>(
+        &'a self,
+        _registry: Option<&'a str>,
+        _file_path: &'a std::path::Path,
+    ) -> FaillibleFuture<'a, Option<PathBuf>> {
+        resolved_default()
+    }
+
+    fn download_crate<'a>(
+        &'a self,
+        _registry: Option<&'a str>,
+        _name: &'a str,
+        _version: &'a str,
+    ) -> FaillibleFuture<'a, Option<Vec<u8>>> {
+        resolved_default()
+    }
+
+    fn refresh_versions<'a>(&'a self, _registry: Option<&'a str>, _name: &'a str) -> FaillibleFuture<'a, Option<Vec<String>>> {
+        resolved_default()
+    }
+
+    fn get_crates_io_versions<'a>(&'a self, name: &'a str) -> FaillibleFuture<'a, Option<Vec<String>>> {
+        if name == MOCK_CRATES_IO_FAILURE {
+            return Box::pin(async move { Err(specialize(error_backend_failure(), String::from("crates.io is unreachable"))) });
+        }
+        let versions = (name == MOCK_CRATES_IO_CRATE).then(|| vec![String::from("1.0.0")]);
+        Box::pin(async move { Ok(versions) })
+    }
 }
 
 impl DepsChecker for MockService {
@@ -118,11 +250,36 @@ impl DepsChecker for MockService {
     ) -> FaillibleFuture<'a, DepsAnalysis> {
         resolved_default()
     }
+
+    fn check_crate_deps<'a>(
+        &'a self,
+        _deps: &'a [IndexCrateDependency],
+        _targets: &'a [String],
+    ) -> FaillibleFuture<'a, DepsAnalysis> {
+        resolved_default()
+    }
+
+    fn get_crate_versions<'a>(
+        &'a self,
+        _registry: Option<&'a str>,
+        package: &'a str,
+    ) -> FaillibleFuture<'a, Vec<IndexCrateMetadata>> {
+        // a single yanked version for all the crates in the external registries
+        Box::pin(async move {
+            Ok(vec![IndexCrateMetadata {
+                name: package.to_string(),
+                vers: String::from("1.0.0"),
+                yanked: true,
+                ..Default::default()
+            }])
+        })
+    }
 }
 
-impl DocsGenerator for MockService {
+impl DocsGenerator for MockDocsGenerator {
     fn get_jobs(&self) -> FaillibleFuture<'_, Vec<DocGenJob>> {
-        resolved_default()
+        let jobs = self.jobs.lock().unwrap().clone();
+        Box::pin(async move { Ok(jobs) })
     }
 
     fn get_job_log(&self, _job_id: i64) -> FaillibleFuture<'_, String> {
@@ -130,22 +287,22 @@ impl DocsGenerator for MockService {
     }
 
     fn queue<'a>(&'a self, spec: &'a DocGenJobSpec, trigger: &'a DocGenTrigger) -> FaillibleFuture<'a, DocGenJob> {
-        Box::pin(async {
-            Ok(DocGenJob {
-                id: -1,
-                package: spec.package.clone(),
-                version: spec.version.clone(),
-                target: spec.target.clone(),
-                use_native: false,
-                capabilities: Vec::new(),
-                state: DocGenJobState::Queued,
-                queued_on: NaiveDateTime::default(),
-                started_on: NaiveDateTime::default(),
-                finished_on: NaiveDateTime::default(),
-                last_update: NaiveDateTime::default(),
-                trigger: trigger.clone(),
-            })
-        })
+        let job = DocGenJob {
+            id: -1,
+            package: spec.package.clone(),
+            version: spec.version.clone(),
+            target: spec.target.clone(),
+            use_native: false,
+            capabilities: Vec::new(),
+            state: DocGenJobState::Queued,
+            queued_on: NaiveDateTime::default(),
+            started_on: NaiveDateTime::default(),
+            finished_on: NaiveDateTime::default(),
+            last_update: NaiveDateTime::default(),
+            trigger: trigger.clone(),
+        };
+        self.jobs.lock().unwrap().push(job.clone());
+        Box::pin(async move { Ok(job) })
     }
 
     fn add_listener(&self, _listener: Sender<DocGenEvent>) -> FaillibleFuture<'_, ()> {
@@ -159,29 +316,104 @@ impl EmailSender for MockService {
     }
 }
 
-impl RustSecChecker for MockService {
-    fn check_crate<'a>(&'a self, _package: &'a str, _version: &'a Version) -> FaillibleFuture<'a, Vec<SimpleAdvisory>> {
+impl ChatNotifier for MockService {
+    fn notify<'a>(&'a self, _message: &'a ChatMessage) -> FaillibleFuture<'a, ()> {
         resolved_default()
     }
 }
 
-impl Storage for MockService {
-    fn store_crate<'a>(&'a self, _metadata: &'a CrateMetadata, _content: Vec<u8>) -> FaillibleFuture<'a, ()> {
+impl WebhookSender for MockService {
+    fn deliver<'a>(
+        &'a self,
+        _url: &'a str,
+        _secret: &'a str,
+        _event: &'a str,
+        _delivery_id: i64,
+        _body: &'a str,
+    ) -> FaillibleFuture<'a, u16> {
+        Box::pin(async { Ok(200) })
+    }
+}
+
+impl RustSecChecker for MockService {
+    fn check_crate<'a>(&'a self, _package: &'a str, _version: &'a Version) -> FaillibleFuture<'a, Vec<SimpleAdvisory>> {
         resolved_default()
     }
 
-    fn download_crate<'a>(&'a self, _name: &'a str, _version: &'a str) -> FaillibleFuture<'a, Vec<u8>> {
+    fn sync(&self) -> FaillibleFuture<'_, bool> {
         resolved_default()
     }
+}
 
-    fn download_crate_metadata<'a>(&'a self, _name: &'a str, _version: &'a str) -> FaillibleFuture<'a, Option<CrateMetadata>> {
+impl Storage for MockStorage {
+    fn store_crate<'a>(&'a self, metadata: &'a CrateMetadata, content: Vec<u8>) -> FaillibleFuture<'a, ()> {
+        self.crates
+            .lock()
+            .unwrap()
+            .insert((metadata.name.clone(), metadata.vers.clone()), (content, metadata.clone()));
         resolved_default()
     }
 
+    fn download_crate<'a>(&'a self, name: &'a str, version: &'a str) -> FaillibleFuture<'a, Vec<u8>> {
+        let content = self
+            .crates
+            .lock()
+            .unwrap()
+            .get(&(name.to_string(), version.to_string()))
+            .map(|(content, _)| content.clone());
+        Box::pin(async move { Ok(content.unwrap_or_default()) })
+    }
+
+    fn download_crate_range<'a>(
+        &'a self,
+        name: &'a str,
+        version: &'a str,
+        range: ByteRange,
+    ) -> FaillibleFuture<'a, CrateContent> {
+        Box::pin(async move {
+            let content = self.download_crate(name, version).await?;
+            Ok(CrateContent::from_content(content, Some(range)))
+        })
+    }
+
+    fn download_crate_metadata<'a>(&'a self, name: &'a str, version: &'a str) -> FaillibleFuture<'a, Option<CrateMetadata>> {
+        let metadata = self
+            .crates
+            .lock()
+            .unwrap()
+            .get(&(name.to_string(), version.to_string()))
+            .map(|(_, metadata)| metadata.clone());
+        Box::pin(async move { Ok(metadata) })
+    }
+
     fn download_crate_readme<'a>(&'a self, _name: &'a str, _version: &'a str) -> FaillibleFuture<'a, Vec<u8>> {
         resolved_default()
     }
 
+    fn store_crate_binary<'a>(
+        &'a self,
+        name: &'a str,
+        version: &'a str,
+        target: &'a str,
+        content: Vec<u8>,
+    ) -> FaillibleFuture<'a, ()> {
+        self.binaries
+            .lock()
+            .unwrap()
+            .insert((name.to_string(), version.to_string(), target.to_string()), content);
+        resolved_default()
+    }
+
+    fn download_crate_binary<'a>(&'a self, name: &'a str, version: &'a str, target: &'a str) -> FaillibleFuture<'a, Vec<u8>> {
+        let content = self
+            .binaries
+            .lock()
+            .unwrap()
+            .get(&(name.to_string(), version.to_string(), target.to_string()))
+            .cloned();
+        Box::pin(async move { Ok(content.unwrap_or_default()) })
+    }
+
     fn store_doc_file<'a>(&'a self, _path: &'a str, _file: &'a std::path::Path) -> FaillibleFuture<'a, ()> {
         resolved_default()
     }
@@ -193,4 +425,28 @@ impl Storage for MockService {
     fn download_doc_file<'a>(&'a self, _path: &'a str) -> FaillibleFuture<'a, Vec<u8>> {
         resolved_default()
     }
+
+    fn store_backup<'a>(&'a self, name: &'a str, content: Vec<u8>) -> FaillibleFuture<'a, ()> {
+        self.backups.lock().unwrap().insert(name.to_string(), content);
+        resolved_default()
+    }
+
+    fn download_backup<'a>(&'a self, name: &'a str) -> FaillibleFuture<'a, Vec<u8>> {
+        let content = self.backups.lock().unwrap().get(name).cloned();
+        Box::pin(async move { content.ok_or_else(error_not_found) })
+    }
+
+    fn list_backups(&self) -> FaillibleFuture<'_, Vec<String>> {
+        let names = self.backups.lock().unwrap().keys().cloned().collect();
+        Box::pin(async move { Ok(names) })
+    }
+
+    fn delete_backup<'a>(&'a self, name: &'a str) -> FaillibleFuture<'a, ()> {
+        self.backups.lock().unwrap().remove(name);
+        resolved_default()
+    }
+
+    fn check_health(&self) -> FaillibleFuture<'_, ()> {
+        resolved_default()
+    }
 }
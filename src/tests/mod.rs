@@ -8,17 +8,66 @@ use std::future::Future;
 use std::sync::Arc;
 
 use chrono::Local;
+use flate2::write::GzEncoder;
+use flate2::Compression;
 use tokio::runtime::Builder;
 
 use crate::application::Application;
 use crate::model::auth::ROLE_ADMIN;
+use crate::model::cargo::CrateMetadata;
 use crate::services::ServiceProvider;
 use crate::utils::apierror::ApiError;
 use crate::utils::axum::auth::{AuthData, Token};
 use crate::utils::token::{generate_token, hash_token};
 
+pub mod admin;
+pub mod advisories;
+pub mod announcements;
+pub mod backup;
+pub mod binaries;
+pub mod cache_headers;
+pub mod checksums;
+pub mod client_ip;
+pub mod compression;
+pub mod config;
+pub mod confusion;
+pub mod cors;
+pub mod crate_info_cache;
+pub mod downloads;
+pub mod etag;
+pub mod export;
+pub mod freeze;
+pub mod graphql;
+pub mod health;
+pub mod index_cache;
+pub mod licenses;
+pub mod markdown;
 pub mod mocks;
+pub mod notifications;
+pub mod openapi;
+pub mod owners;
+pub mod profile;
+pub mod provenance;
+pub mod proxy;
+pub mod quarantine;
+pub mod quotas;
+pub mod ranges;
+pub mod ratelimit;
+pub mod request_id;
+pub mod sbom;
+pub mod scheduler;
+pub mod search;
+pub mod secrets;
 pub mod security;
+pub mod security_headers;
+pub mod sources;
+pub mod stars;
+pub mod storage;
+pub mod tenants;
+pub mod vendor;
+pub mod visibility;
+pub mod webhooks;
+pub mod yanks;
 
 pub const ADMIN_UID: i64 = 1;
 pub const ADMIN_NAME: &str = "admin";
@@ -32,7 +81,7 @@ where
     let runtime = Builder::new_current_thread().enable_all().build()?;
     runtime.block_on(async move {
         let application = Application::launch::<mocks::MockService>(mocks::MockService::get_configuration().await?).await?;
-        println!("data_dir={}", &application.configuration.data_dir);
+        println!("data_dir={}", &application.configuration.load().data_dir);
         // create the first user ad admin and its token
         setup_create_admin(&application, ADMIN_NAME).await?;
         let token_secret = setup_create_token(&application, ADMIN_UID, true, true).await?;
@@ -41,7 +90,9 @@ where
             secret: token_secret,
         });
         let r = payload(application.clone(), admin_auth).await;
-        tokio::fs::remove_dir_all(&application.configuration.data_dir).await.unwrap();
+        tokio::fs::remove_dir_all(&application.configuration.load().data_dir)
+            .await
+            .unwrap();
         r
     })?;
     Ok(())
@@ -103,3 +154,35 @@ pub async fn setup_create_token(
     }).await?;
     Ok(token_secret)
 }
+
+/// Builds the payload to publish a crate version, with an empty `.crate` archive
+pub fn build_payload(name: &str, version: &str) -> Vec<u8> {
+    build_payload_with_files(name, version, &[])
+}
+
+/// Builds the payload to publish a crate version, with a `.crate` archive containing the files
+pub fn build_payload_with_files(name: &str, version: &str, files: &[(&str, &str)]) -> Vec<u8> {
+    let metadata = serde_json::to_vec(&CrateMetadata {
+        name: name.to_string(),
+        vers: version.to_string(),
+        ..Default::default()
+    })
+    .unwrap();
+    let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+    for (path, data) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, format!("{name}-{version}/{path}"), data.as_bytes())
+            .unwrap();
+    }
+    let content = builder.into_inner().unwrap().finish().unwrap();
+    let mut payload = Vec::new();
+    payload.extend_from_slice(&u32::try_from(metadata.len()).unwrap().to_le_bytes());
+    payload.extend_from_slice(&metadata);
+    payload.extend_from_slice(&u32::try_from(content.len()).unwrap().to_le_bytes());
+    payload.extend_from_slice(&content);
+    payload
+}
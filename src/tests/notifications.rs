@@ -0,0 +1,164 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Tests about the notifications sent by email
+
+use std::sync::{Arc, Mutex};
+
+use super::{async_test, build_payload, setup_create_token, setup_create_user, ADMIN_NAME};
+use crate::application::Application;
+use crate::model::packages::CrateVisibilityQuery;
+use crate::model::webhooks::{WebhookEvent, WebhookPayload};
+use crate::services::emails::EmailSender;
+use crate::services::notifications::{notify_deprecation, notify_watchers, notify_yank};
+use crate::utils::apierror::ApiError;
+use crate::utils::axum::auth::{AuthData, Token};
+use crate::utils::FaillibleFuture;
+
+/// An email as recorded by the [`RecordingEmailSender`]
+type SentEmail = (Vec<String>, String, String);
+
+/// An email sender recording the emails instead of sending them
+#[derive(Default)]
+struct RecordingEmailSender {
+    sent: Mutex<Vec<SentEmail>>,
+}
+
+impl EmailSender for RecordingEmailSender {
+    fn send_email<'a>(&'a self, to: &'a [String], subject: &'a str, body: String) -> FaillibleFuture<'a, ()> {
+        Box::pin(async move {
+            self.sent.lock().unwrap().push((to.to_vec(), subject.to_string(), body));
+            Ok(())
+        })
+    }
+}
+
+/// Publishes `base` and `dependent`, a crate depending on it
+async fn setup_dependent(application: &Application, admin_auth: &AuthData) -> Result<(), ApiError> {
+    application
+        .publish_crate_version(admin_auth, &build_payload("base", "1.0.0"))
+        .await?;
+    application
+        .publish_crate_version(admin_auth, &build_payload("dependent", "0.1.0"))
+        .await?;
+    application
+        .db_transaction_write("setup_dependent", |app| async move {
+            sqlx::query("INSERT INTO PackageDependency (package, version, dependency, versionReq, kind, optional) VALUES ('dependent', '0.1.0', 'base', '^1.0', 'normal', FALSE)")
+                .execute(&mut *app.database.transaction.borrow().await)
+                .await?;
+            Ok::<(), ApiError>(())
+        })
+        .await
+}
+
+#[test]
+fn test_deprecation_is_notified_once_with_reason() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        setup_dependent(&application, &admin_auth).await?;
+        let set_deprecation = |deprecated: bool| {
+            application.db_transaction_write("set_crate_deprecation", move |app| async move {
+                app.database
+                    .set_crate_deprecation("base", deprecated, Some("replaced by base2"))
+                    .await
+            })
+        };
+        // only the transition to deprecated is notified
+        assert!(set_deprecation(true).await?);
+        assert!(!set_deprecation(true).await?);
+        assert!(!set_deprecation(false).await?);
+        assert!(set_deprecation(true).await?);
+
+        let mut configuration = (*application.configuration.load_full()).clone();
+        configuration.email_notify_yanks = true;
+        let sender = Arc::new(RecordingEmailSender::default());
+        notify_deprecation(
+            &configuration,
+            sender.clone(),
+            application.get_service_db_pool(),
+            "base",
+            Some("replaced by base2"),
+        )
+        .await?;
+        let sent = sender.sent.lock().unwrap().drain(..).collect::<Vec<_>>();
+        assert_eq!(sent.len(), 1);
+        let (to, subject, body) = &sent[0];
+        assert_eq!(to, &vec![ADMIN_NAME.to_string()]);
+        assert!(subject.contains("dependent"));
+        assert!(body.contains("Reason: replaced by base2"));
+        Ok(())
+    })
+}
+
+#[test]
+fn test_yank_is_notified_with_reason() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        setup_dependent(&application, &admin_auth).await?;
+        let mut configuration = (*application.configuration.load_full()).clone();
+        configuration.email_notify_yanks = true;
+        let sender = Arc::new(RecordingEmailSender::default());
+        let pool = application.get_service_db_pool();
+        notify_yank(&configuration, sender.clone(), pool, "base", "1.0.0", Some("security issue")).await?;
+        // versions that the dependent crate cannot select are not notified
+        notify_yank(&configuration, sender.clone(), pool, "base", "2.0.0", None).await?;
+        let sent = sender.sent.lock().unwrap().drain(..).collect::<Vec<_>>();
+        assert_eq!(sent.len(), 1);
+        assert!(sent[0].2.contains("Reason: security issue"));
+        Ok(())
+    })
+}
+
+#[test]
+fn test_restricted_crate_is_not_notified_to_former_watchers() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        setup_create_user(&application, "reader", "").await?;
+        setup_create_user(&application, "other", "").await?;
+        let reader_auth = AuthData::from(Token {
+            id: String::from("reader"),
+            secret: setup_create_token(&application, 2, false, false).await?,
+        });
+        let other_auth = AuthData::from(Token {
+            id: String::from("other"),
+            secret: setup_create_token(&application, 3, false, false).await?,
+        });
+        application
+            .publish_crate_version(&admin_auth, &build_payload("secret", "0.1.0"))
+            .await?;
+        application.watch_crate(&reader_auth, "secret").await?;
+        application.watch_crate(&other_auth, "secret").await?;
+        application
+            .set_crate_visibility(
+                &admin_auth,
+                "secret",
+                &CrateVisibilityQuery {
+                    is_restricted: true,
+                    readers: vec![String::from("reader")],
+                    teams: Vec::new(),
+                },
+            )
+            .await?;
+        // the crate cannot be watched anymore by the users that cannot read it
+        let error = application.watch_crate(&other_auth, "secret").await.unwrap_err();
+        assert_eq!(error.http, 404);
+
+        let mut configuration = (*application.configuration.load_full()).clone();
+        configuration.email_notify_watchers = true;
+        let sender = Arc::new(RecordingEmailSender::default());
+        let payload = WebhookPayload::new(WebhookEvent::Yank, "secret", Some("0.1.0"));
+        notify_watchers(&configuration, sender.clone(), application.get_service_db_pool(), &payload).await?;
+        let sent = sender.sent.lock().unwrap().drain(..).collect::<Vec<_>>();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].0, vec![String::from("reader")]);
+        // the publication is notified in the background, only the yank is checked
+        let count_yanks = |auth_data: AuthData| {
+            let application = application.clone();
+            async move {
+                let notifications = application.get_user_notifications(&auth_data, false, None).await?;
+                Ok::<_, ApiError>(notifications.iter().filter(|n| n.event == "yank").count())
+            }
+        };
+        assert_eq!(count_yanks(reader_auth).await?, 1);
+        assert_eq!(count_yanks(other_auth).await?, 0);
+        Ok(())
+    })
+}
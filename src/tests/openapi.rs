@@ -0,0 +1,22 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Tests about the `OpenAPI` specification
+
+use utoipa::OpenApi;
+
+use crate::openapi::ApiDoc;
+
+#[test]
+fn test_openapi_specification() {
+    let specification = ApiDoc::openapi();
+    let crate_info = specification.paths.paths.get("/api/v1/crates/{package}").unwrap();
+    assert!(crate_info.get.is_some());
+    assert!(crate_info.get.as_ref().unwrap().responses.responses.contains_key("default"));
+    let components = specification.components.as_ref().unwrap();
+    for schema in ["ApiError", "CrateInfo", "RegistryUser", "WebhookCreateQuery"] {
+        assert!(components.schemas.contains_key(schema), "missing schema {schema}");
+    }
+    assert!(specification.to_json().is_ok());
+}
@@ -0,0 +1,54 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Tests about the owners of crates
+
+use super::{async_test, build_payload, setup_create_user, ADMIN_NAME};
+use crate::model::cargo::parse_team_owner;
+use crate::utils::apierror::ApiError;
+
+#[test]
+fn test_parse_team_owner() {
+    assert_eq!(parse_team_owner("github:acme:core-team"), Some(("acme", "core-team")));
+    assert_eq!(parse_team_owner("jane@example.com"), None);
+    assert_eq!(parse_team_owner("github:acme"), None);
+    assert_eq!(parse_team_owner("github:acme:"), None);
+    assert_eq!(parse_team_owner("github:acme:core:extra"), None);
+    assert_eq!(parse_team_owner("gitlab:acme:core"), None);
+    assert_eq!(parse_team_owner("mailto:jane:x@example.com"), None);
+}
+
+#[test]
+fn test_add_remove_team_owners() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        setup_create_user(&application, "alice", "core").await?;
+        setup_create_user(&application, "bob", "core,ops").await?;
+        setup_create_user(&application, "carol", "ops").await?;
+        application
+            .publish_crate_version(&admin_auth, &build_payload("teamed", "0.1.0"))
+            .await?;
+
+        application
+            .add_crate_owners(&admin_auth, "teamed", &[String::from("github:acme:core")])
+            .await?;
+        let owners = application.get_crate_owners(&admin_auth, "teamed").await?;
+        let mut logins = owners.users.iter().map(|user| user.login.as_str()).collect::<Vec<_>>();
+        logins.sort_unstable();
+        assert_eq!(logins, vec![ADMIN_NAME, "alice", "bob"]);
+
+        // a team without any member is refused
+        assert!(application
+            .add_crate_owners(&admin_auth, "teamed", &[String::from("github:acme:nobody")])
+            .await
+            .is_err());
+
+        application
+            .remove_crate_owners(&admin_auth, "teamed", &[String::from("github:acme:core")])
+            .await?;
+        let owners = application.get_crate_owners(&admin_auth, "teamed").await?;
+        let logins = owners.users.iter().map(|user| user.login.as_str()).collect::<Vec<_>>();
+        assert_eq!(logins, vec![ADMIN_NAME]);
+        Ok(())
+    })
+}
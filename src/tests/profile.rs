@@ -0,0 +1,99 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Tests about the profile and the preferences of users
+
+use super::{async_test, setup_create_token, setup_create_user};
+use crate::model::profile::{UserPreferences, UserProfileUpdate};
+use crate::utils::apierror::ApiError;
+use crate::utils::axum::auth::{AuthData, Token};
+
+#[test]
+fn test_update_current_user_name() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        let user = application
+            .update_current_user(
+                &admin_auth,
+                &UserProfileUpdate {
+                    name: String::from("  Jane Doe "),
+                },
+            )
+            .await?;
+        assert_eq!(user.name, "Jane Doe");
+        assert_eq!(application.get_current_user(&admin_auth).await?.name, "Jane Doe");
+
+        let error = application
+            .update_current_user(&admin_auth, &UserProfileUpdate { name: String::from(" ") })
+            .await
+            .unwrap_err();
+        assert_eq!(error.http, 400);
+        Ok(())
+    })
+}
+
+#[test]
+fn test_token_defaults_from_preferences() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        assert_eq!(
+            application.get_current_user_preferences(&admin_auth).await?,
+            UserPreferences::default()
+        );
+        let token = application.create_token(&admin_auth, "default", None, None).await?;
+        assert!(!token.can_write);
+        assert!(!token.can_admin);
+
+        let preferences = application
+            .set_current_user_preferences(
+                &admin_auth,
+                &UserPreferences {
+                    notify_watches: false,
+                    token_can_write: true,
+                    ..UserPreferences::default()
+                },
+            )
+            .await?;
+        assert!(!preferences.notify_watches);
+        let token = application.create_token(&admin_auth, "ci", None, None).await?;
+        assert!(token.can_write);
+        assert!(!token.can_admin);
+        // explicit rights override the preferences
+        let token = application.create_token(&admin_auth, "read", Some(false), None).await?;
+        assert!(!token.can_write);
+        Ok(())
+    })
+}
+
+#[test]
+fn test_read_only_token_cannot_update_profile() -> Result<(), ApiError> {
+    async_test(|application, _admin_auth| async move {
+        setup_create_user(&application, "other", "").await?;
+        let other_auth = AuthData::from(Token {
+            id: String::from("other"),
+            secret: setup_create_token(&application, 2, false, false).await?,
+        });
+        let error = application
+            .update_current_user(
+                &other_auth,
+                &UserProfileUpdate {
+                    name: String::from("Jane Doe"),
+                },
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(error.http, 403);
+        let error = application
+            .set_current_user_preferences(
+                &other_auth,
+                &UserPreferences {
+                    notify_watches: false,
+                    ..UserPreferences::default()
+                },
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(error.http, 403);
+        assert_eq!(application.get_current_user(&other_auth).await?.name, "other");
+        Ok(())
+    })
+}
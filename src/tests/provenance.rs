@@ -0,0 +1,52 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Tests about the provenance of crate versions
+
+use super::{async_test, build_payload_with_files};
+use crate::model::packages::VersionProvenanceDeclaration;
+use crate::utils::apierror::{error_conflict, ApiError};
+
+const COMMIT: &str = "0123456789abcdef0123456789abcdef01234567";
+
+#[test]
+fn test_crate_version_provenance() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        let vcs_info = format!("{{\"git\": {{\"sha1\": \"{COMMIT}\"}}, \"path_in_vcs\": \"\"}}");
+        application
+            .publish_crate_version(
+                &admin_auth,
+                &build_payload_with_files("traced", "0.1.0", &[(".cargo_vcs_info.json", vcs_info.as_str())]),
+            )
+            .await?;
+        let provenance = application
+            .db_transaction_read(|app| async move { app.database.get_crate_version_provenance("traced", "0.1.0").await })
+            .await?;
+        assert_eq!(provenance.commit.as_deref(), Some(COMMIT));
+        assert!(!provenance.verified);
+
+        let mut declaration = VersionProvenanceDeclaration {
+            commit: Some(String::from("fedcba9")),
+            ci_run_url: Some(String::from("https://ci.acme.com/runs/42")),
+            builder: Some(String::from("ci-runner")),
+        };
+        let error = application
+            .set_crate_version_provenance(&admin_auth, "traced", "0.1.0", &declaration)
+            .await
+            .unwrap_err();
+        assert_eq!(error.http, 400);
+        declaration.commit = Some(String::from("0123456"));
+        let provenance = application
+            .set_crate_version_provenance(&admin_auth, "traced", "0.1.0", &declaration)
+            .await?;
+        assert_eq!(provenance.commit.as_deref(), Some(COMMIT));
+        assert_eq!(provenance.builder.as_deref(), Some("ci-runner"));
+        let error = application
+            .set_crate_version_provenance(&admin_auth, "traced", "0.1.0", &declaration)
+            .await
+            .unwrap_err();
+        assert_eq!(error.http, error_conflict().http);
+        Ok(())
+    })
+}
@@ -0,0 +1,233 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Tests about the proxy for the upstream registry
+
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use axum::http::{header, HeaderMap, StatusCode, Uri};
+use axum::response::{IntoResponse, Response};
+use axum::Router;
+use tokio::net::TcpListener;
+use tokio::runtime::Builder;
+
+use super::mocks::MockService;
+use crate::model::config::{ExternalRegistry, ExternalRegistryProtocol, ProxyConfig};
+use crate::services::ServiceProvider;
+use crate::utils::apierror::ApiError;
+use crate::utils::hashes::sha256;
+
+/// The requests received by a mock server, as the path and the authorization header
+type Received = Arc<Mutex<Vec<(String, Option<String>)>>>;
+
+/// Starts a mock server answering with the handler, called with the server's URI and the requested path, and recording the received requests
+async fn start_mock_server(handler: impl Fn(&str, &str) -> Response + Clone + Send + Sync + 'static) -> (String, Received) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base_uri = format!("http://{}", listener.local_addr().unwrap());
+    let received = Received::default();
+    let app = Router::new().fallback({
+        let received = received.clone();
+        let base_uri = base_uri.clone();
+        move |uri: Uri, headers: HeaderMap| async move {
+            let authorization = headers
+                .get(header::AUTHORIZATION)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string);
+            received.lock().unwrap().push((uri.path().to_string(), authorization));
+            handler(&base_uri, uri.path())
+        }
+    });
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    (base_uri, received)
+}
+
+#[test]
+fn test_proxy_disabled() -> Result<(), ApiError> {
+    let runtime = Builder::new_current_thread().enable_all().build()?;
+    runtime.block_on(async {
+        let configuration = MockService::get_configuration().await?;
+        let proxy = crate::services::proxy::get_service(&configuration)?;
+        assert!(proxy.get_index_file(None, Path::new("/se/rd/serde")).await?.is_none());
+        assert!(proxy.download_crate(None, "serde", "1.0.0").await?.is_none());
+        tokio::fs::remove_dir_all(&configuration.data_dir).await?;
+        Ok(())
+    })
+}
+
+#[test]
+fn test_proxy_rejects_non_canonical_paths() -> Result<(), ApiError> {
+    let runtime = Builder::new_current_thread().enable_all().build()?;
+    runtime.block_on(async {
+        let mut configuration = MockService::get_configuration().await?;
+        configuration.proxy = ProxyConfig {
+            enabled: true,
+            // never reached
+            index_uri: String::from("http://127.0.0.1:1/"),
+            download_uri: String::from("http://127.0.0.1:1/"),
+            ..Default::default()
+        };
+        let proxy = crate::services::proxy::get_service(&configuration)?;
+        for path in ["/config.json", "/se/rd/../../../etc/passwd", "/xx/yy/serde", "/se/rd/Serde"] {
+            assert!(proxy.get_index_file(None, Path::new(path)).await?.is_none(), "{path}");
+        }
+        assert!(proxy.download_crate(None, "../serde", "1.0.0").await?.is_none());
+        tokio::fs::remove_dir_all(&configuration.data_dir).await?;
+        Ok(())
+    })
+}
+
+#[test]
+fn test_proxy_external_registries() -> Result<(), ApiError> {
+    let runtime = Builder::new_current_thread().enable_all().build()?;
+    runtime.block_on(async {
+        let mut configuration = MockService::get_configuration().await?;
+        for (name, protocol, proxy) in [
+            ("sparse-proxied", ExternalRegistryProtocol::Sparse, true),
+            ("sparse-not-proxied", ExternalRegistryProtocol::Sparse, false),
+            ("git-proxied", ExternalRegistryProtocol::Git, true),
+        ] {
+            configuration.external_registries.push(ExternalRegistry {
+                name: name.to_string(),
+                index: String::from("http://127.0.0.1:1"),
+                protocol,
+                docs_root: String::new(),
+                login: String::new(),
+                token: String::new(),
+                proxy,
+                proxy_stale_index: 0,
+            });
+        }
+        let proxy = crate::services::proxy::get_service(&configuration)?;
+        assert!(!proxy.is_proxied(None));
+        assert!(proxy.is_proxied(Some("sparse-proxied")));
+        assert!(!proxy.is_proxied(Some("sparse-not-proxied")));
+        assert!(!proxy.is_proxied(Some("git-proxied")));
+        assert!(proxy.download_crate(Some("git-proxied"), "serde", "1.0.0").await?.is_none());
+        tokio::fs::remove_dir_all(&configuration.data_dir).await?;
+        Ok(())
+    })
+}
+
+#[test]
+fn test_proxy_downloads_from_upstream() -> Result<(), ApiError> {
+    let runtime = Builder::new_current_thread().enable_all().build()?;
+    runtime.block_on(async {
+        // another host, the crates are redirected to
+        let (cdn_uri, cdn_received) = start_mock_server(|_, _| b"redirected".to_vec().into_response()).await;
+        let (upstream_uri, upstream_received) = start_mock_server(move |base_uri, path| match path {
+            "/config.json" => format!(r#"{{"dl":"{base_uri}/{{prefix}}/dl/{{crate}}/{{version}}"}}"#).into_response(),
+            "/3/f/foo" => [
+                ("1.0.0", sha256(b"valid")),
+                ("1.0.1", sha256(b"valid")),
+                ("1.0.2", sha256(b"redirected")),
+            ]
+            .iter()
+            .map(|(version, cksum)| format!("{{\"name\":\"foo\",\"vers\":\"{version}\",\"cksum\":\"{cksum}\"}}\n"))
+            .collect::<Vec<_>>()
+            .concat()
+            .into_response(),
+            "/3/f/dl/foo/1.0.0" => b"valid".to_vec().into_response(),
+            "/3/f/dl/foo/1.0.1" => b"corrupted".to_vec().into_response(),
+            "/3/f/dl/foo/1.0.2" => {
+                (StatusCode::FOUND, [(header::LOCATION, format!("{cdn_uri}/foo-1.0.2.crate"))]).into_response()
+            }
+            _ => StatusCode::NOT_FOUND.into_response(),
+        })
+        .await;
+
+        let mut configuration = MockService::get_configuration().await?;
+        configuration.external_registries.push(ExternalRegistry {
+            name: String::from("upstream"),
+            index: upstream_uri.clone(),
+            protocol: ExternalRegistryProtocol::Sparse,
+            docs_root: String::new(),
+            login: String::from("login"),
+            token: String::from("token"),
+            proxy: true,
+            proxy_stale_index: 60_000,
+        });
+        let proxy = crate::services::proxy::get_service(&configuration)?;
+        let registry = Some("upstream");
+
+        // cache miss, then cache hit
+        let downloads = |received: &Received| {
+            received
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|(path, _)| path.starts_with("/3/f/dl/"))
+                .count()
+        };
+        assert_eq!(proxy.download_crate(registry, "foo", "1.0.0").await?, Some(b"valid".to_vec()));
+        assert_eq!(proxy.download_crate(registry, "foo", "1.0.0").await?, Some(b"valid".to_vec()));
+        assert_eq!(downloads(&upstream_received), 1);
+
+        // content not matching the checksum in the index is neither served nor cached
+        assert!(proxy.download_crate(registry, "foo", "1.0.1").await.is_err());
+        assert!(proxy.download_crate(registry, "foo", "1.0.1").await.is_err());
+        assert_eq!(downloads(&upstream_received), 3);
+
+        // unknown versions are not fetched
+        assert!(proxy.download_crate(registry, "foo", "2.0.0").await?.is_none());
+        assert_eq!(downloads(&upstream_received), 3);
+
+        // the credentials are only sent to the upstream registry
+        assert_eq!(
+            proxy.download_crate(registry, "foo", "1.0.2").await?,
+            Some(b"redirected".to_vec())
+        );
+        let expected = Some(String::from("Basic bG9naW46dG9rZW4="));
+        assert!(upstream_received.lock().unwrap().iter().all(|(_, auth)| auth == &expected));
+        let cdn_received = cdn_received.lock().unwrap().clone();
+        assert_eq!(cdn_received, vec![(String::from("/foo-1.0.2.crate"), None)]);
+
+        tokio::fs::remove_dir_all(&configuration.data_dir).await?;
+        Ok(())
+    })
+}
+
+#[test]
+fn test_proxy_external_registries_stale_index() -> Result<(), ApiError> {
+    let runtime = Builder::new_current_thread().enable_all().build()?;
+    runtime.block_on(async {
+        let (upstream_uri, received) = start_mock_server(|_, path| match path {
+            "/3/f/foo" => {
+                format!("{{\"name\":\"foo\",\"vers\":\"1.0.0\",\"cksum\":\"{}\"}}\n", sha256(b"valid")).into_response()
+            }
+            _ => StatusCode::NOT_FOUND.into_response(),
+        })
+        .await;
+        let mut configuration = MockService::get_configuration().await?;
+        for (name, proxy_stale_index) in [("fresh", 3_600_000), ("stale", 0)] {
+            configuration.external_registries.push(ExternalRegistry {
+                name: name.to_string(),
+                index: upstream_uri.clone(),
+                protocol: ExternalRegistryProtocol::Sparse,
+                docs_root: String::new(),
+                login: String::from("login"),
+                token: name.to_string(),
+                proxy: true,
+                proxy_stale_index,
+            });
+        }
+        let proxy = crate::services::proxy::get_service(&configuration)?;
+        for _ in 0..2 {
+            for name in ["fresh", "stale"] {
+                assert!(proxy.get_index_file(Some(name), Path::new("/3/f/foo")).await?.is_some());
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        // each registry uses its own credentials and its own duration before refreshing the index
+        let received = received.lock().unwrap().clone();
+        let fresh = Some(String::from("Basic bG9naW46ZnJlc2g="));
+        let stale = Some(String::from("Basic bG9naW46c3RhbGU="));
+        assert_eq!(received.iter().filter(|(_, auth)| auth == &fresh).count(), 1);
+        assert_eq!(received.iter().filter(|(_, auth)| auth == &stale).count(), 2);
+        assert_eq!(received.len(), 3);
+
+        tokio::fs::remove_dir_all(&configuration.data_dir).await?;
+        Ok(())
+    })
+}
@@ -0,0 +1,158 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Tests about the quarantine of newly published versions
+
+use std::sync::Arc;
+
+use super::{async_test, build_payload, setup_create_token, setup_create_user};
+use crate::application::Application;
+use crate::model::config::QuarantineConfig;
+use crate::utils::apierror::ApiError;
+use crate::utils::axum::auth::{AuthData, Token};
+
+#[test]
+fn test_publish_quarantine() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        let result = application
+            .publish_crate_version(&admin_auth, &build_payload("quarantined", "0.1.0"))
+            .await?;
+        assert!(result.warnings.other.is_empty());
+
+        let mut configuration = (**application.configuration.load()).clone();
+        configuration.quarantine.period = 3600;
+        application.configuration.store(Arc::new(configuration));
+
+        let result = application
+            .publish_crate_version(&admin_auth, &build_payload("quarantined", "0.2.0"))
+            .await?;
+        assert_eq!(result.warnings.other.len(), 1);
+        let versions = application.get_quarantined_versions(&admin_auth).await?;
+        assert_eq!(versions.len(), 1);
+        assert_eq!(versions[0].version, "0.2.0");
+
+        let error = application
+            .release_quarantined_version(&admin_auth, "quarantined", "0.1.0")
+            .await
+            .unwrap_err();
+        assert_eq!(error.http, 400);
+        Ok(())
+    })
+}
+
+/// Enables the quarantine and publishes a version that gets quarantined
+async fn publish_quarantined(application: &Application, auth_data: &AuthData, name: &str) -> Result<(), ApiError> {
+    let mut configuration = (**application.configuration.load()).clone();
+    configuration.quarantine.period = 3600;
+    application.configuration.store(Arc::new(configuration));
+    application
+        .publish_crate_version(auth_data, &build_payload(name, "0.1.0"))
+        .await?;
+    Ok(())
+}
+
+#[test]
+fn test_quarantine_not_released_on_checks_by_default() {
+    assert!(!QuarantineConfig::default().release_on_checks);
+}
+
+#[test]
+fn test_quarantine_release_defers_docs() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        publish_quarantined(&application, &admin_auth, "deferred").await?;
+        assert!(application.get_doc_gen_jobs(&admin_auth).await?.is_empty());
+        assert!(application.get_crate_index_file(&admin_auth, "deferred").await?.is_empty());
+
+        application
+            .release_quarantined_version(&admin_auth, "deferred", "0.1.0")
+            .await?;
+        let jobs = application.get_doc_gen_jobs(&admin_auth).await?;
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].package, "deferred");
+        assert!(application.get_quarantined_versions(&admin_auth).await?.is_empty());
+        Ok(())
+    })
+}
+
+#[test]
+fn test_quarantine_release_once() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        publish_quarantined(&application, &admin_auth, "raced").await?;
+        let (first, second) = tokio::join!(
+            application.release_quarantined_version(&admin_auth, "raced", "0.1.0"),
+            application.release_quarantined_version(&admin_auth, "raced", "0.1.0"),
+        );
+        assert!(first.is_ok() != second.is_ok());
+        assert_eq!(application.get_doc_gen_jobs(&admin_auth).await?.len(), 1);
+        Ok(())
+    })
+}
+
+#[test]
+fn test_quarantine_release_due_continues_on_error() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        publish_quarantined(&application, &admin_auth, "broken").await?;
+        publish_quarantined(&application, &admin_auth, "fine").await?;
+        application
+            .db_transaction_write("expire_quarantine", |app| async move {
+                sqlx::query("UPDATE PackageVersion SET quarantineUntil = '2000-01-01T00:00:00'")
+                    .execute(&mut *app.database.transaction.borrow().await)
+                    .await?;
+                // the content of this version is no longer in the storage
+                sqlx::query("UPDATE PackageVersion SET version = '0.1.1' WHERE package = 'broken'")
+                    .execute(&mut *app.database.transaction.borrow().await)
+                    .await?;
+                Ok::<(), ApiError>(())
+            })
+            .await?;
+        application.quarantine_release_due().await?;
+        let versions = application.get_quarantined_versions(&admin_auth).await?;
+        assert_eq!(versions.len(), 1);
+        assert_eq!(versions[0].package, "broken");
+        Ok(())
+    })
+}
+
+#[test]
+fn test_quarantine_hides_content_from_non_owners() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        application
+            .publish_crate_version(&admin_auth, &build_payload("hidden", "0.1.0"))
+            .await?;
+        let mut configuration = (**application.configuration.load()).clone();
+        configuration.quarantine.period = 3600;
+        application.configuration.store(Arc::new(configuration));
+        application
+            .publish_crate_version(&admin_auth, &build_payload("hidden", "0.2.0"))
+            .await?;
+        setup_create_user(&application, "other", "").await?;
+        let other_auth = AuthData::from(Token {
+            id: String::from("other"),
+            secret: setup_create_token(&application, 2, false, false).await?,
+        });
+
+        // the owners can read the quarantined version
+        application.get_crate_files(&admin_auth, "hidden", "0.2.0").await?;
+        // the other users can only read the released ones
+        application.get_crate_files(&other_auth, "hidden", "0.1.0").await?;
+        let error = application.get_crate_files(&other_auth, "hidden", "0.2.0").await.unwrap_err();
+        assert_eq!(error.http, 403);
+        let error = application
+            .get_crate_diff(&other_auth, "hidden", "0.1.0", "0.2.0")
+            .await
+            .unwrap_err();
+        assert_eq!(error.http, 403);
+        let error = application
+            .get_crate_binstall(&other_auth, "hidden", "0.2.0")
+            .await
+            .unwrap_err();
+        assert_eq!(error.http, 403);
+        let error = application
+            .get_crate_readme(&other_auth, "hidden", "0.2.0")
+            .await
+            .unwrap_err();
+        assert_eq!(error.http, 403);
+        Ok(())
+    })
+}
@@ -0,0 +1,181 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Tests about the quotas on the crates published by users
+
+use std::sync::Arc;
+
+use super::{async_test, build_payload, setup_create_token, setup_create_user, ADMIN_NAME};
+use crate::model::quotas::{TeamQuota, UserQuota};
+use crate::utils::apierror::ApiError;
+use crate::utils::axum::auth::{AuthData, Token};
+
+#[test]
+fn test_publish_quotas() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        application
+            .publish_crate_version(&admin_auth, &build_payload("first", "0.1.0"))
+            .await?;
+        let usage = application.get_current_user_quota(&admin_auth).await?;
+        assert_eq!(usage.crates, 1);
+        assert!(usage.bytes > 0);
+        assert_eq!(usage.max_crates, None);
+        assert_eq!(usage.max_bytes, None);
+
+        let mut configuration = (**application.configuration.load()).clone();
+        configuration.quota.max_crates = 2;
+        application.configuration.store(Arc::new(configuration));
+        application
+            .publish_crate_version(&admin_auth, &build_payload("second", "0.1.0"))
+            .await?;
+        let error = application
+            .publish_crate_version(&admin_auth, &build_payload("third", "0.1.0"))
+            .await
+            .unwrap_err();
+        assert_eq!(error.http, 403);
+        // new versions of owned crates are not limited by the number of crates
+        application
+            .publish_crate_version(&admin_auth, &build_payload("first", "0.2.0"))
+            .await?;
+
+        // the quota for the user overrides the default
+        let usage = application
+            .set_user_quota(
+                &admin_auth,
+                ADMIN_NAME,
+                &UserQuota {
+                    max_bytes: None,
+                    max_crates: Some(0),
+                },
+            )
+            .await?;
+        assert_eq!(usage.max_crates, None);
+        application
+            .publish_crate_version(&admin_auth, &build_payload("third", "0.1.0"))
+            .await?;
+
+        let usage = application.get_user_quota(&admin_auth, ADMIN_NAME).await?;
+        assert_eq!(usage.crates, 3);
+        let usage = application
+            .set_user_quota(
+                &admin_auth,
+                ADMIN_NAME,
+                &UserQuota {
+                    max_bytes: Some(usage.bytes),
+                    max_crates: None,
+                },
+            )
+            .await?;
+        assert_eq!(usage.max_crates, Some(2));
+        let error = application
+            .publish_crate_version(&admin_auth, &build_payload("first", "0.3.0"))
+            .await
+            .unwrap_err();
+        assert_eq!(error.http, 403);
+
+        let error = application
+            .set_user_quota(
+                &admin_auth,
+                ADMIN_NAME,
+                &UserQuota {
+                    max_bytes: Some(-1),
+                    max_crates: None,
+                },
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(error.http, 400);
+        Ok(())
+    })
+}
+
+#[test]
+fn test_quota_counts_versions_without_size() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        application
+            .publish_crate_version(&admin_auth, &build_payload("first", "0.1.0"))
+            .await?;
+        let expected = application.get_current_user_quota(&admin_auth).await?.bytes;
+        // as for the versions published before their size was stored
+        application
+            .db_transaction_write("test_clear_size", |app| async move {
+                sqlx::query("UPDATE PackageVersion SET size = NULL")
+                    .execute(&mut *app.database.transaction.borrow().await)
+                    .await?;
+                Ok::<(), ApiError>(())
+            })
+            .await?;
+        assert_eq!(application.get_current_user_quota(&admin_auth).await?.bytes, expected);
+        assert_eq!(application.get_user_quota(&admin_auth, ADMIN_NAME).await?.bytes, expected);
+        Ok(())
+    })
+}
+
+#[test]
+fn test_publish_team_quotas() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        let mut members = Vec::new();
+        for (uid, name) in [(2, "alice"), (3, "bob")] {
+            setup_create_user(&application, name, "core").await?;
+            members.push(AuthData::from(Token {
+                id: name.to_string(),
+                secret: setup_create_token(&application, uid, true, false).await?,
+            }));
+        }
+        let usage = application
+            .set_team_quota(
+                &admin_auth,
+                "core",
+                &TeamQuota {
+                    max_bytes: 0,
+                    max_crates: 1,
+                },
+            )
+            .await?;
+        assert_eq!(usage.team.as_deref(), Some("core"));
+        assert_eq!(usage.crates, 0);
+        assert_eq!(usage.max_bytes, None);
+        assert_eq!(usage.max_crates, Some(1));
+
+        application
+            .publish_crate_version(&members[0], &build_payload("first", "0.1.0"))
+            .await?;
+        // the crates owned by the members of the team count against its quota
+        let error = application
+            .publish_crate_version(&members[1], &build_payload("second", "0.1.0"))
+            .await
+            .unwrap_err();
+        assert_eq!(error.http, 403);
+        assert!(error.details.unwrap().contains("core"));
+        // users outside of the team are not limited
+        application
+            .publish_crate_version(&admin_auth, &build_payload("second", "0.1.0"))
+            .await?;
+        let usages = application.get_current_user_team_quotas(&members[1]).await?;
+        assert_eq!(usages.len(), 1);
+        assert_eq!(usages[0].crates, 1);
+        assert!(usages[0].bytes > 0);
+        assert!(application.get_current_user_team_quotas(&admin_auth).await?.is_empty());
+
+        // removing the quota lifts the limits
+        application.set_team_quota(&admin_auth, "core", &TeamQuota::default()).await?;
+        application
+            .publish_crate_version(&members[1], &build_payload("third", "0.1.0"))
+            .await?;
+        assert!(application.get_current_user_team_quotas(&members[1]).await?.is_empty());
+        let usage = application.get_team_quota(&admin_auth, "core").await?;
+        assert_eq!(usage.crates, 2);
+        assert_eq!(usage.max_crates, None);
+
+        // only administrators manage the quotas of teams, named as roles
+        let error = application.get_team_quota(&members[0], "core").await.unwrap_err();
+        assert_eq!(error.http, 403);
+        let error = application
+            .set_team_quota(&admin_auth, "a,b", &TeamQuota::default())
+            .await
+            .unwrap_err();
+        assert_eq!(error.http, 400);
+        Ok(())
+    })
+}
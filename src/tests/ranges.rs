@@ -0,0 +1,67 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Tests about the downloads of ranges of bytes
+
+use super::{async_test, build_payload};
+use crate::model::{ByteRange, ContentPart, CrateContent};
+use crate::utils::apierror::ApiError;
+
+#[test]
+fn test_byte_ranges() {
+    assert_eq!(ByteRange::parse("bytes=0-99"), Some(ByteRange::FromTo(0, Some(99))));
+    assert_eq!(ByteRange::parse("bytes=100-"), Some(ByteRange::FromTo(100, None)));
+    assert_eq!(ByteRange::parse("bytes=-10"), Some(ByteRange::Suffix(10)));
+    assert_eq!(ByteRange::parse("bytes=0-1,5-6"), None);
+    assert_eq!(ByteRange::parse("bytes=9-1"), None);
+    assert_eq!(ByteRange::parse("items=0-1"), None);
+
+    assert_eq!(ByteRange::FromTo(0, Some(99)).resolve(50), Some(0..50));
+    assert_eq!(ByteRange::FromTo(10, None).resolve(50), Some(10..50));
+    assert_eq!(ByteRange::FromTo(50, None).resolve(50), None);
+    assert_eq!(ByteRange::Suffix(10).resolve(50), Some(40..50));
+    assert_eq!(ByteRange::Suffix(100).resolve(50), Some(0..50));
+
+    assert!(ByteRange::FromTo(0, Some(10)).is_from_start());
+    assert!(!ByteRange::FromTo(2, Some(10)).is_from_start());
+    assert!(!ByteRange::Suffix(100).is_from_start());
+
+    let content = CrateContent::from_content((0..10).collect(), Some(ByteRange::FromTo(2, Some(4))));
+    assert_eq!(content.content, vec![2, 3, 4]);
+    assert_eq!(content.part, ContentPart::Range(2..5));
+    assert_eq!(content.total, 10);
+    let content = CrateContent::from_content(vec![0; 10], Some(ByteRange::FromTo(10, None)));
+    assert_eq!(content.part, ContentPart::Unsatisfiable);
+    assert_eq!(content.total, 10);
+}
+
+#[test]
+fn test_range_downloads_counting() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        application
+            .publish_crate_version(&admin_auth, &build_payload("ranged", "0.1.0"))
+            .await?;
+        for range in [
+            None,
+            Some(ByteRange::FromTo(0, Some(9))),
+            Some(ByteRange::FromTo(5, None)),
+            Some(ByteRange::Suffix(1_000_000)),
+            Some(ByteRange::FromTo(1_000_000, None)),
+        ] {
+            application.get_crate_content(&admin_auth, "ranged", "0.1.0", range).await?;
+        }
+        let content = application
+            .get_crate_content(&admin_auth, "ranged", "0.1.0", Some(ByteRange::FromTo(1_000_000, None)))
+            .await?;
+        assert_eq!(content.part, ContentPart::Unsatisfiable);
+        assert!(content.total > 0);
+
+        // only the whole downloads and the ranges from the start are counted
+        application.flush_downloads().await?;
+        let downloads = application.get_crate_downloads(&admin_auth, "ranged").await?;
+        let total: u32 = downloads.version_downloads.iter().map(|day| day.downloads).sum();
+        assert_eq!(total, 2);
+        Ok(())
+    })
+}
@@ -0,0 +1,141 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Tests about the limits of the rate of requests
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::body::Body;
+use axum::http::{header, Request, StatusCode};
+use axum::routing::get;
+use axum::{middleware, Router};
+use data_encoding::BASE64;
+use tokio::runtime::Builder;
+use tower::ServiceExt;
+
+use crate::utils::apierror::error_unauthorized;
+use crate::utils::axum::auth::Token;
+use crate::utils::axum::ratelimit::{rate_limit, AxumStateForRateLimits, RateLimiter};
+use crate::utils::FaillibleFuture;
+
+/// A state only knowing a single valid token
+struct MockTokens;
+
+impl AxumStateForRateLimits for MockTokens {
+    fn get_token_principal<'a>(&'a self, token: &'a Token) -> FaillibleFuture<'a, String> {
+        Box::pin(async move {
+            if token.id == "valid" && token.secret == "secret" {
+                Ok(String::from("user:1"))
+            } else {
+                Err(error_unauthorized())
+            }
+        })
+    }
+}
+
+/// Builds an application limited to a single request per minute
+fn build_app() -> Router {
+    let limiter = Arc::new(RateLimiter::new(1, Duration::from_secs(60)));
+    Router::new()
+        .route("/", get(|| async { "ok" }))
+        .layer(middleware::from_fn_with_state(
+            (limiter, Arc::new(MockTokens)),
+            rate_limit::<MockTokens>,
+        ))
+}
+
+/// Sends a request with the given token, if any
+async fn send(app: &Router, token: Option<(&str, &str)>) -> axum::response::Response {
+    let mut request = Request::builder().uri("/");
+    if let Some((id, secret)) = token {
+        let value = BASE64.encode(format!("{id}:{secret}").as_bytes());
+        request = request.header(header::AUTHORIZATION, format!("Basic {value}"));
+    }
+    app.clone().oneshot(request.body(Body::empty()).unwrap()).await.unwrap()
+}
+
+#[test]
+fn test_rate_limiter_allows_bursts_up_to_the_limit() {
+    let limiter = RateLimiter::new(3, Duration::from_secs(1));
+    let now = Instant::now();
+    for _ in 0..3 {
+        assert!(limiter.check("token:a", now).is_ok());
+    }
+    let retry_after = limiter.check("token:a", now).unwrap_err();
+    assert!(retry_after > Duration::ZERO && retry_after <= Duration::from_secs(1));
+    // other keys are not affected
+    assert!(limiter.check("ip:127.0.0.1", now).is_ok());
+    // allowed again after waiting
+    assert!(limiter.check("token:a", now + retry_after).is_ok());
+    assert!(limiter.check("token:a", now + retry_after).is_err());
+}
+
+#[test]
+fn test_rate_limiter_without_limit() {
+    let limiter = RateLimiter::new(0, Duration::from_secs(1));
+    let now = Instant::now();
+    for _ in 0..1000 {
+        assert!(limiter.check("token:a", now).is_ok());
+    }
+}
+
+#[test]
+fn test_rate_limiter_forgets_expired_generations() {
+    let limiter = RateLimiter::new(1, Duration::from_secs(1));
+    let now = Instant::now();
+    assert!(limiter.check("token:a", now).is_ok());
+    assert!(limiter.check("token:a", now).is_err());
+    // still limited after a new generation is started by another key
+    let later = now + Duration::from_millis(500);
+    assert!(limiter.check("token:b", later + Duration::from_secs(1)).is_ok());
+    assert!(limiter.check("token:a", later).is_err());
+    assert!(limiter.check("token:a", now + Duration::from_secs(1)).is_ok());
+}
+
+#[test]
+fn test_rate_limit_middleware() {
+    let runtime = Builder::new_current_thread().enable_all().build().unwrap();
+    runtime.block_on(async {
+        let app = build_app();
+        assert_eq!(send(&app, None).await.status(), StatusCode::OK);
+        let response = send(&app, None).await;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let retry_after = response.headers().get(header::RETRY_AFTER).unwrap().to_str().unwrap();
+        assert!(retry_after.parse::<u64>().unwrap() > 0);
+        // forged tokens do not get a fresh allowance
+        for index in 0..3 {
+            let secret = format!("forged-{index}");
+            let response = send(&app, Some(("forged", &secret))).await;
+            assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        }
+    });
+}
+
+#[test]
+fn test_rate_limit_middleware_principals() {
+    let runtime = Builder::new_current_thread().enable_all().build().unwrap();
+    runtime.block_on(async {
+        let app = build_app();
+        // the client address is still accounted for verified tokens
+        assert_eq!(send(&app, Some(("valid", "secret"))).await.status(), StatusCode::OK);
+        assert_eq!(send(&app, None).await.status(), StatusCode::TOO_MANY_REQUESTS);
+        let response = send(&app, Some(("valid", "secret"))).await;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(response.headers().contains_key(header::RETRY_AFTER));
+    });
+}
+
+#[test]
+fn test_rate_limiter_shared_falls_back_locally() {
+    let runtime = Builder::new_current_thread().enable_all().build().unwrap();
+    runtime.block_on(async {
+        // nothing listens on this port, the limits are applied locally
+        let client = redis::Client::open("redis://127.0.0.1:1/").unwrap();
+        let limiter = RateLimiter::new(1, Duration::from_secs(60)).with_shared(client, String::from("cratery:ratelimit:test"));
+        assert!(limiter.acquire("ip:127.0.0.1").await.is_ok());
+        assert!(limiter.acquire("ip:127.0.0.1").await.is_err());
+        assert!(limiter.acquire("ip:127.0.0.2").await.is_ok());
+    });
+}
@@ -0,0 +1,131 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Tests about the identifiers of the HTTP requests
+
+use std::sync::Arc;
+
+use axum::body::{to_bytes, Body};
+use axum::http::{Request, StatusCode};
+use axum::middleware;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::{Json, Router};
+use tokio::runtime::Builder;
+use tower::ServiceExt;
+
+use super::async_test;
+use crate::model::cargo::ApiResponseErrors;
+use crate::model::pagination::Pagination;
+use crate::utils::apierror::{error_invalid_request, ApiError};
+use crate::utils::axum::response_error;
+use crate::utils::context::{format_log_line, track_request_context, HEADER_REQUEST_ID};
+
+/// Wraps routes with the tracking of the context of the requests
+fn with_context(router: Router) -> Router {
+    router.layer(middleware::from_fn_with_state(Arc::new(Vec::new()), track_request_context))
+}
+
+/// Sends a GET request with an optional identifier given by the client, returns the identifier of the request and the body
+async fn send(app: &Router, uri: &str, client_request_id: Option<&str>) -> (String, serde_json::Value) {
+    let mut request = Request::builder().uri(uri);
+    if let Some(client_request_id) = client_request_id {
+        request = request.header(HEADER_REQUEST_ID, client_request_id);
+    }
+    let response = app.clone().oneshot(request.body(Body::empty()).unwrap()).await.unwrap();
+    let request_id = response.headers()[HEADER_REQUEST_ID].to_str().unwrap().to_string();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body = serde_json::from_slice(&body).unwrap_or_else(|_| serde_json::Value::from(String::from_utf8_lossy(&body)));
+    (request_id, body)
+}
+
+#[test]
+fn test_request_id_in_errors_and_logs() {
+    let app = with_context(
+        Router::new()
+            .route("/error", get(|| async { response_error(error_invalid_request()) }))
+            .route(
+                "/cargo-error",
+                get(|| async {
+                    (
+                        StatusCode::BAD_REQUEST,
+                        Json(ApiResponseErrors::from(error_invalid_request())),
+                    )
+                        .into_response()
+                }),
+            )
+            .route(
+                "/log",
+                get(|| async { format_log_line("[2024-01-01 00:00:00]", log::Level::Info, "published") }),
+            ),
+    );
+    let runtime = Builder::new_current_thread().enable_all().build().unwrap();
+    runtime.block_on(async move {
+        // the identifier is generated even when the client gives one
+        let (request_id, body) = send(&app, "/error", Some("client-1")).await;
+        assert_ne!(request_id, "client-1");
+        assert_eq!(body["requestId"], request_id.as_str());
+        assert_eq!(body["clientRequestId"], "client-1");
+        let (request_id, body) = send(&app, "/error", None).await;
+        assert_eq!(body["requestId"], request_id.as_str());
+        assert!(body.get("clientRequestId").is_none());
+
+        let (request_id, body) = send(&app, "/cargo-error", Some("client-2")).await;
+        assert_eq!(body["errors"][0]["requestId"], request_id.as_str());
+        assert_eq!(body["errors"][0]["clientRequestId"], "client-2");
+
+        let (request_id, body) = send(&app, "/log", None).await;
+        assert_eq!(
+            body,
+            format!("[2024-01-01 00:00:00]\tINFO\t[{request_id}] published").as_str()
+        );
+    });
+    // outside of a request
+    assert_eq!(
+        format_log_line("[2024-01-01 00:00:00]", log::Level::Info, "published"),
+        "[2024-01-01 00:00:00]\tINFO\tpublished"
+    );
+}
+
+#[test]
+fn test_request_id_in_audit_log() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        let admin_auth = Arc::new(admin_auth);
+        let app = with_context(Router::new().route(
+            "/clear",
+            get({
+                let application = application.clone();
+                let admin_auth = admin_auth.clone();
+                move || async move {
+                    application.clear_announcement(&admin_auth).await.unwrap();
+                }
+            }),
+        ));
+        let (request_id, _) = send(&app, "/clear", Some("client-3")).await;
+        send(&app, "/clear", None).await;
+
+        let pagination = Pagination::new(None, None, None);
+        let log = application.get_audit_log(&admin_auth, None, pagination).await?.items;
+        assert_eq!(log.len(), 2);
+        assert!(log[0].request_id.is_some());
+        assert!(log[0].client_request_id.is_none());
+        assert_eq!(log[1].request_id.as_deref(), Some(request_id.as_str()));
+        assert_eq!(log[1].client_request_id.as_deref(), Some("client-3"));
+
+        // the filter matches both identifiers
+        let filtered = application
+            .get_audit_log(&admin_auth, Some(&request_id), pagination)
+            .await?
+            .items;
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].id, log[1].id);
+        let filtered = application
+            .get_audit_log(&admin_auth, Some("client-3"), pagination)
+            .await?
+            .items;
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].id, log[1].id);
+        Ok(())
+    })
+}
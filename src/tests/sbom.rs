@@ -0,0 +1,207 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Tests about the SBOM of crate versions
+
+use chrono::NaiveDateTime;
+use tokio::runtime::Builder;
+
+use crate::model::cargo::{CrateMetadata, DependencyKind, IndexCrateDependency, IndexCrateMetadata};
+use crate::model::deps::{DepAdvisory, DepsAnalysis, DepsGraph, DepsGraphCrateOrigin, ResolvedDepInfo, ResolvedDepRef};
+use crate::model::osv::SimpleAdvisory;
+use crate::model::sbom::Sbom;
+use crate::utils::apierror::ApiError;
+
+fn resolved(registry: Option<&str>, package: &str, is_direct: bool, is_dev_only: bool) -> ResolvedDepInfo {
+    ResolvedDepInfo {
+        registry: registry.map(str::to_string),
+        package: package.to_string(),
+        version: String::from("1.2.3"),
+        checksum: format!("{package}-checksum"),
+        is_direct,
+        is_dev_only,
+        dependencies: Vec::new(),
+    }
+}
+
+fn upload() -> NaiveDateTime {
+    "2024-03-01T10:20:30".parse().unwrap()
+}
+
+#[test]
+fn test_sbom_from_analysis() {
+    let index = IndexCrateMetadata {
+        name: String::from("foo"),
+        vers: String::from("0.1.0"),
+        cksum: String::from("foo-checksum"),
+        ..Default::default()
+    };
+    let metadata = CrateMetadata {
+        name: String::from("foo"),
+        vers: String::from("0.1.0"),
+        license: Some(String::from("MIT OR Apache-2.0")),
+        repository: Some(String::from("https://example.com/foo")),
+        ..Default::default()
+    };
+    let mut serde = resolved(Some("https://github.com/rust-lang/crates.io-index"), "serde", true, false);
+    serde.dependencies.push(ResolvedDepRef {
+        registry: None,
+        package: String::from("bar"),
+        version: String::from("1.2.3"),
+    });
+    let analysis = DepsAnalysis {
+        direct_dependencies: Vec::new(),
+        resolved: vec![
+            serde,
+            resolved(None, "bar", false, false),
+            resolved(Some("https://github.com/rust-lang/crates.io-index"), "mockall", true, true),
+        ],
+        advisories: vec![DepAdvisory {
+            package: String::from("serde"),
+            version: "1.2.3".parse().unwrap(),
+            content: SimpleAdvisory {
+                package: String::from("serde"),
+                id: String::from("RUSTSEC-2000-0001"),
+                published: String::new(),
+                modified: String::new(),
+                summary: String::from("bad"),
+                ranges: Vec::new(),
+                versions: Vec::new(),
+            },
+        }],
+    };
+    let sbom = Sbom::new("https://registry.example.com", &index, Some(&metadata), upload(), &analysis);
+
+    let root = &sbom.metadata.component;
+    assert_eq!(
+        root.purl,
+        "pkg:cargo/foo@0.1.0?repository_url=https%3A%2F%2Fregistry.example.com"
+    );
+    assert_eq!(root.licenses[0].expression, "MIT OR Apache-2.0");
+    assert_eq!(root.hashes[0].content, "foo-checksum");
+    assert!(root.external_references.iter().any(|r| r.kind == "vcs"));
+
+    assert_eq!(sbom.components.len(), 3);
+    assert_eq!(sbom.components[0].purl, "pkg:cargo/serde@1.2.3");
+    assert_eq!(
+        sbom.components[1].purl,
+        "pkg:cargo/bar@1.2.3?repository_url=https%3A%2F%2Fregistry.example.com"
+    );
+    assert_eq!(sbom.components[2].scope.as_deref(), Some("excluded"));
+
+    assert_eq!(sbom.dependencies.len(), 4);
+    assert_eq!(
+        sbom.dependencies[0].depends_on,
+        vec![String::from("pkg:cargo/serde@1.2.3"), String::from("pkg:cargo/mockall@1.2.3")]
+    );
+    // the transitive edges
+    assert_eq!(sbom.dependencies[1].reference, "pkg:cargo/serde@1.2.3");
+    assert_eq!(
+        sbom.dependencies[1].depends_on,
+        vec![String::from(
+            "pkg:cargo/bar@1.2.3?repository_url=https%3A%2F%2Fregistry.example.com"
+        )]
+    );
+    assert!(sbom.dependencies[2].depends_on.is_empty());
+    assert_eq!(sbom.vulnerabilities.len(), 1);
+    assert_eq!(sbom.vulnerabilities[0].affects[0].reference, "pkg:cargo/serde@1.2.3");
+}
+
+#[test]
+fn test_sbom_is_deterministic() {
+    let index = IndexCrateMetadata {
+        name: String::from("foo"),
+        vers: String::from("0.1.0"),
+        cksum: String::from("foo-checksum"),
+        ..Default::default()
+    };
+    let analysis = DepsAnalysis::default();
+    let first = Sbom::new("https://registry.example.com", &index, None, upload(), &analysis);
+    let second = Sbom::new("https://registry.example.com", &index, None, upload(), &analysis);
+    assert_eq!(first.serial_number, second.serial_number);
+    assert_eq!(first.metadata.timestamp, "2024-03-01T10:20:30Z");
+    assert_eq!(first.metadata.timestamp, second.metadata.timestamp);
+
+    let other = IndexCrateMetadata {
+        vers: String::from("0.2.0"),
+        ..index
+    };
+    let other = Sbom::new("https://registry.example.com", &other, None, upload(), &analysis);
+    assert_ne!(first.serial_number, other.serial_number);
+}
+
+/// Builds the metadata of a crate version with its dependencies
+fn crate_version(name: &str, vers: &str, deps: &[(&str, &str, DependencyKind)]) -> IndexCrateMetadata {
+    IndexCrateMetadata {
+        name: name.to_string(),
+        vers: vers.to_string(),
+        cksum: format!("{name}-{vers}"),
+        deps: deps
+            .iter()
+            .map(|&(name, req, kind)| IndexCrateDependency {
+                name: name.to_string(),
+                req: req.to_string(),
+                kind,
+                registry: Some(String::from("reg")),
+                ..Default::default()
+            })
+            .collect(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_resolved_transitive_dependencies() -> Result<(), ApiError> {
+    let get_versions = |_registry: Option<String>, name: String| async move {
+        Ok(match name.as_str() {
+            "a" => vec![
+                crate_version("a", "1.0.0", &[("b", "^1", DependencyKind::Normal)]),
+                crate_version(
+                    "a",
+                    "1.1.0",
+                    &[("b", "^1", DependencyKind::Normal), ("c", "^2", DependencyKind::Dev)],
+                ),
+            ],
+            "b" => vec![crate_version("b", "1.0.0", &[]), crate_version("b", "1.2.0", &[])],
+            _ => vec![crate_version(&name, "2.0.0", &[])],
+        })
+    };
+    let direct = IndexCrateDependency {
+        name: String::from("a"),
+        req: String::from("^1"),
+        registry: Some(String::from("reg")),
+        ..Default::default()
+    };
+    let runtime = Builder::new_current_thread().enable_all().build()?;
+    let analysis = runtime.block_on(async {
+        let mut graph = DepsGraph::new(&[]);
+        graph
+            .resolve(
+                &direct,
+                &[],
+                &[DepsGraphCrateOrigin::Direct(DependencyKind::Normal)],
+                &get_versions,
+            )
+            .await?;
+        graph.close(&get_versions).await?;
+        Ok::<_, ApiError>(DepsAnalysis::new(&graph, std::slice::from_ref(&direct), Vec::new()))
+    })?;
+
+    let a = analysis.resolved.iter().find(|dep| dep.package == "a").unwrap();
+    assert_eq!(a.version, "1.1.0");
+    // the development dependencies of the dependencies are not in the closure
+    assert_eq!(
+        a.dependencies,
+        vec![ResolvedDepRef {
+            registry: Some(String::from("reg")),
+            package: String::from("b"),
+            version: String::from("1.2.0"),
+        }]
+    );
+    let b = analysis.resolved.iter().find(|dep| dep.package == "b").unwrap();
+    assert!(!b.is_direct);
+    assert!(b.dependencies.is_empty());
+    assert!(analysis.resolved.iter().all(|dep| dep.package != "c"));
+    Ok(())
+}
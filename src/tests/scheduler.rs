@@ -0,0 +1,167 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Tests about the scheduler of the background jobs
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::runtime::Builder;
+use tokio::sync::Notify;
+use tokio::task::JoinSet;
+
+use super::async_test;
+use crate::utils::apierror::{error_conflict, ApiError};
+use crate::utils::scheduler::{period_from_secs, FirstRun, Scheduler};
+
+#[test]
+fn test_scheduler_on_demand() {
+    let runtime = Builder::new_current_thread().enable_all().build().unwrap();
+    runtime.block_on(async move {
+        let scheduler = Arc::new(Scheduler::default());
+        let counter = Arc::new(AtomicUsize::new(0));
+        let done = Arc::new(Notify::new());
+        let job_counter = counter.clone();
+        let job_done = done.clone();
+        scheduler.schedule(
+            "count",
+            "Counts its runs",
+            true,
+            None,
+            FirstRun::OnStart,
+            Box::new(move || {
+                let counter = job_counter.clone();
+                let done = job_done.clone();
+                Box::pin(async move {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    done.notify_one();
+                    Ok(())
+                })
+            }),
+        );
+        let status = &scheduler.get_statuses()[0];
+        assert_eq!(status.period, None);
+        assert_eq!(status.next_run_on, None);
+
+        scheduler.trigger("count").unwrap();
+        done.notified().await;
+        // on the current thread runtime, the status is updated before the test is resumed
+        tokio::task::yield_now().await;
+        assert_eq!(counter.load(Ordering::SeqCst), 1);
+        let status = &scheduler.get_statuses()[0];
+        assert_eq!(status.runs, 1);
+        assert!(!status.is_running);
+        assert!(status.last_finished_on.is_some());
+
+        assert_eq!(scheduler.trigger("unknown").unwrap_err().http, 404);
+    });
+}
+
+#[test]
+fn test_scheduler_disabled_job() {
+    let runtime = Builder::new_current_thread().enable_all().build().unwrap();
+    runtime.block_on(async move {
+        let scheduler = Arc::new(Scheduler::default());
+        scheduler.schedule(
+            "disabled",
+            "Is not activated",
+            false,
+            Some(Duration::from_secs(1)),
+            FirstRun::OnStart,
+            Box::new(|| Box::pin(async { Ok(()) })),
+        );
+        let status = &scheduler.get_statuses()[0];
+        assert!(!status.enabled);
+        assert_eq!(status.period, None);
+        assert_eq!(status.next_run_on, None);
+        assert_eq!(scheduler.trigger("disabled").unwrap_err().http, 400);
+
+        // a period of 0 seconds means that the job is only run on demand
+        assert_eq!(period_from_secs(0), None);
+        assert_eq!(period_from_secs(30), Some(Duration::from_secs(30)));
+        scheduler.schedule(
+            "zero",
+            "Has a period of 0",
+            true,
+            period_from_secs(0),
+            FirstRun::OnStart,
+            Box::new(|| Box::pin(async { Ok(()) })),
+        );
+        let status = &scheduler.get_statuses()[1];
+        assert_eq!(status.period, None);
+        assert_eq!(status.next_run_on, None);
+    });
+}
+
+#[test]
+fn test_scheduler_stop_waits_for_running_jobs() {
+    let runtime = Builder::new_current_thread().enable_all().build().unwrap();
+    runtime.block_on(async move {
+        let scheduler = Arc::new(Scheduler::default());
+        let counter = Arc::new(AtomicUsize::new(0));
+        let started = Arc::new(Notify::new());
+        let release = Arc::new(Notify::new());
+        let (job_counter, job_started, job_release) = (counter.clone(), started.clone(), release.clone());
+        scheduler.schedule(
+            "slow",
+            "Waits to be released",
+            true,
+            None,
+            FirstRun::OnStart,
+            Box::new(move || {
+                let (counter, started, release) = (job_counter.clone(), job_started.clone(), job_release.clone());
+                Box::pin(async move {
+                    started.notify_one();
+                    release.notified().await;
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    Ok(())
+                })
+            }),
+        );
+        scheduler.trigger("slow").unwrap();
+        started.notified().await;
+        assert!(scheduler.get_statuses()[0].is_running);
+
+        let mut stopping = JoinSet::new();
+        stopping.spawn({
+            let scheduler = scheduler.clone();
+            async move { scheduler.stop().await }
+        });
+        tokio::task::yield_now().await;
+        // still waiting for the running job
+        assert_eq!(counter.load(Ordering::SeqCst), 0);
+        assert!(stopping.try_join_next().is_none());
+
+        release.notify_one();
+        stopping.join_next().await.unwrap().unwrap();
+        assert_eq!(counter.load(Ordering::SeqCst), 1);
+        assert!(!scheduler.get_statuses()[0].is_running);
+
+        // no new run after the stop
+        assert_eq!(scheduler.trigger("slow").unwrap_err().http, error_conflict().http);
+    });
+}
+
+#[test]
+fn test_scheduled_jobs_registered() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        let jobs = application.get_scheduled_jobs(&admin_auth).await?;
+        for name in [
+            "backup",
+            "advisories",
+            "deps",
+            "confusion",
+            "mirror",
+            "search",
+            "checksums",
+            "quarantine",
+        ] {
+            assert!(jobs.iter().any(|job| job.name == name), "missing job {name}");
+        }
+        let error = application.run_scheduled_job(&admin_auth, "unknown").await.unwrap_err();
+        assert_eq!(error.http, 404);
+        Ok(())
+    })
+}
@@ -0,0 +1,168 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Tests about the full-text search
+
+use std::sync::Arc;
+
+use tokio::runtime::Builder;
+
+use super::mocks::MockService;
+use super::{async_test, build_payload, ADMIN_NAME};
+use crate::model::search::{CrateSearchDocument, SearchFilters, SearchPagination};
+use crate::services::search::Search;
+use crate::services::ServiceProvider;
+use crate::utils::apierror::ApiError;
+
+/// Creates a search service with some indexed crates
+async fn setup_search() -> Result<(Arc<dyn Search + Send + Sync>, String), ApiError> {
+    let configuration = MockService::get_configuration().await?;
+    let service = MockService::get_search(&configuration)?;
+    for (name, description) in [
+        ("my_util", "Utilities for my projects"),
+        ("serde-helpers", "Helpers for serialization"),
+        ("parser", "A parser for configuration files"),
+    ] {
+        service
+            .index_crate(&CrateSearchDocument {
+                name: name.to_string(),
+                description: description.to_string(),
+                keywords: Vec::new(),
+                categories: Vec::new(),
+                readme: String::new(),
+            })
+            .await?;
+    }
+    Ok((service, configuration.data_dir))
+}
+
+/// Searches and returns the names of the matching crates
+async fn search_names(service: &(dyn Search + Send + Sync), query: &str) -> Result<Vec<String>, ApiError> {
+    let hits = service.search(query, &SearchFilters::default()).await?;
+    Ok(hits.into_iter().map(|hit| hit.name).collect())
+}
+
+#[test]
+fn test_search_hyphen_underscore() -> Result<(), ApiError> {
+    let runtime = Builder::new_current_thread().enable_all().build()?;
+    runtime.block_on(async {
+        let (service, data_dir) = setup_search().await?;
+        assert_eq!(
+            Some("my_util"),
+            search_names(service.as_ref(), "my-util").await?.first().map(String::as_str)
+        );
+        assert_eq!(
+            Some("serde-helpers"),
+            search_names(service.as_ref(), "serde_helpers")
+                .await?
+                .first()
+                .map(String::as_str)
+        );
+        tokio::fs::remove_dir_all(&data_dir).await?;
+        Ok(())
+    })
+}
+
+#[test]
+fn test_search_typos() -> Result<(), ApiError> {
+    let runtime = Builder::new_current_thread().enable_all().build()?;
+    runtime.block_on(async {
+        let (service, data_dir) = setup_search().await?;
+        assert_eq!(
+            Some("my_util"),
+            search_names(service.as_ref(), "my_utl").await?.first().map(String::as_str)
+        );
+        assert_eq!(
+            Some("parser"),
+            search_names(service.as_ref(), "parsre").await?.first().map(String::as_str)
+        );
+        tokio::fs::remove_dir_all(&data_dir).await?;
+        Ok(())
+    })
+}
+
+#[test]
+fn test_suggest_prefix() -> Result<(), ApiError> {
+    let runtime = Builder::new_current_thread().enable_all().build()?;
+    runtime.block_on(async {
+        let (service, data_dir) = setup_search().await?;
+        assert_eq!(vec![String::from("serde-helpers")], service.suggest("serde_", 10).await?);
+        assert_eq!(vec![String::from("my_util")], service.suggest("My", 10).await?);
+        assert!(service.suggest("unknown", 10).await?.is_empty());
+        tokio::fs::remove_dir_all(&data_dir).await?;
+        Ok(())
+    })
+}
+
+#[test]
+fn test_search_rebuild() -> Result<(), ApiError> {
+    let runtime = Builder::new_current_thread().enable_all().build()?;
+    runtime.block_on(async {
+        let (service, data_dir) = setup_search().await?;
+        service
+            .rebuild(vec![CrateSearchDocument {
+                name: String::from("tokenizer"),
+                description: String::from("Splits text into tokens"),
+                keywords: Vec::new(),
+                categories: Vec::new(),
+                readme: String::new(),
+            }])
+            .await?;
+        assert_eq!(
+            vec![String::from("tokenizer")],
+            search_names(service.as_ref(), "tokenizer").await?
+        );
+        assert!(search_names(service.as_ref(), "parser").await?.is_empty());
+        tokio::fs::remove_dir_all(&data_dir).await?;
+        Ok(())
+    })
+}
+
+#[test]
+fn test_search_crates_batched() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        for name in ["alpha", "beta", "gamma"] {
+            application
+                .publish_crate_version(&admin_auth, &build_payload(name, "0.1.0"))
+                .await?;
+        }
+        application
+            .publish_crate_version(&admin_auth, &build_payload("alpha", "0.2.0"))
+            .await?;
+        let results = application
+            .search_crates(
+                &admin_auth,
+                "alpha",
+                &SearchFilters::default(),
+                SearchPagination::new(None, None, None),
+            )
+            .await?;
+        assert_eq!(results.meta.total, 1);
+        assert_eq!(results.crates[0].name, "alpha");
+        assert_eq!(results.crates[0].max_version, "0.2.0");
+        assert_eq!(results.meta.facets.owners[0].value, ADMIN_NAME);
+
+        // an empty query requires a filter
+        let error = application
+            .search_crates(
+                &admin_auth,
+                " ",
+                &SearchFilters::default(),
+                SearchPagination::new(None, None, None),
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(error.http, 400);
+        let filters = SearchFilters {
+            owner: Some(String::from(ADMIN_NAME)),
+            ..Default::default()
+        };
+        let results = application
+            .search_crates(&admin_auth, "", &filters, SearchPagination::new(None, None, None))
+            .await?;
+        assert_eq!(results.meta.total, 3);
+        assert_eq!(results.meta.facets.owners[0].count, 3);
+        Ok(())
+    })
+}
@@ -4,9 +4,17 @@
 
 //! Tests about security checks
 
-use super::{async_test, setup_create_user_inactive};
+use super::{async_test, build_payload, setup_create_user_inactive};
 use crate::application::Application;
-use crate::model::auth::ROLE_ADMIN;
+use crate::model::audit::AuditLogEntry;
+use crate::model::auth::{
+    ImpersonationRequest, ProvisioningRule, RegistryUserToken, PROVISIONING_DOMAIN_ANY, ROLE_ADMIN, ROLE_READONLY,
+};
+use crate::model::cargo::CrateUploadData;
+use crate::model::config::MaintenanceConfig;
+use crate::model::pagination::{AuditLogSort, Page, Pagination, TokenSort, UserSort};
+use crate::model::quotas::UserQuota;
+use crate::model::webhooks::{WebhookCreateQuery, WebhookEvent};
 use crate::tests::{setup_create_token, setup_create_user, ADMIN_NAME, ADMIN_UID};
 use crate::utils::apierror::ApiError;
 use crate::utils::axum::auth::{AuthData, Token};
@@ -108,18 +116,318 @@ fn test_get_get_current_user() -> Result<(), ApiError> {
 #[test]
 fn test_get_users_admin_only() -> Result<(), ApiError> {
     async_test(|application, admin_auth| async move {
-        assert!(application.get_users(&AuthData::default()).await.is_err());
-        assert!(application.get_users(&admin_auth).await.is_ok());
+        let pagination = Pagination::new(None, None, None);
+        assert!(application.get_users(&AuthData::default(), None, pagination).await.is_err());
+        assert!(application.get_users(&admin_auth, None, pagination).await.is_ok());
         // test user without admin
         assert!(application
-            .get_users(&create_auth_user_ro(&application).await?)
+            .get_users(&create_auth_user_ro(&application).await?, None, pagination)
             .await
             .is_err());
         // test admin in read-only
         assert!(application
-            .get_users(&create_auth_admin_ro(&application).await?)
+            .get_users(&create_auth_admin_ro(&application).await?, None, pagination)
             .await
             .is_err());
         Ok(())
     })
 }
+
+#[test]
+fn test_get_advisories_report_admin_only() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        assert!(application.get_advisories_report(&AuthData::default()).await.is_err());
+        let report = application.get_advisories_report(&admin_auth).await?;
+        assert!(report.last_sync.is_none());
+        assert!(report.advisories.is_empty());
+        assert!(application
+            .get_advisories_report(&create_auth_user_ro(&application).await?)
+            .await
+            .is_err());
+        Ok(())
+    })
+}
+
+#[test]
+fn test_get_users_pagination() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        setup_create_user(&application, "bob", "").await?;
+        setup_create_user(&application, "carol", "").await?;
+        let page = application
+            .get_users(&admin_auth, None, Pagination::new(Some(2), Some(2), None))
+            .await?;
+        assert_eq!(page.meta.total, 3);
+        assert_eq!(page.items.len(), 1);
+        assert_eq!(page.items[0].login, "carol");
+        let page = application
+            .get_users(&admin_auth, Some("BO"), Pagination::new(None, None, Some(UserSort::Email)))
+            .await?;
+        assert_eq!(page.meta.total, 1);
+        assert_eq!(page.items[0].login, "bob");
+        Ok(())
+    })
+}
+
+#[test]
+fn test_get_global_tokens_pagination() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        for name in ["beta", "alpha", "gamma", "alphabet"] {
+            application.create_global_token(&admin_auth, name).await?;
+        }
+        let names = |page: Page<RegistryUserToken>| page.items.into_iter().map(|token| token.name).collect::<Vec<_>>();
+        let page = application
+            .get_global_tokens(&admin_auth, None, Pagination::new(Some(2), Some(3), None))
+            .await?;
+        assert_eq!(page.meta.total, 4);
+        assert_eq!(names(page), vec!["alphabet"]);
+        let page = application
+            .get_global_tokens(&admin_auth, None, Pagination::new(None, None, Some(TokenSort::Name)))
+            .await?;
+        assert_eq!(names(page), vec!["alpha", "alphabet", "beta", "gamma"]);
+        let page = application
+            .get_global_tokens(
+                &admin_auth,
+                Some("ALPHA"),
+                Pagination::new(None, None, Some(TokenSort::Newest)),
+            )
+            .await?;
+        assert_eq!(page.meta.total, 2);
+        assert_eq!(names(page), vec!["alphabet", "alpha"]);
+        Ok(())
+    })
+}
+
+#[test]
+fn test_get_audit_log_order() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        for _ in 0..3 {
+            application
+                .set_user_quota(&admin_auth, ADMIN_NAME, &UserQuota::default())
+                .await?;
+        }
+        let ids = |page: Page<AuditLogEntry>| page.items.into_iter().map(|entry| entry.id).collect::<Vec<_>>();
+        // the most recent first by default
+        let newest = ids(application.get_audit_log(&admin_auth, None, Pagination::first(2)).await?);
+        assert_eq!(newest.len(), 2);
+        assert!(newest[0] > newest[1]);
+        let all = ids(application.get_audit_log(&admin_auth, None, Pagination::all()).await?);
+        assert_eq!(&all[..2], &newest[..]);
+        let oldest = ids(application
+            .get_audit_log(
+                &admin_auth,
+                None,
+                Pagination::new(None, Some(100), Some(AuditLogSort::Oldest)),
+            )
+            .await?);
+        assert_eq!(oldest, all.into_iter().rev().collect::<Vec<_>>());
+        Ok(())
+    })
+}
+
+#[test]
+fn test_impersonation() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        let user_auth = create_auth_user_ro(&application).await?;
+        let request = ImpersonationRequest {
+            can_write: true,
+            duration_minutes: 10,
+            reason: String::from("troubleshooting"),
+        };
+        // only admins can impersonate
+        assert!(application.impersonate_user(&user_auth, ADMIN_NAME, &request).await.is_err());
+        let token = application.impersonate_user(&admin_auth, USER_NAME, &request).await?;
+        let impersonated_auth = AuthData::from(Token {
+            id: token.login.clone(),
+            secret: token.secret.clone(),
+        });
+        let authentication = application.authenticate(&impersonated_auth).await?;
+        assert_eq!(USER_UID, authentication.uid()?);
+        assert_eq!(Some(ADMIN_UID), authentication.impersonator);
+        assert!(authentication.can_write);
+        assert!(!authentication.can_admin);
+        // writes while impersonating are audited
+        let user = application.get_current_user(&impersonated_auth).await?;
+        application.update_user(&impersonated_auth, &user).await?;
+        let pagination = Pagination::new(None, None, None);
+        let log = application.get_audit_log(&admin_auth, None, pagination).await?.items;
+        assert_eq!(log[0].action, "update_user");
+        assert_eq!(log[0].actor, Some(USER_UID));
+        assert_eq!(log[0].impersonator, Some(ADMIN_UID));
+        assert!(application.get_audit_log(&user_auth, None, pagination).await.is_err());
+        // ending the impersonation revokes the token
+        application.end_impersonation(&admin_auth, token.id).await?;
+        assert!(application.authenticate(&impersonated_auth).await.is_err());
+        Ok(())
+    })
+}
+
+#[test]
+fn test_maintenance_read_only() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        let user_auth = create_auth_user_ro(&application).await?;
+        application
+            .publish_crate_version(&admin_auth, &build_payload("maintained", "0.1.0"))
+            .await?;
+        let maintenance = MaintenanceConfig {
+            enabled: true,
+            message: Some(String::from("backup in progress")),
+        };
+        assert!(application.set_maintenance(&user_auth, maintenance.clone()).await.is_err());
+        application.set_maintenance(&admin_auth, maintenance).await?;
+        // reads are still possible
+        let user = application.get_current_user(&admin_auth).await?;
+        // writes are rejected
+        let error = application.update_user(&admin_auth, &user).await.unwrap_err();
+        assert_eq!(503, error.http);
+        assert_eq!(Some("backup in progress"), error.details.as_deref());
+        let error = application
+            .upload_crate_binary(&admin_auth, "maintained", "0.1.0", "x86_64-unknown-linux-gnu", b"v1".to_vec())
+            .await
+            .unwrap_err();
+        assert_eq!(503, error.http);
+        application.set_maintenance(&admin_auth, MaintenanceConfig::default()).await?;
+        application.update_user(&admin_auth, &user).await?;
+        // the changes of the maintenance mode are audited
+        let pagination = Pagination::new(None, None, None);
+        let log = application
+            .get_audit_log(&admin_auth, Some("set_maintenance"), pagination)
+            .await?
+            .items;
+        let details = log.iter().map(|entry| entry.details.as_str()).collect::<Vec<_>>();
+        assert_eq!(details, vec!["disabled", "enabled: backup in progress"]);
+        Ok(())
+    })
+}
+
+#[test]
+fn test_get_registry_stats_needs_admin() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        let user_auth = create_auth_user_ro(&application).await?;
+        assert!(application.get_registry_stats(&user_auth).await.is_err());
+        let stats = application.get_registry_stats(&admin_auth).await?;
+        assert_eq!(2, stats.total_users);
+        assert_eq!(2, stats.total_tokens);
+        assert_eq!(0, stats.total_versions);
+        assert_eq!(stats.days.len(), stats.publishes_per_day.len());
+        Ok(())
+    })
+}
+
+#[test]
+fn test_get_storage_usage() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        let mut sizes = Vec::new();
+        for (name, version) in [("stored", "0.1.0"), ("stored", "0.2.0"), ("other", "0.1.0")] {
+            let payload = build_payload(name, version);
+            sizes.push(i64::try_from(CrateUploadData::new(&payload)?.content.len()).unwrap());
+            application.publish_crate_version(&admin_auth, &payload).await?;
+        }
+        let user_auth = create_auth_user_ro(&application).await?;
+        assert!(application.get_storage_usage(&user_auth).await.is_err());
+        let usage = application.get_storage_usage(&admin_auth).await?;
+        let stored = usage.crates.iter().find(|usage| usage.package == "stored").unwrap();
+        assert_eq!(stored.versions, 2);
+        assert_eq!(stored.crates_size, sizes[0] + sizes[1]);
+        assert_eq!(stored.docs_size, 0);
+        assert_eq!(usage.crates.len(), 2);
+        assert_eq!(usage.users.len(), 1);
+        assert_eq!(usage.users[0].user.login, ADMIN_NAME);
+        assert_eq!(usage.users[0].versions, 3);
+        assert_eq!(usage.users[0].crates_size, sizes.iter().sum::<i64>());
+        Ok(())
+    })
+}
+
+#[test]
+fn test_get_publish_activity_needs_admin() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        let user_auth = create_auth_user_ro(&application).await?;
+        assert!(application.get_publish_activity(&user_auth).await.is_err());
+        let activity = application.get_publish_activity(&admin_auth).await?;
+        assert_eq!(2, activity.users.len());
+        assert!(activity
+            .users
+            .iter()
+            .all(|user| user.publishes == 0 && user.last_publish.is_none()));
+        assert!(activity.crates_without_active_owner.is_empty());
+        Ok(())
+    })
+}
+
+#[test]
+fn test_get_traffic_report_needs_admin() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        let user_auth = create_auth_user_ro(&application).await?;
+        assert!(application.get_traffic_report(&user_auth).await.is_err());
+        let report = application.get_traffic_report(&admin_auth).await?;
+        assert_eq!(2, report.users.len());
+        assert_eq!(2, report.tokens.len());
+        Ok(())
+    })
+}
+
+#[test]
+fn test_registry_webhook_needs_admin() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        let user_auth = create_auth_user_ro(&application).await?;
+        let query = WebhookCreateQuery {
+            package: None,
+            url: String::from("https://hooks.example.com/cratery"),
+            events: vec![WebhookEvent::Publish, WebhookEvent::Yank],
+        };
+        assert!(application.create_webhook(&user_auth, &query).await.is_err());
+        let webhook = application.create_webhook(&admin_auth, &query).await?;
+        assert!(!webhook.secret.is_empty());
+        assert!(application.get_webhooks(&user_auth).await?.is_empty());
+        assert_eq!(1, application.get_webhooks(&admin_auth).await?.len());
+        assert!(application.delete_webhook(&user_auth, webhook.webhook.id).await.is_err());
+        application.delete_webhook(&admin_auth, webhook.webhook.id).await?;
+        assert!(application.get_webhooks(&admin_auth).await?.is_empty());
+        Ok(())
+    })
+}
+
+#[test]
+fn test_provisioning_rules_resolution() {
+    let rules = [
+        ProvisioningRule {
+            domain: String::from("acme.com"),
+            roles: String::new(),
+        },
+        ProvisioningRule {
+            domain: String::from("contractors.acme.com"),
+            roles: String::from(ROLE_READONLY),
+        },
+        ProvisioningRule {
+            domain: String::from(PROVISIONING_DOMAIN_ANY),
+            roles: String::from(ROLE_READONLY),
+        },
+    ];
+    assert_eq!(Some(""), ProvisioningRule::resolve_roles(&rules, "john@acme.com"));
+    assert_eq!(Some(""), ProvisioningRule::resolve_roles(&rules, "john@eu.acme.com"));
+    assert_eq!(
+        Some(ROLE_READONLY),
+        ProvisioningRule::resolve_roles(&rules, "jane@contractors.acme.com")
+    );
+    assert_eq!(
+        Some(ROLE_READONLY),
+        ProvisioningRule::resolve_roles(&rules, "jane@notacme.com")
+    );
+    assert_eq!(None, ProvisioningRule::resolve_roles(&rules[..2], "jane@other.org"));
+}
+
+#[test]
+fn test_readonly_role_cannot_write() -> Result<(), ApiError> {
+    async_test(|application, _admin_auth| async move {
+        setup_create_user(&application, USER_NAME, ROLE_READONLY).await?;
+        let token = setup_create_token(&application, USER_UID, true, false).await?;
+        let authentication = application
+            .authenticate(&AuthData::from(Token {
+                id: USER_NAME.to_string(),
+                secret: token,
+            }))
+            .await?;
+        assert!(!authentication.can_write);
+        Ok(())
+    })
+}
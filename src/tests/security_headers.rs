@@ -0,0 +1,85 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Tests about the security headers applied to the responses
+
+use std::sync::Arc;
+
+use axum::body::Body;
+use axum::http::{header, HeaderValue, Request};
+use axum::middleware;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::Router;
+use tokio::runtime::Builder;
+use tower::ServiceExt;
+
+use crate::model::config::{with_tenant, SecurityHeadersConfig};
+use crate::utils::axum::security::{security_headers, SecurityHeaders};
+
+#[test]
+fn test_security_headers() {
+    let config = SecurityHeadersConfig {
+        hsts: None,
+        frame_options: Some(String::from("DENY")),
+        content_security_policy: Some(String::from("default-src 'self'")),
+        docs_content_security_policy: Some(String::from("connect-src 'none'")),
+        no_sniff: true,
+    };
+    let app = Router::new()
+        .route("/api", get(|| async { "ok" }))
+        .route("/docs/index.html", get(|| async { "docs" }))
+        .route(
+            "/framed",
+            get(|| async { ([(header::X_FRAME_OPTIONS, HeaderValue::from_static("SAMEORIGIN"))], "framed").into_response() }),
+        )
+        .layer(middleware::from_fn_with_state(
+            Arc::new(SecurityHeaders::new(&config)),
+            security_headers,
+        ));
+    let runtime = Builder::new_current_thread().enable_all().build().unwrap();
+    runtime.block_on(async move {
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri("/api").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let headers = response.headers();
+        assert!(!headers.contains_key(header::STRICT_TRANSPORT_SECURITY));
+        assert_eq!(headers[header::X_FRAME_OPTIONS], "DENY");
+        assert_eq!(headers[header::X_CONTENT_TYPE_OPTIONS], "nosniff");
+        assert_eq!(headers[header::CONTENT_SECURITY_POLICY], "default-src 'self'");
+
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri("/docs/index.html").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.headers()[header::CONTENT_SECURITY_POLICY], "connect-src 'none'");
+
+        let response = app
+            .oneshot(Request::builder().uri("/framed").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.headers()[header::X_FRAME_OPTIONS], "SAMEORIGIN");
+    });
+}
+
+#[tokio::test]
+async fn test_security_headers_from_env() {
+    let id = uuid::Uuid::new_v4().simple().to_string();
+    let tenant = format!("headers-{id}");
+    let name = format!("REGISTRY_TENANT_HEADERS_{}_WEB_FRAME_OPTIONS", id.to_uppercase());
+    std::env::set_var(&name, "DENY");
+    let config = with_tenant(Some(&tenant), async { SecurityHeadersConfig::from_env() })
+        .await
+        .unwrap();
+    assert_eq!(config.frame_options.as_deref(), Some("DENY"));
+
+    // invalid values are reported instead of panicking
+    std::env::set_var(&name, "DENY\n");
+    let result = with_tenant(Some(&tenant), async { SecurityHeadersConfig::from_env() }).await;
+    assert_eq!(result.unwrap_err().http, 400);
+    std::env::remove_var(&name);
+}
@@ -0,0 +1,128 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Tests about browsing the files in `.crate` archives
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use tar::{Builder, EntryType, Header};
+
+use crate::model::binstall::{extract_binstall_metadata, validate_binary_target};
+use crate::model::sources::{
+    diff_crates, extract_crate_file, list_crate_files, CrateFile, CrateFileChange, CRATE_FILE_MAX_SIZE, CRATE_UNPACKED_MAX_SIZE,
+};
+
+/// Builds a `.crate` archive with the given files
+fn build_crate(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut builder = Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+    for (path, data) in files {
+        let mut header = Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_entry_type(EntryType::Regular);
+        builder.append_data(&mut header, path, *data).unwrap();
+    }
+    builder.into_inner().unwrap().finish().unwrap()
+}
+
+#[test]
+fn test_crate_files() {
+    let large = vec![b'a'; usize::try_from(CRATE_FILE_MAX_SIZE).unwrap() + 1];
+    let content = build_crate(&[
+        ("foo-1.0.0/src/lib.rs", b"pub fn foo() {}\n"),
+        ("foo-1.0.0/Cargo.toml", b"[package]\nname = \"foo\"\n"),
+        ("foo-1.0.0/large.txt", &large),
+    ]);
+    let files = list_crate_files(&content, "foo", "1.0.0").unwrap();
+    assert_eq!(
+        files,
+        vec![
+            CrateFile {
+                path: String::from("Cargo.toml"),
+                size: 23,
+            },
+            CrateFile {
+                path: String::from("large.txt"),
+                size: CRATE_FILE_MAX_SIZE + 1,
+            },
+            CrateFile {
+                path: String::from("src/lib.rs"),
+                size: 16,
+            },
+        ]
+    );
+    assert_eq!(
+        extract_crate_file(&content, "foo", "1.0.0", "src/lib.rs").unwrap(),
+        Some(b"pub fn foo() {}\n".to_vec())
+    );
+    assert_eq!(extract_crate_file(&content, "foo", "1.0.0", "src/main.rs").unwrap(), None);
+    assert!(extract_crate_file(&content, "foo", "1.0.0", "large.txt").is_err());
+}
+
+#[test]
+fn test_crate_diff() {
+    let old = build_crate(&[
+        ("foo-1.0.0/src/lib.rs", b"pub fn foo() {}\n"),
+        ("foo-1.0.0/Cargo.toml", b"[package]\n"),
+        ("foo-1.0.0/old.txt", b"old\n"),
+    ]);
+    let new = build_crate(&[
+        ("foo-1.0.1/src/lib.rs", b"pub fn foo() {}\npub fn bar() {}\n"),
+        ("foo-1.0.1/Cargo.toml", b"[package]\n"),
+        ("foo-1.0.1/logo.png", &[0x89, 0x50, 0xff, 0xfe]),
+    ]);
+    let diff = diff_crates("foo", ("1.0.0", &old), ("1.0.1", &new)).unwrap();
+    assert!(!diff.truncated);
+    let changes = diff
+        .files
+        .iter()
+        .map(|file| (file.path.as_str(), file.change))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        changes,
+        vec![
+            ("logo.png", CrateFileChange::Added),
+            ("old.txt", CrateFileChange::Removed),
+            ("src/lib.rs", CrateFileChange::Modified),
+        ]
+    );
+    assert_eq!(diff.files[0].diff, None);
+    assert_eq!(
+        diff.files[2].diff.as_deref(),
+        Some("--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -1 +1,2 @@\n pub fn foo() {}\n+pub fn bar() {}\n")
+    );
+}
+
+#[test]
+fn test_crate_diff_limits() {
+    // large files are compared by their checksums only
+    let large_old = vec![b'a'; usize::try_from(CRATE_FILE_MAX_SIZE).unwrap() + 1];
+    let large_new = vec![b'b'; usize::try_from(CRATE_FILE_MAX_SIZE).unwrap() + 1];
+    let old = build_crate(&[("foo-1.0.0/large.txt", &large_old)]);
+    let new = build_crate(&[("foo-1.0.1/large.txt", &large_new)]);
+    let diff = diff_crates("foo", ("1.0.0", &old), ("1.0.1", &new)).unwrap();
+    assert_eq!(diff.files.len(), 1);
+    assert_eq!(diff.files[0].change, CrateFileChange::Modified);
+    assert!(diff.files[0].diff.is_none());
+
+    // archives expanding beyond the limit are rejected
+    let bomb = vec![0_u8; usize::try_from(CRATE_UNPACKED_MAX_SIZE).unwrap() + 1];
+    let bomb = build_crate(&[("foo-1.0.1/bomb.bin", &bomb)]);
+    assert!(bomb.len() < 1024 * 1024);
+    let error = diff_crates("foo", ("1.0.0", &old), ("1.0.1", &bomb)).unwrap_err();
+    assert_eq!(error.http, 400);
+}
+
+#[test]
+fn test_binstall_metadata() {
+    let content = build_crate(&[(
+        "foo-1.0.0/Cargo.toml",
+        b"[package]\nname = \"foo\"\n\n[package.metadata.binstall]\npkg-fmt = \"tgz\"\n",
+    )]);
+    let metadata = extract_binstall_metadata(&content, "foo", "1.0.0").unwrap();
+    assert_eq!(metadata, Some(serde_json::json!({ "pkg-fmt": "tgz" })));
+    assert!(validate_binary_target("x86_64-unknown-linux-gnu").is_ok());
+    assert!(validate_binary_target("../data").is_err());
+    assert!(validate_binary_target("").is_err());
+}
@@ -0,0 +1,92 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Tests about the crates starred by users
+
+use super::{async_test, build_payload, setup_create_token, setup_create_user};
+use crate::application::Application;
+use crate::model::packages::CrateVisibilityQuery;
+use crate::utils::apierror::ApiError;
+use crate::utils::axum::auth::{AuthData, Token};
+
+async fn get_stars(application: &Application, package: &str) -> Result<i64, ApiError> {
+    application
+        .db_transaction_read(|app| async move { app.database.get_crate_stars(package).await })
+        .await
+}
+
+#[test]
+fn test_star_crate() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        application
+            .publish_crate_version(&admin_auth, &build_payload("blessed", "0.1.0"))
+            .await?;
+        application
+            .publish_crate_version(&admin_auth, &build_payload("other", "0.1.0"))
+            .await?;
+        assert_eq!(get_stars(&application, "blessed").await?, 0);
+
+        application.star_crate(&admin_auth, "blessed").await?;
+        // starring twice is idempotent
+        application.star_crate(&admin_auth, "blessed").await?;
+        assert_eq!(get_stars(&application, "blessed").await?, 1);
+        assert_eq!(
+            application.get_starred_crates(&admin_auth).await?,
+            vec![String::from("blessed")]
+        );
+        let stats = application.get_crates_stats(&admin_auth).await?;
+        assert_eq!(
+            stats
+                .crates_most_starred
+                .into_iter()
+                .map(|crate_version| crate_version.package)
+                .collect::<Vec<_>>(),
+            vec![String::from("blessed")]
+        );
+
+        let error = application.star_crate(&admin_auth, "unknown").await.unwrap_err();
+        assert_eq!(error.http, 404);
+
+        application.unstar_crate(&admin_auth, "blessed").await?;
+        assert_eq!(get_stars(&application, "blessed").await?, 0);
+        assert!(application.get_starred_crates(&admin_auth).await?.is_empty());
+        Ok(())
+    })
+}
+
+#[test]
+fn test_star_restricted_crate() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        setup_create_user(&application, "other", "").await?;
+        let other_auth = AuthData::from(Token {
+            id: String::from("other"),
+            secret: setup_create_token(&application, 2, false, false).await?,
+        });
+        application
+            .publish_crate_version(&admin_auth, &build_payload("secret", "0.1.0"))
+            .await?;
+        application
+            .set_crate_visibility(
+                &admin_auth,
+                "secret",
+                &CrateVisibilityQuery {
+                    is_restricted: true,
+                    readers: Vec::new(),
+                    teams: Vec::new(),
+                },
+            )
+            .await?;
+
+        // a restricted crate cannot be told apart from an unknown one
+        let error = application.star_crate(&other_auth, "secret").await.unwrap_err();
+        assert_eq!(error.http, 404);
+        let error = application.star_crate(&other_auth, "unknown").await.unwrap_err();
+        assert_eq!(error.http, 404);
+        assert_eq!(get_stars(&application, "secret").await?, 0);
+
+        application.star_crate(&admin_auth, "secret").await?;
+        assert_eq!(get_stars(&application, "secret").await?, 1);
+        Ok(())
+    })
+}
@@ -0,0 +1,109 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Tests about the verification of the uploads to S3
+
+use std::sync::{Arc, Mutex};
+
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode, Uri};
+use axum::routing::put;
+use axum::Router;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use tokio::net::TcpListener;
+
+use super::async_test;
+use crate::model::config::{S3Params, StorageConfig};
+use crate::services::storage::{verify_upload_response, Storage, StorageImpl};
+use crate::utils::apierror::ApiError;
+use crate::utils::hashes::{md5, sha256_bytes};
+
+/// The headers of the uploads received by the mock S3 service
+type Received = Arc<Mutex<Vec<HeaderMap>>>;
+
+/// Starts a mock S3 service answering the uploads with the MD5 digest of the content as `ETag`,
+/// except for the objects named `corrupted`
+async fn start_mock_s3() -> (String, Received) {
+    let received = Received::default();
+    let app = Router::new().route(
+        "/*key",
+        put({
+            let received = received.clone();
+            move |uri: Uri, headers: HeaderMap, body: axum::body::Bytes| async move {
+                received.lock().unwrap().push(headers);
+                let digest = if uri.path().ends_with("/corrupted") {
+                    md5(b"something else")
+                } else {
+                    md5(&body)
+                };
+                (StatusCode::OK, [(header::ETAG, format!("\"{digest}\""))])
+            }
+        }),
+    );
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    (format!("http://{address}"), received)
+}
+
+#[test]
+fn test_s3_uploads_are_verified() -> Result<(), ApiError> {
+    async_test(|application, _admin_auth| async move {
+        let (endpoint, received) = start_mock_s3().await;
+        let mut configuration = (*application.configuration.load_full()).clone();
+        configuration.storage = StorageConfig::S3 {
+            params: Box::new(S3Params {
+                endpoint,
+                region: String::from("us-east-1"),
+                access_key: String::from("access"),
+                secret_key: String::from("secret"),
+                request_timeout: 10,
+                pool_max_idle: 1,
+                pool_idle_timeout: 10,
+                checksum_uploads: true,
+                verify_etag: true,
+                ..Default::default()
+            }),
+            bucket: String::from("bucket"),
+        };
+        let storage = StorageImpl::from(&configuration);
+
+        storage.store_backup("valid", b"content".to_vec()).await?;
+        let headers = received.lock().unwrap().pop().unwrap();
+        let checksum = STANDARD.encode(sha256_bytes(b"content"));
+        assert_eq!(headers.get("x-amz-checksum-sha256").unwrap(), checksum.as_str());
+        assert!(headers.get("x-amz-checksum-crc32c").is_none());
+        // the checksum is part of the signature
+        let authorization = headers.get(header::AUTHORIZATION).unwrap().to_str().unwrap();
+        assert!(authorization.contains("x-amz-checksum-sha256"));
+
+        assert!(storage.store_backup("corrupted", b"content".to_vec()).await.is_err());
+        Ok(())
+    })
+}
+
+#[test]
+fn test_verify_upload_response_without_md5_etag() {
+    let checksum = STANDARD.encode(sha256_bytes(b"content"));
+    let mut headers = HeaderMap::new();
+    // multipart uploads and SSE-KMS do not return the MD5 digest
+    headers.insert(
+        header::ETAG,
+        HeaderValue::from_static("\"d41d8cd98f00b204e9800998ecf8427e-2\""),
+    );
+    headers.insert("x-amz-checksum-sha256", HeaderValue::from_str(&checksum).unwrap());
+    assert!(verify_upload_response("key", b"content", Some(&checksum), &headers).is_ok());
+    let other = STANDARD.encode(sha256_bytes(b"other"));
+    assert!(verify_upload_response("key", b"content", Some(&other), &headers).is_err());
+    // without a checksum to compare to, the upload is accepted
+    headers.remove("x-amz-checksum-sha256");
+    assert!(verify_upload_response("key", b"content", Some(&checksum), &headers).is_ok());
+
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::ETAG,
+        HeaderValue::from_str(&format!("\"{}\"", md5(b"other"))).unwrap(),
+    );
+    assert!(verify_upload_response("key", b"content", None, &headers).is_err());
+}
@@ -0,0 +1,81 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Tests about serving tenants under their own prefix
+
+use axum::body::{to_bytes, Body};
+use axum::http::{header, Request, StatusCode};
+use axum::Router;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use cookie::Key;
+use tower::ServiceExt;
+
+use super::async_test;
+use super::mocks::MockService;
+use crate::application::Application;
+use crate::model::config::Configuration;
+use crate::registry_router;
+use crate::services::ServiceProvider;
+use crate::utils::apierror::ApiError;
+use crate::utils::token::generate_token;
+
+/// Sends a GET request to the router and returns the status and the body of the response
+async fn get(app: &Router, uri: &str, credentials: Option<&Configuration>) -> (StatusCode, String) {
+    let mut request = Request::builder().uri(uri);
+    if let Some(configuration) = credentials {
+        let token = STANDARD.encode(format!(
+            "{}:{}",
+            configuration.self_service_login, configuration.self_service_token
+        ));
+        request = request.header(header::AUTHORIZATION, format!("Basic {token}"));
+    }
+    let response = app.clone().oneshot(request.body(Body::empty()).unwrap()).await.unwrap();
+    let status = response.status();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, String::from_utf8_lossy(&body).into_owned())
+}
+
+#[test]
+fn test_tenant_routes() -> Result<(), ApiError> {
+    async_test(|application, _admin_auth| async move {
+        let mut tenant_configuration = MockService::get_configuration().await?;
+        tenant_configuration.tenant = Some(String::from("acme"));
+        tenant_configuration.self_local_name = String::from("acme");
+        tenant_configuration.self_service_login = generate_token(16);
+        tenant_configuration.self_service_token = generate_token(64);
+        let tenant = Application::launch::<MockService>(tenant_configuration).await?;
+        let main_configuration = application.configuration.load_full();
+        let tenant_configuration = tenant.configuration.load_full();
+
+        let app = registry_router(application.clone(), Key::generate())
+            .nest("/acme", registry_router(tenant.clone(), Key::generate()));
+
+        let (status, body) = get(&app, "/webapp/crate.html", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("<base href=\"/\">"));
+        let (status, body) = get(&app, "/acme/webapp/crate.html", None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("<base href=\"/acme/\">"));
+        // the scripts are not rewritten
+        let (_, main_script) = get(&app, "/webapp/api.js", None).await;
+        let (_, tenant_script) = get(&app, "/acme/webapp/api.js", None).await;
+        assert_eq!(main_script, tenant_script);
+
+        let (status, body) = get(&app, "/api/v1/registry-information", Some(&main_configuration)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains(&format!("\"registryName\":\"{}\"", main_configuration.self_local_name)));
+        let (status, body) = get(&app, "/acme/api/v1/registry-information", Some(&tenant_configuration)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("\"registryName\":\"acme\""));
+        // the credentials of a registry are not valid for the others
+        let (status, _) = get(&app, "/acme/api/v1/registry-information", Some(&main_configuration)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        let (status, _) = get(&app, "/api/v1/registry-information", Some(&tenant_configuration)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        tokio::fs::remove_dir_all(&tenant_configuration.data_dir).await?;
+        Ok(())
+    })
+}
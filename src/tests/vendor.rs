@@ -0,0 +1,252 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Tests about the export of vendored crates
+
+use std::io::Read;
+use std::sync::Arc;
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use tar::{Archive, Builder, Header};
+
+use semver::Version;
+
+use super::{async_test, build_payload, setup_create_token, setup_create_user};
+use crate::model::config::{ExternalRegistry, ExternalRegistryProtocol};
+use crate::model::deps::CrateVersionAdvisory;
+use crate::model::osv::SimpleAdvisory;
+use crate::model::packages::{BulkMetadataQuery, CrateVisibilityQuery, BULK_METADATA_MAX_CRATES};
+use crate::model::vendor::{build_vendor_archive, LockedPackage, VendorMissing, VendoredCrate};
+use crate::model::CrateVersion;
+use crate::utils::apierror::ApiError;
+use crate::utils::axum::auth::{AuthData, Token};
+use crate::utils::hashes::sha256;
+
+const LOCK_FILE: &str = r#"# This file is automatically @generated by Cargo.
+version = 3
+
+[[package]]
+name = "app"
+version = "0.1.0"
+dependencies = [
+ "foo",
+]
+
+[[package]]
+name = "foo"
+version = "1.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+checksum = "abcd"
+"#;
+
+#[test]
+fn test_parse_lock_file() {
+    let packages = LockedPackage::parse_lock_file(LOCK_FILE);
+    assert_eq!(
+        vec![
+            LockedPackage {
+                name: String::from("app"),
+                version: String::from("0.1.0"),
+                source: None,
+                checksum: None,
+            },
+            LockedPackage {
+                name: String::from("foo"),
+                version: String::from("1.0.0"),
+                source: Some(String::from("registry+https://github.com/rust-lang/crates.io-index")),
+                checksum: Some(String::from("abcd")),
+            }
+        ],
+        packages
+    );
+}
+
+#[test]
+fn test_build_vendor_archive() -> Result<(), ApiError> {
+    let manifest = b"[package]\nname = \"foo\"\nversion = \"1.0.0\"\n";
+    let mut builder = Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+    let mut header = Header::new_gnu();
+    header.set_size(manifest.len() as u64);
+    header.set_mode(0o644);
+    builder.append_data(&mut header, "foo-1.0.0/Cargo.toml", manifest.as_slice())?;
+    let content = builder.into_inner()?.finish()?;
+    let package_checksum = sha256(&content);
+
+    let archive = build_vendor_archive(
+        &[VendoredCrate {
+            name: String::from("foo"),
+            version: String::from("1.0.0"),
+            content,
+        }],
+        &[VendorMissing {
+            name: String::from("bar"),
+            version: String::from("2.0.0"),
+            reason: String::from("not available in this registry"),
+        }],
+    )?;
+
+    let mut files = Vec::new();
+    let mut checksum_file = String::new();
+    for entry in Archive::new(GzDecoder::new(archive.as_slice())).entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.to_string_lossy().to_string();
+        if path.ends_with(".cargo-checksum.json") {
+            entry.read_to_string(&mut checksum_file)?;
+        }
+        files.push(path);
+    }
+    assert_eq!(
+        vec![
+            "vendor/foo-1.0.0/Cargo.toml",
+            "vendor/foo-1.0.0/.cargo-checksum.json",
+            "vendor-missing.txt"
+        ],
+        files
+    );
+    let checksums: serde_json::Value = serde_json::from_str(&checksum_file)?;
+    assert_eq!(Some(package_checksum.as_str()), checksums["package"].as_str());
+    assert_eq!(Some(sha256(manifest).as_str()), checksums["files"]["Cargo.toml"].as_str());
+    Ok(())
+}
+
+#[test]
+fn test_bulk_metadata_missing() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        let query = BulkMetadataQuery {
+            names: vec![String::from("foo"), String::from("bar")],
+            lock_file: Some(String::from(LOCK_FILE)),
+        };
+        let result = application.get_crates_metadata(&admin_auth, &query).await?;
+        assert!(result.crates.is_empty());
+        // foo in the lock file comes from crates.io
+        assert_eq!(result.missing, vec![String::from("foo"), String::from("bar")]);
+        let query = BulkMetadataQuery {
+            names: vec![String::from("foo"); BULK_METADATA_MAX_CRATES + 1],
+            lock_file: None,
+        };
+        assert!(application.get_crates_metadata(&admin_auth, &query).await.is_err());
+        Ok(())
+    })
+}
+
+#[test]
+fn test_audit_lock_file_missing() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        let self_index = application.configuration.load().get_self_as_external().index;
+        let lock_file =
+            format!("{LOCK_FILE}\n[[package]]\nname = \"bar\"\nversion = \"2.0.0\"\nsource = \"sparse+{self_index}\"\n");
+        let audit = application.audit_lock_file(&admin_auth, &lock_file).await?;
+        // the path package is not checked, foo from crates.io has no advisory
+        assert_eq!(audit.checked, 2);
+        assert!(!audit.passed);
+        assert_eq!(audit.entries.len(), 1);
+        assert_eq!(audit.entries[0].name, "bar");
+        assert!(audit.entries[0].missing);
+        let audit = application.audit_lock_file(&admin_auth, LOCK_FILE).await?;
+        assert!(audit.passed);
+        Ok(())
+    })
+}
+
+#[test]
+fn test_audit_lock_file_local_and_external() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        let mut configuration = (*application.configuration.load_full()).clone();
+        configuration.external_registries.push(ExternalRegistry {
+            name: String::from("ext"),
+            index: String::from("https://ext.example.com/index/"),
+            protocol: ExternalRegistryProtocol::Sparse,
+            docs_root: String::new(),
+            login: String::new(),
+            token: String::new(),
+            proxy: false,
+            proxy_stale_index: 0,
+        });
+        application.configuration.store(Arc::new(configuration));
+        let self_index = application.configuration.load().get_self_as_external().index;
+
+        for package in ["vulnerable", "secret"] {
+            application
+                .publish_crate_version(&admin_auth, &build_payload(package, "0.1.0"))
+                .await?;
+        }
+        let crate_version = CrateVersion {
+            package: String::from("vulnerable"),
+            version: String::from("0.1.0"),
+        };
+        let advisory = SimpleAdvisory {
+            package: String::from("vulnerable"),
+            id: String::from("LOCAL-1"),
+            published: String::new(),
+            modified: String::new(),
+            summary: String::from("bad"),
+            ranges: Vec::new(),
+            versions: Vec::new(),
+        };
+        let advisories = vec![CrateVersionAdvisory::new(
+            &crate_version,
+            "vulnerable",
+            &Version::new(0, 1, 0),
+            &advisory,
+            true,
+        )];
+        application
+            .db_transaction_write("flag", |app| async move {
+                app.database
+                    .set_crate_version_advisories("vulnerable", "0.1.0", true, advisories)
+                    .await
+            })
+            .await?;
+        application
+            .set_crate_visibility(
+                &admin_auth,
+                "secret",
+                &CrateVisibilityQuery {
+                    is_restricted: true,
+                    readers: Vec::new(),
+                    teams: Vec::new(),
+                },
+            )
+            .await?;
+
+        let lock_file = [
+            ("vulnerable", "0.1.0", format!("sparse+{self_index}")),
+            ("secret", "0.1.0", format!("sparse+{self_index}")),
+            ("ext", "1.0.0", String::from("sparse+https://ext.example.com/index/")),
+            ("ext", "2.0.0", String::from("sparse+https://ext.example.com/index/")),
+        ]
+        .iter()
+        .map(|(name, version, source)| {
+            format!("\n[[package]]\nname = \"{name}\"\nversion = \"{version}\"\nsource = \"{source}\"\n")
+        })
+        .collect::<Vec<_>>()
+        .concat();
+
+        let audit = application.audit_lock_file(&admin_auth, &lock_file).await?;
+        assert_eq!(audit.checked, 4);
+        let entries = audit
+            .entries
+            .iter()
+            .map(|entry| (entry.name.as_str(), entry.version.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(entries, vec![("vulnerable", "0.1.0"), ("ext", "1.0.0"), ("ext", "2.0.0")]);
+        assert_eq!(audit.entries[0].advisories[0].id, "LOCAL-1");
+        assert!(audit.entries[1].yanked);
+        assert!(audit.entries[2].missing);
+
+        // the restricted crates cannot be told apart from the missing ones
+        setup_create_user(&application, "other", "").await?;
+        let other_auth = AuthData::from(Token {
+            id: String::from("other"),
+            secret: setup_create_token(&application, 2, false, false).await?,
+        });
+        let audit = application.audit_lock_file(&other_auth, &lock_file).await?;
+        let secret = audit.entries.iter().find(|entry| entry.name == "secret").unwrap();
+        assert!(secret.missing);
+        assert!(!secret.deprecated);
+        Ok(())
+    })
+}
@@ -0,0 +1,295 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Tests about the restricted visibility of crates
+
+use std::io::Read;
+
+use async_graphql::Request;
+use flate2::read::GzDecoder;
+use serde_json::json;
+use tar::Archive;
+
+use super::{async_test, build_payload, setup_create_token, setup_create_user};
+use crate::application::Application;
+use crate::graphql::build_schema;
+use crate::model::packages::{BulkMetadataQuery, CrateVisibilityQuery};
+use crate::utils::apierror::ApiError;
+use crate::utils::axum::auth::{AuthData, Token};
+
+/// Publishes the `secret` crate restricted to its owner, the administrator, and gets the authentication of another user
+async fn setup_restricted_crate(application: &Application, admin_auth: &AuthData) -> Result<AuthData, ApiError> {
+    setup_create_user(application, "other", "").await?;
+    let other_auth = AuthData::from(Token {
+        id: String::from("other"),
+        secret: setup_create_token(application, 2, false, false).await?,
+    });
+    application
+        .publish_crate_version(admin_auth, &build_payload("secret", "0.1.0"))
+        .await?;
+    application
+        .set_crate_visibility(
+            admin_auth,
+            "secret",
+            &CrateVisibilityQuery {
+                is_restricted: true,
+                readers: Vec::new(),
+                teams: Vec::new(),
+            },
+        )
+        .await?;
+    Ok(other_auth)
+}
+
+/// Gets a lock file that locks the `secret` crate from this registry
+fn lock_file_with_secret(application: &Application) -> String {
+    let self_index = application.configuration.load().get_self_as_external().index;
+    format!("version = 3\n\n[[package]]\nname = \"secret\"\nversion = \"0.1.0\"\nsource = \"sparse+{self_index}\"\n")
+}
+
+#[test]
+fn test_crate_visibility() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        setup_create_user(&application, "reader", "").await?;
+        setup_create_user(&application, "other", "").await?;
+        let reader_auth = AuthData::from(Token {
+            id: String::from("reader"),
+            secret: setup_create_token(&application, 2, false, false).await?,
+        });
+        let other_auth = AuthData::from(Token {
+            id: String::from("other"),
+            secret: setup_create_token(&application, 3, false, false).await?,
+        });
+        application
+            .publish_crate_version(&admin_auth, &build_payload("secret", "0.1.0"))
+            .await?;
+        application.check_can_read_crate(&other_auth, "secret").await?;
+
+        let visibility = application
+            .set_crate_visibility(
+                &admin_auth,
+                "secret",
+                &CrateVisibilityQuery {
+                    is_restricted: true,
+                    readers: vec![String::from("reader")],
+                    teams: Vec::new(),
+                },
+            )
+            .await?;
+        assert!(visibility.is_restricted);
+        assert_eq!(visibility.readers.len(), 1);
+
+        // owners, readers and administrators can read the crate
+        application.check_can_read_crate(&admin_auth, "secret").await?;
+        application.check_can_read_crate(&reader_auth, "secret").await?;
+        // the crate does not exist for the other users, whatever the case of its name, as in the index
+        for package in ["secret", "SECRET"] {
+            let error = application.check_can_read_crate(&other_auth, package).await.unwrap_err();
+            assert_eq!(error.http, 404);
+        }
+        assert!(!application.get_feed_versions(&other_auth).await?.contains("secret"));
+        assert!(application.get_feed_versions(&reader_auth).await?.contains("secret"));
+
+        // readers cannot change the visibility
+        let error = application.get_crate_visibility(&reader_auth, "secret").await.unwrap_err();
+        assert_eq!(error.http, 403);
+
+        let visibility = application
+            .set_crate_visibility(&admin_auth, "secret", &CrateVisibilityQuery::default())
+            .await?;
+        assert!(!visibility.is_restricted);
+        assert!(visibility.readers.is_empty());
+        application.check_can_read_crate(&other_auth, "secret").await?;
+        Ok(())
+    })
+}
+
+#[test]
+fn test_crate_visibility_teams() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        setup_create_user(&application, "member", "dev, core").await?;
+        setup_create_user(&application, "other", "dev").await?;
+        let member_auth = AuthData::from(Token {
+            id: String::from("member"),
+            secret: setup_create_token(&application, 2, false, false).await?,
+        });
+        let other_auth = AuthData::from(Token {
+            id: String::from("other"),
+            secret: setup_create_token(&application, 3, false, false).await?,
+        });
+        application
+            .publish_crate_version(&admin_auth, &build_payload("secret", "0.1.0"))
+            .await?;
+        let error = application
+            .set_crate_visibility(
+                &admin_auth,
+                "secret",
+                &CrateVisibilityQuery {
+                    is_restricted: true,
+                    readers: Vec::new(),
+                    teams: vec![String::from("core,dev")],
+                },
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(error.http, 400);
+
+        let visibility = application
+            .set_crate_visibility(
+                &admin_auth,
+                "secret",
+                &CrateVisibilityQuery {
+                    is_restricted: true,
+                    readers: Vec::new(),
+                    teams: vec![String::from("core"), String::from("core")],
+                },
+            )
+            .await?;
+        assert_eq!(visibility.teams, vec![String::from("core")]);
+
+        // the members of the team can read the crate, not the other users
+        application.check_can_read_crate(&member_auth, "Secret").await?;
+        let error = application.check_can_read_crate(&other_auth, "secret").await.unwrap_err();
+        assert_eq!(error.http, 404);
+        assert!(application.get_feed_versions(&member_auth).await?.contains("secret"));
+        assert!(!application.get_feed_versions(&other_auth).await?.contains("secret"));
+
+        let visibility = application
+            .set_crate_visibility(&admin_auth, "secret", &CrateVisibilityQuery::default())
+            .await?;
+        assert!(visibility.teams.is_empty());
+        Ok(())
+    })
+}
+
+#[test]
+fn test_restricted_crate_in_bulk_metadata() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        let other_auth = setup_restricted_crate(&application, &admin_auth).await?;
+        let query = BulkMetadataQuery {
+            names: vec![String::from("Secret")],
+            lock_file: None,
+        };
+        let result = application.get_crates_metadata(&admin_auth, &query).await?;
+        assert_eq!(result.crates.len(), 1);
+        let result = application.get_crates_metadata(&other_auth, &query).await?;
+        assert!(result.crates.is_empty());
+        assert_eq!(result.missing, vec![String::from("Secret")]);
+        Ok(())
+    })
+}
+
+#[test]
+fn test_restricted_crate_in_lock_file_audit() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        let other_auth = setup_restricted_crate(&application, &admin_auth).await?;
+        let lock_file = lock_file_with_secret(&application);
+        assert!(application.audit_lock_file(&admin_auth, &lock_file).await?.passed);
+        let audit = application.audit_lock_file(&other_auth, &lock_file).await?;
+        assert_eq!(audit.entries.len(), 1);
+        assert!(audit.entries[0].missing);
+        Ok(())
+    })
+}
+
+#[test]
+fn test_restricted_crate_in_vendor_export() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        let other_auth = setup_restricted_crate(&application, &admin_auth).await?;
+        let lock_file = lock_file_with_secret(&application);
+        let read_missing = |archive: Vec<u8>| -> Result<String, ApiError> {
+            let mut archive = Archive::new(GzDecoder::new(archive.as_slice()));
+            let mut missing = String::new();
+            for entry in archive.entries()? {
+                let mut entry = entry?;
+                if entry.path()?.to_str() == Some("vendor-missing.txt") {
+                    entry.read_to_string(&mut missing)?;
+                }
+            }
+            Ok(missing)
+        };
+        let archive = application.export_vendor(&admin_auth, &lock_file).await?;
+        assert!(read_missing(archive)?.is_empty());
+        let archive = application.export_vendor(&other_auth, &lock_file).await?;
+        assert!(read_missing(archive)?.contains("secret"));
+        Ok(())
+    })
+}
+
+#[test]
+fn test_restricted_crate_in_stats() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        let other_auth = setup_restricted_crate(&application, &admin_auth).await?;
+        application.star_crate(&admin_auth, "secret").await?;
+        let stats = application.get_crates_stats(&admin_auth).await?;
+        assert_eq!(stats.total_crates, 1);
+        assert_eq!(stats.crates_newest.len(), 1);
+        assert_eq!(stats.crates_most_starred.len(), 1);
+        let stats = application.get_crates_stats(&other_auth).await?;
+        assert_eq!(stats.total_crates, 0);
+        assert!(stats.crates_newest.is_empty());
+        assert!(stats.crates_most_downloaded.is_empty());
+        assert!(stats.crates_most_starred.is_empty());
+        assert!(stats.crates_last_updated.is_empty());
+        Ok(())
+    })
+}
+
+#[test]
+fn test_restricted_crate_in_doc_gen_job_updates() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        let other_auth = setup_restricted_crate(&application, &admin_auth).await?;
+        assert_eq!(application.get_doc_gen_jobs(&admin_auth).await?.len(), 1);
+        assert!(application.get_doc_gen_jobs(&other_auth).await?.is_empty());
+        // the mocking generator does not store its jobs
+        let job_id = application
+            .db_transaction_write("test_add_job", |app| async move {
+                let id = sqlx::query_scalar::<_, i64>(
+                    "INSERT INTO DocGenJob (package, version, target, useNative, capabilities, state, queuedOn, startedOn, finishedOn, lastUpdate, triggerEvent, output)
+                    VALUES ('secret', '0.1.0', 'x86_64-unknown-linux-gnu', FALSE, '', 0, '2024-01-01T00:00:00', '2024-01-01T00:00:00', '2024-01-01T00:00:00', '2024-01-01T00:00:00', 0, '')
+                    RETURNING id",
+                )
+                .fetch_one(&mut *app.database.transaction.borrow().await)
+                .await?;
+                Ok::<_, ApiError>(id)
+            })
+            .await?;
+        // the log and the updates of the job are only available to the readers of the crate
+        let error = application.get_doc_gen_job_log(&other_auth, job_id).await.unwrap_err();
+        assert_eq!(error.http, 404);
+        let error = application
+            .get_doc_gen_job_updates_for(&other_auth, job_id)
+            .await
+            .unwrap_err();
+        assert_eq!(error.http, 404);
+        application.get_doc_gen_job_log(&admin_auth, job_id).await?;
+        let mut receiver = application.get_doc_gen_job_updates_for(&admin_auth, job_id).await?;
+        assert!(receiver.recv().await.is_some());
+        Ok(())
+    })
+}
+
+#[test]
+fn test_restricted_crate_in_graphql() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        let other_auth = setup_restricted_crate(&application, &admin_auth).await?;
+        let other_token = other_auth.token.unwrap();
+        let other_auth = || AuthData::from(other_token.clone());
+        let schema = build_schema();
+        let request = Request::new(r#"{ crates(query: "secret") { total crates { name } } }"#)
+            .data(application.clone())
+            .data(other_auth());
+        let response = schema.execute(request).await;
+        assert!(response.errors.is_empty());
+        assert_eq!(response.data.into_json()?, json!({ "crates": { "total": 0, "crates": [] } }));
+        // the fields that do not need the information about the crate are also checked
+        for field in ["owners { login }", "dailyDownloads { version }"] {
+            let request = Request::new(format!(r#"{{ crate(name: "secret") {{ {field} }} }}"#))
+                .data(application.clone())
+                .data(other_auth());
+            assert_eq!(schema.execute(request).await.errors.len(), 1);
+        }
+        Ok(())
+    })
+}
@@ -0,0 +1,289 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Tests about the outgoing webhooks
+
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use super::{async_test, build_payload};
+use crate::application::Application;
+use crate::model::webhooks::{
+    is_webhook_address_forbidden, WebhookCreateQuery, WebhookDelivery, WebhookDeliveryJob, WebhookEvent, WEBHOOK_MAX_ATTEMPTS,
+};
+use crate::services::webhooks::{
+    deliver_with_retries, get_service, get_service_from, resume_pending, sign_payload, WebhookSender,
+};
+use crate::utils::apierror::{error_backend_failure, specialize, ApiError};
+use crate::utils::axum::auth::AuthData;
+use crate::utils::FaillibleFuture;
+
+/// Builds a query for a webhook on a crate
+fn webhook_query(package: &str, url: &str) -> WebhookCreateQuery {
+    WebhookCreateQuery {
+        package: Some(package.to_string()),
+        url: url.to_string(),
+        events: vec![WebhookEvent::Publish],
+    }
+}
+
+/// A webhook sender failing a number of times before succeeding, recording the time of the attempts
+struct FailingWebhookSender {
+    /// The number of attempts that fail
+    failures: usize,
+    /// The time of the attempts so far
+    attempts: Mutex<Vec<Instant>>,
+}
+
+impl FailingWebhookSender {
+    fn new(failures: usize) -> Arc<Self> {
+        Arc::new(Self {
+            failures,
+            attempts: Mutex::new(Vec::new()),
+        })
+    }
+}
+
+impl WebhookSender for FailingWebhookSender {
+    fn deliver<'a>(
+        &'a self,
+        _url: &'a str,
+        _secret: &'a str,
+        _event: &'a str,
+        _delivery_id: i64,
+        _body: &'a str,
+    ) -> FaillibleFuture<'a, u16> {
+        Box::pin(async move {
+            let mut attempts = self.attempts.lock().unwrap();
+            attempts.push(Instant::now());
+            if attempts.len() <= self.failures {
+                return Err(specialize(error_backend_failure(), String::from("connection refused")));
+            }
+            Ok(200)
+        })
+    }
+}
+
+/// Creates a webhook on all the crates, without publishing any, so that only the deliveries of the test are made
+///
+/// Returns the identifier of the webhook and the job for a new delivery.
+async fn setup_delivery_job(application: &Application, admin_auth: &AuthData) -> Result<(i64, WebhookDeliveryJob), ApiError> {
+    let mut query = webhook_query("hooked", "https://hooks.example.com/cratery");
+    query.package = None;
+    let webhook = application.create_webhook(admin_auth, &query).await?;
+    let delivery_id = application
+        .db_transaction_write("test_create_delivery", |app| async move {
+            app.database
+                .create_webhook_delivery(webhook.webhook.id, "publish", "{}")
+                .await
+        })
+        .await?;
+    Ok((
+        webhook.webhook.id,
+        WebhookDeliveryJob {
+            delivery_id,
+            attempts: 0,
+            event: String::from("publish"),
+            payload: String::from("{}"),
+            url: webhook.webhook.url,
+            secret: webhook.secret,
+        },
+    ))
+}
+
+/// Gets the single delivery of a webhook
+async fn get_delivery(application: &Application, admin_auth: &AuthData, webhook_id: i64) -> Result<WebhookDelivery, ApiError> {
+    let deliveries = application.get_webhook_deliveries(admin_auth, webhook_id, None).await?;
+    assert_eq!(deliveries.len(), 1);
+    Ok(deliveries.into_iter().next().unwrap())
+}
+
+#[test]
+fn test_webhook_signature() {
+    assert_eq!(
+        sign_payload("secret", r#"{"event":"publish"}"#),
+        "sha256=ed17693cd07d4a9a02ccf963275cc7d357282af2ddd354ffe2ad2a763648bef3"
+    );
+}
+
+#[test]
+fn test_webhook_forbidden_addresses() {
+    for address in [
+        "127.0.0.1",
+        "10.1.2.3",
+        "172.16.0.1",
+        "192.168.1.1",
+        "169.254.169.254",
+        "100.64.0.1",
+        "0.0.0.0",
+        "::1",
+        "fe80::1",
+        "fd00::1",
+        "::ffff:127.0.0.1",
+    ] {
+        assert!(is_webhook_address_forbidden(address.parse::<IpAddr>().unwrap()), "{address}");
+    }
+    for address in ["1.1.1.1", "93.184.215.14", "2606:4700::1111"] {
+        assert!(!is_webhook_address_forbidden(address.parse::<IpAddr>().unwrap()), "{address}");
+    }
+}
+
+#[test]
+fn test_webhook_create_rejects_private_urls() {
+    for url in [
+        "http://localhost/hook",
+        "http://127.0.0.1:8080/hook",
+        "http://169.254.169.254/latest/meta-data",
+        "http://[::1]/hook",
+        "file:///etc/passwd",
+    ] {
+        assert!(webhook_query("hooked", url).validate().is_err(), "{url}");
+    }
+    assert!(webhook_query("hooked", "https://hooks.example.com/cratery")
+        .validate()
+        .is_ok());
+}
+
+#[test]
+fn test_webhook_sender_refuses_private_targets() {
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    runtime.block_on(async {
+        let sender = get_service();
+        for url in ["http://127.0.0.1:9/hook", "http://[::1]:9/hook", "http://localhost:9/hook"] {
+            assert!(sender.deliver(url, "secret", "publish", 1, "{}").await.is_err(), "{url}");
+        }
+    });
+}
+
+#[test]
+fn test_webhook_sender_ignores_proxy() {
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    runtime.block_on(async {
+        // a proxy accepting everything, that would reach the private targets
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy_uri = format!("http://{}", listener.local_addr().unwrap());
+        let _handle = tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buffer = [0_u8; 4096];
+                let _ = stream.read(&mut buffer).await;
+                let _ = stream.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n").await;
+            }
+        });
+        let sender = get_service_from(reqwest::Client::builder().proxy(reqwest::Proxy::all(&proxy_uri).unwrap()));
+        for url in ["http://localhost:9/hook", "http://127.0.0.1:9/hook"] {
+            assert!(sender.deliver(url, "secret", "publish", 1, "{}").await.is_err(), "{url}");
+        }
+    });
+}
+
+#[test]
+fn test_webhook_delivery() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        let mut query = webhook_query("hooked", "https://hooks.example.com/cratery");
+        query.package = None;
+        let webhook = application.create_webhook(&admin_auth, &query).await?;
+        application
+            .publish_crate_version(&admin_auth, &build_payload("hooked", "0.2.0"))
+            .await?;
+
+        // the deliveries are made in the background
+        let mut deliveries = Vec::new();
+        for _ in 0..100 {
+            deliveries = application
+                .get_webhook_deliveries(&admin_auth, webhook.webhook.id, None)
+                .await?;
+            if deliveries.iter().any(|delivery| delivery.is_delivered) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(deliveries.len(), 1);
+        assert!(deliveries[0].is_delivered);
+        assert_eq!(deliveries[0].status_code, Some(200));
+        assert_eq!(deliveries[0].event, "publish");
+        let payload = serde_json::from_str::<serde_json::Value>(&deliveries[0].payload)?;
+        assert_eq!(payload["package"], "hooked");
+        assert_eq!(payload["version"], "0.2.0");
+        Ok(())
+    })
+}
+
+#[test]
+fn test_webhook_delivery_retries() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        let (webhook_id, job) = setup_delivery_job(&application, &admin_auth).await?;
+        let sender = FailingWebhookSender::new(2);
+        deliver_with_retries(
+            application.get_service_db_pool(),
+            sender.clone(),
+            job,
+            Duration::from_millis(10),
+        )
+        .await;
+        let delivery = get_delivery(&application, &admin_auth, webhook_id).await?;
+        assert!(delivery.is_delivered);
+        assert_eq!(delivery.attempts, 3);
+        assert_eq!(delivery.status_code, Some(200));
+        assert!(delivery.error.is_none());
+        // the attempts are spaced by an exponential backoff
+        let attempts = sender.attempts.lock().unwrap().clone();
+        assert_eq!(attempts.len(), 3);
+        assert!(attempts[1] - attempts[0] >= Duration::from_millis(20));
+        assert!(attempts[2] - attempts[1] >= Duration::from_millis(40));
+        Ok(())
+    })
+}
+
+#[test]
+fn test_webhook_delivery_exhausted() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        let (webhook_id, job) = setup_delivery_job(&application, &admin_auth).await?;
+        let sender = FailingWebhookSender::new(usize::MAX);
+        deliver_with_retries(application.get_service_db_pool(), sender.clone(), job, Duration::ZERO).await;
+        let delivery = get_delivery(&application, &admin_auth, webhook_id).await?;
+        assert!(!delivery.is_delivered);
+        assert_eq!(delivery.attempts, WEBHOOK_MAX_ATTEMPTS);
+        assert!(delivery.status_code.is_none());
+        assert!(delivery
+            .error
+            .as_deref()
+            .is_some_and(|error| error.contains("connection refused")));
+        assert_eq!(sender.attempts.lock().unwrap().len(), usize::try_from(WEBHOOK_MAX_ATTEMPTS)?);
+        Ok(())
+    })
+}
+
+#[test]
+fn test_webhook_delivery_resumed() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        let (webhook_id, job) = setup_delivery_job(&application, &admin_auth).await?;
+        // the delivery was interrupted by a restart, with a single attempt left
+        application
+            .db_transaction_write("test_interrupt_delivery", |app| async move {
+                app.database
+                    .update_webhook_delivery(job.delivery_id, WEBHOOK_MAX_ATTEMPTS - 1, Some(503), None)
+                    .await
+            })
+            .await?;
+        let sender = FailingWebhookSender::new(0);
+        resume_pending(application.get_service_db_pool().clone(), sender.clone());
+        // the deliveries are resumed in the background
+        let mut delivery = get_delivery(&application, &admin_auth, webhook_id).await?;
+        for _ in 0..100 {
+            if delivery.is_delivered {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            delivery = get_delivery(&application, &admin_auth, webhook_id).await?;
+        }
+        assert!(delivery.is_delivered);
+        assert_eq!(delivery.attempts, WEBHOOK_MAX_ATTEMPTS);
+        assert_eq!(delivery.status_code, Some(200));
+        assert_eq!(sender.attempts.lock().unwrap().len(), 1);
+        Ok(())
+    })
+}
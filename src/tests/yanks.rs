@@ -0,0 +1,76 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Tests about yanking many crate versions at once
+
+use std::time::Instant;
+
+use super::{async_test, build_payload};
+use crate::model::config::MaintenanceConfig;
+use crate::model::packages::BulkYankQuery;
+use crate::utils::apierror::ApiError;
+
+/// Builds a query for a pattern
+fn query(pattern: &str) -> BulkYankQuery {
+    BulkYankQuery {
+        pattern: pattern.to_string(),
+        versions: None,
+        dry_run: true,
+        reason: None,
+    }
+}
+
+#[test]
+fn test_bulk_yank_pattern() {
+    assert!(query("acme-*").matches_name("acme-core"));
+    assert!(query("acme-*").matches_name("ACME-"));
+    assert!(!query("acme-*").matches_name("acme"));
+    assert!(query("*-core").matches_name("acme-core"));
+    assert!(query("a*c*e").matches_name("abcde"));
+    assert!(query("*").matches_name(""));
+    assert!(query("**").matches_name("acme"));
+    assert!(!query("a*d").matches_name("abcde"));
+    assert!(!query("").matches_name("acme"));
+    assert!(query("acme").matches_name("acme"));
+
+    // patterns with many stars do not make the matching exponential
+    let pattern = format!("{}b", "a*".repeat(32));
+    let name = "a".repeat(64);
+    let start = Instant::now();
+    assert!(!query(&pattern).matches_name(&name));
+    assert!(start.elapsed().as_secs() < 1);
+}
+
+#[test]
+fn test_bulk_yank_dry_run_is_read_only() -> Result<(), ApiError> {
+    async_test(|application, admin_auth| async move {
+        application
+            .publish_crate_version(&admin_auth, &build_payload("acme-core", "0.1.0"))
+            .await?;
+        application
+            .set_maintenance(
+                &admin_auth,
+                MaintenanceConfig {
+                    enabled: true,
+                    message: None,
+                },
+            )
+            .await?;
+        // a dry run does not change anything, so it is possible during a maintenance
+        let result = application.bulk_yank(&admin_auth, &query("acme-*")).await?;
+        assert!(result.dry_run);
+        assert_eq!(result.yanked.len(), 1);
+        let mut yank = query("acme-*");
+        yank.dry_run = false;
+        let error = application.bulk_yank(&admin_auth, &yank).await.unwrap_err();
+        assert_eq!(error.http, 503);
+
+        application.set_maintenance(&admin_auth, MaintenanceConfig::default()).await?;
+        let result = application.bulk_yank(&admin_auth, &yank).await?;
+        assert!(!result.dry_run);
+        assert_eq!(result.yanked.len(), 1);
+        assert!(application.bulk_yank(&admin_auth, &query("acme-*")).await?.yanked.is_empty());
+        Ok(())
+    })
+}
@@ -8,9 +8,10 @@ use std::backtrace::Backtrace;
 use std::fmt::{Display, Formatter};
 
 use serde_derive::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
 /// Describes an API error
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
 pub struct ApiError {
     /// The associated HTTP error code
     pub http: u16,
@@ -18,9 +19,15 @@ pub struct ApiError {
     pub message: String,
     /// Optional details for the error
     pub details: Option<String>,
+    /// The identifier of the request that produced the error, if any
+    #[serde(rename = "requestId", default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    /// The identifier given by the client for the request that produced the error, if any
+    #[serde(rename = "clientRequestId", default, skip_serializing_if = "Option::is_none")]
+    pub client_request_id: Option<String>,
     /// The backtrace when the error was produced
     #[serde(skip_serializing, skip_deserializing)]
-    pub backtrace: Option<Backtrace>,
+    pub backtrace: Option<Box<Backtrace>>,
 }
 
 impl ApiError {
@@ -32,7 +39,9 @@ impl ApiError {
             http,
             message: message.to_string(),
             details,
-            backtrace: Some(Backtrace::capture()),
+            request_id: None,
+            client_request_id: None,
+            backtrace: Some(Box::new(Backtrace::capture())),
         }
     }
 }
@@ -50,6 +59,8 @@ impl Clone for ApiError {
             http: self.http,
             message: self.message.clone(),
             details: self.details.clone(),
+            request_id: self.request_id.clone(),
+            client_request_id: self.client_request_id.clone(),
             backtrace: None,
         }
     }
@@ -65,6 +76,7 @@ where
 }
 
 /// Specializes an API error with additional details
+#[allow(clippy::must_use_candidate)]
 pub fn specialize(original: ApiError, details: String) -> ApiError {
     ApiError {
         details: Some(details),
@@ -102,6 +114,28 @@ pub fn error_not_found() -> ApiError {
     ApiError::new(404, "The requested resource cannot be found.", None)
 }
 
+/// Error when the requested range of a content cannot be satisfied
+#[must_use]
+pub fn error_range_not_satisfiable(length: u64) -> ApiError {
+    ApiError::new(
+        416,
+        "The requested range cannot be satisfied.",
+        Some(format!("The length of the content is {length} bytes")),
+    )
+}
+
+/// Error when the client sent too many requests in a given amount of time
+#[must_use]
+pub fn error_too_many_requests() -> ApiError {
+    ApiError::new(429, "Too many requests, retry later.", None)
+}
+
+/// Error when the service is temporarily unavailable, for example during maintenance
+#[must_use]
+pub fn error_service_unavailable() -> ApiError {
+    ApiError::new(503, "The service is temporarily unavailable.", None)
+}
+
 /// Error when the request has a conflicts
 #[must_use]
 pub fn error_conflict() -> ApiError {
@@ -28,7 +28,8 @@ pub struct Token {
 
 impl Token {
     /// Try to parse a token, expected an HTTP Basic auth scheme
-    fn try_parse(input: &str) -> Option<Token> {
+    #[must_use]
+    pub fn try_parse(input: &str) -> Option<Token> {
         let parts: Vec<&str> = input.trim().split_ascii_whitespace().collect();
         if parts.len() == 2 && parts[0] == "Basic" {
             let Ok(decoded) = BASE64_STANDARD.decode(parts[1]) else {
@@ -0,0 +1,31 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Compression of the responses
+
+use tower_http::compression::predicate::{NotForContentType, Predicate, SizeAbove};
+use tower_http::compression::CompressionLayer;
+
+use crate::model::config::CompressionConfig;
+
+/// Builds the layer compressing the responses with gzip or brotli
+///
+/// Binary content, such as the `.crate` files that are already compressed, images and event streams are excluded.
+/// When disabled, no encoding is accepted so that the responses are left as is.
+#[must_use]
+pub fn compression_layer(config: &CompressionConfig) -> CompressionLayer<impl Predicate> {
+    let predicate = SizeAbove::new(config.min_size)
+        .and(NotForContentType::const_new("application/octet-stream"))
+        .and(NotForContentType::const_new("application/gzip"))
+        .and(NotForContentType::const_new("application/zip"))
+        .and(NotForContentType::GRPC)
+        .and(NotForContentType::IMAGES)
+        .and(NotForContentType::SSE);
+    CompressionLayer::new()
+        .gzip(config.enabled)
+        .br(config.enabled)
+        .no_deflate()
+        .no_zstd()
+        .compress_when(predicate)
+}
@@ -0,0 +1,51 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! CORS policy for the API
+
+use axum::http::{HeaderName, HeaderValue, Method};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+use crate::model::config::CorsConfig;
+
+/// Builds the layer applying the configured CORS policy, if any
+///
+/// # Panics
+///
+/// Panic when the configuration contains invalid origins, methods or headers,
+/// they are expected to have been validated when loading the configuration.
+#[must_use]
+pub fn cors_layer(config: &CorsConfig) -> Option<CorsLayer> {
+    if !config.is_enabled() {
+        return None;
+    }
+    let allow_origin = if config.allowed_origins.iter().any(|origin| origin == "*") {
+        AllowOrigin::any()
+    } else {
+        AllowOrigin::list(
+            config
+                .allowed_origins
+                .iter()
+                .map(|origin| HeaderValue::from_str(origin.trim_end_matches('/')).expect("invalid CORS origin")),
+        )
+    };
+    let layer = CorsLayer::new()
+        .allow_origin(allow_origin)
+        .allow_methods(
+            config
+                .allowed_methods
+                .iter()
+                .map(|method| method.parse::<Method>().expect("invalid CORS method"))
+                .collect::<Vec<_>>(),
+        )
+        .allow_headers(
+            config
+                .allowed_headers
+                .iter()
+                .map(|header| header.parse::<HeaderName>().expect("invalid CORS header"))
+                .collect::<Vec<_>>(),
+        )
+        .allow_credentials(config.allow_credentials);
+    Some(layer)
+}
@@ -70,6 +70,8 @@ pub enum WebappResource {
     Embedded(EmbeddedResource),
     /// A local resource served in hot reload mode
     HotReload { content_type: String, data: Vec<u8> },
+    /// A resource generated from another one
+    Generated { content_type: String, data: Vec<u8> },
 }
 
 impl WebappResource {
@@ -78,7 +80,7 @@ impl WebappResource {
     pub fn content_type(&self) -> &str {
         match self {
             Self::Embedded(res) => res.content_type,
-            Self::HotReload { content_type, data: _ } => content_type,
+            Self::HotReload { content_type, data: _ } | Self::Generated { content_type, data: _ } => content_type,
         }
     }
 
@@ -87,7 +89,7 @@ impl WebappResource {
     pub fn into_data(self) -> Cow<'static, [u8]> {
         match self {
             Self::Embedded(res) => Cow::Borrowed(res.content),
-            Self::HotReload { content_type: _, data } => Cow::Owned(data),
+            Self::HotReload { content_type: _, data } | Self::Generated { content_type: _, data } => Cow::Owned(data),
         }
     }
 }
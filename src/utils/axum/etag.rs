@@ -0,0 +1,70 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Support for conditional requests with entity tags
+
+use axum::body::{to_bytes, Body};
+use axum::extract::Request;
+use axum::http::{header, HeaderValue, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+
+use crate::utils::hashes::sha256;
+
+/// Computes the strong entity tag for a content
+#[must_use]
+pub fn compute_etag(content: &[u8]) -> String {
+    format!("\"{}\"", sha256(content))
+}
+
+/// Checks whether the value of an `If-None-Match` header matches an entity tag
+///
+/// The comparison is weak, as required for `If-None-Match`, so that the `W/` prefix is ignored.
+#[must_use]
+pub fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    let etag = etag.trim_start_matches("W/");
+    if_none_match
+        .split(',')
+        .map(str::trim)
+        .any(|candidate| candidate == "*" || candidate.trim_start_matches("W/") == etag)
+}
+
+/// Middleware that adds an `ETag` header to successful responses to `GET` requests
+/// and answers with `304 Not Modified` when the client already has the same content.
+///
+/// The response body is buffered to compute the tag, so this is only meant for small resources,
+/// such as the files of the sparse index and the metadata of crates.
+pub async fn conditional_get(request: Request, next: Next) -> Response {
+    if request.method() != Method::GET && request.method() != Method::HEAD {
+        return next.run(request).await;
+    }
+    let if_none_match = request
+        .headers()
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let response = next.run(request).await;
+    if response.status() != StatusCode::OK {
+        return response;
+    }
+    let (mut parts, body) = response.into_parts();
+    let Ok(content) = to_bytes(body, usize::MAX).await else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    let etag = if let Some(etag) = parts.headers.get(header::ETAG).and_then(|value| value.to_str().ok()) {
+        etag.to_string()
+    } else {
+        let etag = compute_etag(&content);
+        parts.headers.insert(header::ETAG, HeaderValue::from_str(&etag).unwrap());
+        etag
+    };
+    if if_none_match.is_some_and(|if_none_match| etag_matches(&if_none_match, &etag)) {
+        // same headers, but without content
+        parts.status = StatusCode::NOT_MODIFIED;
+        parts.headers.remove(header::CONTENT_TYPE);
+        parts.headers.remove(header::CONTENT_LENGTH);
+        return Response::from_parts(parts, Body::empty());
+    }
+    Response::from_parts(parts, Body::from(content))
+}
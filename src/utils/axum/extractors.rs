@@ -5,19 +5,25 @@
 //! Custom extractors for Axum
 
 use std::fmt;
-use std::net::{IpAddr, SocketAddr};
+use std::net::IpAddr;
 use std::ops::{Deref, DerefMut};
 
-use axum::extract::{ConnectInfo, FromRequestParts};
+use axum::async_trait;
+use axum::extract::FromRequestParts;
 use axum::http::request::Parts;
-use axum::{async_trait, RequestPartsExt};
+use axum::http::HeaderMap;
 use base64::prelude::BASE64_URL_SAFE;
 use base64::Engine;
 use cookie::{Cookie, CookieJar};
+use ipnet::IpNet;
 use serde::de::Visitor;
 use serde::Deserialize;
 
+use crate::utils::context::with_request_context;
+
 /// The client for the request, if any
+///
+/// The address is resolved when setting up the context of the request, see `resolve_client_ip`.
 #[derive(Debug, Clone)]
 pub struct ClientIp(pub Option<IpAddr>);
 
@@ -28,19 +34,41 @@ where
 {
     type Rejection = ();
 
-    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
-        if let Some(forwarded) = parts.headers.get("x-forwarded-for") {
-            if let Ok(forwarded) = forwarded.to_str() {
-                if let Some(Ok(client_ip)) = forwarded.split(',').next().map(str::trim).map(str::parse) {
-                    return Ok(ClientIp(Some(client_ip)));
-                }
+    async fn from_request_parts(_parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(ClientIp(with_request_context(|context| context.client_ip).flatten()))
+    }
+}
+
+/// Resolves the address of the client for a request
+///
+/// The `x-forwarded-for` header is only used when the request comes from a trusted proxy,
+/// or through a Unix domain socket (no peer address) where only local processes can connect.
+/// The addresses in the header are then read from the right, skipping those of trusted proxies,
+/// so that a client cannot spoof its address by sending the header itself.
+/// The walk stops at the first invalid address, the entries on its left being sent by the client.
+#[must_use]
+pub fn resolve_client_ip(peer: Option<IpAddr>, headers: &HeaderMap, trusted_proxies: &[IpNet]) -> Option<IpAddr> {
+    let is_trusted = |ip: &IpAddr| trusted_proxies.iter().any(|net| net.contains(ip));
+    if peer.as_ref().is_some_and(|ip| !is_trusted(ip)) {
+        return peer;
+    }
+    // the last valid address, the closest to the client
+    let mut client = peer;
+    for value in headers.get_all("x-forwarded-for").iter().rev() {
+        let Ok(value) = value.to_str() else {
+            return client;
+        };
+        for entry in value.rsplit(',') {
+            let Ok(ip) = entry.trim().parse::<IpAddr>() else {
+                return client;
+            };
+            client = Some(ip);
+            if !is_trusted(&ip) {
+                return client;
             }
         }
-        match parts.extract::<ConnectInfo<SocketAddr>>().await {
-            Ok(ConnectInfo(addr)) => Ok(ClientIp(Some(addr.ip()))),
-            Err(_) => Ok(ClientIp(None)),
-        }
     }
+    client
 }
 
 impl fmt::Display for ClientIp {
@@ -5,15 +5,22 @@
 //! Utility APIs for axum
 
 pub mod auth;
+pub mod compression;
+pub mod cors;
 pub mod embedded;
+pub mod etag;
 pub mod extractors;
+pub mod ratelimit;
+pub mod security;
 pub mod sse;
+pub mod unix;
 
 use axum::http::StatusCode;
 use axum::Json;
 use log::error;
 
 use crate::utils::apierror::ApiError;
+use crate::utils::context::with_request_context;
 
 /// Defines an API response
 pub type ApiResult<T> = Result<(StatusCode, Json<T>), (StatusCode, Json<ApiError>)>;
@@ -23,12 +30,21 @@ pub type ApiResult<T> = Result<(StatusCode, Json<T>), (StatusCode, Json<ApiError
 /// # Panics
 ///
 /// Panic when the HTTP code is not a correct status code
-pub fn response_error_http(http: u16, error: ApiError) -> (StatusCode, Json<ApiError>) {
+pub fn response_error_http(http: u16, mut error: ApiError) -> (StatusCode, Json<ApiError>) {
+    if error.request_id.is_none() {
+        // so that the client can report the error with the identifier found in the logs
+        if let Some((request_id, client_request_id)) =
+            with_request_context(|context| (context.request_id.clone(), context.client_request_id.clone()))
+        {
+            error.request_id = Some(request_id);
+            error.client_request_id = client_request_id;
+        }
+    }
     if http == 500 {
         // log internal errors
-        error!("{}", error);
+        error!("{error}");
         if let Some(backtrace) = &error.backtrace {
-            error!("{}", backtrace);
+            error!("{backtrace}");
         }
     }
     (StatusCode::from_u16(http).unwrap(), Json(error))
@@ -0,0 +1,280 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Limits of the rate of requests for each token or client address
+
+use std::collections::HashMap;
+use std::mem::take;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::extract::{Request, State};
+use axum::http::{header, HeaderValue, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use log::{error, warn};
+use redis::aio::MultiplexedConnection;
+
+use super::auth::Token;
+use crate::model::config::{RateLimitConfig, RedisConfig};
+use crate::utils::apierror::{error_too_many_requests, specialize, ApiError};
+use crate::utils::context::with_request_context;
+use crate::utils::FaillibleFuture;
+
+/// The maximum number of keys tracked in a generation of a limiter
+const RATE_LIMITER_GENERATION_CAPACITY: usize = 10_000;
+
+/// The script applying the generic cell rate algorithm on Redis, with the time of the server in microseconds
+///
+/// The arguments are the interval consumed by each request and the period, in microseconds.
+/// It returns the excess in microseconds when the request is rejected, 0 otherwise.
+const RATE_LIMITER_SCRIPT: &str = r"
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000000 + tonumber(time[2])
+local arrival = tonumber(redis.call('GET', KEYS[1]) or now)
+if arrival < now then arrival = now end
+local next = arrival + tonumber(ARGV[1])
+local excess = next - now - tonumber(ARGV[2])
+if excess > 0 then return excess end
+redis.call('SET', KEYS[1], string.format('%.0f', next), 'PX', math.ceil((next - now) / 1000))
+return 0
+";
+
+/// Trait for an axum state that is able to verify the tokens of requests, so that the rate of each principal is limited
+pub trait AxumStateForRateLimits {
+    /// Gets the name of the principal authenticated by a token, failing when the token is invalid
+    fn get_token_principal<'a>(&'a self, token: &'a Token) -> FaillibleFuture<'a, String>;
+}
+
+/// The theoretical times of arrival of the next requests, in two generations
+///
+/// A new generation is started after each period, or when the current one is full, so that the memory is bounded
+/// without scanning the keys. Starting a generation after a period never forgets the keys that are still limited.
+#[derive(Debug)]
+struct Arrivals {
+    /// The arrivals recorded in the current generation
+    current: HashMap<String, Instant>,
+    /// The arrivals recorded in the previous generation
+    previous: HashMap<String, Instant>,
+    /// The time at which the current generation started
+    started: Instant,
+}
+
+/// A limiter of the rate of requests for each key, allowing bursts up to the limit
+///
+/// This implements the generic cell rate algorithm: each request consumes an interval of `period / limit`
+/// and is rejected when the consumed intervals are ahead of the current time by more than the period.
+#[derive(Debug)]
+pub struct RateLimiter {
+    /// The time consumed by each request, `None` when there is no limit
+    interval: Option<Duration>,
+    /// The period for the limit
+    period: Duration,
+    /// The theoretical time of arrival of the next request for each key
+    arrivals: Mutex<Arrivals>,
+    /// The arrivals shared with the other instances on Redis, if any
+    shared: Option<SharedArrivals>,
+}
+
+/// The theoretical times of arrival shared between the instances on Redis
+#[derive(Debug)]
+struct SharedArrivals {
+    /// The client for the Redis server
+    client: redis::Client,
+    /// The prefix of the keys on Redis
+    prefix: String,
+    /// The script applying the algorithm
+    script: redis::Script,
+    /// The connection, opened on first use
+    connection: tokio::sync::Mutex<Option<MultiplexedConnection>>,
+}
+
+impl RateLimiter {
+    /// Creates a limiter allowing `limit` requests per `period` for each key, 0 for no limit
+    #[must_use]
+    pub fn new(limit: u32, period: Duration) -> Self {
+        Self {
+            interval: (limit > 0).then(|| period / limit),
+            period,
+            arrivals: Mutex::new(Arrivals {
+                current: HashMap::new(),
+                previous: HashMap::new(),
+                started: Instant::now(),
+            }),
+            shared: None,
+        }
+    }
+
+    /// Shares the limits with the other instances through a Redis server, the keys starting with the given prefix
+    #[must_use]
+    pub fn with_shared(mut self, client: redis::Client, prefix: String) -> Self {
+        self.shared = Some(SharedArrivals {
+            client,
+            prefix,
+            script: redis::Script::new(RATE_LIMITER_SCRIPT),
+            connection: tokio::sync::Mutex::new(None),
+        });
+        self
+    }
+
+    /// Records a request for a key, on Redis when the limits are shared, locally otherwise
+    ///
+    /// When Redis cannot be reached, the limits are applied locally to each instance.
+    ///
+    /// # Errors
+    ///
+    /// Returns the time to wait before retrying when the request exceeds the limit
+    pub async fn acquire(&self, key: &str) -> Result<(), Duration> {
+        let Some(interval) = self.interval else {
+            return Ok(());
+        };
+        if let Some(shared) = &self.shared {
+            match shared.check(key, interval, self.period).await {
+                Ok(result) => return result,
+                Err(e) => warn!("failed to check the rate limit on redis, limiting locally: {e}"),
+            }
+        }
+        self.check(key, Instant::now())
+    }
+
+    /// Records a request for a key
+    ///
+    /// # Errors
+    ///
+    /// Returns the time to wait before retrying when the request exceeds the limit
+    pub fn check(&self, key: &str, now: Instant) -> Result<(), Duration> {
+        let Some(interval) = self.interval else {
+            return Ok(());
+        };
+        let mut arrivals = self.arrivals.lock().unwrap();
+        if now.saturating_duration_since(arrivals.started) >= self.period
+            || arrivals.current.len() >= RATE_LIMITER_GENERATION_CAPACITY
+        {
+            arrivals.previous = take(&mut arrivals.current);
+            arrivals.started = now;
+        }
+        let arrival = arrivals
+            .current
+            .get(key)
+            .or_else(|| arrivals.previous.get(key))
+            .map_or(now, |arrival| (*arrival).max(now));
+        let next = arrival + interval;
+        let ahead = next.saturating_duration_since(now);
+        if let Some(excess) = ahead.checked_sub(self.period).filter(|excess| !excess.is_zero()) {
+            return Err(excess);
+        }
+        arrivals.current.insert(key.to_string(), next);
+        Ok(())
+    }
+}
+
+impl SharedArrivals {
+    /// Records a request for a key on Redis
+    async fn check(&self, key: &str, interval: Duration, period: Duration) -> Result<Result<(), Duration>, ApiError> {
+        let mut connection = self.connection.lock().await;
+        if connection.is_none() {
+            *connection = Some(self.client.get_multiplexed_async_connection().await?);
+        }
+        let Some(connection) = connection.as_mut() else {
+            return Ok(Ok(()));
+        };
+        let result = self
+            .script
+            .key(format!("{}:{key}", self.prefix))
+            .arg(u64::try_from(interval.as_micros()).unwrap_or(u64::MAX))
+            .arg(u64::try_from(period.as_micros()).unwrap_or(u64::MAX))
+            .invoke_async::<u64>(connection)
+            .await;
+        match result {
+            Ok(0) => Ok(Ok(())),
+            Ok(excess) => Ok(Err(Duration::from_micros(excess))),
+            Err(e) => {
+                // reconnect on the next request
+                *self.connection.lock().await = None;
+                Err(e.into())
+            }
+        }
+    }
+}
+
+/// The rate limiters for the limited operations
+#[derive(Debug, Clone)]
+pub struct RateLimits {
+    /// The limiter for the publications
+    pub publish: Arc<RateLimiter>,
+    /// The limiter for the crate downloads
+    pub downloads: Arc<RateLimiter>,
+    /// The limiter for the searches
+    pub search: Arc<RateLimiter>,
+}
+
+impl RateLimits {
+    /// Creates the limiters from the configuration, shared with the other instances when a Redis server is configured
+    #[must_use]
+    pub fn new(config: &RateLimitConfig, redis: &RedisConfig) -> Self {
+        let client = redis.uri.as_deref().and_then(|uri| {
+            redis::Client::open(uri)
+                .inspect_err(|e| error!("invalid redis URI, the rate limits are not shared: {e}"))
+                .ok()
+        });
+        let limiter = |name: &str, limit: u32, period: Duration| {
+            let limiter = RateLimiter::new(limit, period);
+            Arc::new(match &client {
+                Some(client) => limiter.with_shared(client.clone(), format!("{}:ratelimit:{name}", redis.channel)),
+                None => limiter,
+            })
+        };
+        Self {
+            publish: limiter("publish", config.publish_per_minute, Duration::from_secs(60)),
+            downloads: limiter("downloads", config.downloads_per_second, Duration::from_secs(1)),
+            search: limiter("search", config.search_per_second, Duration::from_secs(1)),
+        }
+    }
+}
+
+/// Gets the keys for the rate of a request: the address of the client, and its principal when it has a valid token
+async fn get_rate_keys<S: AxumStateForRateLimits>(state: &S, token: Option<Token>) -> Vec<String> {
+    let mut keys = vec![match with_request_context(|context| context.client_ip).flatten() {
+        Some(ip) => format!("ip:{ip}"),
+        None => String::from("anonymous"),
+    }];
+    if let Some(token) = token {
+        // only verified tokens are accounted separately, so that forged ones cannot get a fresh allowance
+        if let Ok(principal) = state.get_token_principal(&token).await {
+            keys.push(format!("principal:{principal}"));
+        }
+    }
+    keys
+}
+
+/// Middleware that rejects the requests exceeding the rate of a limiter, with the time to wait in `Retry-After`
+pub async fn rate_limit<S: AxumStateForRateLimits>(
+    State((limiter, state)): State<(Arc<RateLimiter>, Arc<S>)>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let token = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(Token::try_parse);
+    for key in get_rate_keys(state.as_ref(), token).await {
+        if let Err(retry_after) = limiter.acquire(&key).await {
+            // round up so that a client retrying after the given number of seconds is accepted
+            let seconds = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+            let error = specialize(
+                error_too_many_requests(),
+                format!("rate limit exceeded, retry after {seconds} seconds"),
+            );
+            return (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(header::RETRY_AFTER, HeaderValue::from(seconds))],
+                Json(error),
+            )
+                .into_response();
+        }
+    }
+    next.run(request).await
+}
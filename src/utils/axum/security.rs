@@ -0,0 +1,83 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Security headers for all the responses
+
+use std::sync::Arc;
+
+use axum::extract::{Request, State};
+use axum::http::{header, HeaderMap, HeaderName, HeaderValue};
+use axum::middleware::Next;
+use axum::response::Response;
+
+use crate::model::config::SecurityHeadersConfig;
+
+/// The prefix of the paths for the generated documentation
+const DOCS_PATH_PREFIX: &str = "/docs/";
+
+/// The security headers ready to be applied to the responses
+#[derive(Debug, Clone)]
+pub struct SecurityHeaders {
+    /// The headers for all the responses
+    common: Vec<(HeaderName, HeaderValue)>,
+    /// The content security policy for the web application and the API
+    csp: Option<HeaderValue>,
+    /// The content security policy for the generated documentation
+    docs_csp: Option<HeaderValue>,
+}
+
+impl SecurityHeaders {
+    /// Prepares the configured security headers
+    ///
+    /// # Panics
+    ///
+    /// Panic when the configuration contains invalid header values,
+    /// they are expected to have been validated when loading the configuration.
+    #[must_use]
+    pub fn new(config: &SecurityHeadersConfig) -> Self {
+        let value = |value: &Option<String>| {
+            value
+                .as_deref()
+                .map(|value| HeaderValue::from_str(value).expect("invalid security header"))
+        };
+        let mut common = Vec::new();
+        if let Some(hsts) = value(&config.hsts) {
+            common.push((header::STRICT_TRANSPORT_SECURITY, hsts));
+        }
+        if let Some(frame_options) = value(&config.frame_options) {
+            common.push((header::X_FRAME_OPTIONS, frame_options));
+        }
+        if config.no_sniff {
+            common.push((header::X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff")));
+        }
+        Self {
+            common,
+            csp: value(&config.content_security_policy),
+            docs_csp: value(&config.docs_content_security_policy),
+        }
+    }
+
+    /// Applies the headers to a response for a path, without replacing those set by the handler
+    fn apply(&self, path: &str, headers: &mut HeaderMap) {
+        let csp = if path.starts_with(DOCS_PATH_PREFIX) {
+            self.docs_csp.as_ref()
+        } else {
+            self.csp.as_ref()
+        };
+        let csp = csp.map(|csp| (header::CONTENT_SECURITY_POLICY, csp.clone()));
+        for (name, value) in self.common.iter().chain(csp.as_ref()) {
+            if !headers.contains_key(name) {
+                headers.insert(name.clone(), value.clone());
+            }
+        }
+    }
+}
+
+/// Middleware that applies the security headers to all the responses
+pub async fn security_headers(State(headers): State<Arc<SecurityHeaders>>, request: Request, next: Next) -> Response {
+    let path = request.uri().path().to_string();
+    let mut response = next.run(request).await;
+    headers.apply(&path, response.headers_mut());
+    response
+}
@@ -0,0 +1,73 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Serving an axum application on a Unix domain socket
+
+use std::fs::Permissions;
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::pin::pin;
+use std::time::Duration;
+
+use axum::Router;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto::Builder;
+use hyper_util::service::TowerToHyperService;
+use log::error;
+use tokio::net::UnixListener;
+use tokio::task::JoinSet;
+use tokio_util::sync::CancellationToken;
+
+/// Serves an application on a Unix domain socket
+///
+/// A socket left at the same path by a previous run is removed.
+/// The permissions of the socket are set to `mode`, when given, so that a reverse proxy running as another user can connect.
+/// When `shutdown` is cancelled, new connections are no longer accepted and the open ones are drained.
+pub async fn serve_unix(path: &str, mode: Option<u32>, app: Router, shutdown: CancellationToken) -> Result<(), std::io::Error> {
+    if let Ok(metadata) = tokio::fs::symlink_metadata(path).await {
+        if metadata.file_type().is_socket() {
+            tokio::fs::remove_file(path).await?;
+        }
+    }
+    let listener = UnixListener::bind(path)?;
+    if let Some(mode) = mode {
+        tokio::fs::set_permissions(path, Permissions::from_mode(mode)).await?;
+    }
+    let mut connections = JoinSet::new();
+    loop {
+        // forget about the closed connections
+        while connections.try_join_next().is_some() {}
+        let accepted = tokio::select! {
+            accepted = listener.accept() => accepted,
+            () = shutdown.cancelled() => break,
+        };
+        let socket = match accepted {
+            Ok((socket, _)) => socket,
+            Err(e) => {
+                // for example when running out of file descriptors, wait a bit before accepting again
+                error!("failed to accept connection on {path}: {e}");
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                continue;
+            }
+        };
+        let service = TowerToHyperService::new(app.clone());
+        let shutdown = shutdown.clone();
+        connections.spawn(async move {
+            let builder = Builder::new(TokioExecutor::new());
+            let mut connection = pin!(builder.serve_connection_with_upgrades(TokioIo::new(socket), service));
+            let result = tokio::select! {
+                result = connection.as_mut() => result,
+                () = shutdown.cancelled() => {
+                    // finish the in-flight requests, then close
+                    connection.as_mut().graceful_shutdown();
+                    connection.await
+                }
+            };
+            if let Err(e) = result {
+                error!("failed to serve connection: {e}");
+            }
+        });
+    }
+    while connections.join_next().await.is_some() {}
+    Ok(())
+}
@@ -0,0 +1,99 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Rendering of SVG badges, in the flat style of shields.io
+
+use std::fmt::Write;
+
+/// The color for a successful or positive badge
+pub const COLOR_GREEN: &str = "#4c1";
+/// The color for a mitigated badge
+pub const COLOR_YELLOW: &str = "#dfb317";
+/// The color for an orange badge
+pub const COLOR_ORANGE: &str = "#fe7d37";
+/// The color for a failure or negative badge
+pub const COLOR_RED: &str = "#e05d44";
+/// The color for an informative badge
+pub const COLOR_BLUE: &str = "#007ec6";
+/// The color for an inactive or unknown badge
+pub const COLOR_GREY: &str = "#9f9f9f";
+
+/// The estimated width of a character, in pixels, for the font used in badges
+const CHAR_WIDTH: usize = 7;
+/// The horizontal padding on each side of a text
+const PADDING: usize = 6;
+
+/// Gets the width of the box for a text
+fn text_width(text: &str) -> usize {
+    text.chars().count() * CHAR_WIDTH + 2 * PADDING
+}
+
+/// Escapes a text for XML
+fn escape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&apos;"),
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+/// Renders a badge with a label and a message
+#[must_use]
+pub fn render_badge(label: &str, message: &str, color: &str) -> String {
+    let label_width = text_width(label);
+    let message_width = text_width(message);
+    let width = label_width + message_width;
+    let label = escape(label);
+    let message = escape(message);
+    let mut svg = String::new();
+    write!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="20" role="img" aria-label="{label}: {message}">"#
+    )
+    .unwrap();
+    write!(svg, "<title>{label}: {message}</title>").unwrap();
+    svg.push_str(r##"<linearGradient id="s" x2="0" y2="100%"><stop offset="0" stop-color="#bbb" stop-opacity=".1"/><stop offset="1" stop-opacity=".1"/></linearGradient>"##);
+    write!(
+        svg,
+        r##"<clipPath id="r"><rect width="{width}" height="20" rx="3" fill="#fff"/></clipPath>"##
+    )
+    .unwrap();
+    write!(
+        svg,
+        r##"<g clip-path="url(#r)"><rect width="{label_width}" height="20" fill="#555"/><rect x="{label_width}" width="{message_width}" height="20" fill="{color}"/><rect width="{width}" height="20" fill="url(#s)"/></g>"##
+    )
+    .unwrap();
+    let label_x = label_width / 2;
+    let message_x = label_width + message_width / 2;
+    write!(
+        svg,
+        r##"<g fill="#fff" text-anchor="middle" font-family="Verdana,Geneva,DejaVu Sans,sans-serif" font-size="11"><text x="{label_x}" y="15" fill="#010101" fill-opacity=".3">{label}</text><text x="{label_x}" y="14">{label}</text><text x="{message_x}" y="15" fill="#010101" fill-opacity=".3">{message}</text><text x="{message_x}" y="14">{message}</text></g>"##
+    )
+    .unwrap();
+    svg.push_str("</svg>");
+    svg
+}
+
+/// Formats a count in a compact form, as in `12.3k` or `4.5M`
+#[must_use]
+#[allow(clippy::cast_precision_loss)]
+pub fn format_count(count: i64) -> String {
+    let value = count as f64;
+    if count >= 1_000_000_000 {
+        format!("{:.1}G", value / 1_000_000_000.0)
+    } else if count >= 1_000_000 {
+        format!("{:.1}M", value / 1_000_000.0)
+    } else if count >= 1_000 {
+        format!("{:.1}k", value / 1_000.0)
+    } else {
+        count.to_string()
+    }
+}
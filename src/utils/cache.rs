@@ -0,0 +1,121 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! In-memory cache of values computed per key, invalidated explicitly or after a maximum age
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// An in-memory cache of values by key
+///
+/// Values computed before an invalidation of their key are not stored, so that a concurrent invalidation is never lost,
+/// while the values for the other keys are still stored.
+/// The whole cache is cleared when it is full.
+pub struct TimedCache<V> {
+    /// The maximum number of entries, 0 to disable the cache
+    capacity: usize,
+    /// The maximum age of an entry
+    max_age: Duration,
+    /// The cached data
+    data: Mutex<TimedCacheData<V>>,
+}
+
+/// The generation of a key in a cache, taken before computing its value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheGeneration {
+    /// The generation of the whole cache
+    global: u64,
+    /// The generation of the key
+    key: u64,
+}
+
+/// The data of a cache
+struct TimedCacheData<V> {
+    /// Incremented when the whole cache is invalidated
+    global: u64,
+    /// The generation of the keys that were invalidated, incremented on each invalidation of the key
+    generations: HashMap<String, u64>,
+    /// The cached values with the time they were stored
+    entries: HashMap<String, (Instant, V)>,
+}
+
+impl<V> TimedCacheData<V> {
+    /// Gets the current generation of a key
+    fn generation(&self, key: &str) -> CacheGeneration {
+        CacheGeneration {
+            global: self.global,
+            key: self.generations.get(key).copied().unwrap_or_default(),
+        }
+    }
+
+    /// Clears all the entries, the values being computed are then no longer stored
+    fn clear(&mut self) {
+        self.global += 1;
+        self.generations.clear();
+        self.entries.clear();
+    }
+}
+
+impl<V: Clone> TimedCache<V> {
+    /// Creates an empty cache
+    #[must_use]
+    pub fn new(capacity: usize, max_age: Duration) -> Self {
+        Self {
+            capacity,
+            max_age,
+            data: Mutex::new(TimedCacheData {
+                global: 0,
+                generations: HashMap::new(),
+                entries: HashMap::new(),
+            }),
+        }
+    }
+
+    /// Gets the value for a key, if it is cached and not too old
+    #[must_use]
+    pub fn get(&self, key: &str) -> Option<V> {
+        let data = self.data.lock().unwrap();
+        let (stored_on, value) = data.entries.get(key)?;
+        (stored_on.elapsed() < self.max_age).then(|| value.clone())
+    }
+
+    /// Gets the current generation of a key, to be given back when storing a value computed afterwards
+    #[must_use]
+    pub fn generation(&self, key: &str) -> CacheGeneration {
+        self.data.lock().unwrap().generation(key)
+    }
+
+    /// Stores a value computed since the given generation, unless the key was invalidated in the meantime
+    pub fn insert(&self, key: &str, value: V, generation: CacheGeneration) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut data = self.data.lock().unwrap();
+        if data.generation(key) != generation {
+            return;
+        }
+        if data.entries.len() >= self.capacity && !data.entries.contains_key(key) {
+            data.clear();
+        }
+        data.entries.insert(key.to_string(), (Instant::now(), value));
+    }
+
+    /// Invalidates the value for a key
+    pub fn invalidate(&self, key: &str) {
+        let mut data = self.data.lock().unwrap();
+        if data.generations.len() >= self.capacity.max(1) && !data.generations.contains_key(key) {
+            // bounds the tracked generations, the values being computed for all the keys are then not stored
+            data.clear();
+            return;
+        }
+        *data.generations.entry(key.to_string()).or_default() += 1;
+        data.entries.remove(key);
+    }
+
+    /// Invalidates all the values
+    pub fn invalidate_all(&self) {
+        self.data.lock().unwrap().clear();
+    }
+}
@@ -0,0 +1,123 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Context of the HTTP request being handled, attached to the logs
+
+use std::fmt::Display;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+
+use axum::extract::{ConnectInfo, RawPathParams, Request, State};
+use axum::http::HeaderValue;
+use axum::middleware::Next;
+use axum::response::Response;
+use ipnet::IpNet;
+
+use crate::model::auth::{AuthenticationPrincipal, TrafficSubject};
+use crate::utils::axum::extractors::resolve_client_ip;
+
+/// The name of the header for the request identifier
+pub const HEADER_REQUEST_ID: &str = "x-request-id";
+
+/// The context of the HTTP request being handled
+#[derive(Debug)]
+pub struct RequestContext {
+    /// The identifier of the request, generated by the registry
+    pub request_id: String,
+    /// The identifier of the request given by the client, if any
+    pub client_request_id: Option<String>,
+    /// The address of the client, if known
+    pub client_ip: Option<IpAddr>,
+    /// The authenticated principal, if any
+    pub principal: Mutex<Option<String>>,
+    /// The name of the crate targeted by the request, if any
+    pub package: Mutex<Option<String>>,
+    /// The subject to which the traffic for this request is accounted, if any
+    pub traffic_subject: Mutex<Option<TrafficSubject>>,
+}
+
+tokio::task_local! {
+    /// The context for the request being handled by the current task
+    static REQUEST_CONTEXT: RequestContext;
+}
+
+/// Executes a function with the context of the current request, if any
+pub fn with_request_context<F, R>(f: F) -> Option<R>
+where
+    F: FnOnce(&RequestContext) -> R,
+{
+    REQUEST_CONTEXT.try_with(f).ok()
+}
+
+/// Records the authenticated principal for the current request, if any
+pub fn set_request_principal(principal: &AuthenticationPrincipal) {
+    let name = match principal {
+        AuthenticationPrincipal::User { email, .. } => email.clone(),
+        AuthenticationPrincipal::Service { token_id } => format!("service:{token_id}"),
+        AuthenticationPrincipal::SelfAuth => String::from("self"),
+    };
+    with_request_context(|context| *context.principal.lock().unwrap() = Some(name));
+}
+
+/// Records the name of the crate targeted by the current request, if any
+pub fn set_request_package(package: &str) {
+    with_request_context(|context| *context.package.lock().unwrap() = Some(package.to_string()));
+}
+
+/// Records the subject to which the traffic of the current request is accounted, if any
+pub fn set_request_traffic_subject(subject: TrafficSubject) {
+    with_request_context(|context| *context.traffic_subject.lock().unwrap() = Some(subject));
+}
+
+/// Formats a line of the text logs, prefixed with the identifier of the current request, if any
+pub fn format_log_line(timestamp: impl Display, level: log::Level, message: impl Display) -> String {
+    match with_request_context(|context| context.request_id.clone()) {
+        Some(request_id) => format!("{timestamp}\t{level}\t[{request_id}] {message}"),
+        None => format!("{timestamp}\t{level}\t{message}"),
+    }
+}
+
+/// Middleware that sets up the context for each HTTP request
+///
+/// The identifier of the request is generated and returned in the response.
+/// The identifier given by the client in the `x-request-id` header, if any, is kept aside because it cannot be trusted.
+/// The address of the client is resolved with the forwarding headers set by the trusted proxies.
+pub async fn track_request_context(
+    State(trusted_proxies): State<Arc<Vec<IpNet>>>,
+    params: Option<RawPathParams>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let request_id = uuid::Uuid::new_v4().to_string();
+    let client_request_id = request
+        .headers()
+        .get(HEADER_REQUEST_ID)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| !value.is_empty() && value.len() <= 128)
+        .map(str::to_string);
+    let package = params.and_then(|params| {
+        params
+            .iter()
+            .find(|(name, _)| *name == "package")
+            .map(|(_, value)| value.to_string())
+    });
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    let client_ip = resolve_client_ip(peer, request.headers(), &trusted_proxies);
+    let context = RequestContext {
+        request_id: request_id.clone(),
+        client_request_id,
+        client_ip,
+        principal: Mutex::new(None),
+        package: Mutex::new(package),
+        traffic_subject: Mutex::new(None),
+    };
+    let mut response = REQUEST_CONTEXT.scope(context, next.run(request)).await;
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(HEADER_REQUEST_ID, value);
+    }
+    response
+}
@@ -69,6 +69,22 @@ impl RwSqlitePool {
         })
     }
 
+    /// Writes a consistent snapshot of the database to a new file, without blocking writers
+    ///
+    /// This uses `VACUUM INTO`, which copies the database within a single read transaction.
+    /// In WAL mode, the snapshot is then consistent even with writes in flight and the writers are not blocked.
+    pub async fn snapshot_into(&self, path: &str) -> Result<(), ApiError> {
+        let mut connection = self.read.acquire().await?;
+        sqlx::query("VACUUM INTO $1").bind(path).execute(&mut *connection).await?;
+        Ok(())
+    }
+
+    /// Closes all the connections, checkpointing the journal
+    pub async fn close(&self) {
+        self.read.close().await;
+        self.write.close().await;
+    }
+
     /// Acquires a write connection
     pub async fn acquire_write(&self, operation: &'static str) -> Result<AppTransaction, sqlx::Error> {
         match self.write.begin().await {
@@ -122,7 +138,7 @@ pub struct CheckedOutAppTransaction<'t> {
     lock: ResourceLock<'t, Transaction<'static, Sqlite>>,
 }
 
-impl<'t> Deref for CheckedOutAppTransaction<'t> {
+impl Deref for CheckedOutAppTransaction<'_> {
     type Target = SqliteConnection;
 
     fn deref(&self) -> &Self::Target {
@@ -130,7 +146,7 @@ impl<'t> Deref for CheckedOutAppTransaction<'t> {
     }
 }
 
-impl<'t> DerefMut for CheckedOutAppTransaction<'t> {
+impl DerefMut for CheckedOutAppTransaction<'_> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.lock
     }
@@ -1,5 +1,14 @@
+use std::io::Read;
+
 use data_encoding::HEXLOWER;
-use ring::digest::{Context, SHA256};
+use md5::{Digest, Md5};
+use ring::digest::{Context, SHA256, SHA512};
+
+/// Computes the MD5 digest of bytes, as used for the `ETag` of the objects in S3
+#[must_use]
+pub fn md5(buffer: &[u8]) -> String {
+    HEXLOWER.encode(Md5::digest(buffer).as_slice())
+}
 
 /// Computes the SHA256 digest of bytes
 #[must_use]
@@ -9,3 +18,35 @@ pub fn sha256(buffer: &[u8]) -> String {
     let digest = context.finish();
     HEXLOWER.encode(digest.as_ref())
 }
+
+/// Computes the raw SHA256 digest of bytes, as used for the checksums of the uploads to S3
+#[must_use]
+pub fn sha256_bytes(buffer: &[u8]) -> Vec<u8> {
+    let mut context = Context::new(&SHA256);
+    context.update(buffer);
+    context.finish().as_ref().to_vec()
+}
+
+/// Computes the SHA256 digest of the bytes from a reader, without keeping them in memory
+pub fn sha256_reader(reader: &mut impl Read) -> std::io::Result<String> {
+    let mut context = Context::new(&SHA256);
+    let mut buffer = [0_u8; 8192];
+    loop {
+        let count = reader.read(&mut buffer)?;
+        if count == 0 {
+            break;
+        }
+        context.update(&buffer[..count]);
+    }
+    let digest = context.finish();
+    Ok(HEXLOWER.encode(digest.as_ref()))
+}
+
+/// Computes the SHA512 digest of bytes
+#[must_use]
+pub fn sha512(buffer: &[u8]) -> String {
+    let mut context = Context::new(&SHA512);
+    context.update(buffer);
+    let digest = context.finish();
+    HEXLOWER.encode(digest.as_ref())
+}
@@ -0,0 +1,66 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Rendering of Markdown documents, such as READMEs, to HTML
+//!
+//! The GitHub-flavored extensions commonly found in READMEs are supported (tables, task lists, strike-through).
+//! Raw HTML is escaped and unsafe URLs are neutralized so that the output can be embedded in a page.
+
+use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag};
+
+/// Renders a Markdown document to an HTML fragment
+#[must_use]
+pub fn render_markdown(input: &str) -> String {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_GFM;
+    let parser = Parser::new_ext(input, options).map(|event| match event {
+        Event::Html(html) | Event::InlineHtml(html) => Event::Text(html),
+        Event::Start(Tag::Link {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) => Event::Start(Tag::Link {
+            link_type,
+            dest_url: sanitize_url(dest_url),
+            title,
+            id,
+        }),
+        Event::Start(Tag::Image {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) => Event::Start(Tag::Image {
+            link_type,
+            dest_url: sanitize_url(dest_url),
+            title,
+            id,
+        }),
+        event => event,
+    });
+    let mut output = String::new();
+    html::push_html(&mut output, parser);
+    output
+}
+
+/// Neutralizes the URLs with a scheme that could execute code
+fn sanitize_url(url: CowStr<'_>) -> CowStr<'_> {
+    let normalized = url
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .collect::<String>()
+        .to_ascii_lowercase();
+    if ["javascript:", "vbscript:", "data:", "file:"]
+        .iter()
+        .any(|scheme| normalized.starts_with(scheme))
+    {
+        CowStr::Borrowed("#")
+    } else {
+        url
+    }
+}
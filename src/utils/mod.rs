@@ -6,6 +6,7 @@
 
 use std::path::Path;
 use std::process::Stdio;
+use std::sync::OnceLock;
 use std::time::{Duration, Instant};
 
 use apierror::{error_backend_failure, specialize, ApiError};
@@ -15,11 +16,17 @@ use tokio::process::Command;
 
 pub mod apierror;
 pub mod axum;
+pub mod badges;
+pub mod cache;
 pub mod concurrent;
+pub mod context;
 pub mod db;
 pub mod hashes;
+pub mod markdown;
+pub mod scheduler;
 pub mod shared;
 pub mod sigterm;
+pub mod telemetry;
 pub mod token;
 
 /// Pushes an element in a vector if it is not present yet
@@ -44,14 +51,56 @@ pub fn stale_instant() -> Instant {
     now.checked_sub(Duration::from_secs(60 * 60 * 24 * 7)).unwrap()
 }
 
+/// The environment variables for the spawned processes (git, Cargo, rustup), set at startup
+static CHILD_ENV: OnceLock<Vec<(String, String)>> = OnceLock::new();
+
+/// Sets the environment variables for the spawned processes
+///
+/// This is expected to be called once at startup, the subsequent calls are ignored.
+pub fn set_child_env(env: Vec<(String, String)>) {
+    let _ = CHILD_ENV.set(env);
+}
+
+/// Creates a command for a program, with the environment variables for the spawned processes
+#[must_use]
+pub fn new_command(program: &str) -> Command {
+    let mut command = Command::new(program);
+    if let Some(env) = CHILD_ENV.get() {
+        command.envs(env.iter().map(|(name, value)| (name, value)));
+    }
+    command
+}
+
+/// The outbound HTTP proxy for the HTTP clients, set at startup
+static OUTBOUND_PROXY: OnceLock<reqwest::Proxy> = OnceLock::new();
+
+/// Sets the outbound HTTP proxy for the HTTP clients
+///
+/// This is expected to be called once at startup, the subsequent calls are ignored.
+pub fn set_outbound_proxy(proxy: reqwest::Proxy) {
+    let _ = OUTBOUND_PROXY.set(proxy);
+}
+
+/// Gets a builder for the HTTP clients, using the outbound HTTP proxy when one is set
+///
+/// Otherwise, the clients honor the conventional `HTTPS_PROXY`, `HTTP_PROXY` and `NO_PROXY` environment variables.
+pub fn http_client_builder() -> reqwest::ClientBuilder {
+    let builder = reqwest::Client::builder();
+    match OUTBOUND_PROXY.get() {
+        Some(proxy) => builder.proxy(proxy.clone()),
+        None => builder,
+    }
+}
+
 /// Execute a git command
 pub async fn execute_git(location: &Path, args: &[&str]) -> Result<(), ApiError> {
     execute_at_location(location, "git", args, &[]).await.map(|_| ())
 }
 
 /// Execute a command at a location
+#[tracing::instrument(skip(location, input))]
 pub async fn execute_at_location(location: &Path, command: &str, args: &[&str], input: &[u8]) -> Result<Vec<u8>, ApiError> {
-    let mut child = Command::new(command)
+    let mut child = new_command(command)
         .current_dir(location)
         .args(args)
         .stdin(Stdio::piped())
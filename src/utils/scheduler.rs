@@ -0,0 +1,208 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Scheduler for the background jobs that run periodically or on demand
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{Local, NaiveDateTime};
+use log::error;
+use tokio::sync::Notify;
+
+use crate::model::scheduler::ScheduledJobStatus;
+use crate::utils::apierror::{error_conflict, error_invalid_request, error_not_found, specialize, ApiError};
+use crate::utils::FaillibleFuture;
+
+/// A job run by the scheduler
+pub type ScheduledJobFn = Box<dyn Fn() -> FaillibleFuture<'static, ()> + Send + Sync>;
+
+/// When a periodic job is run for the first time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FirstRun {
+    /// As soon as the job is scheduled
+    OnStart,
+    /// After a first period
+    AfterPeriod,
+}
+
+/// A job known to the scheduler
+struct ScheduledJob {
+    /// The current status of the job
+    status: ScheduledJobStatus,
+    /// Used to trigger a run on demand
+    trigger: Arc<Notify>,
+}
+
+/// The scheduler for the background jobs
+#[derive(Default)]
+pub struct Scheduler {
+    /// The scheduled jobs
+    jobs: Mutex<Vec<ScheduledJob>>,
+    /// Whether the scheduler is stopped, no job is started anymore
+    stopped: AtomicBool,
+    /// Notified each time a job finishes, to wake up `stop`
+    finished: Notify,
+}
+
+/// Gets the period of a job from a configured number of seconds, `0` meaning that the job is not run periodically
+#[must_use]
+pub fn period_from_secs(seconds: u64) -> Option<Duration> {
+    (seconds > 0).then(|| Duration::from_secs(seconds))
+}
+
+/// Gets the timestamp after a delay from now
+fn timestamp_after(delay: Duration) -> NaiveDateTime {
+    Local::now().naive_local() + chrono::Duration::from_std(delay).unwrap_or_default()
+}
+
+impl Scheduler {
+    /// Registers a job and spawns the task running it on its schedule
+    ///
+    /// A job without a period is only run on demand.
+    /// A disabled job is never run, even on demand.
+    /// A job is never run concurrently with itself.
+    pub fn schedule(
+        self: &Arc<Self>,
+        name: &str,
+        description: &str,
+        enabled: bool,
+        period: Option<Duration>,
+        first_run: FirstRun,
+        job: ScheduledJobFn,
+    ) {
+        let period = period.filter(|_| enabled);
+        let first_delay = period.map(|period| match first_run {
+            FirstRun::OnStart => Duration::ZERO,
+            FirstRun::AfterPeriod => period,
+        });
+        let trigger = Arc::new(Notify::new());
+        self.jobs.lock().unwrap().push(ScheduledJob {
+            status: ScheduledJobStatus {
+                name: name.to_string(),
+                description: description.to_string(),
+                enabled,
+                period: period.map(|period| period.as_secs()),
+                next_run_on: first_delay.map(timestamp_after),
+                ..Default::default()
+            },
+            trigger: trigger.clone(),
+        });
+        let scheduler = self.clone();
+        let name = name.to_string();
+        let _handle = tokio::spawn(async move {
+            let mut delay = first_delay;
+            loop {
+                if let Some(delay) = delay {
+                    tokio::select! {
+                        () = tokio::time::sleep(delay) => {}
+                        () = trigger.notified() => {}
+                    }
+                } else {
+                    trigger.notified().await;
+                }
+                if !scheduler.run(&name, &job).await {
+                    return;
+                }
+                delay = period;
+            }
+        });
+    }
+
+    /// Runs a job and records the outcome in its status
+    ///
+    /// Returns `false` without running the job when the scheduler is stopped.
+    async fn run(&self, name: &str, job: &ScheduledJobFn) -> bool {
+        {
+            // checked under the lock so that `stop` either sees the job running or prevents it from starting
+            let mut jobs = self.jobs.lock().unwrap();
+            if self.stopped.load(Ordering::Acquire) {
+                return false;
+            }
+            if let Some(job) = jobs.iter_mut().find(|job| job.status.name == name) {
+                job.status.is_running = true;
+                job.status.last_started_on = Some(Local::now().naive_local());
+                job.status.next_run_on = None;
+            }
+        }
+        let result = job().await;
+        if let Err(e) = &result {
+            error!("job {name} failed: {e}");
+            if let Some(backtrace) = &e.backtrace {
+                error!("{backtrace}");
+            }
+        }
+        self.update_status(name, |status| {
+            status.is_running = false;
+            status.last_finished_on = Some(Local::now().naive_local());
+            status.last_error = result.err().map(|e| e.to_string());
+            status.runs += 1;
+            status.next_run_on = status.period.map(|period| timestamp_after(Duration::from_secs(period)));
+        });
+        self.finished.notify_waiters();
+        true
+    }
+
+    /// Updates the status of a job
+    fn update_status<F>(&self, name: &str, action: F)
+    where
+        F: FnOnce(&mut ScheduledJobStatus),
+    {
+        if let Some(job) = self.jobs.lock().unwrap().iter_mut().find(|job| job.status.name == name) {
+            action(&mut job.status);
+        }
+    }
+
+    /// Gets the status of all the jobs
+    #[must_use]
+    pub fn get_statuses(&self) -> Vec<ScheduledJobStatus> {
+        self.jobs.lock().unwrap().iter().map(|job| job.status.clone()).collect()
+    }
+
+    /// Stops starting the jobs and waits for the running ones to finish
+    pub async fn stop(&self) {
+        {
+            let _jobs = self.jobs.lock().unwrap();
+            self.stopped.store(true, Ordering::Release);
+        }
+        loop {
+            // registered before checking the jobs so that a job finishing in-between is not missed
+            let finished = self.finished.notified();
+            tokio::pin!(finished);
+            finished.as_mut().enable();
+            if !self.jobs.lock().unwrap().iter().any(|job| job.status.is_running) {
+                return;
+            }
+            finished.await;
+        }
+    }
+
+    /// Triggers a run of a job now, in the background
+    ///
+    /// # Errors
+    ///
+    /// Returns an error when the job is unknown, disabled or already running, or when the scheduler is stopped
+    pub fn trigger(&self, name: &str) -> Result<ScheduledJobStatus, ApiError> {
+        let jobs = self.jobs.lock().unwrap();
+        if self.stopped.load(Ordering::Acquire) {
+            return Err(specialize(error_conflict(), String::from("the scheduler is stopped")));
+        }
+        let job = jobs
+            .iter()
+            .find(|job| job.status.name == name)
+            .ok_or_else(|| specialize(error_not_found(), format!("unknown job {name}")))?;
+        if !job.status.enabled {
+            return Err(specialize(
+                error_invalid_request(),
+                format!("job {name} is not activated in the configuration"),
+            ));
+        }
+        if job.status.is_running {
+            return Err(specialize(error_conflict(), format!("job {name} is already running")));
+        }
+        job.trigger.notify_one();
+        Ok(job.status.clone())
+    }
+}
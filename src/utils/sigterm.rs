@@ -13,6 +13,16 @@ use std::time::Duration;
 use futures::future::{select, Either};
 use tokio::signal::unix::{signal, SignalKind};
 
+/// Waits for the SIGTERM signal
+///
+/// # Panics
+///
+/// Raise a panic when the terminate signal cannot be obtained.
+pub async fn sigterm() {
+    let mut signal = signal(SignalKind::terminate()).unwrap();
+    signal.recv().await;
+}
+
 /// Executes the specified future and listen for SIGTERM to terminate early
 ///
 /// # Panics
@@ -0,0 +1,81 @@
+/*******************************************************************************
+ * Copyright (c) 2024 Cénotélie Opérations SAS (cenotelie.fr)
+ ******************************************************************************/
+
+//! Distributed tracing with the export of spans to an OpenTelemetry collector
+
+use axum::extract::{MatchedPath, Request};
+use axum::middleware::Next;
+use axum::response::Response;
+use log::{error, info};
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::trace::{Sampler, TracerProvider};
+use opentelemetry_sdk::{runtime, Resource};
+use tracing::field::Empty;
+use tracing::{info_span, Instrument};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+
+use crate::model::config::get_var;
+use crate::{CRATE_NAME, GIT_TAG};
+
+/// Sets up the export of tracing spans to an OpenTelemetry collector (OTLP over HTTP)
+///
+/// The export is only enabled when `REGISTRY_TRACING_OTLP_ENDPOINT` is set.
+/// The returned provider must be shut down before exiting so that the remaining spans are flushed.
+#[must_use]
+pub fn setup_tracing() -> Option<TracerProvider> {
+    let endpoint = get_var("REGISTRY_TRACING_OTLP_ENDPOINT").ok()?;
+    let service_name = get_var("REGISTRY_TRACING_SERVICE_NAME").unwrap_or_else(|_| String::from(CRATE_NAME));
+    let sample_ratio = get_var("REGISTRY_TRACING_SAMPLE_RATIO")
+        .map_or(1.0, |v| v.parse::<f64>().expect("invalid REGISTRY_TRACING_SAMPLE_RATIO"));
+    let exporter = match opentelemetry_otlp::SpanExporter::builder()
+        .with_http()
+        .with_endpoint(&endpoint)
+        .build()
+    {
+        Ok(exporter) => exporter,
+        Err(e) => {
+            error!("failed to setup the export of traces to {endpoint}: {e}");
+            return None;
+        }
+    };
+    let provider = TracerProvider::builder()
+        .with_batch_exporter(exporter, runtime::Tokio)
+        .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(sample_ratio))))
+        .with_resource(Resource::new([
+            KeyValue::new("service.name", service_name.clone()),
+            KeyValue::new("service.version", GIT_TAG),
+        ]))
+        .build();
+    let tracer = provider.tracer(service_name);
+    if let Err(e) = tracing_subscriber::registry()
+        .with(tracing_opentelemetry::layer().with_tracer(tracer))
+        .try_init()
+    {
+        error!("failed to setup tracing: {e}");
+        return None;
+    }
+    info!("exporting traces to {endpoint}");
+    Some(provider)
+}
+
+/// Middleware that wraps the handling of each HTTP request into a span
+pub async fn trace_request(matched_path: Option<MatchedPath>, request: Request, next: Next) -> Response {
+    let method = request.method().to_string();
+    // use the route instead of the actual path to limit the cardinality of span names
+    let route = matched_path.as_ref().map_or("fallback", MatchedPath::as_str);
+    let span = info_span!(
+        "http_request",
+        otel.name = format!("{method} {route}"),
+        otel.kind = "server",
+        http.request.method = method,
+        http.route = route,
+        http.response.status_code = Empty,
+    );
+    let response = next.run(request).instrument(span.clone()).await;
+    span.record("http.response.status_code", response.status().as_u16());
+    response
+}
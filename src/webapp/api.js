@@ -1,3 +1,16 @@
+// Gets the path under which the registry is served, set by the server in the base element of the pages
+function getBasePath() {
+  return new URL(document.baseURI).pathname;
+}
+
+// Gets the key in the local storage for an item, specific to the registry served under the base path
+function storageKey(name) {
+  const segments = getBasePath()
+    .split("/")
+    .filter((segment) => segment.length > 0);
+  return ["cratery", ...segments, name].join("-");
+}
+
 async function onResponseJson(response) {
   if (response.status !== 200) {
     throw await response.json();
@@ -6,23 +19,36 @@ async function onResponseJson(response) {
   }
 }
 
+async function apiGetAllPages(url) {
+  const separator = url.includes("?") ? "&" : "?";
+  const items = [];
+  for (let page = 1; ; page++) {
+    const response = await fetch(`${url}${separator}page=${page}&per_page=100`);
+    const result = await onResponseJson(response);
+    items.push(...result.items);
+    if (result.items.length === 0 || items.length >= result.meta.total) {
+      return items;
+    }
+  }
+}
+
 async function apiGetVersion() {
-  const response = await fetch("/api/v1/version");
+  const response = await fetch("api/v1/version");
   return await onResponseJson(response);
 }
 
 async function apiGetRegistryInformation() {
-  const response = await fetch("/api/v1/registry-information");
+  const response = await fetch("api/v1/registry-information");
   return await onResponseJson(response);
 }
 
 async function apiMe() {
-  const response = await fetch("/api/v1/me");
+  const response = await fetch("api/v1/me");
   return await onResponseJson(response);
 }
 
 async function apiOAuthLoginWithCode(code) {
-  const response = await fetch("/api/v1/oauth/code", {
+  const response = await fetch("api/v1/oauth/code", {
     method: "POST",
     body: code,
   });
@@ -30,20 +56,56 @@ async function apiOAuthLoginWithCode(code) {
 }
 
 async function apiLogout() {
-  const response = await fetch("/api/v1/logout", {
+  const response = await fetch("api/v1/logout", {
     method: "POST",
   });
   return await response.text();
 }
 
-async function apiGetUserTokens() {
-  const response = await fetch("/api/v1/me/tokens");
+async function apiGetUserQuota() {
+  const response = await fetch("api/v1/me/quota", {
+    method: "GET",
+  });
   return await onResponseJson(response);
 }
 
+async function apiUpdateCurrentUser(name) {
+  const response = await fetch("api/v1/me", {
+    method: "PATCH",
+    body: JSON.stringify({ name }),
+    headers: [["content-type", "application/json"]],
+  });
+  return await onResponseJson(response);
+}
+
+async function apiGetUserPreferences() {
+  const response = await fetch("api/v1/me/preferences");
+  return await onResponseJson(response);
+}
+
+async function apiSetUserPreferences(preferences) {
+  const response = await fetch("api/v1/me/preferences", {
+    method: "PUT",
+    body: JSON.stringify(preferences),
+    headers: [["content-type", "application/json"]],
+  });
+  return await onResponseJson(response);
+}
+
+async function apiGetUserTeamQuotas() {
+  const response = await fetch("/api/v1/me/quota/teams", {
+    method: "GET",
+  });
+  return await onResponseJson(response);
+}
+
+async function apiGetUserTokens() {
+  return await apiGetAllPages("api/v2/me/tokens");
+}
+
 async function apiCreateUserToken(name, canWrite, canAdmin) {
   const response = await fetch(
-    `/api/v1/me/tokens?canWrite=${canWrite}&canAdmin=${canAdmin}`,
+    `api/v1/me/tokens?canWrite=${canWrite}&canAdmin=${canAdmin}`,
     {
       method: "PUT",
       body: name,
@@ -53,19 +115,18 @@ async function apiCreateUserToken(name, canWrite, canAdmin) {
 }
 
 async function apiRevokeUserToken(token_id) {
-  const response = await fetch(`/api/v1/me/tokens/${token_id}`, {
+  const response = await fetch(`api/v1/me/tokens/${token_id}`, {
     method: "DELETE",
   });
   return await onResponseJson(response);
 }
 
 async function apiGetGlobalTokens() {
-  const response = await fetch("/api/v1/admin/tokens");
-  return await onResponseJson(response);
+  return await apiGetAllPages("api/v2/admin/tokens");
 }
 
 async function apiCreateGlobalToken(name) {
-  const response = await fetch("/api/v1/admin/tokens", {
+  const response = await fetch("api/v1/admin/tokens", {
     method: "PUT",
     body: name,
   });
@@ -73,34 +134,169 @@ async function apiCreateGlobalToken(name) {
 }
 
 async function apiRevokeGlobalToken(token_id) {
-  const response = await fetch(`/api/v1/admin/tokens/${token_id}`, {
+  const response = await fetch(`api/v1/admin/tokens/${token_id}`, {
     method: "DELETE",
   });
   return await onResponseJson(response);
 }
 
+async function apiGetWebhooks() {
+  const response = await fetch("api/v1/webhooks");
+  return await onResponseJson(response);
+}
+
+async function apiCreateWebhook(pkg, url, events) {
+  const response = await fetch("api/v1/webhooks", {
+    method: "PUT",
+    body: JSON.stringify({ package: pkg, url, events }),
+    headers: [["content-type", "application/json"]],
+  });
+  return await onResponseJson(response);
+}
+
+async function apiDeleteWebhook(webhookId) {
+  const response = await fetch(`api/v1/webhooks/${webhookId}`, {
+    method: "DELETE",
+  });
+  return await onResponseJson(response);
+}
+
+async function apiGetWebhookDeliveries(webhookId) {
+  const response = await fetch(`api/v1/webhooks/${webhookId}/deliveries`);
+  return await onResponseJson(response);
+}
+
+async function apiRedeliverWebhookDelivery(webhookId, deliveryId) {
+  const response = await fetch(`api/v1/webhooks/${webhookId}/deliveries/${deliveryId}/redeliver`, {
+    method: "POST",
+  });
+  return await onResponseJson(response);
+}
+
+async function apiGraphQL(query, variables) {
+  const response = await fetch("api/v1/graphql", {
+    method: "POST",
+    body: JSON.stringify({ query, variables }),
+    headers: [["content-type", "application/json"]],
+  });
+  return await onResponseJson(response);
+}
+
+async function apiGetWatchedCrates() {
+  const response = await fetch("api/v1/me/watches");
+  return await onResponseJson(response);
+}
+
+async function apiWatchCrate(crate, watch) {
+  const response = await fetch(`api/v1/crates/${crate}/watch`, {
+    method: watch ? "PUT" : "DELETE",
+  });
+  return await onResponseJson(response);
+}
+
+async function apiGetStarredCrates() {
+  const response = await fetch("api/v1/me/stars");
+  return await onResponseJson(response);
+}
+
+async function apiStarCrate(crate, star) {
+  const response = await fetch(`api/v1/crates/${crate}/star`, {
+    method: star ? "PUT" : "DELETE",
+  });
+  return await onResponseJson(response);
+}
+
+async function apiGetUserNotifications(unreadOnly) {
+  const response = await fetch(`api/v1/me/notifications?unread=${unreadOnly ? "true" : "false"}`);
+  return await onResponseJson(response);
+}
+
+async function apiMarkUserNotificationsRead() {
+  const response = await fetch("api/v1/me/notifications/read", {
+    method: "POST",
+  });
+  return await onResponseJson(response);
+}
+
 async function apiGetDocGenJobs() {
-  const response = await fetch("/api/v1/admin/jobs/docgen");
+  const response = await fetch("api/v1/admin/jobs/docgen");
   return await onResponseJson(response);
 }
 
 async function apiGetDocGenJobLog(jobId) {
-  const response = await fetch(`/api/v1/admin/jobs/docgen/${jobId}/log`);
+  const response = await fetch(`api/v1/admin/jobs/docgen/${jobId}/log`);
+  return await onResponseJson(response);
+}
+
+async function apiGetSearchReindexProgress() {
+  const response = await fetch("api/v1/admin/jobs/reindex");
+  return await onResponseJson(response);
+}
+
+async function apiLaunchSearchReindex() {
+  const response = await fetch("api/v1/admin/jobs/reindex", {
+    method: "POST",
+  });
+  return await onResponseJson(response);
+}
+
+async function apiGetBackupStatus() {
+  const response = await fetch("api/v1/admin/jobs/backup");
+  return await onResponseJson(response);
+}
+
+async function apiLaunchBackup() {
+  const response = await fetch("api/v1/admin/jobs/backup", {
+    method: "POST",
+  });
+  return await onResponseJson(response);
+}
+
+async function apiGetScheduledJobs() {
+  const response = await fetch("api/v1/admin/jobs/scheduled");
+  return await onResponseJson(response);
+}
+
+async function apiRunScheduledJob(name) {
+  const response = await fetch(`api/v1/admin/jobs/scheduled/${encodeURIComponent(name)}`, {
+    method: "POST",
+  });
+  return await onResponseJson(response);
+}
+
+async function apiGetAdvisoriesReport() {
+  const response = await fetch("api/v1/admin/advisories");
+  return await onResponseJson(response);
+}
+
+async function apiGetLicenseInventory(dependencies) {
+  const response = await fetch(`api/v1/admin/licenses?dependencies=${dependencies}`);
+  return await onResponseJson(response);
+}
+
+async function apiGetQuarantinedVersions() {
+  const response = await fetch("api/v1/admin/quarantine");
+  return await onResponseJson(response);
+}
+
+async function apiReleaseQuarantinedVersion(crate, version) {
+  const response = await fetch(`api/v1/admin/quarantine/${crate}/${version}/release`, {
+    method: "POST",
+  });
   return await onResponseJson(response);
 }
 
 async function apiGetWorkers() {
-  const response = await fetch(`/api/v1/admin/workers`);
+  const response = await fetch(`api/v1/admin/workers`);
   return await onResponseJson(response);
 }
 
 async function apiGetUsers() {
-  const response = await fetch("/api/v1/admin/users");
-  return await onResponseJson(response);
+  return await apiGetAllPages("api/v2/admin/users");
 }
 
 async function apiUpdateUser(user) {
-  const response = await fetch(`/api/v1/admin/users/${btoa(user.email)}`, {
+  const response = await fetch(`api/v1/admin/users/${btoa(user.email)}`, {
     method: "PATCH",
     body: JSON.stringify(user),
     headers: [["content-type", "application/json"]],
@@ -109,15 +305,47 @@ async function apiUpdateUser(user) {
 }
 
 async function apiDeleteUser(email) {
-  const response = await fetch(`/api/v1/admin/users/${btoa(email)}`, {
+  const response = await fetch(`api/v1/admin/users/${btoa(email)}`, {
     method: "DELETE",
   });
   return await onResponseJson(response);
 }
 
+async function apiGetUserQuotaFor(email) {
+  const response = await fetch(`api/v1/admin/users/${btoa(email)}/quota`, {
+    method: "GET",
+  });
+  return await onResponseJson(response);
+}
+
+async function apiSetUserQuota(email, maxBytes, maxCrates) {
+  const response = await fetch(`api/v1/admin/users/${btoa(email)}/quota`, {
+    method: "PUT",
+    body: JSON.stringify({ maxBytes, maxCrates }),
+    headers: [["content-type", "application/json"]],
+  });
+  return await onResponseJson(response);
+}
+
+async function apiGetTeamQuota(team) {
+  const response = await fetch(`/api/v1/admin/teams/${encodeURIComponent(team)}/quota`, {
+    method: "GET",
+  });
+  return await onResponseJson(response);
+}
+
+async function apiSetTeamQuota(team, maxBytes, maxCrates) {
+  const response = await fetch(`/api/v1/admin/teams/${encodeURIComponent(team)}/quota`, {
+    method: "PUT",
+    body: JSON.stringify({ maxBytes, maxCrates }),
+    headers: [["content-type", "application/json"]],
+  });
+  return await onResponseJson(response);
+}
+
 async function apiDeactivateUser(email) {
   const response = await fetch(
-    `/api/v1/admin/users/${btoa(email)}/deactivate`,
+    `api/v1/admin/users/${btoa(email)}/deactivate`,
     {
       method: "POST",
     }
@@ -127,7 +355,7 @@ async function apiDeactivateUser(email) {
 
 async function apiReactivateUser(email) {
   const response = await fetch(
-    `/api/v1/admin/users/${btoa(email)}/reactivate`,
+    `api/v1/admin/users/${btoa(email)}/reactivate`,
     {
       method: "POST",
     }
@@ -135,29 +363,165 @@ async function apiReactivateUser(email) {
   return await onResponseJson(response);
 }
 
+async function apiImpersonateUser(email, canWrite, durationMinutes, reason) {
+  const response = await fetch(
+    `api/v1/admin/users/${btoa(email)}/impersonate`,
+    {
+      method: "POST",
+      body: JSON.stringify({ canWrite, durationMinutes, reason }),
+      headers: [["content-type", "application/json"]],
+    }
+  );
+  return await onResponseJson(response);
+}
+
+async function apiEndImpersonation(impersonationId) {
+  const response = await fetch(
+    `api/v1/admin/impersonations/${impersonationId}`,
+    {
+      method: "DELETE",
+    }
+  );
+  return await onResponseJson(response);
+}
+
+async function apiGetAuditLog(page, perPage) {
+  const response = await fetch(`api/v2/admin/audit?page=${page}&per_page=${perPage}`);
+  return await onResponseJson(response);
+}
+
+async function apiBulkYank(pattern, versions, dryRun) {
+  const response = await fetch("api/v1/admin/yank", {
+    method: "POST",
+    body: JSON.stringify({ pattern, versions, dryRun }),
+    headers: [["content-type", "application/json"]],
+  });
+  return await onResponseJson(response);
+}
+
+async function apiGetProvisioningRules() {
+  const response = await fetch("api/v1/admin/provisioning");
+  return await onResponseJson(response);
+}
+
+async function apiSetProvisioningRules(rules) {
+  const response = await fetch("api/v1/admin/provisioning", {
+    method: "PUT",
+    body: JSON.stringify(rules),
+    headers: [["content-type", "application/json"]],
+  });
+  return await onResponseJson(response);
+}
+
+async function apiGetRegistryStats() {
+  const response = await fetch("api/v1/admin/stats");
+  return await onResponseJson(response);
+}
+
+async function apiResyncIndexRemote(mode, confirm) {
+  const response = await fetch("api/v1/admin/index/resync", {
+    method: "POST",
+    body: JSON.stringify({ mode, confirm }),
+    headers: [["content-type", "application/json"]],
+  });
+  return await onResponseJson(response);
+}
+
+async function apiGetStorageUsage() {
+  const response = await fetch("api/v1/admin/storage");
+  return await onResponseJson(response);
+}
+
+async function apiGetMaintenance() {
+  const response = await fetch("api/v1/maintenance");
+  return await onResponseJson(response);
+}
+
+async function apiSetMaintenance(enabled, message) {
+  const response = await fetch("api/v1/admin/maintenance", {
+    method: "PUT",
+    body: JSON.stringify({ enabled, message }),
+    headers: [["content-type", "application/json"]],
+  });
+  return await onResponseJson(response);
+}
+
+async function apiGetCurrentAnnouncement() {
+  const response = await fetch("api/v1/announcement");
+  return await onResponseJson(response);
+}
+
+async function apiGetAnnouncement() {
+  const response = await fetch("api/v1/admin/announcement");
+  return await onResponseJson(response);
+}
+
+async function apiSetAnnouncement(message, level, startsOn, endsOn) {
+  const response = await fetch("api/v1/admin/announcement", {
+    method: "PUT",
+    body: JSON.stringify({ message, level, startsOn, endsOn }),
+    headers: [["content-type", "application/json"]],
+  });
+  return await onResponseJson(response);
+}
+
+async function apiClearAnnouncement() {
+  const response = await fetch("api/v1/admin/announcement", { method: "DELETE" });
+  return await onResponseJson(response);
+}
+
+async function apiReloadConfiguration() {
+  const response = await fetch("api/v1/admin/config/reload", { method: "POST" });
+  return await onResponseJson(response);
+}
+
 async function apiGetCratesStats() {
-  const response = await fetch("/api/v1/crates/stats");
+  const response = await fetch("api/v1/crates/stats");
   return await onResponseJson(response);
 }
 
-async function apiGetCratesOutdatedHeads() {
-  const response = await fetch("/api/v1/crates/outdated");
+async function apiGetCratesMetadata(names, lockFile) {
+  const response = await fetch("api/v1/crates/bulk", {
+    method: "POST",
+    headers: [["content-type", "application/json"]],
+    body: JSON.stringify({ names, lockFile }),
+  });
   return await onResponseJson(response);
 }
 
+async function apiGetCratesOutdatedHeads() {
+  return await apiGetAllPages("api/v2/crates/outdated");
+}
+
 async function apiLookupCrates(input) {
-  const response = await fetch("/api/v1/crates?q=" + encodeURIComponent(input));
+  const response = await fetch("api/v1/crates?q=" + encodeURIComponent(input));
   const responseJson = await onResponseJson(response);
   return responseJson.crates;
 }
 
+async function apiSearchCrates(input, filters) {
+  const parameters = new URLSearchParams({ q: input });
+  for (const name of ["keyword", "category", "owner"]) {
+    if (filters && filters[name]) {
+      parameters.append(name, filters[name]);
+    }
+  }
+  const response = await fetch("api/v1/crates?" + parameters.toString());
+  return await onResponseJson(response);
+}
+
+async function apiSuggestCrates(prefix) {
+  const response = await fetch("api/v1/crates/suggest?q=" + encodeURIComponent(prefix));
+  return await onResponseJson(response);
+}
+
 async function apiGetCrate(crate) {
-  const response = await fetch(`/api/v1/crates/${crate}`);
+  const response = await fetch(`api/v1/crates/${crate}`);
   return await onResponseJson(response);
 }
 
 async function apiGetCrateLastReadme(crate) {
-  const response = await fetch(`/api/v1/crates/${crate}/readme`);
+  const response = await fetch(`api/v1/crates/${crate}/readme/html`);
   if (response.status !== 200) {
     throw await response.json();
   } else {
@@ -166,7 +530,7 @@ async function apiGetCrateLastReadme(crate) {
 }
 
 async function apiGetCrateReadmeAt(crate, version) {
-  const response = await fetch(`/api/v1/crates/${crate}/${version}/readme`);
+  const response = await fetch(`api/v1/crates/${crate}/${version}/readme/html`);
   if (response.status !== 200) {
     throw await response.json();
   } else {
@@ -174,13 +538,32 @@ async function apiGetCrateReadmeAt(crate, version) {
   }
 }
 
+async function apiGetCrateFiles(crate, version) {
+  const response = await fetch(`api/v1/crates/${crate}/${version}/files`);
+  return await onResponseJson(response);
+}
+
+async function apiGetCrateFile(crate, version, path) {
+  const response = await fetch(`api/v1/crates/${crate}/${version}/files/${path}`);
+  if (response.status !== 200) {
+    throw await response.json();
+  } else {
+    return await response.text();
+  }
+}
+
+async function apiGetCrateDiff(crate, from, to) {
+  const response = await fetch(`api/v1/crates/${crate}/diff?from=${encodeURIComponent(from)}&to=${encodeURIComponent(to)}`);
+  return await onResponseJson(response);
+}
+
 async function apiGetCrateOwners(crate) {
-  const response = await fetch(`/api/v1/crates/${crate}/owners`);
+  const response = await fetch(`api/v1/crates/${crate}/owners`);
   return await onResponseJson(response);
 }
 
 async function apiAddCrateOwner(crate, email) {
-  const response = await fetch(`/api/v1/crates/${crate}/owners`, {
+  const response = await fetch(`api/v1/crates/${crate}/owners`, {
     method: "PUT",
     body: JSON.stringify({ users: [email] }),
     headers: [["content-type", "application/json"]],
@@ -189,7 +572,7 @@ async function apiAddCrateOwner(crate, email) {
 }
 
 async function apiRemoveCrateOwners(crate, email) {
-  const response = await fetch(`/api/v1/crates/${crate}/owners`, {
+  const response = await fetch(`api/v1/crates/${crate}/owners`, {
     method: "DELETE",
     body: JSON.stringify({ users: [email] }),
     headers: [["content-type", "application/json"]],
@@ -198,12 +581,12 @@ async function apiRemoveCrateOwners(crate, email) {
 }
 
 async function apiGetCrateTargets(crate) {
-  const response = await fetch(`/api/v1/crates/${crate}/targets`);
+  const response = await fetch(`api/v1/crates/${crate}/targets`);
   return await onResponseJson(response);
 }
 
 async function apiSetCrateTargets(crate, targets) {
-  const response = await fetch(`/api/v1/crates/${crate}/targets`, {
+  const response = await fetch(`api/v1/crates/${crate}/targets`, {
     method: "PATCH",
     body: JSON.stringify(targets),
     headers: [["content-type", "application/json"]],
@@ -212,12 +595,12 @@ async function apiSetCrateTargets(crate, targets) {
 }
 
 async function apiGetCrateCapabilities(crate) {
-  const response = await fetch(`/api/v1/crates/${crate}/capabilities`);
+  const response = await fetch(`api/v1/crates/${crate}/capabilities`);
   return await onResponseJson(response);
 }
 
 async function apiSetCrateCapabilities(crate, capabilities) {
-  const response = await fetch(`/api/v1/crates/${crate}/capabilities`, {
+  const response = await fetch(`api/v1/crates/${crate}/capabilities`, {
     method: "PATCH",
     body: JSON.stringify(capabilities),
     headers: [["content-type", "application/json"]],
@@ -226,7 +609,7 @@ async function apiSetCrateCapabilities(crate, capabilities) {
 }
 
 async function apiSetCrateDeprecation(crate, isDeprecated) {
-  const response = await fetch(`/api/v1/crates/${crate}/deprecated`, {
+  const response = await fetch(`api/v1/crates/${crate}/deprecated`, {
     method: "PATCH",
     body: JSON.stringify(isDeprecated),
     headers: [["content-type", "application/json"]],
@@ -234,22 +617,89 @@ async function apiSetCrateDeprecation(crate, isDeprecated) {
   return await onResponseJson(response);
 }
 
+async function apiSetCrateFrozen(crate, isFrozen) {
+  const response = await fetch(`api/v1/crates/${crate}/frozen`, {
+    method: "PATCH",
+    body: JSON.stringify(isFrozen),
+    headers: [["content-type", "application/json"]],
+  });
+  return await onResponseJson(response);
+}
+
+async function apiGetCrateVisibility(crate) {
+  const response = await fetch(`api/v1/crates/${crate}/visibility`);
+  return await onResponseJson(response);
+}
+
+async function apiSetCrateVisibility(crate, isRestricted, readers, teams) {
+  const response = await fetch(`api/v1/crates/${crate}/visibility`, {
+    method: "PATCH",
+    body: JSON.stringify({ isRestricted, readers, teams: teams || [] }),
+    headers: [["content-type", "application/json"]],
+  });
+  return await onResponseJson(response);
+}
+
 async function apiRegenCrateDoc(crate, version) {
-  const response = await fetch(`/api/v1/crates/${crate}/${version}/docsregen`, {
+  const response = await fetch(`api/v1/crates/${crate}/${version}/docsregen`, {
     method: "POST",
   });
   return await onResponseJson(response);
 }
 
 async function apiCheckCrateDeps(crate, version) {
-  const response = await fetch(`/api/v1/crates/${crate}/${version}/checkdeps`, {
+  const response = await fetch(`api/v1/crates/${crate}/${version}/checkdeps`, {
+    method: "GET",
+  });
+  return await onResponseJson(response);
+}
+
+async function apiSetCrateProvenance(crate, version, provenance) {
+  const response = await fetch(`api/v1/crates/${crate}/${version}/provenance`, {
+    method: "PUT",
+    headers: [["content-type", "application/json"]],
+    body: JSON.stringify(provenance),
+  });
+  return await onResponseJson(response);
+}
+
+async function apiGetCrateChecksums(crate, version) {
+  const response = await fetch(`api/v1/crates/${crate}/${version}/checksums`, {
+    method: "GET",
+  });
+  return await onResponseJson(response);
+}
+
+async function apiGetCrateSbom(crate, version) {
+  const response = await fetch(`api/v1/crates/${crate}/${version}/sbom`, {
+    method: "GET",
+  });
+  return await onResponseJson(response);
+}
+
+async function apiGetCrateDocsCoverage(crate, version) {
+  const response = await fetch(`api/v1/crates/${crate}/${version}/docscoverage`, {
     method: "GET",
   });
   return await onResponseJson(response);
 }
 
 async function apiGetCrateDlStats(crate) {
-  const response = await fetch(`/api/v1/crates/${crate}/dlstats`, {
+  const response = await fetch(`api/v1/crates/${crate}/dlstats`, {
+    method: "GET",
+  });
+  return await onResponseJson(response);
+}
+
+async function apiGetCrateDownloads(crate) {
+  const response = await fetch(`api/v1/crates/${crate}/downloads`, {
+    method: "GET",
+  });
+  return await onResponseJson(response);
+}
+
+async function apiGetCrateReverseDependencies(crate) {
+  const response = await fetch(`api/v1/crates/${crate}/reverse_dependencies`, {
     method: "GET",
   });
   return await onResponseJson(response);
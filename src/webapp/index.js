@@ -1,7 +1,7 @@
 function doLogout() {
   apiLogout().then((_) => {
-    window.localStorage.removeItem("cratery-user");
-    window.location.pathname = "/webapp/index.html";
+    window.localStorage.removeItem(storageKey("user"));
+    window.location.href = "webapp/index.html";
   });
 }
 
@@ -15,10 +15,11 @@ function onPageLoad() {
       if (!isAdmin) {
         document.getElementById("link-admin").remove();
       }
+      setupAnnouncement();
       return user;
     })
     .catch(() => {
-      window.localStorage.removeItem("cratery-user");
+      window.localStorage.removeItem(storageKey("user"));
       return null;
     });
 }
@@ -40,6 +41,26 @@ function setupFooter() {
   });
 }
 
+const ANNOUNCEMENT_CLASSES = {
+  info: "text-blue-800 bg-blue-50 dark:bg-gray-800 dark:text-blue-400",
+  warning: "text-yellow-800 bg-yellow-50 dark:bg-gray-800 dark:text-yellow-300",
+  critical: "text-red-800 bg-red-50 dark:bg-gray-800 dark:text-red-400",
+};
+
+function setupAnnouncement() {
+  apiGetCurrentAnnouncement().then((announcement) => {
+    const nav = document.querySelector("nav");
+    if (announcement === null || nav === null) {
+      return;
+    }
+    const banner = document.createElement("div");
+    banner.className = `p-4 text-sm text-center ${ANNOUNCEMENT_CLASSES[announcement.level] || ANNOUNCEMENT_CLASSES.info}`;
+    banner.setAttribute("role", "alert");
+    banner.appendChild(document.createTextNode(announcement.message));
+    nav.insertAdjacentElement("afterend", banner);
+  });
+}
+
 function setupOnChange(inputEl, applyChange) {
   let timeoutChange = null;
   let lastKnown = inputEl.value;
@@ -47,3 +47,16 @@ pub fn get_resources() -> EmbeddedResources {
     add!(resources, "logo-white.svg");
     resources
 }
+
+/// The base element of the pages of the web application, for the registry served at the root
+const BASE_ELEMENT: &str = "<base href=\"/\">";
+
+/// Sets the base of the relative URLs in a page of the web application for a registry served under a prefix
+///
+/// All the URLs in the pages and scripts of the web application are relative to this base.
+#[must_use]
+pub fn set_base_path(content: &[u8], prefix: &str) -> Vec<u8> {
+    String::from_utf8_lossy(content)
+        .replacen(BASE_ELEMENT, &format!("<base href=\"{prefix}/\">"), 1)
+        .into_bytes()
+}
@@ -13,13 +13,14 @@ use chrono::Local;
 use futures::{select, FutureExt, Sink, SinkExt, StreamExt};
 use log::{error, info, warn};
 use tokio::net::TcpStream;
+use tokio::sync::mpsc::channel;
 use tokio::sync::Mutex;
 use tokio::time::MissedTickBehavior;
 use tokio_tungstenite::tungstenite::{ClientRequestBuilder, Message};
 use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
 
 use crate::model::config::{Configuration, ExternalRegistry, NodeRole, NodeRoleWorker};
-use crate::model::docs::{DocGenJobState, DocGenJobUpdate};
+use crate::model::docs::{DocGenJobLogLine, DocGenJobState, DocGenJobUpdate};
 use crate::model::worker::{JobSpecification, JobUpdate, WorkerDescriptor};
 use crate::services::{ServiceProvider, StandardServiceProvider};
 use crate::utils::apierror::{error_backend_failure, specialize, ApiError};
@@ -172,8 +173,26 @@ where
 {
     let JobSpecification::DocGen(job) = job;
     let service_storage = StandardServiceProvider::get_storage(config);
-    match crate::services::docs::generate_doc_for_job(config, service_storage, &job).await {
-        Ok((state, log)) => {
+    let (log_sender, mut log_receiver) = channel::<String>(64);
+    let forward = async {
+        while let Some(line) = log_receiver.recv().await {
+            sender
+                .lock()
+                .await
+                .send(Message::Text(serde_json::to_string(&JobUpdate::DocGenLog(
+                    DocGenJobLogLine { job_id: job.id, line },
+                ))?))
+                .await?;
+        }
+        Ok::<_, ApiError>(())
+    };
+    let (result, forwarded) = tokio::join!(
+        crate::services::docs::generate_doc_for_job(config, service_storage, &job, log_sender),
+        forward
+    );
+    forwarded?;
+    match result {
+        Ok((state, log, coverage, size)) => {
             let now = Local::now().naive_local();
             sender
                 .lock()
@@ -183,6 +202,8 @@ where
                     state,
                     last_update: now,
                     log: Some(log),
+                    coverage,
+                    size,
                 }))?))
                 .await?;
         }
@@ -196,6 +217,8 @@ where
                     state: DocGenJobState::Failure,
                     last_update: now,
                     log: Some(format!("{error}")),
+                    coverage: None,
+                    size: None,
                 }))?))
                 .await?;
         }